@@ -0,0 +1,230 @@
+//! Timings of the core editing operations, compared across a change as the README
+//! describes. The texts are generated on each run, so no fixtures are kept in the
+//! repository
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use viron::Language;
+use viron::bench::{Buffer, Edit, Point, Screen, StorageKind, SyntaxEngine};
+
+const INSERTED: usize = 100_000;
+
+/// Pseudo-random numbers below `bound`, the same on every run
+fn positions(count: usize, bound: impl Fn(usize) -> usize) -> Vec<usize> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..count)
+        .map(|index| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % (bound(index) as u64 + 1)) as usize
+        })
+        .collect()
+}
+
+/// `lines` lines of prose, with a newline every 60 or so bytes
+fn text(lines: usize) -> String {
+    (0..lines)
+        .map(|line| format!("line {line} of the generated text, long enough to wrap around\n"))
+        .collect()
+}
+
+/// Rust source of about `lines` lines, repeating a module with the common syntax
+fn rust_source(lines: usize) -> String {
+    let module = |index: usize| {
+        format!(
+            r#"/// Documentation of item {index}
+#[derive(Debug, Clone, Default)]
+pub struct Item{index}<'a> {{
+    name: &'a str,
+    values: Vec<u64>,
+}}
+
+impl<'a> Item{index}<'a> {{
+    pub fn new(name: &'a str) -> Self {{
+        Self {{ name, values: vec![1, 2, 3] }}
+    }}
+
+    fn total(&self) -> u64 {{
+        // Sum of the values, or 0
+        self.values.iter().map(|value| value * 2).sum::<u64>() + {index}
+    }}
+}}
+
+"#
+        )
+    };
+    let module_lines = module(0).lines().count();
+    (0..lines.div_ceil(module_lines)).map(module).collect()
+}
+
+fn buffer_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("buffer_insert");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let mut buffer = Buffer::default();
+            for index in 0..INSERTED {
+                let c = if index % 60 == 59 { '\n' } else { 'a' };
+                buffer.insert_char(buffer.len_bytes(), c);
+            }
+            buffer
+        })
+    });
+    let random = positions(INSERTED, |index| index);
+    group.bench_function("random", |b| {
+        b.iter(|| {
+            let mut buffer = Buffer::default();
+            for (index, &position) in random.iter().enumerate() {
+                let c = if index % 60 == 59 { '\n' } else { 'a' };
+                buffer.insert_char(position, c);
+            }
+            buffer
+        })
+    });
+    group.finish();
+}
+
+fn buffer_delete(c: &mut Criterion) {
+    // Ranges are deleted a character at a time, each moving the line starts after it,
+    // so the text is kept small enough for a run to finish
+    let content = text(2_000);
+    let mut group = c.benchmark_group("buffer_delete");
+    group.sample_size(10);
+    group.bench_function("ranges", |b| {
+        b.iter_batched(
+            || Buffer::from_string(&content),
+            |mut buffer| {
+                // Halves of what's left, from the middle, as a large visual delete does
+                while buffer.len_bytes() > 1_000 {
+                    let length = buffer.len_bytes() / 2;
+                    buffer.delete_string(length / 2, length);
+                }
+                buffer
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("lines", |b| {
+        b.iter_batched(
+            || Buffer::from_string(&content),
+            |mut buffer| {
+                buffer.delete_multiple_lines(100, 1_900);
+                buffer
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn storage_edits(c: &mut Criterion) {
+    // Each edit inserts and then deletes as many bytes, so the text keeps its length
+    let content = rust_source(200_000);
+    let inserts = positions(2_000, |_| content.len() - 1);
+    let deletes = positions(2_000, |_| content.len());
+    let mut group = c.benchmark_group("storage_edits");
+    group.sample_size(10);
+    for kind in [StorageKind::Gap, StorageKind::Rope] {
+        group.bench_function(format!("{kind:?}"), |b| {
+            b.iter_batched(
+                || Buffer::with_storage(&content, kind),
+                |mut buffer| {
+                    for (&insert, &delete) in inserts.iter().zip(&deletes) {
+                        buffer.insert_string(insert, "edit");
+                        buffer.delete_string(delete, 4);
+                    }
+                    buffer
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn line_starts(c: &mut Criterion) {
+    let content = text(1_000_000);
+    let mut group = c.benchmark_group("line_starts");
+    group.sample_size(10);
+    group.bench_function("from_string", |b| b.iter(|| Buffer::from_string(black_box(&content))));
+
+    // Every line start after the edit moves, so edits near the top are the slow ones
+    let mut buffer = Buffer::from_string(&content);
+    group.bench_function("newline_at_top", |b| {
+        b.iter(|| {
+            buffer.insert_char(black_box(10), '\n');
+            buffer.delete_char(10)
+        })
+    });
+    group.finish();
+}
+
+fn positions_round_trip(c: &mut Criterion) {
+    let buffer = Buffer::from_string(&text(100_000));
+    let offsets = positions(10_000, |_| buffer.len_bytes() - 1);
+    c.bench_function("point_at_position_round_trip", |b| {
+        b.iter(|| {
+            for &offset in &offsets {
+                let point: Point = buffer.point_at_position(offset);
+                assert_eq!(buffer.cursor_position(&point), offset);
+            }
+        })
+    });
+}
+
+fn highlight(c: &mut Criterion) {
+    let source = rust_source(5_000);
+    // Held so the parser and query stay loaded between iterations
+    let _loaded = SyntaxEngine::new(&Language::Rust).unwrap();
+    let mut group = c.benchmark_group("highlight");
+    group.sample_size(20);
+    group.bench_function("full", |b| {
+        b.iter(|| {
+            let mut engine = SyntaxEngine::new(&Language::Rust).unwrap();
+            engine.highlight(black_box(source.as_bytes()), 0..source.len()).unwrap()
+        })
+    });
+
+    // A line typed in the middle and taken out again, reparsed from the old tree with
+    // the captures of a screen of lines looked for, as each frame after a key does
+    let mut engine = SyntaxEngine::new(&Language::Rust).unwrap();
+    engine.parse(source.as_bytes());
+    let middle = source[..source.len() / 2].rfind('\n').unwrap() + 1;
+    let row = source[..middle].matches('\n').count();
+    let (start, end) = (Point::new(row, 0), Point::new(row + 1, 0));
+    let typed = format!("{}// typed\n{}", &source[..middle], &source[middle..]);
+    let insert = Edit::insert(middle, start, "// typed\n".to_string(), start, end);
+    let delete = Edit::delete(middle, start, "// typed\n".to_string(), start, start);
+    let screen = middle..middle + 60 * 60;
+    group.bench_function("incremental", |b| {
+        b.iter(|| {
+            engine.apply_edit(&insert).unwrap();
+            engine.highlight(black_box(typed.as_bytes()), screen.clone()).unwrap();
+            engine.apply_edit(&delete).unwrap();
+            engine.highlight(black_box(source.as_bytes()), screen.clone()).unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn render(c: &mut Criterion) {
+    let source = rust_source(20_000);
+    let mut screen = Screen::new(&source, Language::Rust, 200, 60);
+    screen.go_to_row(10_000);
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    group.bench_function("full", |b| b.iter(|| screen.render_all().unwrap()));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    buffer_insert,
+    buffer_delete,
+    storage_edits,
+    line_starts,
+    positions_round_trip,
+    highlight,
+    render
+);
+criterion_main!(benches);
@@ -1,15 +1,87 @@
 use crate::actions::core::{CompositeExecutable, Executable};
-use crate::actions::types::{buffer, movement};
+use crate::actions::types::global::{
+    Address, LineCommand, LineSelection, PrintLineNumber, RunLineCommand,
+};
+use crate::actions::types::normal::ExecuteKeys;
+use crate::actions::types::sort::{SortLines, SortOptions};
+use crate::actions::types::{
+    buffer, diff, format, jobs, lsp, movement, quickfix, symbols, system, tutor,
+};
+use crate::core::buffer_manager::BufferManager;
+use crate::core::command::expand_home;
+use crate::core::encoding::Encoding;
+use crate::core::language::Language;
+use crate::core::replace::Replacement;
+use crate::core::suggest::closest;
 use anyhow::{Context, Result, anyhow};
+use regex::Regex;
 use std::path::PathBuf;
 
-pub fn parse_command(input: &str) -> Result<Box<dyn Executable>> {
+/// Names of the commands, for suggesting one when a name is mistyped. Each must parse
+/// as a command, which `test_suggested_commands_parse` checks
+const COMMANDS: [&str; 84] = [
+    "q", "quit", "q!", "quit!", "w", "write", "w!", "write!", "wq", "writequit", "wq!", "x", "x!",
+    "xit", "exit", "wa", "wall", "qa", "qall", "qa!", "qall!", "xa", "xall", "wqa", "wqall", "xa!",
+    "xall!", "wqa!", "wqall!", "e", "edit", "e!", "edit!", "set", "se", "tutor", "diff", "symbols",
+    "sym", "bn", "bnext", "bp", "bprevious", "d", "delete", "s", "substitute", "norm", "normal",
+    "norm!", "normal!", "sor", "sort", "g", "global", "global!", "v", "vglobal", "p", "print", "nu",
+    "number", "reg", "registers", "jobs", "ls", "buffers", "files", "b", "buffer", "bd", "bdelete",
+    "bd!", "bdelete!", "mes", "messages", "rename", "codeaction", "diag", "copen", "cclose", "cnext",
+    "cprev", "fmt",
+];
+
+/// What `%` and `#` in a file name stand for: the current and the alternate file
+#[derive(Debug, Default)]
+pub struct FileNames {
+    pub current: Option<String>,
+    pub alternate: Option<String>,
+}
+
+impl FileNames {
+    pub fn new(buffers: &BufferManager) -> Self {
+        let name = |path: &Option<PathBuf>| path.as_ref().map(|path| path.to_string_lossy().into_owned());
+        Self {
+            current: name(&buffers.current().path),
+            alternate: buffers.alternate().and_then(|document| name(&document.path)),
+        }
+    }
+}
+
+/// Options of `:set`
+const OPTIONS: [&str; 4] = ["filetype", "ft", "fileencoding", "fenc"];
+
+pub fn parse_command(input: &str, files: &FileNames) -> Result<Box<dyn Executable>> {
+    // Commands with a pattern may contain spaces, so they're parsed before splitting
+    if let Some(global) = parse_global(input.trim()) {
+        return global;
+    }
+    // Trailing spaces are kept, since they can be keys of :normal
+    let (selection, rest) = parse_range(input.trim_start());
+    if let Some(command) = parse_line_command(rest) {
+        let command = command?;
+        return Ok(Box::new(RunLineCommand::new(selection, command)));
+    }
+    if let Some(options) = parse_sort(rest) {
+        // Without a range the whole file is sorted
+        let selection = match selection {
+            LineSelection::Current => LineSelection::Range(Address::Line(0), Address::Last),
+            selection => selection,
+        };
+        return Ok(Box::new(SortLines::new(selection, options?)));
+    }
+    if rest.trim() == "=" {
+        return Ok(Box::new(PrintLineNumber::new(selection)));
+    }
+
     let parts: Vec<&str> = input.trim().split_whitespace().collect();
     if parts.is_empty() {
         return Err(anyhow!("Empty command"));
     }
 
     let command = parts[0];
+    if parts.len() > 2 && matches!(command, "e" | "edit" | "e!" | "edit!") {
+        return Err(anyhow!("Only one file name allowed"));
+    }
 
     match command.to_lowercase().as_str() {
         "q" | "quit" => {
@@ -17,35 +89,701 @@ pub fn parse_command(input: &str) -> Result<Box<dyn Executable>> {
             Ok(Box::new(buffer::CloseBuffer::force(force)))
         }
         "q!" | "quit!" => Ok(Box::new(buffer::CloseBuffer::force(true))),
-        "w" | "write" => {
-            let path = parts.get(1).map(|&s| PathBuf::from(s));
-            Ok(Box::new(buffer::WriteBuffer::new(path)))
+        "w" | "write" | "w!" | "write!" => {
+            Ok(Box::new(parse_write(command, &parts[1..], files)?))
         }
-        "wq" | "writequit" => {
-            let path = parts.get(1).map(|&s| PathBuf::from(s));
+        // The close is never forced, so the buffer stays open if the write fails
+        "wq" | "writequit" | "wq!" | "x" | "xit" | "exit" | "x!" => {
             let mut executable = CompositeExecutable::new();
             executable
-                .add(buffer::WriteBuffer::new(path))
+                .add(parse_write(command, &parts[1..], files)?)
                 .add(buffer::CloseBuffer::force(false));
             Ok(Box::new(executable))
         }
+        "wa" | "wall" => Ok(Box::new(buffer::WriteAllBuffers)),
+        "qa" | "qall" => Ok(Box::new(system::QuitAll::force(false))),
+        "qa!" | "qall!" => Ok(Box::new(system::QuitAll::force(true))),
+        "xa" | "xall" | "wqa" | "wqall" | "xa!" | "xall!" | "wqa!" | "wqall!" => {
+            let force = command.ends_with('!');
+            let mut executable = CompositeExecutable::new();
+            executable
+                .add(buffer::WriteAllBuffers)
+                .add(system::QuitAll::force(force));
+            Ok(Box::new(executable))
+        }
         "e" | "edit" => {
-            let path = parts
-                .get(1)
-                .map(|&s| PathBuf::from(s))
-                .context("No such file or directory")?;
+            let path = parts.get(1).context("Missing file name")?;
+            let path = expand_file_name(path, files)?;
             Ok(Box::new(buffer::OpenBuffer::new(path)))
         }
+        // Without a file name, the current one is read again
+        "e!" | "edit!" => match parts.get(1) {
+            Some(path) => Ok(Box::new(buffer::OpenBuffer::new(expand_file_name(path, files)?))),
+            None => Ok(Box::new(buffer::ReloadBuffer)),
+        },
+        "set" | "se" => {
+            let option = parts
+                .get(1)
+                .context("Missing option, as in :set filetype=rust")?;
+            parse_set_option(option)
+        }
+        "tutor" => Ok(Box::new(tutor::OpenTutor)),
+        "diff" => Ok(Box::new(diff::ShowDiff)),
+        "symbols" | "sym" => {
+            let query = parts.get(1).unwrap_or(&"").to_string();
+            Ok(Box::new(symbols::ShowSymbols::new(query)))
+        }
+        "rename" => {
+            let name = parts.get(1).context("Missing new name, as in :rename count")?;
+            Ok(Box::new(lsp::Rename::new(name.to_string())))
+        }
+        "codeaction" => Ok(Box::new(lsp::RequestCodeActions)),
+        "diag" => Ok(Box::new(lsp::ShowDiagnostic)),
+        "fmt" => Ok(Box::new(format::FormatBuffer)),
+        "copen" => Ok(Box::new(quickfix::OpenQuickfix)),
+        "cclose" => Ok(Box::new(quickfix::CloseQuickfix)),
+        "cnext" => Ok(Box::new(quickfix::GoToQuickfixEntry::new(1))),
+        "cprev" => Ok(Box::new(quickfix::GoToQuickfixEntry::new(-1))),
+        "jobs" => Ok(Box::new(jobs::ShowJobs)),
+        "mes" | "messages" => Ok(Box::new(system::ShowMessages)),
+        "reg" | "registers" => Ok(Box::new(buffer::ShowRegisters)),
         "bn" | "bnext" => Ok(Box::new(buffer::NextBuffer)),
         "bp" | "bprevious" => Ok(Box::new(buffer::PreviousBuffer)),
+        "ls" | "buffers" | "files" => Ok(Box::new(buffer::ShowBuffers)),
+        "b" | "buffer" => {
+            let target = parts[1..].join(" ");
+            if target.is_empty() {
+                return Err(anyhow!("Missing buffer name or number"));
+            }
+            Ok(Box::new(buffer::SwitchToBuffer::new(target)))
+        }
+        "bd" | "bdelete" => {
+            let force = parts.get(1) == Some(&"!");
+            Ok(Box::new(buffer::DeleteBuffer::force(force)))
+        }
+        "bd!" | "bdelete!" => Ok(Box::new(buffer::DeleteBuffer::force(true))),
         cmd => {
-            if let Ok(line_number) = cmd.parse::<usize>() {
-                Ok(Box::new(movement::GoToLine::new(
+            if let Some(offset) = parse_relative_line(cmd) {
+                Ok(Box::new(movement::GoToRelativeLine::new(offset)))
+            } else if let Ok(line_number) = cmd.parse::<usize>() {
+                Ok(Box::new(movement::SeekToLine::new(
                     line_number.saturating_sub(1),
                 )))
+            } else if let Some(Ok(percent)) = cmd.strip_suffix('%').map(str::parse::<usize>) {
+                Ok(Box::new(movement::SeekToPercent::new(percent)))
             } else {
-                Err(anyhow!("Command not found {}", input))
+                Err(unknown("command", cmd, COMMANDS))
+            }
+        }
+    }
+}
+
+/// The write of `:w` and `:wq` with their file name, forced by a `!` after the command
+/// to overwrite another file, and creating missing directories with `++p`
+fn parse_write(command: &str, args: &[&str], files: &FileNames) -> Result<buffer::WriteBuffer> {
+    let create_dirs = args.contains(&"++p");
+    let mut names = args.iter().filter(|&&arg| arg != "++p");
+    let path = names.next().map(|name| expand_file_name(name, files)).transpose()?;
+    if names.next().is_some() {
+        return Err(anyhow!("Only one file name allowed"));
+    }
+    let write = buffer::WriteBuffer::new(path)
+        .forced(command.ends_with('!'))
+        .creating_dirs(create_dirs);
+    Ok(write)
+}
+
+/// Replaces `%` with the current file name and `#` with the alternate one, as in
+/// `:e #`, and a leading `~` with the home directory. `\%` and `\#` are the characters
+/// themselves
+fn expand_file_name(name: &str, files: &FileNames) -> Result<PathBuf> {
+    let mut expanded = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        let file = match c {
+            '\\' if matches!(chars.peek(), Some('%' | '#')) => {
+                expanded.extend(chars.next());
+                continue;
+            }
+            '%' => files.current.as_deref().context("Empty file name for '%'")?,
+            '#' => files
+                .alternate
+                .as_deref()
+                .context("No alternate file name to substitute for '#'")?,
+            c => {
+                expanded.push(c);
+                continue;
+            }
+        };
+        expanded.push_str(file);
+    }
+    Ok(expand_home(&expanded))
+}
+
+fn parse_set_option(option: &str) -> Result<Box<dyn Executable>> {
+    match option.split_once('=') {
+        Some(("filetype" | "ft", name)) => {
+            let language = Language::from_name(name)
+                .with_context(|| format!("Unknown filetype: {name}"))?;
+            Ok(Box::new(buffer::SetLanguage::new(language)))
+        }
+        None if matches!(option, "filetype?" | "ft?" | "filetype" | "ft") => {
+            Ok(Box::new(buffer::ShowLanguage))
+        }
+        Some(("fileencoding" | "fenc", name)) => {
+            let encoding = Encoding::from_name(name)
+                .with_context(|| format!("Unknown fileencoding: {name}"))?;
+            Ok(Box::new(buffer::SetEncoding::new(encoding)))
+        }
+        None if matches!(option, "fileencoding?" | "fenc?" | "fileencoding" | "fenc") => {
+            Ok(Box::new(buffer::ShowEncoding))
+        }
+        _ => {
+            let name = option.split(['=', '?']).next().unwrap_or(option);
+            Err(unknown("option", name, OPTIONS))
+        }
+    }
+}
+
+/// Error naming what wasn't recognized, with the closest known name if there is one
+fn unknown<'a>(kind: &str, name: &str, known: impl IntoIterator<Item = &'a str>) -> anyhow::Error {
+    match closest(name, known) {
+        Some(suggestion) => anyhow!("Unknown {kind} '{name}', did you mean '{suggestion}'?"),
+        None => anyhow!("Unknown {kind} '{name}'"),
+    }
+}
+
+/// Parses `:g/pattern/cmd` and the inverted `:g!/pattern/cmd` and `:v/pattern/cmd`
+fn parse_global(input: &str) -> Option<Result<Box<dyn Executable>>> {
+    let (invert, rest) = [("g!", true), ("global!", true), ("v", true), ("vglobal", true)]
+        .into_iter()
+        .chain([("g", false), ("global", false)])
+        .find_map(|(name, invert)| {
+            let rest = input.strip_prefix(name)?;
+            rest.starts_with(is_delimiter).then_some((invert, rest))
+        })?;
+
+    Some(parse_global_command(rest, invert))
+}
+
+fn parse_global_command(input: &str, invert: bool) -> Result<Box<dyn Executable>> {
+    let delimiter = input.chars().next().context("Missing pattern")?;
+    let (pattern, command) = split_delimited(&input[delimiter.len_utf8()..], delimiter);
+    // Without a command the matching lines are printed, as in vim
+    let command = match command.unwrap_or_default().trim_start() {
+        "" => "p",
+        command => command,
+    };
+
+    // The sub-command can't start another :g, which would run on lines being edited
+    if parse_global(command).is_some() {
+        return Err(anyhow!("Cannot nest :global"));
+    }
+    let command = parse_line_command(command)
+        .with_context(|| format!("Not a line command: {command}"))??;
+
+    // An empty pattern is the last search, only known when the command runs
+    let selection = match pattern.as_str() {
+        "" => LineSelection::LastSearch { invert },
+        pattern => LineSelection::Matching { pattern: Regex::new(pattern)?, invert },
+    };
+    Ok(Box::new(RunLineCommand::new(selection, command)))
+}
+
+/// Parses a range such as `5,10`, `.,$` or `%` in front of a line command.
+/// Without one the command runs on the cursor line
+fn parse_range(input: &str) -> (LineSelection, &str) {
+    if let Some(rest) = input.strip_prefix('%') {
+        return (LineSelection::Range(Address::Line(0), Address::Last), rest);
+    }
+    let Some((start, rest)) = parse_address(input) else {
+        return (LineSelection::Current, input);
+    };
+    match rest.strip_prefix(',').and_then(parse_address) {
+        Some((end, rest)) => (LineSelection::Range(start, end), rest),
+        None => (LineSelection::Range(start, start), rest),
+    }
+}
+
+fn parse_address(input: &str) -> Option<(Address, &str)> {
+    if let Some(rest) = input.strip_prefix('.') {
+        return Some((Address::Current, rest));
+    }
+    if let Some(rest) = input.strip_prefix('$') {
+        return Some((Address::Last, rest));
+    }
+    let digits = input.len() - input.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let line = input[..digits].parse::<usize>().ok()?;
+    Some((Address::Line(line.saturating_sub(1)), &input[digits..]))
+}
+
+/// Parses commands that act on a single line: `d`, `p`, `nu`, `s/pattern/replacement/g`
+/// and `normal {keys}`
+fn parse_line_command(input: &str) -> Option<Result<LineCommand>> {
+    if let Some((name, keys)) = input.split_once(char::is_whitespace)
+        && let Some(remap) = match name {
+            "norm" | "normal" => Some(true),
+            "norm!" | "normal!" => Some(false),
+            _ => None,
+        }
+    {
+        let keys = ExecuteKeys::new(keys.trim_start().to_string(), remap);
+        return Some(Ok(LineCommand::Normal(keys)));
+    }
+
+    let input = input.trim_end();
+    match input {
+        "d" | "delete" => return Some(Ok(LineCommand::Delete)),
+        "p" | "print" => return Some(Ok(LineCommand::Print { numbered: false })),
+        "nu" | "number" | "#" => return Some(Ok(LineCommand::Print { numbered: true })),
+        _ => {}
+    }
+
+    if let Some(rest) = input.strip_prefix('S').filter(|rest| rest.starts_with(is_delimiter)) {
+        return Some(parse_substitute(rest, true));
+    }
+    let rest = input
+        .strip_prefix("substitute")
+        .or_else(|| input.strip_prefix('s'))
+        .filter(|rest| rest.starts_with(is_delimiter))?;
+    Some(parse_substitute(rest, false))
+}
+
+/// Parses `sort` with its flags, such as `sort! nu`
+fn parse_sort(input: &str) -> Option<Result<SortOptions>> {
+    let input = input.trim();
+    let rest = input
+        .strip_prefix("sort")
+        .or_else(|| input.strip_prefix("sor"))?;
+    let (reverse, flags) = match rest.strip_prefix('!') {
+        Some(flags) => (true, flags),
+        None => (false, rest),
+    };
+    if !flags.is_empty() && !flags.starts_with(char::is_whitespace) && !reverse {
+        return None;
+    }
+
+    let mut options = SortOptions { reverse, ..SortOptions::default() };
+    for flag in flags.trim_start().chars() {
+        match flag {
+            'n' => options.numeric = true,
+            'u' => options.unique = true,
+            flag => return Some(Err(anyhow!("Unknown flag: {flag}"))),
+        }
+    }
+    Some(Ok(options))
+}
+
+/// Parses the rest of `s/pattern/replacement/flags`, where the flags are `g` for every
+/// match of a line and `i` or `I` to ignore case or not. `:S` ignores case by default
+/// and writes the replacement in the case of each match
+fn parse_substitute(input: &str, smart: bool) -> Result<LineCommand> {
+    let delimiter = input.chars().next().context("Missing pattern")?;
+    let (pattern, rest) = split_delimited(&input[delimiter.len_utf8()..], delimiter);
+    let (replacement, flags) = match rest {
+        Some(rest) => split_delimited(rest, delimiter),
+        None => (String::new(), None),
+    };
+    let flags = flags.unwrap_or_default();
+    let mut all = false;
+    let mut ignore_case = smart;
+    for flag in flags.chars() {
+        match flag {
+            'g' => all = true,
+            'i' => ignore_case = true,
+            'I' => ignore_case = false,
+            _ => return Err(anyhow!("Unknown flags: {flags}")),
+        }
+    }
+
+    let pattern = match ignore_case {
+        true => format!("(?i){pattern}"),
+        false => pattern,
+    };
+    let replacement = match smart {
+        true => Replacement::parse(&replacement).preserving_case(),
+        false => Replacement::parse(&replacement),
+    };
+    Ok(LineCommand::Substitute {
+        pattern: Regex::new(&pattern)?,
+        replacement,
+        all,
+    })
+}
+
+fn is_delimiter(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace() && !matches!(c, '"' | '|' | '\\')
+}
+
+/// Splits at the first delimiter not escaped with a backslash, turning `\/` into `/`
+fn split_delimited(input: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        if c == delimiter {
+            return (part, Some(&input[index + c.len_utf8()..]));
+        }
+        part.push(c);
+        if c == '\\'
+            && let Some((_, next)) = chars.next()
+        {
+            if next == delimiter {
+                part.pop();
             }
+            part.push(next);
         }
     }
+    (part, None)
+}
+
+/// Parses `+N` and `-N` line offsets, where a bare sign means one line
+fn parse_relative_line(command: &str) -> Option<isize> {
+    let (sign, count) = match command.split_at_checked(1)? {
+        ("+", count) => (1, count),
+        ("-", count) => (-1, count),
+        _ => return None,
+    };
+    let count = if count.is_empty() { 1 } else { count.parse::<isize>().ok()? };
+    Some(sign * count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    #[test]
+    fn test_parse_commands() {
+        let cases = [
+            ("q", "CloseBuffer { force: false }"),
+            ("quit", "CloseBuffer { force: false }"),
+            ("q !", "CloseBuffer { force: true }"),
+            ("quit!", "CloseBuffer { force: true }"),
+            ("w", "WriteBuffer { path: None, force: false, create_dirs: false }"),
+            ("write out.txt", "WriteBuffer { path: Some(\"out.txt\"), force: false, create_dirs: false }"),
+            ("w! ++p dir/out.txt", "WriteBuffer { path: Some(\"dir/out.txt\"), force: true, create_dirs: true }"),
+            ("wq", "CompositeExecutable([WriteBuffer { path: None, force: false, create_dirs: false }, CloseBuffer { force: false }])"),
+            ("wq! out.txt", "CompositeExecutable([WriteBuffer { path: Some(\"out.txt\"), force: true, create_dirs: false }, CloseBuffer { force: false }])"),
+            ("e!", "ReloadBuffer"),
+            ("wall", "WriteAllBuffers"),
+            ("qa", "QuitAll { force: false }"),
+            ("qall!", "QuitAll { force: true }"),
+            ("xa", "CompositeExecutable([WriteAllBuffers, QuitAll { force: false }])"),
+            ("wqall!", "CompositeExecutable([WriteAllBuffers, QuitAll { force: true }])"),
+            ("edit foo.rs", "OpenBuffer { path: \"foo.rs\" }"),
+            ("se ft=rust", "SetLanguage { language: Rust }"),
+            ("set filetype?", "ShowLanguage"),
+            ("set fenc=latin1", "SetEncoding { encoding: Latin1 }"),
+            ("set fileencoding", "ShowEncoding"),
+            ("tutor", "OpenTutor"),
+            ("diff", "ShowDiff"),
+            ("sym main", "ShowSymbols { query: \"main\" }"),
+            ("rename total", "Rename { new_name: \"total\" }"),
+            ("codeaction", "RequestCodeActions"),
+            ("diag", "ShowDiagnostic"),
+            ("fmt", "FormatBuffer"),
+            ("cprev", "GoToQuickfixEntry { offset: -1 }"),
+            ("bnext", "NextBuffer"),
+            ("bp", "PreviousBuffer"),
+            ("ls", "ShowBuffers"),
+            ("messages", "ShowMessages"),
+            ("b 2", "SwitchToBuffer { target: \"2\" }"),
+            ("bd!", "DeleteBuffer { force: true }"),
+            ("12", "SeekToLine { line_number: 11 }"),
+            ("-", "GoToRelativeLine { offset: -1 }"),
+            ("50%", "SeekToPercent { percent: 50 }"),
+            ("d", "RunLineCommand { selection: Current, command: Delete }"),
+            ("5,10d", "RunLineCommand { selection: Range(Line(4), Line(9)), command: Delete }"),
+            ("%delete", "RunLineCommand { selection: Range(Line(0), Last), command: Delete }"),
+            (".,$s/a/b/g", "RunLineCommand { selection: Range(Current, Last), command: Substitute { pattern: Regex(\"a\"), replacement: Replacement { parts: [Text(\"b\")], preserve_case: false }, all: true } }"),
+            ("s/a/\\u&/gi", "RunLineCommand { selection: Current, command: Substitute { pattern: Regex(\"(?i)a\"), replacement: Replacement { parts: [Next(Upper), Text(\"${0}\")], preserve_case: false }, all: true } }"),
+            ("%S/a/b/I", "RunLineCommand { selection: Range(Line(0), Last), command: Substitute { pattern: Regex(\"a\"), replacement: Replacement { parts: [Text(\"b\")], preserve_case: true }, all: false } }"),
+            ("2,3norm! dd", "RunLineCommand { selection: Range(Line(1), Line(2)), command: Normal(ExecuteKeys { keys: \"dd\", remap: false }) }"),
+            ("sort", "SortLines { selection: Range(Line(0), Last), options: SortOptions { reverse: false, numeric: false, unique: false } }"),
+            ("10,20sort! nu", "SortLines { selection: Range(Line(9), Line(19)), options: SortOptions { reverse: true, numeric: true, unique: true } }"),
+            ("v/a/d", "RunLineCommand { selection: Matching { pattern: Regex(\"a\"), invert: true }, command: Delete }"),
+            ("10p", "RunLineCommand { selection: Range(Line(9), Line(9)), command: Print { numbered: false } }"),
+            ("10,15nu", "RunLineCommand { selection: Range(Line(9), Line(14)), command: Print { numbered: true } }"),
+            ("g/a", "RunLineCommand { selection: Matching { pattern: Regex(\"a\"), invert: false }, command: Print { numbered: false } }"),
+            ("=", "PrintLineNumber { selection: Current }"),
+            (".=", "PrintLineNumber { selection: Range(Current, Current) }"),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse_command(input, &FileNames::default()).unwrap_or_else(|e| panic!("{input}: {e}"));
+            assert_eq!(format!("{parsed:?}"), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_command_errors() {
+        let cases = [
+            ("", "Empty command"),
+            ("wqa1", "Unknown command 'wqa1', did you mean 'wqa'?"),
+            ("wq!x", "Unknown command 'wq!x', did you mean 'wq!'?"),
+            ("foo", "Unknown command 'foo'"),
+            ("se", "Missing option, as in :set filetype=rust"),
+            ("set filetyp=rust", "Unknown option 'filetyp', did you mean 'filetype'?"),
+            ("set filetype=cobol", "Unknown filetype: cobol"),
+            ("w a b", "Only one file name allowed"),
+            ("e", "Missing file name"),
+            ("sort x", "Unknown flag: x"),
+        ];
+        for (input, expected) in cases {
+            let error = parse_command(input, &FileNames::default()).expect_err(input);
+            assert_eq!(error.to_string(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_expand_file_names() {
+        let files = FileNames {
+            current: Some("src/main.rs".to_string()),
+            alternate: Some("notes.md".to_string()),
+        };
+        let cases = [
+            ("e #", "OpenBuffer { path: \"notes.md\" }"),
+            ("w %.bak", "WriteBuffer { path: Some(\"src/main.rs.bak\"), force: false, create_dirs: false }"),
+            ("e \\#1\\%", "OpenBuffer { path: \"#1%\" }"),
+            ("e a\\b", "OpenBuffer { path: \"a\\\\b\" }"),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse_command(input, &files).unwrap();
+            assert_eq!(format!("{parsed:?}"), expected, "{input}");
+        }
+        let error = parse_command("e #", &FileNames::default()).unwrap_err();
+        assert_eq!(error.to_string(), "No alternate file name to substitute for '#'");
+    }
+
+    #[test]
+    fn test_suggested_commands_parse() {
+        for name in COMMANDS {
+            // Commands taking a pattern are given one. Errors about missing arguments
+            // are fine, the name itself is known
+            let input = match name {
+                "s" | "substitute" | "g" | "global" | "global!" | "v" | "vglobal" => format!("{name}/a/"),
+                _ => format!("{name} "),
+            };
+            if let Err(error) = parse_command(&input, &FileNames::default()) {
+                assert!(!error.to_string().starts_with("Unknown command"), "{name}: {error}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_command_message() {
+        let mut editor = TestEditor::new("");
+        editor.keys(":qiut<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: Unknown command 'qiut', did you mean 'quit'?");
+    }
+
+    #[test]
+    fn test_parse_relative_line() {
+        assert_eq!(parse_relative_line("+5"), Some(5));
+        assert_eq!(parse_relative_line("-3"), Some(-3));
+        assert_eq!(parse_relative_line("+"), Some(1));
+        assert_eq!(parse_relative_line("-"), Some(-1));
+        assert_eq!(parse_relative_line("5"), None);
+        assert_eq!(parse_relative_line("+x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_set_filetype() {
+        let mut editor = TestEditor::new("fn main() {}\n");
+
+        editor.keys(":set filetype=rust<Enter>").await.unwrap();
+        let document = editor.core.current_document();
+        assert_eq!(document.language, Language::Rust);
+        assert!(document.syntax_engine.is_some());
+
+        editor.keys(":set ft=text<Enter>").await.unwrap();
+        let document = editor.core.current_document();
+        assert_eq!(document.language, Language::PlainText);
+        assert!(document.syntax_engine.is_none());
+
+        assert!(parse_command("set filetype=cobol", &FileNames::default()).is_err());
+        assert!(parse_command("set filetype?", &FileNames::default()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_relative_line_commands() {
+        let mut editor = TestEditor::new("0\n1\n2\n3\n4\n5\n6\n");
+
+        editor.keys(":+5<Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (5, 0));
+
+        editor.keys(":-3<Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 0));
+
+        editor.keys(":-10<Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_global_delete() {
+        let mut editor = TestEditor::new("keep\ndrop 1\ndrop 2\nkeep\ndrop 3\n");
+
+        editor.keys(":g/drop/d<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "keep\nkeep\n");
+
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "keep\ndrop 1\ndrop 2\nkeep\ndrop 3\n");
+
+        editor.keys(":v/drop/d<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "drop 1\ndrop 2\ndrop 3\n");
+
+        // A line ends before its carriage return
+        let mut editor = TestEditor::new("drop 1\r\ndrop 2\r\n");
+        editor.keys(":g/2$/d<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "drop 1\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_global_last_search() {
+        let mut editor = TestEditor::new("keep\ndrop 1\nkeep\ndrop 2\n");
+
+        editor.keys(":g//d<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: No previous regular expression");
+
+        // The search is remembered after an edit clears its matches
+        editor.keys("/drop<Enter>A!<Esc>:g//d<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "keep\nkeep\n");
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "2 fewer lines");
+
+        editor.keys("u:v//d<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "drop 1!\ndrop 2\n");
+    }
+
+    #[tokio::test]
+    async fn test_global_substitute() {
+        let mut editor = TestEditor::new("a a\nb a\na b\n");
+
+        editor.keys(":g!/^b/s/a/x/g<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "x x\nb a\nx b\n");
+
+        // The cursor is left on the last substituted line
+        editor.keys(":s/x/y/<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "x x\nb a\ny b\n");
+
+        // A `$` is written as it is
+        editor.keys(":g/a/s/(a)/$1 $/<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "x x\nb $1 $\ny b\n");
+    }
+
+    #[tokio::test]
+    async fn test_print_lines() {
+        let mut editor = TestEditor::new("one\ntwo\nthree\nfour\n");
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+
+        editor.keys(":2p<Enter>").await.unwrap();
+        assert_eq!(shown(&editor), "two");
+        assert_eq!(editor.cursor(), (1, 0));
+        editor.keys(":=<Enter>").await.unwrap();
+        assert_eq!(shown(&editor), "4");
+        editor.keys(":.=<Enter>").await.unwrap();
+        assert_eq!(shown(&editor), "2");
+
+        // Several lines go to the pager, until a key closes it
+        editor.keys(":g/o/nu<Enter>").await.unwrap();
+        let output = editor.message_manager.output().unwrap();
+        assert_eq!(output.lines, ["  1 one", "  2 two", "  4 four"]);
+        assert_eq!(editor.cursor(), (3, 0));
+        editor.keys("x").await.unwrap();
+        assert!(editor.message_manager.output().is_none());
+        assert_eq!(editor.text(), "one\ntwo\nthree\nfour\n");
+    }
+
+    #[tokio::test]
+    async fn test_substitute_case() {
+        let mut editor = TestEditor::new("foo Foo FOO\nstraße\n");
+
+        editor.keys(":%S/foo/bar/g<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "bar Bar BAR\nstraße\n");
+
+        // Case changes that change the length in bytes still edit the right bytes
+        editor.keys(":2s/\\w+/\\U&!<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "bar Bar BAR\nSTRASSE!\n");
+        editor.keys("$x").await.unwrap();
+        assert_eq!(editor.text(), "bar Bar BAR\nSTRASSE\n");
+        editor.keys("uu").await.unwrap();
+        assert_eq!(editor.text(), "bar Bar BAR\nstraße\n");
+    }
+
+    #[tokio::test]
+    async fn test_substitute_range() {
+        let mut editor = TestEditor::new("a/b a/b\nnone\n  a/b\n");
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+
+        editor.keys(":%s/a\\/b/\\0-(b\\/a)/g<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "a/b-(b/a) a/b-(b/a)\nnone\n  a/b-(b/a)\n");
+        assert_eq!(shown(&editor), "3 substitutions on 2 lines");
+        assert_eq!(editor.cursor(), (2, 2));
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "a/b a/b\nnone\n  a/b\n");
+
+        editor.keys(":1,2s#(\\w)/(\\w)#\\2/\\1#<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "b/a a/b\nnone\n  a/b\n");
+        assert_eq!(shown(&editor), "1 substitution on 1 line");
+        assert_eq!(editor.cursor(), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_global_errors() {
+        assert!(parse_command("g/a/g/b/d", &FileNames::default()).is_err());
+        assert!(parse_command("g/a/wq", &FileNames::default()).is_err());
+        assert!(parse_command("g/(/d", &FileNames::default()).is_err());
+        assert!(parse_command("s/a/b/q", &FileNames::default()).is_err());
+        assert_eq!(split_delimited(r"a\/b/c", '/'), ("a/b".to_string(), Some("c")));
+    }
+
+    #[tokio::test]
+    async fn test_global_normal() {
+        let mut editor = TestEditor::new("a 1\nb 2\na 3\n");
+
+        editor.keys(":g/^a/normal A;<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "a 1;\nb 2\na 3;\n");
+
+        editor.keys(":g/;/norm! dd<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "b 2\n");
+
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "a 1;\nb 2\na 3;\n");
+        assert_eq!(editor.core.mode, crate::core::mode::Mode::Normal);
+    }
+
+    #[tokio::test]
+    async fn test_range_normal() {
+        let mut editor = TestEditor::new("one\ntwo\nthree\nfour\n");
+
+        editor.keys(":2,3normal i- <Enter>").await.unwrap();
+        assert_eq!(editor.text(), "one\n- two\n- three\nfour\n");
+
+        editor.keys(":%s/^/>/<Enter>").await.unwrap();
+        assert_eq!(editor.text(), ">one\n>- two\n>- three\n>four\n");
+
+        // Lines run from the top, following the lines added above them
+        let mut editor = TestEditor::new("one\ntwo\nthree\n");
+        editor.keys(":1normal \"ayy<Enter>:2,3normal \"Ayy<Enter>G\"ap").await.unwrap();
+        assert_eq!(editor.text(), "one\ntwo\nthree\none\ntwo\nthree\n");
+        editor.keys(":1,3normal yyp<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "one\none\ntwo\ntwo\nthree\nthree\none\ntwo\nthree\n");
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "one\ntwo\nthree\none\ntwo\nthree\n");
+    }
+
+    #[tokio::test]
+    async fn test_normal_cannot_nest_global() {
+        let mut editor = TestEditor::new("a\nb\n");
+
+        editor.keys(":g/a/normal :g/b/d<lt>Enter><Enter>").await.unwrap();
+        assert_eq!(editor.text(), "a\nb\n");
+    }
 }
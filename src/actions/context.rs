@@ -1,13 +1,17 @@
 use crate::config::Config;
 use crate::core::buffer_manager::BufferManager;
+use crate::core::completions::Completions;
 use crate::core::command::{CommandBuffer, SearchBuffer};
 use crate::core::cursor::Cursor;
+use crate::core::hover::HoverText;
 use crate::core::message::MessageManager;
 use crate::core::mode::Mode;
+use crate::core::quickfix::QuickfixList;
 use crate::core::register::RegisterSystem;
 use crate::core::viewport::Viewport;
 use crate::input::InputProcessor;
-use crate::service::LspService;
+use crate::service::jobs::Jobs;
+use crate::service::{LspService, SymbolIndex};
 use crate::ui::compositor::Compositor;
 
 // Context passed to actions when they execute
@@ -17,6 +21,12 @@ pub struct EditorContext<'a> {
     pub mode: &'a mut Mode,
     pub buffer_manager: &'a mut BufferManager,
     pub register_system: &'a mut RegisterSystem,
+    pub jobs: &'a mut Jobs,
+    /// Row `V` was typed on, the other end of the lines selected in visual line mode
+    pub visual_anchor: &'a mut usize,
+    pub completion: &'a mut Option<Completions>,
+    pub hover: &'a mut Option<HoverText>,
+    pub quickfix: &'a mut QuickfixList,
 }
 
 pub struct UIContext<'a> {
@@ -37,4 +47,5 @@ pub struct ActionContext<'a> {
     pub config: &'a Config,
     pub running: &'a mut bool,
     pub lsp_service: &'a mut LspService,
+    pub symbol_index: &'a mut SymbolIndex,
 }
\ No newline at end of file
@@ -1,18 +1,28 @@
 use crate::actions::core::{Action, CompositeAction};
-use crate::actions::types::{buffer, editing, lsp, mode, movement, search, system};
+use crate::actions::types::{
+    buffer, editing, indent, keyword, lsp, mode, movement, search, symbols, system, visual,
+};
 use crate::core::mode::Mode;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::cell::RefCell;
 use std::path::PathBuf;
 use crate::actions::command;
 
+/// Keymap definitions of actions.
+///
+/// New fields must have a serde default so that `{ type = "X" }` keeps
+/// loading for every action that worked without parameters before.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", content = "params")]
+#[serde(remote = "Self", tag = "type", content = "params")]
 pub enum ActionDefinition {
     // Movement actions
     MoveLeft {
+        #[serde(default = "default_inline")]
         inline: bool,
     },
     MoveRight {
+        #[serde(default = "default_inline")]
         inline: bool,
     },
     MoveUp,
@@ -24,33 +34,114 @@ pub enum ActionDefinition {
     MoveToViewportCenter,
     MoveToPreviousWord,
     MoveToNextWord,
+    MoveToNextParagraph,
+    MoveToPreviousParagraph,
+    MoveToNextSentence,
+    MoveToPreviousSentence,
     GoToLine {
         line_number: usize,
     },
+    /// Goes to a zero-based column of the line, taken from the count when one is typed
+    GoToColumn {
+        #[serde(default)]
+        column: usize,
+    },
+    /// Goes to a zero-based byte of the buffer, taken from the count when one is typed
+    GoToByte {
+        #[serde(default)]
+        offset: usize,
+    },
+    /// Goes to the bracket matching the one under the cursor, or with a count to that
+    /// percent of the file
+    MoveToMatchingBracket {
+        #[serde(default)]
+        percent: usize,
+    },
+    /// `m` and a letter
+    SetMark {
+        name: char,
+    },
+    /// `'` and a mark, or `` ` `` and a mark when `exact`
+    GoToMark {
+        name: char,
+        #[serde(default)]
+        exact: bool,
+    },
+    /// `Ctrl-o`, back to the position the last jump left
+    JumpBack,
+    /// `Ctrl-i`, forward again after `Ctrl-o`
+    JumpForward,
+    /// `iw`, `a(` and the like after an operator, which acts on the object around the
+    /// cursor. On its own it goes to the start of the object
+    TextObject {
+        object: char,
+        #[serde(default)]
+        inner: bool,
+    },
 
     // Editing actions
     InsertChar {
         ch: char,
     },
+    /// `r` and a character, replacing as many characters as the count
+    ReplaceChar {
+        ch: char,
+        #[serde(default = "default_count")]
+        count: usize,
+    },
     InsertNewLine,
+    /// Tab in insert mode, which inserts spaces with `expand_tab`
+    InsertTab,
     InsertNewLineBelow,
     InsertNewLineAbove,
 
+    /// Deletes before the cursor, stopping at the line start when `inline`
     Backspace {
+        #[serde(default)]
         inline: bool,
     },
+    /// Deletes under the cursor, never removing the newline when `inline`
     DeleteChar {
+        #[serde(default = "default_inline")]
         inline: bool,
     },
+    /// Deletes the word before the cursor, Ctrl-w in insert mode
+    DeleteWordBefore,
+    /// Deletes the text of the line before the cursor, Ctrl-u in insert mode
+    DeleteToLineStart,
     DeleteCurrentLine,
     ChangeCurrentLine,
 
     Undo,
     Redo,
-    PasteBeforeCursor,
-    PasteAfterCursor,
+    /// Pastes `count` copies of the register, taken from the count when one is typed
+    PasteBeforeCursor {
+        #[serde(default = "default_count")]
+        count: usize,
+    },
+    PasteAfterCursor {
+        #[serde(default = "default_count")]
+        count: usize,
+    },
+    /// `gP`, leaving the cursor just after the pasted text
+    PasteBeforeCursorAndMove {
+        #[serde(default = "default_count")]
+        count: usize,
+    },
+    /// `gp`, leaving the cursor just after the pasted text
+    PasteAfterCursorAndMove {
+        #[serde(default = "default_count")]
+        count: usize,
+    },
 
     YankCurrentLine,
+    IndentCurrentLine,
+    DedentCurrentLine,
+
+    // Visual line actions, on the lines between the anchor and the cursor
+    DeleteSelectedLines,
+    ChangeSelectedLines,
+    YankSelectedLines,
     
     // Command actions
     CommandMoveLeft,
@@ -58,6 +149,13 @@ pub enum ActionDefinition {
     CommandBackspace,
     CommandDeleteChar,
     CommandExecute,
+    /// Up and Down in command mode
+    CommandHistoryOlder,
+    CommandHistoryNewer,
+    /// Tab in command mode, completing a file name and cycling through the matches
+    CommandComplete,
+    /// Shift-Tab in command mode, cycling through the matches the other way
+    CommandCompleteBack,
 
     // Search actions
     SearchMoveLeft,
@@ -65,8 +163,15 @@ pub enum ActionDefinition {
     SearchDeleteChar,
     SearchBackspace,
     SearchSubmit,
-    FindNext,
-    FindPrevious,
+    #[serde(alias = "FindNext")]
+    SearchNext,
+    #[serde(alias = "FindPrevious")]
+    SearchPrevious,
+    /// Up and Down in search mode
+    SearchHistoryOlder,
+    SearchHistoryNewer,
+    SearchWordForward,
+    SearchWordBackward,
 
     // Mode actions
     EnterMode {
@@ -76,21 +181,64 @@ pub enum ActionDefinition {
     // Buffer actions
     NextBuffer,
     PreviousBuffer,
+    SwitchToAlternate,
     OpenBuffer {
         path: String,
     },
+    ReloadBuffer,
     WriteBuffer {
+        #[serde(default)]
         path: Option<String>,
+        #[serde(default)]
+        force: bool,
+        #[serde(default)]
+        create_dirs: bool,
     },
     CloseBuffer {
+        #[serde(default)]
         force: bool,
     },
+    DeleteBuffer {
+        #[serde(default)]
+        force: bool,
+    },
+    ShowBuffers,
+    SwitchToBuffer {
+        target: String,
+    },
+    WriteAllBuffers,
+    /// Goes from a diff view to the changed line, from a symbol or reference list to the
+    /// place listed, applies the code action listed, or goes down a line anywhere else
+    #[serde(alias = "FollowDiff")]
+    FollowLine,
 
     // LSP actions
     GoToDefinition,
+    /// Goes to a definition found by the symbol index, which `GoToDefinition` uses without a server
+    GoToDefinitionFallback,
+    /// Shows the language server's hover information, falling back to `LookupKeyword`
+    Hover,
+    /// Asks the language server for completions of the word before the cursor
+    RequestCompletion,
+    /// Lists the references of the symbol under the cursor
+    FindReferences,
+    /// Goes to the next diagnostic of the document, wrapping around
+    NextDiagnostic,
+    /// Goes to the previous diagnostic of the document, wrapping around
+    PreviousDiagnostic,
+    /// Runs the configured documentation command on the word under the cursor
+    LookupKeyword,
 
     // System actions
     Quit,
+    /// Quits unless a buffer has unsaved changes, or regardless of them when `force`
+    QuitAll {
+        #[serde(default)]
+        force: bool,
+    },
+    ShowFileInfo,
+    Suspend,
+    ShowMessages,
 
     // Composite actions
     Composite {
@@ -99,6 +247,87 @@ pub enum ActionDefinition {
     },
 }
 
+fn default_inline() -> bool {
+    true
+}
+
+fn default_count() -> usize {
+    1
+}
+
+/// Old action names that are still accepted, with their current names
+const RENAMED_ACTIONS: [(&str, &str); 3] = [
+    ("FindNext", "SearchNext"),
+    ("FindPrevious", "SearchPrevious"),
+    ("FollowDiff", "FollowLine"),
+];
+
+thread_local! {
+    static DEPRECATION_WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the deprecated keymap forms found since the last call
+pub fn take_deprecation_warnings() -> Vec<String> {
+    DEPRECATION_WARNINGS.with(|warnings| warnings.take())
+}
+
+fn warn_deprecated(warning: String) {
+    DEPRECATION_WARNINGS.with(|warnings| warnings.borrow_mut().push(warning));
+}
+
+/// Rewrites definitions written for older versions into the current form
+fn migrate_definition(value: &mut Value) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    let Some(kind) = object.get("type").and_then(Value::as_str).map(str::to_string) else {
+        return;
+    };
+
+    if let Some((_, name)) = RENAMED_ACTIONS.iter().find(|(old, _)| *old == kind) {
+        warn_deprecated(format!("action `{kind}` was renamed to `{name}`"));
+    }
+
+    // Movements used to take a count, which now comes from the key sequence
+    if matches!(kind.as_str(), "MoveLeft" | "MoveRight")
+        && let Some(params) = object.get_mut("params").and_then(Value::as_object_mut)
+        && params.remove("count").is_some()
+    {
+        warn_deprecated(format!(
+            "`count` of `{kind}` is ignored, type a count before the key instead"
+        ));
+    }
+}
+
+impl Serialize for ActionDefinition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ActionDefinition::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ActionDefinition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = Value::deserialize(deserializer)?;
+        migrate_definition(&mut value);
+
+        let error = match ActionDefinition::deserialize(&value) {
+            Ok(definition) => return Ok(definition),
+            Err(error) => error,
+        };
+
+        // Actions whose fields all have defaults may leave out `params`
+        if let Some(object) = value.as_object_mut()
+            && !object.contains_key("params")
+        {
+            object.insert("params".to_string(), Value::Object(Default::default()));
+            if let Ok(definition) = ActionDefinition::deserialize(&value) {
+                return Ok(definition);
+            }
+        }
+        Err(serde::de::Error::custom(error))
+    }
+}
+
 pub fn create_action_from_definition(definition: &ActionDefinition) -> Box<dyn Action> {
     match definition {
         // Movement actions
@@ -113,26 +342,65 @@ pub fn create_action_from_definition(definition: &ActionDefinition) -> Box<dyn A
         ActionDefinition::MoveToViewportCenter => Box::new(movement::MoveToViewportCenter),
         ActionDefinition::MoveToPreviousWord => Box::new(movement::MoveToPreviousWord),
         ActionDefinition::MoveToNextWord => Box::new(movement::MoveToNextWord),
+        ActionDefinition::MoveToNextParagraph => Box::new(movement::MoveToNextParagraph),
+        ActionDefinition::MoveToPreviousParagraph => Box::new(movement::MoveToPreviousParagraph),
+        ActionDefinition::MoveToNextSentence => Box::new(movement::MoveToNextSentence),
+        ActionDefinition::MoveToPreviousSentence => Box::new(movement::MoveToPreviousSentence),
         ActionDefinition::GoToLine { line_number } => {
             Box::new(movement::GoToLine::new(*line_number))
         }
+        ActionDefinition::GoToColumn { column } => Box::new(movement::GoToColumn::new(*column)),
+        ActionDefinition::GoToByte { offset } => Box::new(movement::GoToByte::new(*offset)),
+        ActionDefinition::MoveToMatchingBracket { percent } => {
+            Box::new(movement::MoveToMatchingBracket::new(*percent))
+        }
+        ActionDefinition::SetMark { name } => Box::new(movement::SetMark::new(*name)),
+        ActionDefinition::GoToMark { name, exact } => {
+            Box::new(movement::GoToMark::new(*name, *exact))
+        }
+        ActionDefinition::JumpBack => Box::new(movement::WalkJumps::new(false)),
+        ActionDefinition::JumpForward => Box::new(movement::WalkJumps::new(true)),
+        ActionDefinition::TextObject { object, inner } => {
+            Box::new(movement::MoveToTextObject::new(*object, *inner))
+        }
 
         // Editing actions
         ActionDefinition::InsertChar { ch } => Box::new(editing::InsertChar::new(*ch)),
+        ActionDefinition::ReplaceChar { ch, count } => {
+            Box::new(editing::ReplaceChar::new(*ch, *count))
+        }
         ActionDefinition::DeleteChar { inline } => Box::new(editing::DeleteChar::new(*inline)),
         ActionDefinition::Backspace { inline } => Box::new(editing::Backspace::new(*inline)),
+        ActionDefinition::DeleteWordBefore => Box::new(editing::DeleteWordBefore),
+        ActionDefinition::DeleteToLineStart => Box::new(editing::DeleteToLineStart),
         ActionDefinition::InsertNewLine => Box::new(editing::InsertNewLine),
+        ActionDefinition::InsertTab => Box::new(editing::InsertTab),
         ActionDefinition::InsertNewLineBelow => Box::new(editing::InsertNewLineBelow),
         ActionDefinition::InsertNewLineAbove => Box::new(editing::InsertNewLineAbove),
         ActionDefinition::DeleteCurrentLine => Box::new(editing::DeleteCurrentLine),
         ActionDefinition::ChangeCurrentLine => Box::new(editing::ChangeCurrentLine),
         ActionDefinition::YankCurrentLine => Box::new(editing::YankCurrentLine),
+        ActionDefinition::IndentCurrentLine => Box::new(indent::IndentCurrentLine),
+        ActionDefinition::DedentCurrentLine => Box::new(indent::DedentCurrentLine),
+        ActionDefinition::DeleteSelectedLines => Box::new(visual::DeleteSelectedLines),
+        ActionDefinition::ChangeSelectedLines => Box::new(visual::ChangeSelectedLines),
+        ActionDefinition::YankSelectedLines => Box::new(visual::YankSelectedLines),
 
         ActionDefinition::Undo => Box::new(editing::Undo),
         ActionDefinition::Redo => Box::new(editing::Redo),
 
-        ActionDefinition::PasteBeforeCursor => Box::new(editing::PasteBeforeCursor),
-        ActionDefinition::PasteAfterCursor => Box::new(editing::PasteAfterCursor),
+        ActionDefinition::PasteBeforeCursor { count } => {
+            Box::new(editing::PasteBeforeCursor::new(*count))
+        }
+        ActionDefinition::PasteAfterCursor { count } => {
+            Box::new(editing::PasteAfterCursor::new(*count))
+        }
+        ActionDefinition::PasteBeforeCursorAndMove { count } => {
+            Box::new(editing::PasteBeforeCursorAndMove::new(*count))
+        }
+        ActionDefinition::PasteAfterCursorAndMove { count } => {
+            Box::new(editing::PasteAfterCursorAndMove::new(*count))
+        }
         
         // Command actions
         ActionDefinition::CommandMoveLeft => Box::new(command::CommandMoveLeft),
@@ -147,8 +415,16 @@ pub fn create_action_from_definition(definition: &ActionDefinition) -> Box<dyn A
         ActionDefinition::SearchDeleteChar => Box::new(search::SearchDeleteChar),
         ActionDefinition::SearchBackspace => Box::new(search::SearchBackspace),
         ActionDefinition::SearchSubmit => Box::new(search::SearchSubmit),
-        ActionDefinition::FindNext => Box::new(search::FindNext),
-        ActionDefinition::FindPrevious => Box::new(search::FindPrevious),
+        ActionDefinition::SearchNext => Box::new(search::FindNext),
+        ActionDefinition::SearchPrevious => Box::new(search::FindPrevious),
+        ActionDefinition::CommandHistoryOlder => Box::new(command::CommandHistoryOlder),
+        ActionDefinition::CommandHistoryNewer => Box::new(command::CommandHistoryNewer),
+        ActionDefinition::CommandComplete => Box::new(command::CommandComplete::new(false)),
+        ActionDefinition::CommandCompleteBack => Box::new(command::CommandComplete::new(true)),
+        ActionDefinition::SearchHistoryOlder => Box::new(search::SearchHistoryOlder),
+        ActionDefinition::SearchHistoryNewer => Box::new(search::SearchHistoryNewer),
+        ActionDefinition::SearchWordForward => Box::new(search::SearchWordForward),
+        ActionDefinition::SearchWordBackward => Box::new(search::SearchWordBackward),
 
         // Mode actions
         ActionDefinition::EnterMode { mode } => Box::new(mode::EnterMode::new(*mode)),
@@ -156,21 +432,44 @@ pub fn create_action_from_definition(definition: &ActionDefinition) -> Box<dyn A
         // Buffer actions
         ActionDefinition::NextBuffer => Box::new(buffer::NextBuffer),
         ActionDefinition::PreviousBuffer => Box::new(buffer::PreviousBuffer),
+        ActionDefinition::SwitchToAlternate => Box::new(buffer::SwitchToAlternate),
         ActionDefinition::OpenBuffer { path } => {
             let path_buf = PathBuf::from(path);
             Box::new(buffer::OpenBuffer::new(path_buf))
         }
-        ActionDefinition::WriteBuffer { path } => {
+        ActionDefinition::ReloadBuffer => Box::new(buffer::ReloadBuffer),
+        ActionDefinition::WriteBuffer { path, force, create_dirs } => {
             let path_buf = path.as_ref().map(PathBuf::from);
-            Box::new(buffer::WriteBuffer::new(path_buf))
+            let write = buffer::WriteBuffer::new(path_buf)
+                .forced(*force)
+                .creating_dirs(*create_dirs);
+            Box::new(write)
         }
         ActionDefinition::CloseBuffer { force } => Box::new(buffer::CloseBuffer::force(*force)),
+        ActionDefinition::DeleteBuffer { force } => Box::new(buffer::DeleteBuffer::force(*force)),
+        ActionDefinition::ShowBuffers => Box::new(buffer::ShowBuffers),
+        ActionDefinition::SwitchToBuffer { target } => {
+            Box::new(buffer::SwitchToBuffer::new(target.clone()))
+        }
+        ActionDefinition::WriteAllBuffers => Box::new(buffer::WriteAllBuffers),
+        ActionDefinition::FollowLine => Box::new(movement::FollowLine),
 
         // LSP actions
         ActionDefinition::GoToDefinition => Box::new(lsp::GoToDefinition),
+        ActionDefinition::GoToDefinitionFallback => Box::new(symbols::GoToDefinitionFallback),
+        ActionDefinition::RequestCompletion => Box::new(lsp::RequestCompletion),
+        ActionDefinition::Hover => Box::new(lsp::Hover),
+        ActionDefinition::FindReferences => Box::new(lsp::FindReferences),
+        ActionDefinition::NextDiagnostic => Box::new(lsp::NextDiagnostic),
+        ActionDefinition::PreviousDiagnostic => Box::new(lsp::PreviousDiagnostic),
+        ActionDefinition::LookupKeyword => Box::new(keyword::LookupKeyword),
 
         // System actions
         ActionDefinition::Quit => Box::new(system::Quit),
+        ActionDefinition::QuitAll { force } => Box::new(system::QuitAll::force(*force)),
+        ActionDefinition::ShowFileInfo => Box::new(system::ShowFileInfo),
+        ActionDefinition::Suspend => Box::new(system::Suspend),
+        ActionDefinition::ShowMessages => Box::new(system::ShowMessages),
 
         ActionDefinition::Composite {
             description,
@@ -198,12 +497,23 @@ impl ActionDefinition {
             | ActionDefinition::MoveToLineStart
             | ActionDefinition::MoveToLineEnd
             | ActionDefinition::MoveToNextWord
-            | ActionDefinition::MoveToPreviousWord => Some(MovementType::Character),
+            | ActionDefinition::MoveToPreviousWord
+            | ActionDefinition::MoveToNextParagraph
+            | ActionDefinition::MoveToPreviousParagraph
+            | ActionDefinition::MoveToNextSentence
+            | ActionDefinition::MoveToPreviousSentence
+            | ActionDefinition::GoToColumn { .. }
+            | ActionDefinition::GoToByte { .. }
+            | ActionDefinition::MoveToMatchingBracket { percent: 0 }
+            | ActionDefinition::GoToMark { exact: true, .. }
+            | ActionDefinition::TextObject { .. } => Some(MovementType::Character),
             ActionDefinition::MoveUp
             | ActionDefinition::MoveDown
             | ActionDefinition::MoveToTop
             | ActionDefinition::MoveToBottom
-            | ActionDefinition::GoToLine { .. } => Some(MovementType::Line),
+            | ActionDefinition::GoToLine { .. }
+            | ActionDefinition::GoToMark { .. }
+            | ActionDefinition::MoveToMatchingBracket { .. } => Some(MovementType::Line),
             _ => None,
         }
     }
@@ -211,4 +521,108 @@ impl ActionDefinition {
     pub fn is_movement_type(&self) -> bool {
         self.get_movement_type().is_some()
     }
+
+    /// Character motions that, like vim's `%`, also take the character they end on
+    pub fn is_inclusive(&self) -> bool {
+        matches!(self, ActionDefinition::MoveToMatchingBracket { percent: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Actions that can't be written without parameters
+    const REQUIRED_PARAMS: [&str; 10] = [
+        "GoToLine",
+        "SetMark",
+        "GoToMark",
+        "TextObject",
+        "InsertChar",
+        "ReplaceChar",
+        "EnterMode",
+        "OpenBuffer",
+        "SwitchToBuffer",
+        "Composite",
+    ];
+
+    /// Keymap entries in the forms accepted by earlier versions
+    const HISTORICAL_KEYMAPS: [&str; 6] = [
+        r#"action = { type = "MoveLeft", params = { count = 1 } }"#,
+        r#"action = { type = "MoveRight", params = { count = 1, inline = false } }"#,
+        r#"action = { type = "DeleteChar" }"#,
+        r#"action = { type = "Backspace" }"#,
+        r#"action = { type = "FindNext" }"#,
+        r#"action = { type = "Composite", params = { description = "Find previous", actions = [
+            { type = "FindPrevious" },
+            { type = "MoveLeft", params = { count = 2 } },
+        ] } }"#,
+    ];
+
+    #[derive(Deserialize)]
+    struct Entry {
+        action: ActionDefinition,
+    }
+
+    fn parse(source: &str) -> Result<ActionDefinition, toml::de::Error> {
+        toml::from_str::<Entry>(source).map(|entry| entry.action)
+    }
+
+    /// Names kept as aliases of the actions that replaced them
+    const OLD_NAMES: [(&str, &str); 3] = [
+        ("FindNext", "SearchNext"),
+        ("FindPrevious", "SearchPrevious"),
+        ("FollowDiff", "FollowLine"),
+    ];
+
+    /// Every action name a keymap can use, as serde lists them for an unknown one, so
+    /// that a new action is covered without being listed here
+    fn action_names() -> Vec<String> {
+        let error = parse(r#"action = { type = "" }"#).unwrap_err().to_string();
+        let (_, names) = error.split_once("expected one of ").unwrap();
+        let names = names.lines().next().unwrap_or_default();
+        names.split(", ").map(|name| name.trim_matches('`').to_string()).collect()
+    }
+
+    #[test]
+    fn test_minimal_definitions() {
+        let names = action_names();
+        assert!(names.contains(&"MoveLeft".to_string()) && names.contains(&"Composite".to_string()));
+        for name in &names {
+            let result = parse(&format!(r#"action = {{ type = "{name}" }}"#));
+            if REQUIRED_PARAMS.contains(&name.as_str()) {
+                assert!(result.is_err(), "{name} unexpectedly loads without params");
+                continue;
+            }
+            // The definition loads as the action it names, or the one it's an old name of
+            let definition =
+                result.unwrap_or_else(|error| panic!("{name} must load without params: {error}"));
+            let value = serde_json::to_value(&definition).unwrap();
+            let renamed = OLD_NAMES.iter().find(|(old, _)| old == name).map(|(_, new)| *new);
+            assert_eq!(value["type"], renamed.unwrap_or(name));
+        }
+    }
+
+    #[test]
+    fn test_historical_keymaps() {
+        take_deprecation_warnings();
+        let definitions = HISTORICAL_KEYMAPS
+            .iter()
+            .map(|source| parse(source).unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(matches!(definitions[0], ActionDefinition::MoveLeft { inline: true }));
+        assert!(matches!(definitions[1], ActionDefinition::MoveRight { inline: false }));
+        assert!(matches!(definitions[2], ActionDefinition::DeleteChar { inline: true }));
+        assert!(matches!(definitions[3], ActionDefinition::Backspace { inline: false }));
+        assert!(matches!(definitions[4], ActionDefinition::SearchNext));
+        let ActionDefinition::Composite { actions, .. } = &definitions[5] else {
+            panic!("Expected a composite action");
+        };
+        assert!(matches!(actions[0], ActionDefinition::SearchPrevious));
+
+        let warnings = take_deprecation_warnings();
+        assert_eq!(warnings.len(), 5);
+        assert!(warnings.iter().any(|warning| warning.contains("`FindNext`")));
+    }
 }
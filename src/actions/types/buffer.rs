@@ -1,35 +1,94 @@
 use crate::actions::core::{impl_action, ActionDefinition, Executable};
 use crate::actions::types::system;
 use crate::actions::ActionResult;
-use crate::core::message::Message;
+use crate::actions::types::editing::after_edit;
+use crate::actions::types::format::{Formatted, format_buffer};
+use crate::actions::types::quickfix::update_quickfix;
+use crate::core::buffer_manager::BufferManager;
+use crate::core::document::canonicalize_path;
+use crate::core::encoding::{Encoding, FileEncoding};
+use crate::core::history::edit::Edit;
+use crate::core::swap::{Swap, newer_swap};
+use crate::core::language::Language;
+use crate::core::message::{Message, Recovery};
 use async_trait::async_trait;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
 use crate::actions::context::ActionContext;
-use crate::constants::components::EDITOR_VIEW;
-use crate::core::register::RegisterName;
+use crate::constants::components::{EDITOR_VIEW, STATUS_LINE};
+use crate::config::editor::ControlChars;
+use crate::core::register::{RegisterKind, RegisterName};
+use crate::ui::display::Glyph;
+use crate::core::syntax;
+use crate::ui::osc;
+use tree_sitter::Point;
 
-async fn after_buffer_change(ctx: &mut ActionContext<'_>) -> ActionResult {
+pub(super) async fn after_buffer_change(ctx: &mut ActionContext<'_>) -> ActionResult {
     let document = ctx.editor.buffer_manager.current();
     let language = document.language;
 
-    // Update syntax highlighter with the current document's language
-    if let Some(client) = ctx.lsp_service.start_server(language).await? {
-        client.did_open(&document).await?;
+    if ctx.config.report_directory
+        && let Some(path) = document.full_file_path()
+        && let Some(directory) = path.parent()
+    {
+        osc::report_directory(directory)?;
+    }
+
+    // Update syntax highlighter with the current document's language. A large file
+    // isn't sent to the server, which would read all of it
+    let server = ctx.config.lsp_servers.get(language.to_str());
+    let started = match document.is_large_file() {
+        true => Ok(None),
+        false => ctx.lsp_service.start_server(language, server).await,
     };
+    match started {
+        Ok(Some(client)) => client.did_open(document).await?,
+        Ok(None) => {}
+        Err(e) => system::ShowMessage(Message::error(format!("E: {e}"))).execute(ctx).await?,
+    }
+
+    // Highlight queries are compiled when a document first needs them
+    let warnings = syntax::take_query_warnings();
+    if let Some(warning) = warnings.first() {
+        let content = match warnings.len() - 1 {
+            0 => warning.clone(),
+            more => format!("{warning} (and {more} more)"),
+        };
+        system::ShowMessage(Message::info(content)).execute(ctx).await?;
+    }
 
     ctx.ui.compositor.mark_all_dirty();
     Ok(())
 }
 
+/// Switches to another buffer. One left with unsaved changes stays open, which is
+/// pointed out so the changes aren't forgotten
+async fn switch_buffer(
+    ctx: &mut ActionContext<'_>,
+    switch: impl FnOnce(&mut BufferManager),
+) -> ActionResult {
+    let buffers = &mut *ctx.editor.buffer_manager;
+    let left = buffers.current_index();
+    let unsaved = buffers.current().has_unsaved_changes().then(|| buffers.current().display_name());
+    switch(buffers);
+    after_buffer_change(ctx).await?;
+    match unsaved {
+        Some(name) if ctx.editor.buffer_manager.current_index() != left => {
+            let message = format!("No write since last change for {name}, which is still open");
+            system::ShowMessage(Message::info(message)).execute(ctx).await
+        }
+        _ => Ok(()),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NextBuffer;
 
 #[async_trait(?Send)]
 impl Executable for NextBuffer {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        ctx.editor.buffer_manager.next_buffer();
-        after_buffer_change(ctx).await
+        switch_buffer(ctx, BufferManager::next_buffer).await
     }
 }
 
@@ -41,8 +100,7 @@ pub struct PreviousBuffer;
 #[async_trait(?Send)]
 impl Executable for PreviousBuffer {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        ctx.editor.buffer_manager.previous_buffer();
-        after_buffer_change(ctx).await
+        switch_buffer(ctx, BufferManager::previous_buffer).await
     }
 }
 
@@ -52,6 +110,23 @@ impl_action!(
     ActionDefinition::PreviousBuffer
 );
 
+/// `Ctrl-^`, going back to the buffer that was current before
+#[derive(Debug, Clone)]
+pub struct SwitchToAlternate;
+
+#[async_trait(?Send)]
+impl Executable for SwitchToAlternate {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ctx.editor.buffer_manager.switch_to_alternate() {
+            let message = Message::error("E: No alternate file".to_string());
+            return system::ShowMessage(message).execute(ctx).await;
+        }
+        after_buffer_change(ctx).await
+    }
+}
+
+impl_action!(SwitchToAlternate, "Switch to alternate buffer", ActionDefinition::SwitchToAlternate);
+
 #[derive(Debug, Clone)]
 pub struct OpenBuffer {
     path: PathBuf,
@@ -66,8 +141,107 @@ impl OpenBuffer {
 #[async_trait(?Send)]
 impl Executable for OpenBuffer {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        ctx.editor.buffer_manager.open_file(&self.path);
-        after_buffer_change(ctx).await
+        let mut opened = false;
+        switch_buffer(ctx, |buffers| {
+            let count = buffers.documents().count();
+            buffers.open_file(&self.path, ctx.config.large_file_size.bytes());
+            opened = buffers.documents().count() > count;
+        })
+        .await?;
+        if opened {
+            offer_recovery(ctx).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Asks what to do with the swap file of the file just opened, when an editor that
+/// didn't exit cleanly left it newer than the file. Of several files opened at once,
+/// the first with one is asked about
+async fn offer_recovery(ctx: &mut ActionContext<'_>) -> ActionResult {
+    let Some(directory) = &ctx.config.swap_directory else {
+        return Ok(());
+    };
+    if ctx.message.recovery().is_some() {
+        return Ok(());
+    }
+    let buffers = &ctx.editor.buffer_manager;
+    let buffer = buffers.current_index();
+    let Some(swap) = newer_swap(directory, buffers.current(), buffer) else {
+        return Ok(());
+    };
+    ctx.message.ask_recovery(Recovery { swap, buffer });
+    show_recovery_prompt(ctx).await
+}
+
+async fn show_recovery_prompt(ctx: &mut ActionContext<'_>) -> ActionResult {
+    let Some(recovery) = ctx.message.recovery() else {
+        return Ok(());
+    };
+    let buffers = &ctx.editor.buffer_manager;
+    let name = buffers.documents().nth(recovery.buffer).map(|document| document.display_name());
+    let name = name.unwrap_or_default();
+    let prompt = format!("Swap file of {name} is newer than it: (r)ecover, (d)elete");
+    system::ShowMessage(Message::info(prompt)).execute(ctx).await
+}
+
+/// Keys of the swap file prompt. Recovering puts the text of the swap file in the
+/// buffer as a change, which `u` takes back and `:w` saves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryKey {
+    Recover,
+    Delete,
+    Other,
+}
+
+#[async_trait(?Send)]
+impl Executable for RecoveryKey {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if *self == RecoveryKey::Other {
+            return show_recovery_prompt(ctx).await;
+        }
+        let Some(Recovery { swap: path, buffer }) = ctx.message.take_recovery() else {
+            return Ok(());
+        };
+        if *self == RecoveryKey::Delete {
+            let message = match std::fs::remove_file(&path) {
+                Ok(()) => Message::info("Swap file deleted".to_string()),
+                Err(e) => Message::error(format!("E: {}: {e}", path.display())),
+            };
+            return system::ShowMessage(message).execute(ctx).await;
+        }
+        let swap = match Swap::read(&path) {
+            Ok(swap) => swap,
+            Err(e) => return system::ShowMessage(Message::error(format!("E: {e:#}"))).execute(ctx).await,
+        };
+        if buffer != ctx.editor.buffer_manager.current_index() {
+            ctx.editor.buffer_manager.switch_to(buffer)?;
+            after_buffer_change(ctx).await?;
+        }
+        // Only part of a large file is loaded, so there's no whole text to replace
+        if ctx.editor.buffer_manager.current().is_large_file() {
+            let message = "E: Cannot recover into a file opened in large-file mode";
+            return system::ShowMessage(Message::error(message.to_string())).execute(ctx).await;
+        }
+
+        let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+        let text = buffer.to_string();
+        buffer.delete_string(0, text.len());
+        buffer.insert_string(0, &swap.text);
+        let start = Point::default();
+        let edit = Edit::Group(vec![
+            Edit::delete(0, start, text, start, start),
+            Edit::insert(0, start, swap.text, start, swap.cursor),
+        ]);
+        after_edit(ctx, &edit).await?;
+        let document = ctx.editor.buffer_manager.current_mut();
+        document.history.push(edit);
+        // Removed like the swap files autosave writes, once the text is saved or dropped
+        document.adopt_swap(path);
+        ctx.editor.cursor.set_point(swap.cursor, &document.buffer);
+
+        let message = format!("Recovered {}, :w to keep it", document.display_name());
+        system::ShowMessage(Message::info(message)).execute(ctx).await
     }
 }
 
@@ -77,21 +251,113 @@ impl_action!(OpenBuffer, "Open buffer", self {
     }
 });
 
+/// `:e!`, reading the current file again and dropping the changes made to it
+#[derive(Debug, Clone)]
+pub struct ReloadBuffer;
+
+#[async_trait(?Send)]
+impl Executable for ReloadBuffer {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current_mut();
+        if let Err(e) = document.reload(ctx.config.large_file_size.bytes()) {
+            return system::ShowMessage(Message::error(format!("E: {e}"))).execute(ctx).await;
+        }
+        let point = ctx.editor.cursor.get_point();
+        let cursor = &mut *ctx.editor.cursor;
+        cursor.set_point(point, &document.buffer);
+        cursor.clamp_row(&document.buffer);
+        cursor.clamp_column(&document.buffer, ctx.editor.mode);
+        if let Some(client) = ctx.lsp_service.get_client_mut() {
+            client.did_change(document, None).await?;
+        }
+        ctx.input.search_buffer.reset();
+        ctx.ui.compositor.mark_all_dirty();
+
+        let message = format!(
+            "{:?} {}L, {}B reloaded",
+            document.display_name(),
+            document.buffer.line_count(),
+            document.buffer.len_bytes()
+        );
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+}
+
+impl_action!(ReloadBuffer, "Reload buffer", ActionDefinition::ReloadBuffer);
+
+/// `:w`, writing the buffer to its file or another one. An unnamed buffer takes the
+/// name of the file it's written to, other buffers write a copy
 #[derive(Debug, Clone)]
 pub struct WriteBuffer {
     path: Option<PathBuf>,
+    /// `:w!`, overwriting another file that exists
+    force: bool,
+    /// `++p`, creating the missing directories of the file
+    create_dirs: bool,
 }
 
 impl WriteBuffer {
     pub fn new(path: Option<PathBuf>) -> Self {
-        Self { path }
+        Self {
+            path,
+            force: false,
+            create_dirs: false,
+        }
+    }
+
+    pub fn forced(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    pub fn creating_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// Refuses to overwrite another file unless forced, and to write into a directory
+    /// that doesn't exist unless `++p` creates it
+    fn prepare_target(&self, path: &Path, elsewhere: bool) -> anyhow::Result<()> {
+        if elsewhere && !self.force && path.exists() {
+            return Err(anyhow!("{} exists (add ! to override)", path.display()));
+        }
+        let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) else {
+            return Ok(());
+        };
+        if !parent.exists() {
+            if !self.create_dirs {
+                let parent = parent.display();
+                return Err(anyhow!("Directory {parent} doesn't exist (add ++p to create it)"));
+            }
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(())
     }
 }
 
+/// Names the unnamed current buffer after the file it was written to, detecting its
+/// language from the name, and opens it with the language server
+async fn name_buffer(ctx: &mut ActionContext<'_>, path: &Path) -> ActionResult {
+    let buffers = &mut *ctx.editor.buffer_manager;
+    buffers.name_current(path);
+    let language = Language::from_path(path);
+    if language != Language::PlainText {
+        buffers.current_mut().set_language(language);
+    }
+    after_buffer_change(ctx).await
+}
+
 #[async_trait(?Send)]
 impl Executable for WriteBuffer {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         let document = ctx.editor.buffer_manager.current();
+        if document.is_read_only() {
+            return system::ShowMessage(Message::error(
+                "Cannot write a file opened read-only".to_string(),
+            ))
+            .execute(ctx)
+            .await;
+        }
         let path = self.path.clone().or(document.path.clone());
         let Some(path) = path else {
             return system::ShowMessage(Message::error(
@@ -101,29 +367,65 @@ impl Executable for WriteBuffer {
             .execute(ctx)
             .await;
         };
+        let adopt = document.path.is_none();
+        let elsewhere = self
+            .path
+            .as_ref()
+            .is_some_and(|path| Some(canonicalize_path(path)) != document.canonical_path);
+        if let Err(e) = self.prepare_target(&path, elsewhere) {
+            return system::ShowMessage(Message::error(format!("E: {e}"))).execute(ctx).await;
+        }
+        if !elsewhere && !self.force && document.changed_on_disk() {
+            let message = "E: File changed on disk; use :w! to overwrite or :e! to reload";
+            return system::ShowMessage(Message::error(message.to_string())).execute(ctx).await;
+        }
 
-        let content = document.buffer.to_string();
+        // Only the buffer's own file is formatted. A formatter that fails leaves the buffer
+        // as it was, to be written all the same
+        let format_error = match ctx.config.format_on_save && (adopt || !elsewhere) {
+            true => match format_buffer(ctx).await? {
+                Formatted::Failed(error) => Some(error),
+                _ => None,
+            },
+            false => None,
+        };
+        let document = ctx.editor.buffer_manager.current();
         let line_count = document.buffer.line_count();
+        let byte_count = document.buffer.len_bytes();
 
-        if let Some(client) = ctx.lsp_service.get_client_mut() {
-            client.did_save(document).await?;
-        }
-
-        match std::fs::write(&path, &content) {
-            Ok(_) => {
+        // The text is written from the chunks of the buffer. Text the encoding can't
+        // store fails before the file is touched
+        match document.encoding.write_file(&document.buffer, &path) {
+            Ok(()) => {
+                if let Some(client) = ctx.lsp_service.get_client_mut() {
+                    client.did_save(document).await?;
+                }
+                let full_path = std::env::current_dir().unwrap_or_default().join(&path);
+                ctx.symbol_index.refresh(&full_path, &document.buffer);
                 let message = format!(
                     "{:?} {}L, {}B written",
                     path.to_string_lossy().to_string(),
                     line_count,
-                    content.len()
+                    byte_count
                 );
-                ctx.editor.buffer_manager.current_mut().modified = false;
-                system::ShowMessage(Message::info(message))
-                    .execute(ctx)
-                    .await
+                if adopt {
+                    name_buffer(ctx, &path).await?;
+                }
+                // A copy written elsewhere leaves the buffer's own file as it was
+                if adopt || !elsewhere {
+                    let document = ctx.editor.buffer_manager.current_mut();
+                    document.mark_saved();
+                    document.record_disk_time();
+                    document.remove_swap();
+                }
+                system::ShowMessage(Message::info(message)).execute(ctx).await?;
+                match format_error {
+                    Some(error) => system::ShowMessage(Message::error(error)).execute(ctx).await,
+                    None => Ok(()),
+                }
             }
             Err(e) => {
-                system::ShowMessage(Message::error(format!("E: {e}")))
+                system::ShowMessage(Message::error(format!("E: {e:#}")))
                     .execute(ctx)
                     .await
             }
@@ -134,9 +436,66 @@ impl Executable for WriteBuffer {
 impl_action!(WriteBuffer, "Write buffer", self {
     ActionDefinition::WriteBuffer {
         path: self.path.as_ref().map(|p| p.to_string_lossy().to_string()),
+        force: self.force,
+        create_dirs: self.create_dirs,
     }
 });
 
+/// Writes every modified buffer that has a file, reporting how many were written.
+/// A failed write stops the others and is reported with its cause
+#[derive(Debug, Clone)]
+pub struct WriteAllBuffers;
+
+#[async_trait(?Send)]
+impl Executable for WriteAllBuffers {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let mut written = 0;
+        let mut failed = None;
+        let mut unnamed = 0;
+        for document in ctx.editor.buffer_manager.documents_mut() {
+            if !document.has_unsaved_changes() {
+                continue;
+            }
+            let Some(name) = document.file_name() else {
+                unnamed += 1;
+                continue;
+            };
+            // The first failure stops the writing, as the next ones likely fail alike
+            if let Err(e) = document.save() {
+                failed = Some(format!("{name}: {}", e.root_cause()));
+                break;
+            }
+            written += 1;
+            if let Some(path) = document.full_file_path() {
+                ctx.symbol_index.refresh(&path, &document.buffer);
+            }
+            // The file is written either way, so the other buffers still are
+            if let Some(client) = ctx.lsp_service.get_client_mut()
+                && let Err(e) = client.did_save(document).await
+            {
+                log::error!("Failed to tell the language server {name} was saved: {e}");
+            }
+        }
+        ctx.ui.compositor.mark_dirty(STATUS_LINE)?;
+
+        let mut summary = format!("{written} written");
+        if unnamed > 0 {
+            summary.push_str(&format!(", {unnamed} without a file name skipped"));
+        }
+        if let Some(failed) = &failed {
+            summary.push_str(&format!(", stopped at {failed}"));
+        }
+        let message = if failed.is_none() && unnamed == 0 {
+            Message::info(summary)
+        } else {
+            Message::error(format!("E: {summary}"))
+        };
+        system::ShowMessage(message).execute(ctx).await
+    }
+}
+
+impl_action!(WriteAllBuffers, "Write all buffers", ActionDefinition::WriteAllBuffers);
+
 #[derive(Debug, Clone)]
 pub struct CloseBuffer {
     force: bool,
@@ -148,22 +507,35 @@ impl CloseBuffer {
     }
 }
 
+/// Closes the current buffer, telling the language server and removing the file made
+/// for it, like the tutor's. Unsaved changes refuse it unless forced, returning false
+pub(super) async fn close_current(ctx: &mut ActionContext<'_>, force: bool) -> Result<bool, anyhow::Error> {
+    let document = ctx.editor.buffer_manager.current();
+    if !force && document.has_unsaved_changes() {
+        let message = format!(
+            "E: No write since last change for {} (add ! to override)",
+            document.display_name()
+        );
+        system::ShowMessage(Message::error(message)).execute(ctx).await?;
+        return Ok(false);
+    }
+
+    let document = ctx.editor.buffer_manager.close_current();
+    if let Some(client) = ctx.lsp_service.get_client_mut() {
+        client.did_close(&document).await?;
+    }
+    if let Some(path) = &document.temp_file {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(true)
+}
+
 #[async_trait(?Send)]
 impl Executable for CloseBuffer {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        if !self.force && ctx.editor.buffer_manager.current().modified {
-            return system::ShowMessage(Message::error(
-                "Buffer has unsaved changes. Use 'force' to close anyway.".to_string(),
-            ))
-            .execute(ctx)
-            .await;
-        }
-
-        let document = ctx.editor.buffer_manager.close_current();
-        if let Some(client) = ctx.lsp_service.get_client_mut() {
-            client.did_close(&document).await?;
+        if !close_current(ctx, self.force).await? {
+            return Ok(());
         }
-
         if ctx.editor.buffer_manager.is_empty() {
             *ctx.running = false;
         } else {
@@ -177,6 +549,122 @@ impl_action!(CloseBuffer, "Close the current buffer", self {
     ActionDefinition::CloseBuffer { force: self.force }
 });
 
+/// `:bd`, closing the current buffer like `:q` but leaving an empty one in place of the
+/// last instead of quitting
+#[derive(Debug, Clone)]
+pub struct DeleteBuffer {
+    force: bool,
+}
+
+impl DeleteBuffer {
+    pub fn force(force: bool) -> Self {
+        Self { force }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for DeleteBuffer {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !close_current(ctx, self.force).await? {
+            return Ok(());
+        }
+        if ctx.editor.buffer_manager.is_empty() {
+            ctx.editor.buffer_manager.new_buffer();
+        }
+        after_buffer_change(ctx).await
+    }
+}
+
+impl_action!(DeleteBuffer, "Delete the current buffer", self {
+    ActionDefinition::DeleteBuffer { force: self.force }
+});
+
+/// `:ls`, a line per buffer with the number `:b` takes, `%` on the current one, `#` on
+/// the alternate and `+` on those with unsaved changes
+#[derive(Debug, Clone)]
+pub struct ShowBuffers;
+
+#[async_trait(?Send)]
+impl Executable for ShowBuffers {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let lines = ctx
+            .editor
+            .buffer_manager
+            .list_buffers()
+            .into_iter()
+            .map(|info| {
+                let flag = match (info.is_current, info.is_alternate) {
+                    (true, _) => '%',
+                    (_, true) => '#',
+                    _ => ' ',
+                };
+                let modified = if info.is_modified { '+' } else { ' ' };
+                let name = match &info.path {
+                    Some(path) => path.display().to_string(),
+                    None => info.name,
+                };
+                format!("{:>3} {flag}{modified} \"{name}\"", info.index + 1)
+            })
+            .collect();
+        system::ShowOutput(lines).execute(ctx).await
+    }
+}
+
+impl_action!(ShowBuffers, "List buffers", ActionDefinition::ShowBuffers);
+
+/// `:b`, switching to the buffer with the number `:ls` shows, or to the one whose path
+/// contains the text. Several matches are listed instead
+#[derive(Debug, Clone)]
+pub struct SwitchToBuffer {
+    target: String,
+}
+
+impl SwitchToBuffer {
+    pub fn new(target: String) -> Self {
+        Self { target }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SwitchToBuffer {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffers = &ctx.editor.buffer_manager;
+        let target = &self.target;
+        let found = match target.parse::<usize>() {
+            Ok(number) => number
+                .checked_sub(1)
+                .filter(|&index| index < buffers.len())
+                .ok_or_else(|| format!("E: Buffer {number} does not exist")),
+            Err(_) => match buffers.find(target).as_slice() {
+                [index] => Ok(*index),
+                [] => Err(format!("E: No matching buffer for {target}")),
+                indices => {
+                    let documents = buffers.documents().collect::<Vec<_>>();
+                    let names = indices
+                        .iter()
+                        .filter_map(|&index| documents[index].path.as_ref())
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>();
+                    Err(format!("E: More than one match for {target}: {}", names.join(", ")))
+                }
+            },
+        };
+        match found {
+            Ok(index) => {
+                switch_buffer(ctx, |buffers| {
+                    let _ = buffers.switch_to(index);
+                })
+                .await
+            }
+            Err(message) => system::ShowMessage(Message::error(message)).execute(ctx).await,
+        }
+    }
+}
+
+impl_action!(SwitchToBuffer, "Switch to buffer", self {
+    ActionDefinition::SwitchToBuffer { target: self.target.clone() }
+});
+
 #[derive(Debug, Clone)]
 pub struct RefreshBuffer;
 
@@ -206,4 +694,594 @@ impl Executable for SetRegister {
         ctx.editor.register_system.set_current_target(self.name);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Characters of each register that `:registers` shows
+const REGISTER_PREVIEW: usize = 50;
+
+/// `:registers`, one line per register that isn't empty, with the kind and the start
+/// of the content on one line, newlines and other control characters shown as `^J`
+#[derive(Debug, Clone)]
+pub struct ShowRegisters;
+
+#[async_trait(?Send)]
+impl Executable for ShowRegisters {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let mut lines = vec!["Type Name Content".to_string()];
+        for (name, register) in ctx.editor.register_system.non_empty() {
+            let kind = match register.kind {
+                RegisterKind::Character => 'c',
+                RegisterKind::Line => 'l',
+            };
+            let content = register
+                .content
+                .chars()
+                .take(REGISTER_PREVIEW)
+                .flat_map(|c| match c {
+                    '\n' => ['^', 'J'].to_vec(),
+                    '\t' => ['^', 'I'].to_vec(),
+                    c => Glyph::new(c, ControlChars::Caret).cells().collect(),
+                })
+                .collect::<String>();
+            lines.push(format!("  {kind}  \"{}   {content}", name.to_char()));
+        }
+        system::ShowOutput(lines).execute(ctx).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SetLanguage {
+    language: Language,
+}
+
+impl SetLanguage {
+    pub fn new(language: Language) -> Self {
+        Self { language }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SetLanguage {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        if document.language == self.language {
+            return Ok(());
+        }
+
+        // The server only knows the document under its old language
+        if let Some(client) = ctx.lsp_service.get_client_mut() {
+            client.did_close(document).await?;
+        }
+        if let Some(uri) = document.get_uri() {
+            ctx.lsp_service.clear_diagnostics(&uri);
+            update_quickfix(ctx)?;
+        }
+
+        ctx.editor.buffer_manager.current_mut().set_language(self.language);
+        after_buffer_change(ctx).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowLanguage;
+
+#[async_trait(?Send)]
+impl Executable for ShowLanguage {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let language = ctx.editor.buffer_manager.current().language;
+        let available = Language::ALL
+            .iter()
+            .map(Language::to_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!("filetype={} (available: {available})", language.to_str());
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+}
+
+/// `:set fileencoding=latin1`, converting the file when it's next written
+#[derive(Debug, Clone)]
+pub struct SetEncoding {
+    encoding: Encoding,
+}
+
+impl SetEncoding {
+    pub fn new(encoding: Encoding) -> Self {
+        Self { encoding }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SetEncoding {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current_mut();
+        if document.encoding.encoding == self.encoding {
+            return Ok(());
+        }
+        // A large file is read-only, and only part of it is loaded to check
+        if document.is_large_file() {
+            return Err(anyhow!("Cannot convert a file opened in large-file mode"));
+        }
+        // Text the encoding can't store is refused now, rather than when writing
+        let encoding = FileEncoding::converted_to(self.encoding);
+        encoding.encode(&document.buffer.to_string())?;
+        document.encoding = encoding;
+        ctx.ui.compositor.mark_dirty(STATUS_LINE)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowEncoding;
+
+#[async_trait(?Send)]
+impl Executable for ShowEncoding {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let encoding = ctx.editor.buffer_manager.current().encoding;
+        let available = Encoding::ALL
+            .iter()
+            .map(|encoding| encoding.to_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!("fileencoding={encoding} (available: {available})");
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::config::editor::LargeFileSize;
+    use crate::core::language::Language;
+    use crate::core::swap::swap_path;
+    use crate::editor::testing::TestEditor;
+    use crate::input::default_keymap::default_keymap;
+    use tree_sitter::Point;
+
+    #[tokio::test]
+    async fn test_write_all_and_quit() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-write-all", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first, second) = (dir.join("first.txt"), dir.join("second.txt"));
+        std::fs::write(&first, "one\n").unwrap();
+        std::fs::write(&second, "two\n").unwrap();
+
+        // The unnamed buffer can't be written, so quitting is refused
+        let mut editor = TestEditor::new("");
+        editor.keys("iunnamed<Esc>").await.unwrap();
+        editor.keys(&format!(":e {}<Enter><Home>x", first.display())).await.unwrap();
+        editor.keys(&format!(":e {}<Enter><Home>x", second.display())).await.unwrap();
+        editor.keys(":xa<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "ne\n");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "wo\n");
+        assert!(editor.running);
+        let message = editor.message_manager.current_message().unwrap();
+        assert!(message.content.contains("[No Name]"), "{}", message.content);
+
+        editor.keys(":wa<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: 0 written, 1 without a file name skipped");
+
+        editor.keys(":xa!<Enter>").await.unwrap();
+        assert!(!editor.running);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_buffer_list() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-buffers", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (main, mod_rs, notes) = (dir.join("main.rs"), dir.join("mod.rs"), dir.join("notes.md"));
+        for file in [&main, &mod_rs, &notes] {
+            std::fs::write(file, "text\n").unwrap();
+        }
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+
+        let mut editor = TestEditor::new("");
+        for file in [&main, &mod_rs, &notes] {
+            editor.keys(&format!(":e {}<Enter>", file.display())).await.unwrap();
+        }
+        editor.keys("x:ls<Enter>").await.unwrap();
+        let lines = editor.message_manager.output().unwrap().lines.clone();
+        assert_eq!(lines[0], "  1    \"[No Name]\"");
+        assert_eq!(lines[2], format!("  3 #  \"{}\"", mod_rs.display()));
+        assert_eq!(lines[3], format!("  4 %+ \"{}\"", notes.display()));
+        editor.keys("q").await.unwrap();
+
+        // By number, by a unique part of the path, or by a file name that's also part
+        // of another path
+        editor.keys(":b 2<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path, Some(main.clone()));
+        editor.keys(":b 9<Enter>").await.unwrap();
+        assert_eq!(shown(&editor), "E: Buffer 9 does not exist");
+        editor.keys(":b notes<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path, Some(notes.clone()));
+        editor.keys(":b .rs<Enter>").await.unwrap();
+        let expected = format!("E: More than one match for .rs: {}, {}", main.display(), mod_rs.display());
+        assert_eq!(shown(&editor), expected);
+        editor.keys(":b main.rs<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path, Some(main.clone()));
+
+        // Deleting shows the alternate buffer, and refuses unsaved changes without !
+        editor.keys(":b notes<Enter>:bd<Enter>").await.unwrap();
+        assert!(shown(&editor).starts_with("E: No write since last change for notes.md"));
+        editor.keys(":bd!<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path, Some(main.clone()));
+        for _ in 0..3 {
+            editor.keys(":bd<Enter>").await.unwrap();
+        }
+        assert!(editor.running);
+        assert_eq!(editor.core.current_document().path, None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_all_stops_at_failure() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-write-fail", std::process::id()));
+        std::fs::create_dir_all(dir.join("gone")).unwrap();
+        let (gone, kept) = (dir.join("gone/first.txt"), dir.join("second.txt"));
+        std::fs::write(&gone, "one\n").unwrap();
+        std::fs::write(&kept, "two\n").unwrap();
+
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>x", gone.display())).await.unwrap();
+        editor.keys(&format!(":e {}<Enter>x", kept.display())).await.unwrap();
+        std::fs::remove_dir_all(dir.join("gone")).unwrap();
+        editor.keys(":wa<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert!(message.content.starts_with("E: 0 written, stopped at first.txt: "), "{}", message.content);
+        assert_eq!(std::fs::read_to_string(&kept).unwrap(), "two\n");
+        editor.keys(":wqa<Enter>").await.unwrap();
+        assert!(editor.running);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_names_buffer() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-save-as", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (taken, file) = (dir.join("taken.txt"), dir.join("new/main.rs"));
+        std::fs::write(&taken, "kept\n").unwrap();
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+
+        let mut editor = TestEditor::new("fn main() {}\n");
+        editor.keys(&format!(":w {}<Enter>", taken.display())).await.unwrap();
+        assert_eq!(shown(&editor), format!("E: {} exists (add ! to override)", taken.display()));
+        assert_eq!(std::fs::read_to_string(&taken).unwrap(), "kept\n");
+        editor.keys(&format!(":w {}<Enter>", file.display())).await.unwrap();
+        let missing = dir.join("new");
+        assert_eq!(
+            shown(&editor),
+            format!("E: Directory {} doesn't exist (add ++p to create it)", missing.display())
+        );
+
+        // The written file names the buffer, so a plain :w goes back to it
+        editor.keys(&format!(":w ++p {}<Enter>", file.display())).await.unwrap();
+        let document = editor.core.current_document();
+        assert_eq!(document.path, Some(file.clone()));
+        assert_eq!(document.language, Language::Rust);
+        assert!(!document.has_unsaved_changes());
+        editor.keys("x:w<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "n main() {}\n");
+
+        // Writing a named buffer elsewhere leaves it unsaved and named as it was
+        editor.keys(&format!("x:w! {}<Enter>", taken.display())).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&taken).unwrap(), " main() {}\n");
+        let document = editor.core.current_document();
+        assert_eq!(document.path, Some(file.clone()));
+        assert!(document.has_unsaved_changes());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_changed_on_disk() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-changed", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "one\ntwo\nthree\n").unwrap();
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+        // Another program writing the file, a second later so that the time differs
+        let change_on_disk = |text: &str| {
+            let time = std::fs::metadata(&file).unwrap().modified().unwrap();
+            std::fs::write(&file, text).unwrap();
+            let handle = std::fs::File::options().write(true).open(&file).unwrap();
+            handle.set_modified(time + std::time::Duration::from_secs(1)).unwrap();
+        };
+
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>jjx", file.display())).await.unwrap();
+        change_on_disk("changed\n");
+        editor.keys(":w<Enter>").await.unwrap();
+        assert_eq!(shown(&editor), "E: File changed on disk; use :w! to overwrite or :e! to reload");
+        editor.keys(":wa<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "changed\n");
+
+        // Reloading drops the changes and their history, keeping the cursor in the text
+        editor.keys(":e!<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "changed\n");
+        assert_eq!(editor.cursor(), (1, 0));
+        assert!(!editor.core.current_document().is_modified());
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "changed\n");
+
+        // Once reloaded or forced, writes go through again
+        editor.keys("ggx:w<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "hanged\n");
+        change_on_disk("again\n");
+        editor.keys("x:w!<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "anged\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recover_swap_file() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-recover", std::process::id()));
+        let swap_directory = dir.join("swap");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "one\n").unwrap();
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(10);
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(old).unwrap();
+        let open = async || {
+            let config = Config {
+                keymap: default_keymap().clone(),
+                swap_directory: Some(swap_directory.clone()),
+                ..Default::default()
+            };
+            let mut editor = TestEditor::with_config("", config);
+            editor.keys(&format!(":e {}<Enter>", file.display())).await.unwrap();
+            editor
+        };
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+
+        // Autosave writes the changed buffer in the background
+        let mut editor = open().await;
+        editor.keys("x").await.unwrap();
+        editor.core.write_swaps(&swap_directory);
+        let swap = swap_path(&swap_directory, editor.core.current_document(), 1);
+        for _ in 0..100 {
+            if swap.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        // Opened again as if the editor had crashed, the swap file is offered
+        let mut editor = open().await;
+        let prompt = "Swap file of file.txt is newer than it: (r)ecover, (d)elete";
+        assert_eq!(shown(&editor), prompt);
+        editor.keys("x").await.unwrap();
+        assert_eq!((editor.text().as_str(), shown(&editor).as_str()), ("one\n", prompt));
+        editor.keys("r").await.unwrap();
+        assert_eq!(editor.text(), "ne\n");
+        assert!(editor.core.current_document().is_modified());
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "one\n");
+
+        // Saving removes it, so the next open doesn't ask
+        editor.keys("<C-r>:w<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "ne\n");
+        assert!(!swap.exists());
+        let mut editor = open().await;
+        assert!(editor.message_manager.recovery().is_none());
+
+        editor.keys("x").await.unwrap();
+        editor.core.current_document().write_swap(&swap_directory, 1, Point::default()).unwrap();
+        let mut editor = open().await;
+        editor.keys("d").await.unwrap();
+        assert_eq!(shown(&editor), "Swap file deleted");
+        assert!(!swap.exists());
+        assert_eq!(editor.text(), "ne\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quit_keeps_changes() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-quit", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "one\n").unwrap();
+        let shown = |editor: &TestEditor| {
+            let message = editor.message_manager.current_message().unwrap();
+            message.content.clone()
+        };
+
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>x:q<Enter>", file.display())).await.unwrap();
+        assert!(editor.running);
+        assert_eq!(shown(&editor), "E: No write since last change for file.txt (add ! to override)");
+
+        // Switching away keeps the buffer open, and says so
+        editor.keys(":bn<Enter>").await.unwrap();
+        assert_eq!(shown(&editor), "No write since last change for file.txt, which is still open");
+        editor.keys(":bn<Enter>:x<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "ne\n");
+
+        editor.keys("x:q!<Enter>").await.unwrap();
+        assert!(!editor.running);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_piped_text() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-piped", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("out.txt");
+
+        // Text read from a pipe opens as a scratch buffer, which writing makes an ordinary one
+        let mut editor = TestEditor::new("piped\n");
+        editor.core.current_document_mut().scratch = true;
+        editor.keys(&format!(":w {}<Enter>x:q<Enter>", file.display())).await.unwrap();
+        assert!(editor.running);
+        editor.keys(":q!<Enter>").await.unwrap();
+        assert!(!editor.running);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "piped\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_registers() {
+        let mut editor = TestEditor::new("one\ntwo\nthree\n");
+        editor.keys("ddddxyl").await.unwrap();
+        assert_eq!(editor.text(), "hree\n");
+
+        // Each kept register can be pasted from by name
+        editor.keys("\"2P\"1p\"-p").await.unwrap();
+        assert_eq!(editor.text(), "one\nttwo\nhree\n");
+        editor.keys("\"0P").await.unwrap();
+        assert_eq!(editor.text(), "one\nthtwo\nhree\n");
+
+        editor.resize(30, 8);
+        editor.keys(":reg<Enter>").await.unwrap();
+        let output = editor.message_manager.output().unwrap();
+        assert_eq!(
+            output.lines,
+            [
+                "Type Name Content",
+                "  c  \"\"   h",
+                "  c  \"0   h",
+                "  l  \"1   two^J",
+                "  l  \"2   one^J",
+                "  c  \"-   t",
+            ]
+        );
+
+        // Uppercase names append, and only the start of a long register is listed
+        editor.keys("qgg\"ayyj\"Ayy\"ap").await.unwrap();
+        assert_eq!(editor.text(), "one\nthtwo\none\nthtwo\nhree\n");
+        let keys = format!("\"byy0i{}<Esc>\"Byy:reg<Enter>", "x".repeat(60));
+        editor.keys(&keys).await.unwrap();
+        let output = editor.message_manager.output().unwrap();
+        let long = format!("one^J{}", "x".repeat(46));
+        assert_eq!(output.lines[1], format!("  l  \"\"   {long}"));
+        assert_eq!(output.lines[5], "  l  \"a   one^Jthtwo^J");
+        assert_eq!(output.lines[6], format!("  l  \"b   {long}"));
+    }
+
+    #[tokio::test]
+    async fn test_alternate_file() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-alternate", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first, second) = (dir.join("first.txt"), dir.join("second.txt"));
+        std::fs::write(&first, "one\n").unwrap();
+        std::fs::write(&second, "two\n").unwrap();
+        let mut editor = TestEditor::new("");
+        let current = |editor: &TestEditor| editor.core.current_document().path.clone();
+
+        editor.keys(&format!(":e {}<Enter>", first.display())).await.unwrap();
+        editor.keys(&format!(":e {}<Enter>", second.display())).await.unwrap();
+        editor.keys("<C-6>").await.unwrap();
+        assert_eq!(current(&editor), Some(first.clone()));
+        editor.keys(":e #<Enter>").await.unwrap();
+        assert_eq!(current(&editor), Some(second.clone()));
+
+        // `%` is the current file, which gets written back
+        editor.keys("x:w %<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "wo\n");
+
+        // Closing a buffer goes back to the first file, whose alternate is now gone
+        editor.keys(":q<Enter><C-6>").await.unwrap();
+        assert_eq!(current(&editor), Some(first.clone()));
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: No alternate file");
+        editor.keys(":e #<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: No alternate file name to substitute for '#'");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_encoding() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-encoding", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("legacy.txt");
+        std::fs::write(&file, b"caf\xe9\n").unwrap();
+
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>A!<Esc>:w<Enter>", file.display())).await.unwrap();
+        assert_eq!(editor.text(), "café!\n");
+        assert_eq!(std::fs::read(&file).unwrap(), b"caf\xe9!\n");
+        assert!(!editor.core.current_document().is_modified());
+
+        // Converting marks the buffer modified until it's written
+        editor.keys(":set fenc=utf-8<Enter>").await.unwrap();
+        assert!(editor.core.current_document().is_modified());
+        editor.keys(":w<Enter>").await.unwrap();
+        assert_eq!(std::fs::read(&file).unwrap(), "café!\n".as_bytes());
+
+        editor.keys("A€<Esc>:set fenc=latin1<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: '€' can't be written in latin1");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_large_file_mode() {
+        let file = std::env::temp_dir().join(format!("viron-{}-large.rs", std::process::id()));
+        std::fs::write(&file, "fn main() {}\n".repeat(1000)).unwrap();
+
+        // Files over the size, none with 0, open windowed, without highlighting
+        let mut editor = TestEditor::new("");
+        editor.config.large_file_size = LargeFileSize(0);
+        editor.keys(&format!(":e {}<Enter>", file.display())).await.unwrap();
+        let document = editor.core.current_document();
+        assert!(document.is_large_file() && document.is_read_only());
+        assert!(document.syntax_engine.is_none());
+        editor.keys(":set ft=rust<Enter>G").await.unwrap();
+        assert!(editor.core.current_document().syntax_engine.is_none());
+        editor.keys(":set fenc=latin1<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: Cannot convert a file opened in large-file mode");
+        let frame = editor.render().unwrap();
+        // Blanks show as `·`
+        assert!(format!("{:?}", frame.buffer).contains("[RO]·[big·file]"));
+
+        editor.config.large_file_size = LargeFileSize::default();
+        editor.keys(":e!<Enter>").await.unwrap();
+        assert!(!editor.core.current_document().is_large_file());
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_open_dedupes_by_canonical_path() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-canonical", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let file = dir.join("src/main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(&file, dir.join("link.rs")).unwrap();
+
+        let mut editor = TestEditor::new("");
+        let typed = dir.join("src/../src/main.rs");
+        for path in [&typed, &file, &dir.join("link.rs")] {
+            editor.keys(&format!(":e {}<Enter>", path.display())).await.unwrap();
+        }
+        // The first typed path is kept for display, and the URI uses the real file
+        let documents = editor.core.buffer_manager.documents().count();
+        assert_eq!(documents, 2, "the unnamed buffer and the file");
+        let document = editor.core.current_document();
+        assert_eq!(document.path.as_ref(), Some(&typed));
+        let canonical = file.canonicalize().unwrap();
+        assert_eq!(document.get_uri(), Some(format!("file://{}", canonical.display())));
+
+        // A new file resolves its directory
+        editor.keys(&format!(":e {}/./new.rs<Enter>", dir.join("src").display())).await.unwrap();
+        editor.keys(&format!(":e {}<Enter>", dir.join("src/new.rs").display())).await.unwrap();
+        let document = editor.core.current_document();
+        assert_eq!(document.full_file_path(), Some(canonical.with_file_name("new.rs")));
+        assert_eq!(editor.core.buffer_manager.documents().count(), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
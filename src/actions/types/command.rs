@@ -1,4 +1,4 @@
-use crate::actions::command_parser::parse_command;
+use crate::actions::command_parser::{FileNames, parse_command};
 use crate::actions::core::{impl_action, ActionDefinition, Executable};
 use crate::actions::types::{mode, system};
 use crate::actions::ActionResult;
@@ -6,7 +6,7 @@ use crate::core::message::Message;
 use crate::core::mode::Mode;
 use async_trait::async_trait;
 use crate::actions::context::ActionContext;
-use crate::constants::components::COMMAND_LINE;
+use crate::constants::components::{BOTTOM_BAR, STATUS_LINE};
 
 #[derive(Debug, Clone)]
 pub struct CommandMoveLeft;
@@ -48,9 +48,13 @@ impl CommandInsertChar {
 #[async_trait(?Send)]
 impl Executable for CommandInsertChar {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        // Typing ends a completion, whose matches cover the status line
+        if ctx.input.command_buffer.completion().is_some() {
+            ctx.ui.compositor.mark_dirty(STATUS_LINE)?;
+        }
         ctx.input.command_buffer.insert_char(self.ch);
         ctx.ui.compositor
-            .mark_dirty(COMMAND_LINE)?;
+            .mark_dirty(BOTTOM_BAR)?;
         Ok(())
     }
 }
@@ -65,7 +69,7 @@ impl Executable for CommandDeleteChar {
             Executable::execute(&mode::EnterMode::new(Mode::Normal), ctx).await?;
         }
         ctx.ui.compositor
-            .mark_dirty(COMMAND_LINE)?;
+            .mark_dirty(BOTTOM_BAR)?;
         Ok(())
     }
 }
@@ -82,13 +86,82 @@ impl Executable for CommandBackspace {
             Executable::execute(&mode::EnterMode::new(Mode::Normal), ctx).await?;
         }
         ctx.ui.compositor
-            .mark_dirty(COMMAND_LINE)?;
+            .mark_dirty(BOTTOM_BAR)?;
         Ok(())
     }
 }
 
 impl_action!(CommandBackspace, "Command backspace", ActionDefinition::CommandBackspace);
 
+/// Up in command mode, recalling the previous command starting with what was typed
+#[derive(Debug, Clone)]
+pub struct CommandHistoryOlder;
+
+#[async_trait(?Send)]
+impl Executable for CommandHistoryOlder {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        ctx.input.command_buffer.recall_older();
+        ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+        Ok(())
+    }
+}
+
+impl_action!(
+    CommandHistoryOlder,
+    "Recall the previous command",
+    ActionDefinition::CommandHistoryOlder
+);
+
+/// Down in command mode, recalling the next command or what was typed
+#[derive(Debug, Clone)]
+pub struct CommandHistoryNewer;
+
+#[async_trait(?Send)]
+impl Executable for CommandHistoryNewer {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        ctx.input.command_buffer.recall_newer();
+        ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+        Ok(())
+    }
+}
+
+impl_action!(
+    CommandHistoryNewer,
+    "Recall the next command",
+    ActionDefinition::CommandHistoryNewer
+);
+
+/// Tab in command mode, completing the file name before the cursor. The matches are
+/// shown in place of the status line while Tab and Shift-Tab cycle through them
+#[derive(Debug, Clone)]
+pub struct CommandComplete {
+    backward: bool,
+}
+
+impl CommandComplete {
+    pub fn new(backward: bool) -> Self {
+        Self { backward }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for CommandComplete {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if ctx.input.command_buffer.complete(self.backward) {
+            ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+            ctx.ui.compositor.mark_dirty(STATUS_LINE)?;
+        }
+        Ok(())
+    }
+}
+
+impl_action!(CommandComplete, "Complete file name", self {
+    match self.backward {
+        true => ActionDefinition::CommandCompleteBack,
+        false => ActionDefinition::CommandComplete,
+    }
+});
+
 #[derive(Debug, Clone)]
 pub struct CommandExecute;
 
@@ -96,14 +169,15 @@ pub struct CommandExecute;
 impl Executable for CommandExecute {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         let input = ctx.input.command_buffer.content();
+        let limit = ctx.config.history.0;
+        ctx.input.command_buffer.push_history(input.clone(), limit);
         Executable::execute(&mode::EnterMode::new(Mode::Normal), ctx).await?;
 
-        match parse_command(&input) {
+        let files = FileNames::new(ctx.editor.buffer_manager);
+        match parse_command(&input, &files) {
             Ok(action) => match action.as_ref().execute(ctx).await {
                 Ok(_) => {
                     ctx.input.command_buffer.clear();
-                    ctx.ui.compositor
-                        .mark_visible(COMMAND_LINE, false)?;
                 }
                 Err(err) => {
                     system::ShowMessage(Message::error(format!("E: {err}")))
@@ -123,3 +197,61 @@ impl Executable for CommandExecute {
 }
 
 impl_action!(CommandExecute, "Execute command", ActionDefinition::CommandExecute);
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::testing::TestEditor;
+
+    #[tokio::test]
+    async fn test_command_history() {
+        let mut editor = TestEditor::new("one\ntwo\nthree\n");
+        editor.keys(":set ft=rust<Enter>:3<Enter>:set ft=c<Enter>").await.unwrap();
+
+        // Only commands starting with what was typed are recalled
+        editor.keys(":se<Up>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), "set ft=c");
+        editor.keys("<Up><Up>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), "set ft=rust");
+        assert_eq!(editor.command_buffer.cursor_position(), 11);
+
+        // Down past the newest gives back what was typed
+        editor.keys("<Down>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), "set ft=c");
+        editor.keys("<Down>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), "se");
+
+        editor.keys("<Esc>gg:<Up><Up><Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_complete_file_names() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-tab", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        for file in ["alpha.rs", "also.rs"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let root = dir.display();
+        let mut editor = TestEditor::new("");
+
+        // Tab cycles through the matches, Shift-Tab goes back
+        editor.keys(&format!(":e {root}/al<Tab>")).await.unwrap();
+        assert_eq!(editor.command_buffer.content(), format!("e {root}/alpha.rs"));
+        editor.keys("<Tab>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), format!("e {root}/also.rs"));
+        editor.keys("<S-Tab><S-Tab>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), format!("e {root}/also.rs"));
+        assert_eq!(editor.command_buffer.completion().unwrap().matches.len(), 2);
+
+        // Typing keeps the match, and a lone match is taken at once
+        editor.keys("<BS><BS>x").await.unwrap();
+        assert!(editor.command_buffer.completion().is_none());
+        editor.keys(&format!("<Esc>:w {root}/d<Tab>")).await.unwrap();
+        assert_eq!(editor.command_buffer.content(), format!("w {root}/docs/"));
+
+        // Commands without a file name leave Tab alone
+        editor.keys("<Esc>:set ft<Tab>").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), "set ft");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -3,12 +3,13 @@ use crate::actions::context::ActionContext;
 use crate::actions::core::definition::{MovementType, create_action_from_definition};
 use crate::actions::core::{ActionDefinition, Executable};
 use crate::actions::types::editing::after_edit;
-use crate::actions::types::{editing, mode};
+use crate::actions::types::{editing, indent, mode};
 use crate::core::history::edit::Edit;
 use crate::core::mode::Mode;
 use crate::core::operation::Operator;
 use crate::core::register::{Register, RegisterKind};
 use async_trait::async_trait;
+use tree_sitter::Point;
 
 #[derive(Debug, Clone)]
 pub struct RepeatingAction {
@@ -51,15 +52,9 @@ impl ComboAction {
 
     async fn perform_yank(&self, ctx: &mut ActionContext<'_>) -> ActionResult {
         let movement_type = self.motion.get_movement_type().unwrap();
-        let before = ctx.editor.cursor.get_point();
-        let action = create_action_from_definition(&self.motion);
-        for _ in 0..self.repeat {
-            action.execute(ctx).await?;
-        }
-        let after = ctx.editor.cursor.get_point();
-
-        let from = before.min(after);
-        let to = before.max(after);
+        let Some((from, to)) = self.operator_range(ctx).await? else {
+            return Ok(());
+        };
 
         let buffer = ctx.editor.buffer_manager.current_buffer();
         let content = match movement_type {
@@ -81,25 +76,99 @@ impl ComboAction {
             MovementType::Line => RegisterKind::Line,
             MovementType::Character => RegisterKind::Character,
         };
-        ctx.editor.cursor.set_point(before, buffer);
+        // Like vim, the cursor goes to the start of the yanked text, so ygg goes to the top
+        ctx.editor.cursor.set_point(from, buffer);
         ctx.editor
             .register_system
             .on_yank(Register::new(content, kind));
         Ok(())
     }
 
-    async fn perform_delete(&self, ctx: &mut ActionContext<'_>) -> anyhow::Result<bool> {
-        let movement_type = self.motion.get_movement_type().unwrap();
+    /// The range the operator acts on: the text object around the cursor, or `None`
+    /// when there's none so that `di(` outside parentheses does nothing, else the
+    /// range the motion covers
+    async fn operator_range(&self, ctx: &mut ActionContext<'_>) -> anyhow::Result<Option<(Point, Point)>> {
+        // Going to a mark that isn't set reports it, and the operator does nothing
+        if let ActionDefinition::GoToMark { name, .. } = self.motion
+            && ctx.editor.buffer_manager.current().marks.get(name).is_none()
+        {
+            create_action_from_definition(&self.motion).execute(ctx).await?;
+            return Ok(None);
+        }
+        let ActionDefinition::TextObject { object, inner } = self.motion else {
+            return self.motion_range(ctx).await.map(Some);
+        };
+        let document = ctx.editor.buffer_manager.current();
+        let keywords = ctx.config.keyword_chars(document.language);
+        let cursor = ctx.editor.cursor.get_point();
+        let range = document.buffer.text_object(&cursor, object, inner, &keywords);
+        if let Some((from, _)) = range {
+            // An empty object, as in `ci(` on `()`, still puts the cursor inside
+            ctx.editor.cursor.set_point(from, &document.buffer);
+        }
+        Ok(range)
+    }
+
+    /// Runs the motion and returns the range it covered
+    async fn motion_range(&self, ctx: &mut ActionContext<'_>) -> anyhow::Result<(Point, Point)> {
         let before = ctx.editor.cursor.get_point();
         let action = create_action_from_definition(&self.motion);
+
+        // Counted x and X run from normal mode, where the cursor can't move past
+        // the last character, so `5x` would stop one short of `5dl`
+        let mode = std::mem::replace(ctx.editor.mode, Mode::OperationPending(self.operator));
+        let mut result = Ok(());
         for _ in 0..self.repeat {
-            action.execute(ctx).await?;
+            result = action.execute(ctx).await;
+            if result.is_err() {
+                break;
+            }
         }
+        *ctx.editor.mode = mode;
+        result?;
+
         let after = ctx.editor.cursor.get_point();
+        let (from, mut to) = (before.min(after), before.max(after));
+        if self.motion.is_inclusive() && from != to {
+            let buffer = ctx.editor.buffer_manager.current_buffer();
+            let position = buffer.cursor_position(&to);
+            let width = buffer.get_char(position).map_or(0, char::len_utf8);
+            to = buffer.point_at_position(position + width);
+        }
+        Ok((from, to))
+    }
 
-        let from = before.min(after);
-        let to = before.max(after);
+    /// Like vim, `cw` on a non-blank stops at the end of the word instead of
+    /// also changing the whitespace after it
+    fn change_word_range(&self, ctx: &ActionContext<'_>) -> Option<(Point, Point)> {
+        if !matches!(self.motion, ActionDefinition::MoveToNextWord) {
+            return None;
+        }
+        let document = ctx.editor.buffer_manager.current();
+        let buffer = &document.buffer;
+        let from = ctx.editor.cursor.get_point();
+        let current = buffer.get_char(buffer.cursor_position(&from))?;
+        if current.is_whitespace() {
+            return None;
+        }
+        let keywords = ctx.config.keyword_chars(document.language);
+        Some((from, buffer.end_of_word(&from, self.repeat, &keywords)))
+    }
+
+    async fn perform_delete(&self, ctx: &mut ActionContext<'_>) -> anyhow::Result<bool> {
+        let Some((from, to)) = self.operator_range(ctx).await? else {
+            return Ok(false);
+        };
+        self.delete_range(ctx, from, to).await
+    }
 
+    async fn delete_range(
+        &self,
+        ctx: &mut ActionContext<'_>,
+        from: Point,
+        to: Point,
+    ) -> anyhow::Result<bool> {
+        let movement_type = self.motion.get_movement_type().unwrap();
         let buffer = ctx.editor.buffer_manager.current_buffer_mut();
         let result = match movement_type {
             MovementType::Line => {
@@ -126,6 +195,10 @@ impl ComboAction {
             to,
         );
         ctx.editor.cursor.set_point(from, buffer);
+        if matches!(movement_type, MovementType::Line) {
+            ctx.editor.cursor.clamp_row(buffer);
+            ctx.editor.cursor.move_to_first_non_blank(buffer);
+        }
         after_edit(ctx, &edit).await?;
 
         ctx.editor.buffer_manager.current_mut().history.push(edit);
@@ -141,16 +214,20 @@ impl ComboAction {
 
     async fn perform_change(&self, ctx: &mut ActionContext<'_>) -> ActionResult {
         let movement_type = self.motion.get_movement_type().unwrap();
-        let deleted = self.perform_delete(ctx).await?;
-        match movement_type {
-            MovementType::Line if deleted => {
-                editing::InsertNewLineAbove.execute(ctx).await?;
-            }
-            _ => {
-                mode::EnterMode::new(Mode::Insert).execute(ctx).await?;
-            }
+        let range = match self.change_word_range(ctx) {
+            Some(range) => Some(range),
+            None => self.operator_range(ctx).await?,
+        };
+        let Some((from, to)) = range else {
+            return Ok(());
+        };
+        // The deleted text and what's typed instead are undone together
+        ctx.editor.buffer_manager.current_mut().history.begin_group();
+        let deleted = self.delete_range(ctx, from, to).await?;
+        if matches!(movement_type, MovementType::Line) && deleted {
+            editing::InsertNewLineAbove.execute(ctx).await?;
         }
-        Ok(())
+        mode::EnterMode::new(Mode::Insert).execute(ctx).await
     }
 }
 
@@ -160,6 +237,9 @@ impl Executable for ComboAction {
         if !self.motion.is_movement_type() {
             return Ok(());
         };
+        if self.operator != Operator::Yank && !editing::ensure_writable(ctx).await? {
+            return Ok(());
+        }
 
         match self.operator {
             Operator::Yank => {
@@ -169,6 +249,13 @@ impl Executable for ComboAction {
                 self.perform_delete(ctx).await?;
             }
             Operator::Change => self.perform_change(ctx).await?,
+            Operator::Indent | Operator::Dedent => {
+                // Like vim, any motion shifts the whole lines it covers
+                if let Some((from, to)) = self.operator_range(ctx).await? {
+                    let dedent = self.operator == Operator::Dedent;
+                    indent::shift_lines(ctx, from.row, to.row, dedent).await?;
+                }
+            }
         };
         let buffer = ctx.editor.buffer_manager.current_buffer();
         ctx.editor.cursor.clamp_row(buffer);
@@ -176,3 +263,96 @@ impl Executable for ComboAction {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::mode::Mode;
+    use crate::editor::testing::TestEditor;
+
+    async fn run(content: &str, keys: &str) -> String {
+        let mut editor = TestEditor::new(content);
+        editor.keys(keys).await.unwrap();
+        editor.text()
+    }
+
+    #[tokio::test]
+    async fn test_change_word_keeps_whitespace() {
+        assert_eq!(run("foo bar baz\n", "cwX<Esc>").await, "X bar baz\n");
+        assert_eq!(run("foo bar baz\n", "llcwX<Esc>").await, "foX bar baz\n");
+        assert_eq!(run("foo.bar baz\n", "cwX<Esc>").await, "X.bar baz\n");
+        assert_eq!(run("foo bar baz\n", "dw").await, "bar baz\n");
+    }
+
+    #[tokio::test]
+    async fn test_change_word_with_count() {
+        assert_eq!(run("foo   bar baz\n", "2cwX<Esc>").await, "X baz\n");
+        assert_eq!(run("foo bar\nbaz qux\n", "w2cwX<Esc>").await, "foo X qux\n");
+        assert_eq!(run("foo   bar\n", "lllcwX<Esc>").await, "fooXbar\n");
+    }
+
+    #[tokio::test]
+    async fn test_change_word_from_its_last_character() {
+        assert_eq!(run("foo\nbar\n", "llcwX<Esc>").await, "foX\nbar\n");
+        assert_eq!(run("foo.bar\n", "llcwX<Esc>").await, "foX.bar\n");
+        assert_eq!(run("foo bar baz\n", "ll2cwX<Esc>").await, "foX baz\n");
+    }
+
+    #[tokio::test]
+    async fn test_text_objects() {
+        let content = "let s = f(\"a b\", [x]);\n";
+        assert_eq!(run(content, "wdiw").await, "let  = f(\"a b\", [x]);\n");
+        assert_eq!(run(content, "wciwvalue<Esc>").await, "let value = f(\"a b\", [x]);\n");
+        assert_eq!(run(content, "wdaw").await, "let = f(\"a b\", [x]);\n");
+        assert_eq!(run(content, "ci\"c<Esc>").await, "let s = f(\"c\", [x]);\n");
+        assert_eq!(run(content, "$hhhca(<Esc>").await, "let s = f;\n");
+        assert_eq!(run(content, "$hhhdi]").await, "let s = f(\"a b\", []);\n");
+        assert_eq!(run("if x {\n    y();\n}\n", "jdiB").await, "if x {\n}\n");
+        assert_eq!(run("f(x<y>)\n", "$hhda<lt>").await, "f(x)\n");
+
+        // An empty object still changes inside it, one without its closing does nothing
+        let mut editor = TestEditor::new("f()\n");
+        editor.keys("lci(x<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "f(x)\n");
+        let mut editor = TestEditor::new("f(x\n");
+        editor.keys("ldi(ci(").await.unwrap();
+        assert_eq!(editor.text(), "f(x\n");
+        assert_eq!(editor.core.mode, Mode::Normal);
+
+        // The whole object is undone at once and goes to the registers
+        let mut editor = TestEditor::new(content);
+        editor.keys("$hhhdi(u").await.unwrap();
+        assert_eq!(editor.text(), content);
+        editor.keys("$hhhyi[$p").await.unwrap();
+        assert_eq!(editor.text(), "let s = f(\"a b\", [x]);x\n");
+    }
+
+    #[tokio::test]
+    async fn test_operator_to_line() {
+        let content = "one\ntwo\n  three\nfour\nfive\n";
+        assert_eq!(run(content, "jjdG").await, "one\ntwo\n");
+        assert_eq!(run(content, "jjdgg").await, "four\nfive\n");
+        // The count of G is the target line, whether the line is above or below
+        assert_eq!(run(content, "jd4G").await, "one\nfive\n");
+        assert_eq!(run(content, "jjjjd2G").await, "one\n");
+        assert_eq!(run(content, "2dG").await, "  three\nfour\nfive\n");
+        assert_eq!(run(content, "jj3dgg").await, "one\ntwo\nfour\nfive\n");
+
+        let mut editor = TestEditor::new(content);
+        editor.keys("jjjy2G").await.unwrap();
+        assert_eq!(editor.cursor(), (1, 0));
+        editor.keys("ggP").await.unwrap();
+        assert_eq!(editor.text(), "two\n  three\nfour\none\ntwo\n  three\nfour\nfive\n");
+
+        // The cursor ends on the first non-blank of the first line removed
+        let mut editor = TestEditor::new("one\ntwo\nthree\nfour\n  five\n");
+        editor.keys("jjjlld2G").await.unwrap();
+        assert_eq!(editor.text(), "one\n  five\n");
+        assert_eq!(editor.cursor(), (1, 2));
+
+        let mut editor = TestEditor::new("  one\ntwo\nthree\n");
+        editor.keys("jjlygg").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 2));
+        editor.keys("Gp").await.unwrap();
+        assert_eq!(editor.text(), "  one\ntwo\nthree\n  one\ntwo\nthree\n");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::actions::types::buffer::after_buffer_change;
+use crate::actions::types::movement::GoToLine;
+use crate::actions::types::system;
+use crate::core::buffer::Buffer;
+use crate::core::buffer::backend::ViewBackend;
+use crate::core::diff::{DiffRowKind, DiffView};
+use crate::core::encoding::FileEncoding;
+use crate::core::message::Message;
+use async_trait::async_trait;
+use std::io::ErrorKind;
+
+/// Shows the changes made to the buffer since it was last written, in a read-only scratch buffer
+#[derive(Debug, Clone)]
+pub struct ShowDiff;
+
+#[async_trait(?Send)]
+impl Executable for ShowDiff {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let error = if document.diff.is_some() || document.scratch {
+            Some("E: Buffer is not a file".to_string())
+        } else if document.is_read_only() {
+            Some("E: Cannot diff a file opened read-only".to_string())
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return system::ShowMessage(Message::error(error)).execute(ctx).await;
+        }
+        let Some(path) = document.path.clone() else {
+            let message = "E: No file name to compare with".to_string();
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        };
+
+        // A file that was never written compares as empty
+        let saved = match std::fs::read(&path) {
+            Ok(bytes) => FileEncoding::decode(&bytes).0,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                let message = format!("E: {}: {e}", path.display());
+                return system::ShowMessage(Message::error(message)).execute(ctx).await;
+            }
+        };
+        let Some((text, view)) = DiffView::new(&path, &saved, &document.buffer.to_string()) else {
+            let message = "No changes since the last write".to_string();
+            return system::ShowMessage(Message::info(message)).execute(ctx).await;
+        };
+        let first_change = view
+            .rows
+            .iter()
+            .position(|row| row.kind != DiffRowKind::Header)
+            .unwrap_or(0);
+
+        ctx.editor.buffer_manager.new_buffer();
+        let diff = ctx.editor.buffer_manager.current_mut();
+        diff.buffer = Buffer::from_string(&text);
+        diff.backend = Box::new(ViewBackend);
+        diff.scratch = true;
+        diff.diff = Some(view);
+        after_buffer_change(ctx).await?;
+        GoToLine::new(first_change).execute(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::testing::TestEditor;
+
+    #[tokio::test]
+    async fn test_diff_against_saved_file() {
+        let path = std::env::temp_dir().join(format!("viron-{}-diff.txt", std::process::id()));
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>", path.display())).await.unwrap();
+        editor.keys(":diff<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "No changes since the last write");
+
+        editor.keys("jddofour<Esc>:diff<Enter>").await.unwrap();
+        let name = path.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            editor.text(),
+            format!("--- {name} (saved)\n+++ {name} (buffer)\n@@ -1,3 +1,3 @@\n one\n-two\n three\n+four\n")
+        );
+        assert_eq!(editor.cursor(), (3, 1));
+        assert!(editor.core.current_document().is_read_only());
+
+        // Enter on the added line goes back to it in the file
+        editor.keys("jjj<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path.as_ref(), Some(&path));
+        assert_eq!(editor.cursor(), (2, 0));
+        std::fs::remove_file(path).unwrap();
+    }
+}
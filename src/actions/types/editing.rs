@@ -1,33 +1,83 @@
 use crate::actions::ActionResult;
 use crate::actions::context::ActionContext;
 use crate::actions::core::{ActionDefinition, Executable, impl_action};
-use crate::actions::types::{movement, system};
+use crate::actions::types::{mode, movement, system};
 use crate::constants::components::{EDITOR_VIEW, STATUS_LINE};
+use crate::core::grapheme::{next_boundary, previous_boundary};
+use crate::core::keyword_chars::KeywordChars;
 use crate::core::history::edit::Edit;
 use crate::core::message::Message;
 use crate::core::mode::Mode;
 use crate::core::register::{Register, RegisterKind, RegisterName};
+use crate::ui::display::display_width;
 use async_trait::async_trait;
 use std::fmt::Debug;
+use tree_sitter::Point;
 
 pub(super) async fn after_edit(ctx: &mut ActionContext<'_>, edit: &Edit) -> ActionResult {
     let document = ctx.editor.buffer_manager.current_mut();
-    document.mark_modified();
 
     ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
     ctx.ui.compositor.mark_dirty(STATUS_LINE)?;
     ctx.input.search_buffer.reset();
 
+    document.revision += 1;
     if let Some(syntax_engine) = document.syntax_engine.as_mut() {
         syntax_engine.apply_edit(&edit)?;
     }
+    document.marks.apply_edit(edit);
 
     if let Some(client) = ctx.lsp_service.get_client_mut() {
-        client.did_change(document).await?;
+        client.did_change(document, Some(edit)).await?;
     }
     Ok(())
 }
 
+/// Text replacing the bytes from `start` to `end` of a buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct TextChange {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Makes the changes to the current document bottom-up, so that the bytes of the ones
+/// above stay where they were, as one change to undo
+pub(super) async fn apply_changes(ctx: &mut ActionContext<'_>, mut changes: Vec<TextChange>) -> ActionResult {
+    changes.sort_by_key(|change| std::cmp::Reverse(change.start));
+    let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+    let mut group = Vec::new();
+    for TextChange { start, end, text } in changes {
+        let point = buffer.point_at_position(start);
+        if end > start
+            && let Some((deleted, _)) = buffer.delete_string(start, end - start)
+        {
+            group.push(Edit::delete(start, point, deleted, point, point));
+        }
+        if !text.is_empty() {
+            buffer.insert_string(start, &text);
+            group.push(Edit::insert(start, point, text, point, point));
+        }
+    }
+    let edit = Edit::Group(group);
+    after_edit(ctx, &edit).await?;
+    ctx.editor.buffer_manager.current_mut().history.push(edit);
+    Ok(())
+}
+
+/// Shows an error and returns false when the current document can't be edited
+pub(super) async fn ensure_writable(ctx: &mut ActionContext<'_>) -> anyhow::Result<bool> {
+    if !ctx.editor.buffer_manager.current().is_read_only() {
+        return Ok(true);
+    }
+    system::ShowMessage(Message::error(
+        "Cannot modify a file opened read-only".to_string(),
+    ))
+    .execute(ctx)
+    .await?;
+    Ok(false)
+}
+
 #[derive(Debug, Clone)]
 pub struct InsertChar(char);
 
@@ -59,6 +109,16 @@ impl Executable for InsertChar {
         );
         after_edit(ctx, &edit).await?;
         ctx.editor.buffer_manager.current_mut().history.push(edit);
+
+        // A member or a path item is likely to be typed next
+        let document = ctx.editor.buffer_manager.current();
+        let line = document.buffer.get_line_as_string(new_point.row);
+        let before = line.get(..new_point.column).unwrap_or_default();
+        if (before.ends_with('.') || before.ends_with("::"))
+            && let Some(client) = ctx.lsp_service.get_client_mut()
+        {
+            client.completion(document, new_point.row, new_point.column).await?;
+        }
         Ok(())
     }
 }
@@ -66,6 +126,71 @@ impl_action!(InsertChar, "Insert char", self {
     ActionDefinition::InsertChar { ch: self.0 }
 });
 
+/// Text pasted in insert mode, inserted at the cursor as one edit, which is one step
+/// to undo and one change for the language server
+#[derive(Debug, Clone)]
+pub struct InsertText(String);
+
+impl InsertText {
+    pub fn new(text: String) -> Self {
+        Self(text)
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for InsertText {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if self.0.is_empty() || !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let current_point = ctx.editor.cursor.get_point();
+
+        let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+        let byte_start = buffer.cursor_position(&current_point);
+        let new_position = buffer.insert_string(byte_start, &self.0);
+        let new_point = buffer.point_at_position(new_position);
+        ctx.editor.cursor.set_point(new_point, buffer);
+
+        let edit = Edit::insert(byte_start, current_point, self.0.clone(), current_point, new_point);
+        after_edit(ctx, &edit).await?;
+        ctx.editor.buffer_manager.current_mut().history.push(edit);
+        Ok(())
+    }
+}
+
+/// A tab, or with `expand_tab` the spaces up to the next tab stop
+#[derive(Debug, Clone)]
+pub struct InsertTab;
+
+#[async_trait(?Send)]
+impl Executable for InsertTab {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let layout = ctx.config.layout();
+        let point = ctx.editor.cursor.get_point();
+        let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+        let text = match ctx.config.expand_tab {
+            true => {
+                let line = buffer.get_line_as_string(point.row);
+                let before = &line[..point.column.min(line.len())];
+                let column = display_width(before, layout);
+                " ".repeat(layout.glyph('\t', column).width())
+            }
+            false => "\t".to_string(),
+        };
+
+        let byte_start = buffer.cursor_position(&point);
+        buffer.insert_string(byte_start, &text);
+        let new_point = buffer.point_at_position(byte_start + text.len());
+        ctx.editor.cursor.set_point(new_point, buffer);
+        let edit = Edit::insert(byte_start, point, text, point, new_point);
+        after_edit(ctx, &edit).await?;
+        ctx.editor.buffer_manager.current_mut().history.push(edit);
+        Ok(())
+    }
+}
+
+impl_action!(InsertTab, "Insert tab", ActionDefinition::InsertTab);
+
 #[derive(Debug, Clone)]
 pub struct DeleteChar {
     inline: bool,
@@ -80,6 +205,9 @@ impl DeleteChar {
 #[async_trait(?Send)]
 impl Executable for DeleteChar {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
         let buffer = ctx.editor.buffer_manager.current_buffer_mut();
         let point = ctx.editor.cursor.get_point();
         let byte_start = buffer.cursor_position(&point);
@@ -92,19 +220,22 @@ impl Executable for DeleteChar {
             return Ok(());
         }
 
-        if let Some((c, _)) = buffer.delete_char(byte_start) {
+        // The whole grapheme goes, with the marks combined with the character
+        let line = buffer.get_line_as_string(point.row);
+        let length = next_boundary(&line, point.column) - point.column;
+        if let Some((text, _)) = buffer.delete_string(byte_start, length) {
             ctx.editor.cursor.clamp_column(buffer, ctx.editor.mode);
             let edit = Edit::delete(
                 byte_start,
                 point,
-                c.to_string(),
+                text.clone(),
                 point,
                 ctx.editor.cursor.get_point(),
             );
             after_edit(ctx, &edit).await?;
             ctx.editor
                 .register_system
-                .on_delete(Register::new(c.to_string(), RegisterKind::Character));
+                .on_delete(Register::new(text, RegisterKind::Character));
             ctx.editor.buffer_manager.current_mut().history.push(edit);
         }
         Ok(())
@@ -115,6 +246,66 @@ impl_action!(DeleteChar, "Delete character", self {
     ActionDefinition::DeleteChar { inline: self.inline }
 });
 
+/// Replaces the character under the cursor and those after it, doing nothing when the
+/// line is too short. A line break splits the line, replacing all of them with one
+#[derive(Debug, Clone)]
+pub struct ReplaceChar {
+    ch: char,
+    count: usize,
+}
+
+impl ReplaceChar {
+    pub fn new(ch: char, count: usize) -> Self {
+        Self { ch, count: count.max(1) }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ReplaceChar {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+        let point = ctx.editor.cursor.get_point();
+        let line = buffer.get_line_as_string(point.row);
+        let rest = line.trim_end_matches('\n').get(point.column..).unwrap_or_default();
+        // Bytes up to the end of the last character replaced
+        let mut ends = rest.char_indices().map(|(byte, c)| byte + c.len_utf8());
+        let Some(replaced) = ends.nth(self.count - 1) else {
+            return Ok(());
+        };
+
+        let replacement = match self.ch {
+            '\n' => "\n".to_string(),
+            ch => ch.to_string().repeat(self.count),
+        };
+        let start_byte = buffer.cursor_position(&point);
+        let Some((deleted, _)) = buffer.delete_string(start_byte, replaced) else {
+            return Ok(());
+        };
+        let end_byte = buffer.insert_string(start_byte, &replacement);
+        let last = match self.ch {
+            '\n' => end_byte,
+            ch => end_byte - ch.len_utf8(),
+        };
+        let cursor = buffer.point_at_position(last);
+        ctx.editor.cursor.set_point(cursor, buffer);
+
+        let edit = Edit::Group(vec![
+            Edit::delete(start_byte, point, deleted, point, point),
+            Edit::insert(start_byte, point, replacement, point, cursor),
+        ]);
+        after_edit(ctx, &edit).await?;
+        ctx.editor.buffer_manager.current_mut().history.push(edit);
+        Ok(())
+    }
+}
+
+impl_action!(ReplaceChar, "Replace character", self {
+    ActionDefinition::ReplaceChar { ch: self.ch, count: self.count }
+});
+
 #[derive(Debug, Clone)]
 pub struct Backspace {
     inline: bool,
@@ -129,6 +320,9 @@ impl Backspace {
 #[async_trait(?Send)]
 impl Executable for Backspace {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
         let document = ctx.editor.buffer_manager.current_mut();
         let point = ctx.editor.cursor.get_point();
 
@@ -141,9 +335,12 @@ impl Executable for Backspace {
             .cursor
             .move_left(&document.buffer, ctx.editor.mode, self.inline);
         if position > 0 {
-            if let Some((c, new_position)) = document.buffer.delete_char(position - 1) {
+            // The grapheme before the cursor, or the line break at the start of a line
+            let line = document.buffer.get_line_as_string(point.row);
+            let start = position - (point.column - previous_boundary(&line, point.column)).max(1);
+            if let Some((text, new_position)) = document.buffer.delete_string(start, position - start) {
                 let new_point = document.buffer.point_at_position(new_position);
-                let edit = Edit::delete(position - 1, point, c.to_string(), point, new_point);
+                let edit = Edit::delete(start, point, text, point, new_point);
                 after_edit(ctx, &edit).await?;
                 ctx.editor.buffer_manager.current_mut().history.push(edit);
             }
@@ -156,19 +353,143 @@ impl_action!(Backspace, "Backspace", self {
     ActionDefinition::Backspace { inline: self.inline }
 });
 
+/// Deletes the text between the column returned for the line before the cursor and
+/// the cursor as one edit. At the start of a line it joins the line with the one
+/// above instead, as vim does with `backspace=eol`.
+// TODO: stop at the start of the insert once a `backspace` option exists
+async fn delete_before_cursor(
+    ctx: &mut ActionContext<'_>,
+    start_column: impl FnOnce(&str) -> usize,
+) -> ActionResult {
+    if !ensure_writable(ctx).await? {
+        return Ok(());
+    }
+    let point = ctx.editor.cursor.get_point();
+    if point.column == 0 {
+        return Backspace::new(false).execute(ctx).await;
+    }
+
+    let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+    let line = buffer.get_line_as_string(point.row);
+    let before = line.get(..point.column).unwrap_or(&line);
+    let start = Point {
+        row: point.row,
+        column: start_column(before),
+    };
+    let start_byte = buffer.cursor_position(&start);
+    let Some((text, _)) = buffer.delete_string(start_byte, before.len() - start.column) else {
+        return Ok(());
+    };
+    ctx.editor.cursor.set_point(start, buffer);
+
+    let edit = Edit::delete(start_byte, start, text, point, start);
+    after_edit(ctx, &edit).await?;
+    ctx.editor.buffer_manager.current_mut().history.push(edit);
+    Ok(())
+}
+
+/// Column where the word before the end of the text starts, after skipping the
+/// blanks that end it. A word is a run of keyword characters or of other non-blank ones
+fn word_start(text: &str, keywords: &KeywordChars) -> usize {
+    let trimmed = text.trim_end();
+    let Some(last) = trimmed.chars().next_back() else {
+        return 0;
+    };
+    let keyword = keywords.is_keyword(last);
+    trimmed
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| !c.is_whitespace() && keywords.is_keyword(c) == keyword)
+        .last()
+        .map_or(trimmed.len(), |(index, _)| index)
+}
+
+/// Column the text before the cursor is deleted to: the end of the indent, or the
+/// start of the line when there is only the indent left
+fn line_start(text: &str) -> usize {
+    let indent = text.len() - text.trim_start().len();
+    if indent < text.len() { indent } else { 0 }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteWordBefore;
+
+#[async_trait(?Send)]
+impl Executable for DeleteWordBefore {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let language = ctx.editor.buffer_manager.current().language;
+        let keywords = ctx.config.keyword_chars(language);
+        delete_before_cursor(ctx, |text| word_start(text, &keywords)).await
+    }
+}
+
+impl_action!(
+    DeleteWordBefore,
+    "Delete the word before the cursor",
+    ActionDefinition::DeleteWordBefore
+);
+
+#[derive(Debug, Clone)]
+pub struct DeleteToLineStart;
+
+#[async_trait(?Send)]
+impl Executable for DeleteToLineStart {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        delete_before_cursor(ctx, line_start).await
+    }
+}
+
+impl_action!(
+    DeleteToLineStart,
+    "Delete to the start of the line",
+    ActionDefinition::DeleteToLineStart
+);
+
+/// The line break for Enter at the column, and where the cursor goes in it. The new
+/// line keeps the indent, with a level more after an opening bracket, and a closing
+/// bracket right after the cursor moves to a line of its own below
+fn indented_line_break(line: &str, column: usize, shift_width: usize) -> (String, usize) {
+    let before = line.get(..column).unwrap_or(line);
+    let indent = before
+        .chars()
+        .take_while(|&c| c == ' ' || c == '\t')
+        .collect::<String>();
+    let close = match before.chars().last() {
+        Some('{') => '}',
+        Some('(') => ')',
+        Some('[') => ']',
+        _ => return (format!("\n{indent}"), 1 + indent.len()),
+    };
+    let inner = format!("{indent}{}", " ".repeat(shift_width));
+    let cursor_offset = 1 + inner.len();
+    match line[before.len()..].starts_with(close) {
+        true => (format!("\n{inner}\n{indent}"), cursor_offset),
+        false => (format!("\n{inner}"), cursor_offset),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InsertNewLine;
 
 #[async_trait(?Send)]
 impl Executable for InsertNewLine {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let shift_width = ctx.config.shift_width.0;
+        let auto_indent = ctx.config.auto_indent.0;
         let buffer = ctx.editor.buffer_manager.current_buffer_mut();
         let point = ctx.editor.cursor.get_point();
         let byte_start = buffer.cursor_position(&point);
-        let new_position = buffer.insert_char(byte_start, '\n');
-        let new_point = buffer.point_at_position(new_position);
+        let (text, cursor_offset) = match auto_indent {
+            true => {
+                let line = buffer.get_line_as_string(point.row);
+                indented_line_break(&line, point.column, shift_width)
+            }
+            false => ("\n".to_string(), 1),
+        };
+        buffer.insert_string(byte_start, &text);
+        let new_point = buffer.point_at_position(byte_start + cursor_offset);
         ctx.editor.cursor.set_point(new_point, &buffer);
-        let edit = Edit::insert(byte_start, point, "\n".to_string(), point, new_point);
+        let edit = Edit::insert(byte_start, point, text, point, new_point);
         after_edit(ctx, &edit).await?;
         ctx.editor.buffer_manager.current_mut().history.push(edit);
         Ok(())
@@ -187,6 +508,9 @@ pub struct InsertNewLineBelow;
 #[async_trait(?Send)]
 impl Executable for InsertNewLineBelow {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
         let buffer = ctx.editor.buffer_manager.current_buffer_mut();
         let point = ctx.editor.cursor.get_point();
 
@@ -231,6 +555,9 @@ pub struct InsertNewLineAbove;
 #[async_trait(?Send)]
 impl Executable for InsertNewLineAbove {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
         let buffer = ctx.editor.buffer_manager.current_buffer_mut();
         let point = ctx.editor.cursor.get_point();
 
@@ -276,6 +603,9 @@ pub struct DeleteCurrentLine;
 #[async_trait(?Send)]
 impl Executable for DeleteCurrentLine {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
         let buffer = ctx.editor.buffer_manager.current_buffer_mut();
         let start_point = ctx.editor.cursor.get_point();
         let (deleted, start_byte) = buffer.delete_line(start_point.row).unwrap();
@@ -307,9 +637,13 @@ pub struct ChangeCurrentLine;
 #[async_trait(?Send)]
 impl Executable for ChangeCurrentLine {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        ctx.editor.buffer_manager.current_mut().history.begin_group();
         DeleteCurrentLine.execute(ctx).await?;
         InsertNewLineAbove.execute(ctx).await?;
-        Ok(())
+        mode::EnterMode::new(Mode::Insert).execute(ctx).await
     }
 }
 
@@ -407,20 +741,32 @@ impl Executable for Redo {
 
 impl_action!(Redo, "Redo", ActionDefinition::Redo);
 
+/// Pastes the register, which the four paste actions share
 #[derive(Debug, Clone)]
 pub struct Paste {
     after_cursor: bool,
+    count: usize,
+    /// Leaves the cursor just after the pasted text rather than on its last character,
+    /// or on the line below the pasted lines
+    move_past: bool,
 }
 
 impl Paste {
-    pub fn new(after_cursor: bool) -> Self {
-        Self { after_cursor }
+    pub fn new(after_cursor: bool, count: usize, move_past: bool) -> Self {
+        Self {
+            after_cursor,
+            count,
+            move_past,
+        }
     }
 }
 
 #[async_trait(?Send)]
 impl Executable for Paste {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
         let Some(register) = ctx.editor.register_system.on_paste() else {
             return Ok(());
         };
@@ -438,7 +784,7 @@ impl Executable for Paste {
         log::info!("Before cursor: {:?}", cursor);
         match (self.after_cursor, &register.kind) {
             (true, RegisterKind::Character) => {
-                cursor.move_right(buffer, &Mode::Insert, false);
+                cursor.move_right(buffer, &Mode::Insert, true);
             }
             (false, RegisterKind::Line) => {
                 cursor.move_to_line_start();
@@ -450,34 +796,36 @@ impl Executable for Paste {
             _ => {}
         }
 
-        // Insert pasted text
+        // Insert pasted text, where line registers always end with a newline so the
+        // copies stay whole lines
         log::info!("After cursor: {:?}", cursor);
+        let content = register.content.repeat(self.count.max(1));
         let point = cursor.get_point();
         let byte_start = buffer.cursor_position(&point);
-        let new_position = buffer.insert_string(byte_start, &register.content);
+        let new_position = buffer.insert_string(byte_start, &content);
 
         let old_point = ctx.editor.cursor.get_point();
 
-        match register.kind {
-            RegisterKind::Character => {
+        match (&register.kind, self.move_past) {
+            (RegisterKind::Character, false) => {
                 ctx.editor
                     .cursor
                     .set_point(buffer.point_at_position(new_position - 1), buffer);
             }
-            RegisterKind::Line => {
+            (RegisterKind::Line, false) => {
                 ctx.editor.cursor.set_point(cursor.get_point(), buffer);
             }
+            (_, true) => {
+                let cursor = &mut ctx.editor.cursor;
+                cursor.set_point(buffer.point_at_position(new_position), buffer);
+                cursor.clamp_row(buffer);
+                cursor.clamp_column(buffer, &Mode::Normal);
+            }
         }
 
         let new_point = ctx.editor.cursor.get_point();
 
-        let edit = Edit::insert(
-            byte_start,
-            point,
-            register.content.to_string(),
-            old_point,
-            new_point,
-        );
+        let edit = Edit::insert(byte_start, point, content, old_point, new_point);
         after_edit(ctx, &edit).await?;
         ctx.editor.buffer_manager.current_mut().history.push(edit);
         Ok(())
@@ -485,33 +833,397 @@ impl Executable for Paste {
 }
 
 #[derive(Debug, Clone)]
-pub struct PasteBeforeCursor;
+pub struct PasteBeforeCursor {
+    count: usize,
+}
+
+impl PasteBeforeCursor {
+    pub fn new(count: usize) -> Self {
+        Self { count }
+    }
+}
 
 #[async_trait(?Send)]
 impl Executable for PasteBeforeCursor {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        Paste::new(false).execute(ctx).await
+        Paste::new(false, self.count, false).execute(ctx).await
     }
 }
 
-impl_action!(
-    PasteBeforeCursor,
-    "Paste before cursor",
-    ActionDefinition::PasteBeforeCursor
-);
+impl_action!(PasteBeforeCursor, "Paste before cursor", self {
+    ActionDefinition::PasteBeforeCursor { count: self.count }
+});
 
 #[derive(Debug, Clone)]
-pub struct PasteAfterCursor;
+pub struct PasteAfterCursor {
+    count: usize,
+}
+
+impl PasteAfterCursor {
+    pub fn new(count: usize) -> Self {
+        Self { count }
+    }
+}
 
 #[async_trait(?Send)]
 impl Executable for PasteAfterCursor {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        Paste::new(true).execute(ctx).await
+        Paste::new(true, self.count, false).execute(ctx).await
     }
 }
 
-impl_action!(
-    PasteAfterCursor,
-    "Paste after cursor",
-    ActionDefinition::PasteAfterCursor
-);
+impl_action!(PasteAfterCursor, "Paste after cursor", self {
+    ActionDefinition::PasteAfterCursor { count: self.count }
+});
+
+#[derive(Debug, Clone)]
+pub struct PasteBeforeCursorAndMove {
+    count: usize,
+}
+
+impl PasteBeforeCursorAndMove {
+    pub fn new(count: usize) -> Self {
+        Self { count }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for PasteBeforeCursorAndMove {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        Paste::new(false, self.count, true).execute(ctx).await
+    }
+}
+
+impl_action!(PasteBeforeCursorAndMove, "Paste before cursor, moving past the text", self {
+    ActionDefinition::PasteBeforeCursorAndMove { count: self.count }
+});
+
+#[derive(Debug, Clone)]
+pub struct PasteAfterCursorAndMove {
+    count: usize,
+}
+
+impl PasteAfterCursorAndMove {
+    pub fn new(count: usize) -> Self {
+        Self { count }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for PasteAfterCursorAndMove {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        Paste::new(true, self.count, true).execute(ctx).await
+    }
+}
+
+impl_action!(PasteAfterCursorAndMove, "Paste after cursor, moving past the text", self {
+    ActionDefinition::PasteAfterCursorAndMove { count: self.count }
+});
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::config::editor::AutoIndent;
+    use crate::editor::testing::TestEditor;
+
+    const TEXT: &str = "abc\n\nxyz\n";
+
+    /// (keys, expected text, expected cursor), starting at the first line of `TEXT`.
+    /// `l` moves to the middle, `$` to the end and `j` to the empty line.
+    const CASES: &[(&str, &str, (usize, usize))] = &[
+        // x deletes under the cursor and never removes the newline
+        ("x", "bc\n\nxyz\n", (0, 0)),
+        ("lx", "ac\n\nxyz\n", (0, 1)),
+        ("$x", "ab\n\nxyz\n", (0, 1)),
+        ("jx", TEXT, (1, 0)),
+        ("l5x", "a\n\nxyz\n", (0, 0)),
+        // dl is the same as x
+        ("dl", "bc\n\nxyz\n", (0, 0)),
+        ("ldl", "ac\n\nxyz\n", (0, 1)),
+        ("$dl", "ab\n\nxyz\n", (0, 1)),
+        ("jdl", TEXT, (1, 0)),
+        ("l5dl", "a\n\nxyz\n", (0, 0)),
+        // X deletes before the cursor and never crosses the line start
+        ("X", TEXT, (0, 0)),
+        ("lX", "bc\n\nxyz\n", (0, 0)),
+        ("$X", "ac\n\nxyz\n", (0, 1)),
+        ("jX", TEXT, (1, 0)),
+        ("$5X", "c\n\nxyz\n", (0, 0)),
+        // dh is the same as X
+        ("dh", TEXT, (0, 0)),
+        ("ldh", "bc\n\nxyz\n", (0, 0)),
+        ("$dh", "ac\n\nxyz\n", (0, 1)),
+        ("jdh", TEXT, (1, 0)),
+        ("$5dh", "c\n\nxyz\n", (0, 0)),
+        // Delete in insert mode joins lines at the end of a line
+        ("i<Delete>", "bc\n\nxyz\n", (0, 0)),
+        ("li<Delete>", "ac\n\nxyz\n", (0, 1)),
+        ("A<Delete>", "abc\nxyz\n", (0, 3)),
+        ("ji<Delete>", "abc\nxyz\n", (1, 0)),
+    ];
+
+    /// (text, keys, expected text, expected cursor) for Ctrl-w and Ctrl-u in insert mode
+    const INSERT_DELETE_CASES: &[(&str, &str, &str, (usize, usize))] = &[
+        ("foo bar baz\n", "A<C-w>", "foo bar \n", (0, 8)),
+        ("foo bar  \n", "A<C-w>", "foo \n", (0, 4)),
+        ("foo.bar\n", "A<C-w>", "foo.\n", (0, 4)),
+        ("foo.bar\n", "A<C-w><C-w>", "foo\n", (0, 3)),
+        // Mid-line, before the last word
+        ("foo bar baz\n", "<Home>wwi<C-w>", "foo baz\n", (0, 4)),
+        // At the start of a line, joins it with the one above
+        ("abc\ndef\n", "ji<C-w>", "abcdef\n", (0, 3)),
+        ("abc\ndef\n", "ji<C-u>", "abcdef\n", (0, 3)),
+        // Multibyte words are deleted whole
+        ("héllo wörld\n", "A<C-w>", "héllo \n", (0, 6)),
+        ("日本 語\n", "A<C-w><C-w>", "\n", (0, 0)),
+        // Ctrl-u keeps the indent, then deletes it
+        ("    foo bar\n", "A<C-u>", "    \n", (0, 4)),
+        ("    foo bar\n", "A<C-u><C-u>", "\n", (0, 0)),
+        ("    foo bar\n", "<Home>wwi<C-u>", "    bar\n", (0, 4)),
+    ];
+
+    #[tokio::test]
+    async fn test_insert_mode_deletes() {
+        for (text, keys, expected, cursor) in INSERT_DELETE_CASES {
+            let mut editor = TestEditor::new(text);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), *expected, "text after {keys}");
+            assert_eq!(editor.cursor(), *cursor, "cursor after {keys}");
+        }
+
+        // The whole insert session is undone at once
+        let mut editor = TestEditor::new("one\n");
+        editor.keys("A two three<C-w>four<Left><Backspace><Esc>").await.unwrap();
+        assert_eq!(editor.text(), "one two for\n");
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "one\n");
+    }
+
+    #[tokio::test]
+    async fn test_changes_undo_as_one_step() {
+        // Keys, then the text after them and after undoing
+        const CASES: [(&str, &str, &str); 6] = [
+            ("ccnew<Esc>", "new\nthree\n", "one two\nthree\n"),
+            ("cjnew<Esc>", "new\n", "one two\nthree\n"),
+            ("wciwX<Esc>", "one X\nthree\n", "one two\nthree\n"),
+            ("Vcnew<Esc>", "new\nthree\n", "one two\nthree\n"),
+            ("ofoo<Esc>", "one two\nfoo\nthree\n", "one two\nthree\n"),
+            // An insert right after a quick edit is a step of its own
+            ("xifoo<Esc>", "foone two\nthree\n", "ne two\nthree\n"),
+        ];
+        for (keys, changed, undone) in CASES {
+            let mut editor = TestEditor::new("one two\nthree\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), changed, "{keys}");
+            editor.keys("u").await.unwrap();
+            assert_eq!(editor.text(), undone, "{keys}u");
+        }
+    }
+
+    /// (text, keys, expected text, expected cursor) for Enter in insert mode
+    #[tokio::test]
+    async fn test_insert_tab() {
+        let mut editor = TestEditor::new("ab\n");
+        editor.keys("a<Tab>x<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "a\txb\n");
+
+        let config = Config {
+            expand_tab: true,
+            ..TestEditor::new("").config
+        };
+        let mut editor = TestEditor::with_config("ab\n", config);
+        editor.keys("a<Tab>x<Tab><Esc>").await.unwrap();
+        assert_eq!(editor.text(), "a   x   b\n");
+        assert_eq!(editor.cursor(), (0, 7));
+    }
+
+    const NEW_LINE_CASES: &[(&str, &str, &str, (usize, usize))] = &[
+        ("    foo\n", "A<Enter>bar<Esc>", "    foo\n    bar\n", (1, 6)),
+        ("\tfoo\n", "A<Enter>bar<Esc>", "\tfoo\n\tbar\n", (1, 3)),
+        ("fn f() {\n", "A<Enter>x<Esc>", "fn f() {\n    x\n", (1, 4)),
+        // Between brackets, the closing one goes to a line of its own
+        ("  f()\n", "$i<Enter>x<Esc>", "  f(\n      x\n  )\n", (1, 6)),
+        ("[]\n", "$i<Enter><Esc>", "[\n    \n]\n", (1, 3)),
+        // Only the indent before the cursor is kept
+        ("  foo\n", "i<Enter><Esc>", "\n  foo\n", (1, 0)),
+    ];
+
+    #[tokio::test]
+    async fn test_new_line_indent() {
+        for (text, keys, expected, cursor) in NEW_LINE_CASES {
+            let mut editor = TestEditor::new(text);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), *expected, "text after {keys}");
+            assert_eq!(editor.cursor(), *cursor, "cursor after {keys}");
+        }
+
+        let mut editor = TestEditor::new("  f()\n");
+        editor.keys("$i<Enter>x<Esc>u").await.unwrap();
+        assert_eq!(editor.text(), "  f()\n");
+
+        let config = Config {
+            auto_indent: AutoIndent(false),
+            ..TestEditor::new("").config
+        };
+        let mut editor = TestEditor::with_config("  f(\n", config);
+        editor.keys("A<Enter>x<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "  f(\nx\n");
+    }
+
+    /// (keys, expected text, expected cursor) for pasting "ab\ncd\n" with a count
+    const PASTE_CASES: &[(&str, &str, (usize, usize))] = &[
+        ("ylp", "aab\ncd\n", (0, 1)),
+        ("yl2p", "aaab\ncd\n", (0, 2)),
+        ("yl5p", "aaaaaab\ncd\n", (0, 5)),
+        ("yl2P", "aaab\ncd\n", (0, 1)),
+        // gp and gP leave the cursor after the text
+        ("ylgp", "aab\ncd\n", (0, 2)),
+        ("yl2gp", "aaab\ncd\n", (0, 3)),
+        ("yl5gP", "aaaaaab\ncd\n", (0, 5)),
+        // Lines are pasted as whole lines, below or above the cursor
+        ("yyp", "ab\nab\ncd\n", (1, 0)),
+        ("yy2p", "ab\nab\nab\ncd\n", (1, 0)),
+        ("yy5p", "ab\nab\nab\nab\nab\nab\ncd\n", (1, 0)),
+        ("jyy2P", "ab\ncd\ncd\ncd\n", (1, 0)),
+        // or on the line after them
+        ("yygp", "ab\nab\ncd\n", (2, 0)),
+        ("yy2gP", "ab\nab\nab\ncd\n", (2, 0)),
+        ("yy5gp", "ab\nab\nab\nab\nab\nab\ncd\n", (6, 0)),
+    ];
+
+    #[tokio::test]
+    async fn test_paste_count() {
+        for (keys, text, cursor) in PASTE_CASES {
+            let mut editor = TestEditor::new("ab\ncd\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), *text, "text after {keys}");
+            assert_eq!(editor.cursor(), *cursor, "cursor after {keys}");
+        }
+
+        // All the copies are undone at once
+        let mut editor = TestEditor::new("ab\ncd\n");
+        editor.keys("yy5pu").await.unwrap();
+        assert_eq!(editor.text(), "ab\ncd\n");
+        editor.keys("yl3gpu").await.unwrap();
+        assert_eq!(editor.text(), "ab\ncd\n");
+    }
+
+    #[tokio::test]
+    async fn test_bracketed_paste() {
+        let mut editor = TestEditor::new("start\n");
+        editor.keys("A ").await.unwrap();
+        let revision = editor.core.current_document().revision;
+        let pasted = (1..=500).map(|n| format!("line {n}\r")).collect::<String>();
+        editor.paste(&pasted).await.unwrap();
+
+        // One edit for the whole paste, with the line breaks the terminal sent as `\r`
+        let lines = (1..=500).map(|n| format!("line {n}\n")).collect::<String>();
+        assert_eq!(editor.text(), format!("start {lines}\n"));
+        assert_eq!(editor.core.current_document().revision, revision + 1);
+        assert_eq!(editor.cursor(), (500, 0));
+        editor.keys("<Esc>u").await.unwrap();
+        assert_eq!(editor.text(), "start\n");
+
+        // The command line takes the first line only, normal mode nothing
+        editor.paste("x").await.unwrap();
+        assert_eq!(editor.text(), "start\n");
+        editor.keys(":").await.unwrap();
+        editor.paste("s/t/T/g\r\nmore").await.unwrap();
+        assert_eq!(editor.command_buffer.content(), "s/t/T/g");
+        editor.keys("<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "sTarT\n");
+    }
+
+    #[tokio::test]
+    async fn test_delete_char_semantics() {
+        for (keys, text, cursor) in CASES {
+            let mut editor = TestEditor::new(TEXT);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), *text, "text after {keys}");
+            assert_eq!(editor.cursor(), *cursor, "cursor after {keys}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graphemes() {
+        // `e` with a combining accent, a wide character and an emoji sequence
+        let text = "ae\u{301}日👍\u{1f3fd}b\n";
+        let mut editor = TestEditor::new(text);
+        editor.keys("l").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 1));
+        editor.keys("ll").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+        editor.keys("$h").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+        editor.keys("x").await.unwrap();
+        assert_eq!(editor.text(), "ae\u{301}日b\n");
+        editor.keys("|lx").await.unwrap();
+        assert_eq!(editor.text(), "a日b\n");
+        editor.keys("A<Backspace><Backspace>").await.unwrap();
+        assert_eq!(editor.text(), "a\n");
+
+        // The cursor is drawn after the cells of the wide character
+        let mut editor = TestEditor::new(text);
+        editor.keys("3l").await.unwrap();
+        let frame = editor.render().unwrap();
+        let (_, start) = TestEditor::new(text).render().unwrap().cursor.unwrap();
+        assert_eq!(frame.cursor.unwrap().1, start + 4);
+    }
+
+    /// (keys, expected text, expected cursor) for replacing in "abc déf\n"
+    const REPLACE_CASES: &[(&str, &str, (usize, usize))] = &[
+        ("rx", "xbc déf\n", (0, 0)),
+        ("l2rx", "axx déf\n", (0, 2)),
+        ("$r<lt>", "abc dé<\n", (0, 6)),
+        ("w2rü", "abc üüf\n", (0, 5)),
+        // Too few characters left on the line
+        ("l9rx", "abc déf\n", (0, 1)),
+        // A line break replaces all the characters with one
+        ("llr<Enter>", "ab\n déf\n", (1, 0)),
+        ("l3r<Enter>", "a\ndéf\n", (1, 0)),
+        // Escape cancels it
+        ("r<Esc>x", "bc déf\n", (0, 0)),
+    ];
+
+    #[tokio::test]
+    async fn test_replace_char() {
+        for (keys, text, cursor) in REPLACE_CASES {
+            let mut editor = TestEditor::new("abc déf\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), *text, "text after {keys}");
+            assert_eq!(editor.cursor(), *cursor, "cursor after {keys}");
+        }
+
+        let mut editor = TestEditor::new("abc déf\n");
+        editor.keys("w3rxu").await.unwrap();
+        assert_eq!(editor.text(), "abc déf\n");
+    }
+
+    /// (keys, expected text, expected cursor) for yanking from "foo bar\nbaz\nqux\n"
+    const YANK_CASES: &[(&str, &str, (usize, usize))] = &[
+        ("ywP", "foo foo bar\nbaz\nqux\n", (0, 3)),
+        ("wy$P", "foo barbar\nbaz\nqux\n", (0, 6)),
+        ("jyyP", "foo bar\nbaz\nbaz\nqux\n", (1, 0)),
+        // A count on yy yanks that many lines
+        ("2yyGp", "foo bar\nbaz\nqux\nfoo bar\nbaz\n", (3, 0)),
+        // A named register is left alone by later yanks
+        ("\"ayyjyyG\"ap", "foo bar\nbaz\nqux\nfoo bar\n", (3, 0)),
+        ("\"aywjyy\"aP", "foo bar\nfoo baz\nqux\n", (1, 3)),
+    ];
+
+    #[tokio::test]
+    async fn test_yank() {
+        for (keys, text, cursor) in YANK_CASES {
+            let mut editor = TestEditor::new("foo bar\nbaz\nqux\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), *text, "text after {keys}");
+            assert_eq!(editor.cursor(), *cursor, "cursor after {keys}");
+        }
+
+        // Yanking leaves the buffer and its history alone
+        let mut editor = TestEditor::new("foo bar\nbaz\n");
+        editor.keys("xyyywy$u").await.unwrap();
+        assert_eq!(editor.text(), "foo bar\nbaz\n");
+        assert!(!editor.core.current_document().is_modified());
+    }
+}
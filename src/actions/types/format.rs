@@ -0,0 +1,226 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::actions::types::editing::{TextChange, apply_changes, ensure_writable};
+use crate::actions::types::lsp::text_changes;
+use crate::actions::types::system;
+use crate::core::diff::{LineChange, diff_lines};
+use crate::core::message::Message;
+use async_trait::async_trait;
+use lsp_types::FormattingOptions;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Longest wait for a `formatprg` command, which is killed after
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What formatting the buffer came to
+pub(super) enum Formatted {
+    /// Made this many changes
+    Changed(usize),
+    Unchanged,
+    /// Neither the server nor a `formatprg` command formats the language
+    Unavailable,
+    /// The formatter failed, with the error to show. The buffer is as it was
+    Failed(String),
+}
+
+/// Formats the current buffer with the language server, or the `formatprg` command of
+/// its language when the server doesn't format documents. The changes are undone as
+/// one, and the cursor stays where it was in the text around it
+pub(super) async fn format_buffer(ctx: &mut ActionContext<'_>) -> anyhow::Result<Formatted> {
+    let document = ctx.editor.buffer_manager.current();
+    // Only part of a large file is loaded, so there's no whole text to format
+    if document.is_large_file() {
+        return Ok(Formatted::Failed("E: Cannot format a file opened in large-file mode".to_string()));
+    }
+    let options = FormattingOptions {
+        tab_size: ctx.config.shift_width.0 as u32,
+        insert_spaces: ctx.config.expand_tab,
+        ..Default::default()
+    };
+    let edits = match ctx.lsp_service.get_client_mut() {
+        Some(client) => client.formatting(document, options).await,
+        None => Ok(None),
+    };
+    let changes = match edits {
+        Ok(Some(edits)) => text_changes(&document.buffer, edits, ctx.lsp_service.position_encoding()),
+        Ok(None) => {
+            let Some(program) = ctx.config.formatprg.get(document.language.to_str()) else {
+                return Ok(Formatted::Unavailable);
+            };
+            let file = document.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+            let text = document.buffer.to_string();
+            match run_formatter(program, &file, &text).await {
+                Ok(formatted) => line_changes(&text, &formatted),
+                Err(e) => return Ok(Formatted::Failed(e)),
+            }
+        }
+        Err(e) => return Ok(Formatted::Failed(format!("E: {e}"))),
+    };
+    if changes.is_empty() {
+        return Ok(Formatted::Unchanged);
+    }
+
+    let buffer = ctx.editor.buffer_manager.current_buffer();
+    let cursor = shift_position(buffer.cursor_position(&ctx.editor.cursor.get_point()), &changes);
+    let count = changes.len();
+    apply_changes(ctx, changes).await?;
+    let buffer = ctx.editor.buffer_manager.current_buffer();
+    ctx.editor.cursor.set_point(buffer.point_at_position(cursor), buffer);
+    Ok(Formatted::Changed(count))
+}
+
+/// Pipes the text through the command, split on whitespace with `{file}` filled in,
+/// failing with its error output when it exits with an error
+async fn run_formatter(program: &str, file: &str, text: &str) -> Result<String, String> {
+    let mut args = program.split_whitespace().map(|arg| arg.replace("{file}", file));
+    let Some(name) = args.next() else {
+        return Err("E: Empty formatprg".to_string());
+    };
+    let spawned = Command::new(&name)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = spawned.map_err(|e| format!("E: {name}: {e}"))?;
+
+    // Written while the output is read, as a formatter may not read all of it first
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = text.to_string();
+    tokio::spawn(async move { stdin.write_all(input.as_bytes()).await });
+    let output = match tokio::time::timeout(FORMAT_TIMEOUT, child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| format!("E: {name}: {e}"))?,
+        Err(_) => return Err(format!("E: {name}: No output after {}s", FORMAT_TIMEOUT.as_secs())),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = match stderr.trim_end() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_string(),
+        };
+        return Err(format!("E: {name}: {reason}"));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("E: {name}: Output is not UTF-8"))
+}
+
+/// The changes turning the text into the formatted text, one for each run of changed
+/// lines, so that the lines that stay don't count as changed
+fn line_changes(text: &str, formatted: &str) -> Vec<TextChange> {
+    let old = text.split_inclusive('\n').collect::<Vec<_>>();
+    let new = formatted.split_inclusive('\n').collect::<Vec<_>>();
+    let mut line_starts = vec![0];
+    line_starts.extend(old.iter().scan(0, |start, line| {
+        *start += line.len();
+        Some(*start)
+    }));
+
+    let mut changes: Vec<TextChange> = Vec::new();
+    let mut in_run = false;
+    for line in diff_lines(&old, &new) {
+        if line.change == LineChange::Equal {
+            in_run = false;
+            continue;
+        }
+        // The deleted lines of a run follow each other, from where it starts
+        if !in_run {
+            let start = line_starts[line.old];
+            changes.push(TextChange { start, end: start, text: String::new() });
+            in_run = true;
+        }
+        let change = changes.last_mut().expect("the run has a change");
+        match line.change {
+            LineChange::Delete => change.end += old[line.old].len(),
+            LineChange::Insert => change.text.push_str(new[line.new]),
+            LineChange::Equal => {}
+        }
+    }
+    changes
+}
+
+/// Where a byte of the text ends up after the changes, which don't overlap. A byte
+/// inside a change keeps its distance from the start of it, as far as the new text goes
+fn shift_position(position: usize, changes: &[TextChange]) -> usize {
+    let mut shifted = position;
+    for change in changes.iter().filter(|change| change.start < position) {
+        let removed = change.end.min(position) - change.start;
+        let added = match change.end <= position {
+            true => change.text.len(),
+            false => removed.min(change.text.len()),
+        };
+        shifted = shifted - removed + added;
+    }
+    shifted
+}
+
+/// Formats the buffer, as `:fmt` does
+#[derive(Debug, Clone)]
+pub struct FormatBuffer;
+
+#[async_trait(?Send)]
+impl Executable for FormatBuffer {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let message = match format_buffer(ctx).await? {
+            Formatted::Changed(1) => Message::info("Formatted, 1 change".to_string()),
+            Formatted::Changed(count) => Message::info(format!("Formatted, {count} changes")),
+            Formatted::Unchanged => Message::info("Already formatted".to_string()),
+            Formatted::Unavailable => {
+                let language = ctx.editor.buffer_manager.current().language.to_str();
+                Message::error(format!("E: No formatter for {language}, set one in [formatprg]"))
+            }
+            Formatted::Failed(error) => Message::error(error),
+        };
+        system::ShowMessage(message).execute(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    #[tokio::test]
+    async fn test_format_with_formatprg() {
+        let mut editor = TestEditor::new("one\nbanana\nlast\n");
+        editor.config.formatprg.insert("text".to_string(), "tr b B".to_string());
+        editor.keys("jll").await.unwrap();
+        editor.keys(":fmt<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "one\nBanana\nlast\n");
+        assert_eq!(editor.cursor(), (1, 2));
+        assert_eq!(editor.message_manager.current_message().unwrap().content, "Formatted, 1 change");
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "one\nbanana\nlast\n");
+
+        // A failing formatter leaves the buffer, and format on save still writes it
+        editor.config.formatprg.insert("text".to_string(), "ls /viron-missing-dir".to_string());
+        editor.config.format_on_save = true;
+        let path = std::env::temp_dir().join(format!("viron-{}-format", std::process::id()));
+        editor.keys(&format!(":w {}<Enter>", path.display())).await.unwrap();
+        let message = &editor.message_manager.current_message().unwrap().content;
+        assert!(message.starts_with("E: ls:"), "{message}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\nbanana\nlast\n");
+
+        editor.config.formatprg.insert("text".to_string(), "tr a-z A-Z".to_string());
+        editor.keys(":w<Enter>").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ONE\nBANANA\nLAST\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_shift_position() {
+        let changes = line_changes("a\nb\nc\nd\n", "a\nbb\nc\n");
+        let texts = changes.iter().map(|change| (change.start, change.end, change.text.as_str()));
+        assert_eq!(texts.collect::<Vec<_>>(), [(2, 4, "bb\n"), (6, 8, "")]);
+        assert_eq!(shift_position(1, &changes), 1);
+        assert_eq!(shift_position(3, &changes), 3);
+        assert_eq!(shift_position(4, &changes), 5);
+        assert_eq!(shift_position(7, &changes), 7);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::actions::types::editing::{after_edit, ensure_writable};
+use crate::actions::types::normal::ExecuteKeys;
+use crate::actions::types::{movement, system};
+use crate::core::buffer::Buffer;
+use crate::core::command::search_regex;
+use crate::core::history::edit::Edit;
+use crate::core::message::Message;
+use crate::core::replace::Replacement;
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use regex::{Captures, Regex};
+use std::cell::Cell;
+use tree_sitter::Point;
+
+thread_local! {
+    /// Set while `:g` runs, so keys run by `:normal` can't start another one
+    static GLOBAL_RUNNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Ex command that acts on one line at a time, such as `d` or `s/a/b/g`
+#[derive(Debug, Clone)]
+pub enum LineCommand {
+    Delete,
+    /// `p`, or `nu` to put the line number in front
+    Print { numbered: bool },
+    Substitute {
+        pattern: Regex,
+        replacement: Replacement,
+        all: bool,
+    },
+    Normal(ExecuteKeys),
+}
+
+impl LineCommand {
+    /// Runs an editing command on the row and returns the edits it made and how many
+    /// substitutions happened. Keys of `:normal` need the context and run elsewhere
+    fn apply(&self, buffer: &mut Buffer, row: usize) -> (Vec<Edit>, usize) {
+        let start = Point { row, column: 0 };
+        let start_byte = buffer.cursor_position(&start);
+        match self {
+            LineCommand::Normal(_) | LineCommand::Print { .. } => (Vec::new(), 0),
+            LineCommand::Delete => match buffer.delete_line(row) {
+                Some((deleted, _)) => (
+                    vec![Edit::delete(start_byte, start, deleted, start, start)],
+                    0,
+                ),
+                None => (Vec::new(), 0),
+            },
+            LineCommand::Substitute {
+                pattern,
+                replacement,
+                all,
+            } => {
+                let line = buffer.get_line_as_string(row);
+                let line = line.trim_end_matches('\n');
+                let (count, limit) = if *all {
+                    (pattern.find_iter(line).count(), 0)
+                } else {
+                    (usize::from(pattern.is_match(line)), 1)
+                };
+                if count == 0 {
+                    return (Vec::new(), 0);
+                }
+
+                let replaced = pattern
+                    .replacen(line, limit, |captures: &Captures| replacement.expand(captures))
+                    .into_owned();
+                buffer.delete_string(start_byte, line.len());
+                buffer.insert_string(start_byte, &replaced);
+                let edits = vec![
+                    Edit::delete(start_byte, start, line.to_string(), start, start),
+                    Edit::insert(start_byte, start, replaced, start, start),
+                ];
+                (edits, count)
+            }
+        }
+    }
+}
+
+/// Line of a range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// Zero-based row
+    Line(usize),
+    /// `.`, the cursor line
+    Current,
+    /// `$`, the last line
+    Last,
+}
+
+impl Address {
+    fn row(self, cursor_row: usize, last_row: usize) -> usize {
+        match self {
+            Address::Line(row) => row.min(last_row),
+            Address::Current => cursor_row,
+            Address::Last => last_row,
+        }
+    }
+}
+
+/// Lines a line command runs on
+#[derive(Debug, Clone)]
+pub enum LineSelection {
+    Current,
+    /// Lines between the addresses, both included, as in `:5,10`
+    Range(Address, Address),
+    /// Lines matching the pattern, or not matching it when inverted, as in `:g` and `:v`
+    Matching { pattern: Regex, invert: bool },
+    /// Lines matching the last search, as in `:g//d`, which runs as `Matching`
+    LastSearch { invert: bool },
+}
+
+impl LineSelection {
+    /// Rows selected in the buffer, top to bottom
+    pub fn rows(&self, buffer: &Buffer, cursor_row: usize) -> Vec<usize> {
+        match self {
+            LineSelection::Current => vec![cursor_row],
+            LineSelection::Range(start, end) => {
+                let last_row = last_text_row(buffer);
+                let start = start.row(cursor_row, last_row);
+                let end = end.row(cursor_row, last_row);
+                (start.min(end)..=start.max(end)).collect()
+            }
+            LineSelection::Matching { pattern, invert } => {
+                if buffer.len_bytes() == 0 {
+                    return Vec::new();
+                }
+                let last_row = last_text_row(buffer);
+                let mut rows = Vec::new();
+                buffer.for_each_line(|row, line| {
+                    let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+                    if row <= last_row && pattern.is_match(&line) != *invert {
+                        rows.push(row);
+                    }
+                });
+                rows
+            }
+            LineSelection::LastSearch { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Row of the last line of the text, which the empty line after a final newline isn't
+fn last_text_row(buffer: &Buffer) -> usize {
+    let last_row = buffer.line_count().saturating_sub(1);
+    match buffer.get_line_length_bytes(last_row) {
+        0 => last_row.saturating_sub(1),
+        _ => last_row,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunLineCommand {
+    selection: LineSelection,
+    command: LineCommand,
+}
+
+impl RunLineCommand {
+    pub fn new(selection: LineSelection, command: LineCommand) -> Self {
+        Self { selection, command }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for RunLineCommand {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let prints = matches!(self.command, LineCommand::Print { .. });
+        if !prints && !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let selection = match &self.selection {
+            LineSelection::LastSearch { invert } => {
+                let pattern = ctx
+                    .input
+                    .search_buffer
+                    .last_pattern()
+                    .context("No previous regular expression")?;
+                let pattern = search_regex(&pattern, ctx.config.smart_case.0)?;
+                &LineSelection::Matching { pattern, invert: *invert }
+            }
+            selection => selection,
+        };
+        let is_global = matches!(selection, LineSelection::Matching { .. });
+        if is_global && GLOBAL_RUNNING.get() {
+            return Err(anyhow!("Cannot nest :global"));
+        }
+
+        // Collect every line before editing, so deleting a line doesn't skip the next one
+        let cursor_row = ctx.editor.cursor.get_point().row;
+        let rows = selection.rows(ctx.editor.buffer_manager.current_buffer(), cursor_row);
+        if let LineSelection::Matching { pattern, .. } = selection
+            && rows.is_empty()
+        {
+            let message = format!("E: Pattern not found: {pattern}");
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        }
+
+        let was_running = GLOBAL_RUNNING.replace(is_global);
+        let result = match &self.command {
+            LineCommand::Normal(keys) => self.run_keys(ctx, &rows, keys).await,
+            LineCommand::Print { numbered } => print_lines(ctx, &rows, *numbered).await,
+            _ => self.run_edits(ctx, &rows).await,
+        };
+        GLOBAL_RUNNING.set(was_running);
+        result
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
+
+/// Shows the lines, and like vim leaves the cursor on the last one
+async fn print_lines(ctx: &mut ActionContext<'_>, rows: &[usize], numbered: bool) -> ActionResult {
+    let document = ctx.editor.buffer_manager.current();
+    let lines = rows
+        .iter()
+        .map(|&row| {
+            let line = document.buffer.get_line_as_string(row);
+            let line = line.trim_end_matches('\n');
+            match numbered {
+                true => format!("{:>3} {line}", document.line_label(row)),
+                false => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    if let Some(&row) = rows.last() {
+        movement::GoToLine::new(row).execute(ctx).await?;
+    }
+    system::ShowOutput(lines).execute(ctx).await
+}
+
+/// `:=` shows the number of the last line, and `:.=` or `:10=` that of the line
+#[derive(Debug, Clone)]
+pub struct PrintLineNumber {
+    selection: LineSelection,
+}
+
+impl PrintLineNumber {
+    pub fn new(selection: LineSelection) -> Self {
+        Self { selection }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for PrintLineNumber {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let selection = match &self.selection {
+            LineSelection::Current => &LineSelection::Range(Address::Last, Address::Last),
+            selection => selection,
+        };
+        let cursor_row = ctx.editor.cursor.get_point().row;
+        let row = selection.rows(&document.buffer, cursor_row).last().copied().unwrap_or(0);
+        let label = document.line_label(row);
+        system::ShowMessage(Message::info(label)).execute(ctx).await
+    }
+}
+
+impl RunLineCommand {
+    async fn run_edits(&self, ctx: &mut ActionContext<'_>, rows: &[usize]) -> ActionResult {
+        // Bottom to top, so the positions of the lines left to edit stay valid
+        let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+        let mut edits = Vec::new();
+        let mut changed_rows = Vec::new();
+        let mut substitutions = 0;
+        for &row in rows.iter().rev() {
+            let (line_edits, count) = self.command.apply(buffer, row);
+            if !line_edits.is_empty() {
+                changed_rows.push(row);
+            }
+            edits.extend(line_edits);
+            substitutions += count;
+        }
+
+        let (Some(&top), Some(&bottom)) = (changed_rows.last(), changed_rows.first()) else {
+            let LineCommand::Substitute { pattern, .. } = &self.command else {
+                return Ok(());
+            };
+            let message = format!("E: Pattern not found: {pattern}");
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        };
+
+        let edit = Edit::Group(edits);
+        after_edit(ctx, &edit).await?;
+        ctx.editor.buffer_manager.current_mut().history.push(edit);
+
+        // The cursor ends on the last substituted line, or where deleted lines were
+        let substitute = matches!(self.command, LineCommand::Substitute { .. });
+        let row = if substitute { bottom } else { top };
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let row = row.min(buffer.line_count().saturating_sub(1));
+        movement::GoToLine::new(row).execute(ctx).await?;
+
+        let lines = changed_rows.len();
+        let message = match substitute {
+            true => format!(
+                "{substitutions} substitution{} on {lines} line{}",
+                plural(substitutions),
+                plural(lines)
+            ),
+            false if matches!(self.selection, LineSelection::Current) => return Ok(()),
+            false => format!("{lines} fewer lines"),
+        };
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+
+    /// Runs the keys on each line from the top, starting at the line start. The lines
+    /// below move with the lines the keys add or remove. An error stops the keys for
+    /// that line, and the remaining lines still run
+    async fn run_keys(
+        &self,
+        ctx: &mut ActionContext<'_>,
+        rows: &[usize],
+        keys: &ExecuteKeys,
+    ) -> ActionResult {
+        // Everything the keys change is undone in one step
+        ctx.editor.buffer_manager.current_mut().history.begin_transaction();
+        let mut error = None;
+        let mut shift = 0isize;
+        for &row in rows {
+            let line_count = ctx.editor.buffer_manager.current_buffer().line_count();
+            if !matches!(self.selection, LineSelection::Current) {
+                let row = row.saturating_add_signed(shift);
+                if row >= line_count {
+                    break;
+                }
+                // The transaction is still ended when the cursor can't get there
+                if let Err(e) = movement::GoToPosition::new(row, 0).execute(ctx).await {
+                    error.get_or_insert(e);
+                    break;
+                }
+            }
+            if let Err(e) = keys.execute(ctx).await {
+                error.get_or_insert(e);
+            }
+            let new_line_count = ctx.editor.buffer_manager.current_buffer().line_count();
+            shift += new_line_count as isize - line_count as isize;
+        }
+        ctx.editor.buffer_manager.current_mut().history.end_transaction();
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
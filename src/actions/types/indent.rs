@@ -0,0 +1,143 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::{ActionDefinition, Executable, impl_action};
+use crate::actions::types::editing::{after_edit, ensure_writable};
+use crate::core::history::edit::Edit;
+use async_trait::async_trait;
+use tree_sitter::Point;
+
+/// The line moved right or left by the shift width. Tabs in the indent count up to the
+/// next multiple of it, and a dedented indent is written with spaces. Blank lines
+/// aren't indented
+fn shift_line(line: &str, width: usize, dedent: bool) -> String {
+    let content = line.trim_start_matches([' ', '\t']);
+    if !dedent {
+        return match content.is_empty() {
+            true => line.to_string(),
+            false => format!("{}{line}", " ".repeat(width)),
+        };
+    }
+    let indent = &line[..line.len() - content.len()];
+    let columns = indent.chars().fold(0, |columns, c| match c {
+        '\t' => (columns / width + 1) * width,
+        _ => columns + 1,
+    });
+    format!("{}{content}", " ".repeat(columns.saturating_sub(width)))
+}
+
+/// Shifts the lines from first to last as one edit, then puts the cursor on the first
+/// non-blank of the first line
+pub(super) async fn shift_lines(
+    ctx: &mut ActionContext<'_>,
+    first: usize,
+    last: usize,
+    dedent: bool,
+) -> ActionResult {
+    let width = ctx.config.shift_width.0.max(1);
+    let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+    let last = last.min(buffer.line_count().saturating_sub(1));
+
+    // The last line of the file may have no newline, and must not gain one
+    let text = buffer.get_lines(first, last);
+    let body = text.strip_suffix('\n').unwrap_or(&text);
+    let shifted = body
+        .split('\n')
+        .map(|line| shift_line(line, width, dedent))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let start = Point { row: first, column: 0 };
+    if shifted != body {
+        let start_byte = buffer.cursor_position(&start);
+        buffer.delete_string(start_byte, body.len());
+        buffer.insert_string(start_byte, &shifted);
+        let edit = Edit::Group(vec![
+            Edit::delete(start_byte, start, body.to_string(), start, start),
+            Edit::insert(start_byte, start, shifted, start, start),
+        ]);
+        after_edit(ctx, &edit).await?;
+        ctx.editor.buffer_manager.current_mut().history.push(edit);
+    }
+
+    let buffer = ctx.editor.buffer_manager.current_buffer();
+    ctx.editor.cursor.set_point(start, buffer);
+    ctx.editor.cursor.move_to_first_non_blank(buffer);
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct IndentCurrentLine;
+
+#[async_trait(?Send)]
+impl Executable for IndentCurrentLine {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let row = ctx.editor.cursor.get_point().row;
+        shift_lines(ctx, row, row, false).await
+    }
+}
+
+impl_action!(
+    IndentCurrentLine,
+    "Indent current line",
+    ActionDefinition::IndentCurrentLine
+);
+
+#[derive(Debug, Clone)]
+pub struct DedentCurrentLine;
+
+#[async_trait(?Send)]
+impl Executable for DedentCurrentLine {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let row = ctx.editor.cursor.get_point().row;
+        shift_lines(ctx, row, row, true).await
+    }
+}
+
+impl_action!(
+    DedentCurrentLine,
+    "Dedent current line",
+    ActionDefinition::DedentCurrentLine
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    #[test]
+    fn test_shift_line() {
+        assert_eq!(shift_line("foo", 4, false), "    foo");
+        assert_eq!(shift_line("", 4, false), "");
+        assert_eq!(shift_line("      foo", 4, true), "  foo");
+        assert_eq!(shift_line("  foo", 4, true), "foo");
+        assert_eq!(shift_line("\tfoo", 4, true), "foo");
+        assert_eq!(shift_line("  \t  foo", 4, true), "  foo");
+    }
+
+    #[tokio::test]
+    async fn test_shift_operators() {
+        // Keys, expected text and expected cursor
+        const CASES: [(&str, &str, (usize, usize)); 7] = [
+            (">>", "    a\n  b\n\n\tc\n", (0, 4)),
+            ("j<lt><lt>", "a\nb\n\n\tc\n", (1, 0)),
+            ("3>>", "    a\n      b\n\n\tc\n", (0, 4)),
+            (">j", "    a\n      b\n\n\tc\n", (0, 4)),
+            ("G<lt>k", "a\n  b\n\nc\n", (3, 0)),
+            ("jll>G", "a\n      b\n\n    \tc\n", (1, 6)),
+            ("3>>u", "a\n  b\n\n\tc\n", (0, 0)),
+        ];
+
+        for (keys, text, cursor) in CASES {
+            let mut editor = TestEditor::new("a\n  b\n\n\tc\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.text(), text, "text after {keys}");
+            assert_eq!(editor.cursor(), cursor, "cursor after {keys}");
+        }
+    }
+}
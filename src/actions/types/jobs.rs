@@ -0,0 +1,61 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::constants::components::{EDITOR_VIEW, JOBS};
+use async_trait::async_trait;
+
+/// `:jobs`, opening the list of background jobs, which takes every key while open
+#[derive(Debug, Clone)]
+pub struct ShowJobs;
+
+#[async_trait(?Send)]
+impl Executable for ShowJobs {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        ctx.editor.jobs.selected = Some(0);
+        ctx.ui.compositor.mark_visible(JOBS, true)?;
+        ctx.ui.compositor.set_focus(JOBS)?;
+        Ok(())
+    }
+}
+
+/// Keys of the `:jobs` panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobsPanelKey {
+    Select(isize),
+    /// Cancels the selected job, if it's still running
+    Cancel,
+    Close,
+    Other,
+}
+
+#[async_trait(?Send)]
+impl Executable for JobsPanelKey {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(selected) = ctx.editor.jobs.selected else {
+            return Ok(());
+        };
+        let jobs = ctx.editor.jobs.list();
+        let last = jobs.len().saturating_sub(1);
+        match *self {
+            JobsPanelKey::Select(rows) => {
+                ctx.editor.jobs.selected = Some(selected.saturating_add_signed(rows).min(last));
+            }
+            JobsPanelKey::Cancel => {
+                if let Some(job) = jobs.get(selected.min(last)) {
+                    ctx.editor.jobs.cancel(job.id);
+                }
+            }
+            JobsPanelKey::Close => {
+                ctx.editor.jobs.selected = None;
+                ctx.ui.compositor.mark_visible(JOBS, false)?;
+                ctx.ui.compositor.set_focus(EDITOR_VIEW)?;
+                ctx.ui.compositor.mark_all_dirty();
+                return Ok(());
+            }
+            JobsPanelKey::Other => {}
+        }
+        ctx.ui.compositor.mark_dirty(JOBS)?;
+        Ok(())
+    }
+}
+
@@ -0,0 +1,216 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::{ActionDefinition, Executable, impl_action};
+use crate::actions::types::buffer::after_buffer_change;
+use crate::actions::types::system;
+use crate::config::editor::{DEFAULT_KEYWORD_PROGRAM, KeywordProgram};
+use crate::core::buffer::Buffer;
+use crate::core::buffer::backend::ViewBackend;
+use crate::core::message::Message;
+use async_trait::async_trait;
+use crate::service::jobs::JobState;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Looks up the word under the cursor with the `keywordprg` command of the buffer's
+/// language, showing the output in a read-only scratch buffer. `K` does this when no
+/// language server gives hover information. The command runs as a job,
+/// so the editor stays usable while it does and `:jobs` can cancel it
+#[derive(Debug, Clone)]
+pub struct LookupKeyword;
+
+#[async_trait(?Send)]
+impl Executable for LookupKeyword {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let language = document.language.to_str();
+        let program = ctx
+            .config
+            .keywordprg
+            .get(language)
+            .or_else(|| ctx.config.keywordprg.get(DEFAULT_KEYWORD_PROGRAM));
+        let Some(program) = program else {
+            let message = format!("E: No keywordprg for {language}");
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        };
+        let point = ctx.editor.cursor.get_point();
+        let keywords = ctx.config.keyword_chars(document.language);
+        let Some(word) = document.buffer.word_at(&point, &keywords) else {
+            let message = "E: No word under the cursor".to_string();
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        };
+
+        let file = document
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let args = expand_command(program, &word, &file);
+        let description = args.join(" ");
+        let mut args = args.into_iter();
+        let Some(name) = args.next() else {
+            let message = format!("E: Empty keywordprg for {language}");
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        };
+        let mut command = Command::new(&name);
+        command.args(args).stdin(Stdio::null());
+
+        if program.detach {
+            let spawned = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+            return match spawned {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let message = format!("E: {name}: {e}");
+                    system::ShowMessage(Message::error(message)).execute(ctx).await
+                }
+            };
+        }
+
+        // Pagers would wait for input that never comes, and man formats for the window width
+        let width = ctx.editor.viewport.width();
+        command
+            .env("MANPAGER", "cat")
+            .env("PAGER", "cat")
+            .env("MANWIDTH", width.to_string());
+        // Dropping the output future, as on cancelling or timing out, kills the command
+        command.kill_on_drop(true);
+        let timeout = Duration::from_secs(program.timeout);
+        let job = ctx.editor.jobs.start(description);
+        tokio::spawn(async move {
+            let token = job.token();
+            let output = tokio::select! {
+                output = command.output() => output.map_err(|e| e.to_string()),
+                _ = tokio::time::sleep(timeout) => Err(format!("No output after {}s", timeout.as_secs())),
+                _ = token.cancelled() => return job.finish(JobState::Cancelled),
+            };
+            let state = match &output {
+                Ok(output) if output.status.success() => JobState::Done(output.status.to_string()),
+                Ok(output) => JobState::Failed(output.status.to_string()),
+                Err(e) => JobState::Failed(e.clone()),
+            };
+            job.finish_with(state, ShowKeywordOutput { name, word, output });
+        });
+        Ok(())
+    }
+}
+
+/// Shows the output of a `keywordprg` command once it has finished
+#[derive(Debug)]
+struct ShowKeywordOutput {
+    name: String,
+    word: String,
+    output: Result<Output, String>,
+}
+
+#[async_trait(?Send)]
+impl Executable for ShowKeywordOutput {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let name = &self.name;
+        let output = match &self.output {
+            Ok(output) => output,
+            Err(e) => {
+                let message = format!("E: {name}: {e}");
+                return system::ShowMessage(Message::error(message)).execute(ctx).await;
+            }
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = match stderr.lines().find(|line| !line.trim().is_empty()) {
+                Some(line) => line.trim().to_string(),
+                None => output.status.to_string(),
+            };
+            let message = format!("E: {name}: {reason}");
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        }
+
+        let text = strip_overstrike(&String::from_utf8_lossy(&output.stdout));
+        if text.trim().is_empty() {
+            let message = format!("{name}: No output for {}", self.word);
+            return system::ShowMessage(Message::info(message)).execute(ctx).await;
+        }
+
+        ctx.editor.buffer_manager.new_buffer();
+        let panel = ctx.editor.buffer_manager.current_mut();
+        panel.buffer = Buffer::from_string(&text);
+        panel.backend = Box::new(ViewBackend);
+        panel.scratch = true;
+        after_buffer_change(ctx).await
+    }
+}
+
+impl_action!(LookupKeyword, "Look up the word under the cursor", ActionDefinition::LookupKeyword);
+
+/// Splits the command into arguments, filling in `{word}` and `{file}` in each of them
+fn expand_command(program: &KeywordProgram, word: &str, file: &str) -> Vec<String> {
+    program
+        .command
+        .split_whitespace()
+        .map(|arg| arg.replace("{word}", word).replace("{file}", file))
+        .collect()
+}
+
+/// Removes the `c\b` pairs man uses for bold and underlined text
+fn strip_overstrike(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\x08' {
+            result.pop();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::editor::KeywordProgram;
+    use crate::editor::testing::TestEditor;
+    use std::collections::HashMap;
+
+    fn editor_with_program(content: &str, command: &str) -> TestEditor {
+        let mut config = TestEditor::new("").config;
+        let program = KeywordProgram {
+            command: command.to_string(),
+            detach: false,
+            timeout: 1,
+        };
+        config.keywordprg = HashMap::from([("default".to_string(), program)]);
+        TestEditor::with_config(content, config)
+    }
+
+    #[tokio::test]
+    async fn test_lookup_shows_output() {
+        let mut editor = editor_with_program("let  value = 1;\n", "echo docs:{word}");
+        // From the blank, the next word on the line is looked up
+        editor.keys("lllK").await.unwrap();
+        editor.wait_for_jobs().await.unwrap();
+        assert_eq!(editor.text(), "docs:value\n");
+        assert!(editor.core.current_document().is_read_only());
+
+        let mut editor = editor_with_program("   \n", "echo {word}");
+        editor.keys("K").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: No word under the cursor");
+        assert_eq!(editor.text(), "   \n");
+
+        let mut editor = editor_with_program("word\n", "viron-missing-command {word}");
+        editor.keys("K").await.unwrap();
+        editor.wait_for_jobs().await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert!(message.content.starts_with("E: viron-missing-command: "));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_times_out() {
+        let mut editor = editor_with_program("word\n", "sleep 30");
+        editor.keys("K").await.unwrap();
+        let started = std::time::Instant::now();
+        editor.wait_for_jobs().await.unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: sleep: No output after 1s");
+        assert_eq!(editor.text(), "word\n");
+    }
+}
@@ -1,11 +1,31 @@
 use crate::actions::ActionResult;
 use crate::actions::context::ActionContext;
 use crate::actions::core::{ActionDefinition, Executable, impl_action};
+use crate::actions::types::buffer::{after_buffer_change, close_current};
+use crate::actions::types::editing::{TextChange, after_edit, apply_changes, ensure_writable};
+use crate::actions::types::movement::GoToPosition;
+use crate::actions::types::quickfix::update_quickfix;
+use crate::actions::types::keyword::LookupKeyword;
+use crate::actions::types::symbols::GoToDefinitionFallback;
 use crate::actions::types::system;
+use crate::core::buffer::Buffer;
+use crate::core::buffer::backend::ViewBackend;
+use crate::core::completions::{Completions, Insertion};
+use crate::core::history::edit::Edit;
+use crate::core::hover::HoverText;
 use crate::core::message::Message;
+use crate::core::mode::Mode;
+use crate::service::lsp::{PositionEncoding, uri_to_path};
 use async_trait::async_trait;
-use lsp_types::Diagnostic;
-use crate::constants::components::EDITOR_VIEW;
+use lsp_types::{
+    CodeActionOrCommand, CompletionItem, Diagnostic, DiagnosticSeverity, DocumentChangeOperation,
+    DocumentChanges, HoverContents, Location, MarkupContent, MarkupKind, NumberOrString, OneOf,
+    Position, Range, TextDocumentEdit, TextEdit, WorkspaceEdit,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tree_sitter::Point;
+use crate::constants::components::{COMPLETION, EDITOR_VIEW, HOVER, STATUS_LINE};
 
 #[derive(Debug, Clone)]
 pub struct GoToDefinition;
@@ -14,9 +34,7 @@ pub struct GoToDefinition;
 impl Executable for GoToDefinition {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         let Some(lsp) = ctx.lsp_service.get_client_mut() else {
-            return system::ShowMessage(Message::error("LSP client is not available".to_string()))
-                .execute(ctx)
-                .await;
+            return GoToDefinitionFallback.execute(ctx).await;
         };
 
         let document = ctx.editor.buffer_manager.current();
@@ -65,8 +83,908 @@ impl Executable for UpdateDiagnostics {
                 ctx.ui
                     .compositor
                     .mark_dirty(EDITOR_VIEW)?;
+                ctx.ui
+                    .compositor
+                    .mark_dirty(STATUS_LINE)?;
+            }
+        }
+        update_quickfix(ctx)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestCompletion;
+
+#[async_trait(?Send)]
+impl Executable for RequestCompletion {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(lsp) = ctx.lsp_service.get_client_mut() else {
+            return no_server(ctx).await;
+        };
+
+        let document = ctx.editor.buffer_manager.current();
+        let point = ctx.editor.cursor.get_point();
+        if let Err(err) = lsp.completion(document, point.row, point.column).await {
+            return system::ShowMessage(Message::error(format!("Error: {}", err)))
+                .execute(ctx)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+impl_action!(
+    RequestCompletion,
+    "Request completions",
+    ActionDefinition::RequestCompletion
+);
+
+/// Where the word before the cursor starts
+fn word_start(ctx: &ActionContext) -> Point {
+    let document = ctx.editor.buffer_manager.current();
+    let keywords = ctx.config.keyword_chars(document.language);
+    let point = ctx.editor.cursor.get_point();
+    let line = document.buffer.get_line_as_string(point.row);
+    let before = line.get(..point.column).unwrap_or_default();
+    let column = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| keywords.is_keyword(*c))
+        .last()
+        .map_or(point.column, |(column, _)| column);
+    Point::new(point.row, column)
+}
+
+/// The text typed since the completed word started, none once the cursor left it
+fn typed_since(ctx: &ActionContext, start: Point) -> Option<String> {
+    let point = ctx.editor.cursor.get_point();
+    if *ctx.editor.mode != Mode::Insert || point.row != start.row || point.column < start.column {
+        return None;
+    }
+    let line = ctx.editor.buffer_manager.current().buffer.get_line_as_string(point.row);
+    line.get(start.column..point.column).map(str::to_string)
+}
+
+fn close_completion(ctx: &mut ActionContext) -> ActionResult {
+    *ctx.editor.completion = None;
+    ctx.ui.compositor.mark_visible(COMPLETION, false)?;
+    ctx.ui.compositor.mark_dirty(EDITOR_VIEW)
+}
+
+/// Opens the menu of completions the server answered with, when still typing
+#[derive(Debug, Clone)]
+pub struct ShowCompletions {
+    items: Vec<CompletionItem>,
+}
+
+impl ShowCompletions {
+    pub fn new(items: Vec<CompletionItem>) -> Self {
+        Self { items }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ShowCompletions {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let start = word_start(ctx);
+        let Some(typed) = typed_since(ctx, start) else {
+            return Ok(());
+        };
+        let mut completions = Completions::new(self.items.clone(), start);
+        completions.filter(&typed);
+        if completions.is_empty() {
+            return system::ShowMessage(Message::info("No completions".to_string()))
+                .execute(ctx)
+                .await;
+        }
+        *ctx.editor.completion = Some(completions);
+        ctx.ui.compositor.mark_visible(COMPLETION, true)?;
+        ctx.ui.compositor.mark_dirty(COMPLETION)
+    }
+}
+
+/// Narrows the open menu down to what's typed after each key, and closes it when
+/// nothing matches or the cursor left the word
+#[derive(Debug, Clone)]
+pub struct RefilterCompletion;
+
+#[async_trait(?Send)]
+impl Executable for RefilterCompletion {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(start) = ctx.editor.completion.as_ref().map(|completions| completions.start) else {
+            return Ok(());
+        };
+        if let Some(typed) = typed_since(ctx, start)
+            && let Some(completions) = ctx.editor.completion.as_mut()
+        {
+            completions.filter(&typed);
+            if !completions.is_empty() {
+                return ctx.ui.compositor.mark_dirty(COMPLETION);
+            }
+        }
+        close_completion(ctx)
+    }
+}
+
+/// Keys of the completion menu. Other keys go on to insert mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKey {
+    Select(isize),
+    Accept,
+    Dismiss,
+}
+
+#[async_trait(?Send)]
+impl Executable for CompletionKey {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(completions) = ctx.editor.completion.as_mut() else {
+            return Ok(());
+        };
+        match self {
+            CompletionKey::Select(offset) => {
+                completions.select(*offset);
+                ctx.ui.compositor.mark_dirty(COMPLETION)
+            }
+            CompletionKey::Dismiss => close_completion(ctx),
+            CompletionKey::Accept => {
+                let start = completions.start;
+                let insertion = completions.selected_item().map(Insertion::new);
+                close_completion(ctx)?;
+                match insertion {
+                    Some(insertion) if ensure_writable(ctx).await? => {
+                        let encoding = ctx.lsp_service.position_encoding();
+                        let buffer = ctx.editor.buffer_manager.current_buffer();
+                        let start = insertion.start.map_or(start, |start| encoding.point(buffer, start));
+                        accept_completion(ctx, start, insertion).await
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the text from the start to the cursor by the completion, as a single change
+async fn accept_completion(ctx: &mut ActionContext<'_>, start: Point, insertion: Insertion) -> ActionResult {
+    let point = ctx.editor.cursor.get_point();
+    let start = if start.row == point.row && start.column <= point.column { start } else { point };
+    let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+    let start_byte = buffer.cursor_position(&start);
+    let end_byte = buffer.cursor_position(&point);
+    let Some((deleted, _)) = buffer.delete_string(start_byte, end_byte - start_byte) else {
+        return Ok(());
+    };
+    buffer.insert_string(start_byte, &insertion.text);
+    let cursor = buffer.point_at_position(start_byte + insertion.cursor);
+    ctx.editor.cursor.set_point(cursor, buffer);
+
+    let edit = Edit::Group(vec![
+        Edit::delete(start_byte, start, deleted, point, start),
+        Edit::insert(start_byte, start, insertion.text, start, cursor),
+    ]);
+    after_edit(ctx, &edit).await?;
+    ctx.editor.buffer_manager.current_mut().history.push(edit);
+    Ok(())
+}
+
+/// Shows what the language server says about the symbol under the cursor, or looks up
+/// the word with `keywordprg` when there's no server or it doesn't answer hovers. A
+/// second `K` scrolls a popup taller than it can show
+#[derive(Debug, Clone)]
+pub struct Hover;
+
+#[async_trait(?Send)]
+impl Executable for Hover {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if let Some(hover) = ctx.editor.hover.as_mut() {
+            let height = hover.height(ctx.editor.viewport.height());
+            if hover.scroll_page(height) {
+                hover.kept = true;
+                return ctx.ui.compositor.mark_dirty(HOVER);
+            }
+            return Ok(());
+        }
+        let Some(lsp) = ctx.lsp_service.get_client_mut() else {
+            return LookupKeyword.execute(ctx).await;
+        };
+
+        let document = ctx.editor.buffer_manager.current();
+        let point = ctx.editor.cursor.get_point();
+        match lsp.hover(document, point.row, point.column).await {
+            Ok(true) => Ok(()),
+            Ok(false) => LookupKeyword.execute(ctx).await,
+            Err(err) => {
+                system::ShowMessage(Message::error(format!("Error: {}", err)))
+                    .execute(ctx)
+                    .await
+            }
+        }
+    }
+}
+
+impl_action!(Hover, "Show hover information", ActionDefinition::Hover);
+
+/// Shows the hover information the server answered with, in the message area when
+/// it's a single line
+#[derive(Debug, Clone)]
+pub struct ShowHover {
+    contents: HoverContents,
+}
+
+impl ShowHover {
+    pub fn new(contents: HoverContents) -> Self {
+        Self { contents }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ShowHover {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let hover = HoverText::new(&self.contents);
+        match hover.lines.as_slice() {
+            [] => {
+                system::ShowMessage(Message::info("No hover information".to_string()))
+                    .execute(ctx)
+                    .await
+            }
+            [line] => system::ShowMessage(Message::info(line.text.clone())).execute(ctx).await,
+            _ => {
+                *ctx.editor.hover = Some(hover);
+                ctx.ui.compositor.mark_visible(HOVER, true)?;
+                ctx.ui.compositor.mark_dirty(HOVER)
+            }
+        }
+    }
+}
+
+/// Closes the hover popup after a key, unless it was the `K` that scrolled it
+#[derive(Debug, Clone)]
+pub struct CloseHover;
+
+#[async_trait(?Send)]
+impl Executable for CloseHover {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(hover) = ctx.editor.hover.as_mut() else {
+            return Ok(());
+        };
+        if hover.kept {
+            hover.kept = false;
+            return Ok(());
+        }
+        *ctx.editor.hover = None;
+        ctx.ui.compositor.mark_visible(HOVER, false)?;
+        ctx.ui.compositor.mark_dirty(EDITOR_VIEW)
+    }
+}
+
+fn is_error(diagnostic: &Diagnostic) -> bool {
+    diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR) == DiagnosticSeverity::ERROR
+}
+
+/// Where the diagnostics of the current document start, in order. Only errors when
+/// `diagnostics_errors_first` is set and there are some
+fn diagnostic_positions(ctx: &ActionContext) -> Vec<Position> {
+    let uri = ctx.editor.buffer_manager.current().get_uri().unwrap_or_default();
+    let diagnostics = ctx.lsp_service.get_diagnostics(&uri);
+    let errors_only = ctx.config.diagnostics_errors_first && diagnostics.iter().any(is_error);
+    diagnostics
+        .iter()
+        .filter(|diagnostic| !errors_only || is_error(diagnostic))
+        .map(|diagnostic| diagnostic.range.start)
+        .collect()
+}
+
+/// Moves the cursor to the next diagnostic, or the previous one, wrapping around the
+/// ends of the document
+async fn go_to_diagnostic(ctx: &mut ActionContext<'_>, forward: bool) -> ActionResult {
+    let positions = diagnostic_positions(ctx);
+    let encoding = ctx.lsp_service.position_encoding();
+    let buffer = ctx.editor.buffer_manager.current_buffer();
+    let point = |position: &Position| encoding.point(buffer, *position);
+    let cursor = ctx.editor.cursor.get_point();
+    let target = match forward {
+        true => positions.iter().find(|position| point(position) > cursor).or(positions.first()),
+        false => positions.iter().rev().find(|position| point(position) < cursor).or(positions.last()),
+    };
+    let Some(&target) = target else {
+        let message = "E: No diagnostics in this buffer".to_string();
+        return system::ShowMessage(Message::error(message)).execute(ctx).await;
+    };
+    GoToPosition::checked(target).execute(ctx).await
+}
+
+/// Goes to the next diagnostic of the document, as `]d` does
+#[derive(Debug, Clone)]
+pub struct NextDiagnostic;
+
+#[async_trait(?Send)]
+impl Executable for NextDiagnostic {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        go_to_diagnostic(ctx, true).await
+    }
+}
+
+impl_action!(NextDiagnostic, "Go to the next diagnostic", ActionDefinition::NextDiagnostic);
+
+/// Goes to the previous diagnostic of the document, as `[d` does
+#[derive(Debug, Clone)]
+pub struct PreviousDiagnostic;
+
+#[async_trait(?Send)]
+impl Executable for PreviousDiagnostic {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        go_to_diagnostic(ctx, false).await
+    }
+}
+
+impl_action!(PreviousDiagnostic, "Go to the previous diagnostic", ActionDefinition::PreviousDiagnostic);
+
+/// Shows the whole message of the diagnostics under the cursor, or else on its line, in
+/// the hover popup, after their severity, code and source, as `:diag` does. The inline
+/// text only has the first line of one
+#[derive(Debug, Clone)]
+pub struct ShowDiagnostic;
+
+#[async_trait(?Send)]
+impl Executable for ShowDiagnostic {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let uri = ctx.editor.buffer_manager.current().get_uri().unwrap_or_default();
+        let encoding = ctx.lsp_service.position_encoding();
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let point = |position: Position| encoding.point(buffer, position);
+        let cursor = ctx.editor.cursor.get_point();
+        let on_line = ctx
+            .lsp_service
+            .get_diagnostics(&uri)
+            .iter()
+            .filter(|diagnostic| {
+                (diagnostic.range.start.line..=diagnostic.range.end.line).contains(&(cursor.row as u32))
+            })
+            .collect::<Vec<_>>();
+        let under_cursor = on_line
+            .iter()
+            .copied()
+            .filter(|diagnostic| {
+                (point(diagnostic.range.start)..=point(diagnostic.range.end)).contains(&cursor)
+            })
+            .collect::<Vec<_>>();
+        let shown = if under_cursor.is_empty() { on_line } else { under_cursor };
+        if shown.is_empty() {
+            let message = "E: No diagnostic under the cursor".to_string();
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        }
+
+        let sections = shown.iter().map(|diagnostic| {
+            let severity = match diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR) {
+                DiagnosticSeverity::WARNING => "warning",
+                DiagnosticSeverity::INFORMATION => "info",
+                DiagnosticSeverity::HINT => "hint",
+                _ => "error",
+            };
+            let code = match &diagnostic.code {
+                Some(NumberOrString::Number(code)) => format!("[{code}]"),
+                Some(NumberOrString::String(code)) => format!("[{code}]"),
+                None => String::new(),
+            };
+            let source = diagnostic.source.as_ref().map(|source| format!(" ({source})")).unwrap_or_default();
+            format!("{severity}{code}{source}\n{}", diagnostic.message)
+        });
+        let value = sections.collect::<Vec<_>>().join("\n\n");
+        ShowHover::new(HoverContents::Markup(MarkupContent { kind: MarkupKind::PlainText, value }))
+            .execute(ctx)
+            .await?;
+        // Opened by a key rather than a reply of the server, the popup outlives that key
+        if let Some(hover) = ctx.editor.hover.as_mut() {
+            hover.kept = true;
+        }
+        Ok(())
+    }
+}
+
+/// Tells that an action needs a language server
+async fn no_server(ctx: &mut ActionContext<'_>) -> ActionResult {
+    system::ShowMessage(Message::error("E: No language server is running".to_string()))
+        .execute(ctx)
+        .await
+}
+
+/// Lists the references of the symbol under the cursor, as `gr` does
+#[derive(Debug, Clone)]
+pub struct FindReferences;
+
+#[async_trait(?Send)]
+impl Executable for FindReferences {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(lsp) = ctx.lsp_service.get_client_mut() else {
+            return no_server(ctx).await;
+        };
+
+        let document = ctx.editor.buffer_manager.current();
+        let point = ctx.editor.cursor.get_point();
+        if let Err(err) = lsp.references(document, point.row, point.column).await {
+            return system::ShowMessage(Message::error(format!("Error: {}", err)))
+                .execute(ctx)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+impl_action!(FindReferences, "Find references", ActionDefinition::FindReferences);
+
+/// Lists the references the server found in a read-only scratch buffer, with the line
+/// of each, where Enter goes to the one on the line
+#[derive(Debug, Clone)]
+pub struct ShowReferences {
+    locations: Vec<Location>,
+}
+
+impl ShowReferences {
+    pub fn new(locations: Vec<Location>) -> Self {
+        Self { locations }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ShowReferences {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if self.locations.is_empty() {
+            let message = "E: No references found".to_string();
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        }
+        let root = std::env::current_dir().unwrap_or_default();
+        // Lines of the files not open, read once each
+        let mut files: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut text = String::new();
+        let mut locations = Vec::new();
+        for location in &self.locations {
+            let path = uri_to_path(&location.uri);
+            let position = location.range.start;
+            let row = position.line as usize;
+            let open = ctx
+                .editor
+                .buffer_manager
+                .documents()
+                .find(|document| document.full_file_path().as_ref() == Some(&path));
+            let line = match open {
+                Some(document) => document.buffer.get_line_as_string(row),
+                None => {
+                    let lines = files.entry(path.clone()).or_insert_with(|| {
+                        let text = std::fs::read_to_string(&path).unwrap_or_default();
+                        text.lines().map(str::to_string).collect()
+                    });
+                    lines.get(row).cloned().unwrap_or_default()
+                }
+            };
+            let shown = path.strip_prefix(&root).unwrap_or(&path);
+            let column = ctx.lsp_service.position_encoding().byte_column(&line, position.character);
+            text.push_str(&format!("{}:{}:{}: {}\n", shown.display(), row + 1, column + 1, line.trim()));
+            locations.push((path, position));
+        }
+
+        ctx.editor.buffer_manager.new_buffer();
+        let list = ctx.editor.buffer_manager.current_mut();
+        list.buffer = Buffer::from_string(&text);
+        list.backend = Box::new(ViewBackend);
+        list.scratch = true;
+        list.locations = Some(locations);
+        after_buffer_change(ctx).await?;
+        GoToPosition::new(0, 0).execute(ctx).await
+    }
+}
+
+/// Renames the symbol under the cursor everywhere, as `:rename` does
+#[derive(Debug, Clone)]
+pub struct Rename {
+    new_name: String,
+}
+
+impl Rename {
+    pub fn new(new_name: String) -> Self {
+        Self { new_name }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for Rename {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let Some(lsp) = ctx.lsp_service.get_client_mut() else {
+            return no_server(ctx).await;
+        };
+
+        let document = ctx.editor.buffer_manager.current();
+        let point = ctx.editor.cursor.get_point();
+        if let Err(err) = lsp.rename(document, point.row, point.column, &self.new_name).await {
+            return system::ShowMessage(Message::error(format!("Error: {}", err)))
+                .execute(ctx)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+/// The text edits of a workspace edit by file, files sorted by path
+fn file_edits(edit: &WorkspaceEdit) -> Vec<(PathBuf, Vec<TextEdit>)> {
+    let mut files: BTreeMap<PathBuf, Vec<TextEdit>> = BTreeMap::new();
+    let mut add = |document: &TextDocumentEdit| {
+        let edits = document.edits.iter().map(|edit| match edit {
+            OneOf::Left(edit) => edit.clone(),
+            OneOf::Right(annotated) => annotated.text_edit.clone(),
+        });
+        files.entry(uri_to_path(&document.text_document.uri)).or_default().extend(edits);
+    };
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(documents)) => documents.iter().for_each(&mut add),
+        Some(DocumentChanges::Operations(operations)) => {
+            for operation in operations {
+                // Creating, renaming and deleting files is left to the user
+                if let DocumentChangeOperation::Edit(document) = operation {
+                    add(document);
+                }
+            }
+        }
+        None => {
+            for (uri, edits) in edit.changes.iter().flatten() {
+                files.entry(uri_to_path(uri)).or_default().extend(edits.iter().cloned());
             }
         }
+    }
+    files.into_iter().collect()
+}
+
+/// The bytes of the buffer the edits replace, with their text
+pub(super) fn text_changes(
+    buffer: &Buffer,
+    edits: Vec<TextEdit>,
+    encoding: PositionEncoding,
+) -> Vec<TextChange> {
+    edits
+        .into_iter()
+        .map(|edit| TextChange {
+            start: buffer.cursor_position(&encoding.point(buffer, edit.range.start)),
+            end: buffer.cursor_position(&encoding.point(buffer, edit.range.end)),
+            text: edit.new_text,
+        })
+        .collect()
+}
+
+/// Applies the edit of a rename or code action to every file it changes, opening those
+/// that aren't yet. Each buffer takes its changes as one, which `u` takes back
+#[derive(Debug, Clone)]
+pub struct ApplyWorkspaceEdit {
+    edit: WorkspaceEdit,
+}
+
+impl ApplyWorkspaceEdit {
+    pub fn new(edit: WorkspaceEdit) -> Self {
+        Self { edit }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ApplyWorkspaceEdit {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let files = file_edits(&self.edit);
+        if files.is_empty() {
+            return system::ShowMessage(Message::info("No changes to make".to_string()))
+                .execute(ctx)
+                .await;
+        }
+        let original = ctx.editor.buffer_manager.current_index();
+        let cursor = ctx.editor.cursor.get_point();
+        let (mut changes, mut changed_files) = (0, 0);
+        for (path, edits) in files {
+            let large_file_threshold = ctx.config.large_file_size.bytes();
+            let buffers = &mut *ctx.editor.buffer_manager;
+            let count = buffers.len();
+            buffers.open_file(&path, large_file_threshold);
+            if buffers.len() > count {
+                after_buffer_change(ctx).await?;
+            }
+            if !ensure_writable(ctx).await? {
+                continue;
+            }
+            changes += edits.len();
+            changed_files += 1;
+            let encoding = ctx.lsp_service.position_encoding();
+            let changes = text_changes(ctx.editor.buffer_manager.current_buffer(), edits, encoding);
+            apply_changes(ctx, changes).await?;
+        }
+
+        ctx.editor.buffer_manager.switch_to(original)?;
+        after_buffer_change(ctx).await?;
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        ctx.editor.cursor.set_point(buffer.clamp_point(&cursor), buffer);
+        let message = format!("{changes} changes in {changed_files} files");
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+}
+
+/// Asks for the code actions at the cursor, offered for the diagnostics on its line
+/// among others, as `:codeaction` does
+#[derive(Debug, Clone)]
+pub struct RequestCodeActions;
+
+#[async_trait(?Send)]
+impl Executable for RequestCodeActions {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let point = ctx.editor.cursor.get_point();
+        let uri = document.get_uri().unwrap_or_default();
+        let row = point.row as u32;
+        let diagnostics = ctx
+            .lsp_service
+            .get_diagnostics(&uri)
+            .iter()
+            .filter(|diagnostic| (diagnostic.range.start.line..=diagnostic.range.end.line).contains(&row))
+            .cloned()
+            .collect();
+        let position = ctx.lsp_service.position_encoding().position(&document.buffer, point);
+        let Some(lsp) = ctx.lsp_service.get_client_mut() else {
+            return no_server(ctx).await;
+        };
+
+        let range = Range {
+            start: position,
+            end: position,
+        };
+        if let Err(err) = lsp.code_action(document, range, diagnostics).await {
+            return system::ShowMessage(Message::error(format!("Error: {}", err)))
+                .execute(ctx)
+                .await;
+        }
         Ok(())
     }
 }
+
+/// Lists the code actions offered in a read-only scratch buffer, where Enter applies
+/// the one on the line
+#[derive(Debug, Clone)]
+pub struct ShowCodeActions {
+    actions: Vec<CodeActionOrCommand>,
+}
+
+impl ShowCodeActions {
+    pub fn new(actions: Vec<CodeActionOrCommand>) -> Self {
+        Self { actions }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ShowCodeActions {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if self.actions.is_empty() {
+            let message = "No code actions available".to_string();
+            return system::ShowMessage(Message::info(message)).execute(ctx).await;
+        }
+        let text = self
+            .actions
+            .iter()
+            .map(|action| match action {
+                CodeActionOrCommand::Command(command) => format!("{}\n", command.title),
+                CodeActionOrCommand::CodeAction(action) => match &action.kind {
+                    Some(kind) if !kind.as_str().is_empty() => {
+                        format!("{} ({})\n", action.title, kind.as_str())
+                    }
+                    _ => format!("{}\n", action.title),
+                },
+            })
+            .collect::<String>();
+
+        ctx.editor.buffer_manager.new_buffer();
+        let list = ctx.editor.buffer_manager.current_mut();
+        list.buffer = Buffer::from_string(&text);
+        list.backend = Box::new(ViewBackend);
+        list.scratch = true;
+        list.code_actions = Some(self.actions.clone());
+        after_buffer_change(ctx).await?;
+        GoToPosition::new(0, 0).execute(ctx).await
+    }
+}
+
+/// Closes the list of code actions and applies the one picked, making its edit and
+/// running its command
+#[derive(Debug, Clone)]
+pub struct ApplyCodeAction {
+    action: CodeActionOrCommand,
+}
+
+impl ApplyCodeAction {
+    pub fn new(action: CodeActionOrCommand) -> Self {
+        Self { action }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ApplyCodeAction {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if ctx.editor.buffer_manager.current().code_actions.is_some() && close_current(ctx, true).await? {
+            after_buffer_change(ctx).await?;
+        }
+        let (edit, command) = match &self.action {
+            CodeActionOrCommand::Command(command) => (None, Some(command)),
+            CodeActionOrCommand::CodeAction(action) => (action.edit.clone(), action.command.as_ref()),
+        };
+        if edit.is_none() && command.is_none() {
+            let message = "E: The code action has nothing to apply".to_string();
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        }
+        if let Some(edit) = edit {
+            ApplyWorkspaceEdit::new(edit).execute(ctx).await?;
+        }
+        if let Some(command) = command {
+            let Some(lsp) = ctx.lsp_service.get_client_mut() else {
+                return no_server(ctx).await;
+            };
+            if let Err(err) = lsp.execute_command(command).await {
+                return system::ShowMessage(Message::error(format!("Error: {}", err)))
+                    .execute(ctx)
+                    .await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+    use lsp_types::{CodeAction, CompletionItemKind, InsertTextFormat, Uri};
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_complete_from_menu() {
+        let item = |label: &str, insert_text: &str| CompletionItem {
+            label: label.to_string(),
+            kind: Some(CompletionItemKind::METHOD),
+            insert_text: Some(insert_text.to_string()),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        };
+        let items = vec![item("push", "push(${1:value})$0"), item("pop", "pop()"), item("len", "len()")];
+        let mut editor = TestEditor::new("\n");
+        editor.keys("iv.p<Esc>a").await.unwrap();
+        editor.execute(&ShowCompletions::new(items)).await.unwrap();
+        assert_eq!(editor.core.completion.as_ref().unwrap().len(), 2);
+
+        // Typing narrows the menu down, `<C-n>` selects the next item
+        editor.keys("o").await.unwrap();
+        assert_eq!(editor.core.completion.as_ref().unwrap().len(), 1);
+        editor.keys("<Backspace><C-n><Enter>").await.unwrap();
+        assert!(editor.core.completion.is_none());
+        assert_eq!(editor.text(), "v.push(value)\n");
+        assert_eq!(editor.cursor(), (0, 7));
+
+        // The completion is undone with the rest of what was typed
+        editor.keys("<Esc>u").await.unwrap();
+        assert_eq!(editor.text(), "v.p\n");
+
+        // `<Esc>` closes the menu and stays in insert mode
+        editor.keys("a").await.unwrap();
+        editor.execute(&ShowCompletions::new(vec![item("pop", "pop()")])).await.unwrap();
+        assert!(editor.core.completion.is_some());
+        editor.keys("<Esc>x").await.unwrap();
+        assert!(editor.core.completion.is_none());
+        assert_eq!(editor.text(), "v.px\n");
+    }
+
+    #[tokio::test]
+    async fn test_workspace_edit_and_lists() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-rename", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.rs"), dir.join("b.rs"));
+        std::fs::write(&a, "let count = 1;\ncount += 1;\n").unwrap();
+        std::fs::write(&b, "use a::count;\n").unwrap();
+        let uri = |path: &PathBuf| Uri::from_str(&format!("file://{}", path.display())).unwrap();
+        let edit = |row: u32, column: u32, len: u32, text: &str| TextEdit {
+            range: Range {
+                start: Position { line: row, character: column },
+                end: Position { line: row, character: column + len },
+            },
+            new_text: text.to_string(),
+        };
+
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>j", a.display())).await.unwrap();
+        let changes = HashMap::from([
+            (uri(&a), vec![edit(0, 4, 5, "total"), edit(1, 0, 5, "total")]),
+            (uri(&b), vec![edit(0, 7, 5, "total")]),
+        ]);
+        let workspace_edit = WorkspaceEdit { changes: Some(changes), ..Default::default() };
+        editor.execute(&ApplyWorkspaceEdit::new(workspace_edit)).await.unwrap();
+        assert_eq!(editor.text(), "let total = 1;\ntotal += 1;\n");
+        assert_eq!(editor.cursor(), (1, 0));
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "3 changes in 2 files");
+
+        // Each buffer takes back its changes at once
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "let count = 1;\ncount += 1;\n");
+
+        // Enter on a reference goes to it, opening the file if needed
+        let location = |path: &PathBuf, row: u32, column: u32| Location {
+            uri: uri(path),
+            range: edit(row, column, 5, "").range,
+        };
+        let locations = vec![location(&a, 0, 4), location(&b, 0, 7)];
+        editor.execute(&ShowReferences::new(locations)).await.unwrap();
+        assert_eq!(
+            editor.text(),
+            format!("{}:1:5: let count = 1;\n{}:1:8: use a::total;\n", a.display(), b.display())
+        );
+        editor.keys("j<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path.as_ref(), Some(&b));
+        assert_eq!(editor.cursor(), (0, 7));
+
+        // Enter on a code action closes the list and applies it
+        let action = |title: &str, text: &str| {
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: title.to_string(),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri(&b), vec![edit(0, 0, 3, text)])])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        };
+        let actions = vec![action("Make public", "pub use"), action("Remove", "")];
+        editor.execute(&ShowCodeActions::new(actions)).await.unwrap();
+        assert_eq!(editor.text(), "Make public\nRemove\n");
+        editor.keys("<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path.as_ref(), Some(&b));
+        assert_eq!(editor.text(), "pub use a::total;\n");
+        assert!(editor.core.buffer_manager.documents().all(|document| document.code_actions.is_none()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic_navigation() {
+        let path = std::env::temp_dir().join(format!("viron-{}-diagnostics.rs", std::process::id()));
+        std::fs::write(&path, "let a = 1;\nlet b = 2;\nlet c = 3;\n").unwrap();
+        let diagnostic = |row: u32, column: u32, severity, message: &str| Diagnostic {
+            range: Range {
+                start: Position { line: row, character: column },
+                end: Position { line: row, character: column + 1 },
+            },
+            severity: Some(severity),
+            code: Some(NumberOrString::String("E0308".to_string())),
+            source: Some("rustc".to_string()),
+            message: message.to_string(),
+            ..Default::default()
+        };
+        let mut editor = TestEditor::new("");
+        editor.keys(&format!(":e {}<Enter>", path.display())).await.unwrap();
+        let uri = editor.core.current_document().get_uri().unwrap();
+        let diagnostics = vec![
+            diagnostic(2, 4, DiagnosticSeverity::ERROR, "mismatched types\nexpected `u8`"),
+            diagnostic(0, 4, DiagnosticSeverity::WARNING, "unused variable"),
+        ];
+        editor.lsp_service.update_diagnostics(&uri, diagnostics);
+
+        editor.keys("]d").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+        editor.keys("]d").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 4));
+        editor.keys("]d").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+        editor.keys("[d").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 4));
+
+        // Warnings are skipped while there are errors
+        editor.config.diagnostics_errors_first = true;
+        editor.keys("gg]d").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 4));
+        editor.keys("]d").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 4));
+
+        // The whole message shows in the popup
+        editor.keys(":diag<Enter>").await.unwrap();
+        let hover = editor.core.hover.as_ref().unwrap();
+        let lines = hover.lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(lines, ["error[E0308] (rustc)", "mismatched types", "expected `u8`"]);
+        editor.keys("j").await.unwrap();
+        assert!(editor.core.hover.is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,9 +1,21 @@
 pub mod buffer;
 pub mod command;
 pub mod composite;
+pub mod diff;
 pub mod editing;
+pub mod format;
+pub mod global;
+pub mod indent;
+pub mod jobs;
+pub mod keyword;
 pub mod lsp;
 pub mod mode;
 pub mod movement;
+pub mod normal;
+pub mod quickfix;
 pub mod search;
+pub mod sort;
+pub mod symbols;
 pub mod system;
+pub mod tutor;
+pub mod visual;
@@ -1,11 +1,12 @@
 use crate::actions::core::{Action, ActionDefinition, Executable};
 use crate::actions::ActionResult;
+use crate::actions::types::editing;
 use crate::core::mode::Mode;
 use crate::core::operation::Operator;
 use async_trait::async_trait;
 use std::fmt::Debug;
 use crate::actions::context::ActionContext;
-use crate::constants::components::{COMMAND_LINE, EDITOR_VIEW, PENDING_KEYS, SEARCH_BOX, STATUS_LINE};
+use crate::constants::components::{BOTTOM_BAR, EDITOR_VIEW, STATUS_LINE};
 
 #[derive(Debug, Clone)]
 pub struct EnterMode {
@@ -21,65 +22,91 @@ impl EnterMode {
 #[async_trait(?Send)]
 impl Executable for EnterMode {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let edits = matches!(
+            self.mode,
+            Mode::Insert
+                | Mode::OperationPending(
+                    Operator::Delete | Operator::Change | Operator::Indent | Operator::Dedent
+                )
+        );
+        if edits && !editing::ensure_writable(ctx).await? {
+            ctx.input.input_state.clear();
+            return Ok(());
+        }
+
         match &ctx.editor.mode {
             Mode::Command => {
                 ctx.input.command_buffer.clear();
-                ctx.ui.compositor
-                    .mark_visible(COMMAND_LINE, false)?;
             }
             Mode::Search => {
-                ctx.input.search_buffer.buffer.clear();
-                ctx.ui.compositor
-                    .mark_visible(SEARCH_BOX, false)?;
+                ctx.input.search_buffer.clear_input();
             }
             Mode::OperationPending(_) => {
                 ctx.input.input_state.clear();
-                ctx.ui.compositor
-                    .mark_visible(PENDING_KEYS, false)?;
             }
             _ => {}
         };
 
         match &self.mode {
+            // The prompt replaces the message, so only what comes after it is shown
             Mode::Command => {
                 ctx.input.command_buffer.clear();
+                ctx.message.clear_message();
                 ctx.ui.compositor
-                    .mark_visible(COMMAND_LINE, true)?;
-                ctx.ui.compositor
-                    .set_focus(COMMAND_LINE)?;
+                    .set_focus(BOTTOM_BAR)?;
             }
             Mode::Search => {
-                ctx.input.search_buffer.buffer.clear();
-                ctx.ui.compositor
-                    .mark_visible(SEARCH_BOX, true)?;
-                ctx.ui.compositor.set_focus(SEARCH_BOX)?;
+                ctx.input.search_buffer.clear_input();
+                ctx.message.clear_message();
+                ctx.ui.compositor.set_focus(BOTTOM_BAR)?;
             }
             Mode::Normal | Mode::Insert => {
                 ctx.input.command_buffer.clear();
-                ctx.input.search_buffer.buffer.clear();
+                ctx.input.search_buffer.clear_input();
                 ctx.ui.compositor
                     .set_focus(EDITOR_VIEW)?;
                 ctx.input.input_state.clear();
-                ctx.editor.cursor
-                    .clamp_column(ctx.editor.buffer_manager.current_buffer(), &Mode::Normal);
-                ctx.ui.compositor
-                    .mark_visible(COMMAND_LINE, false)?;
-                ctx.ui.compositor
-                    .mark_visible(SEARCH_BOX, false)?;
-                ctx.ui.compositor
-                    .mark_visible(PENDING_KEYS, false)?;
+                let buffer = ctx.editor.buffer_manager.current_buffer();
+                // Leaving insert mode steps back onto the last inserted character, like vim
+                if *ctx.editor.mode == Mode::Insert
+                    && self.mode == Mode::Normal
+                    && !ctx.config.keep_cursor_on_escape
+                {
+                    ctx.editor.cursor.move_left(buffer, &Mode::Normal, true);
+                }
+                ctx.editor.cursor.clamp_column(buffer, &Mode::Normal);
+                ctx.message.hide_search_result();
             }
             Mode::OperationPending(_) => {
                 ctx.ui.compositor
                     .set_focus(EDITOR_VIEW)?;
-                ctx.ui.compositor
-                    .mark_visible(PENDING_KEYS, true)?;
+            }
+            Mode::VisualLine => {
+                ctx.ui.compositor.set_focus(EDITOR_VIEW)?;
+                ctx.input.input_state.clear();
+                *ctx.editor.visual_anchor = ctx.editor.cursor.get_point().row;
             }
         };
 
+        // The selected lines and the matches of the pattern being typed are highlighted
+        let highlights = |mode: &Mode| matches!(mode, Mode::VisualLine | Mode::Search);
+        if highlights(ctx.editor.mode) || highlights(&self.mode) {
+            ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
+        }
+
+        // Everything typed, deleted or joined in insert mode is one undo step
+        let history = &mut ctx.editor.buffer_manager.current_mut().history;
+        match (*ctx.editor.mode == Mode::Insert, self.mode == Mode::Insert) {
+            (true, false) => history.end_group(),
+            (false, true) => history.begin_group(),
+            _ => {}
+        }
+
         *ctx.editor.mode = self.mode.clone();
         ctx.ui.compositor
             .mark_dirty(STATUS_LINE)?;
+        ctx.ui.compositor
+            .mark_dirty(BOTTOM_BAR)?;
         Ok(())
     }
 }
@@ -94,6 +121,9 @@ impl Action for EnterMode {
             Mode::OperationPending(Operator::Change) => "Change",
             Mode::OperationPending(Operator::Delete) => "Delete",
             Mode::OperationPending(Operator::Yank) => "Yank",
+            Mode::OperationPending(Operator::Indent) => "Indent",
+            Mode::OperationPending(Operator::Dedent) => "Dedent",
+            Mode::VisualLine => "Enter visual line mode",
         }
     }
 
@@ -105,3 +135,44 @@ impl Action for EnterMode {
         Box::new(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::editor::testing::TestEditor;
+
+    #[tokio::test]
+    async fn test_escape_moves_cursor_left() {
+        let mut editor = TestEditor::new("hello\n");
+
+        editor.keys("A<Esc>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+
+        editor.keys("A!<Esc>A?<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "hello!?\n");
+        assert_eq!(editor.cursor(), (0, 6));
+    }
+
+    #[tokio::test]
+    async fn test_paste_after_escape_on_empty_line() {
+        let mut editor = TestEditor::new("word\nnext\n");
+
+        editor.keys("y$o<Esc>p").await.unwrap();
+        assert_eq!(editor.text(), "word\nword\nnext\n");
+        assert_eq!(editor.cursor(), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_keep_cursor_on_escape() {
+        let keymap = TestEditor::new("").config.keymap;
+        let config = Config {
+            keymap,
+            keep_cursor_on_escape: true,
+            ..Default::default()
+        };
+        let mut editor = TestEditor::with_config("hello\n", config);
+
+        editor.keys("0li<Esc>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 1));
+    }
+}
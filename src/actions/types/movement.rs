@@ -1,10 +1,20 @@
 use crate::actions::core::{impl_action, ActionDefinition, Executable};
 use crate::actions::ActionResult;
-use crate::config::editor::Gutter;
+use crate::actions::types::buffer::{after_buffer_change, OpenBuffer};
+use crate::actions::types::lsp::ApplyCodeAction;
+use crate::actions::types::symbols::go_to_symbol;
+use crate::actions::types::system;
+use crate::core::buffer::backend::{SeekPosition, SeekResult};
+use crate::core::jumps::Jump;
+use crate::core::marks::JUMP_MARK;
+use crate::core::message::Message;
+use crate::ui::display::display_column;
 use async_trait::async_trait;
+use lsp_types::Position;
 use std::fmt::Debug;
 use crate::actions::context::ActionContext;
 use crate::constants::components::{EDITOR_VIEW, STATUS_LINE};
+use tree_sitter::Point;
 
 #[derive(Debug, Clone)]
 pub struct MoveLeft {
@@ -24,7 +34,7 @@ impl Executable for MoveLeft {
         ctx.editor.cursor
             .move_left(ctx.editor.buffer_manager.current_buffer(), ctx.editor.mode, self.inline);
         let new_row = ctx.editor.cursor.get_point().row;
-        if old_row != new_row && ctx.config.gutter == Gutter::Relative {
+        if old_row != new_row && ctx.config.gutter.is_relative() {
             ctx.ui.compositor
                 .mark_dirty(EDITOR_VIEW)?;
         }
@@ -56,7 +66,7 @@ impl Executable for MoveRight {
         ctx.editor.cursor
             .move_right(ctx.editor.buffer_manager.current_buffer(), ctx.editor.mode, self.inline);
         let new_row = ctx.editor.cursor.get_point().row;
-        if old_row != new_row && ctx.config.gutter == Gutter::Relative {
+        if old_row != new_row && ctx.config.gutter.is_relative() {
             ctx.ui.compositor
                 .mark_dirty(EDITOR_VIEW)?;
         }
@@ -78,7 +88,7 @@ impl Executable for MoveUp {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         ctx.editor.cursor
             .move_up(ctx.editor.buffer_manager.current_buffer(), ctx.editor.mode);
-        if ctx.config.gutter == Gutter::Relative {
+        if ctx.config.gutter.is_relative() {
             ctx.ui.compositor
                 .mark_dirty(EDITOR_VIEW)?;
         }
@@ -98,7 +108,7 @@ impl Executable for MoveDown {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         ctx.editor.cursor
             .move_down(ctx.editor.buffer_manager.current_buffer(), ctx.editor.mode);
-        if ctx.config.gutter == Gutter::Relative {
+        if ctx.config.gutter.is_relative() {
             ctx.ui.compositor
                 .mark_dirty(EDITOR_VIEW)?;
         }
@@ -117,8 +127,6 @@ pub struct MoveToLineStart;
 impl Executable for MoveToLineStart {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         ctx.editor.cursor.move_to_line_start();
-        ctx.editor.cursor
-            .find_next_word(ctx.editor.buffer_manager.current_buffer());
         ctx.ui.compositor
             .mark_dirty(STATUS_LINE)?;
         Ok(())
@@ -157,7 +165,7 @@ pub struct MoveToTop;
 #[async_trait(?Send)]
 impl Executable for MoveToTop {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        GoToLine::new(0).execute(ctx).await
+        seek_to(ctx, SeekPosition::Start).await
     }
 }
 
@@ -173,10 +181,7 @@ pub struct MoveToBottom;
 #[async_trait(?Send)]
 impl Executable for MoveToBottom {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        let line_count = ctx.editor.buffer_manager.current_buffer().line_count();
-        GoToLine::new(line_count.saturating_sub(1))
-            .execute(ctx)
-            .await
+        seek_to(ctx, SeekPosition::End).await
     }
 }
 
@@ -215,9 +220,11 @@ pub struct MoveToNextWord;
 impl Executable for MoveToNextWord {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         let old_row = ctx.editor.cursor.get_point().row;
-        let buffer = ctx.editor.buffer_manager.current_buffer();
-        let cursor = ctx.editor.cursor.find_next_word(buffer);
-        if cursor.get_point().row != old_row && ctx.config.gutter == Gutter::Relative {
+        let document = ctx.editor.buffer_manager.current();
+        let keywords = ctx.config.keyword_chars(document.language);
+        let buffer = &document.buffer;
+        let cursor = ctx.editor.cursor.find_next_word(buffer, &keywords);
+        if cursor.get_point().row != old_row && ctx.config.gutter.is_relative() {
             ctx.ui.compositor
                 .mark_dirty(EDITOR_VIEW)?;
         }
@@ -241,9 +248,11 @@ pub struct MoveToPreviousWord;
 impl Executable for MoveToPreviousWord {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         let old_row = ctx.editor.cursor.get_point().row;
-        let buffer = ctx.editor.buffer_manager.current_buffer();
-        let cursor = ctx.editor.cursor.find_previous_word(buffer);
-        if cursor.get_point().row != old_row && ctx.config.gutter == Gutter::Relative {
+        let document = ctx.editor.buffer_manager.current();
+        let keywords = ctx.config.keyword_chars(document.language);
+        let buffer = &document.buffer;
+        let cursor = ctx.editor.cursor.find_previous_word(buffer, &keywords);
+        if cursor.get_point().row != old_row && ctx.config.gutter.is_relative() {
             ctx.ui.compositor
                 .mark_dirty(EDITOR_VIEW)?;
         }
@@ -260,6 +269,92 @@ impl_action!(
     ActionDefinition::MoveToPreviousWord
 );
 
+fn move_cursor_to(ctx: &mut ActionContext<'_>, point: Point) -> ActionResult {
+    let old_row = ctx.editor.cursor.get_point().row;
+    let buffer = ctx.editor.buffer_manager.current_buffer();
+    ctx.editor.cursor.set_point(point, buffer);
+    ctx.editor.cursor.clamp_column(buffer, ctx.editor.mode);
+    if point.row != old_row && ctx.config.gutter.is_relative() {
+        ctx.ui.compositor
+            .mark_dirty(EDITOR_VIEW)?;
+    }
+    ctx.ui.compositor
+        .mark_dirty(STATUS_LINE)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveToNextParagraph;
+
+#[async_trait(?Send)]
+impl Executable for MoveToNextParagraph {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let point = buffer.next_paragraph_start(&ctx.editor.cursor.get_point());
+        move_cursor_to(ctx, point)
+    }
+}
+
+impl_action!(
+    MoveToNextParagraph,
+    "Move to next paragraph",
+    ActionDefinition::MoveToNextParagraph
+);
+
+#[derive(Debug, Clone)]
+pub struct MoveToPreviousParagraph;
+
+#[async_trait(?Send)]
+impl Executable for MoveToPreviousParagraph {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let point = buffer.previous_paragraph_start(&ctx.editor.cursor.get_point());
+        move_cursor_to(ctx, point)
+    }
+}
+
+impl_action!(
+    MoveToPreviousParagraph,
+    "Move to previous paragraph",
+    ActionDefinition::MoveToPreviousParagraph
+);
+
+#[derive(Debug, Clone)]
+pub struct MoveToNextSentence;
+
+#[async_trait(?Send)]
+impl Executable for MoveToNextSentence {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let point = buffer.next_sentence_start(&ctx.editor.cursor.get_point());
+        move_cursor_to(ctx, point)
+    }
+}
+
+impl_action!(
+    MoveToNextSentence,
+    "Move to next sentence",
+    ActionDefinition::MoveToNextSentence
+);
+
+#[derive(Debug, Clone)]
+pub struct MoveToPreviousSentence;
+
+#[async_trait(?Send)]
+impl Executable for MoveToPreviousSentence {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let point = buffer.previous_sentence_start(&ctx.editor.cursor.get_point());
+        move_cursor_to(ctx, point)
+    }
+}
+
+impl_action!(
+    MoveToPreviousSentence,
+    "Move to previous sentence",
+    ActionDefinition::MoveToPreviousSentence
+);
+
 #[derive(Debug, Clone)]
 pub struct GoToLine {
     line_number: usize,
@@ -274,46 +369,663 @@ impl GoToLine {
 #[async_trait(?Send)]
 impl Executable for GoToLine {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        let old_line = ctx.editor.cursor.get_point().row;
-        let buffer = ctx.editor.buffer_manager.current_buffer();
-        ctx.editor.cursor.go_to_line(self.line_number, buffer, ctx.editor.mode);
-        let new_line = ctx.editor.cursor.get_point().row;
-        let viewport = &ctx.editor.viewport;
-        if new_line < viewport.top_line() || new_line >= viewport.top_line() + viewport.height() {
-            MoveToViewportCenter.execute(ctx).await?;
-        } else if old_line != new_line && ctx.config.gutter == Gutter::Relative {
-            ctx.ui.compositor
-                .mark_dirty(EDITOR_VIEW)?;
+        let old_point = ctx.editor.cursor.get_point();
+        go_to_line(ctx, self.line_number).await?;
+        if ctx.editor.cursor.get_point().row != old_point.row {
+            record_jump(ctx, old_point);
         }
-        ctx.ui.compositor
-            .mark_dirty(STATUS_LINE)?;
         Ok(())
     }
 }
 
+/// Goes to the first non-blank of the line, centering it when it's out of view
+async fn go_to_line(ctx: &mut ActionContext<'_>, line_number: usize) -> ActionResult {
+    let old_line = ctx.editor.cursor.get_point().row;
+    let buffer = ctx.editor.buffer_manager.current_buffer();
+    ctx.editor.cursor.go_to_line(line_number, buffer, ctx.editor.mode);
+    ctx.editor.cursor.move_to_first_non_blank(buffer);
+    let new_line = ctx.editor.cursor.get_point().row;
+    let viewport = &ctx.editor.viewport;
+    if new_line < viewport.top_line() || new_line >= viewport.top_line() + viewport.height() {
+        MoveToViewportCenter.execute(ctx).await?;
+    } else if old_line != new_line && ctx.config.gutter.is_relative() {
+        ctx.ui.compositor
+            .mark_dirty(EDITOR_VIEW)?;
+    }
+    ctx.ui.compositor
+        .mark_dirty(STATUS_LINE)?;
+    Ok(())
+}
+
+/// Remembers the position a jump left, for `''` and `Ctrl-o`
+fn record_jump(ctx: &mut ActionContext<'_>, point: Point) {
+    let buffers = &mut *ctx.editor.buffer_manager;
+    buffers.current_mut().marks.set(JUMP_MARK, point);
+    let buffer = buffers.current_index();
+    buffers.jumps.push(Jump { buffer, point });
+}
+
 impl_action!(GoToLine, "Go to line", self {
     ActionDefinition::GoToLine { line_number: self.line_number }
 });
 
+/// Moves the cursor by a number of lines relative to the current one
+#[derive(Debug, Clone)]
+pub struct GoToRelativeLine {
+    offset: isize,
+}
+
+impl GoToRelativeLine {
+    pub fn new(offset: isize) -> Self {
+        Self { offset }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for GoToRelativeLine {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let row = ctx.editor.cursor.get_point().row;
+        GoToLine::new(row.saturating_add_signed(self.offset))
+            .execute(ctx)
+            .await
+    }
+}
+
+/// Loads the part of the document containing the position if needed and moves the cursor there
+async fn seek_to(ctx: &mut ActionContext<'_>, position: SeekPosition) -> ActionResult {
+    let document = ctx.editor.buffer_manager.current_mut();
+    match document.backend.seek(&mut document.buffer, position) {
+        Ok(SeekResult { row, reloaded }) => {
+            if reloaded {
+                ctx.ui.compositor.mark_all_dirty();
+            }
+            GoToLine::new(row).execute(ctx).await
+        }
+        Err(error) => {
+            system::ShowMessage(Message::error(error.to_string()))
+                .execute(ctx)
+                .await
+        }
+    }
+}
+
+/// Goes to an absolute line of the file, which may be outside the loaded part of it
+#[derive(Debug, Clone)]
+pub struct SeekToLine {
+    line_number: usize,
+}
+
+impl SeekToLine {
+    pub fn new(line_number: usize) -> Self {
+        Self { line_number }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SeekToLine {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        seek_to(ctx, SeekPosition::Line(self.line_number)).await
+    }
+}
+
+/// Goes to a position given as a percentage of the file
+#[derive(Debug, Clone)]
+pub struct SeekToPercent {
+    percent: usize,
+}
+
+impl SeekToPercent {
+    pub fn new(percent: usize) -> Self {
+        Self { percent }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SeekToPercent {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        seek_to(ctx, SeekPosition::Percent(self.percent)).await
+    }
+}
+
+/// Goes to a screen column of the current line, clamped at the line end. A tab
+/// or a wide character covers all the columns it's drawn in
+#[derive(Debug, Clone)]
+pub struct GoToColumn {
+    column: usize,
+}
+
+impl GoToColumn {
+    pub fn new(column: usize) -> Self {
+        Self { column }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for GoToColumn {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let layout = ctx.config.layout();
+        ctx.editor.cursor.go_to_display_column(self.column, buffer, ctx.editor.mode, layout);
+        center_column(ctx)?;
+        ctx.ui.compositor
+            .mark_dirty(STATUS_LINE)?;
+        Ok(())
+    }
+}
+
+/// Scrolls a cursor column that's out of view to the middle, as going to a line
+/// centers it
+fn center_column(ctx: &mut ActionContext<'_>) -> ActionResult {
+    if ctx.config.wrap {
+        return Ok(());
+    }
+    let document = ctx.editor.buffer_manager.current();
+    let (row, column) = ctx.editor.cursor.get_display_cursor();
+    let line = document.buffer.get_line_as_string(row);
+    let column = display_column(&line, column, ctx.config.layout());
+    let content_width = ctx.editor.viewport.content_width(ctx.config.gutter_width(document));
+    let left = ctx.editor.viewport.left_column();
+    ctx.editor.viewport.center_on_column(column, content_width);
+    if ctx.editor.viewport.left_column() != left {
+        ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
+    }
+    Ok(())
+}
+
+impl_action!(GoToColumn, "Go to column", self {
+    ActionDefinition::GoToColumn { column: self.column }
+});
+
+/// Goes to a byte offset of the buffer, landing on the character containing it
+#[derive(Debug, Clone)]
+pub struct GoToByte {
+    offset: usize,
+}
+
+impl GoToByte {
+    pub fn new(offset: usize) -> Self {
+        Self { offset }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for GoToByte {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let offset = self.offset.min(buffer.len_bytes().saturating_sub(1));
+        let point = buffer.point_at_position(offset);
+        let column = buffer
+            .get_line_as_string(point.row)
+            .char_indices()
+            .take_while(|(index, _)| *index <= point.column)
+            .count()
+            .saturating_sub(1);
+        GoToPosition::new(point.row, column).execute(ctx).await?;
+        center_column(ctx)
+    }
+}
+
+impl_action!(GoToByte, "Go to byte", self {
+    ActionDefinition::GoToByte { offset: self.offset }
+});
+
+/// Goes to the matching bracket, or with a count to the line that percent of the way
+/// through the file as in vim
+#[derive(Debug, Clone)]
+pub struct MoveToMatchingBracket {
+    percent: usize,
+}
+
+impl MoveToMatchingBracket {
+    pub fn new(percent: usize) -> Self {
+        Self { percent }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for MoveToMatchingBracket {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        if self.percent > 0 {
+            let line = (self.percent * buffer.line_count()).div_ceil(100);
+            return GoToLine::new(line.saturating_sub(1)).execute(ctx).await;
+        }
+        match buffer.matching_bracket(&ctx.editor.cursor.get_point()) {
+            Some(point) => move_cursor_to(ctx, point),
+            None => Ok(()),
+        }
+    }
+}
+
+impl_action!(MoveToMatchingBracket, "Move to matching bracket", self {
+    ActionDefinition::MoveToMatchingBracket { percent: self.percent }
+});
+
+/// Goes to the start of a text object, which operators use as their range instead
+#[derive(Debug, Clone)]
+pub struct MoveToTextObject {
+    object: char,
+    inner: bool,
+}
+
+impl MoveToTextObject {
+    pub fn new(object: char, inner: bool) -> Self {
+        Self { object, inner }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for MoveToTextObject {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let keywords = ctx.config.keyword_chars(document.language);
+        let cursor = ctx.editor.cursor.get_point();
+        match document.buffer.text_object(&cursor, self.object, self.inner, &keywords) {
+            Some((start, _)) => move_cursor_to(ctx, start),
+            None => Ok(()),
+        }
+    }
+}
+
+impl_action!(MoveToTextObject, "Move to text object", self {
+    ActionDefinition::TextObject { object: self.object, inner: self.inner }
+});
+
+/// Goes to a row and character column, clamped into the text
 #[derive(Debug, Clone)]
 pub struct GoToPosition {
     row: usize,
     column: usize,
+    /// Shows an error instead of clamping a position outside the text
+    checked: bool,
 }
 
 impl GoToPosition {
     pub fn new(row: usize, column: usize) -> Self {
-        Self { row, column }
+        Self { row, column, checked: false }
+    }
+
+    /// For positions from a language server, which should be inside the text, with the
+    /// column in the units of its positions
+    pub fn checked(position: Position) -> Self {
+        Self {
+            row: position.line as usize,
+            column: position.character as usize,
+            checked: true,
+        }
     }
 }
+
 #[async_trait(?Send)]
 impl Executable for GoToPosition {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        GoToLine::new(self.row).execute(ctx).await?;
+        let mut column = self.column;
+        if self.checked {
+            let buffer = ctx.editor.buffer_manager.current_buffer();
+            let line = buffer.get_line_as_string(self.row);
+            let encoding = ctx.lsp_service.position_encoding();
+            let point = Point::new(self.row, encoding.byte_column(&line, self.column as u32));
+            if let Err(e) = buffer.checked_cursor_position(&point) {
+                let message = Message::error(format!("E: {e}"));
+                return system::ShowMessage(message).execute(ctx).await;
+            }
+            column = line[..point.column].chars().count();
+        }
+        let old_point = ctx.editor.cursor.get_point();
+        go_to_line(ctx, self.row).await?;
         let buffer = ctx.editor.buffer_manager.current_buffer();
-        ctx.editor.cursor.go_to_column(self.column, buffer, ctx.editor.mode);
+        ctx.editor.cursor.go_to_column(column, buffer, ctx.editor.mode);
+        // A language server position may be in another file, where the jump was
+        // recorded before opening it
+        if !self.checked && ctx.editor.cursor.get_point() != old_point {
+            record_jump(ctx, old_point);
+        }
         ctx.ui.compositor
             .mark_dirty(STATUS_LINE)?;
         Ok(())
     }
 }
+
+/// `m` and a letter, saving the cursor position in the document
+#[derive(Debug, Clone)]
+pub struct SetMark {
+    name: char,
+}
+
+impl SetMark {
+    pub fn new(name: char) -> Self {
+        Self { name }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SetMark {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let point = ctx.editor.cursor.get_point();
+        ctx.editor.buffer_manager.current_mut().marks.set(self.name, point);
+        Ok(())
+    }
+}
+
+impl_action!(SetMark, "Set mark", self {
+    ActionDefinition::SetMark { name: self.name }
+});
+
+/// Goes to the first non-blank of the line of a mark, or with `exact` to its position
+#[derive(Debug, Clone)]
+pub struct GoToMark {
+    name: char,
+    exact: bool,
+}
+
+impl GoToMark {
+    pub fn new(name: char, exact: bool) -> Self {
+        Self { name, exact }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for GoToMark {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let Some(point) = document.marks.get(self.name) else {
+            let message = Message::error("E: Mark not set".to_string());
+            return system::ShowMessage(message).execute(ctx).await;
+        };
+        if !self.exact {
+            return GoToLine::new(point.row).execute(ctx).await;
+        }
+        let line = document.buffer.get_line_as_string(point.row);
+        let column = line
+            .char_indices()
+            .take_while(|(index, _)| *index < point.column)
+            .count();
+        GoToPosition::new(point.row, column).execute(ctx).await
+    }
+}
+
+impl_action!(GoToMark, "Go to mark", self {
+    ActionDefinition::GoToMark { name: self.name, exact: self.exact }
+});
+
+/// Records a jump from the cursor, for jumps that open another file first
+#[derive(Debug, Clone)]
+pub struct RecordJump;
+
+#[async_trait(?Send)]
+impl Executable for RecordJump {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        record_jump(ctx, ctx.editor.cursor.get_point());
+        Ok(())
+    }
+}
+
+/// `Ctrl-o` and `Ctrl-i`, going back to the position a jump left or forward again,
+/// switching to its buffer. A line that's gone by now is clamped
+#[derive(Debug, Clone)]
+pub struct WalkJumps {
+    newer: bool,
+}
+
+impl WalkJumps {
+    pub fn new(newer: bool) -> Self {
+        Self { newer }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for WalkJumps {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffers = &mut *ctx.editor.buffer_manager;
+        let current = Jump {
+            buffer: buffers.current_index(),
+            point: ctx.editor.cursor.get_point(),
+        };
+        let jump = match self.newer {
+            true => buffers.jumps.newer(),
+            false => buffers.jumps.older(current),
+        };
+        let Some(Jump { buffer, point }) = jump else {
+            return Ok(());
+        };
+        if buffer != current.buffer {
+            buffers.switch_to(buffer)?;
+            after_buffer_change(ctx).await?;
+        }
+        go_to_line(ctx, point.row).await?;
+        let row = ctx.editor.cursor.get_point().row;
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        ctx.editor.cursor.set_point(Point::new(row, point.column), buffer);
+        ctx.editor.cursor.clamp_column(buffer, ctx.editor.mode);
+        Ok(())
+    }
+}
+
+impl_action!(WalkJumps, "Walk the jump list", self {
+    match self.newer {
+        true => ActionDefinition::JumpForward,
+        false => ActionDefinition::JumpBack,
+    }
+});
+
+/// Follows the line under the cursor: from a diff view to the line of the buffer it
+/// belongs to, from a symbol or reference list to the place listed, or from a list of
+/// code actions to applying the one on the line. Elsewhere moves to the first non-blank
+/// of the next line, like vim's Enter
+#[derive(Debug, Clone)]
+pub struct FollowLine;
+
+#[async_trait(?Send)]
+impl Executable for FollowLine {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let row = ctx.editor.cursor.get_point().row;
+        let target = ctx
+            .editor
+            .buffer_manager
+            .current()
+            .diff
+            .as_ref()
+            .and_then(|diff| Some((diff.source.clone(), diff.row(row)?.target)));
+        let Some((source, line)) = target else {
+            let document = ctx.editor.buffer_manager.current();
+            if let Some(symbol) = document.symbols.as_ref().and_then(|symbols| symbols.get(row)) {
+                return go_to_symbol(ctx, &symbol.clone()).await;
+            }
+            let location = document.locations.as_ref().and_then(|locations| locations.get(row));
+            if let Some((path, position)) = location.cloned() {
+                OpenBuffer::new(path).execute(ctx).await?;
+                return GoToPosition::checked(position).execute(ctx).await;
+            }
+            if let Some(action) = document.code_actions.as_ref().and_then(|actions| actions.get(row)) {
+                return ApplyCodeAction::new(action.clone()).execute(ctx).await;
+            }
+            return GoToRelativeLine::new(1).execute(ctx).await;
+        };
+
+        OpenBuffer::new(source).execute(ctx).await?;
+        GoToLine::new(line).execute(ctx).await
+    }
+}
+
+impl_action!(FollowLine, "Follow the line under the cursor", ActionDefinition::FollowLine);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    #[tokio::test]
+    async fn test_go_to_column_and_byte() {
+        // Keys, expected cursor and expected text
+        const CASES: [(&str, (usize, usize), &str); 7] = [
+            ("4|", (0, 3), "abcdef\nhéllo\n"),
+            ("$|", (0, 0), "abcdef\nhéllo\n"),
+            ("100|", (0, 5), "abcdef\nhéllo\n"),
+            ("10go", (1, 1), "abcdef\nhéllo\n"),
+            ("9go", (1, 1), "abcdef\nhéllo\n"),
+            ("1000go", (1, 4), "abcdef\nhéllo\n"),
+            ("$d3|", (0, 2), "abf\nhéllo\n"),
+        ];
+
+        for (keys, cursor, text) in CASES {
+            let mut editor = TestEditor::new("abcdef\nhéllo\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+            assert_eq!(editor.text(), text, "{keys}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_go_to_screen_column() {
+        // Keys and expected cursor
+        const CASES: [(&str, (usize, usize)); 6] = [
+            ("3|", (0, 0)),
+            ("5|", (0, 1)),
+            ("j2|", (1, 0)),
+            ("j3|", (1, 1)),
+            ("j4|", (1, 1)),
+            ("j5|", (1, 2)),
+        ];
+        for (keys, cursor) in CASES {
+            let mut editor = TestEditor::new("\tab\n漢字x\n");
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+        }
+
+        // A column out of view is scrolled to the middle
+        let mut editor = TestEditor::new(&format!("{}\n", "x".repeat(300)));
+        editor.keys("200|").await.unwrap();
+        let document = editor.core.current_document();
+        let width = editor.core.viewport.content_width(editor.config.gutter_width(document));
+        assert_eq!(editor.core.viewport.left_column(), 199 - width / 2);
+        editor.keys("1go").await.unwrap();
+        assert_eq!(editor.core.viewport.left_column(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_paragraph_and_bracket_motions() {
+        const TEXT: &str = "fn f(a, [b]) {\n    g();\n}\n\nnext\n";
+        // Keys, expected cursor and expected text
+        const CASES: [(&str, (usize, usize), &str); 13] = [
+            ("%", (0, 11), TEXT),
+            ("%%", (0, 4), TEXT),
+            ("$%", (2, 0), TEXT),
+            ("50%", (2, 0), TEXT),
+            ("d%", (0, 0), " {\n    g();\n}\n\nnext\n"),
+            ("jd%", (1, 0), "fn f(a, [b]) {\n;\n}\n\nnext\n"),
+            ("d}", (0, 0), "\nnext\n"),
+            ("jjj2{", (0, 0), TEXT),
+            ("jjw", (3, 0), TEXT),
+            ("jjww", (4, 0), TEXT),
+            ("$b", (0, 10), TEXT),
+            ("jllllb", (0, 13), TEXT),
+            ("jjjjb", (2, 0), TEXT),
+        ];
+
+        for (keys, cursor, text) in CASES {
+            let mut editor = TestEditor::new(TEXT);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+            assert_eq!(editor.text(), text, "{keys}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_go_to_line_number() {
+        let text = (1..=100).map(|line| format!("  {line}\n")).collect::<String>();
+        // Keys and expected cursor, on the first non-blank of the line
+        const CASES: [(&str, (usize, usize)); 5] = [
+            ("3G", (2, 2)),
+            ("3gg", (2, 2)),
+            (":4<Enter>", (3, 2)),
+            ("999G", (100, 0)),
+            (":999<Enter>", (100, 0)),
+        ];
+        for (keys, cursor) in CASES {
+            let mut editor = TestEditor::new(&text);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+        }
+
+        // A line out of view is centered
+        let mut editor = TestEditor::new(&text);
+        editor.keys("40G").await.unwrap();
+        let viewport = &editor.core.viewport;
+        assert_eq!(viewport.top_line(), 39 - viewport.height() / 2);
+    }
+
+    #[tokio::test]
+    async fn test_marks() {
+        const TEXT: &str = "one\n  two\nthree\nfour\n";
+        // Keys, expected cursor and expected text
+        const CASES: [(&str, (usize, usize), &str); 7] = [
+            ("jlllmaG'a", (1, 2), TEXT),
+            ("jlllmaG`a", (1, 3), TEXT),
+            ("3G''", (0, 0), TEXT),
+            ("3G''''", (2, 0), TEXT),
+            ("/four<Enter>``", (0, 0), TEXT),
+            ("jlllmaggOnew<Esc>`a", (2, 3), "new\none\n  two\nthree\nfour\n"),
+            ("jjmaggd'a", (0, 0), "four\n"),
+        ];
+        for (keys, cursor, text) in CASES {
+            let mut editor = TestEditor::new(TEXT);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+            assert_eq!(editor.text(), text, "{keys}");
+        }
+
+        // Deleting the marked line drops the mark, and an operator to it does nothing
+        let mut editor = TestEditor::new(TEXT);
+        editor.keys("jmaddggd'a").await.unwrap();
+        assert_eq!(editor.text(), "one\nthree\nfour\n");
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: Mark not set");
+    }
+
+    #[tokio::test]
+    async fn test_jump_list() {
+        const TEXT: &str = "one\n  two\nthree\nfour\n";
+        // Keys and expected cursor
+        const CASES: [(&str, (usize, usize)); 6] = [
+            ("3G/our<Enter><C-o>", (2, 0)),
+            ("3G/our<Enter><C-o><C-o>", (0, 0)),
+            ("3G/our<Enter><C-o><C-o><C-o>", (0, 0)),
+            ("3G/our<Enter><C-o><C-o><Tab><Tab>", (3, 1)),
+            ("3G/our<Enter><C-o><C-o><C-i>", (2, 0)),
+            ("3G/our<Enter><C-o><C-o>2G<C-o><C-i><C-i>", (1, 2)),
+        ];
+        for (keys, cursor) in CASES {
+            let mut editor = TestEditor::new(TEXT);
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_positions_outside_the_text() {
+        let mut editor = TestEditor::new("abc\nhéllo\n");
+
+        // Kept from before the text shrank, the cursor lands on the last line
+        let buffer = &editor.core.buffer_manager.current().buffer;
+        editor.core.cursor.set_point(Point::new(7, 40), buffer);
+        assert_eq!(editor.cursor(), (2, 0));
+        editor.execute(&GoToPosition::new(1, 40)).await.unwrap();
+        assert_eq!(editor.cursor(), (1, 4));
+
+        // Language server positions are reported rather than clamped
+        editor.execute(&GoToPosition::checked(Position::new(0, 2))).await.unwrap();
+        assert_eq!(editor.cursor(), (0, 2));
+        for (row, column, error) in [
+            (0, 5, "E: Column 6 is past the end of line 1"),
+            (9, 0, "E: Line 10 is past the end (3 lines)"),
+        ] {
+            editor.execute(&GoToPosition::checked(Position::new(row, column))).await.unwrap();
+            assert_eq!(editor.cursor(), (0, 2));
+            let message = editor.message_manager.current_message().unwrap();
+            assert_eq!(message.content, error);
+        }
+        // Columns count UTF-16 code units, one for `é`
+        editor.execute(&GoToPosition::checked(Position::new(1, 4))).await.unwrap();
+        assert_eq!(editor.cursor(), (1, 4));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::actions::types::mode;
+use crate::input::default_keymap::default_keymap;
+use crate::core::mode::Mode;
+use crate::input::keys::decode_keys;
+use crate::input::{InputProcessor, get_default_input_action};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::cell::Cell;
+
+/// Deepest nesting of `:normal` commands run by keys of another `:normal`,
+/// which stops keys that run themselves
+const MAX_NORMAL_DEPTH: usize = 20;
+
+thread_local! {
+    static NORMAL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs keys as if typed in normal mode, as `:normal {keys}` does
+#[derive(Debug, Clone)]
+pub struct ExecuteKeys {
+    keys: String,
+    /// Use the user's keymap rather than the default one, false for `:normal!`
+    remap: bool,
+}
+
+impl ExecuteKeys {
+    pub fn new(keys: String, remap: bool) -> Self {
+        Self { keys, remap }
+    }
+
+    async fn feed(&self, ctx: &mut ActionContext<'_>) -> ActionResult {
+        let config = ctx.config;
+        let keymap = if self.remap {
+            &config.keymap
+        } else {
+            default_keymap()
+        };
+
+        if *ctx.editor.mode != Mode::Normal {
+            mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
+        }
+        let mut input = InputProcessor::new();
+        for key in decode_keys(&self.keys) {
+            let action = match get_default_input_action(&key, ctx.editor.mode) {
+                Some(action) => Some(action),
+                None => {
+                    input.add_key(key);
+                    input.get_executable(ctx.editor.mode, keymap)
+                }
+            };
+            let Some(action) = action else {
+                continue;
+            };
+            action.execute(ctx).await?;
+            if input.is_empty() && matches!(ctx.editor.mode, Mode::OperationPending(_)) {
+                mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
+            }
+        }
+
+        // Like vim, an unfinished command is dropped and the keys end in normal mode
+        if *ctx.editor.mode != Mode::Normal {
+            mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ExecuteKeys {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let depth = NORMAL_DEPTH.get();
+        if depth >= MAX_NORMAL_DEPTH {
+            return Err(anyhow!("Too many nested :normal commands"));
+        }
+        NORMAL_DEPTH.set(depth + 1);
+        let result = self.feed(ctx).await;
+        NORMAL_DEPTH.set(depth);
+        result
+    }
+}
@@ -0,0 +1,135 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::actions::types::buffer::OpenBuffer;
+use crate::actions::types::movement::GoToPosition;
+use crate::actions::types::system;
+use crate::constants::components::QUICKFIX;
+use crate::core::message::Message;
+use async_trait::async_trait;
+
+/// Lists the diagnostics the language server has again, after they changed
+pub(super) fn update_quickfix(ctx: &mut ActionContext<'_>) -> ActionResult {
+    ctx.editor.quickfix.update(ctx.lsp_service.all_diagnostics());
+    if ctx.editor.quickfix.open {
+        ctx.ui.compositor.mark_dirty(QUICKFIX)?;
+    }
+    Ok(())
+}
+
+/// Opens or closes the panel, giving its rows to the text or taking them back
+fn show_quickfix(ctx: &mut ActionContext<'_>, open: bool) -> ActionResult {
+    if ctx.editor.quickfix.open == open {
+        return Ok(());
+    }
+    ctx.editor.quickfix.open = open;
+    let (width, height) = ctx.ui.compositor.size();
+    ctx.editor.viewport.resize(width, ctx.editor.quickfix.editor_height(height));
+    ctx.ui.compositor.mark_visible(QUICKFIX, open)?;
+    ctx.ui.compositor.mark_all_dirty();
+    Ok(())
+}
+
+/// Opens the quickfix panel, as `:copen` does
+#[derive(Debug, Clone)]
+pub struct OpenQuickfix;
+
+#[async_trait(?Send)]
+impl Executable for OpenQuickfix {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        show_quickfix(ctx, true)
+    }
+}
+
+/// Closes the quickfix panel, as `:cclose` does
+#[derive(Debug, Clone)]
+pub struct CloseQuickfix;
+
+#[async_trait(?Send)]
+impl Executable for CloseQuickfix {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        show_quickfix(ctx, false)
+    }
+}
+
+/// Goes to the quickfix entry the offset away from the last one gone to, opening its
+/// file if needed, as `:cnext` and `:cprev` do. The list doesn't wrap around
+#[derive(Debug, Clone)]
+pub struct GoToQuickfixEntry {
+    offset: isize,
+}
+
+impl GoToQuickfixEntry {
+    pub fn new(offset: isize) -> Self {
+        Self { offset }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for GoToQuickfixEntry {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let quickfix = &mut *ctx.editor.quickfix;
+        let count = quickfix.entries.len();
+        if count == 0 {
+            return system::ShowMessage(Message::error("E42: No Errors".to_string())).execute(ctx).await;
+        }
+        let Some(entry) = quickfix.select(self.offset).cloned() else {
+            return system::ShowMessage(Message::error("E553: No more items".to_string())).execute(ctx).await;
+        };
+        let index = quickfix.selected.unwrap_or_default();
+        if quickfix.open {
+            ctx.ui.compositor.mark_dirty(QUICKFIX)?;
+        }
+
+        OpenBuffer::new(entry.path).execute(ctx).await?;
+        GoToPosition::checked(entry.position).execute(ctx).await?;
+        let message = format!("({} of {count}): {}", index + 1, entry.message);
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::lsp::UpdateDiagnostics;
+    use crate::editor::testing::TestEditor;
+    use lsp_types::{Diagnostic, Position, Range};
+
+    #[tokio::test]
+    async fn test_go_through_entries() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-quickfix", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.rs"), dir.join("b.rs"));
+        std::fs::write(&a, "fn a() {}\n").unwrap();
+        std::fs::write(&b, "fn b() {}\nfn c() {}\n").unwrap();
+        let diagnostic = |line: u32, message: &str| Diagnostic {
+            range: Range {
+                start: Position { line, character: 3 },
+                end: Position { line, character: 4 },
+            },
+            message: message.to_string(),
+            ..Default::default()
+        };
+        let update = |path: &std::path::Path, diagnostics| {
+            UpdateDiagnostics::new(Some(format!("file://{}", path.display())), diagnostics)
+        };
+
+        let mut editor = TestEditor::new("");
+        editor.execute(&update(&b, vec![diagnostic(1, "c unused"), diagnostic(0, "b unused")])).await.unwrap();
+        editor.execute(&update(&a, vec![diagnostic(0, "a unused")])).await.unwrap();
+        editor.keys(":cprev<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path.as_ref(), Some(&b));
+        assert_eq!(editor.cursor(), (1, 3));
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "(3 of 3): c unused");
+
+        // New diagnostics keep the selection on its entry
+        editor.execute(&update(&a, vec![])).await.unwrap();
+        assert_eq!(editor.core.quickfix.selected, Some(1));
+        editor.keys(":cnext<Enter>").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E553: No more items");
+        editor.keys(":cprev<Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 3));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,10 +2,20 @@ use crate::actions::ActionResult;
 use crate::actions::context::ActionContext;
 use crate::actions::core::{ActionDefinition, Executable, impl_action};
 use crate::actions::types::{mode, movement, system};
-use crate::constants::components::SEARCH_BOX;
+use crate::constants::components::{BOTTOM_BAR, EDITOR_VIEW};
+use crate::core::command::SearchOffset;
 use crate::core::message::Message;
 use crate::core::mode::Mode;
 use async_trait::async_trait;
+use tree_sitter::Point;
+
+/// Highlights the matches of the pattern as it's typed
+fn pattern_changed(ctx: &mut ActionContext) -> ActionResult {
+    ctx.input.search_buffer.update_pattern(ctx.config.smart_case.0);
+    ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
+    ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+    Ok(())
+}
 
 #[derive(Debug, Clone)]
 pub struct SearchMoveLeft;
@@ -56,8 +66,7 @@ impl SearchInsertChar {
 impl Executable for SearchInsertChar {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         ctx.input.search_buffer.buffer.insert_char(self.ch);
-        ctx.ui.compositor.mark_dirty(SEARCH_BOX)?;
-        Ok(())
+        pattern_changed(ctx)
     }
 }
 
@@ -68,10 +77,9 @@ pub struct SearchDeleteChar;
 impl Executable for SearchDeleteChar {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         if !ctx.input.search_buffer.buffer.delete_char() {
-            mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
+            return mode::EnterMode::new(Mode::Normal).execute(ctx).await;
         }
-        ctx.ui.compositor.mark_dirty(SEARCH_BOX)?;
-        Ok(())
+        pattern_changed(ctx)
     }
 }
 
@@ -87,10 +95,9 @@ pub struct SearchBackspace;
 impl Executable for SearchBackspace {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
         if !ctx.input.search_buffer.buffer.backspace() {
-            mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
+            return mode::EnterMode::new(Mode::Normal).execute(ctx).await;
         }
-        ctx.ui.compositor.mark_dirty(SEARCH_BOX)?;
-        Ok(())
+        pattern_changed(ctx)
     }
 }
 
@@ -100,13 +107,59 @@ impl_action!(
     ActionDefinition::SearchBackspace
 );
 
+/// Up in search mode, recalling the previous search
+#[derive(Debug, Clone)]
+pub struct SearchHistoryOlder;
+
+#[async_trait(?Send)]
+impl Executable for SearchHistoryOlder {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        ctx.input.search_buffer.buffer.recall_older();
+        pattern_changed(ctx)
+    }
+}
+
+impl_action!(
+    SearchHistoryOlder,
+    "Recall the previous search",
+    ActionDefinition::SearchHistoryOlder
+);
+
+/// Down in search mode, recalling the next search or what was typed
+#[derive(Debug, Clone)]
+pub struct SearchHistoryNewer;
+
+#[async_trait(?Send)]
+impl Executable for SearchHistoryNewer {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        ctx.input.search_buffer.buffer.recall_newer();
+        pattern_changed(ctx)
+    }
+}
+
+impl_action!(
+    SearchHistoryNewer,
+    "Recall the next search",
+    ActionDefinition::SearchHistoryNewer
+);
+
 #[derive(Debug, Clone)]
 pub struct SearchSubmit;
 
 #[async_trait(?Send)]
 impl Executable for SearchSubmit {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        let pattern = ctx.input.search_buffer.buffer.content();
+        let input = ctx.input.search_buffer.buffer.content();
+        // Closed first, so an error shows instead of waiting for the prompt to close
+        mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
+        let (pattern, offset) = match SearchOffset::split(&input) {
+            Ok(split) => split,
+            Err(e) => {
+                return system::ShowMessage(Message::error(format!("E: {e}")))
+                    .execute(ctx)
+                    .await;
+            }
+        };
 
         if pattern.is_empty() {
             return system::ShowMessage(Message::error(
@@ -115,27 +168,37 @@ impl Executable for SearchSubmit {
             .execute(ctx)
             .await;
         }
+        let limit = ctx.config.history.0;
+        ctx.input.search_buffer.buffer.push_history(input.clone(), limit);
         let result = ctx
             .input
             .search_buffer
-            .search(&pattern, &ctx.editor.buffer_manager.current_buffer());
+            .search(
+                pattern,
+                offset,
+                ctx.editor.buffer_manager.current_buffer(),
+                ctx.config.smart_case.0,
+            );
         if let Err(e) = result {
-            system::ShowMessage(Message::error(format!("E: {e}")))
+            return system::ShowMessage(Message::error(format!("E: {e}")))
                 .execute(ctx)
-                .await?;
+                .await;
         }
         if let Some(point) = ctx
             .input
             .search_buffer
-            .find_first(&ctx.editor.cursor.get_point())
+            .find_first(
+                &ctx.editor.cursor.get_point(),
+                ctx.editor.buffer_manager.current_buffer(),
+            )
         {
             movement::GoToPosition::new(point.row, point.column)
                 .execute(ctx)
                 .await?;
         }
-        mode::EnterMode::new(Mode::Normal).execute(ctx).await?;
-        ctx.ui.compositor.mark_visible(SEARCH_BOX, true)?;
-        ctx.ui.compositor.mark_dirty(SEARCH_BOX)?;
+        ctx.message.show_search_result();
+        ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+        ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
         Ok(())
     }
 }
@@ -152,22 +215,33 @@ pub struct FindNext;
 #[async_trait(?Send)]
 impl Executable for FindNext {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let smart_case = ctx.config.smart_case.0;
+        if let Err(e) = ctx.input.search_buffer.search_again(buffer, smart_case) {
+            return system::ShowMessage(Message::error(format!("E: {e}")))
+                .execute(ctx)
+                .await;
+        }
         if let Some(point) = ctx
             .input
             .search_buffer
-            .find_next(&ctx.editor.cursor.get_point())
+            .find_next(
+                &ctx.editor.cursor.get_point(),
+                ctx.editor.buffer_manager.current_buffer(),
+            )
         {
             movement::GoToPosition::new(point.row, point.column)
                 .execute(ctx)
                 .await?;
         }
-        ctx.ui.compositor.mark_visible(SEARCH_BOX, true)?;
-        ctx.ui.compositor.mark_dirty(SEARCH_BOX)?;
+        ctx.message.show_search_result();
+        ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+        ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
         Ok(())
     }
 }
 
-impl_action!(FindNext, "Find next match", ActionDefinition::FindNext);
+impl_action!(FindNext, "Find next match", ActionDefinition::SearchNext);
 
 #[derive(Debug, Clone)]
 pub struct FindPrevious;
@@ -175,17 +249,28 @@ pub struct FindPrevious;
 #[async_trait(?Send)]
 impl Executable for FindPrevious {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let smart_case = ctx.config.smart_case.0;
+        if let Err(e) = ctx.input.search_buffer.search_again(buffer, smart_case) {
+            return system::ShowMessage(Message::error(format!("E: {e}")))
+                .execute(ctx)
+                .await;
+        }
         if let Some(point) = ctx
             .input
             .search_buffer
-            .find_previous(&ctx.editor.cursor.get_point())
+            .find_previous(
+                &ctx.editor.cursor.get_point(),
+                ctx.editor.buffer_manager.current_buffer(),
+            )
         {
             movement::GoToPosition::new(point.row, point.column)
                 .execute(ctx)
                 .await?;
         }
-        ctx.ui.compositor.mark_visible(SEARCH_BOX, true)?;
-        ctx.ui.compositor.mark_dirty(SEARCH_BOX)?;
+        ctx.message.show_search_result();
+        ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+        ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
         Ok(())
     }
 }
@@ -193,5 +278,195 @@ impl Executable for FindPrevious {
 impl_action!(
     FindPrevious,
     "Find previous match",
-    ActionDefinition::FindPrevious
+    ActionDefinition::SearchPrevious
+);
+
+/// Searches for the word under the cursor, or the next one on the line, as a whole
+/// word, going to the next match after it or the one before it
+async fn search_word(ctx: &mut ActionContext<'_>, backward: bool) -> ActionResult {
+    let point = ctx.editor.cursor.get_point();
+    let document = ctx.editor.buffer_manager.current();
+    let keywords = ctx.config.keyword_chars(document.language);
+    let Some((start, end)) = document.buffer.word_bounds(&point, &keywords) else {
+        let message = "E: No word under the cursor".to_string();
+        return system::ShowMessage(Message::error(message)).execute(ctx).await;
+    };
+    let line = document.buffer.get_line_as_string(point.row);
+    let search = &mut ctx.input.search_buffer;
+    search.search_word(&line[start..end], &keywords, &document.buffer);
+    search.buffer.push_history(search.last_search.clone(), ctx.config.history.0);
+
+    // Matches are in characters, and stepping from the word's start skips it
+    let from = Point {
+        row: point.row,
+        column: line[..start].chars().count(),
+    };
+    let found = match backward {
+        true => search.find_previous(&from, &document.buffer),
+        false => search.find_next(&from, &document.buffer),
+    };
+    if let Some(point) = found {
+        movement::GoToPosition::new(point.row, point.column)
+            .execute(ctx)
+            .await?;
+    }
+    ctx.message.show_search_result();
+    ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+    ctx.ui.compositor.mark_dirty(EDITOR_VIEW)?;
+    Ok(())
+}
+
+/// `*`
+#[derive(Debug, Clone)]
+pub struct SearchWordForward;
+
+#[async_trait(?Send)]
+impl Executable for SearchWordForward {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        search_word(ctx, false).await
+    }
+}
+
+impl_action!(
+    SearchWordForward,
+    "Search forward for the word under the cursor",
+    ActionDefinition::SearchWordForward
+);
+
+/// `#`
+#[derive(Debug, Clone)]
+pub struct SearchWordBackward;
+
+#[async_trait(?Send)]
+impl Executable for SearchWordBackward {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        search_word(ctx, true).await
+    }
+}
+
+impl_action!(
+    SearchWordBackward,
+    "Search backward for the word under the cursor",
+    ActionDefinition::SearchWordBackward
 );
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::config::editor::SmartCase;
+    use crate::editor::testing::TestEditor;
+    use std::collections::HashMap;
+
+    async fn cursor_after(keys: &str) -> (usize, usize) {
+        let mut editor = TestEditor::new("x foo bar foo\nnext");
+        editor.keys(keys).await.unwrap();
+        editor.cursor()
+    }
+
+    #[tokio::test]
+    async fn test_search_offset() {
+        assert_eq!(cursor_after("/foo/e<Enter>").await, (0, 4));
+        assert_eq!(cursor_after("/foo/e+1<Enter>").await, (0, 5));
+        assert_eq!(cursor_after("/foo/s-1<Enter>").await, (0, 1));
+        assert_eq!(cursor_after("/foo/+1<Enter>").await, (1, 0));
+        assert_eq!(cursor_after("/foo/+9<Enter>").await, (1, 0));
+        assert_eq!(cursor_after("/foo/s-9<Enter>").await, (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_search_offset_is_kept_for_next() {
+        assert_eq!(cursor_after("/foo/e<Enter>n").await, (0, 12));
+        assert_eq!(cursor_after("/foo/e<Enter>nn").await, (0, 4));
+        assert_eq!(cursor_after("/foo/e<Enter>N").await, (0, 12));
+    }
+
+    #[tokio::test]
+    async fn test_smart_case() {
+        let mut editor = TestEditor::new("x Foo foo(\nfoo( \\S");
+        // Keys and expected cursor
+        let cases = [
+            ("/foo<Enter>", (0, 2)),
+            ("/Foo<Enter>n", (0, 2)),
+            ("/\\V(<Enter>", (0, 9)),
+            ("/\\Vfoo(<Enter>n", (1, 0)),
+            ("/\\S\\S<Enter>", (0, 2)),
+        ];
+        for (keys, cursor) in cases {
+            editor.keys("gg").await.unwrap();
+            editor.keys(keys).await.unwrap();
+            assert_eq!(editor.cursor(), cursor, "{keys}");
+        }
+
+        let config = Config {
+            keymap: editor.config.keymap.clone(),
+            smart_case: SmartCase(false),
+            ..Default::default()
+        };
+        let mut editor = TestEditor::with_config("x Foo foo", config);
+        editor.keys("/foo<Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 6));
+    }
+
+    #[tokio::test]
+    async fn test_word_boundaries_agree() {
+        let text = "café_2 x café_2b\ncafé_2.y café_2";
+        let mut editor = TestEditor::new(text);
+        editor.keys("w").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 7));
+
+        // The longer word isn't a match, the one followed by `.` is
+        editor.keys("gg*").await.unwrap();
+        assert_eq!(editor.cursor(), (1, 0));
+        assert_eq!(editor.search_buffer.last_search, r"\bcafé_2\b");
+        editor.keys("n").await.unwrap();
+        assert_eq!(editor.cursor(), (1, 9));
+        editor.keys("gg#").await.unwrap();
+        assert_eq!(editor.cursor(), (1, 9));
+
+        editor.keys("ggcwx<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "x x café_2b\ncafé_2.y café_2");
+        editor.keys("j$b").await.unwrap();
+        assert_eq!(editor.cursor(), (1, 9));
+    }
+
+    #[tokio::test]
+    async fn test_configured_keyword_chars() {
+        let config = Config {
+            keymap: TestEditor::new("").config.keymap,
+            iskeyword: HashMap::from([("default".to_string(), "-".to_string())]),
+            ..Default::default()
+        };
+        let mut editor = TestEditor::with_config("a-b a a-b-c a-b", config);
+
+        editor.keys("w").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+        editor.keys("gg*").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 12));
+        editor.keys("ggdw").await.unwrap();
+        assert_eq!(editor.text(), "a a-b-c a-b");
+    }
+
+    #[tokio::test]
+    async fn test_search_history() {
+        let mut editor = TestEditor::new("foo bar\nbaz foo\nbar\n");
+        editor.keys("/foo<Enter>/bar<Enter>/bar<Enter>/<Up>").await.unwrap();
+        assert_eq!(editor.search_buffer.buffer.content(), "bar");
+        assert_eq!(editor.search_buffer.buffer.cursor_position(), 3);
+
+        // The repeated search is kept once, and the oldest stays when going past it
+        editor.keys("<Up><Up>").await.unwrap();
+        assert_eq!(editor.search_buffer.buffer.content(), "foo");
+        editor.keys("<Down><Down>ba").await.unwrap();
+        assert_eq!(editor.search_buffer.buffer.content(), "ba");
+        editor.keys("<Up><Up><Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 4));
+
+        // n and N keep searching for it after an edit
+        editor.keys("ggx").await.unwrap();
+        assert!(editor.search_buffer.results.is_empty());
+        editor.keys("n").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 3));
+        editor.keys("N").await.unwrap();
+        assert_eq!(editor.cursor(), (2, 0));
+    }
+}
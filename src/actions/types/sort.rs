@@ -0,0 +1,149 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::Executable;
+use crate::actions::types::editing::{after_edit, ensure_writable};
+use crate::actions::types::global::LineSelection;
+use crate::actions::types::{movement, system};
+use crate::core::history::edit::Edit;
+use crate::core::message::Message;
+use async_trait::async_trait;
+use std::cmp::Ordering;
+use tree_sitter::Point;
+
+/// Flags of `:sort`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    /// `!`, largest first
+    pub reverse: bool,
+    /// `n`, by the first number of each line
+    pub numeric: bool,
+    /// `u`, keeping only the first of lines that compare equal
+    pub unique: bool,
+}
+
+impl SortOptions {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = if self.numeric {
+            first_number(a).cmp(&first_number(b))
+        } else {
+            a.as_bytes().cmp(b.as_bytes())
+        };
+        if self.reverse { ordering.reverse() } else { ordering }
+    }
+
+    /// Sorts the lines keeping the order of equal ones
+    fn sort(&self, lines: &mut Vec<&str>) {
+        lines.sort_by(|a, b| self.compare(a, b));
+        if self.unique {
+            lines.dedup_by(|a, b| self.compare(a, b) == Ordering::Equal);
+        }
+    }
+}
+
+/// Value of the first decimal number in the line, negative when a `-` comes right
+/// before it, as vim's `:sort n` reads it. Lines without one sort first
+fn first_number(line: &str) -> Option<i128> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let digits = &line[start..];
+    let digits = &digits[..digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len())];
+    let value = digits.parse::<i128>().unwrap_or(i128::MAX);
+    Some(if line[..start].ends_with('-') { -value } else { value })
+}
+
+/// Sorts the lines of a range as one undoable edit, as `:sort` does
+#[derive(Debug, Clone)]
+pub struct SortLines {
+    selection: LineSelection,
+    options: SortOptions,
+}
+
+impl SortLines {
+    pub fn new(selection: LineSelection, options: SortOptions) -> Self {
+        Self { selection, options }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for SortLines {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return Ok(());
+        }
+        let cursor_row = ctx.editor.cursor.get_point().row;
+        let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+        let rows = self.selection.rows(buffer, cursor_row);
+        let (Some(&first), Some(&last)) = (rows.first(), rows.last()) else {
+            return Ok(());
+        };
+        if first == last {
+            return Ok(());
+        }
+
+        // The last line of the file may have no newline, and must not gain or lose one
+        let text = buffer.get_lines(first, last);
+        let body = text.strip_suffix('\n').unwrap_or(&text);
+        let mut lines = body.split('\n').collect::<Vec<_>>();
+        let count = lines.len();
+        self.options.sort(&mut lines);
+        let removed = count - lines.len();
+
+        let sorted = lines.join("\n");
+        if sorted != body {
+            let start = Point { row: first, column: 0 };
+            let start_byte = buffer.cursor_position(&start);
+            buffer.delete_string(start_byte, body.len());
+            buffer.insert_string(start_byte, &sorted);
+            let edit = Edit::Group(vec![
+                Edit::delete(start_byte, start, body.to_string(), start, start),
+                Edit::insert(start_byte, start, sorted, start, start),
+            ]);
+            after_edit(ctx, &edit).await?;
+            ctx.editor.buffer_manager.current_mut().history.push(edit);
+        }
+        movement::GoToLine::new(first).execute(ctx).await?;
+
+        let message = match removed {
+            0 => format!("{count} lines sorted"),
+            removed => format!("{count} lines sorted, {removed} fewer lines"),
+        };
+        system::ShowMessage(Message::info(message)).execute(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    #[test]
+    fn test_first_number() {
+        assert_eq!(first_number("item 42 of 50"), Some(42));
+        assert_eq!(first_number("x-7"), Some(-7));
+        assert_eq!(first_number("v1.10"), Some(1));
+        assert_eq!(first_number("007"), Some(7));
+        assert_eq!(first_number("none"), None);
+        assert_eq!(first_number(&"9".repeat(60)), Some(i128::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_sort() {
+        let mut editor = TestEditor::new("b\nB\na\nc\na\n");
+        editor.keys(":sort<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "B\na\na\nb\nc\n");
+        editor.keys(":sort! u<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "c\nb\na\nB\n");
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "5 lines sorted, 1 fewer lines");
+        editor.keys("u").await.unwrap();
+        assert_eq!(editor.text(), "B\na\na\nb\nc\n");
+
+        // Lines with the same number stay in their order, and lines without one go first
+        let mut editor = TestEditor::new("head\nx10 b\nx9\nnone\nx10 a\n-3\nlast");
+        editor.keys(":2,$sort n<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "head\nnone\nlast\n-3\nx9\nx10 b\nx10 a");
+        editor.keys(":5,$sort nu<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "head\nnone\nlast\n-3\nx9\nx10 b");
+    }
+}
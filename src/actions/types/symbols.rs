@@ -0,0 +1,152 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::{ActionDefinition, Executable, impl_action};
+use crate::actions::types::buffer::{OpenBuffer, after_buffer_change};
+use crate::actions::types::movement::GoToPosition;
+use crate::actions::types::system;
+use crate::core::buffer::Buffer;
+use crate::core::buffer::backend::ViewBackend;
+use crate::core::message::Message;
+use crate::service::symbols::Symbol;
+use async_trait::async_trait;
+
+/// Lists the indexed definitions whose name contains the query, as `:symbols` does
+#[derive(Debug, Clone)]
+pub struct ShowSymbols {
+    query: String,
+}
+
+impl ShowSymbols {
+    pub fn new(query: String) -> Self {
+        Self { query }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for ShowSymbols {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let symbols = ctx.symbol_index.search(&self.query);
+        if symbols.is_empty() {
+            let message = match self.query.as_str() {
+                "" => "E: No symbols found".to_string(),
+                query => format!("E: No symbols matching {query}"),
+            };
+            return not_found(ctx, message).await;
+        }
+        show_symbol_list(ctx, symbols).await
+    }
+}
+
+/// Goes to the indexed definition of the word under the cursor, listing them when
+/// there are several. This is what `gd` does without a language server
+#[derive(Debug, Clone)]
+pub struct GoToDefinitionFallback;
+
+#[async_trait(?Send)]
+impl Executable for GoToDefinitionFallback {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let point = ctx.editor.cursor.get_point();
+        let document = ctx.editor.buffer_manager.current();
+        let keywords = ctx.config.keyword_chars(document.language);
+        let Some(word) = document.buffer.word_at(&point, &keywords) else {
+            let message = "E: No word under the cursor".to_string();
+            return system::ShowMessage(Message::error(message)).execute(ctx).await;
+        };
+
+        let mut symbols = ctx.symbol_index.find(&word);
+        match symbols.len() {
+            0 => not_found(ctx, format!("E: No definition found for {word}")).await,
+            1 => go_to_symbol(ctx, &symbols.remove(0)).await,
+            _ => show_symbol_list(ctx, symbols).await,
+        }
+    }
+}
+
+impl_action!(
+    GoToDefinitionFallback,
+    "Go to definition using the symbol index",
+    ActionDefinition::GoToDefinitionFallback
+);
+
+pub(super) async fn go_to_symbol(ctx: &mut ActionContext<'_>, symbol: &Symbol) -> ActionResult {
+    OpenBuffer::new(symbol.path.clone()).execute(ctx).await?;
+    GoToPosition::new(symbol.row, symbol.column).execute(ctx).await
+}
+
+/// Reports that nothing was found, mentioning why the index may be missing it
+async fn not_found(ctx: &mut ActionContext<'_>, message: String) -> ActionResult {
+    let message = if ctx.symbol_index.is_indexing() {
+        format!("{message}, the project is still being indexed")
+    } else if ctx.symbol_index.is_full() {
+        format!("{message}, the project has too many symbols to index them all")
+    } else {
+        message
+    };
+    system::ShowMessage(Message::error(message)).execute(ctx).await
+}
+
+/// Lists the symbols in a read-only scratch buffer, where Enter goes to the one on the line
+async fn show_symbol_list(ctx: &mut ActionContext<'_>, symbols: Vec<Symbol>) -> ActionResult {
+    let root = std::env::current_dir().unwrap_or_default();
+    let text = symbols
+        .iter()
+        .map(|symbol| {
+            let path = symbol.path.strip_prefix(&root).unwrap_or(&symbol.path);
+            format!(
+                "{}:{}: {} {}\n",
+                path.display(),
+                symbol.row + 1,
+                symbol.kind,
+                symbol.name
+            )
+        })
+        .collect::<String>();
+
+    ctx.editor.buffer_manager.new_buffer();
+    let list = ctx.editor.buffer_manager.current_mut();
+    list.buffer = Buffer::from_string(&text);
+    list.backend = Box::new(ViewBackend);
+    list.scratch = true;
+    list.symbols = Some(symbols);
+    after_buffer_change(ctx).await?;
+    GoToPosition::new(0, 0).execute(ctx).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::testing::TestEditor;
+
+    #[tokio::test]
+    async fn test_go_to_indexed_definition() {
+        let root = std::env::temp_dir().join(format!("viron-{}-definition", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let main = root.join("main.rs");
+        std::fs::write(&main, "fn main() {\n    helper();\n}\n").unwrap();
+        std::fs::write(root.join("a.rs"), "fn helper() {}\n").unwrap();
+        std::fs::write(root.join("b.rs"), "struct Other;\n\n  fn helper() {}\n").unwrap();
+
+        let mut editor = TestEditor::new("");
+        let job = editor.core.jobs.start("Indexing symbols".to_string());
+        editor.symbol_index.start(root.clone(), job);
+        editor.symbol_index.wait().await;
+
+        // Without a language server, gd on a name defined twice lists both definitions
+        editor.keys(&format!(":e {}<Enter>", main.display())).await.unwrap();
+        editor.keys("jwgd").await.unwrap();
+        let lines = editor.text();
+        assert_eq!(lines.lines().count(), 2, "{lines}");
+        assert!(lines.lines().all(|line| line.ends_with(": function helper")));
+
+        editor.keys("j<Enter>").await.unwrap();
+        assert_eq!(editor.core.current_document().path, Some(root.join("b.rs")));
+        assert_eq!(editor.cursor(), (2, 5));
+
+        editor.keys("<Home>gd").await.unwrap();
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "E: No definition found for fn");
+
+        editor.keys(":symbols oth<Enter><Enter>").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 7));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
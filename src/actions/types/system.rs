@@ -1,9 +1,12 @@
 use crate::actions::ActionResult;
 use crate::actions::context::ActionContext;
 use crate::actions::core::{ActionDefinition, Executable, impl_action};
-use crate::core::message::Message;
+use crate::core::document::Document;
+use crate::core::message::{Message, MessageType};
 use async_trait::async_trait;
-use crate::constants::components::MESSAGE_AREA;
+use crate::constants::components::{BOTTOM_BAR, EDITOR_VIEW, PAGER};
+use crate::actions::types::mode::EnterMode;
+use crate::core::mode::Mode;
 
 #[derive(Debug, Clone)]
 pub struct Quit;
@@ -19,16 +22,316 @@ impl Executable for Quit {
 
 impl_action!(Quit, "Quit the editor", ActionDefinition::Quit);
 
+/// Quits the editor, refusing while a buffer has unsaved changes unless forced
+#[derive(Debug, Clone)]
+pub struct QuitAll {
+    force: bool,
+}
+
+impl QuitAll {
+    pub fn force(force: bool) -> Self {
+        Self { force }
+    }
+}
+
+#[async_trait(?Send)]
+impl Executable for QuitAll {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let unsaved = ctx
+            .editor
+            .buffer_manager
+            .documents()
+            .filter(|document| document.has_unsaved_changes())
+            .map(|document| document.display_name())
+            .collect::<Vec<_>>();
+        if !self.force && !unsaved.is_empty() {
+            let message = format!(
+                "E: No write since last change for {} (add ! to override)",
+                unsaved.join(", ")
+            );
+            return ShowMessage(Message::error(message)).execute(ctx).await;
+        }
+
+        for document in ctx.editor.buffer_manager.documents() {
+            if let Some(path) = &document.temp_file {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        *ctx.running = false;
+        Ok(())
+    }
+}
+
+impl_action!(QuitAll, "Quit all buffers", self {
+    ActionDefinition::QuitAll { force: self.force }
+});
+
+/// Ctrl-c: drops the pending keys and returns to normal mode like Esc, with a hint
+/// for those who expected it to quit
+#[derive(Debug, Clone)]
+pub struct Interrupt;
+
+#[async_trait(?Send)]
+impl Executable for Interrupt {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        ctx.input.input_state.clear();
+        ctx.ui.compositor.mark_dirty(BOTTOM_BAR)?;
+        EnterMode::new(Mode::Normal).execute(ctx).await?;
+        ShowMessage(Message::info("Type :q to exit".to_string())).execute(ctx).await
+    }
+}
+
+/// Ctrl-z: raw mode turns it into a key, so the stop signal is sent from here.
+/// The editor handles it like one sent by `kill`, restoring the terminal first
+#[derive(Debug, Clone)]
+pub struct Suspend;
+
+#[async_trait(?Send)]
+impl Executable for Suspend {
+    async fn execute(&self, _ctx: &mut ActionContext) -> ActionResult {
+        unsafe { libc::raise(libc::SIGTSTP) };
+        Ok(())
+    }
+}
+
+impl_action!(Suspend, "Suspend the editor", ActionDefinition::Suspend);
+
+/// Whether the command line, the search box or paged output holds the bottom row
+fn prompt_open(ctx: &ActionContext) -> bool {
+    matches!(ctx.editor.mode, Mode::Command | Mode::Search) || ctx.message.output().is_some()
+}
+
+/// Shows a message on the bottom row, or as output waiting for a key when it has
+/// several lines, such as an error with a backtrace
+async fn display(ctx: &mut ActionContext<'_>, message: Message) -> ActionResult {
+    if message.content.contains('\n') {
+        let lines = message.content.lines().map(str::to_string).collect();
+        return ShowOutput(lines).execute(ctx).await;
+    }
+    ctx.message.show_message(message);
+    ctx.ui
+        .compositor
+        .mark_dirty(BOTTOM_BAR)?;
+    Ok(())
+}
+
+/// Shows a message and keeps it in the `:messages` log, or queues it while a prompt
+/// is open on the bottom row
 #[derive(Debug, Clone)]
 pub struct ShowMessage(pub Message);
 
 #[async_trait(?Send)]
 impl Executable for ShowMessage {
     async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
-        ctx.message.show_message(self.0.clone());
-        ctx.ui
-            .compositor
-            .mark_visible(MESSAGE_AREA, true)?;
+        ctx.message.log(&self.0);
+        if prompt_open(ctx) {
+            ctx.message.queue_message(self.0.clone());
+            return Ok(());
+        }
+        display(ctx, self.0.clone()).await
+    }
+}
+
+/// Run after each key: once the prompt is closed, shows the messages queued while it
+/// was open, followed by the one from whatever closed it, like the result of a command.
+/// One fits the bottom row and several are shown together as output
+#[derive(Debug, Clone)]
+pub struct ShowQueuedMessages;
+
+#[async_trait(?Send)]
+impl Executable for ShowQueuedMessages {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if prompt_open(ctx) {
+            return Ok(());
+        }
+        let mut messages = ctx.message.take_queued();
+        if messages.is_empty() {
+            return Ok(());
+        }
+        // Opening the prompt cleared the message, so any shown now came after it
+        messages.extend(ctx.message.current_message().cloned());
+        match messages.as_slice() {
+            [message] => display(ctx, message.clone()).await,
+            _ => {
+                let lines = messages
+                    .iter()
+                    .flat_map(|message| message.content.lines())
+                    .map(str::to_string)
+                    .collect();
+                ShowOutput(lines).execute(ctx).await
+            }
+        }
+    }
+}
+
+/// Shows lines printed by a command, in the message area when there is only one
+/// and a page at a time otherwise
+#[derive(Debug, Clone)]
+pub struct ShowOutput(pub Vec<String>);
+
+#[async_trait(?Send)]
+impl Executable for ShowOutput {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if let [line] = self.0.as_slice() {
+            return display(ctx, Message::info(line.clone())).await;
+        }
+        ctx.message.show_output(self.0.clone());
+        ctx.ui.compositor.mark_visible(PAGER, true)?;
+        ctx.ui.compositor.set_focus(PAGER)?;
+        Ok(())
+    }
+}
+
+/// `:messages`, the messages shown so far with their time and kind, oldest first
+#[derive(Debug, Clone)]
+pub struct ShowMessages;
+
+#[async_trait(?Send)]
+impl Executable for ShowMessages {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let mut lines = Vec::new();
+        for logged in ctx.message.logged() {
+            let kind = match logged.message.message_type {
+                MessageType::Info => "info",
+                MessageType::Error => "error",
+            };
+            let mut content = logged.message.content.lines();
+            let first = content.next().unwrap_or_default();
+            lines.push(format!("{} {kind:<5} {first}", logged.clock()));
+            // Further lines of a message line up under its first
+            lines.extend(content.map(|line| format!("{:15}{line}", "")));
+        }
+        if lines.is_empty() {
+            return display(ctx, Message::info("No messages".to_string())).await;
+        }
+        ShowOutput(lines).execute(ctx).await
+    }
+}
+
+impl_action!(ShowMessages, "Show messages", ActionDefinition::ShowMessages);
+
+/// Keys of the `-- More --` prompt. Going forward from the last page or quitting
+/// closes the output, as does any other key once the last page is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageOutput {
+    Lines(isize),
+    Pages(isize),
+    First,
+    Last,
+    Quit,
+    Other,
+}
+
+#[async_trait(?Send)]
+impl Executable for PageOutput {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        // Every row but the prompt
+        let height = ctx.ui.compositor.size().1 - 1;
+        let Some(output) = ctx.message.output_mut() else {
+            return Ok(());
+        };
+        let last_top = output.last_top(height);
+        let forward = matches!(self, PageOutput::Lines(1..) | PageOutput::Pages(1..));
+        let close = match self {
+            PageOutput::Quit => true,
+            PageOutput::Other => output.at_end(height),
+            _ => forward && output.at_end(height),
+        };
+        if close {
+            ctx.message.close_output();
+            ctx.message.clear_message();
+            ctx.ui.compositor.mark_visible(PAGER, false)?;
+            ctx.ui.compositor.set_focus(EDITOR_VIEW)?;
+            ctx.ui.compositor.mark_all_dirty();
+            return Ok(());
+        }
+
+        output.top = match *self {
+            PageOutput::Lines(lines) => output.top.saturating_add_signed(lines),
+            PageOutput::Pages(pages) => output.top.saturating_add_signed(pages * height as isize),
+            PageOutput::First => 0,
+            _ => last_top,
+        }
+        .min(last_top);
+        ctx.ui.compositor.mark_dirty(PAGER)?;
         Ok(())
     }
 }
+
+/// Formats the file info line shown by Ctrl-g, e.g. `"main.rs" [Modified] 120 lines --4%--`
+pub fn file_info(document: &Document, row: usize) -> String {
+    let name = document
+        .path
+        .as_ref()
+        .map_or("[No Name]".to_string(), |path| format!("\"{}\"", path.display()));
+    let modified = if document.is_modified() { " [Modified]" } else { "" };
+
+    // Only part of a read-only large file is loaded, so its length is unknown
+    if document.is_read_only() {
+        let line_number = document.backend.line_number(row);
+        return format!("{name} [readonly] line {line_number}");
+    }
+
+    let line_count = document.buffer.line_count();
+    let percent = (row + 1) * 100 / line_count.max(1);
+    let lines = if line_count == 1 { "line" } else { "lines" };
+    format!("{name}{modified} {line_count} {lines} --{percent}%--")
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowFileInfo;
+
+#[async_trait(?Send)]
+impl Executable for ShowFileInfo {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let document = ctx.editor.buffer_manager.current();
+        let info = file_info(document, ctx.editor.cursor.get_point().row);
+        ShowMessage(Message::info(info)).execute(ctx).await
+    }
+}
+
+impl_action!(ShowFileInfo, "Show file info", ActionDefinition::ShowFileInfo);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::buffer::Buffer;
+    use crate::core::history::edit::Edit;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_file_info() {
+        let mut document = Document::new();
+        assert_eq!(file_info(&document, 0), "[No Name] 1 line --100%--");
+
+        document.buffer = Buffer::from_string("one\ntwo\nthree\n");
+        document.path = Some(PathBuf::from("src/main.rs"));
+        assert_eq!(file_info(&document, 0), "\"src/main.rs\" 4 lines --25%--");
+
+        document.history.push(Edit::Group(Vec::new()));
+        assert_eq!(file_info(&document, 2), "\"src/main.rs\" [Modified] 4 lines --75%--");
+    }
+
+    #[tokio::test]
+    async fn test_message_log() {
+        let mut editor = crate::editor::testing::TestEditor::new("");
+        editor.keys(":messages<Enter>").await.unwrap();
+        assert_eq!(editor.message_manager.current_message().unwrap().content, "No messages");
+
+        // A message of several lines waits for a key as output
+        let error = Message::error("E: Server crashed\n  at main.rs:3\n  at lib.rs:9".to_string());
+        editor.execute(&ShowMessage(error)).await.unwrap();
+        let output = editor.message_manager.output().unwrap();
+        assert_eq!(output.lines, ["E: Server crashed", "  at main.rs:3", "  at lib.rs:9"]);
+        editor.keys("q:set ft=nope<Enter>").await.unwrap();
+
+        editor.keys(":mes<Enter>").await.unwrap();
+        let lines = &editor.message_manager.output().unwrap().lines;
+        assert_eq!(lines.len(), 4, "{lines:?}");
+        assert!(lines[0].ends_with(" error E: Server crashed"), "{lines:?}");
+        assert_eq!(lines[1], format!("{:15}  at main.rs:3", ""));
+        assert!(lines[3].ends_with(" error E: Unknown filetype: nope"), "{lines:?}");
+        let clock = &lines[0][..8];
+        assert!(clock.chars().all(|c| c.is_ascii_digit() || c == ':'), "{clock}");
+    }
+}
@@ -0,0 +1,131 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::{ActionDefinition, Executable};
+use crate::actions::types::{buffer, system};
+use crate::core::message::Message;
+use crate::core::mode::Mode;
+use crate::input::keymaps::KeyMap;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+/// Tutorial text, where `{{Name}}` stands for the key bound to an action
+const TUTOR_TEMPLATE: &str = include_str!("tutor.txt");
+
+/// Fills in the tutorial with the keys of the given keymap
+pub fn render_tutor(keymap: &KeyMap) -> String {
+    let mut output = String::with_capacity(TUTOR_TEMPLATE.len());
+    let mut rest = TUTOR_TEMPLATE;
+    while let Some(start) = rest.find("{{")
+        && let Some(length) = rest[start..].find("}}")
+    {
+        let name = &rest[start + 2..start + length];
+        output.push_str(&rest[..start]);
+        match keymap.find_normal_key(|definition| action_name_matches(definition, name)) {
+            Some(key) => output.push_str(&key),
+            None => output.push_str("(unmapped)"),
+        }
+        rest = &rest[start + length + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Matches an action by its type, the mode it enters, or the description of a composite
+fn action_name_matches(definition: &ActionDefinition, name: &str) -> bool {
+    match definition {
+        ActionDefinition::EnterMode {
+            mode: Mode::OperationPending(operator),
+        } => operator.to_name() == name,
+        ActionDefinition::EnterMode { mode } => mode.to_name() == name,
+        ActionDefinition::Composite { description, .. } => description == name,
+        definition => serde_json::to_value(definition)
+            .ok()
+            .and_then(|value| value.get("type").and_then(Value::as_str).map(|kind| kind == name))
+            .unwrap_or(false),
+    }
+}
+
+/// Writes the tutorial to a new file in the temporary directory. The file is created only
+/// if nothing has the name, so that another tutor's copy or a link put there is left alone
+fn create_tutor_file(text: &str) -> std::io::Result<PathBuf> {
+    for attempt in 0..100 {
+        let name = format!("viron-tutor-{}-{attempt}.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(text.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(ErrorKind::AlreadyExists.into())
+}
+
+/// Opens a throwaway copy of the tutorial
+#[derive(Debug, Clone)]
+pub struct OpenTutor;
+
+#[async_trait(?Send)]
+impl Executable for OpenTutor {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let path = match create_tutor_file(&render_tutor(&ctx.config.keymap)) {
+            Ok(path) => path,
+            Err(e) => {
+                return system::ShowMessage(Message::error(format!("E: {e}")))
+                    .execute(ctx)
+                    .await;
+            }
+        };
+
+        buffer::OpenBuffer::new(path.clone()).execute(ctx).await?;
+        let document = ctx.editor.buffer_manager.current_mut();
+        document.scratch = true;
+        document.temp_file = Some(path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    #[test]
+    fn test_render_tutor_uses_keymap() {
+        let editor = TestEditor::new("");
+        let tutor = render_tutor(&editor.config.keymap);
+
+        assert!(!tutor.contains("{{"));
+        assert!(!tutor.contains("(unmapped)"));
+        assert!(tutor.contains("Move the cursor with h (left), j (down), k (up) and l (right)."));
+        assert!(tutor.contains("dw deletes a word and dd deletes the whole line."));
+    }
+
+    #[tokio::test]
+    async fn test_tutor_closes_without_saving() {
+        let mut editor = TestEditor::new("original\n");
+
+        editor.keys(":tutor<Enter>").await.unwrap();
+        let document = editor.core.current_document();
+        assert!(document.scratch);
+        let path = document.path.clone().unwrap();
+        assert!(path.exists());
+
+        // Another tutor gets a file of its own
+        editor.keys(":tutor<Enter>").await.unwrap();
+        let other = editor.core.current_document().path.clone().unwrap();
+        assert_ne!(other, path);
+        editor.keys(":q<Enter>").await.unwrap();
+        assert!(!other.exists());
+        assert!(path.exists());
+
+        editor.keys("dd:q<Enter>").await.unwrap();
+        assert_eq!(editor.text(), "original\n");
+        assert!(!path.exists());
+    }
+}
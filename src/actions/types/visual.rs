@@ -0,0 +1,151 @@
+use crate::actions::ActionResult;
+use crate::actions::context::ActionContext;
+use crate::actions::core::{ActionDefinition, Executable, impl_action};
+use crate::actions::types::editing::{self, after_edit, ensure_writable};
+use crate::actions::types::mode::EnterMode;
+use crate::core::history::edit::Edit;
+use crate::core::mode::{Mode, selected_lines};
+use crate::core::register::{Register, RegisterKind};
+use async_trait::async_trait;
+use tree_sitter::Point;
+
+/// First and last of the selected lines
+fn selection(ctx: &ActionContext) -> (usize, usize) {
+    let row = ctx.editor.cursor.get_point().row;
+    let line_count = ctx.editor.buffer_manager.current_buffer().line_count();
+    selected_lines(*ctx.editor.visual_anchor, row, line_count)
+}
+
+/// Deletes the selected lines into the registers, returning false if there were none
+async fn delete_selection(ctx: &mut ActionContext<'_>) -> anyhow::Result<bool> {
+    let (first, last) = selection(ctx);
+    let cursor = ctx.editor.cursor.get_point();
+    let buffer = ctx.editor.buffer_manager.current_buffer_mut();
+    let Some((deleted, start_byte)) = buffer.delete_multiple_lines(first, last) else {
+        return Ok(false);
+    };
+
+    let start = Point { row: first, column: 0 };
+    let edit = Edit::delete(
+        start_byte,
+        buffer.point_at_position(start_byte),
+        deleted.clone(),
+        cursor,
+        start,
+    );
+    ctx.editor.cursor.set_point(start, buffer);
+    ctx.editor.cursor.clamp_row(buffer);
+    ctx.editor.cursor.move_to_first_non_blank(buffer);
+    after_edit(ctx, &edit).await?;
+
+    ctx.editor.buffer_manager.current_mut().history.push(edit);
+    ctx.editor
+        .register_system
+        .on_delete(Register::new(deleted, RegisterKind::Line));
+    Ok(true)
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteSelectedLines;
+
+#[async_trait(?Send)]
+impl Executable for DeleteSelectedLines {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if ensure_writable(ctx).await? {
+            delete_selection(ctx).await?;
+        }
+        EnterMode::new(Mode::Normal).execute(ctx).await
+    }
+}
+
+impl_action!(
+    DeleteSelectedLines,
+    "Delete the selected lines",
+    ActionDefinition::DeleteSelectedLines
+);
+
+#[derive(Debug, Clone)]
+pub struct ChangeSelectedLines;
+
+#[async_trait(?Send)]
+impl Executable for ChangeSelectedLines {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        if !ensure_writable(ctx).await? {
+            return EnterMode::new(Mode::Normal).execute(ctx).await;
+        }
+        ctx.editor.buffer_manager.current_mut().history.begin_group();
+        if delete_selection(ctx).await? {
+            editing::InsertNewLineAbove.execute(ctx).await?;
+        }
+        EnterMode::new(Mode::Insert).execute(ctx).await
+    }
+}
+
+impl_action!(
+    ChangeSelectedLines,
+    "Change the selected lines",
+    ActionDefinition::ChangeSelectedLines
+);
+
+#[derive(Debug, Clone)]
+pub struct YankSelectedLines;
+
+#[async_trait(?Send)]
+impl Executable for YankSelectedLines {
+    async fn execute(&self, ctx: &mut ActionContext) -> ActionResult {
+        let (first, last) = selection(ctx);
+        let buffer = ctx.editor.buffer_manager.current_buffer();
+        let yanked = buffer.get_lines(first, last);
+        if !yanked.is_empty() {
+            ctx.editor
+                .register_system
+                .on_yank(Register::new(yanked, RegisterKind::Line));
+        }
+        // Like vim, the cursor goes to the first line, so yanking upwards moves it
+        let column = ctx.editor.cursor.get_point().column;
+        ctx.editor.cursor.set_point(Point { row: first, column }, buffer);
+        EnterMode::new(Mode::Normal).execute(ctx).await
+    }
+}
+
+impl_action!(
+    YankSelectedLines,
+    "Yank the selected lines",
+    ActionDefinition::YankSelectedLines
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::core::mode::Mode;
+    use crate::editor::testing::TestEditor;
+
+    const TEXT: &str = "one\ntwo\nthree\nfour\nfive\n";
+
+    #[tokio::test]
+    async fn test_delete_lines_and_paste_below() {
+        let mut editor = TestEditor::new(TEXT);
+        editor.keys("jVjd").await.unwrap();
+        assert_eq!(editor.text(), "one\nfour\nfive\n");
+        assert_eq!(editor.core.mode, Mode::Normal);
+        assert_eq!(editor.cursor(), (1, 0));
+
+        editor.keys("p").await.unwrap();
+        assert_eq!(editor.text(), "one\nfour\ntwo\nthree\nfive\n");
+
+        editor.keys("uu").await.unwrap();
+        assert_eq!(editor.text(), TEXT);
+    }
+
+    #[tokio::test]
+    async fn test_selection_flips_above_the_anchor() {
+        let mut editor = TestEditor::new(TEXT);
+        editor.keys("jjVjkkky").await.unwrap();
+        assert_eq!(editor.cursor(), (0, 0));
+        editor.keys("Gp").await.unwrap();
+        assert_eq!(editor.text(), "one\ntwo\nthree\nfour\nfive\none\ntwo\nthree\n");
+
+        let mut editor = TestEditor::new(TEXT);
+        editor.keys("jjjVkkcnew<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "one\nnew\nfive\n");
+    }
+}
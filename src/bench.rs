@@ -0,0 +1,39 @@
+//! What the benchmarks in `benches/` measure, built with the `bench` feature. The
+//! modules of the editor are private, so the parts worth timing are exported here
+use crate::core::language::Language;
+use crate::editor::testing::TestEditor;
+use anyhow::Result;
+
+pub use crate::core::buffer::Buffer;
+pub use crate::core::buffer::storage::StorageKind;
+pub use crate::core::history::edit::Edit;
+pub use crate::core::syntax::{SyntaxEngine, TokenInfo};
+pub use tree_sitter::Point;
+
+/// Headless editor drawing frames the way the main loop does, as the tests use
+pub struct Screen {
+    editor: TestEditor,
+}
+
+impl Screen {
+    pub fn new(content: &str, language: Language, width: usize, height: usize) -> Self {
+        let mut editor = TestEditor::new(content);
+        editor.core.current_document_mut().set_language(language);
+        editor.resize(width, height);
+        Self { editor }
+    }
+
+    /// Puts the cursor on the row, scrolling there on the next frame
+    pub fn go_to_row(&mut self, row: usize) {
+        let core = &mut self.editor.core;
+        let buffer = &core.buffer_manager.current().buffer;
+        core.cursor.set_point(Point::new(row, 0), buffer);
+    }
+
+    /// Draws every component again, as after a resize, rather than only those that
+    /// changed since the last frame
+    pub fn render_all(&mut self) -> Result<()> {
+        self.editor.ui.mark_all_dirty();
+        self.editor.render().map(|_| ())
+    }
+}
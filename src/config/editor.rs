@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -6,5 +7,238 @@ pub enum Gutter {
     None,
     #[default]
     Absolute,
+    /// Distance from the cursor line, which shows its own number on the left
     Relative,
+    /// Distance from the cursor line, which shows its own number aligned with the
+    /// others, as `number` and `relativenumber` together do in vim
+    RelativeNumber,
+}
+
+impl Gutter {
+    /// Whether the numbers change with the cursor line, so moving to another line
+    /// redraws them
+    pub fn is_relative(&self) -> bool {
+        matches!(self, Gutter::Relative | Gutter::RelativeNumber)
+    }
+}
+
+/// How control characters in the buffer are shown
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlChars {
+    /// Caret notation taking two cells, e.g. `^[` for an escape
+    #[default]
+    Caret,
+    /// A single `�`
+    Replacement,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DiagnosticSigns {
+    pub error: String,
+    pub warning: String,
+}
+
+impl Default for DiagnosticSigns {
+    fn default() -> Self {
+        Self {
+            error: "E:".to_string(),
+            warning: "W:".to_string(),
+        }
+    }
+}
+
+/// External command run by `K` to look up the word under the cursor
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeywordProgram {
+    /// Command line where `{word}` and `{file}` are replaced
+    pub command: String,
+    /// Starts the command without waiting for it or showing its output, for commands
+    /// that open a browser
+    #[serde(default)]
+    pub detach: bool,
+    /// Seconds the command may run before it's stopped
+    #[serde(default = "default_keyword_timeout")]
+    pub timeout: u64,
+}
+
+fn default_keyword_timeout() -> u64 {
+    10
+}
+
+/// Key of the program used for languages without their own
+pub const DEFAULT_KEYWORD_PROGRAM: &str = "default";
+
+/// Key of the word characters used for languages without their own
+pub const DEFAULT_KEYWORD_CHARS: &str = "default";
+
+/// Characters that are part of words besides letters, digits and `_`, by language
+pub fn default_keyword_chars() -> HashMap<String, String> {
+    HashMap::from([("css".to_string(), "-".to_string())])
+}
+
+/// Commands `:fmt` pipes the buffer through when the language server doesn't format it
+pub fn default_format_programs() -> HashMap<String, String> {
+    HashMap::from([("rust".to_string(), "rustfmt --emit stdout".to_string())])
+}
+
+/// The `[lsp]` table
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LspConfig {
+    /// Servers by language name, used before the builtin ones
+    #[serde(default)]
+    pub servers: HashMap<String, LanguageServer>,
+}
+
+/// Language server started for the documents of a language
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LanguageServer {
+    /// The builtin server of the language when left out, to only pass options to it
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Sent as the `initializationOptions` of the server
+    pub init_options: Option<serde_json::Value>,
+    /// No server is started for the language when false
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+pub fn default_keyword_programs() -> HashMap<String, KeywordProgram> {
+    let man = KeywordProgram {
+        command: "man {word}".to_string(),
+        detach: false,
+        timeout: default_keyword_timeout(),
+    };
+    HashMap::from([(DEFAULT_KEYWORD_PROGRAM.to_string(), man)])
+}
+
+/// Number of searches and commands kept for recalling with Up and Down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HistorySize(pub usize);
+
+impl Default for HistorySize {
+    fn default() -> Self {
+        Self(50)
+    }
+}
+
+/// Seconds between writes of the unsaved text to swap files, none when 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SwapInterval(pub u64);
+
+impl Default for SwapInterval {
+    fn default() -> Self {
+        Self(10)
+    }
+}
+
+/// Megabytes a file can have before it opens in large-file mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LargeFileSize(pub u64);
+
+impl LargeFileSize {
+    pub fn bytes(self) -> u64 {
+        self.0 * 1024 * 1024
+    }
+}
+
+impl Default for LargeFileSize {
+    fn default() -> Self {
+        Self(64)
+    }
+}
+
+/// A part of the status line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusSegment {
+    Mode,
+    /// The path relative to the working directory, with `[+]` when it's modified. It
+    /// takes the width the others leave, so the segments after it are on the right
+    File,
+    Language,
+    /// How the file is stored, shown when it isn't plain UTF-8
+    Encoding,
+    /// Counts of the errors and warnings in the file
+    Diagnostics,
+    /// Count, register and operator of the command being typed
+    Command,
+    /// How far through the file the cursor line is, `Top` and `Bot` at the ends
+    Percent,
+    /// `line:column` of the cursor
+    Position,
+}
+
+/// Segments of the status line in the order they're shown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StatusLineSegments(pub Vec<StatusSegment>);
+
+impl Default for StatusLineSegments {
+    fn default() -> Self {
+        Self(vec![
+            StatusSegment::Mode,
+            StatusSegment::File,
+            StatusSegment::Language,
+            StatusSegment::Encoding,
+            StatusSegment::Diagnostics,
+            StatusSegment::Command,
+            StatusSegment::Percent,
+            StatusSegment::Position,
+        ])
+    }
+}
+
+/// Whether Enter in insert mode keeps the indent of the line, adding a level after an
+/// opening bracket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AutoIndent(pub bool);
+
+impl Default for AutoIndent {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Whether a search pattern without capitals ignores case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SmartCase(pub bool);
+
+impl Default for SmartCase {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Cells between tab stops, where tabs are drawn up to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TabWidth(pub usize);
+
+impl Default for TabWidth {
+    fn default() -> Self {
+        Self(4)
+    }
+}
+
+/// Number of spaces `>>` and `<<` add or remove
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ShiftWidth(pub usize);
+
+impl Default for ShiftWidth {
+    fn default() -> Self {
+        Self(4)
+    }
 }
@@ -1,27 +1,165 @@
 pub mod editor;
 
-use crate::config::editor::Gutter;
-use crate::input::keymaps::{KeyMap};
-use crate::ui::theme::Theme;
+use crate::config::editor::{
+    AutoIndent, ControlChars, DEFAULT_KEYWORD_CHARS, DiagnosticSigns, Gutter, HistorySize,
+    KeywordProgram, LanguageServer, LargeFileSize, LspConfig, ShiftWidth, SmartCase, StatusLineSegments,
+    SwapInterval, TabWidth,
+    default_format_programs, default_keyword_chars, default_keyword_programs,
+};
+use crate::constants::MIN_GUTTER_WIDTH;
+use crate::core::document::Document;
+use crate::core::keyword_chars::KeywordChars;
+use crate::ui::display::Layout;
+use crate::core::language::Language;
+use crate::actions::core::definition::take_deprecation_warnings;
+use crate::input::default_keymap::default_keymap;
+use crate::input::keymaps::KeyMap;
+use crate::ui::theme::{DEFAULT_THEME, DEFAULT_THEME_NAME, Theme};
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 const CONFIG_DIRECTORY: &str = ".viron";
+const CONFIG_FILE: &str = "config.toml";
+const SWAP_DIRECTORY: &str = "swap";
+/// Left in the config directory once the missing config was reported
+const NO_CONFIG_MARKER: &str = ".no-config-shown";
+
+/// Config shipped with the editor, written by `--init-config`
+const BUNDLED_CONFIG: &str = include_str!("../../config.toml");
 
 #[derive(Serialize, Deserialize)]
 pub struct FileConfig {
     pub theme: String,
     #[serde(default)]
     pub gutter: Gutter,
+    #[serde(default)]
+    pub diagnostic_signs: DiagnosticSigns,
+    #[serde(default)]
+    pub keep_cursor_on_escape: bool,
+    #[serde(default)]
+    pub report_directory: bool,
+    #[serde(default)]
+    pub keep_parsers: bool,
+    #[serde(default)]
+    pub control_chars: ControlChars,
+    #[serde(default)]
+    pub scrollbar: bool,
+    #[serde(default)]
+    pub mouse: bool,
+    #[serde(default)]
+    pub diagnostics_errors_first: bool,
+    #[serde(default)]
+    pub history: HistorySize,
+    #[serde(default)]
+    pub shift_width: ShiftWidth,
+    #[serde(default)]
+    pub auto_indent: AutoIndent,
+    #[serde(default)]
+    pub tab_width: TabWidth,
+    #[serde(default)]
+    pub expand_tab: bool,
+    #[serde(default)]
+    pub wrap: bool,
+    #[serde(default)]
+    pub smartcase: SmartCase,
+    #[serde(default)]
+    pub scrolloff: usize,
+    #[serde(default)]
+    pub sidescrolloff: usize,
+    #[serde(default)]
+    pub swap_interval: SwapInterval,
+    #[serde(default)]
+    pub large_file_size: LargeFileSize,
+    #[serde(default)]
+    pub statusline: StatusLineSegments,
+    #[serde(default = "default_keyword_programs")]
+    pub keywordprg: HashMap<String, KeywordProgram>,
+    #[serde(default = "default_keyword_chars")]
+    pub iskeyword: HashMap<String, String>,
+    #[serde(default)]
+    pub format_on_save: bool,
+    #[serde(default = "default_format_programs")]
+    pub formatprg: HashMap<String, String>,
+    #[serde(default)]
+    pub lsp: LspConfig,
+    /// Bindings layered on the default keymap
+    #[serde(default)]
     pub keymap: KeyMap,
 }
 
 impl FileConfig {
-    fn load_from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let string = std::fs::read_to_string(path)?;
-        let config = toml::from_str(&string)?;
-        Ok(config)
+    /// Loads the config file, naming the file and the location of a parse error on failure
+    fn load_from_file(path: &Path) -> anyhow::Result<Self> {
+        let string =
+            std::fs::read_to_string(path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        toml::from_str(&string).map_err(|e| {
+            let (line, column) = e
+                .span()
+                .map(|span| line_column(&string, span.start))
+                .unwrap_or((1, 1));
+            anyhow!(
+                "{}: {} at line {line} column {column}",
+                path.display(),
+                e.message().trim_end()
+            )
+        })
+    }
+
+    fn bundled() -> Self {
+        toml::from_str(BUNDLED_CONFIG).expect("Invalid bundled config")
+    }
+}
+
+/// One-based line and column of a byte offset
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+    (line, column)
+}
+
+/// Writes the bundled config and default theme into the directory as a starting point,
+/// keeping files that already exist. Returns the files that were written
+pub fn init_config(config_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let files = [
+        (config_dir.join(CONFIG_FILE), BUNDLED_CONFIG),
+        (theme_path(config_dir, DEFAULT_THEME_NAME), DEFAULT_THEME),
+    ];
+    let mut written = Vec::new();
+    for (path, content) in files {
+        if path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+        written.push(path);
     }
+    Ok(written)
+}
+
+fn theme_path(config_dir: &Path, name: &str) -> PathBuf {
+    config_dir.join(format!("themes/{name}.json"))
+}
+
+/// Layers the keymap of the config on the default one, unless it clears the
+/// defaults, warning about default keys it makes unreachable
+fn layer_keymap(keymap: KeyMap, warnings: &mut Vec<String>) -> KeyMap {
+    if keymap.clear_defaults {
+        return keymap;
+    }
+    let mut layered = default_keymap().clone();
+    warnings.extend(
+        layered
+            .shadowed_by(&keymap)
+            .into_iter()
+            .map(|conflict| format!("Keymap: {conflict}")),
+    );
+    layered.extend(keymap);
+    layered
 }
 
 pub fn get_config_dir() -> PathBuf {
@@ -33,27 +171,260 @@ pub fn get_config_dir() -> PathBuf {
 pub struct Config {
     pub theme: Theme,
     pub gutter: Gutter,
+    pub diagnostic_signs: DiagnosticSigns,
+    pub keep_cursor_on_escape: bool,
+    /// Report the directory of the current file to the terminal with OSC 7
+    pub report_directory: bool,
+    /// Keep the parser of a language loaded after its last document closes
+    pub keep_parsers: bool,
+    pub control_chars: ControlChars,
+    /// Show where the viewport and the diagnostics are in the file on the right edge
+    pub scrollbar: bool,
+    /// Capture the mouse, so a click on the scrollbar jumps to that part of the file
+    pub mouse: bool,
+    /// `]d` and `[d` go over errors only, as long as the document has some
+    pub diagnostics_errors_first: bool,
+    pub history: HistorySize,
+    pub shift_width: ShiftWidth,
+    pub auto_indent: AutoIndent,
+    pub tab_width: TabWidth,
+    /// Tab in insert mode inserts spaces up to the next tab stop instead of a tab
+    pub expand_tab: bool,
+    /// Long lines continue on the next screen rows instead of scrolling sideways
+    pub wrap: bool,
+    pub smart_case: SmartCase,
+    /// Lines kept in view above and below the cursor when scrolling
+    pub scrolloff: usize,
+    /// Columns kept in view left and right of the cursor when scrolling sideways
+    pub sidescrolloff: usize,
+    pub swap_interval: SwapInterval,
+    /// Files larger than this open read-only, with only the part around the cursor
+    /// loaded and without highlighting or a language server
+    pub large_file_size: LargeFileSize,
+    /// Where the unsaved text of buffers is written, none in tests
+    pub swap_directory: Option<PathBuf>,
+    pub statusline: StatusLineSegments,
+    /// Documentation commands for `K`, by language name or `default`
+    pub keywordprg: HashMap<String, KeywordProgram>,
+    /// Characters that are part of words besides letters, digits and `_`, by language
+    /// name or `default`
+    pub iskeyword: HashMap<String, String>,
+    /// `:w` formats the buffer before writing it, as `:fmt` does
+    pub format_on_save: bool,
+    /// Formatting commands by language name, for languages the server doesn't format
+    pub formatprg: HashMap<String, String>,
+    /// Language servers by language name, in place of the builtin ones
+    pub lsp_servers: HashMap<String, LanguageServer>,
     pub keymap: KeyMap,
+    /// Problems found while loading the config, shown at startup
+    pub warnings: Vec<String>,
 }
 
-impl TryFrom<FileConfig> for Config {
-    type Error = anyhow::Error;
+impl Config {
+    /// Loads the config from the directory. A missing or broken config or theme
+    /// falls back to the bundled one, with the reason in `warnings`
+    pub fn load(config_dir: &Path) -> Self {
+        let path = config_dir.join(CONFIG_FILE);
+        let marker = config_dir.join(NO_CONFIG_MARKER);
+        let mut warnings = Vec::new();
+        // Running without a config is reported on the first start only
+        let quiet = !path.exists() && marker.exists();
+
+        let file_config = if path.exists() {
+            let loaded = FileConfig::load_from_file(&path);
+            let deprecations = take_deprecation_warnings();
+            match loaded {
+                Ok(file_config) => {
+                    warnings.extend(deprecations.into_iter().map(|warning| {
+                        format!("Deprecated keymap in {}: {warning}", path.display())
+                    }));
+                    file_config
+                }
+                Err(e) => {
+                    warnings.push(format!("{e}, using the default config"));
+                    FileConfig::bundled()
+                }
+            }
+        } else {
+            if !quiet {
+                warnings.push(format!(
+                    "No config at {}, using the default. Run `viron --init-config` to create one",
+                    path.display()
+                ));
+                let _ = std::fs::create_dir_all(config_dir).and_then(|_| std::fs::write(&marker, ""));
+            }
+            FileConfig::bundled()
+        };
+
+        let theme = match Theme::load_from_file(theme_path(config_dir, &file_config.theme)) {
+            Ok(theme) => theme,
+            Err(e) => {
+                if !quiet {
+                    warnings.push(format!("{e}, using the default theme"));
+                }
+                Theme::embedded()
+            }
+        };
 
-    fn try_from(file_config: FileConfig) -> Result<Self, Self::Error> {
-        let theme_path = get_config_dir().join(format!("themes/{}.json", file_config.theme));
-        let theme = Theme::load_from_file(&theme_path)?;
-        Ok(Self {
+        let keymap = layer_keymap(file_config.keymap, &mut warnings);
+        Self {
             theme,
-            keymap: file_config.keymap,
+            keymap,
             gutter: file_config.gutter,
-        })
+            diagnostic_signs: file_config.diagnostic_signs,
+            keep_cursor_on_escape: file_config.keep_cursor_on_escape,
+            report_directory: file_config.report_directory,
+            keep_parsers: file_config.keep_parsers,
+            control_chars: file_config.control_chars,
+            scrollbar: file_config.scrollbar,
+            mouse: file_config.mouse,
+            diagnostics_errors_first: file_config.diagnostics_errors_first,
+            history: file_config.history,
+            shift_width: file_config.shift_width,
+            auto_indent: file_config.auto_indent,
+            tab_width: file_config.tab_width,
+            expand_tab: file_config.expand_tab,
+            wrap: file_config.wrap,
+            smart_case: file_config.smartcase,
+            scrolloff: file_config.scrolloff,
+            sidescrolloff: file_config.sidescrolloff,
+            swap_interval: file_config.swap_interval,
+            large_file_size: file_config.large_file_size,
+            swap_directory: Some(config_dir.join(SWAP_DIRECTORY)),
+            statusline: file_config.statusline,
+            keywordprg: file_config.keywordprg,
+            iskeyword: file_config.iskeyword,
+            format_on_save: file_config.format_on_save,
+            formatprg: file_config.formatprg,
+            lsp_servers: file_config.lsp.servers,
+            warnings,
+        }
+    }
+
+    /// Columns left of the text of the document, for the line numbers and the scrollbar
+    pub fn gutter_width(&self, document: &Document) -> usize {
+        let line_count = document.buffer.line_count();
+        let numbers = match self.gutter {
+            Gutter::None => 0,
+            _ => (document.line_label(line_count.saturating_sub(1)).len() + 1).max(MIN_GUTTER_WIDTH),
+        };
+        numbers + usize::from(self.scrollbar)
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout {
+            control_chars: self.control_chars,
+            tab_width: self.tab_width.0,
+        }
+    }
+
+    /// What counts as a word in documents of the language
+    pub fn keyword_chars(&self, language: Language) -> KeywordChars {
+        let extra = self
+            .iskeyword
+            .get(language.to_str())
+            .or_else(|| self.iskeyword.get(DEFAULT_KEYWORD_CHARS));
+        KeywordChars::new(extra.map_or("", String::as_str))
     }
 }
 
-impl Config {
-    pub fn load_from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let file_config = FileConfig::load_from_file(path)?;
-        let config = Config::try_from(file_config)?;
-        Ok(config)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::core::ActionDefinition;
+    use crate::core::mode::Mode;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("viron-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_missing_config_uses_defaults() {
+        let dir = temp_dir("missing-config");
+        let config = Config::load(&dir);
+
+        assert!(config.keymap.find_normal_key(|_| true).is_some());
+        assert_eq!(config.warnings.len(), 2);
+        assert!(config.warnings[0].contains("--init-config"));
+        assert!(config.warnings[1].contains("using the default theme"));
+        let config = Config::load(&dir);
+        assert!(config.warnings.is_empty(), "{:?}", config.warnings);
+
+        let written = init_config(&dir).unwrap();
+        assert_eq!(written.len(), 2);
+        let config = Config::load(&dir);
+        assert!(config.warnings.is_empty(), "{:?}", config.warnings);
+        assert!(init_config(&dir).unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_files_name_the_location() {
+        let dir = temp_dir("corrupt-config");
+        init_config(&dir).unwrap();
+        let config = "theme = \"catppuchin/mocha\"\ngutter = \"sideways\"\n";
+        std::fs::write(dir.join(CONFIG_FILE), config).unwrap();
+        std::fs::write(theme_path(&dir, DEFAULT_THEME_NAME), "{\n  \"colors\": ,\n}").unwrap();
+
+        let config = Config::load(&dir);
+        let path = dir.join(CONFIG_FILE);
+        assert!(
+            config.warnings[0].starts_with(&format!("{}: ", path.display())),
+            "{}",
+            config.warnings[0]
+        );
+        assert!(config.warnings[0].contains("line 2"), "{}", config.warnings[0]);
+        assert!(config.warnings[1].contains("mocha.json: "), "{}", config.warnings[1]);
+        assert!(config.warnings[1].contains("line 2"), "{}", config.warnings[1]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_keymap_is_layered_on_defaults() {
+        let dir = temp_dir("layered-keymap");
+        init_config(&dir).unwrap();
+        let keymap = "[keymap.normal]\n\"g\" = { type = \"Undo\" }\n\"x\" = { type = \"Redo\" }\n";
+        let config = format!("theme = \"catppuchin/mocha\"\n{keymap}");
+        std::fs::write(dir.join(CONFIG_FILE), config).unwrap();
+
+        let config = Config::load(&dir);
+        let keymap = &config.keymap;
+        assert!(matches!(keymap.get_action(&Mode::Normal, "x"), Some(ActionDefinition::Redo)));
+        assert!(matches!(keymap.get_action(&Mode::Normal, "u"), Some(ActionDefinition::Undo)));
+        assert_eq!(config.warnings, [
+            "Keymap: `g` shadows the default keys `gP`, `gd`, `gg`, `go`, `gp`, `gr`"
+        ]);
+
+        let config = "theme = \"catppuchin/mocha\"\n[keymap]\nclear_defaults = true\n";
+        std::fs::write(dir.join(CONFIG_FILE), config).unwrap();
+        let config = Config::load(&dir);
+        assert!(config.keymap.get_action(&Mode::Normal, "u").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_lsp_servers() {
+        let dir = temp_dir("lsp-servers");
+        init_config(&dir).unwrap();
+        let servers = concat!(
+            "[lsp.servers]\n",
+            "rust = { command = \"ra\", args = [\"-v\"], ",
+            "init_options = { cargo = { features = \"all\" } } }\n",
+            "go = { enabled = false }\n",
+        );
+        std::fs::write(dir.join(CONFIG_FILE), format!("theme = \"catppuchin/mocha\"\n{servers}")).unwrap();
+
+        let config = Config::load(&dir);
+        assert!(config.warnings.is_empty(), "{:?}", config.warnings);
+        let rust = &config.lsp_servers["rust"];
+        assert_eq!((rust.command.as_deref(), rust.args.as_slice()), (Some("ra"), &["-v".to_string()][..]));
+        assert_eq!(rust.init_options, Some(serde_json::json!({ "cargo": { "features": "all" } })));
+        assert!(rust.enabled);
+        let go = &config.lsp_servers["go"];
+        assert!(!go.enabled && go.command.is_none());
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
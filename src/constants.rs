@@ -4,8 +4,10 @@ pub const MIN_GUTTER_WIDTH: usize = 4;
 pub mod components {
     pub const EDITOR_VIEW: &str = "editor-view";
     pub const STATUS_LINE: &str = "status-line";
-    pub const PENDING_KEYS: &str = "pending-keys";
-    pub const COMMAND_LINE: &str = "command-line";
-    pub const SEARCH_BOX: &str = "search-box";
-    pub const MESSAGE_AREA: &str = "message-area";
+    pub const BOTTOM_BAR: &str = "bottom-bar";
+    pub const PAGER: &str = "pager";
+    pub const JOBS: &str = "jobs";
+    pub const COMPLETION: &str = "completion";
+    pub const HOVER: &str = "hover";
+    pub const QUICKFIX: &str = "quickfix";
 }
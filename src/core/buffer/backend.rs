@@ -0,0 +1,144 @@
+use crate::core::buffer::Buffer;
+use anyhow::Result;
+use std::fmt::{Display, Formatter};
+
+/// Zero-based absolute line number of a buffer row in the underlying file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineNumber {
+    Exact(usize),
+    /// The true line number is unknown but at least this value
+    AtLeast(usize),
+}
+
+impl LineNumber {
+    pub fn offset(self, rows: usize) -> Self {
+        match self {
+            LineNumber::Exact(line) => LineNumber::Exact(line + rows),
+            LineNumber::AtLeast(line) => LineNumber::AtLeast(line + rows),
+        }
+    }
+}
+
+impl Display for LineNumber {
+    /// Formats the one-based line number, e.g. `42` or `~123,456+`
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineNumber::Exact(line) => write!(f, "{}", line + 1),
+            LineNumber::AtLeast(line) => write!(f, "~{}+", group_digits(line + 1)),
+        }
+    }
+}
+
+fn group_digits(number: usize) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Target of a jump that may require loading another part of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekPosition {
+    Start,
+    End,
+    /// Zero-based absolute line number
+    Line(usize),
+    /// Position in the file as a percentage of its size
+    Percent(usize),
+}
+
+/// Outcome of a seek: the buffer row to move to, and whether the buffer content was replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekResult {
+    pub row: usize,
+    pub reloaded: bool,
+}
+
+/// Source of the text held by a document's buffer
+pub trait BufferBackend {
+    /// Returns true if edits to the buffer must be rejected
+    fn is_read_only(&self) -> bool;
+
+    /// Returns true if only a window of the file around the cursor is held in the buffer
+    fn is_windowed(&self) -> bool;
+
+    /// Absolute line number of the given buffer row
+    fn line_number(&self, row: usize) -> LineNumber;
+
+    /// Makes sure the text around the given row is loaded, replacing the buffer content if needed.
+    /// Returns the number of rows the content moved by, so the cursor can follow it
+    fn ensure_loaded(&mut self, buffer: &mut Buffer, row: usize) -> Result<Option<isize>>;
+
+    /// Loads the part of the file containing the position and returns the row to move to
+    fn seek(&mut self, buffer: &mut Buffer, position: SeekPosition) -> Result<SeekResult>;
+}
+
+/// Backend for documents that are fully loaded in memory
+#[derive(Debug, Default)]
+pub struct MemoryBackend;
+
+/// Backend for text the editor made for viewing, such as a diff, which can't be edited
+#[derive(Debug, Default)]
+pub struct ViewBackend;
+
+impl BufferBackend for ViewBackend {
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_windowed(&self) -> bool {
+        false
+    }
+
+    fn line_number(&self, row: usize) -> LineNumber {
+        MemoryBackend.line_number(row)
+    }
+
+    fn ensure_loaded(&mut self, buffer: &mut Buffer, row: usize) -> Result<Option<isize>> {
+        MemoryBackend.ensure_loaded(buffer, row)
+    }
+
+    fn seek(&mut self, buffer: &mut Buffer, position: SeekPosition) -> Result<SeekResult> {
+        MemoryBackend.seek(buffer, position)
+    }
+}
+
+impl BufferBackend for MemoryBackend {
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn is_windowed(&self) -> bool {
+        false
+    }
+
+    fn line_number(&self, row: usize) -> LineNumber {
+        LineNumber::Exact(row)
+    }
+
+    fn ensure_loaded(&mut self, _buffer: &mut Buffer, _row: usize) -> Result<Option<isize>> {
+        Ok(None)
+    }
+
+    fn seek(&mut self, buffer: &mut Buffer, position: SeekPosition) -> Result<SeekResult> {
+        let last_row = buffer.line_count().saturating_sub(1);
+        let row = match position {
+            SeekPosition::Start => 0,
+            SeekPosition::End => last_row,
+            SeekPosition::Line(line) => line.min(last_row),
+            SeekPosition::Percent(percent) => {
+                // Same rounding as vim's N%
+                ((percent.min(100) * buffer.line_count()).div_ceil(100)).saturating_sub(1)
+            }
+        };
+        Ok(SeekResult {
+            row,
+            reloaded: false,
+        })
+    }
+}
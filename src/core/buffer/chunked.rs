@@ -0,0 +1,434 @@
+use crate::core::buffer::Buffer;
+use crate::core::buffer::backend::{BufferBackend, LineNumber, SeekPosition, SeekResult};
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use tree_sitter::Point;
+
+/// Size of a single read from the file
+pub const CHUNK_SIZE: usize = 1 << 20;
+/// Number of chunks held in the buffer at once
+const WINDOW_CHUNKS: usize = 4;
+/// Distance in rows from the edge of the window at which the window slides
+const SLIDE_MARGIN: usize = 200;
+/// Maximum number of chunks scanned to find an absolute line number
+const MAX_SCAN_CHUNKS: usize = 64;
+
+/// Read-only backend that keeps a sliding window of a large file in the buffer
+pub struct ChunkedBackend {
+    file: File,
+    file_size: u64,
+    chunk_size: usize,
+    /// Byte range of the file loaded in the buffer, starting and ending on line boundaries
+    window: Range<u64>,
+    /// Absolute line number of the first row in the window
+    first_line: LineNumber,
+    /// Known counts of line breaks before an offset, sorted by offset
+    checkpoints: Vec<(u64, usize)>,
+}
+
+impl ChunkedBackend {
+    /// Opens the file and loads the beginning of it into a new buffer
+    pub fn open(path: &Path) -> Result<(Self, Buffer)> {
+        Self::with_chunk_size(path, CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(path: &Path, chunk_size: usize) -> Result<(Self, Buffer)> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut backend = Self {
+            file,
+            file_size,
+            chunk_size,
+            window: 0..0,
+            first_line: LineNumber::Exact(0),
+            checkpoints: vec![(0, 0)],
+        };
+        let mut buffer = Buffer::default();
+        backend.seek(&mut buffer, SeekPosition::Start)?;
+        Ok((backend, buffer))
+    }
+
+    fn window_size(&self) -> u64 {
+        (self.chunk_size * WINDOW_CHUNKS) as u64
+    }
+
+    fn read_range(&mut self, range: Range<u64>) -> Result<Vec<u8>> {
+        let mut bytes = vec![0; (range.end - range.start) as usize];
+        self.file.seek(SeekFrom::Start(range.start))?;
+        self.file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads a window starting at the first line boundary at or after the offset
+    fn read_window(&mut self, offset: u64) -> Result<(Range<u64>, Vec<u8>)> {
+        let offset = offset.min(self.file_size);
+        let end = (offset + self.window_size()).min(self.file_size);
+
+        // Read one byte before the offset to know if it starts a line
+        let read_start = offset.saturating_sub(1);
+        let mut bytes = self.read_range(read_start..end)?;
+        let skip = if offset == 0 {
+            0
+        } else {
+            bytes
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(0, |index| index + 1)
+        };
+        bytes.drain(..skip);
+        let start = read_start + skip as u64;
+
+        // Drop the trailing partial line unless the window reaches the end of the file
+        if end < self.file_size
+            && let Some(index) = bytes.iter().rposition(|&byte| byte == b'\n')
+        {
+            bytes.truncate(index + 1);
+        }
+        let end = start + bytes.len() as u64;
+
+        Ok((start..end, bytes))
+    }
+
+    fn load(
+        &mut self,
+        buffer: &mut Buffer,
+        range: Range<u64>,
+        bytes: &[u8],
+        first_line: LineNumber,
+    ) {
+        *buffer = Buffer::from_string(&String::from_utf8_lossy(bytes));
+        self.set_window(range, bytes, first_line);
+    }
+
+    fn set_window(&mut self, range: Range<u64>, bytes: &[u8], first_line: LineNumber) {
+        self.window = range;
+        self.first_line = first_line;
+
+        if let LineNumber::Exact(line) = first_line {
+            self.add_checkpoint(self.window.start, line);
+            let lines = bytes.iter().filter(|&&byte| byte == b'\n').count();
+            self.add_checkpoint(self.window.end, line + lines);
+        }
+    }
+
+    fn add_checkpoint(&mut self, offset: u64, line: usize) {
+        if let Err(index) = self
+            .checkpoints
+            .binary_search_by_key(&offset, |&(offset, _)| offset)
+        {
+            self.checkpoints.insert(index, (offset, line));
+        }
+    }
+
+    /// Nearest checkpoint at or before the offset
+    fn checkpoint_before(&self, offset: u64) -> (u64, usize) {
+        let index = self
+            .checkpoints
+            .partition_point(|&(checkpoint, _)| checkpoint <= offset);
+        self.checkpoints[index.saturating_sub(1)]
+    }
+
+    /// Line number of the line starting at the offset, counting lines from the
+    /// nearest checkpoint when it is close enough
+    fn line_at_offset(&mut self, offset: u64) -> Result<LineNumber> {
+        let (checkpoint, line) = self.checkpoint_before(offset);
+        if offset - checkpoint > (self.chunk_size * MAX_SCAN_CHUNKS) as u64 {
+            return Ok(LineNumber::AtLeast(line));
+        }
+
+        let mut line = line;
+        let mut position = checkpoint;
+        while position < offset {
+            let end = (position + self.chunk_size as u64).min(offset);
+            let bytes = self.read_range(position..end)?;
+            line += bytes.iter().filter(|&&byte| byte == b'\n').count();
+            position = end;
+            self.add_checkpoint(position, line);
+        }
+        Ok(LineNumber::Exact(line))
+    }
+
+    /// Finds the offset where the given line starts by scanning forward from the nearest checkpoint
+    fn offset_of_line(&mut self, target: usize) -> Result<u64> {
+        if target == 0 {
+            return Ok(0);
+        }
+        let index = self.checkpoints.partition_point(|&(_, line)| line < target);
+        let (mut position, mut line) = self.checkpoints[index - 1];
+
+        for _ in 0..MAX_SCAN_CHUNKS {
+            if position >= self.file_size {
+                return Ok(self.file_size);
+            }
+            let end = (position + self.chunk_size as u64).min(self.file_size);
+            let bytes = self.read_range(position..end)?;
+            for (index, _) in bytes.iter().enumerate().filter(|&(_, &byte)| byte == b'\n') {
+                line += 1;
+                if line == target {
+                    let offset = position + index as u64 + 1;
+                    self.add_checkpoint(offset, line);
+                    return Ok(offset);
+                }
+            }
+            position = end;
+            self.add_checkpoint(position, line);
+        }
+
+        Err(anyhow!(
+            "Line {} is too far beyond the indexed part of the file, use :N% to jump by position",
+            target + 1
+        ))
+    }
+
+    /// Loads a window around the offset and returns the row of the line containing it
+    fn load_around(&mut self, buffer: &mut Buffer, offset: u64) -> Result<usize> {
+        let start = offset.saturating_sub(self.window_size() / 2);
+        let (range, bytes) = self.read_window(start)?;
+        let first_line = self.line_at_offset(range.start)?;
+        let row_offset = offset.clamp(range.start, range.end) - range.start;
+        self.load(buffer, range, &bytes, first_line);
+        Ok(buffer.point_at_position(row_offset as usize).row)
+    }
+
+    fn slide_backward(&mut self, buffer: &mut Buffer) -> Result<isize> {
+        let old_start = self.window.start;
+        let (range, bytes) = self.read_window(old_start.saturating_sub(self.window_size() / 2))?;
+        *buffer = Buffer::from_string(&String::from_utf8_lossy(&bytes));
+
+        let added = buffer
+            .point_at_position((old_start - range.start) as usize)
+            .row;
+        let first_line = match self.first_line {
+            LineNumber::Exact(line) => LineNumber::Exact(line.saturating_sub(added)),
+            LineNumber::AtLeast(_) if range.start == 0 => LineNumber::Exact(0),
+            LineNumber::AtLeast(line) => LineNumber::AtLeast(line.saturating_sub(added)),
+        };
+        self.set_window(range, &bytes, first_line);
+        Ok(added as isize)
+    }
+
+    fn slide_forward(&mut self, buffer: &mut Buffer, row: usize) -> Result<isize> {
+        // Keep the rows above the cursor that are within the margin
+        let keep_from = buffer.cursor_position(&Point {
+            row: row.saturating_sub(SLIDE_MARGIN),
+            column: 0,
+        }) as u64;
+        let offset = self.window.start + keep_from.min(self.window_size() / 2);
+        let (range, bytes) = self.read_window(offset)?;
+
+        let removed = buffer
+            .point_at_position((range.start - self.window.start) as usize)
+            .row;
+        let first_line = self.first_line.offset(removed);
+        self.load(buffer, range, &bytes, first_line);
+        Ok(-(removed as isize))
+    }
+}
+
+impl BufferBackend for ChunkedBackend {
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_windowed(&self) -> bool {
+        true
+    }
+
+    fn line_number(&self, row: usize) -> LineNumber {
+        self.first_line.offset(row)
+    }
+
+    fn ensure_loaded(&mut self, buffer: &mut Buffer, row: usize) -> Result<Option<isize>> {
+        if row < SLIDE_MARGIN && self.window.start > 0 {
+            return self.slide_backward(buffer).map(Some);
+        }
+        if row + SLIDE_MARGIN >= buffer.line_count() && self.window.end < self.file_size {
+            return self.slide_forward(buffer, row).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn seek(&mut self, buffer: &mut Buffer, position: SeekPosition) -> Result<SeekResult> {
+        let row = match position {
+            SeekPosition::Start => {
+                let (range, bytes) = self.read_window(0)?;
+                self.load(buffer, range, &bytes, LineNumber::Exact(0));
+                0
+            }
+            SeekPosition::End => {
+                let (range, bytes) =
+                    self.read_window(self.file_size.saturating_sub(self.window_size()))?;
+                let first_line = self.line_at_offset(range.start)?;
+                self.load(buffer, range, &bytes, first_line);
+                buffer.line_count().saturating_sub(1)
+            }
+            SeekPosition::Line(line) => {
+                // Lines already in the window don't need a reload
+                if let LineNumber::Exact(first_line) = self.first_line
+                    && line >= first_line
+                    && line - first_line < buffer.line_count()
+                {
+                    return Ok(SeekResult {
+                        row: line - first_line,
+                        reloaded: false,
+                    });
+                }
+                let offset = self.offset_of_line(line)?;
+                self.load_around(buffer, offset)?
+            }
+            SeekPosition::Percent(percent) => {
+                let offset = self.file_size * percent.min(100) as u64 / 100;
+                self.load_around(buffer, offset)?
+            }
+        };
+
+        Ok(SeekResult {
+            row,
+            reloaded: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::document::Document;
+    use crate::editor::testing::TestEditor;
+    use std::path::PathBuf;
+
+    const LINE_COUNT: usize = 20_000;
+    const TEST_CHUNK_SIZE: usize = 1024;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn with_lines(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("viron-{}-{name}.log", std::process::id()));
+            let content = (0..LINE_COUNT)
+                .map(|line| format!("line {line}\n"))
+                .collect::<String>();
+            std::fs::write(&path, content).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// The line number written in the text of a buffer row
+    fn line_in_text(buffer: &Buffer, row: usize) -> usize {
+        let text = buffer.get_line_as_string(row);
+        text.trim_end().trim_start_matches("line ").parse().unwrap()
+    }
+
+    #[test]
+    fn test_slide_through_file() {
+        let file = TempFile::with_lines("slide");
+        let (mut backend, mut buffer) =
+            ChunkedBackend::with_chunk_size(&file.0, TEST_CHUNK_SIZE).unwrap();
+
+        assert!(backend.is_read_only());
+        assert!(buffer.line_count() < LINE_COUNT);
+        assert_eq!(line_in_text(&buffer, 0), 0);
+
+        // Walk down to the end of the file, one window edge at a time
+        let mut row = buffer.line_count() - 1;
+        while let Some(shift) = backend.ensure_loaded(&mut buffer, row).unwrap() {
+            row = row.saturating_add_signed(shift);
+            assert_eq!(
+                backend.line_number(row),
+                LineNumber::Exact(line_in_text(&buffer, row))
+            );
+            row = buffer.line_count() - 2;
+        }
+        assert_eq!(line_in_text(&buffer, row), LINE_COUNT - 1);
+
+        // And back up to the start
+        row = 0;
+        while let Some(shift) = backend.ensure_loaded(&mut buffer, row).unwrap() {
+            row = row.saturating_add_signed(shift);
+            assert_eq!(
+                backend.line_number(row),
+                LineNumber::Exact(line_in_text(&buffer, row))
+            );
+            row = 0;
+        }
+        assert_eq!(line_in_text(&buffer, 0), 0);
+    }
+
+    #[test]
+    fn test_seek() {
+        let file = TempFile::with_lines("seek");
+        let (mut backend, mut buffer) =
+            ChunkedBackend::with_chunk_size(&file.0, TEST_CHUNK_SIZE).unwrap();
+
+        // The end is too far from the start to be counted
+        let SeekResult { row, reloaded } = backend.seek(&mut buffer, SeekPosition::End).unwrap();
+        assert!(reloaded);
+        assert_eq!(buffer.get_line_as_string(row), "");
+        assert_eq!(line_in_text(&buffer, row - 1), LINE_COUNT - 1);
+        assert!(matches!(
+            backend.line_number(row - 1),
+            LineNumber::AtLeast(line) if line < LINE_COUNT - 1
+        ));
+        assert_eq!(LineNumber::AtLeast(123_455).to_string(), "~123,456+");
+
+        let SeekResult { row, .. } = backend
+            .seek(&mut buffer, SeekPosition::Percent(50))
+            .unwrap();
+        let line = line_in_text(&buffer, row);
+        assert!(line.abs_diff(LINE_COUNT / 2) < LINE_COUNT / 10);
+
+        let SeekResult { row, .. } = backend
+            .seek(&mut buffer, SeekPosition::Line(4_321))
+            .unwrap();
+        assert_eq!(line_in_text(&buffer, row), 4_321);
+        assert_eq!(backend.line_number(row), LineNumber::Exact(4_321));
+
+        // Lines within the loaded window don't reload the buffer
+        let SeekResult { row, reloaded } = backend
+            .seek(&mut buffer, SeekPosition::Line(4_330))
+            .unwrap();
+        assert!(!reloaded);
+        assert_eq!(line_in_text(&buffer, row), 4_330);
+
+        assert!(
+            backend
+                .seek(&mut buffer, SeekPosition::Line(LINE_COUNT - 1))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_editing_is_disabled() {
+        let file = TempFile::with_lines("read-only");
+        let mut editor = TestEditor::new("");
+        *editor.core.current_document_mut() = Document::from_large_file(&file.0).unwrap();
+
+        editor.keys("xddihello<Esc>p").await.unwrap();
+        let document = editor.core.current_document();
+        assert_eq!(document.buffer.get_line_as_string(0), "line 0\n");
+        assert!(!document.is_modified());
+    }
+
+    #[test]
+    fn test_line_labels() {
+        let file = TempFile::with_lines("labels");
+        let mut document = Document::from_large_file(&file.0).unwrap();
+        let (backend, buffer) = ChunkedBackend::with_chunk_size(&file.0, TEST_CHUNK_SIZE).unwrap();
+        (document.backend, document.buffer) = (Box::new(backend), buffer);
+        assert_eq!(document.line_label(9), "10");
+
+        // Past what was counted, the label is the approximate line number
+        let SeekResult { row, .. } = document.backend.seek(&mut document.buffer, SeekPosition::End).unwrap();
+        let label = document.line_label(row - 1);
+        assert!(label.starts_with('~') && label.ends_with('+'), "{label}");
+    }
+}
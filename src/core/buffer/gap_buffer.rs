@@ -1,3 +1,4 @@
+use crate::core::buffer::storage::TextStorage;
 use std::ops::Range;
 
 #[derive(Debug)]
@@ -35,16 +36,6 @@ where
         }
     }
 
-    pub fn get_range(&self, range: Range<usize>) -> impl Iterator<Item = &T> {
-        range.map(move |pos| {
-            if pos < self.gap_start {
-                &self.buffer[pos]
-            } else {
-                &self.buffer[pos + self.gap_end - self.gap_start]
-            }
-        })
-    }
-
     pub fn len_without_gap(&self) -> usize {
         self.buffer.len() - self.gap_len()
     }
@@ -82,18 +73,10 @@ where
         }
     }
 
-    pub fn insert_single(&mut self, value: T) {
-        if self.gap_len() == 0 {
-            self.expand_gap(2 * self.buffer.len());
-        }
-        self.buffer[self.gap_start] = value;
-        self.gap_start += 1;
-    }
-
     pub fn insert_multiple(&mut self, values: &[T]) {
         if self.gap_len() < values.len() {
-            let mut capacity = self.buffer.len();
-            while capacity < values.len() {
+            let mut capacity = self.buffer.len().max(INITIAL_CAPACITY);
+            while capacity < self.len_without_gap() + values.len() {
                 capacity *= 2;
             }
             self.expand_gap(capacity);
@@ -102,13 +85,6 @@ where
         self.gap_start += values.len();
     }
 
-    pub fn delete_single(&mut self) -> Option<T> {
-        let value = self.buffer.get(self.gap_end);
-        if value.is_some() {
-            self.gap_end += 1;
-        }
-        value.copied()
-    }
     pub fn backspace_single(&mut self) -> Option<T> {
         let value = self.buffer.get(self.gap_start - 1);
         if value.is_none() {
@@ -139,3 +115,34 @@ where
         self.gap_end = self.buffer.len();
     }
 }
+
+impl TextStorage for GapBuffer<u8> {
+    fn len(&self) -> usize {
+        self.len_without_gap()
+    }
+
+    fn insert(&mut self, position: usize, bytes: &[u8]) {
+        self.move_gap(position);
+        self.insert_multiple(bytes);
+    }
+
+    fn delete(&mut self, range: Range<usize>) -> Vec<u8> {
+        self.move_gap(range.start);
+        let count = range.len().min(self.buffer.len() - self.gap_end);
+        self.delete_multiple(count).unwrap_or_default()
+    }
+
+    fn copy_to(&self, range: Range<usize>, out: &mut Vec<u8>) {
+        // The range may fall on either side of the gap or across it
+        let before = range.start.min(self.gap_start)..range.end.min(self.gap_start);
+        let after = range.start.max(self.gap_start)..range.end.max(self.gap_start);
+        out.extend_from_slice(&self.buffer[before]);
+        out.extend_from_slice(&self.buffer[after.start + self.gap_len()..after.end + self.gap_len()]);
+    }
+
+    fn chunks(&self) -> Box<dyn Iterator<Item = &[u8]> + '_> {
+        let before = &self.buffer[..self.gap_start];
+        let after = &self.buffer[self.gap_end..];
+        Box::new([before, after].into_iter())
+    }
+}
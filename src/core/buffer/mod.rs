@@ -1,25 +1,30 @@
+use crate::core::buffer::storage::{StorageKind, TextStorage};
+use crate::core::history::edit::{Delete, Edit, Insert};
 use crate::core::utf8::Utf8CharIterator;
-use crate::core::{
-    buffer::gap_buffer::GapBuffer,
-    history::edit::{Delete, Edit, Insert},
-};
+use anyhow::{Result, anyhow};
+use std::io::Write;
+use std::ops::Range;
 use tree_sitter::Point;
 
+pub mod backend;
+pub mod chunked;
 pub mod gap_buffer;
+mod motion;
+pub mod rope;
+pub mod storage;
+pub mod text_object;
 
 #[derive(Debug)]
 pub struct Buffer {
-    buffer: GapBuffer<u8>,
+    storage: Box<dyn TextStorage>,
     line_starts: Vec<usize>,
     // pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Default for Buffer {
     fn default() -> Self {
-        let mut buffer = GapBuffer::default();
-        buffer.insert_single(b'\n');
         Self {
-            buffer,
+            storage: StorageKind::Gap.create(b"\n"),
             line_starts: vec![0],
         }
     }
@@ -30,18 +35,60 @@ impl Buffer {
         self.line_starts.len()
     }
 
+    /// Size of the content in bytes
+    pub fn len_bytes(&self) -> usize {
+        self.storage.len()
+    }
+
     pub fn to_string(&self) -> String {
         let bytes = self.to_bytes();
         String::from_utf8_lossy(&bytes).to_string()
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let prefix = &self.buffer.buffer[..self.buffer.gap_start];
-        let suffix = &self.buffer.buffer[self.buffer.gap_end..];
-        prefix.iter().chain(suffix.iter()).copied().collect()
+        self.range(0..self.storage.len())
+    }
+
+    /// The bytes of the text in order, borrowed from the storage without a copy
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        self.storage.chunks()
+    }
+
+    /// Writes the bytes of the text without copying them into one piece first
+    pub fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        self.chunks().try_for_each(|chunk| out.write_all(chunk))
     }
 
+    /// Calls `visit` with the row and bytes of each line, without its newline. Lines
+    /// are borrowed from the storage, only one across two chunks is copied, into a
+    /// vector kept for the next one
+    pub fn for_each_line(&self, mut visit: impl FnMut(usize, &[u8])) {
+        let mut row = 0;
+        let mut split = Vec::new();
+        for chunk in self.chunks() {
+            let mut rest = chunk;
+            while let Some(end) = rest.iter().position(|&byte| byte == b'\n') {
+                if split.is_empty() {
+                    visit(row, &rest[..end]);
+                } else {
+                    split.extend_from_slice(&rest[..end]);
+                    visit(row, &split);
+                    split.clear();
+                }
+                row += 1;
+                rest = &rest[end + 1..];
+            }
+            split.extend_from_slice(rest);
+        }
+        visit(row, &split);
+    }
+
+    /// Buffer of the text, kept in the storage suited to its size
     pub fn from_string(content: &str) -> Self {
+        Self::with_storage(content, StorageKind::for_size(content.len()))
+    }
+
+    pub fn with_storage(content: &str, kind: StorageKind) -> Self {
         let chars = content.as_bytes();
         let mut lines_start = vec![0];
         for (i, &byte) in chars.iter().enumerate() {
@@ -50,12 +97,17 @@ impl Buffer {
             }
         }
         Self {
-            buffer: GapBuffer::from_slice(&chars),
+            storage: kind.create(chars),
             line_starts: lines_start,
-            ..Default::default()
         }
     }
 
+    fn range(&self, range: Range<usize>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(range.len());
+        self.storage.copy_to(range, &mut bytes);
+        bytes
+    }
+
     pub fn get_line_as_bytes(&self, line: usize) -> Vec<u8> {
         if line >= self.line_count() {
             return Vec::new();
@@ -64,12 +116,9 @@ impl Buffer {
         let line_end = if line + 1 < self.line_starts.len() {
             self.line_starts[line + 1]
         } else {
-            self.buffer.len_without_gap()
+            self.storage.len()
         };
-        self.buffer
-            .get_range(line_start..line_end)
-            .copied()
-            .collect()
+        self.range(line_start..line_end)
     }
 
     pub fn get_lines(&self, start_line: usize, end_line: usize) -> String {
@@ -81,7 +130,7 @@ impl Buffer {
         let line_end = if end_line + 1 < self.line_starts.len() {
             self.line_starts[end_line + 1]
         } else {
-            self.buffer.len_without_gap()
+            self.storage.len()
         };
         self.get_string(line_start, line_end - line_start)
     }
@@ -101,48 +150,94 @@ impl Buffer {
     }
 
     pub fn get_line_length_bytes(&self, line: usize) -> usize {
-        if line > self.line_count() {
+        if line >= self.line_count() {
             return 0;
         }
         let line_end = if line + 1 < self.line_starts.len() {
             self.line_starts[line + 1] - 1
         } else {
-            self.buffer.len_without_gap()
+            self.storage.len()
         };
         line_end - self.line_starts[line]
     }
 
+    /// UTF-16 code units before the byte column of the line, as LSP positions count
+    pub fn byte_column_to_utf16(&self, line: usize, column: usize) -> usize {
+        byte_column_to_utf16(&self.get_line_as_string(line), column)
+    }
+
+    /// Byte column of a column of the line counted in UTF-16 code units
+    pub fn utf16_to_byte_column(&self, line: usize, column: usize) -> usize {
+        utf16_to_byte_column(&self.get_line_as_string(line), column)
+    }
+
+    /// Byte offset of the point, clamped into the text so that a stale point can't
+    /// panic: a row past the end is the last line, and a column past the line end or
+    /// inside a character moves back to the line end or the start of the character
     pub fn cursor_position(&self, cursor: &Point) -> usize {
-        self.line_starts[cursor.row] + cursor.column
+        let row = cursor.row.min(self.line_count() - 1);
+        let start = self.line_starts[row];
+        let mut position = start + cursor.column.min(self.get_line_length_bytes(row));
+        while position > start && !self.is_char_boundary(position) {
+            position -= 1;
+        }
+        position
+    }
+
+    /// Byte offset of the point, or an error when it's outside the text or inside a
+    /// character, for points that are better reported than clamped
+    pub fn checked_cursor_position(&self, point: &Point) -> Result<usize> {
+        let Point { row, column } = *point;
+        if row >= self.line_count() {
+            return Err(anyhow!("Line {} is past the end ({} lines)", row + 1, self.line_count()));
+        }
+        if column > self.get_line_length_bytes(row) {
+            return Err(anyhow!("Column {} is past the end of line {}", column + 1, row + 1));
+        }
+        let position = self.line_starts[row] + column;
+        if !self.is_char_boundary(position) {
+            return Err(anyhow!("Column {} of line {} is inside a character", column + 1, row + 1));
+        }
+        Ok(position)
+    }
+
+    /// The point moved into the text the way `cursor_position` clamps it
+    pub fn clamp_point(&self, point: &Point) -> Point {
+        self.point_at_position(self.cursor_position(point))
+    }
+
+    fn is_char_boundary(&self, position: usize) -> bool {
+        if position >= self.storage.len() {
+            return true;
+        }
+        // Bytes after the first of a UTF-8 character are 10xxxxxx
+        self.range(position..position + 1)[0] & 0xC0 != 0x80
     }
 
     pub fn get_char(&self, position: usize) -> Option<char> {
-        if position >= self.buffer.len_without_gap() {
+        if position >= self.storage.len() {
             return None;
         };
 
-        let char_len = usize::min(4, self.buffer.len_without_gap() - position);
-        let bytes = self
-            .buffer
-            .get_range(position..position + char_len)
-            .copied()
-            .collect();
+        let char_len = usize::min(4, self.storage.len() - position);
+        let bytes = self.range(position..position + char_len);
 
-        let string = String::from_utf8(bytes).ok()?;
-        string.chars().next()
+        // The bytes read can end partway through a character after the first one
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(valid) => valid,
+            Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        };
+        valid.chars().next()
     }
 
     pub fn get_bytes(&self, position: usize, byte_count: usize) -> Vec<u8> {
-        if position >= self.buffer.len_without_gap()
-            || position + byte_count > self.buffer.len_without_gap()
+        if position >= self.storage.len()
+            || position + byte_count > self.storage.len()
             || byte_count == 0
         {
             return Vec::new();
         }
-        self.buffer
-            .get_range(position..position + byte_count)
-            .cloned()
-            .collect()
+        self.range(position..position + byte_count)
     }
 
     pub fn get_string(&self, position: usize, byte_count: usize) -> String {
@@ -157,11 +252,7 @@ impl Buffer {
     }
 
     pub fn insert_bytes(&mut self, position: usize, bytes: &[u8]) -> usize {
-        // Move gap to insertion byte_position
-        self.buffer.move_gap(position);
-
-        // Insert the character
-        self.buffer.insert_multiple(bytes);
+        self.storage.insert(position, bytes);
 
         // Update line starts for all lines after the current one
         let row = self.row_at_position(position);
@@ -194,19 +285,17 @@ impl Buffer {
     }
 
     pub fn delete_char(&mut self, position: usize) -> Option<(char, usize)> {
-        if position >= self.buffer.len_without_gap() {
+        if position >= self.storage.len() {
             return None;
         }
 
-        self.buffer.move_gap(position);
-
         // For UTF-8, we need to determine how many bytes to delete
-        let remaining_bytes = self.buffer.len_without_gap() - position;
+        let remaining_bytes = self.storage.len() - position;
         if remaining_bytes == 0 {
             return None;
         }
 
-        let first_byte = *self.buffer.get_range(position..position + 1).next()?;
+        let first_byte = *self.range(position..position + 1).first()?;
         let char_len = if first_byte < 0x80 {
             1
         } else if first_byte < 0xE0 {
@@ -218,15 +307,7 @@ impl Buffer {
         };
 
         let char_len = char_len.min(remaining_bytes);
-        let mut char_bytes = Vec::with_capacity(char_len);
-
-        for _ in 0..char_len {
-            if let Some(byte) = self.buffer.delete_single() {
-                char_bytes.push(byte);
-            } else {
-                break;
-            }
-        }
+        let char_bytes = self.storage.delete(position..position + char_len);
 
         let deleted_char = String::from_utf8_lossy(&char_bytes)
             .chars()
@@ -275,7 +356,7 @@ impl Buffer {
         let end = if line + 1 < self.line_starts.len() {
             self.line_starts[line + 1]
         } else {
-            self.buffer.len_without_gap()
+            self.storage.len()
         };
         self.delete_string(start, end - start)
     }
@@ -294,7 +375,7 @@ impl Buffer {
         let end = if end_line + 1 < self.line_starts.len() {
             self.line_starts[end_line + 1]
         } else {
-            self.buffer.len_without_gap()
+            self.storage.len()
         };
 
         self.delete_string(start, end - start)
@@ -318,6 +399,11 @@ impl Buffer {
                     self.delete_char(*position);
                 }
             }
+            Edit::Group(edits) => {
+                for edit in edits {
+                    self.apply_edit(edit);
+                }
+            }
         }
     }
 
@@ -336,3 +422,170 @@ impl Buffer {
         Point { row, column }
     }
 }
+
+/// UTF-16 code units before the byte column of the line. Columns past its end count as
+/// a unit each
+pub fn byte_column_to_utf16(line: &str, column: usize) -> usize {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let units = line
+        .char_indices()
+        .take_while(|&(index, _)| index < column)
+        .map(|(_, c)| c.len_utf16())
+        .sum::<usize>();
+    units + column.saturating_sub(line.len())
+}
+
+/// Byte column of a column of the line counted in UTF-16 code units, at the start of
+/// a character it falls inside of. Units past the end of the line count as a byte each,
+/// so that the column is still past it
+pub fn utf16_to_byte_column(line: &str, column: usize) -> usize {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        units += c.len_utf16();
+        if units > column {
+            return index;
+        }
+    }
+    line.len() + (column - units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORAGES: [StorageKind; 2] = [StorageKind::Gap, StorageKind::Rope];
+
+    /// Xorshift generator, so the edits are the same on every run
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound.max(1) as u64) as usize
+        }
+
+        /// Byte position of a random character boundary of the text
+        fn boundary(&mut self, text: &str) -> usize {
+            let boundaries = text.char_indices().map(|(index, _)| index).chain([text.len()]);
+            boundaries.clone().nth(self.below(text.chars().count() + 1)).unwrap()
+        }
+    }
+
+    fn assert_lines(buffer: &Buffer, expected: &str) {
+        assert_eq!(buffer.to_string(), expected);
+        assert_eq!(buffer.line_count(), expected.matches('\n').count() + 1);
+        let lines = (0..buffer.line_count())
+            .map(|row| buffer.get_line_as_string(row))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.concat(), expected);
+        let (last, rest) = lines.split_last().unwrap();
+        assert!(!last.contains('\n'));
+        assert!(rest.iter().all(|line| line.find('\n') == Some(line.len() - 1)));
+    }
+
+    #[test]
+    fn test_random_edits() {
+        const PIECES: [&str; 5] = ["a", "bc\n", "\n", "é", "long text without a newline "];
+
+        for kind in STORAGES {
+            let mut random = Random(0x2545_f491_4f6c_dd1d);
+            let mut expected = String::from("start\n");
+            let mut buffer = Buffer::with_storage(&expected, kind);
+
+            for _ in 0..500 {
+                let position = random.boundary(&expected);
+                match random.below(3) {
+                    0 => {
+                        let text = (0..random.below(300))
+                            .map(|_| PIECES[random.below(PIECES.len())])
+                            .collect::<String>();
+                        buffer.insert_string(position, &text);
+                        expected.insert_str(position, &text);
+                    }
+                    1 => {
+                        let end = random.boundary(&expected).max(position);
+                        let (deleted, _) = buffer.delete_string(position, end - position).unwrap();
+                        assert_eq!(deleted, expected[position..end]);
+                        expected.replace_range(position..end, "");
+                    }
+                    _ => {
+                        let deleted = buffer.delete_char(position).map(|(c, _)| c);
+                        assert_eq!(deleted, expected[position..].chars().next());
+                        if deleted.is_some() {
+                            expected.remove(position);
+                        }
+                    }
+                }
+                assert_lines(&buffer, &expected);
+                let row = random.below(buffer.line_count());
+                let point = Point { row, column: 0 };
+                assert_eq!(buffer.point_at_position(buffer.cursor_position(&point)), point);
+            }
+        }
+    }
+
+    #[test]
+    fn test_points_outside_the_text() {
+        for kind in STORAGES {
+            // `é` is bytes 4 and 5, the last line is empty
+            let buffer = Buffer::with_storage("ab\nhé\n", kind);
+            let cases = [((0, 9), 2), ((1, 2), 4), ((1, 9), 6), ((2, 3), 7), ((40, 0), 7)];
+            for ((row, column), position) in cases {
+                let point = Point { row, column };
+                assert_eq!(buffer.cursor_position(&point), position, "{kind:?} {point:?}");
+                assert!(buffer.checked_cursor_position(&point).is_err(), "{kind:?} {point:?}");
+            }
+            let clamped = buffer.clamp_point(&Point { row: 1, column: 2 });
+            assert_eq!(clamped, Point { row: 1, column: 1 });
+
+            assert_eq!(buffer.checked_cursor_position(&Point { row: 1, column: 3 }).unwrap(), 6);
+            let error = |row, column| {
+                buffer.checked_cursor_position(&Point { row, column }).unwrap_err().to_string()
+            };
+            assert_eq!(error(1, 2), "Column 3 of line 2 is inside a character");
+            assert_eq!(error(0, 3), "Column 4 is past the end of line 1");
+            assert_eq!(error(3, 0), "Line 4 is past the end (3 lines)");
+        }
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        for kind in STORAGES {
+            // `é` is two bytes and one unit, `😀` four bytes and two units
+            let buffer = Buffer::with_storage("é😀x\n", kind);
+            let columns = [(0, 0), (2, 1), (6, 3), (7, 4), (9, 6)];
+            for (bytes, units) in columns {
+                assert_eq!(buffer.byte_column_to_utf16(0, bytes), units, "{kind:?} {bytes}");
+                assert_eq!(buffer.utf16_to_byte_column(0, units), bytes, "{kind:?} {units}");
+            }
+            // Inside the surrogate pair of `😀`
+            assert_eq!(buffer.utf16_to_byte_column(0, 2), 2);
+        }
+    }
+
+    #[test]
+    fn test_chunks() {
+        for kind in STORAGES {
+            let text = "ab€\n".repeat(1000);
+            let mut buffer = Buffer::with_storage(&text, kind);
+            // The second byte of `é` goes in first, so the first ends up before the gap
+            buffer.insert_bytes(3600, &[0xa9]);
+            buffer.insert_bytes(3600, &[0xc3]);
+            let mut expected = text.clone();
+            expected.insert(3600, 'é');
+            assert!(buffer.chunks().any(|chunk| std::str::from_utf8(chunk).is_err()), "{kind:?}");
+
+            let mut written = Vec::new();
+            buffer.write_to(&mut written).unwrap();
+            assert_eq!(written, buffer.to_bytes());
+            assert_eq!(written, expected.as_bytes());
+            let mut lines = Vec::new();
+            buffer.for_each_line(|row, line| lines.push((row, String::from_utf8(line.to_vec()).unwrap())));
+            let expected = expected.split('\n').map(str::to_string).enumerate().collect::<Vec<_>>();
+            assert_eq!(lines, expected);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::core::buffer::Buffer;
+use crate::core::keyword_chars::KeywordChars;
+use tree_sitter::Point;
+
+const SENTENCE_TERMINATORS: [char; 3] = ['.', '!', '?'];
+const SENTENCE_CLOSERS: [char; 4] = [')', ']', '"', '\''];
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+impl Buffer {
+    /// Returns true if the line has no content besides its line break
+    pub fn is_blank_line(&self, line: usize) -> bool {
+        line < self.line_count() && self.get_line_length_bytes(line) == 0
+    }
+
+    /// Keyword under the point, or the first one after it on the same line
+    pub fn word_at(&self, point: &Point, keywords: &KeywordChars) -> Option<String> {
+        let (start, end) = self.word_bounds(point, keywords)?;
+        Some(self.get_line_as_string(point.row)[start..end].to_string())
+    }
+
+    /// Byte columns where the keyword `word_at` finds starts and ends
+    pub fn word_bounds(&self, point: &Point, keywords: &KeywordChars) -> Option<(usize, usize)> {
+        let line = self.get_line_as_string(point.row);
+        let chars = line.char_indices().collect::<Vec<_>>();
+        let cursor = chars.iter().position(|(byte, _)| *byte >= point.column)?;
+        let start = cursor + chars[cursor..].iter().position(|(_, c)| keywords.is_keyword(*c))?;
+
+        let word_start = chars[..start]
+            .iter()
+            .rposition(|(_, c)| !keywords.is_keyword(*c))
+            .map_or(0, |index| index + 1);
+        let word_end = chars[start..]
+            .iter()
+            .find(|(_, c)| !keywords.is_keyword(*c))
+            .map_or(line.len(), |(byte, _)| *byte);
+        Some((chars[word_start].0, word_end))
+    }
+
+    /// Point at the end of the last line, used when a motion runs off the buffer
+    fn end_point(&self) -> Point {
+        let row = self.line_count().saturating_sub(1);
+        Point {
+            row,
+            column: self.get_line_length_bytes(row),
+        }
+    }
+
+    /// Finds the next paragraph boundary (blank line) after the given point
+    pub fn next_paragraph_start(&self, point: &Point) -> Point {
+        let line_count = self.line_count();
+        let mut row = point.row;
+
+        // Skip the blank lines at and below the cursor
+        while row < line_count && self.is_blank_line(row) {
+            row += 1;
+        }
+
+        // Stop on the first blank line after the paragraph body
+        while row < line_count {
+            if self.is_blank_line(row) {
+                return Point { row, column: 0 };
+            }
+            row += 1;
+        }
+
+        self.end_point()
+    }
+
+    /// Finds the previous paragraph boundary (blank line) before the given point
+    pub fn previous_paragraph_start(&self, point: &Point) -> Point {
+        let mut row = point.row;
+
+        // Skip the blank lines at and above the cursor
+        while row > 0 && self.is_blank_line(row) {
+            row -= 1;
+        }
+
+        // Stop on the first blank line before the paragraph body
+        while row > 0 {
+            if self.is_blank_line(row) {
+                return Point { row, column: 0 };
+            }
+            row -= 1;
+        }
+
+        Point { row: 0, column: 0 }
+    }
+
+    /// First line a sentence boundary on `row` depends on: the line above it,
+    /// and any whitespace or closing-bracket lines between it and the text before
+    fn sentence_context(&self, row: usize) -> usize {
+        let mut start = row.saturating_sub(1);
+        while start > 0
+            && !self.is_blank_line(start)
+            && self
+                .get_line_as_string(start)
+                .chars()
+                .all(|c| c.is_whitespace() || SENTENCE_CLOSERS.contains(&c))
+        {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Characters of the lines from `start` to `end`, with their byte positions in the buffer
+    fn lines_chars(&self, start: usize, end: usize) -> Vec<(usize, char)> {
+        let offset = self.line_starts[start];
+        self.get_lines(start, end)
+            .char_indices()
+            .map(|(byte, c)| (offset + byte, c))
+            .collect()
+    }
+
+    /// Sentence boundaries on `row`, in order, as byte positions in the buffer
+    fn sentence_boundaries(&self, row: usize) -> Vec<usize> {
+        let chars = self.lines_chars(self.sentence_context(row), row);
+        let row_start = self.line_starts[row];
+        (0..chars.len())
+            .filter(|&index| chars[index].0 >= row_start && is_sentence_boundary(&chars, index))
+            .map(|index| chars[index].0)
+            .collect()
+    }
+
+    /// Finds the start of the next sentence after the given point
+    pub fn next_sentence_start(&self, point: &Point) -> Point {
+        let position = self.cursor_position(point);
+
+        (point.row..self.line_count())
+            .find_map(|row| {
+                let boundaries = self.sentence_boundaries(row);
+                boundaries.into_iter().find(|&byte| byte > position)
+            })
+            .map(|byte| self.point_at_position(byte))
+            .unwrap_or_else(|| self.end_point())
+    }
+
+    /// Finds the start of the current or previous sentence before the given point
+    pub fn previous_sentence_start(&self, point: &Point) -> Point {
+        let position = self.cursor_position(point);
+        let row = point.row.min(self.line_count().saturating_sub(1));
+
+        (0..=row)
+            .rev()
+            .find_map(|row| {
+                let boundaries = self.sentence_boundaries(row);
+                boundaries.into_iter().rev().find(|&byte| byte < position)
+            })
+            .map(|byte| self.point_at_position(byte))
+            .unwrap_or_default()
+    }
+
+    /// Finds the end (exclusive) of the word under the point, extended over
+    /// `count - 1` more words, without including the whitespace after it
+    pub fn end_of_word(&self, point: &Point, count: usize, keywords: &KeywordChars) -> Point {
+        let mut chars = self.chars_from(point).peekable();
+        let mut current = chars.next();
+
+        for word in 0..count.max(1) {
+            if word > 0 {
+                current = chars.next();
+                while let Some((_, c)) = current
+                    && c.is_whitespace()
+                {
+                    current = chars.next();
+                }
+            }
+            let Some((_, first)) = current else {
+                break;
+            };
+            let keyword_type = keywords.is_keyword(first);
+            while let Some(&(_, next)) = chars.peek()
+                && !next.is_whitespace()
+                && keywords.is_keyword(next) == keyword_type
+            {
+                current = chars.next();
+            }
+        }
+
+        match chars.next() {
+            Some((point, _)) => point,
+            None => self.end_point(),
+        }
+    }
+
+    /// Characters from the point onwards, read a line at a time
+    fn chars_from(&self, point: &Point) -> impl Iterator<Item = (Point, char)> + '_ {
+        let start = *point;
+        (start.row..self.line_count()).flat_map(move |row| {
+            let line = self.get_line_as_string(row);
+            let column = if row == start.row { start.column } else { 0 };
+            line.char_indices()
+                .filter(|&(byte, _)| byte >= column)
+                .map(|(byte, c)| (Point { row, column: byte }, c))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    /// The bracket matching the one under the point, or the one after it on the line,
+    /// skipping over nested pairs. `None` when there's no bracket or it's unmatched
+    pub fn matching_bracket(&self, point: &Point) -> Option<Point> {
+        let line = self.get_line_as_string(point.row);
+        let (column, bracket, (open, close)) = line
+            .char_indices()
+            .filter(|&(byte, _)| byte >= point.column)
+            .take_while(|&(_, c)| c != '\n')
+            .find_map(|(byte, c)| {
+                let pair = BRACKET_PAIRS.iter().find(|(open, close)| c == *open || c == *close)?;
+                Some((byte, c, *pair))
+            })?;
+
+        let forward = bracket == open;
+        let (same, other) = if forward { (open, close) } else { (close, open) };
+        let mut depth = 0;
+        let mut is_match = |c: char| {
+            match c {
+                c if c == same => depth += 1,
+                c if c == other && depth == 0 => return true,
+                c if c == other => depth -= 1,
+                _ => {}
+            }
+            false
+        };
+        if forward {
+            let after = Point { row: point.row, column: column + bracket.len_utf8() };
+            return self.chars_from(&after).find(|&(_, c)| is_match(c)).map(|(point, _)| point);
+        }
+        // Read backwards a line at a time, starting before the bracket
+        (0..=point.row).rev().find_map(|row| {
+            let text = match row == point.row {
+                true => line[..column].to_string(),
+                false => self.get_line_as_string(row),
+            };
+            let (byte, _) = text.char_indices().rev().find(|&(_, c)| is_match(c))?;
+            Some(Point { row, column: byte })
+        })
+    }
+}
+
+/// A sentence boundary is an empty line, or the first non-blank character
+/// following a sentence terminator (optionally closed by brackets or quotes)
+/// and at least one whitespace character.
+fn is_sentence_boundary(chars: &[(usize, char)], index: usize) -> bool {
+    let (_, current) = chars[index];
+
+    // Empty lines separate sentences the same way they separate paragraphs
+    if current == '\n' {
+        return index == 0 || chars[index - 1].1 == '\n';
+    }
+
+    if current.is_whitespace() {
+        return false;
+    }
+
+    let mut before = index;
+    while before > 0 && chars[before - 1].1.is_whitespace() {
+        before -= 1;
+    }
+
+    if before == index {
+        return index == 0;
+    }
+
+    let whitespace = &chars[before..index];
+    if whitespace.windows(2).any(|pair| pair[0].1 == '\n' && pair[1].1 == '\n') {
+        return true;
+    }
+
+    if before == 0 {
+        return true;
+    }
+
+    let mut last = before - 1;
+    while last > 0 && SENTENCE_CLOSERS.contains(&chars[last].1) {
+        last -= 1;
+    }
+    SENTENCE_TERMINATORS.contains(&chars[last].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(row: usize, column: usize) -> Point {
+        Point { row, column }
+    }
+
+    #[test]
+    fn test_paragraph_motions() {
+        let buffer = Buffer::from_string("first\nparagraph\n\n\nsecond\nparagraph\n\nthird\n");
+
+        assert_eq!(buffer.next_paragraph_start(&point(0, 2)), point(2, 0));
+        assert_eq!(buffer.next_paragraph_start(&point(2, 0)), point(6, 0));
+        assert_eq!(buffer.next_paragraph_start(&point(6, 0)), point(8, 0));
+
+        assert_eq!(buffer.previous_paragraph_start(&point(7, 3)), point(6, 0));
+        assert_eq!(buffer.previous_paragraph_start(&point(6, 0)), point(3, 0));
+        assert_eq!(buffer.previous_paragraph_start(&point(3, 0)), point(0, 0));
+    }
+
+    #[test]
+    fn test_end_of_word() {
+        let buffer = Buffer::from_string("foo.bar  baz\nqux");
+        let keywords = KeywordChars::default();
+        let end_of_word = |point: Point, count| buffer.end_of_word(&point, count, &keywords);
+
+        assert_eq!(end_of_word(point(0, 0), 1), point(0, 3));
+        assert_eq!(end_of_word(point(0, 2), 1), point(0, 3));
+        assert_eq!(end_of_word(point(0, 0), 3), point(0, 7));
+        assert_eq!(end_of_word(point(0, 4), 2), point(0, 12));
+        assert_eq!(end_of_word(point(0, 9), 2), point(1, 3));
+        assert_eq!(end_of_word(point(0, 6), 1), point(0, 7));
+        assert_eq!(end_of_word(point(1, 2), 1), point(1, 3));
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let buffer = Buffer::from_string("if (a[0] == b) {\n    f(c);\n}\nx)");
+
+        assert_eq!(buffer.matching_bracket(&point(0, 3)), Some(point(0, 13)));
+        assert_eq!(buffer.matching_bracket(&point(0, 13)), Some(point(0, 3)));
+        assert_eq!(buffer.matching_bracket(&point(0, 0)), Some(point(0, 13)));
+        assert_eq!(buffer.matching_bracket(&point(0, 6)), Some(point(0, 5)));
+        assert_eq!(buffer.matching_bracket(&point(0, 14)), Some(point(2, 0)));
+        assert_eq!(buffer.matching_bracket(&point(2, 0)), Some(point(0, 15)));
+        assert_eq!(buffer.matching_bracket(&point(1, 0)), Some(point(1, 7)));
+        assert_eq!(buffer.matching_bracket(&point(3, 0)), None);
+        assert_eq!(buffer.matching_bracket(&point(2, 1)), None);
+    }
+
+    #[test]
+    fn test_sentence_motions() {
+        let buffer = Buffer::from_string("One. Two?  (Three!) Four\nfive.\n\nSix");
+
+        assert_eq!(buffer.next_sentence_start(&point(0, 0)), point(0, 5));
+        assert_eq!(buffer.next_sentence_start(&point(0, 5)), point(0, 11));
+        assert_eq!(buffer.next_sentence_start(&point(0, 11)), point(0, 20));
+        assert_eq!(buffer.next_sentence_start(&point(0, 20)), point(2, 0));
+        assert_eq!(buffer.next_sentence_start(&point(2, 0)), point(3, 0));
+        assert_eq!(buffer.next_sentence_start(&point(3, 0)), point(3, 3));
+
+        assert_eq!(buffer.previous_sentence_start(&point(0, 22)), point(0, 20));
+        assert_eq!(buffer.previous_sentence_start(&point(0, 20)), point(0, 11));
+        assert_eq!(buffer.previous_sentence_start(&point(3, 1)), point(3, 0));
+        assert_eq!(buffer.previous_sentence_start(&point(0, 3)), point(0, 0));
+
+        let buffer = Buffer::from_string("Ends here.\n  \n  (Next\n");
+        assert_eq!(buffer.next_sentence_start(&point(0, 0)), point(2, 2));
+        assert_eq!(buffer.previous_sentence_start(&point(2, 4)), point(2, 2));
+    }
+}
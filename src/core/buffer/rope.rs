@@ -0,0 +1,152 @@
+use crate::core::buffer::storage::TextStorage;
+use std::ops::Range;
+
+/// Largest chunk before it is split
+const MAX_CHUNK: usize = 4096;
+/// Size of the chunks made when loading or splitting text
+const CHUNK_SIZE: usize = MAX_CHUNK / 2;
+/// Chunks smaller than this are merged with their neighbor after a delete
+const MIN_CHUNK: usize = MAX_CHUNK / 4;
+
+/// Flat rope: the text split into chunks of bounded size, so an edit only moves the
+/// bytes of the chunks it touches instead of everything between two edit positions
+#[derive(Debug)]
+pub struct Rope {
+    /// Never empty, an empty text is a single empty chunk
+    chunks: Vec<Vec<u8>>,
+    /// Position of the first byte of each chunk, to find a chunk by binary search
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl Rope {
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        let mut chunks = bytes
+            .chunks(CHUNK_SIZE)
+            .map(<[u8]>::to_vec)
+            .collect::<Vec<_>>();
+        if chunks.is_empty() {
+            chunks.push(Vec::new());
+        }
+        let mut rope = Self {
+            chunks,
+            starts: Vec::new(),
+            len: bytes.len(),
+        };
+        rope.update_starts(0);
+        rope
+    }
+
+    /// Recomputes the chunk starts from the chunk at the index on, after the chunks
+    /// there changed
+    fn update_starts(&mut self, from: usize) {
+        self.starts.truncate(from);
+        let mut start = match from {
+            0 => 0,
+            _ => self.starts[from - 1] + self.chunks[from - 1].len(),
+        };
+        for chunk in &self.chunks[from..] {
+            self.starts.push(start);
+            start += chunk.len();
+        }
+    }
+
+    /// Chunk holding the byte at the position and the offset of the byte in it.
+    /// The end of the text is the end of the last chunk
+    fn locate(&self, position: usize) -> (usize, usize) {
+        if position >= self.len {
+            let last = self.chunks.len() - 1;
+            return (last, self.chunks[last].len());
+        }
+        let index = self.starts.partition_point(|&start| start <= position) - 1;
+        (index, position - self.starts[index])
+    }
+
+    /// Merges the chunk with the next one if either got too small
+    fn merge_small(&mut self, index: usize) {
+        if index + 1 >= self.chunks.len() {
+            return;
+        }
+        let (current, next) = (self.chunks[index].len(), self.chunks[index + 1].len());
+        if (current < MIN_CHUNK || next < MIN_CHUNK) && current + next <= MAX_CHUNK {
+            let next = self.chunks.remove(index + 1);
+            self.chunks[index].extend(next);
+        }
+    }
+}
+
+impl TextStorage for Rope {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn insert(&mut self, position: usize, bytes: &[u8]) {
+        let (index, offset) = self.locate(position);
+        let chunk = &mut self.chunks[index];
+        chunk.splice(offset..offset, bytes.iter().copied());
+        self.len += bytes.len();
+
+        if chunk.len() > MAX_CHUNK {
+            let pieces = chunk
+                .chunks(CHUNK_SIZE)
+                .map(<[u8]>::to_vec)
+                .collect::<Vec<_>>();
+            self.chunks.splice(index..=index, pieces);
+        }
+        self.update_starts(index);
+    }
+
+    fn delete(&mut self, range: Range<usize>) -> Vec<u8> {
+        let range = range.start.min(self.len)..range.end.min(self.len);
+        if range.is_empty() {
+            return Vec::new();
+        }
+        let (first, start) = self.locate(range.start);
+        let (last, end) = self.locate(range.end);
+        let mut deleted = Vec::with_capacity(range.len());
+
+        if first == last {
+            deleted.extend(self.chunks[first].drain(start..end));
+        } else {
+            deleted.extend(self.chunks[first].drain(start..));
+            for chunk in &self.chunks[first + 1..last] {
+                deleted.extend_from_slice(chunk);
+            }
+            deleted.extend(self.chunks[last].drain(..end));
+        }
+        self.len -= deleted.len();
+
+        // The chunks in between and the ones emptied at the ends go in one step
+        let remove_start = if self.chunks[first].is_empty() { first } else { first + 1 };
+        let remove_end = if self.chunks[last].is_empty() { last + 1 } else { last };
+        self.chunks.drain(remove_start..remove_end.max(remove_start));
+        if self.chunks.is_empty() {
+            self.chunks.push(Vec::new());
+        }
+
+        let first = first.min(self.chunks.len() - 1);
+        self.merge_small(first);
+        if first > 0 {
+            self.merge_small(first - 1);
+        }
+        self.update_starts(first.saturating_sub(1));
+        deleted
+    }
+
+    fn copy_to(&self, range: Range<usize>, out: &mut Vec<u8>) {
+        let (mut index, mut offset) = self.locate(range.start);
+        let mut remaining = range.len();
+        while remaining > 0 && index < self.chunks.len() {
+            let chunk = &self.chunks[index];
+            let end = (offset + remaining).min(chunk.len());
+            out.extend_from_slice(&chunk[offset..end]);
+            remaining -= end - offset;
+            index += 1;
+            offset = 0;
+        }
+    }
+
+    fn chunks(&self) -> Box<dyn Iterator<Item = &[u8]> + '_> {
+        Box::new(self.chunks.iter().map(Vec::as_slice))
+    }
+}
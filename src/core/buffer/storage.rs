@@ -0,0 +1,51 @@
+use crate::core::buffer::gap_buffer::GapBuffer;
+use crate::core::buffer::rope::Rope;
+use std::fmt::Debug;
+use std::ops::Range;
+
+/// Texts larger than this are kept in a rope, where edits far apart stay cheap
+pub const ROPE_THRESHOLD: usize = 1024 * 1024;
+
+/// Bytes of a buffer, leaving line tracking to the buffer itself
+pub trait TextStorage: Debug {
+    /// Number of bytes stored
+    fn len(&self) -> usize;
+
+    fn insert(&mut self, position: usize, bytes: &[u8]);
+
+    /// Removes the bytes in the range and returns them
+    fn delete(&mut self, range: Range<usize>) -> Vec<u8>;
+
+    /// Appends the bytes in the range to `out`
+    fn copy_to(&self, range: Range<usize>, out: &mut Vec<u8>);
+
+    /// The stored bytes in order, as the slices they're kept in. A character may be
+    /// split between two of them
+    fn chunks(&self) -> Box<dyn Iterator<Item = &[u8]> + '_>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Fast for edits close to each other, such as typing
+    Gap,
+    /// Fast for edits spread over a large text
+    Rope,
+}
+
+impl StorageKind {
+    /// Storage suited to a text of the given size
+    pub fn for_size(size: usize) -> Self {
+        if size > ROPE_THRESHOLD {
+            StorageKind::Rope
+        } else {
+            StorageKind::Gap
+        }
+    }
+
+    pub fn create(self, bytes: &[u8]) -> Box<dyn TextStorage> {
+        match self {
+            StorageKind::Gap => Box::new(GapBuffer::from_slice(bytes)),
+            StorageKind::Rope => Box::new(Rope::from_slice(bytes)),
+        }
+    }
+}
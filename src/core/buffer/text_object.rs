@@ -0,0 +1,240 @@
+use crate::core::buffer::Buffer;
+use crate::core::keyword_chars::KeywordChars;
+use tree_sitter::Point;
+
+/// Characters that name a text object after `i` or `a`
+pub const TEXT_OBJECTS: &str = "wW\"'`()b{}B[]<>";
+
+/// Opening and closing characters of a bracket object, with vim's `b` and `B`
+fn brackets(object: char) -> Option<(char, char)> {
+    match object {
+        '(' | ')' | 'b' => Some(('(', ')')),
+        '{' | '}' | 'B' => Some(('{', '}')),
+        '[' | ']' => Some(('[', ']')),
+        '<' | '>' => Some(('<', '>')),
+        _ => None,
+    }
+}
+
+impl Buffer {
+    /// Start and exclusive end of the text object around the point: a word (`w`), a
+    /// word of non-blanks (`W`), a quoted string on the line or a bracketed block.
+    /// The inner object leaves out the quotes or brackets, the outer one also takes the
+    /// whitespace after a word or string. `None` when the point isn't in one
+    pub fn text_object(
+        &self,
+        point: &Point,
+        object: char,
+        inner: bool,
+        keywords: &KeywordChars,
+    ) -> Option<(Point, Point)> {
+        match object {
+            'w' => self.word_object(point, inner, |c| match c {
+                c if c.is_whitespace() => 0,
+                c if keywords.is_keyword(c) => 1,
+                _ => 2,
+            }),
+            'W' => self.word_object(point, inner, |c| u8::from(!c.is_whitespace())),
+            '"' | '\'' | '`' => self.quote_object(point, object, inner),
+            _ => {
+                let (open, close) = brackets(object)?;
+                self.bracket_object(point, open, close, inner)
+            }
+        }
+    }
+
+    /// Characters of the point's line without the line break, and the index of the
+    /// character under the point
+    fn line_chars(&self, point: &Point) -> Option<(Vec<(usize, char)>, usize)> {
+        let line = self.get_line_as_string(point.row);
+        let chars = line
+            .trim_end_matches('\n')
+            .char_indices()
+            .collect::<Vec<_>>();
+        let cursor = chars.iter().position(|(byte, _)| *byte >= point.column)?;
+        Some((chars, cursor))
+    }
+
+    /// Points on the row for a range of character indices into its characters
+    fn line_range(&self, row: usize, chars: &[(usize, char)], start: usize, end: usize) -> (Point, Point) {
+        let column = |index: usize| {
+            chars
+                .get(index)
+                .map_or(self.get_line_length_bytes(row), |(byte, _)| *byte)
+        };
+        (Point::new(row, column(start)), Point::new(row, column(end)))
+    }
+
+    /// The run of characters of the same class around the point. On a word the outer
+    /// object adds the whitespace after it, or before it at the end of the line, and
+    /// on whitespace it adds the word after it
+    fn word_object(&self, point: &Point, inner: bool, class: impl Fn(char) -> u8) -> Option<(Point, Point)> {
+        let (chars, cursor) = self.line_chars(point)?;
+        let run_end = |from: usize| {
+            let kind = class(chars[from].1);
+            chars[from..]
+                .iter()
+                .position(|(_, c)| class(*c) != kind)
+                .map_or(chars.len(), |offset| from + offset)
+        };
+        let kind = class(chars[cursor].1);
+        let mut start = chars[..cursor]
+            .iter()
+            .rposition(|(_, c)| class(*c) != kind)
+            .map_or(0, |index| index + 1);
+        let mut end = run_end(cursor);
+
+        if !inner {
+            let is_blank = |index: usize| chars[index].1.is_whitespace();
+            if is_blank(cursor) {
+                if end < chars.len() {
+                    end = run_end(end);
+                }
+            } else if end < chars.len() && is_blank(end) {
+                end = run_end(end);
+            } else if start > 0 && is_blank(start - 1) {
+                start = chars[..start]
+                    .iter()
+                    .rposition(|(_, c)| !c.is_whitespace())
+                    .map_or(0, |index| index + 1);
+            }
+        }
+        Some(self.line_range(point.row, &chars, start, end))
+    }
+
+    /// The string the point is in, or the next one on the line. Quotes pair up from the
+    /// start of the line, skipping those escaped with a backslash
+    fn quote_object(&self, point: &Point, quote: char, inner: bool) -> Option<(Point, Point)> {
+        let (chars, cursor) = self.line_chars(point)?;
+        let mut quotes = Vec::new();
+        let mut escaped = false;
+        for (index, &(_, c)) in chars.iter().enumerate() {
+            if c == quote && !escaped {
+                quotes.push(index);
+            }
+            escaped = c == '\\' && !escaped;
+        }
+        let (open, close) = quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|&(_, close)| cursor <= close)?;
+
+        if inner {
+            return Some(self.line_range(point.row, &chars, open + 1, close));
+        }
+        let end = chars[close + 1..]
+            .iter()
+            .position(|(_, c)| !c.is_whitespace())
+            .map_or(chars.len(), |offset| close + 1 + offset);
+        Some(self.line_range(point.row, &chars, open, end))
+    }
+
+    /// The innermost block around the point, which may span lines. Inside a block
+    /// whose brackets end and start lines, the inner object is the lines in between
+    fn bracket_object(&self, point: &Point, open: char, close: char, inner: bool) -> Option<(Point, Point)> {
+        // A closing bracket under the cursor belongs to the block it closes
+        let mut depth = 0;
+        let open_point = (0..=point.row).rev().find_map(|row| {
+            let line = self.get_line_as_string(row);
+            let at_cursor = |byte: usize| row == point.row && byte == point.column;
+            line.char_indices()
+                .rev()
+                .skip_while(|&(byte, _)| row == point.row && byte > point.column)
+                .find(|&(byte, c)| {
+                    match c {
+                        c if c == close && !at_cursor(byte) => depth += 1,
+                        c if c == open && depth == 0 => return true,
+                        c if c == open => depth -= 1,
+                        _ => {}
+                    }
+                    false
+                })
+                .map(|(column, _)| Point::new(row, column))
+        })?;
+        let mut depth = 0;
+        let close_point = (open_point.row..self.line_count()).find_map(|row| {
+            let line = self.get_line_as_string(row);
+            line.char_indices()
+                .skip_while(|&(byte, _)| row == open_point.row && byte <= open_point.column)
+                .find(|&(_, c)| {
+                    match c {
+                        c if c == open => depth += 1,
+                        c if c == close && depth == 0 => return true,
+                        c if c == close => depth -= 1,
+                        _ => {}
+                    }
+                    false
+                })
+                .map(|(column, _)| Point::new(row, column))
+        })?;
+
+        if !inner {
+            let end = Point::new(close_point.row, close_point.column + close.len_utf8());
+            return Some((open_point, end));
+        }
+
+        let after_open = open_point.column + open.len_utf8();
+        let start = match self.get_line_as_string(open_point.row)[after_open..].starts_with('\n') {
+            true => Point::new(open_point.row + 1, 0),
+            false => Point::new(open_point.row, after_open),
+        };
+        // A closing bracket with only indentation before it leaves its line out
+        let close_line = self.get_line_as_string(close_point.row);
+        let indented = close_line[..close_point.column].chars().all(|c| matches!(c, ' ' | '\t'));
+        let end = match indented && close_point.row > open_point.row {
+            true => Point::new(close_point.row, 0),
+            false => close_point,
+        };
+        Some((start, end.max(start)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(text: &str, column: usize, object: &str) -> Option<String> {
+        let buffer = Buffer::from_string(text);
+        let inner = object.starts_with('i');
+        let object = object.chars().nth(1).unwrap();
+        let point = Point::new(0, column);
+        let (start, end) = buffer.text_object(&point, object, inner, &KeywordChars::default())?;
+        let start = buffer.cursor_position(&start);
+        let end = buffer.cursor_position(&end);
+        Some(buffer.get_string(start, end - start))
+    }
+
+    #[test]
+    fn test_word_objects() {
+        let text = "foo.bar  baz";
+        assert_eq!(object(text, 5, "iw").as_deref(), Some("bar"));
+        assert_eq!(object(text, 5, "aw").as_deref(), Some("bar  "));
+        assert_eq!(object(text, 3, "iw").as_deref(), Some("."));
+        assert_eq!(object(text, 7, "aw").as_deref(), Some("  baz"));
+        assert_eq!(object(text, 10, "aw").as_deref(), Some("  baz"));
+        assert_eq!(object(text, 1, "iW").as_deref(), Some("foo.bar"));
+        assert_eq!(object("", 0, "iw"), None);
+    }
+
+    #[test]
+    fn test_quote_objects() {
+        let text = r#"say "a \"b\"" and "c" "#;
+        assert_eq!(object(text, 6, "i\"").as_deref(), Some(r#"a \"b\""#));
+        assert_eq!(object(text, 0, "a\"").as_deref(), Some(r#""a \"b\"" "#));
+        assert_eq!(object(text, 15, "i\"").as_deref(), Some("c"));
+        assert_eq!(object(text, 0, "i'"), None);
+    }
+
+    #[test]
+    fn test_bracket_objects() {
+        let text = "f(a, (b), c)";
+        assert_eq!(object(text, 2, "i(").as_deref(), Some("a, (b), c"));
+        assert_eq!(object(text, 6, "ab").as_deref(), Some("(b)"));
+        assert_eq!(object(text, 11, "i)").as_deref(), Some("a, (b), c"));
+        assert_eq!(object(text, 0, "i("), None);
+        assert_eq!(object("()", 0, "i(").as_deref(), Some(""));
+        assert_eq!(object("if {\n    x;\n  }", 3, "iB").as_deref(), Some("    x;\n"));
+        assert_eq!(object("(a", 1, "i("), None);
+        assert_eq!(object("(a, [b\n], c)\n", 1, "a(").as_deref(), Some("(a, [b\n], c)"));
+    }
+}
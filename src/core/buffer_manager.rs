@@ -3,12 +3,16 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use crate::core::buffer::Buffer;
-use crate::core::document::Document;
+use crate::core::document::{Document, canonicalize_path};
+use crate::core::jumps::JumpList;
 
 pub struct BufferManager {
     documents: Vec<Document>,
     current_index: usize,
+    /// The buffer that was current before, for `Ctrl-^` and `#` in commands
+    alternate_index: Option<usize>,
     path_to_index: HashMap<PathBuf, usize>,
+    pub jumps: JumpList,
 }
 
 impl BufferManager {
@@ -16,7 +20,9 @@ impl BufferManager {
         Self {
             documents: Vec::new(),
             current_index: 0,
+            alternate_index: None,
             path_to_index: HashMap::new(),
+            jumps: JumpList::default(),
         }
     }
 
@@ -24,6 +30,10 @@ impl BufferManager {
         self.documents.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
     // Get the current active document
     pub fn current(&self) -> &Document {
         &self.documents[self.current_index]
@@ -34,6 +44,23 @@ impl BufferManager {
         &mut self.documents[self.current_index]
     }
 
+    pub fn current_index(&self) -> usize {
+        self.current_index
+    }
+
+    /// The previously current document, if it's still open
+    pub fn alternate(&self) -> Option<&Document> {
+        self.alternate_index.map(|index| &self.documents[index])
+    }
+
+    /// Makes the document at the index current, the one it replaces becoming the alternate
+    fn set_current(&mut self, index: usize) {
+        if index != self.current_index && self.current_index < self.documents.len() {
+            self.alternate_index = Some(self.current_index);
+        }
+        self.current_index = index;
+    }
+
     /// Get the current active buffer
     pub fn current_buffer(&self) -> &Buffer {
         &self.current().buffer
@@ -44,29 +71,38 @@ impl BufferManager {
         &mut self.current_mut().buffer
     }
 
-    /// Open a file and add it to the buffer list
-    pub fn open_file(&mut self, path: &Path) -> usize {
-        let mut absolute_path = std::env::current_dir().unwrap_or_default();
-        absolute_path.push(path);
+    /// All open documents, in the order they were opened
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        self.documents.iter()
+    }
+
+    pub fn documents_mut(&mut self) -> impl Iterator<Item = &mut Document> {
+        self.documents.iter_mut()
+    }
+
+    /// Open a file and add it to the buffer list. Paths reaching the same file, through
+    /// `..` or symlinks, share one buffer that keeps the path it was first opened with
+    pub fn open_file(&mut self, path: &Path, large_file_threshold: u64) -> usize {
+        let canonical_path = canonicalize_path(path);
 
         // Check if file is already open
-        if let Some(&index) = self.path_to_index.get(&absolute_path) {
-            self.current_index = index;
+        if let Some(&index) = self.path_to_index.get(&canonical_path) {
+            self.set_current(index);
             return index;
         }
 
         // Load the document
-        let document = Document::from_file(path);
+        let document = Document::from_file(path, large_file_threshold);
 
         // Add to documents list
         let index = self.documents.len();
         self.documents.push(document);
 
         // Update path mapping
-        self.path_to_index.insert(absolute_path, index);
+        self.path_to_index.insert(canonical_path, index);
 
         // Set as current
-        self.current_index = index;
+        self.set_current(index);
 
         index
     }
@@ -80,14 +116,26 @@ impl BufferManager {
 
     /// Save the current buffer to a specific path
     pub fn save_current_as(&mut self, path: &Path) -> Result<String> {
+        self.name_current(path);
+        self.current_mut().save()?;
+        Ok(format!("Saved as {}", path.display()))
+    }
+
+    /// Gives the current buffer another file, as when an unnamed buffer is first written
+    pub fn name_current(&mut self, path: &Path) {
+        let index = self.current_index;
         let document = self.current_mut();
-        document.save_as(path)?;
+        let old_path = document.full_file_path();
+        document.set_path(path);
+        let new_path = document.full_file_path();
 
         // Update path mapping
-        self.path_to_index
-            .insert(path.to_path_buf(), self.current_index);
-
-        Ok(format!("Saved as {}", path.display()))
+        if let Some(old_path) = old_path {
+            self.path_to_index.remove(&old_path);
+        }
+        if let Some(new_path) = new_path {
+            self.path_to_index.insert(new_path, index);
+        }
     }
 
     /// Create a new empty buffer
@@ -95,14 +143,17 @@ impl BufferManager {
         let document = Document::new();
         let index = self.documents.len();
         self.documents.push(document);
-        self.current_index = index;
+        self.set_current(index);
         index
     }
 
-    /// Close the current buffer
+    /// Close the current buffer, showing the alternate one in its place when there is
+    /// one, and otherwise the buffer after it
     pub fn close_current(&mut self) -> Document {
         // Remove from path mapping if it has a path
-        let document = self.documents.remove(self.current_index);
+        let closed = self.current_index;
+        let mut document = self.documents.remove(closed);
+        document.remove_swap();
 
         if let Some(path) = document.full_file_path() {
             self.path_to_index.remove(&path);
@@ -120,24 +171,38 @@ impl BufferManager {
             self.current_index = self.documents.len().saturating_sub(1);
         }
 
+        // Indices after the closed document shift down, and an alternate that became
+        // current is forgotten
+        self.alternate_index = match self.alternate_index {
+            Some(index) if index > closed => Some(index - 1),
+            Some(index) if index < closed => Some(index),
+            _ => None,
+        }
+        .filter(|&index| index != self.current_index);
+        if let Some(index) = self.alternate_index.take() {
+            self.current_index = index;
+        }
+        self.jumps.remove_buffer(closed);
+
         document
     }
 
     /// Switch to the next buffer
     pub fn next_buffer(&mut self) {
         if !self.documents.is_empty() {
-            self.current_index = (self.current_index + 1) % self.documents.len();
+            self.set_current((self.current_index + 1) % self.documents.len());
         }
     }
 
     /// Switch to the previous buffer
     pub fn previous_buffer(&mut self) {
         if !self.documents.is_empty() {
-            self.current_index = if self.current_index == 0 {
+            let index = if self.current_index == 0 {
                 self.documents.len() - 1
             } else {
                 self.current_index - 1
             };
+            self.set_current(index);
         }
     }
 
@@ -146,10 +211,42 @@ impl BufferManager {
         if index >= self.documents.len() {
             return Err(anyhow::anyhow!("Invalid buffer index"));
         }
-        self.current_index = index;
+        self.set_current(index);
         Ok(())
     }
 
+    /// Buffers whose path contains the text, as `:b` takes them. One whose path or file
+    /// name is the text is taken over the others
+    pub fn find(&self, text: &str) -> Vec<usize> {
+        let matching = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter_map(|(index, document)| {
+                let path = document.path.as_ref()?.to_string_lossy().into_owned();
+                path.contains(text).then_some((index, path))
+            })
+            .collect::<Vec<_>>();
+        let exact = matching.iter().find(|(index, path)| {
+            path == text || self.documents[*index].file_name().as_deref() == Some(text)
+        });
+        match exact {
+            Some(&(index, _)) => vec![index],
+            None => matching.into_iter().map(|(index, _)| index).collect(),
+        }
+    }
+
+    /// Switch to the alternate buffer, returning false if there is none
+    pub fn switch_to_alternate(&mut self) -> bool {
+        match self.alternate_index {
+            Some(index) => {
+                self.set_current(index);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Get list of all open buffers
     pub fn list_buffers(&self) -> Vec<BufferInfo> {
         self.documents
@@ -157,10 +254,11 @@ impl BufferManager {
             .enumerate()
             .map(|(i, doc)| BufferInfo {
                 index: i,
-                name: doc.file_name().unwrap_or_else(|| "[No Name]".to_string()),
+                name: doc.display_name(),
                 path: doc.path.clone(),
                 is_current: i == self.current_index,
-                is_modified: doc.modified,
+                is_alternate: Some(i) == self.alternate_index,
+                is_modified: doc.is_modified(),
             })
             .collect()
     }
@@ -172,5 +270,6 @@ pub struct BufferInfo {
     pub name: String,
     pub path: Option<PathBuf>,
     pub is_current: bool,
+    pub is_alternate: bool,
     pub is_modified: bool,
 }
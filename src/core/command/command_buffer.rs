@@ -1,7 +1,14 @@
+use crate::core::command::History;
+use crate::core::command::completion::{Completion, path_matches, takes_file};
+
 #[derive(Debug, Clone, Default)]
 pub struct CommandBuffer {
     content: Vec<char>,
     cursor_position: usize,
+    /// What was submitted before, kept when the buffer is cleared
+    history: History,
+    /// Shown while Tab cycles through file names, until another key changes the content
+    completion: Option<Completion>,
 }
 
 impl CommandBuffer {
@@ -24,14 +31,113 @@ impl CommandBuffer {
     pub fn clear(&mut self) {
         self.content.clear();
         self.cursor_position = 0;
+        self.history.stop_recalling();
+        self.completion = None;
+    }
+
+    pub fn completion(&self) -> Option<&Completion> {
+        self.completion.as_ref()
+    }
+
+    /// Tab, or Shift-Tab going the other way: completes the file name before the cursor
+    /// of a command taking one, then cycles through the other matches. Returns false
+    /// when nothing matches
+    pub fn complete(&mut self, backward: bool) -> bool {
+        if let Some(completion) = &mut self.completion {
+            let count = completion.matches.len();
+            let step = if backward { count - 1 } else { 1 };
+            completion.selected = (completion.selected + step) % count;
+            self.insert_completion();
+            return true;
+        }
+
+        let before = self.content[..self.cursor_position].iter().collect::<String>();
+        let start = before.rfind(char::is_whitespace).map(|index| index + 1);
+        let command = before.split_whitespace().next().unwrap_or_default();
+        let Some(start) = start.filter(|_| takes_file(command)) else {
+            return false;
+        };
+        let matches = path_matches(&before[start..]);
+        let selected = match backward {
+            true => matches.len().saturating_sub(1),
+            false => 0,
+        };
+        let start = before[..start].chars().count();
+        let end = self.cursor_position;
+        match matches.len() {
+            0 => return false,
+            // A lone match is taken, so Tab goes on into a directory
+            1 => {
+                self.replace(start, end, &matches[0]);
+            }
+            _ => {
+                self.completion = Some(Completion { matches, selected, start, end });
+                self.insert_completion();
+            }
+        }
+        true
+    }
+
+    /// Puts the selected match in place of the one before
+    fn insert_completion(&mut self) {
+        let Some(completion) = &self.completion else {
+            return;
+        };
+        let (start, end) = (completion.start, completion.end);
+        let selected = completion.matches[completion.selected].clone();
+        let end_after = self.replace(start, end, &selected);
+        if let Some(completion) = &mut self.completion {
+            completion.end = end_after;
+        }
+    }
+
+    /// Replaces the characters from start to end, leaving the cursor after the text.
+    /// Returns where the text ends
+    fn replace(&mut self, start: usize, end: usize, text: &str) -> usize {
+        self.content.splice(start..end, text.chars());
+        self.cursor_position = start + text.chars().count();
+        self.cursor_position
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Adds a submitted entry to the history, keeping the newest `limit` entries
+    pub fn push_history(&mut self, entry: String, limit: usize) {
+        self.history.push(entry, limit);
+    }
+
+    /// Up, replacing the content with the previous entry starting with what was typed
+    pub fn recall_older(&mut self) {
+        if let Some(entry) = self.history.older(&self.content()) {
+            self.set_content(&entry);
+        }
+    }
+
+    /// Down, replacing the content with the next entry, or with what was typed after
+    /// the newest
+    pub fn recall_newer(&mut self) {
+        if let Some(entry) = self.history.newer() {
+            self.set_content(&entry);
+        }
+    }
+
+    /// Replaces the content, with the cursor at its end
+    pub fn set_content(&mut self, content: &str) {
+        self.completion = None;
+        self.content = content.chars().collect();
+        self.cursor_position = self.content.len();
     }
 
     pub fn insert_char(&mut self, ch: char) {
+        self.completion = None;
         self.content.insert(self.cursor_position, ch);
         self.cursor_position += 1;
     }
 
     pub fn delete_char(&mut self) -> bool {
+        self.completion = None;
         if self.empty() {
             return false;
         }
@@ -45,6 +151,7 @@ impl CommandBuffer {
     }
 
     pub fn backspace(&mut self) -> bool {
+        self.completion = None;
         if self.empty() {
             return false;
         }
@@ -56,12 +163,14 @@ impl CommandBuffer {
     }
 
     pub fn move_cursor_left(&mut self) {
+        self.completion = None;
         if self.cursor_position > 0 {
             self.cursor_position -= 1;
         }
     }
 
     pub fn move_cursor_right(&mut self) {
+        self.completion = None;
         if self.cursor_position < self.content.len() {
             self.cursor_position += 1;
         }
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+/// Commands taking a file name, which Tab completes
+const FILE_COMMANDS: [&str; 15] = [
+    "e", "edit", "e!", "edit!", "w", "write", "w!", "write!", "wq", "writequit", "wq!", "x",
+    "xit", "exit", "x!",
+];
+
+/// Matches for the word before the cursor, cycled through by Tab in its place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub matches: Vec<String>,
+    pub selected: usize,
+    /// Character range of the command line holding the selected match
+    pub(super) start: usize,
+    pub(super) end: usize,
+}
+
+/// Whether the argument of the command is a file name
+pub fn takes_file(command: &str) -> bool {
+    FILE_COMMANDS.contains(&command)
+}
+
+/// Replaces a leading `~` with the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            dirs::home_dir().unwrap_or_default().join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Files and directories starting with the partial path, written as it was typed and
+/// sorted, with a `/` after directories. Hidden ones are left out unless the partial
+/// name starts with a `.`
+pub fn path_matches(partial: &str) -> Vec<String> {
+    let (directory, prefix) = match partial.rfind('/') {
+        Some(index) => partial.split_at(index + 1),
+        None => ("", partial),
+    };
+    let listed = match directory {
+        "" => PathBuf::from("."),
+        directory => expand_home(directory),
+    };
+    let Ok(entries) = std::fs::read_dir(listed) else {
+        return Vec::new();
+    };
+    let mut matches = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || name.starts_with('.') && !prefix.starts_with('.') {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{directory}{name}{slash}"))
+        })
+        .collect::<Vec<_>>();
+    matches.sort();
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_matches() {
+        let dir = std::env::temp_dir().join(format!("viron-{}-complete", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        for file in ["main.rs", "map.txt", ".hidden", "other"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let root = format!("{}/", dir.display());
+
+        let matches = path_matches(&format!("{root}ma"));
+        assert_eq!(matches, [format!("{root}main.rs"), format!("{root}map.txt")]);
+        assert_eq!(path_matches(&format!("{root}s")), [format!("{root}src/")]);
+        assert_eq!(path_matches(&root).len(), 4);
+        assert_eq!(path_matches(&format!("{root}.")), [format!("{root}.hidden")]);
+        assert!(path_matches(&format!("{root}missing/")).is_empty());
+
+        let home = dirs::home_dir().unwrap_or_default();
+        assert_eq!(expand_home("~/notes.md"), home.join("notes.md"));
+        assert_eq!(expand_home("a~/b"), PathBuf::from("a~/b"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
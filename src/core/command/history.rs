@@ -0,0 +1,96 @@
+/// Entries submitted at a prompt, oldest first, recalled with Up and Down
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Entry being shown while recalling
+    position: Option<usize>,
+    /// What was typed before recalling started, shown again after the newest entry
+    typed: String,
+}
+
+impl History {
+    /// Adds the entry unless it repeats the newest one, dropping the oldest past the limit
+    pub fn push(&mut self, entry: String, limit: usize) {
+        self.position = None;
+        if entry.is_empty() || self.entries.last() == Some(&entry) {
+            return;
+        }
+        self.entries.push(entry);
+        let excess = self.entries.len().saturating_sub(limit);
+        self.entries.drain(..excess);
+    }
+
+    pub fn newest(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+
+    /// The entry before the one shown that starts with what was typed, beginning from
+    /// the newest. Past the oldest it returns `None` and the input stays as it is
+    pub fn older(&mut self, typed: &str) -> Option<String> {
+        let position = match self.position {
+            Some(position) => position,
+            None => {
+                self.typed = typed.to_string();
+                self.entries.len()
+            }
+        };
+        let index = self.entries[..position]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.typed))?;
+        self.position = Some(index);
+        Some(self.entries[index].clone())
+    }
+
+    /// The entry after the one shown that starts with what was typed, or what was
+    /// typed after the newest
+    pub fn newer(&mut self) -> Option<String> {
+        let position = self.position?;
+        let next = self.entries[position + 1..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.typed));
+        if let Some(offset) = next {
+            self.position = Some(position + 1 + offset);
+            return Some(self.entries[position + 1 + offset].clone());
+        }
+        self.position = None;
+        Some(std::mem::take(&mut self.typed))
+    }
+
+    /// Ends recalling, as when the prompt closes
+    pub fn stop_recalling(&mut self) {
+        self.position = None;
+        self.typed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recall() {
+        let mut history = History::default();
+        for entry in ["one", "two", "two", "three"] {
+            history.push(entry.to_string(), 3);
+        }
+        assert_eq!(history.older("").as_deref(), Some("three"));
+        assert_eq!(history.older("").as_deref(), Some("two"));
+        assert_eq!(history.older("").as_deref(), Some("one"));
+        assert_eq!(history.older(""), None);
+        assert_eq!(history.newer().as_deref(), Some("two"));
+        assert_eq!(history.newer().as_deref(), Some("three"));
+        assert_eq!(history.newer().as_deref(), Some(""));
+        assert_eq!(history.newer(), None);
+
+        // Only entries starting with what was typed are recalled
+        assert_eq!(history.older("t").as_deref(), Some("three"));
+        assert_eq!(history.older("").as_deref(), Some("two"));
+        assert_eq!(history.older(""), None);
+        assert_eq!(history.newer().as_deref(), Some("three"));
+        assert_eq!(history.newer().as_deref(), Some("t"));
+
+        // The oldest entry is dropped past the limit
+        history.push("four".to_string(), 3);
+        assert_eq!(history.entries, ["two", "three", "four"]);
+    }
+}
@@ -1,5 +1,9 @@
 mod command_buffer;
+mod completion;
+mod history;
 mod search_buffer;
 
 pub use command_buffer::CommandBuffer;
-pub use search_buffer::SearchBuffer;
\ No newline at end of file
+pub use completion::{Completion, expand_home};
+pub use history::History;
+pub use search_buffer::{SearchBuffer, SearchOffset, search_regex};
\ No newline at end of file
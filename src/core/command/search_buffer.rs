@@ -1,15 +1,104 @@
-use crate::core::{buffer::Buffer, command::CommandBuffer};
-use regex::Regex;
+use crate::core::keyword_chars::KeywordChars;
+use crate::core::buffer::Buffer;
+use crate::core::command::CommandBuffer;
+use anyhow::anyhow;
+use regex::{Match, Regex};
+use std::ops::Range;
 use tree_sitter::Point;
 
+/// Where the cursor lands relative to a match, written after the pattern,
+/// e.g. `/foo/e+1` or `/foo/-2`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOffset {
+    /// Characters from the start of the match
+    Start(isize),
+    /// Characters from the last character of the match
+    End(isize),
+    /// Lines below the match, landing on the first column
+    Lines(isize),
+}
+
+impl Default for SearchOffset {
+    fn default() -> Self {
+        SearchOffset::Start(0)
+    }
+}
+
+impl SearchOffset {
+    /// Splits `pattern/offset` at the first unescaped `/`
+    pub fn split(input: &str) -> anyhow::Result<(&str, SearchOffset)> {
+        let mut escaped = false;
+        let separator = input.char_indices().find(|&(_, c)| {
+            let found = c == '/' && !escaped;
+            escaped = c == '\\' && !escaped;
+            found
+        });
+        let Some((index, _)) = separator else {
+            return Ok((input, SearchOffset::default()));
+        };
+
+        let offset = &input[index + 1..];
+        let parsed = match offset.chars().next() {
+            None => Some(SearchOffset::default()),
+            Some('e') => parse_count(&offset[1..]).map(SearchOffset::End),
+            Some('s' | 'b') => parse_count(&offset[1..]).map(SearchOffset::Start),
+            Some(_) => parse_count(offset).map(SearchOffset::Lines),
+        };
+        let offset = parsed.ok_or_else(|| anyhow!("Invalid search offset: {offset}"))?;
+        Ok((&input[..index], offset))
+    }
+}
+
+/// Parses `+N`, `-N` or `N`, where a bare sign means one and nothing means zero
+fn parse_count(count: &str) -> Option<isize> {
+    match count {
+        "" => Some(0),
+        "+" => Some(1),
+        "-" => Some(-1),
+        count => count.strip_prefix('+').unwrap_or(count).parse().ok(),
+    }
+}
+
+/// Compiles a search pattern. `\V` in front makes the rest literal text, and with
+/// smart case a pattern without capitals ignores case. Escapes such as `\S` aren't
+/// capitals
+pub fn search_regex(pattern: &str, smart_case: bool) -> Result<Regex, regex::Error> {
+    let pattern = match pattern.strip_prefix(r"\V") {
+        Some(literal) => regex::escape(literal),
+        None => pattern.to_string(),
+    };
+    let mut escaped = false;
+    let has_capital = pattern.chars().any(|c| {
+        let capital = c.is_uppercase() && !escaped;
+        escaped = c == '\\' && !escaped;
+        capital
+    });
+    match smart_case && !has_capital {
+        true => Regex::new(&format!("(?i){pattern}")),
+        false => Regex::new(&pattern),
+    }
+}
+
+/// Start and last character of a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub start: Point,
+    pub end: Point,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SearchBuffer {
     pub buffer: CommandBuffer,
 
     // Search results
     pub last_search: String,
-    pub results: Vec<Point>,
+    pub offset: SearchOffset,
+    pub results: Vec<SearchMatch>,
     pub current: Option<usize>,
+    /// Where the cursor was put for the current match
+    landing: Option<Point>,
+    /// Pattern being typed, compiled as it changes so that its matches are highlighted
+    pub pattern: Option<Regex>,
 }
 
 impl SearchBuffer {
@@ -20,34 +109,147 @@ impl SearchBuffer {
     pub fn reset(&mut self) {
         self.buffer.clear();
         self.last_search.clear();
+        self.offset = SearchOffset::default();
         self.results.clear();
         self.current = None;
+        self.landing = None;
+        self.pattern = None;
     }
 
-    pub fn search(&mut self, pattern: &str, buffer: &Buffer) -> anyhow::Result<()> {
-        self.reset();
-        self.last_search = pattern.to_string();
-        let regex = Regex::new(pattern)?;
+    /// Clears what was typed, keeping the results of the last search
+    pub fn clear_input(&mut self) {
+        self.buffer.clear();
+        self.pattern = None;
+    }
 
-        // Find all matches in the buffer content
-        self.results = buffer
-            .to_string()
-            .lines()
+    /// Compiles the pattern typed so far, leaving out its offset. An incomplete
+    /// pattern, such as `foo(`, has no matches until it's valid again
+    pub fn update_pattern(&mut self, smart_case: bool) {
+        let input = self.buffer.content();
+        self.pattern = SearchOffset::split(&input)
+            .ok()
+            .filter(|(pattern, _)| !pattern.is_empty())
+            .and_then(|(pattern, _)| search_regex(pattern, smart_case).ok());
+    }
+
+    /// Matches on a line of the text to highlight, as ranges of characters and whether
+    /// each is the current match. While `typing`, they are those of the typed pattern
+    pub fn highlights(&self, row: usize, line: &str, typing: bool) -> Vec<(Range<usize>, bool)> {
+        if typing {
+            let Some(pattern) = &self.pattern else {
+                return Vec::new();
+            };
+            return pattern
+                .find_iter(line)
+                .filter(|m| !m.is_empty())
+                .filter_map(|m| {
+                    let start = byte_to_char_index(line, m.start())?;
+                    let end = byte_to_char_index(line, m.end())?;
+                    Some((start..end, false))
+                })
+                .collect();
+        }
+        let first = self.results.partition_point(|m| m.start.row < row);
+        self.results[first..]
+            .iter()
+            .take_while(|m| m.start.row == row)
             .enumerate()
-            .map(|(r, line)| {
-                regex
-                    .find_iter(line)
-                    .filter_map(|m| byte_to_char_index(line, m.start()))
-                    .map(|c| Point { row: r, column: c })
-                    .collect::<Vec<_>>()
+            .map(|(index, m)| {
+                let current = self.current == Some(first + index);
+                (m.start.column..m.end.column + 1, current)
             })
-            .flatten()
-            .collect();
+            .collect()
+    }
 
+    pub fn search(
+        &mut self,
+        pattern: &str,
+        offset: SearchOffset,
+        buffer: &Buffer,
+        smart_case: bool,
+    ) -> anyhow::Result<()> {
+        let regex = search_regex(pattern, smart_case)?;
+        self.search_with(pattern, offset, &regex, buffer, |_, _| true);
         Ok(())
     }
 
-    pub fn find_first(&mut self, point: &Point) -> Option<Point> {
+    /// Searches for the word where it isn't part of a longer one, for `*` and `#`. It's
+    /// shown as `\bword\b`, but the keyword characters decide where words end
+    /// Searches again for the newest pattern of the history after an edit cleared the
+    /// results, so that `n` and `N` keep going
+    pub fn search_again(&mut self, buffer: &Buffer, smart_case: bool) -> anyhow::Result<()> {
+        if !self.last_search.is_empty() {
+            return Ok(());
+        }
+        let Some(input) = self.buffer.history().newest().map(str::to_string) else {
+            return Ok(());
+        };
+        let (pattern, offset) = SearchOffset::split(&input)?;
+        self.search(pattern, offset, buffer, smart_case)
+    }
+
+    /// Pattern of the last search, which an edit clearing the results doesn't forget
+    pub fn last_pattern(&self) -> Option<String> {
+        if !self.last_search.is_empty() {
+            return Some(self.last_search.clone());
+        }
+        let input = self.buffer.history().newest()?;
+        let (pattern, _) = SearchOffset::split(input).ok()?;
+        Some(pattern.to_string())
+    }
+
+    pub fn search_word(&mut self, word: &str, keywords: &KeywordChars, buffer: &Buffer) {
+        let escaped = regex::escape(word);
+        let regex = Regex::new(&escaped).expect("An escaped word is a valid pattern");
+        let is_keyword = |c: Option<char>| c.is_some_and(|c| keywords.is_keyword(c));
+        let whole = |line: &str, m: &Match| {
+            !is_keyword(line[..m.start()].chars().next_back())
+                && !is_keyword(line[m.end()..].chars().next())
+        };
+        let pattern = format!(r"\b{escaped}\b");
+        self.search_with(&pattern, SearchOffset::default(), &regex, buffer, whole);
+    }
+
+    fn search_with(
+        &mut self,
+        pattern: &str,
+        offset: SearchOffset,
+        regex: &Regex,
+        buffer: &Buffer,
+        keep: impl Fn(&str, &Match) -> bool,
+    ) {
+        self.reset();
+        self.last_search = pattern.to_string();
+        self.offset = offset;
+
+        // Find all matches line by line, as the buffer stores them
+        let last_row = buffer.line_count() - 1;
+        let mut results = Vec::new();
+        buffer.for_each_line(|r, bytes| {
+            // Lines as `str::lines` has them, without the `\r` of a CRLF and the empty
+            // line after the last newline
+            if r == last_row && bytes.is_empty() {
+                return;
+            }
+            let line = String::from_utf8_lossy(bytes);
+            let line = match r < last_row {
+                true => line.strip_suffix('\r').unwrap_or(&line),
+                false => &line,
+            };
+            let matches = regex.find_iter(line).filter(|m| keep(line, m)).filter_map(|m| {
+                let start = byte_to_char_index(line, m.start())?;
+                let end = byte_to_char_index(line, m.end())?;
+                Some(SearchMatch {
+                    start: Point { row: r, column: start },
+                    end: Point { row: r, column: end.saturating_sub(1).max(start) },
+                })
+            });
+            results.extend(matches);
+        });
+        self.results = results;
+    }
+
+    pub fn find_first(&mut self, point: &Point, buffer: &Buffer) -> Option<Point> {
         if self.results.is_empty() {
             self.current = None;
             return None;
@@ -55,40 +257,99 @@ impl SearchBuffer {
         // Binary search for the first occurrence
         let index = self
             .results
-            .binary_search(point)
+            .binary_search_by_key(point, |m| m.start)
             .unwrap_or_else(|i| i.checked_sub(1).unwrap_or(0));
-        self.current = Some(index);
-        Some(self.results[index].clone())
+        Some(self.land(index, buffer))
     }
 
-    pub fn find_next(&mut self, point: &Point) -> Option<Point> {
+    pub fn find_next(&mut self, point: &Point, buffer: &Buffer) -> Option<Point> {
         if self.results.is_empty() {
             self.current = None;
             return None;
         }
         let len = self.results.len();
-        // Binary search for the next occurrence
-        let index = match self.results.binary_search(point) {
-            Ok(i) => i + 1,
-            Err(i) => i,
-        } % len;
-        self.current = Some(index);
-        Some(self.results[index].clone())
+        // Step from the current match, or binary search for the next occurrence
+        let index = match self.current_at(point) {
+            Some(current) => (current + 1) % len,
+            None => match self.results.binary_search_by_key(point, |m| m.start) {
+                Ok(i) => (i + 1) % len,
+                Err(i) => i % len,
+            },
+        };
+        Some(self.land(index, buffer))
     }
 
-    pub fn find_previous(&mut self, point: &Point) -> Option<Point> {
+    pub fn find_previous(&mut self, point: &Point, buffer: &Buffer) -> Option<Point> {
         if self.results.is_empty() {
             self.current = None;
             return None;
         }
         let len = self.results.len();
-        let index = match self.results.binary_search(point) {
-            Ok(i) => i.checked_sub(1).unwrap_or(len - 1),
-            Err(i) => i.checked_sub(1).unwrap_or(len - 1),
+        let index = match self.current_at(point) {
+            Some(current) => current.checked_sub(1).unwrap_or(len - 1),
+            None => match self.results.binary_search_by_key(point, |m| m.start) {
+                Ok(i) => i.checked_sub(1).unwrap_or(len - 1),
+                Err(i) => i.checked_sub(1).unwrap_or(len - 1),
+            },
+        };
+        Some(self.land(index, buffer))
+    }
+
+    /// The current match, if the cursor is still where it was put for it.
+    /// With an offset the cursor isn't on the match, so `n` steps from the match instead
+    fn current_at(&self, point: &Point) -> Option<usize> {
+        self.current.filter(|_| self.landing == Some(*point))
+    }
+
+    /// Selects the match and returns where the cursor goes after applying the offset
+    fn land(&mut self, index: usize, buffer: &Buffer) -> Point {
+        let SearchMatch { start, end } = self.results[index];
+        let point = match self.offset {
+            SearchOffset::Start(count) => shift_chars(buffer, start, count),
+            SearchOffset::End(count) => shift_chars(buffer, end, count),
+            SearchOffset::Lines(count) => Point {
+                row: start
+                    .row
+                    .saturating_add_signed(count)
+                    .min(buffer.line_count().saturating_sub(1)),
+                column: 0,
+            },
         };
         self.current = Some(index);
-        Some(self.results[index].clone())
+        self.landing = Some(point);
+        point
+    }
+}
+
+/// Moves the point by a number of characters, continuing on the next or previous
+/// line and stopping at the edges of the buffer
+fn shift_chars(buffer: &Buffer, point: Point, count: isize) -> Point {
+    let line_length = |row: usize| {
+        buffer
+            .get_line_as_string(row)
+            .trim_end_matches('\n')
+            .chars()
+            .count()
+    };
+    let last_row = buffer.line_count().saturating_sub(1);
+    let Point { mut row, mut column } = point;
+
+    for _ in 0..count.unsigned_abs() {
+        if count > 0 {
+            if column + 1 < line_length(row) {
+                column += 1;
+            } else if row < last_row {
+                row += 1;
+                column = 0;
+            }
+        } else if column > 0 {
+            column -= 1;
+        } else if row > 0 {
+            row -= 1;
+            column = line_length(row).saturating_sub(1);
+        }
     }
+    Point { row, column }
 }
 
 fn byte_to_char_index(s: &str, byte_index: usize) -> Option<usize> {
@@ -100,3 +361,22 @@ fn byte_to_char_index(s: &str, byte_index: usize) -> Option<usize> {
     // Count characters up to the byte index
     Some(s[..byte_index].chars().count())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_offset() {
+        assert_eq!(SearchOffset::split("foo").unwrap(), ("foo", SearchOffset::Start(0)));
+        assert_eq!(SearchOffset::split("foo/").unwrap(), ("foo", SearchOffset::Start(0)));
+        assert_eq!(SearchOffset::split("foo/e").unwrap(), ("foo", SearchOffset::End(0)));
+        assert_eq!(SearchOffset::split("foo/e+1").unwrap(), ("foo", SearchOffset::End(1)));
+        assert_eq!(SearchOffset::split("foo/s-1").unwrap(), ("foo", SearchOffset::Start(-1)));
+        assert_eq!(SearchOffset::split("foo/b+").unwrap(), ("foo", SearchOffset::Start(1)));
+        assert_eq!(SearchOffset::split("foo/+2").unwrap(), ("foo", SearchOffset::Lines(2)));
+        assert_eq!(SearchOffset::split("foo/-").unwrap(), ("foo", SearchOffset::Lines(-1)));
+        assert_eq!(SearchOffset::split(r"a\/b/e").unwrap(), (r"a\/b", SearchOffset::End(0)));
+        assert!(SearchOffset::split("foo/x").is_err());
+    }
+}
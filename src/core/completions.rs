@@ -0,0 +1,178 @@
+use lsp_types::{CompletionItem, CompletionTextEdit, InsertTextFormat, Position};
+use tree_sitter::Point;
+
+/// Completions the language server offered for the word before the cursor, shown in a
+/// menu below it while typing in insert mode narrows them down
+#[derive(Debug, Clone)]
+pub struct Completions {
+    items: Vec<CompletionItem>,
+    /// Where the word being completed starts. The menu closes when the cursor leaves
+    /// its line or goes before it
+    pub start: Point,
+    /// Indices of the items starting with the typed part of the word
+    shown: Vec<usize>,
+    pub selected: usize,
+}
+
+impl Completions {
+    /// The items in the order the server asks for with their sort text
+    pub fn new(mut items: Vec<CompletionItem>, start: Point) -> Self {
+        items.sort_by(|a, b| sort_key(a).cmp(sort_key(b)));
+        let shown = (0..items.len()).collect();
+        Self { items, start, shown, selected: 0 }
+    }
+
+    /// Keeps the items whose filter text starts with the typed text, ignoring case
+    pub fn filter(&mut self, typed: &str) {
+        let typed = typed.to_lowercase();
+        self.shown = (0..self.items.len())
+            .filter(|&index| {
+                let item = &self.items[index];
+                let text = item.filter_text.as_deref().unwrap_or(&item.label);
+                text.to_lowercase().starts_with(&typed)
+            })
+            .collect();
+        self.selected = self.selected.min(self.shown.len().saturating_sub(1));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty()
+    }
+
+    pub fn shown(&self) -> impl Iterator<Item = &CompletionItem> {
+        self.shown.iter().map(|&index| &self.items[index])
+    }
+
+    pub fn len(&self) -> usize {
+        self.shown.len()
+    }
+
+    /// Moves the selection by the offset, wrapping around at the ends
+    pub fn select(&mut self, offset: isize) {
+        let count = self.shown.len() as isize;
+        if count > 0 {
+            self.selected = (self.selected as isize + offset).rem_euclid(count) as usize;
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.shown.get(self.selected).map(|&index| &self.items[index])
+    }
+}
+
+fn sort_key(item: &CompletionItem) -> &str {
+    item.sort_text.as_deref().unwrap_or(&item.label)
+}
+
+/// What accepting an item inserts, and where the text it replaces starts when the
+/// item says so instead of replacing the typed word
+pub struct Insertion {
+    pub text: String,
+    /// In the units of the server's positions
+    pub start: Option<Position>,
+    /// Bytes into the text the cursor goes, the first tab stop of a snippet
+    pub cursor: usize,
+}
+
+impl Insertion {
+    pub fn new(item: &CompletionItem) -> Self {
+        let (text, start) = match &item.text_edit {
+            Some(CompletionTextEdit::Edit(edit)) => (edit.new_text.clone(), Some(edit.range.start)),
+            Some(CompletionTextEdit::InsertAndReplace(edit)) => {
+                (edit.new_text.clone(), Some(edit.insert.start))
+            }
+            None => (item.insert_text.clone().unwrap_or_else(|| item.label.clone()), None),
+        };
+        match item.insert_text_format {
+            Some(InsertTextFormat::SNIPPET) => {
+                let (text, cursor) = expand_snippet(&text);
+                Self { cursor: cursor.unwrap_or(text.len()), text, start }
+            }
+            _ => Self { cursor: text.len(), text, start },
+        }
+    }
+}
+
+/// The text of a snippet with its tab stops left out and placeholders replaced by
+/// their default text, as in `push(${1:value})$0`, and where the first tab stop was.
+/// `\$` is a dollar sign
+pub fn expand_snippet(snippet: &str) -> (String, Option<usize>) {
+    let mut text = String::new();
+    let mut first_stop: Option<(usize, usize)> = None;
+    let mut stop = |number: usize, at: usize| {
+        // $0 is where the snippet ends, the other stops come first
+        let number = if number == 0 { usize::MAX } else { number };
+        if first_stop.is_none_or(|(first, _)| number < first) {
+            first_stop = Some((number, at));
+        }
+    };
+    let mut chars = snippet.chars().peekable();
+    // Closing braces still open for placeholders
+    let mut open = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('$' | '}' | '\\')) => text.extend(chars.next()),
+            '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                let mut number = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+                stop(number.parse().unwrap_or(0), text.len());
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut number = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+                stop(number.parse().unwrap_or(0), text.len());
+                // The default text follows the colon, a choice's first option the bar
+                match chars.next() {
+                    Some(':') => open += 1,
+                    Some('|') => {
+                        while let Some(c) = chars.next_if(|&c| c != ',' && c != '|') {
+                            text.push(c);
+                        }
+                        chars.by_ref().find(|&c| c == '}');
+                    }
+                    _ => {}
+                }
+            }
+            '}' if open > 0 => open -= 1,
+            c => text.push(c),
+        }
+    }
+    (text, first_stop.map(|(_, at)| at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_snippet() {
+        assert_eq!(expand_snippet("push(${1:value})$0"), ("push(value)".to_string(), Some(5)));
+        assert_eq!(expand_snippet("len()"), ("len()".to_string(), None));
+        assert_eq!(expand_snippet("f($1, $2)$0"), ("f(, )".to_string(), Some(2)));
+        assert_eq!(expand_snippet("x$0;"), ("x;".to_string(), Some(1)));
+        assert_eq!(expand_snippet("${1|a,b|} \\$5"), ("a $5".to_string(), Some(0)));
+        assert_eq!(expand_snippet("{${1:x}}"), ("{x}".to_string(), Some(1)));
+    }
+
+    #[test]
+    fn test_filter_completions() {
+        let item = |label: &str| CompletionItem { label: label.to_string(), ..Default::default() };
+        let mut completions = Completions::new(vec![item("push"), item("pop"), item("len")], Point::default());
+        assert_eq!(completions.shown().map(|item| item.label.as_str()).collect::<Vec<_>>(), ["len", "pop", "push"]);
+
+        completions.select(-1);
+        assert_eq!(completions.selected_item().unwrap().label, "push");
+        completions.filter("P");
+        assert_eq!(completions.len(), 2);
+        assert_eq!(completions.selected_item().unwrap().label, "push");
+        completions.filter("pu");
+        assert_eq!(completions.selected_item().unwrap().label, "push");
+        completions.filter("x");
+        assert!(completions.is_empty());
+    }
+}
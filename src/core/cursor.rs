@@ -1,5 +1,8 @@
+use crate::core::grapheme::{floor_boundary, next_boundary, previous_boundary};
+use crate::core::keyword_chars::KeywordChars;
 use crate::core::mode::Mode;
 use crate::core::{buffer::Buffer, utf8::Utf8CharIterator};
+use crate::ui::display::Layout;
 use tree_sitter::Point;
 
 #[derive(Debug, Clone, Default)]
@@ -27,8 +30,10 @@ impl Cursor {
     }
 
     pub fn set_point(&mut self, position: Point, buffer: &Buffer) {
+        // A point kept from before an edit may no longer be inside the text
+        let position = buffer.clamp_point(&position);
         self.row = position.row;
-        self.byte_column = position.column;
+        self.byte_column = floor_boundary(&buffer.get_line_as_string(self.row), position.column);
         self.char_column = self.byte_to_char_column(buffer);
         self.preferred_column = self.char_column;
     }
@@ -56,32 +61,41 @@ impl Cursor {
         self.byte_column = self.char_to_byte_column(buffer);
     }
 
-    /// Move cursor one character to the left
+    /// Character column of the current line moved to a grapheme boundary by the step
+    fn grapheme_column(&self, buffer: &Buffer, column: usize, step: fn(&str, usize) -> usize) -> usize {
+        let line = buffer.get_line_as_string(self.row);
+        let byte = line.char_indices().nth(column).map_or(line.len(), |(byte, _)| byte);
+        line[..step(&line, byte)].chars().count()
+    }
+
+    /// Last character column of the current line the cursor can be on. In non-insert
+    /// modes that's the start of the last grapheme, not past it
+    fn last_column(&self, buffer: &Buffer, mode: &Mode) -> usize {
+        let end = buffer.get_line_length(self.row).saturating_sub(1);
+        match mode.is_insert_type() {
+            true => end,
+            false => self.grapheme_column(buffer, end, previous_boundary),
+        }
+    }
+
+    /// Move cursor one grapheme to the left
     pub fn move_left(&mut self, buffer: &Buffer, mode: &Mode, inline: bool) {
         if self.char_column > 0 {
-            self.char_column -= 1;
+            self.char_column = self.grapheme_column(buffer, self.char_column, previous_boundary);
         } else if self.row > 0 && !inline {
             self.row -= 1;
-            self.char_column = buffer.get_line_length(self.row).saturating_sub(1);
-            if !mode.is_insert_type() {
-                // In non-insert mode, don't allow cursor to go beyond the last character
-                self.char_column = self.char_column.saturating_sub(1);
-            }
+            self.char_column = self.last_column(buffer, mode);
         }
         self.sync_byte_column(buffer);
         self.preferred_column = self.char_column;
     }
 
-    /// Move cursor one character to the right
+    /// Move cursor one grapheme to the right
     pub fn move_right(&mut self, buffer: &Buffer, mode: &Mode, inline: bool) {
-        let mut line_length = buffer.get_line_length(self.row).saturating_sub(1);
-
-        if !mode.is_insert_type() {
-            line_length = line_length.saturating_sub(1);
-        }
-
-        if self.char_column < line_length {
-            self.char_column += 1;
+        let last_column = self.last_column(buffer, mode);
+        if self.char_column < last_column {
+            let next = self.grapheme_column(buffer, self.char_column, next_boundary);
+            self.char_column = next.min(last_column);
         } else if self.row + 1 < buffer.line_count() && !inline {
             self.row += 1;
             self.char_column = 0;
@@ -117,112 +131,95 @@ impl Cursor {
         self.byte_column = 0;
     }
 
+    /// Move to the first non-blank character of the current line
+    pub fn move_to_first_non_blank(&mut self, buffer: &Buffer) {
+        let line = buffer.get_line_as_string(self.row);
+        self.char_column = line
+            .chars()
+            .take_while(|c| *c != '\n')
+            .position(|c| !c.is_whitespace())
+            .unwrap_or(0);
+        self.sync_byte_column(buffer);
+        self.preferred_column = self.char_column;
+    }
+
     /// Move to the end of the current line
     pub fn move_to_line_end(&mut self, buffer: &Buffer, mode: &Mode) {
-        let mut line_length = buffer.get_line_length(self.row).saturating_sub(1);
-        if !mode.is_insert_type() {
-            line_length = line_length.saturating_sub(1);
-        }
-        self.char_column = line_length;
+        self.char_column = self.last_column(buffer, mode);
         self.sync_byte_column(buffer);
         self.preferred_column = self.char_column;
     }
 
     /// Jump to the next word
-    pub fn find_next_word(&self, buffer: &Buffer) -> Cursor {
-        // Get the position within the buffer
-        let current_point = self.get_point();
-        let position = buffer.cursor_position(&current_point);
-
-        // Get buffer content
-        let content = buffer.to_string();
-        let chars: Vec<char> = content.chars().collect();
-
-        if position >= chars.len() {
-            return self.clone();
-        }
-
-        let mut index = position;
+    pub fn find_next_word(&self, buffer: &Buffer, keywords: &KeywordChars) -> Cursor {
+        let line = buffer.get_line_as_string(self.row);
+        let mut chars = line.get(self.byte_column..).unwrap_or_default().char_indices().peekable();
 
         // Skip the current word
-        if !chars[index].is_whitespace() {
-            let keyword_type = is_keyword(chars[index]);
-
-            while index < chars.len()
-                && !chars[index].is_whitespace()
-                && is_keyword(chars[index]) == keyword_type
-            {
-                index += 1;
-            }
+        if let Some(&(_, first)) = chars.peek()
+            && !first.is_whitespace()
+        {
+            let keyword_type = keywords.is_keyword(first);
+            while chars
+                .next_if(|(_, c)| !c.is_whitespace() && keywords.is_keyword(*c) == keyword_type)
+                .is_some()
+            {}
         }
 
-        // Skip whitespace
-        while index < chars.len() && chars[index].is_whitespace() {
-            index += 1;
-        }
+        // Skip whitespace on the rest of the line
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let target = match chars.next() {
+            Some((offset, _)) => Some(Point {
+                row: self.row,
+                column: self.byte_column + offset,
+            }),
+            // Continue on the following lines, stopping on a blank line like `}` does
+            None => (self.row + 1..buffer.line_count()).find_map(|row| {
+                if buffer.is_blank_line(row) {
+                    return Some(Point { row, column: 0 });
+                }
+                let line = buffer.get_line_as_string(row);
+                let column = line.find(|c: char| !c.is_whitespace())?;
+                Some(Point { row, column })
+            }),
+        };
 
-        // Update the cursor position
-        if index < chars.len() {
-            let new_point = buffer.point_at_position(index);
-            let mut new_cursor = Cursor {
-                row: new_point.row,
-                byte_column: new_point.column,
-                char_column: 0,      // Will be calculated
-                preferred_column: 0, // Will be set
-            };
-            new_cursor.char_column = new_cursor.byte_to_char_column(buffer);
-            new_cursor.preferred_column = new_cursor.char_column;
-            new_cursor
-        } else {
-            self.clone()
+        let mut new_cursor = self.clone();
+        if let Some(point) = target {
+            new_cursor.set_point(point, buffer);
         }
+        new_cursor
     }
 
     /// Jump to the previous word
-    pub fn find_previous_word(&self, buffer: &Buffer) -> Cursor {
-        // Get the position within the buffer
-        let current_point = self.get_point();
-        let position = buffer.cursor_position(&current_point);
-
-        if position == 0 {
+    pub fn find_previous_word(&self, buffer: &Buffer, keywords: &KeywordChars) -> Cursor {
+        if self.row == 0 && self.byte_column == 0 {
             return self.clone();
         }
 
-        // Get buffer content
-        let content = buffer.to_string();
-        let chars: Vec<char> = content.chars().collect();
-
-        let mut index = position.saturating_sub(1);
-
-        // Skip whitespace backwards
-        while index > 0 && chars[index].is_whitespace() {
-            index -= 1;
-        }
-
-        if index == 0 {
+        // The last character before the cursor that isn't whitespace, a line at a time
+        let mut end = Some(self.byte_column);
+        let found = (0..=self.row).rev().find_map(|row| {
+            let line = buffer.get_line_as_string(row);
+            let end = end.take().map_or(line.len(), |column| column.min(line.len()));
+            let (offset, c) = line[..end].char_indices().rev().find(|(_, c)| !c.is_whitespace())?;
+            Some((row, line, offset, c))
+        });
+        let Some((row, line, offset, last)) = found else {
             return Cursor::new();
-        }
-
-        // Find the start of the current word
-        let keyword_type = is_keyword(chars[index]);
-        let mut word_start = index;
-
-        while word_start > 0
-            && !chars[word_start - 1].is_whitespace()
-            && is_keyword(chars[word_start - 1]) == keyword_type
-        {
-            word_start -= 1;
-        }
-
-        // Create new cursor at the target position
-        let new_point = buffer.point_at_position(word_start);
-        let mut new_cursor = Cursor {
-            row: new_point.row,
-            byte_column: new_point.column,
-            ..Default::default()
         };
-        new_cursor.char_column = new_cursor.byte_to_char_column(buffer);
-        new_cursor.preferred_column = new_cursor.char_column;
+
+        // Find the start of its word, which a line break always ends
+        let keyword_type = keywords.is_keyword(last);
+        let column = line[..offset]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| !c.is_whitespace() && keywords.is_keyword(*c) == keyword_type)
+            .last()
+            .map_or(offset, |(start, _)| start);
+
+        let mut new_cursor = Cursor::new();
+        new_cursor.set_point(Point { row, column }, buffer);
         new_cursor
     }
 
@@ -234,13 +231,9 @@ impl Cursor {
 
     /// Ensure the cursor is at a valid position in the current line
     pub fn clamp_column(&mut self, buffer: &Buffer, mode: &Mode) {
-        let mut line_length = buffer.get_line_length(self.row).saturating_sub(1);
-        if !mode.is_insert_type() {
-            line_length = line_length.saturating_sub(1);
-        }
-
         // Try to maintain the preferred column if possible
-        self.char_column = self.preferred_column.min(line_length);
+        let column = self.preferred_column.min(self.last_column(buffer, mode));
+        self.char_column = self.grapheme_column(buffer, column, floor_boundary);
         self.sync_byte_column(buffer);
     }
 
@@ -254,17 +247,27 @@ impl Cursor {
         self.clamp_column(buffer, mode);
     }
 
+    /// Move to a character column of the current line, clamped the same way as `clamp_column`
     pub fn go_to_column(&mut self, column: usize, buffer: &Buffer, mode: &Mode) {
-        let mut line_length = buffer.get_line_length(self.row);
-        if !mode.is_insert_type() {
-            line_length = line_length.saturating_sub(1);
-        }
-        self.char_column = column.min(line_length);
+        let column = column.min(self.last_column(buffer, mode));
+        self.char_column = self.grapheme_column(buffer, column, floor_boundary);
         self.sync_byte_column(buffer);
         self.preferred_column = self.char_column;
     }
-}
 
-fn is_keyword(c: char) -> bool {
-    c.is_alphanumeric() || c == '_'
+    /// Move to the character drawn at a screen column of the current line, as `|` does,
+    /// so a tab or a wide character takes up all the columns it covers
+    pub fn go_to_display_column(&mut self, column: usize, buffer: &Buffer, mode: &Mode, layout: Layout) {
+        let line = buffer.get_line_as_string(self.row);
+        let mut width = 0;
+        let char_column = line
+            .trim_end_matches('\n')
+            .chars()
+            .take_while(|&c| {
+                width += layout.glyph(c, width).width();
+                width <= column
+            })
+            .count();
+        self.go_to_column(char_column, buffer, mode);
+    }
 }
@@ -0,0 +1,311 @@
+use std::path::{Path, PathBuf};
+
+/// Lines of context shown around each change
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// One line of a diff, with its row in the old and new text. An inserted line has
+/// the old row it was inserted before and a deleted line the new row it was deleted before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffLine {
+    pub change: LineChange,
+    pub old: usize,
+    pub new: usize,
+}
+
+/// Shortest list of line changes turning `old` into `new`, using Myers' algorithm
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffLine> {
+    // Lines shared at the start and end don't take part in the search
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let middle = myers(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let equal = |old, new| DiffLine {
+        change: LineChange::Equal,
+        old,
+        new,
+    };
+    let mut lines = (0..prefix).map(|row| equal(row, row)).collect::<Vec<_>>();
+    lines.extend(middle.into_iter().map(|line| DiffLine {
+        old: line.old + prefix,
+        new: line.new + prefix,
+        ..line
+    }));
+    lines.extend(
+        (0..suffix).map(|row| equal(old.len() - suffix + row, new.len() - suffix + row)),
+    );
+    lines
+}
+
+fn myers<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffLine> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    // Furthest x reached on each diagonal k = x - y, stored at k + max
+    let mut furthest = vec![0isize; 2 * max as usize + 2];
+    // The diagonals of `furthest` round d reads, -d - 1 to d + 1, as they were before
+    // it, to walk the path back. Keeping only those makes the trace grow with the
+    // square of the number of changes instead of with the length of the texts
+    let mut trace = Vec::new();
+
+    let index = |k: isize| (k + max) as usize;
+    let goes_down = |v: &dyn Fn(isize) -> isize, d: isize, k: isize| {
+        k == -d || (k != d && v(k - 1) < v(k + 1))
+    };
+
+    'search: for d in 0..=max {
+        let (low, high) = ((-d - 1).max(-max), (d + 1).min(max + 1));
+        trace.push((low, furthest[index(low)..=index(high)].to_vec()));
+        for k in (-d..=d).step_by(2) {
+            let mut x = if goes_down(&|k| furthest[index(k)], d, k) {
+                furthest[index(k + 1)]
+            } else {
+                furthest[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, (low, v)) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let v = |k: isize| v[(k - low) as usize];
+        let k = x - y;
+        let previous_k = if goes_down(&v, d, k) { k + 1 } else { k - 1 };
+        let previous_x = v(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            lines.push(DiffLine {
+                change: LineChange::Equal,
+                old: x as usize,
+                new: y as usize,
+            });
+        }
+        if d > 0 {
+            let change = if x == previous_x {
+                LineChange::Insert
+            } else {
+                LineChange::Delete
+            };
+            lines.push(DiffLine {
+                change,
+                old: previous_x as usize,
+                new: previous_y as usize,
+            });
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    lines.reverse();
+    lines
+}
+
+/// Kind of a line shown in a diff view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffRowKind {
+    /// The `---`/`+++` file names and the `@@` hunk ranges
+    Header,
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRow {
+    pub kind: DiffRowKind,
+    /// Row of the new text the line belongs to
+    pub target: usize,
+}
+
+/// Unified diff shown in a buffer, remembering where each of its rows came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffView {
+    /// File whose changes are shown
+    pub source: PathBuf,
+    pub rows: Vec<DiffRow>,
+}
+
+impl DiffView {
+    /// Builds the unified diff text of two versions of a file, or None if they have the same lines
+    pub fn new(source: &Path, old: &str, new: &str) -> Option<(String, DiffView)> {
+        let old_lines = old.lines().collect::<Vec<_>>();
+        let new_lines = new.lines().collect::<Vec<_>>();
+        let lines = diff_lines(&old_lines, &new_lines);
+
+        let changed = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.change != LineChange::Equal)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let first_target = lines.get(*changed.first()?).map_or(0, |line| line.new);
+
+        let name = source.file_name().unwrap_or_default().to_string_lossy();
+        let mut text = format!("--- {name} (saved)\n+++ {name} (buffer)\n");
+        let header = DiffRow {
+            kind: DiffRowKind::Header,
+            target: first_target,
+        };
+        let mut rows = vec![header, header];
+
+        // Changes whose context touches share a hunk
+        let mut hunks: Vec<(usize, usize)> = Vec::new();
+        for index in changed {
+            let start = index.saturating_sub(CONTEXT_LINES);
+            let end = (index + CONTEXT_LINES + 1).min(lines.len());
+            match hunks.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = end,
+                _ => hunks.push((start, end)),
+            }
+        }
+
+        for (start, end) in hunks {
+            let hunk = &lines[start..end];
+            let count = |change| hunk.iter().filter(|line| line.change != change).count();
+            let (old_count, new_count) = (count(LineChange::Insert), count(LineChange::Delete));
+            text.push_str(&format!(
+                "@@ -{},{old_count} +{},{new_count} @@\n",
+                hunk[0].old + 1,
+                hunk[0].new + 1
+            ));
+            let first_change = hunk.iter().find(|line| line.change != LineChange::Equal);
+            rows.push(DiffRow {
+                kind: DiffRowKind::Header,
+                target: first_change.map_or(hunk[0].new, |line| line.new),
+            });
+
+            for line in hunk {
+                let (prefix, content, kind) = match line.change {
+                    LineChange::Equal => (' ', new_lines[line.new], DiffRowKind::Context),
+                    LineChange::Insert => ('+', new_lines[line.new], DiffRowKind::Added),
+                    LineChange::Delete => ('-', old_lines[line.old], DiffRowKind::Removed),
+                };
+                text.push(prefix);
+                text.push_str(content);
+                text.push('\n');
+                rows.push(DiffRow {
+                    kind,
+                    target: line.new,
+                });
+            }
+        }
+
+        let source = source.to_path_buf();
+        Some((text, DiffView { source, rows }))
+    }
+
+    pub fn row(&self, row: usize) -> Option<&DiffRow> {
+        self.rows.get(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies the diff to `old`, checking that it yields `new`
+    fn apply(old: &[&str], new: &[&str], lines: &[DiffLine]) -> Vec<String> {
+        let mut result = Vec::new();
+        for line in lines {
+            match line.change {
+                LineChange::Equal => {
+                    assert_eq!(old[line.old], new[line.new]);
+                    result.push(old[line.old].to_string());
+                }
+                LineChange::Insert => result.push(new[line.new].to_string()),
+                LineChange::Delete => {}
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        // Old text, new text and the expected number of changed lines
+        const CASES: [(&str, &str, usize); 7] = [
+            ("", "", 0),
+            ("a b c", "a b c", 0),
+            ("", "a b", 2),
+            ("a b", "", 2),
+            ("a b c a b b a", "c b a b a c", 5),
+            ("x a b c y", "a b c", 2),
+            ("a b c d e", "a x c d y e", 3),
+        ];
+
+        for (old, new, changes) in CASES {
+            let old = old.split_whitespace().collect::<Vec<_>>();
+            let new = new.split_whitespace().collect::<Vec<_>>();
+            let lines = diff_lines(&old, &new);
+
+            assert_eq!(apply(&old, &new, &lines), new, "{old:?} -> {new:?}");
+            let changed = lines.iter().filter(|line| line.change != LineChange::Equal);
+            assert_eq!(changed.count(), changes, "{old:?} -> {new:?}");
+        }
+    }
+
+    #[test]
+    fn test_many_changes() {
+        // Every fourth line changed, and lines added at the end
+        let old = (0..3000).map(|line| line.to_string()).collect::<Vec<_>>();
+        let mut new = old.clone();
+        for line in new.iter_mut().step_by(4) {
+            line.push('!');
+        }
+        new.extend((0..500).map(|line| format!("new {line}")));
+        let old = old.iter().map(String::as_str).collect::<Vec<_>>();
+        let new = new.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let lines = diff_lines(&old, &new);
+        assert_eq!(apply(&old, &new, &lines), new);
+        let changed = lines.iter().filter(|line| line.change != LineChange::Equal);
+        assert_eq!(changed.count(), 2 * 750 + 500);
+    }
+
+    #[test]
+    fn test_unified_view() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\n3\n4\n5\n6\n7\n8\nnine\n10\n11\n";
+        let path = Path::new("dir/file");
+        assert_eq!(DiffView::new(path, old, old), None);
+
+        let (text, view) = DiffView::new(path, old, new).unwrap();
+        assert_eq!(
+            text,
+            "--- file (saved)\n+++ file (buffer)\n@@ -6,5 +6,6 @@\n 6\n 7\n 8\n-9\n+nine\n 10\n+11\n"
+        );
+        let kinds = view.rows.iter().map(|row| row.kind).collect::<Vec<_>>();
+        assert_eq!(kinds[5..9], [
+            DiffRowKind::Context,
+            DiffRowKind::Removed,
+            DiffRowKind::Added,
+            DiffRowKind::Context,
+        ]);
+        assert_eq!(view.row(2).unwrap().target, 8);
+        assert_eq!(view.row(7).unwrap().target, 8);
+        assert_eq!(view.row(9).unwrap().target, 10);
+    }
+}
@@ -1,56 +1,217 @@
+use crate::core::buffer::backend::{BufferBackend, MemoryBackend};
+use crate::core::buffer::chunked::ChunkedBackend;
+use crate::core::diff::DiffView;
+use crate::core::encoding::FileEncoding;
 use crate::core::history::edit::Edit;
 use crate::core::language::Language;
+use crate::core::marks::Marks;
+use crate::core::swap::{Swap, swap_path};
 use crate::core::syntax::SyntaxEngine;
 use crate::core::{buffer::Buffer, history::History};
+use crate::service::symbols::Symbol;
 use anyhow::{Context, Result};
+use lsp_types::{CodeActionOrCommand, Position};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tree_sitter::Point;
 
 pub struct Document {
     pub buffer: Buffer,
+    pub backend: Box<dyn BufferBackend>,
+    /// Path as the user typed it, used for display
     pub path: Option<PathBuf>,
-    pub modified: bool,
+    /// Absolute path with symlinks resolved, identifying the file for dedup and LSP URIs
+    pub canonical_path: Option<PathBuf>,
+    /// Throwaway copy, such as the tutor, that can be closed without saving. Giving it
+    /// a file or writing it makes it an ordinary buffer
+    pub scratch: bool,
+    /// File made for the document, such as the tutor's copy, removed when it's closed
+    pub temp_file: Option<PathBuf>,
+    /// Set when the document shows the changes of another one
+    pub diff: Option<DiffView>,
+    /// Set when the document lists symbols to jump to, one per line
+    pub symbols: Option<Vec<Symbol>>,
+    /// Set when the document lists places to jump to, such as references, one per line
+    pub locations: Option<Vec<(PathBuf, Position)>>,
+    /// Set when the document lists code actions to pick from, one per line
+    pub code_actions: Option<Vec<CodeActionOrCommand>>,
     pub language: Language,
+    /// How the file is stored, converted to when writing
+    pub encoding: FileEncoding,
+    /// The encoding of the last write, so that converting marks the document modified
+    saved_encoding: FileEncoding,
     pub syntax_engine: Option<SyntaxEngine>,
     pub version: usize,
+    /// Counts the edits made, so that what's worked out from the text, like the
+    /// highlighting, is only worked out again after one
+    pub revision: u64,
     pub history: History,
+    pub marks: Marks,
+    /// Modification time of the file when it was last read or written, telling when
+    /// another program changed it since
+    disk_time: Option<SystemTime>,
+    /// Set once the change on disk was reported, so that it's reported once
+    disk_change_shown: bool,
+    /// Swap file holding the unsaved text, removed once it's saved or dropped
+    swap_file: Option<PathBuf>,
+    /// Change count of the history when the swap file was last written
+    swapped_changes: Option<usize>,
 }
 
 impl Document {
     pub fn new() -> Self {
         Self {
             buffer: Buffer::default(),
+            backend: Box::new(MemoryBackend),
             path: None,
-            modified: false,
+            canonical_path: None,
+            scratch: false,
+            temp_file: None,
+            diff: None,
+            symbols: None,
+            locations: None,
+            code_actions: None,
             language: Language::PlainText,
+            encoding: FileEncoding::default(),
+            saved_encoding: FileEncoding::default(),
             syntax_engine: None,
             version: 1,
+            revision: 0,
             history: History::new(1000),
+            marks: Marks::default(),
+            disk_time: None,
+            disk_change_shown: false,
+            swap_file: None,
+            swapped_changes: None,
         }
     }
 
-    pub fn from_file(path: &Path) -> Self {
-        let content = std::fs::read_to_string(path).unwrap_or_default();
+    /// Reads the file, or opens it in large-file mode when it has more bytes than the
+    /// threshold
+    pub fn from_file(path: &Path, large_file_threshold: u64) -> Self {
+        let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default();
+        if size > large_file_threshold
+            && let Ok(document) = Self::from_large_file(path)
+        {
+            return document;
+        }
+
+        let bytes = std::fs::read(path).unwrap_or_default();
+        let (content, encoding) = FileEncoding::decode(&bytes);
 
         let language = Language::from_path(path);
         let syntax_engine = SyntaxEngine::new(&language).ok();
 
         Self {
             buffer: Buffer::from_string(&content),
+            backend: Box::new(MemoryBackend),
             path: Some(path.to_path_buf()),
-            modified: false,
+            canonical_path: Some(canonicalize_path(path)),
+            scratch: false,
+            temp_file: None,
+            diff: None,
+            symbols: None,
+            locations: None,
+            code_actions: None,
             language,
+            encoding,
+            saved_encoding: encoding,
             syntax_engine,
             version: 1,
+            revision: 0,
             history: History::new(1000),
+            marks: Marks::default(),
+            disk_time: file_time(path),
+            disk_change_shown: false,
+            swap_file: None,
+            swapped_changes: None,
         }
     }
 
+    /// Opens the file read-only, keeping only the part around the cursor in memory
+    pub fn from_large_file(path: &Path) -> Result<Self> {
+        let (backend, buffer) = ChunkedBackend::open(path)?;
+        Ok(Self {
+            buffer,
+            backend: Box::new(backend),
+            path: Some(path.to_path_buf()),
+            canonical_path: Some(canonicalize_path(path)),
+            scratch: false,
+            temp_file: None,
+            diff: None,
+            symbols: None,
+            locations: None,
+            code_actions: None,
+            language: Language::PlainText,
+            encoding: FileEncoding::default(),
+            saved_encoding: FileEncoding::default(),
+            syntax_engine: None,
+            version: 1,
+            revision: 0,
+            history: History::new(1000),
+            marks: Marks::default(),
+            disk_time: file_time(path),
+            disk_change_shown: false,
+            swap_file: None,
+            swapped_changes: None,
+        })
+    }
+
+    /// Reads the file again, dropping the changes and the undo history. The language
+    /// picked with `:set filetype` is kept
+    pub fn reload(&mut self, large_file_threshold: u64) -> Result<()> {
+        let path = self.path.clone().context("No file name")?;
+        std::fs::metadata(&path).with_context(|| format!("Can't open file {}", path.display()))?;
+        let mut reloaded = Self::from_file(&path, large_file_threshold);
+        if reloaded.language != self.language && !reloaded.is_read_only() {
+            reloaded.set_language(self.language);
+        }
+        reloaded.scratch = self.scratch;
+        reloaded.temp_file = self.temp_file.take();
+        reloaded.version = self.version + 1;
+        self.remove_swap();
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Switches the language, replacing the syntax highlighter so the buffer is re-parsed.
+    /// A file in large-file mode is never highlighted
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+        self.syntax_engine = match self.is_large_file() {
+            true => None,
+            false => SyntaxEngine::new(&language).ok(),
+        };
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.backend.is_read_only()
+    }
+
+    /// Whether the file was too large to read whole, so that only the part around the
+    /// cursor is loaded, and it's neither highlighted nor sent to a language server
+    pub fn is_large_file(&self) -> bool {
+        self.backend.is_windowed()
+    }
+
+    /// Line number shown in the gutter, approximate (`~N+`) when the backend only
+    /// knows a lower bound for it
+    pub fn line_label(&self, row: usize) -> String {
+        self.backend.line_number(row).to_string()
+    }
+
     pub fn save(&mut self) -> Result<()> {
+        if self.is_read_only() {
+            return Err(anyhow::anyhow!("File is opened read-only"));
+        }
+        if self.changed_on_disk() {
+            return Err(anyhow::anyhow!("File changed on disk since it was read"));
+        }
         if let Some(path) = &self.path {
-            let content = self.buffer.to_bytes();
-            std::fs::write(path, content)
-                .context(format!("Failed to write to file: {}", path.display()))?;
-            self.modified = false;
+            self.encoding.write_file(&self.buffer, path)?;
+            self.mark_saved();
+            self.record_disk_time();
+            self.remove_swap();
             Ok(())
         } else {
             Err(anyhow::anyhow!("No file path set"))
@@ -58,12 +219,59 @@ impl Document {
     }
 
     pub fn save_as(&mut self, path: &Path) -> Result<()> {
-        self.path = Some(path.to_path_buf());
+        self.set_path(path);
         self.save()
     }
 
-    pub fn mark_modified(&mut self) {
-        self.modified = true;
+    pub fn set_path(&mut self, path: &Path) {
+        self.path = Some(path.to_path_buf());
+        self.canonical_path = Some(canonicalize_path(path));
+        self.disk_time = None;
+        self.scratch = false;
+    }
+
+    /// Remembers the modification time of the file after it was written
+    pub fn record_disk_time(&mut self) {
+        self.disk_time = self.path.as_deref().and_then(file_time);
+        self.disk_change_shown = false;
+    }
+
+    /// Whether another program modified the file since it was read or written
+    pub fn changed_on_disk(&self) -> bool {
+        let now = self.path.as_deref().and_then(file_time);
+        matches!((self.disk_time, now), (Some(then), Some(now)) if then != now)
+    }
+
+    /// True the first time the file is found changed on disk while the document has no
+    /// changes of its own, which reloading would lose
+    pub fn take_disk_change(&mut self) -> bool {
+        if self.disk_change_shown || self.is_modified() || !self.changed_on_disk() {
+            return false;
+        }
+        self.disk_change_shown = true;
+        true
+    }
+
+    /// Returns true if the text differs from the last save, as far as the undo history
+    /// tells, or the file is to be written in another encoding
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified() || self.encoding != self.saved_encoding
+    }
+
+    /// Whether closing the document would lose changes. Scratch buffers are thrown away
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.is_modified() && !self.scratch
+    }
+
+    /// File name for messages, `[No Name]` for a document without a file
+    pub fn display_name(&self) -> String {
+        self.file_name().unwrap_or_else(|| "[No Name]".to_string())
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.saved_encoding = self.encoding;
+        self.scratch = false;
     }
 
     pub fn file_name(&self) -> Option<String> {
@@ -75,8 +283,7 @@ impl Document {
     }
 
     pub fn full_file_path(&self) -> Option<PathBuf> {
-        let current = std::env::current_dir().ok()?;
-        self.path.as_ref().map(|p| current.join(p))
+        self.canonical_path.clone()
     }
 
     pub fn full_path_string(&self) -> Option<String> {
@@ -90,6 +297,46 @@ impl Document {
         Some(format!("file://{}", path))
     }
 
+    fn swap(&self, cursor: Point) -> Swap {
+        Swap {
+            cursor,
+            text: self.buffer.to_string(),
+        }
+    }
+
+    /// Writes the unsaved text to the swap file right away, as when the editor is killed
+    pub fn write_swap(&self, directory: &Path, index: usize, cursor: Point) -> Result<PathBuf> {
+        let path = swap_path(directory, self, index);
+        self.swap(cursor).write(&path)?;
+        Ok(path)
+    }
+
+    /// The swap file to write and what goes in it, when the text has unsaved changes
+    /// made since the last write
+    pub fn pending_swap(&mut self, directory: &Path, index: usize, cursor: Point) -> Option<(PathBuf, Swap)> {
+        let changes = self.history.change_count();
+        if !self.has_unsaved_changes() || self.swapped_changes == Some(changes) {
+            return None;
+        }
+        let path = swap_path(directory, self, index);
+        self.swap_file = Some(path.clone());
+        self.swapped_changes = Some(changes);
+        Some((path, self.swap(cursor)))
+    }
+
+    /// Takes over a swap file the text was recovered from, removed with the others
+    pub fn adopt_swap(&mut self, path: PathBuf) {
+        self.swap_file = Some(path);
+    }
+
+    /// Deletes the swap file, once the text is saved or its changes are dropped
+    pub fn remove_swap(&mut self) {
+        if let Some(path) = self.swap_file.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        self.swapped_changes = None;
+    }
+
     pub fn get_undo(&mut self) -> Result<Edit> {
         if let Some(change) = self.history.undo() {
             Ok(change)
@@ -106,3 +353,23 @@ impl Document {
         }
     }
 }
+
+fn file_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Absolute path of the file with `..` and symlinks resolved. For a file that doesn't
+/// exist yet only its directory is resolved, and the path is kept as is when that fails too
+pub fn canonicalize_path(path: &Path) -> PathBuf {
+    let absolute = std::env::current_dir().unwrap_or_default().join(path);
+    if let Ok(canonical) = absolute.canonicalize() {
+        return canonical;
+    }
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or(absolute),
+        _ => absolute,
+    }
+}
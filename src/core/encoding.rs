@@ -0,0 +1,245 @@
+use crate::core::buffer::Buffer;
+use anyhow::{Context, Result, anyhow};
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Character encoding of a file. Buffers always hold UTF-8, converted from and to
+/// the file's encoding when it's read and written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where each byte is the character with the same code
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 4] = [
+        Encoding::Utf8,
+        Encoding::Latin1,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin1",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" | "utf-16" | "utf16" => Some(Encoding::Utf16Be),
+            _ => None,
+        }
+    }
+
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => UTF8_BOM,
+            Encoding::Latin1 => &[],
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+        }
+    }
+}
+
+/// How the text of a document is stored in its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileEncoding {
+    pub encoding: Encoding,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+}
+
+impl FileEncoding {
+    /// Converting to the encoding keeps a byte order mark only for UTF-16, which can't
+    /// be told apart from other bytes without one
+    pub fn converted_to(encoding: Encoding) -> Self {
+        let bom = matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be);
+        Self { encoding, bom }
+    }
+
+    /// The text of a file and how it was stored. A byte order mark decides the
+    /// encoding, else the bytes are UTF-8 when they're valid UTF-8 and latin-1 when
+    /// not. Latin-1 text whose bytes also form UTF-8, such as `Ã©`, is read as UTF-8
+    pub fn decode(bytes: &[u8]) -> (String, Self) {
+        if let Some(rest) = bytes.strip_prefix(UTF8_BOM)
+            && let Ok(text) = std::str::from_utf8(rest)
+        {
+            let encoding = Self { encoding: Encoding::Utf8, bom: true };
+            return (text.to_string(), encoding);
+        }
+        for encoding in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            if let Some(rest) = bytes.strip_prefix(encoding.bom())
+                && let Some(text) = decode_utf16(rest, encoding)
+            {
+                return (text, Self { encoding, bom: true });
+            }
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), Self::default()),
+            Err(_) => {
+                let text = bytes.iter().map(|&byte| char::from(byte)).collect();
+                (text, Self::converted_to(Encoding::Latin1))
+            }
+        }
+    }
+
+    /// The bytes of the file for the text, failing on a character the encoding can't store
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let mut bytes = match self.bom {
+            true => self.encoding.bom().to_vec(),
+            false => Vec::new(),
+        };
+        match self.encoding {
+            Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Latin1 => {
+                for c in text.chars() {
+                    let byte = u8::try_from(c)
+                        .map_err(|_| anyhow!("'{c}' can't be written in {}", self.encoding))?;
+                    bytes.push(byte);
+                }
+            }
+            Encoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Encoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+        }
+        Ok(bytes)
+    }
+
+    /// Writes the text of the buffer to the file. UTF-8 goes out from the chunks of the
+    /// buffer as they are, other encodings are converted first, so that a character they
+    /// can't store fails before the file is touched
+    pub fn write_file(&self, buffer: &Buffer, path: &Path) -> Result<()> {
+        let converted = match self.encoding {
+            Encoding::Utf8 => None,
+            _ => Some(self.encode(&buffer.to_string())?),
+        };
+        let write = || -> std::io::Result<()> {
+            let mut file = BufWriter::new(File::create(path)?);
+            match &converted {
+                Some(bytes) => file.write_all(bytes)?,
+                None if self.bom => {
+                    file.write_all(UTF8_BOM)?;
+                    buffer.write_to(&mut file)?;
+                }
+                None => buffer.write_to(&mut file)?,
+            }
+            file.flush()
+        };
+        write().context(format!("Failed to write to file: {}", path.display()))
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+impl Display for FileEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bom {
+            true => write!(f, "{} bom", self.encoding),
+            false => write!(f, "{}", self.encoding),
+        }
+    }
+}
+
+/// UTF-16 text, or `None` for an odd number of bytes or an unpaired surrogate
+fn decode_utf16(bytes: &[u8], encoding: Encoding) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        match encoding {
+            Encoding::Utf16Be => u16::from_be_bytes(pair),
+            _ => u16::from_le_bytes(pair),
+        }
+    });
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let cases: [(&[u8], &str, &str); 5] = [
+            (b"caf\xc3\xa9\n", "café\n", "utf-8"),
+            (b"\xef\xbb\xbfcaf\xc3\xa9\n", "café\n", "utf-8 bom"),
+            (b"caf\xe9 \xb1\n", "café ±\n", "latin1"),
+            (b"\xff\xfec\x00\xe9\x00\n\x00", "cé\n", "utf-16le bom"),
+            (b"\xfe\xff\x00c\xd8\x3d\xde\x00\x00\n", "c😀\n", "utf-16be bom"),
+        ];
+        for (bytes, text, name) in cases {
+            let (decoded, encoding) = FileEncoding::decode(bytes);
+            assert_eq!(decoded, text, "{name}");
+            assert_eq!(encoding.to_string(), name);
+            assert_eq!(encoding.encode(&decoded).unwrap(), bytes, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_latin1_that_is_valid_utf8() {
+        // Written as latin-1, `Ã©` is the two bytes of `é` in UTF-8, and UTF-8 wins
+        let (text, encoding) = FileEncoding::decode(b"\xc3\xa9");
+        assert_eq!(text, "é");
+        assert_eq!(encoding, FileEncoding::default());
+
+        // A BOM-like start that isn't valid UTF-16 is latin-1
+        let (text, encoding) = FileEncoding::decode(b"\xff\xfex");
+        assert_eq!(text, "ÿþx");
+        assert_eq!(encoding.encoding, Encoding::Latin1);
+    }
+
+    #[test]
+    fn test_unencodable() {
+        let latin1 = FileEncoding::converted_to(Encoding::Latin1);
+        let error = latin1.encode("a€").unwrap_err();
+        assert_eq!(error.to_string(), "'€' can't be written in latin1");
+    }
+
+    #[test]
+    fn test_write_file() {
+        let path = std::env::temp_dir().join(format!("viron-{}-write", std::process::id()));
+        // The gap of the buffer is inside `é`
+        let mut buffer = Buffer::from_string("caf\n");
+        buffer.insert_bytes(3, &[0xa9]);
+        buffer.insert_bytes(3, &[0xc3]);
+        let encodings = [
+            FileEncoding::default(),
+            FileEncoding { encoding: Encoding::Utf8, bom: true },
+            FileEncoding::converted_to(Encoding::Latin1),
+            FileEncoding::converted_to(Encoding::Utf16Le),
+        ];
+        for encoding in encodings {
+            encoding.write_file(&buffer, &path).unwrap();
+            let written = std::fs::read(&path).unwrap();
+            assert_eq!(written, encoding.encode(&buffer.to_string()).unwrap(), "{encoding}");
+        }
+
+        // Nothing is written when a character can't be
+        buffer.insert_string(0, "€");
+        let latin1 = FileEncoding::converted_to(Encoding::Latin1);
+        assert!(latin1.write_file(&buffer, &path).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"\xff\xfec\x00a\x00f\x00\xe9\x00\n\x00");
+        std::fs::remove_file(path).unwrap();
+    }
+}
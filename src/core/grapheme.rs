@@ -0,0 +1,41 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Byte columns between the grapheme clusters of the line and at its end, as a
+/// character and the marks combined with it or an emoji sequence are one thing to
+/// the cursor
+fn boundaries(line: &str) -> impl Iterator<Item = usize> + '_ {
+    line.grapheme_indices(true).map(|(start, _)| start).chain([line.len()])
+}
+
+/// Start of the grapheme the byte column is in, the end of the line past it
+pub fn floor_boundary(line: &str, column: usize) -> usize {
+    boundaries(line).take_while(|&start| start <= column).last().unwrap_or(0)
+}
+
+/// Start of the grapheme before the one the byte column is in, or 0
+pub fn previous_boundary(line: &str, column: usize) -> usize {
+    boundaries(line).take_while(|&start| start < column).last().unwrap_or(0)
+}
+
+/// End of the grapheme the byte column is in, the end of the line past it
+pub fn next_boundary(line: &str, column: usize) -> usize {
+    boundaries(line).find(|&start| start > column).unwrap_or(line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundaries() {
+        // `e` with a combining acute accent, then a family emoji joined by ZWJs
+        let line = "ae\u{301}👨\u{200d}👩\u{200d}👧b";
+        assert_eq!(next_boundary(line, 1), 4);
+        assert_eq!(next_boundary(line, 4), line.len() - 1);
+        assert_eq!(floor_boundary(line, 2), 1);
+        assert_eq!(floor_boundary(line, line.len()), line.len());
+        assert_eq!(previous_boundary(line, line.len() - 1), 4);
+        assert_eq!(previous_boundary(line, 3), 1);
+        assert_eq!(previous_boundary(line, 0), 0);
+    }
+}
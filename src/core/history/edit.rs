@@ -16,6 +16,8 @@ impl Transition {
 pub enum Edit {
     Insert(Insert),
     Delete(Delete),
+    /// Edits made by one command, applied in order and undone together
+    Group(Vec<Edit>),
 }
 
 impl Edit {
@@ -82,7 +84,8 @@ impl Edit {
                 text.clone(),
                 point.after,
                 point.before,
-            )
+            ),
+            Edit::Group(edits) => Edit::Group(edits.iter().rev().map(Edit::undo).collect()),
         }
     }
 }
@@ -238,6 +241,7 @@ impl Edit {
         match self {
             Edit::Insert(insert) => insert.transition.before,
             Edit::Delete(delete) => delete.transition.before,
+            Edit::Group(edits) => edits.first().map(Edit::point_before).unwrap_or_default(),
         }
     }
 
@@ -245,6 +249,7 @@ impl Edit {
         match self {
             Edit::Insert(insert) => insert.transition.after,
             Edit::Delete(delete) => delete.transition.after,
+            Edit::Group(edits) => edits.last().map(Edit::point_after).unwrap_or_default(),
         }
     }
 }
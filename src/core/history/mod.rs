@@ -7,13 +7,22 @@ use crate::core::history::edit::Edit;
 
 pub mod edit;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct History {
     edits: VecDeque<Edit>,
     redos: VecDeque<Edit>,
     max_size: usize,
     last_action_time: Option<std::time::Instant>,
     group_timeout: Duration,
+    /// Number of undo steps when the text matched the file, None once that state
+    /// can't be reached again, such as after it was evicted or its redos were dropped
+    saved: Option<usize>,
+    /// Number of undo steps when the open group started, such as on entering insert mode
+    group_start: Option<usize>,
+    /// Number of undo steps when each open transaction started, innermost last
+    transaction_starts: Vec<usize>,
+    /// Edits, undos and redos made so far, telling whether the text changed since
+    changes: usize,
 }
 
 impl History {
@@ -24,11 +33,20 @@ impl History {
             max_size: size,
             last_action_time: None,
             group_timeout: Duration::from_millis(500),
+            saved: Some(0),
+            group_start: None,
+            transaction_starts: Vec::new(),
+            changes: 0,
         }
     }
 
     pub fn push(&mut self, change: Edit) {
+        // A saved state among the redos is gone with them
+        if self.saved.is_some_and(|saved| saved > self.edits.len()) {
+            self.saved = None;
+        }
         self.redos.clear();
+        self.changes += 1;
 
         let now = Instant::now();
 
@@ -37,7 +55,8 @@ impl History {
             now.duration_since(last_time) <= self.group_timeout
         });
 
-        if should_group {
+        // Merging into the saved state's last edit would leave no way back to it
+        if should_group && self.saved != Some(self.edits.len()) {
             if let Some(last_change) = self.edits.pop_back() {
                 if let Some(merged) = last_change.merge(&change) {
                     self.edits.push_back(merged);
@@ -54,11 +73,27 @@ impl History {
         // Ensure we don't exceed max size
         while self.edits.len() > self.max_size {
             self.edits.pop_front();
+            self.saved = self.saved.and_then(|saved| saved.checked_sub(1));
+            self.group_start = self.group_start.map(|start| start.saturating_sub(1));
+            for start in &mut self.transaction_starts {
+                *start = start.saturating_sub(1);
+            }
         }
     }
 
+    /// Records that the text now matches the file
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.edits.len());
+    }
+
+    /// Returns true unless undo and redo led back to the text last saved
+    pub fn is_modified(&self) -> bool {
+        self.saved != Some(self.edits.len())
+    }
+
     pub fn undo(&mut self) -> Option<Edit> {
         if let Some(change) = self.edits.pop_back() {
+            self.changes += 1;
             let undo = change.undo();
             self.redos.push_back(change);
             Some(undo)
@@ -69,6 +104,7 @@ impl History {
 
     pub fn redo(&mut self) -> Option<Edit> {
         if let Some(change) = self.redos.pop_back() {
+            self.changes += 1;
             self.edits.push_back(change.clone());
             Some(change)
         } else {
@@ -76,6 +112,30 @@ impl History {
         }
     }
 
+    pub fn change_count(&self) -> usize {
+        self.changes
+    }
+
+    /// Number of undo steps
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Merges the undo steps made since `start` into one
+    fn group_from(&mut self, start: usize) {
+        if start + 1 >= self.edits.len() {
+            return;
+        }
+        let end = self.edits.len();
+        self.saved = self.saved.and_then(|saved| match saved {
+            saved if saved <= start => Some(saved),
+            saved if saved >= end => Some(saved - (end - start - 1)),
+            _ => None,
+        });
+        let edits = self.edits.drain(start..).collect();
+        self.edits.push_back(Edit::Group(edits));
+    }
+
     pub fn can_undo(&self) -> bool {
         !self.edits.is_empty()
     }
@@ -85,12 +145,126 @@ impl History {
     }
 
     pub fn clear(&mut self) {
+        self.saved = (!self.is_modified()).then_some(0);
         self.edits.clear();
         self.redos.clear();
         self.last_action_time = None;
+        self.group_start = None;
+        self.transaction_starts.fill(0);
+    }
+
+    /// Starts a group of edits that are undone together once it ends, however long it
+    /// takes. Beginning an open group again keeps its start, so that `ciw` groups the
+    /// delete with what's typed in the insert mode it enters
+    pub fn begin_group(&mut self) {
+        self.group_start.get_or_insert(self.edits.len());
+        self.last_action_time = None;
+    }
+
+    /// Ends the open group. Edits right after it aren't merged into it by their timing
+    pub fn end_group(&mut self) {
+        if let Some(start) = self.group_start.take() {
+            self.group_from(start);
+        }
+        self.last_action_time = None;
+    }
+
+    /// Starts a transaction, whose edits are undone together once it ends. Unlike a
+    /// group it nests, and the groups opened and closed inside it end as usual
+    pub fn begin_transaction(&mut self) {
+        self.transaction_starts.push(self.edits.len());
+        self.last_action_time = None;
+    }
+
+    /// Ends the innermost transaction
+    pub fn end_transaction(&mut self) {
+        if let Some(start) = self.transaction_starts.pop() {
+            self.group_from(start);
+        }
+        self.last_action_time = None;
     }
 
     pub fn break_group(&mut self) {
         self.last_action_time = Some(Instant::now());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Point;
+
+    fn insert(text: &str) -> Edit {
+        let point = Point::default();
+        Edit::insert(0, point, text.to_string(), point, point)
+    }
+
+    #[test]
+    fn test_modified_follows_save_point() {
+        let mut history = History::new(10);
+        history.push(insert("a"));
+        assert!(history.is_modified());
+        history.undo();
+        assert!(!history.is_modified());
+        history.redo();
+        assert!(history.is_modified());
+
+        history.mark_saved();
+        history.undo();
+        assert!(history.is_modified());
+        history.redo();
+        assert!(!history.is_modified());
+
+        // An edit typed right after saving isn't merged into the saved state
+        history.push(insert("b"));
+        history.undo();
+        assert!(!history.is_modified());
+
+        // Editing after undoing past the save point drops the way back to it
+        history.undo();
+        history.push(insert("c"));
+        history.undo();
+        assert!(history.is_modified());
+    }
+
+    #[test]
+    fn test_groups_ignore_timing() {
+        let mut history = History::new(10);
+        history.push(insert("a"));
+        history.begin_group();
+        history.push(insert("b"));
+        history.begin_group();
+        history.push(Edit::Group(Vec::new()));
+        history.end_group();
+        history.push(insert("c"));
+        assert_eq!(history.len(), 3);
+        assert!(matches!(history.undo(), Some(Edit::Delete(_))));
+        assert!(matches!(history.undo(), Some(Edit::Group(edits)) if edits.len() == 2));
+    }
+
+    #[test]
+    fn test_transaction_survives_eviction() {
+        let mut history = History::new(3);
+        history.push(insert("a"));
+        history.begin_transaction();
+        history.push(insert("b"));
+        history.begin_group();
+        history.push(Edit::Group(Vec::new()));
+        history.end_group();
+        history.push(Edit::Group(Vec::new()));
+        history.push(Edit::Group(Vec::new()));
+        history.end_transaction();
+        assert_eq!(history.len(), 1);
+        assert!(matches!(history.undo(), Some(Edit::Group(edits)) if edits.len() == 3));
+    }
+
+    #[test]
+    fn test_evicted_save_point() {
+        let mut history = History::new(2);
+        history.push(Edit::Group(Vec::new()));
+        history.push(Edit::Group(Vec::new()));
+        history.push(Edit::Group(Vec::new()));
+        while history.undo().is_some() {}
+        assert!(history.is_modified());
+    }
+}
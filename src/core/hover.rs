@@ -0,0 +1,123 @@
+use lsp_types::{HoverContents, MarkedString, MarkupKind};
+
+/// Rows the hover popup takes at most, less on a short screen
+const MAX_ROWS: usize = 15;
+
+/// A line of hover text, in a code block or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverLine {
+    pub text: String,
+    pub code: bool,
+}
+
+/// What the language server says about the symbol under the cursor, shown in a popup
+/// next to it until the next key
+#[derive(Debug, Clone, Default)]
+pub struct HoverText {
+    pub lines: Vec<HoverLine>,
+    /// First line shown, a second `K` scrolls text taller than the popup
+    pub scroll: usize,
+    /// Set by the `K` that scrolled the popup or the key that opened it, so that the key
+    /// doesn't close it
+    pub kept: bool,
+}
+
+impl HoverText {
+    pub fn new(contents: &HoverContents) -> Self {
+        let markdown = |marked: &MarkedString| match marked {
+            MarkedString::String(text) => text.clone(),
+            MarkedString::LanguageString(code) => format!("```{}\n{}\n```", code.language, code.value),
+        };
+        let lines = match contents {
+            HoverContents::Scalar(marked) => parse_markdown(&markdown(marked)),
+            HoverContents::Array(marked) => {
+                let sections = marked.iter().map(markdown).collect::<Vec<_>>();
+                parse_markdown(&sections.join("\n\n"))
+            }
+            HoverContents::Markup(markup) => match markup.kind {
+                MarkupKind::Markdown => parse_markdown(&markup.value),
+                MarkupKind::PlainText => collapse_blank_lines(
+                    markup.value.lines().map(|text| HoverLine { text: text.trim_end().to_string(), code: false }),
+                ),
+            },
+        };
+        Self { lines, ..Default::default() }
+    }
+
+    /// Rows of the popup in an editor area of the height
+    pub fn height(&self, editor_height: usize) -> usize {
+        self.lines.len().min(MAX_ROWS).min(editor_height / 2).max(1)
+    }
+
+    /// Scrolls a popup of the height down a page, false when the end is in view already
+    pub fn scroll_page(&mut self, height: usize) -> bool {
+        let last_top = self.lines.len().saturating_sub(height);
+        if self.scroll >= last_top {
+            return false;
+        }
+        self.scroll = (self.scroll + height).min(last_top);
+        true
+    }
+}
+
+/// Lines of markdown with the fences of code blocks left out and their lines marked
+/// as code. Runs of blank lines are collapsed into one
+fn parse_markdown(markdown: &str) -> Vec<HoverLine> {
+    let mut code = false;
+    let lines = markdown.lines().filter_map(|line| {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            code = !code;
+            return None;
+        }
+        Some(HoverLine { text: line.trim_end().to_string(), code })
+    });
+    collapse_blank_lines(lines)
+}
+
+fn collapse_blank_lines(lines: impl Iterator<Item = HoverLine>) -> Vec<HoverLine> {
+    let mut collapsed: Vec<HoverLine> = Vec::new();
+    for line in lines {
+        let blank = line.text.is_empty();
+        if blank && collapsed.last().is_none_or(|last| last.text.is_empty()) {
+            continue;
+        }
+        collapsed.push(line);
+    }
+    if collapsed.last().is_some_and(|last| last.text.is_empty()) {
+        collapsed.pop();
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::MarkupContent;
+
+    #[test]
+    fn test_hover_markdown() {
+        let contents = HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: "```rust\nfn push(&mut self, value: T)\n```\n\n\n\n---\n\nAppends an element.\n\n".to_string(),
+        });
+        let hover = HoverText::new(&contents);
+        let line = |text: &str, code| HoverLine { text: text.to_string(), code };
+        assert_eq!(
+            hover.lines,
+            [
+                line("fn push(&mut self, value: T)", true),
+                line("", false),
+                line("---", false),
+                line("", false),
+                line("Appends an element.", false),
+            ]
+        );
+
+        let mut hover = HoverText { lines: vec![line("x", false); 20], ..Default::default() };
+        assert_eq!(hover.height(40), 15);
+        assert!(hover.scroll_page(15));
+        assert_eq!(hover.scroll, 5);
+        assert!(!hover.scroll_page(15));
+    }
+}
@@ -0,0 +1,111 @@
+use tree_sitter::Point;
+
+/// Jumps kept, dropping the oldest past it
+const MAX_JUMPS: usize = 100;
+
+/// A position left by a jump, in the document at the index of the buffer list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    pub buffer: usize,
+    pub point: Point,
+}
+
+/// Positions the cursor jumped from, oldest first, walked with `Ctrl-o` and `Ctrl-i`
+#[derive(Debug, Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    /// Entry the cursor is at while walking, the length of the list when not walking
+    position: usize,
+}
+
+impl JumpList {
+    /// Records the position a jump left. A jump made while walking the list drops the
+    /// entries after the one the cursor is at
+    pub fn push(&mut self, jump: Jump) {
+        self.jumps.truncate(self.position);
+        if self.jumps.last() != Some(&jump) {
+            self.jumps.push(jump);
+        }
+        let excess = self.jumps.len().saturating_sub(MAX_JUMPS);
+        self.jumps.drain(..excess);
+        self.position = self.jumps.len();
+    }
+
+    /// The entry before the one the cursor is at. Starting to walk records where the
+    /// cursor is, so that `newer` can come back to it
+    pub fn older(&mut self, current: Jump) -> Option<Jump> {
+        if self.position == self.jumps.len() {
+            if self.jumps.last() != Some(&current) {
+                self.jumps.push(current);
+            }
+            self.position = self.jumps.len() - 1;
+        }
+        self.position = self.position.checked_sub(1)?;
+        Some(self.jumps[self.position])
+    }
+
+    /// The entry after the one the cursor is at
+    pub fn newer(&mut self) -> Option<Jump> {
+        if self.position + 1 >= self.jumps.len() {
+            return None;
+        }
+        self.position += 1;
+        Some(self.jumps[self.position])
+    }
+
+    /// Drops the jumps into a closed buffer, and moves those into the buffers after it
+    pub fn remove_buffer(&mut self, buffer: usize) {
+        let before = self.jumps[..self.position.min(self.jumps.len())]
+            .iter()
+            .filter(|jump| jump.buffer == buffer)
+            .count();
+        self.jumps.retain(|jump| jump.buffer != buffer);
+        for jump in &mut self.jumps {
+            if jump.buffer > buffer {
+                jump.buffer -= 1;
+            }
+        }
+        self.position = self.position.saturating_sub(before).min(self.jumps.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jump(buffer: usize, row: usize) -> Jump {
+        Jump { buffer, point: Point::new(row, 0) }
+    }
+
+    #[test]
+    fn test_walk_jumps() {
+        let mut jumps = JumpList::default();
+        jumps.push(jump(0, 1));
+        jumps.push(jump(0, 2));
+        assert_eq!(jumps.older(jump(1, 3)), Some(jump(0, 2)));
+        assert_eq!(jumps.older(jump(0, 2)), Some(jump(0, 1)));
+        assert_eq!(jumps.older(jump(0, 1)), None);
+        assert_eq!(jumps.newer(), Some(jump(0, 2)));
+        assert_eq!(jumps.newer(), Some(jump(1, 3)));
+        assert_eq!(jumps.newer(), None);
+
+        // Jumping from the middle drops the newer entries
+        jumps.older(jump(1, 3));
+        jumps.older(jump(0, 2));
+        jumps.push(jump(0, 1));
+        assert_eq!(jumps.jumps, [jump(0, 1)]);
+
+        // Closing a buffer drops its jumps
+        jumps.push(jump(1, 5));
+        jumps.push(jump(2, 6));
+        jumps.remove_buffer(1);
+        assert_eq!(jumps.jumps, [jump(0, 1), jump(1, 6)]);
+        assert_eq!(jumps.older(jump(0, 9)), Some(jump(1, 6)));
+
+        for row in 0..200 {
+            jumps.push(jump(0, row));
+        }
+        assert_eq!(jumps.jumps.len(), MAX_JUMPS);
+        assert_eq!(jumps.jumps[0], jump(0, 100));
+    }
+}
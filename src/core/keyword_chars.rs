@@ -0,0 +1,19 @@
+/// Which characters make up a word, the same for motions, `K`, `*` and `Ctrl-w`.
+/// Like vim's `iskeyword` these are letters, digits and `_`, plus the extra
+/// characters configured for the language, such as `-` in CSS
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeywordChars {
+    extra: Vec<char>,
+}
+
+impl KeywordChars {
+    pub fn new(extra: &str) -> Self {
+        Self {
+            extra: extra.chars().filter(|c| !c.is_whitespace()).collect(),
+        }
+    }
+
+    pub fn is_keyword(&self, c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || self.extra.contains(&c)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{env, fs, path::Path};
+use std::path::Path;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Language {
@@ -45,6 +45,31 @@ impl Language {
         }
     }
 
+    pub const ALL: [Language; 14] = [
+        Self::Rust,
+        Self::JavaScript,
+        Self::TypeScript,
+        Self::Python,
+        Self::Go,
+        Self::C,
+        Self::Cpp,
+        Self::Toml,
+        Self::Json,
+        Self::Markdown,
+        Self::Html,
+        Self::Css,
+        Self::Bash,
+        Self::PlainText,
+    ];
+
+    /// Looks up a language by the name returned from `to_str`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|language| language.to_str() == name)
+    }
+
     pub fn from_path(path: &Path) -> Self {
         path.extension()
             .and_then(|ext| ext.to_str())
@@ -85,23 +110,19 @@ impl Language {
         }
     }
 
-    pub fn get_language_server(&self) -> Option<&str> {
-        let executable = match self {
-            Self::Rust => Some("rust-analyzer"),
+    /// Query capturing definitions as `@definition.<kind>` with their `@name`
+    pub fn get_tags_query(&self) -> Option<&str> {
+        match self {
+            Self::Rust => Some(tree_sitter_rust::TAGS_QUERY),
             _ => None,
-        };
-        executable.filter(|&executable| is_program_in_path(executable))
+        }
     }
-}
 
-fn is_program_in_path(program: &str) -> bool {
-    if let Ok(path) = env::var("PATH") {
-        for p in path.split(":") {
-            let p_str = format!("{}/{}", p, program);
-            if fs::metadata(p_str).is_ok() {
-                return true;
-            }
+    /// Command of the builtin language server, when `[lsp.servers]` doesn't name one
+    pub fn get_language_server(&self) -> Option<&str> {
+        match self {
+            Self::Rust => Some("rust-analyzer"),
+            _ => None,
         }
     }
-    false
 }
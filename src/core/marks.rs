@@ -0,0 +1,149 @@
+use crate::core::history::edit::Edit;
+use std::collections::HashMap;
+use tree_sitter::Point;
+
+/// The mark set by jumps, holding where the cursor was before the last one
+pub const JUMP_MARK: char = '\'';
+
+/// Whether the character names a mark that `m` can set, `'` and `` ` `` jump to
+pub fn is_mark_name(name: char) -> bool {
+    name.is_ascii_lowercase() || name == JUMP_MARK || name == '`'
+}
+
+/// Positions of a document saved with `m{a-z}` and by jumps, which move with the
+/// text as it's edited. `` ` `` names the jump mark too
+#[derive(Debug, Clone, Default)]
+pub struct Marks {
+    points: HashMap<char, Point>,
+}
+
+impl Marks {
+    fn key(name: char) -> char {
+        if name == '`' { JUMP_MARK } else { name }
+    }
+
+    pub fn set(&mut self, name: char, point: Point) {
+        self.points.insert(Self::key(name), point);
+    }
+
+    pub fn get(&self, name: char) -> Option<Point> {
+        self.points.get(&Self::key(name)).copied()
+    }
+
+    /// Moves the marks after the edited text along with it. A mark in deleted text
+    /// is dropped, unless the deletion is replaced by text with as many lines at the
+    /// same place, as `>>` and `r` do
+    pub fn apply_edit(&mut self, edit: &Edit) {
+        match edit {
+            Edit::Insert(insert) => self.update(|point| Some(inserted(point, insert.start_point, &insert.text))),
+            Edit::Delete(delete) => self.update(|point| deleted(point, delete.start_point, &delete.text)),
+            Edit::Group(edits) => {
+                let mut edits = edits.iter().peekable();
+                while let Some(edit) = edits.next() {
+                    if let Edit::Delete(delete) = edit
+                        && let Some(Edit::Insert(insert)) = edits.peek()
+                        && insert.start_byte == delete.start_byte
+                    {
+                        let (start, old, new) = (delete.start_point, &delete.text, &insert.text);
+                        self.update(|point| replaced(point, start, old, new));
+                        edits.next();
+                        continue;
+                    }
+                    self.apply_edit(edit);
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, update: impl Fn(Point) -> Option<Point>) {
+        self.points.retain(|_, point| match update(*point) {
+            Some(updated) => {
+                *point = updated;
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+/// Where text starting at the point ends
+fn end_of(start: Point, text: &str) -> Point {
+    match text.rfind('\n') {
+        Some(index) => Point::new(start.row + text.matches('\n').count(), text.len() - index - 1),
+        None => Point::new(start.row, start.column + text.len()),
+    }
+}
+
+/// A point after text was inserted at the start, which pushes a point at the start too
+fn inserted(point: Point, start: Point, text: &str) -> Point {
+    if point < start {
+        return point;
+    }
+    let end = end_of(start, text);
+    match point.row == start.row {
+        true => Point::new(end.row, end.column + point.column - start.column),
+        false => Point::new(point.row + end.row - start.row, point.column),
+    }
+}
+
+/// A point after text was deleted from the start, `None` when it was in the text
+fn deleted(point: Point, start: Point, text: &str) -> Option<Point> {
+    let end = end_of(start, text);
+    if point < start {
+        return Some(point);
+    }
+    if point < end {
+        return None;
+    }
+    Some(match point.row == end.row {
+        true => Point::new(start.row, start.column + point.column - end.column),
+        false => Point::new(point.row - (end.row - start.row), point.column),
+    })
+}
+
+/// A point after the old text at the start was replaced by the new text. Points in
+/// the old text stay where they are while the new text reaches their line
+fn replaced(point: Point, start: Point, old: &str, new: &str) -> Option<Point> {
+    if point >= start && point < end_of(start, old) {
+        let within = point.row - start.row <= new.matches('\n').count();
+        return within.then_some(point);
+    }
+    deleted(point, start, old).map(|point| inserted(point, start, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_follow_edits() {
+        let at = |row, column| Point::new(row, column);
+        let mut marks = Marks::default();
+        marks.set('a', at(2, 3));
+        marks.set('b', at(1, 1));
+        marks.set('`', at(0, 0));
+        assert_eq!(marks.get(JUMP_MARK), Some(at(0, 0)));
+
+        // Two lines inserted above move the marks below down
+        marks.apply_edit(&Edit::insert(0, at(1, 0), "x\ny\n".to_string(), at(1, 0), at(3, 0)));
+        assert_eq!(marks.get('a'), Some(at(4, 3)));
+        assert_eq!(marks.get('b'), Some(at(3, 1)));
+        assert_eq!(marks.get('\''), Some(at(0, 0)));
+
+        // Text inserted before a mark on its line moves it right
+        marks.apply_edit(&Edit::insert(0, at(4, 1), "ab".to_string(), at(4, 1), at(4, 3)));
+        assert_eq!(marks.get('a'), Some(at(4, 5)));
+
+        // Deleting the marked line drops the mark and moves the ones below up
+        marks.apply_edit(&Edit::delete(0, at(3, 0), "line\n".to_string(), at(3, 0), at(3, 0)));
+        assert_eq!(marks.get('b'), None);
+        assert_eq!(marks.get('a'), Some(at(3, 5)));
+
+        // Replacing the line with as many lines keeps the mark
+        marks.apply_edit(&Edit::Group(vec![
+            Edit::delete(0, at(3, 0), "xxxxxxx".to_string(), at(3, 0), at(3, 0)),
+            Edit::insert(0, at(3, 0), "    xxxxxxx".to_string(), at(3, 0), at(3, 0)),
+        ]));
+        assert_eq!(marks.get('a'), Some(at(3, 5)));
+    }
+}
@@ -1,3 +1,10 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Messages kept for `:messages`, dropping the oldest past it
+const LOG_SIZE: usize = 200;
+
 #[derive(Debug, Clone)]
 pub enum MessageType {
     Info,
@@ -26,9 +33,73 @@ impl Message {
     }
 }
 
+/// A message as `:messages` lists it, with when it was shown
+#[derive(Debug, Clone)]
+pub struct LoggedMessage {
+    pub message: Message,
+    pub time: SystemTime,
+}
+
+impl LoggedMessage {
+    /// `HH:MM:SS` of the time it was shown, in the local time zone
+    pub fn clock(&self) -> String {
+        let seconds = self.time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let seconds = seconds as libc::time_t;
+        // SAFETY: `localtime_r` only writes the fields of the zeroed `tm` it's given
+        let tm = unsafe {
+            let mut tm = std::mem::zeroed::<libc::tm>();
+            libc::localtime_r(&seconds, &mut tm);
+            tm
+        };
+        format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+    }
+}
+
+/// Lines printed by a command, such as `:g/pattern/p`, that don't fit in the message
+/// area. They're shown a page at a time above a `-- More --` prompt
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub lines: Vec<String>,
+    /// Index of the first line shown
+    pub top: usize,
+}
+
+impl Output {
+    /// First line shown on the last page, for pages of the height
+    pub fn last_top(&self, height: usize) -> usize {
+        self.lines.len().saturating_sub(height)
+    }
+
+    pub fn at_end(&self, height: usize) -> bool {
+        self.top >= self.last_top(height)
+    }
+}
+
+/// A swap file found on opening a file, for the buffer at the index
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    pub swap: PathBuf,
+    pub buffer: usize,
+}
+
+/// What the bottom row shows while no prompt is open, the latest replacing the other
+#[derive(Debug, Clone)]
+pub enum Notice {
+    Message(Message),
+    /// The pattern and match counter of the last search
+    SearchResult,
+}
+
 #[derive(Debug, Default)]
 pub struct MessageManager {
-    current_message: Option<Message>,
+    notice: Option<Notice>,
+    /// Messages that came while a prompt was open, shown once it closes
+    queued: Vec<Message>,
+    output: Option<Output>,
+    /// Swap file of a file opened, asked about until it's recovered or deleted
+    recovery: Option<Recovery>,
+    /// Every message shown, oldest first
+    log: VecDeque<LoggedMessage>,
 }
 
 impl MessageManager {
@@ -36,15 +107,83 @@ impl MessageManager {
         Self::default()
     }
 
+    pub fn notice(&self) -> Option<&Notice> {
+        self.notice.as_ref()
+    }
+
     pub fn current_message(&self) -> Option<&Message> {
-        self.current_message.as_ref()
+        match &self.notice {
+            Some(Notice::Message(message)) => Some(message),
+            _ => None,
+        }
     }
 
     pub fn show_message(&mut self, message: Message) {
-        self.current_message = Some(message);
+        self.notice = Some(Notice::Message(message));
+    }
+
+    pub fn show_search_result(&mut self) {
+        self.notice = Some(Notice::SearchResult);
+    }
+
+    pub fn hide_search_result(&mut self) {
+        if matches!(self.notice, Some(Notice::SearchResult)) {
+            self.notice = None;
+        }
     }
 
     pub fn clear_message(&mut self) {
-        self.current_message = None;
+        self.notice = None;
+    }
+
+    pub fn queue_message(&mut self, message: Message) {
+        self.queued.push(message);
+    }
+
+    pub fn take_queued(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.queued)
+    }
+
+    pub fn output(&self) -> Option<&Output> {
+        self.output.as_ref()
+    }
+
+    pub fn output_mut(&mut self) -> Option<&mut Output> {
+        self.output.as_mut()
+    }
+
+    pub fn show_output(&mut self, lines: Vec<String>) {
+        self.output = Some(Output { lines, top: 0 });
+    }
+
+    pub fn close_output(&mut self) {
+        self.output = None;
+    }
+
+    /// Keeps the message for `:messages`
+    pub fn log(&mut self, message: &Message) {
+        if self.log.len() == LOG_SIZE {
+            self.log.pop_front();
+        }
+        self.log.push_back(LoggedMessage {
+            message: message.clone(),
+            time: SystemTime::now(),
+        });
+    }
+
+    pub fn logged(&self) -> impl Iterator<Item = &LoggedMessage> {
+        self.log.iter()
+    }
+
+    pub fn recovery(&self) -> Option<&Recovery> {
+        self.recovery.as_ref()
+    }
+
+    pub fn ask_recovery(&mut self, recovery: Recovery) {
+        self.recovery = Some(recovery);
+    }
+
+    pub fn take_recovery(&mut self) -> Option<Recovery> {
+        self.recovery.take()
     }
 }
@@ -1,13 +1,25 @@
 pub mod buffer;
 pub mod buffer_manager;
 pub mod command;
+pub mod completions;
 pub mod cursor;
+pub mod diff;
 pub mod document;
+pub mod encoding;
+pub mod grapheme;
 pub mod history;
+pub mod hover;
+pub mod jumps;
+pub mod keyword_chars;
 pub mod language;
+pub mod marks;
 pub mod message;
 pub mod mode;
 pub mod operation;
+pub mod quickfix;
+pub mod replace;
+pub mod suggest;
+pub mod swap;
 pub mod syntax;
 pub mod utf8;
 pub mod viewport;
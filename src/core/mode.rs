@@ -9,6 +9,8 @@ pub enum Mode {
     Command,
     Search,
     OperationPending(Operator),
+    /// `V`, selecting whole lines from the row it was entered on to the cursor row
+    VisualLine,
 }
 
 impl Mode {
@@ -19,6 +21,7 @@ impl Mode {
             Mode::Command => "command".to_string(),
             Mode::Search => "search".to_string(),
             Mode::OperationPending(_) => "o-pending".to_string(),
+            Mode::VisualLine => "visual-line".to_string(),
         }
     }
 
@@ -29,6 +32,7 @@ impl Mode {
             Mode::Command => "command",
             Mode::Search => "search",
             Mode::OperationPending(_) => "o-pending",
+            Mode::VisualLine => "visual line",
         }
     }
 
@@ -43,3 +47,11 @@ impl Mode {
         matches!(self, Mode::Insert) || matches!(self, Mode::OperationPending(_))
     }
 }
+
+/// First and last of the lines selected in visual line mode, whichever side of the
+/// anchor the cursor is on. An anchor past the end, left by lines deleted since, is
+/// kept to the last line
+pub fn selected_lines(anchor: usize, row: usize, line_count: usize) -> (usize, usize) {
+    let anchor = anchor.min(line_count.saturating_sub(1));
+    (anchor.min(row), anchor.max(row))
+}
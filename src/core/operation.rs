@@ -5,6 +5,8 @@ pub enum Operator {
     Delete,
     Change,
     Yank,
+    Indent,
+    Dedent,
 }
 
 impl Operator {
@@ -13,6 +15,8 @@ impl Operator {
             Operator::Delete => "d".to_string(),
             Operator::Change => "c".to_string(),
             Operator::Yank => "y".to_string(),
+            Operator::Indent => ">".to_string(),
+            Operator::Dedent => "<".to_string(),
         }
     }
 
@@ -21,6 +25,8 @@ impl Operator {
             Operator::Delete => "delete",
             Operator::Change => "change",
             Operator::Yank => "yank",
+            Operator::Indent => "indent",
+            Operator::Dedent => "dedent",
         }
     }
 }
@@ -0,0 +1,117 @@
+use crate::constants::RESERVED_ROW_COUNT;
+use crate::service::lsp::uri_to_path;
+use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Uri};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Rows the panel takes at most, its title included
+const MAX_ROWS: usize = 10;
+
+/// A diagnostic of the quickfix list, with the first line of its message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickfixEntry {
+    pub path: PathBuf,
+    /// Where it starts, in the units of the server's positions
+    pub position: Position,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// The diagnostics of every file the language server reported on, listed in a panel
+/// between the status line and the command line by `:copen`, and gone through with
+/// `:cnext` and `:cprev`
+#[derive(Debug, Default)]
+pub struct QuickfixList {
+    pub entries: Vec<QuickfixEntry>,
+    /// Entry last jumped to, none before the first `:cnext`
+    pub selected: Option<usize>,
+    pub open: bool,
+}
+
+impl QuickfixList {
+    /// Replaces the entries with the diagnostics by document URI, in file order. The
+    /// selection stays on its entry while it's still there
+    pub fn update<'a>(&mut self, diagnostics: impl Iterator<Item = (&'a str, &'a [Diagnostic])>) {
+        let selected = self.selected.and_then(|index| Some((index, self.entries.get(index)?.clone())));
+        self.entries = diagnostics
+            .filter_map(|(uri, diagnostics)| Some((uri_to_path(&Uri::from_str(uri).ok()?), diagnostics)))
+            .flat_map(|(path, diagnostics)| {
+                diagnostics.iter().map(move |diagnostic| QuickfixEntry {
+                    path: path.clone(),
+                    position: diagnostic.range.start,
+                    severity: diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR),
+                    message: diagnostic.message.lines().next().unwrap_or_default().to_string(),
+                })
+            })
+            .collect();
+        // Diagnostics come sorted by position, the sort is stable
+        self.entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.selected = match selected {
+            Some((index, entry)) if !self.entries.is_empty() => {
+                let kept = self.entries.iter().position(|other| *other == entry);
+                Some(kept.unwrap_or(index.min(self.entries.len() - 1)))
+            }
+            _ => None,
+        };
+    }
+
+    /// Selects the entry the offset away from the selected one, the first or last one
+    /// before any was. None past the ends
+    pub fn select(&mut self, offset: isize) -> Option<&QuickfixEntry> {
+        let index = match self.selected {
+            Some(selected) => selected.checked_add_signed(offset)?,
+            None if offset < 0 => self.entries.len().checked_sub(1)?,
+            None => 0,
+        };
+        let entry = self.entries.get(index)?;
+        self.selected = Some(index);
+        Some(entry)
+    }
+
+    /// Rows of the panel on a screen of the height, none while it's closed
+    pub fn rows(&self, screen_height: usize) -> usize {
+        match self.open {
+            true => MAX_ROWS.min(screen_height.saturating_sub(RESERVED_ROW_COUNT) / 2),
+            false => 0,
+        }
+    }
+
+    /// Rows left for the text on a screen of the height, above the status line
+    pub fn editor_height(&self, screen_height: usize) -> usize {
+        screen_height.saturating_sub(RESERVED_ROW_COUNT + self.rows(screen_height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::Range;
+
+    #[test]
+    fn test_update_keeps_selection() {
+        let diagnostic = |line: u32, message: &str| Diagnostic {
+            range: Range {
+                start: Position { line, character: 2 },
+                end: Position { line, character: 3 },
+            },
+            message: message.to_string(),
+            ..Default::default()
+        };
+        let (a, b) = ([diagnostic(1, "a1\nmore"), diagnostic(4, "a4")], [diagnostic(0, "b0")]);
+        let mut list = QuickfixList::default();
+        list.update([("file:///b.rs", &b[..]), ("file:///a.rs", &a[..])].into_iter());
+        let messages = list.entries.iter().map(|entry| entry.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, ["a1", "a4", "b0"]);
+        assert_eq!(list.entries[0].position, Position::new(1, 2));
+
+        assert_eq!(list.select(1).unwrap().message, "a1");
+        assert_eq!(list.select(1).unwrap().message, "a4");
+        // An entry fixed before the selected one doesn't move the selection off it
+        list.update([("file:///a.rs", &a[1..]), ("file:///b.rs", &b[..])].into_iter());
+        assert_eq!(list.selected, Some(0));
+        assert_eq!(list.select(1).unwrap().message, "b0");
+        assert!(list.select(1).is_none());
+        assert_eq!(list.selected, Some(1));
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum RegisterKind {
     #[default]
     Character,
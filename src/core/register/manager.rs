@@ -1,4 +1,4 @@
-use crate::core::register::internal::Register;
+use crate::core::register::internal::{Register, RegisterKind};
 use crate::core::register::name::RegisterName;
 use std::collections::HashMap;
 
@@ -21,7 +21,7 @@ impl RegisterSystem {
     }
 
     pub fn get(&self, name: &RegisterName) -> Option<&Register> {
-        self.registers.get(name)
+        self.registers.get(&name.lowercase())
     }
 
     pub fn set(&mut self, name: &RegisterName, register: Register) {
@@ -32,37 +32,69 @@ impl RegisterSystem {
         self.current_target = Some(target);
     }
 
+    /// Yanks go to register 0, or only to the one given with `"x`
     pub fn on_yank(&mut self, register: Register) {
         self.registers
             .insert(RegisterName::Unnamed, register.clone());
 
-        let target = self.current_target.take().unwrap_or_default();
-        self.registers.insert(target, register.clone());
-
-        self.registers.insert(RegisterName::LAST_YANK, register);
+        let target = self.take_target().unwrap_or(RegisterName::LAST_YANK);
+        self.store(target, register);
     }
 
+    /// Deletes of whole lines or spanning several lines are pushed to register 1,
+    /// shifting the older ones up to 9, and smaller ones go to `-`. As in vim, a
+    /// delete into a register given with `"x` leaves both alone
     pub fn on_delete(&mut self, register: Register) {
         self.registers
             .insert(RegisterName::Unnamed, register.clone());
 
-        let target = self.current_target.take().unwrap_or_default();
-        self.registers.insert(target, register.clone());
-
-        if register.content.len() < 50 && !register.content.contains('\n') {
-            self.registers.insert(RegisterName::SmallDelete, register);
-        } else {
-            self.shift_numbered_registers(register);
+        match self.take_target() {
+            Some(target) => self.store(target, register),
+            None if register.kind == RegisterKind::Line || register.content.contains('\n') => {
+                self.shift_numbered_registers(register);
+            }
+            None => {
+                self.registers.insert(RegisterName::SmallDelete, register);
+            }
         }
     }
 
     pub fn on_paste(&mut self) -> Option<Register> {
         let target = self.current_target.take().unwrap_or(RegisterName::Unnamed);
-        self.registers.get(&target).cloned()
+        self.get(&target).cloned()
+    }
+
+    /// Registers that aren't empty, in the order `:registers` lists them
+    pub fn non_empty(&self) -> impl Iterator<Item = (RegisterName, &Register)> {
+        RegisterName::all_names().into_iter().filter_map(|name| {
+            let register = self.registers.get(&name)?;
+            (!register.is_empty()).then_some((name, register))
+        })
+    }
+
+    /// Puts the register in the target, or adds it to the end of the register for an
+    /// uppercase name as in `"Ayy`, which the unnamed register then mirrors
+    fn store(&mut self, target: RegisterName, register: Register) {
+        if target == target.lowercase() {
+            self.registers.insert(target, register);
+            return;
+        }
+        let target = target.lowercase();
+        let old = self.registers.remove(&target).unwrap_or_default();
+        let appended = appended(old, register);
+        self.registers.insert(RegisterName::Unnamed, appended.clone());
+        self.registers.insert(target, appended);
+    }
+
+    fn take_target(&mut self) -> Option<RegisterName> {
+        self.current_target
+            .take()
+            .filter(|target| *target != RegisterName::Unnamed)
     }
 
     pub fn shift_numbered_registers(&mut self, register: Register) {
-        for i in 1..9 {
+        // From the top down, so each register moves before the one below replaces it
+        for i in (1..9).rev() {
             let value = self
                 .registers
                 .remove(&RegisterName::Numbered(i))
@@ -72,3 +104,66 @@ impl RegisterSystem {
         self.registers.insert(RegisterName::Numbered(1), register);
     }
 }
+
+/// The new text after the old, linewise when either is. Lines go after characters
+/// on a line of their own, as in vim
+fn appended(old: Register, new: Register) -> Register {
+    match (old.kind, new.kind) {
+        (RegisterKind::Character, RegisterKind::Character) => {
+            Register::new(old.content + &new.content, RegisterKind::Character)
+        }
+        (RegisterKind::Character, RegisterKind::Line) if !old.is_empty() => {
+            Register::new(format!("{}\n{}", old.content, new.content), RegisterKind::Line)
+        }
+        _ => Register::new(old.content + &new.content, RegisterKind::Line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(registers: &RegisterSystem, name: RegisterName) -> &str {
+        &registers.get(&name).unwrap().content
+    }
+
+    #[test]
+    fn test_numbered_rotation() {
+        let mut registers = RegisterSystem::new();
+        for i in 1..=10 {
+            registers.on_delete(Register::new(format!("line {i}"), RegisterKind::Line));
+        }
+        // The newest is in 1 and the oldest fell off the end
+        for n in 1..=9 {
+            let expected = format!("line {}\n", 11 - n);
+            assert_eq!(content(&registers, RegisterName::Numbered(n)), expected);
+        }
+
+        // Small deletes and yanks leave the numbered registers alone
+        registers.on_delete(Register::new("x".to_string(), RegisterKind::Character));
+        registers.on_delete(Register::new("a\nb".to_string(), RegisterKind::Character));
+        registers.on_yank(Register::new("yanked".to_string(), RegisterKind::Character));
+        assert_eq!(content(&registers, RegisterName::SmallDelete), "x");
+        assert_eq!(content(&registers, RegisterName::Numbered(1)), "a\nb");
+        assert_eq!(content(&registers, RegisterName::Numbered(2)), "line 10\n");
+        assert_eq!(content(&registers, RegisterName::LAST_YANK), "yanked");
+
+        // As does a delete into a named register
+        registers.set_current_target(RegisterName::Named('a'));
+        registers.on_delete(Register::new("named".to_string(), RegisterKind::Line));
+        assert_eq!(content(&registers, RegisterName::Numbered(1)), "a\nb");
+        assert_eq!(content(&registers, RegisterName::Unnamed), "named\n");
+
+        // An uppercase name appends, linewise when either part is
+        registers.set_current_target(RegisterName::Named('A'));
+        registers.on_yank(Register::new("more".to_string(), RegisterKind::Character));
+        assert_eq!(content(&registers, RegisterName::Named('a')), "named\nmore\n");
+        assert_eq!(content(&registers, RegisterName::Unnamed), "named\nmore\n");
+        registers.set_current_target(RegisterName::Named('B'));
+        registers.on_yank(Register::new("b".to_string(), RegisterKind::Character));
+        registers.set_current_target(RegisterName::Named('B'));
+        registers.on_yank(Register::new("c".to_string(), RegisterKind::Character));
+        assert_eq!(content(&registers, RegisterName::Named('B')), "bc");
+        assert_eq!(registers.get(&RegisterName::Named('b')).unwrap().kind, RegisterKind::Character);
+    }
+}
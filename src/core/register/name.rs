@@ -16,7 +16,7 @@ impl RegisterName {
             RegisterName::Unnamed => '"',
             RegisterName::Numbered(number) => (number + b'0') as char,
             RegisterName::Named(char) => char,
-            RegisterName::SmallDelete => '_',
+            RegisterName::SmallDelete => '-',
         }
     }
 
@@ -25,12 +25,20 @@ impl RegisterName {
             '"' => RegisterName::Unnamed,
             '0'..='9' => RegisterName::Numbered(c as u8 - b'0'),
             'a'..='z' | 'A'..='Z' => RegisterName::Named(c),
-            '_' => RegisterName::SmallDelete,
+            '-' => RegisterName::SmallDelete,
             _ => return { Err(anyhow!("Invalid register name: {c}")) },
         };
         Ok(register)
     }
 
+    /// The register an uppercase name appends to
+    pub fn lowercase(self) -> RegisterName {
+        match self {
+            RegisterName::Named(c) => RegisterName::Named(c.to_ascii_lowercase()),
+            name => name,
+        }
+    }
+
     pub fn is_valid_name(c: char) -> bool {
         Self::from_char(c).is_ok()
     }
@@ -0,0 +1,222 @@
+use regex::Captures;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    /// Text expanded against the match, where `\1` and `&` became `${1}` and `${0}`
+    /// and a typed `$` became `$$`
+    Text(String),
+    /// `\u` or `\l`, changing the case of the next character
+    Next(Case),
+    /// `\U` or `\L` until `\e` or `\E`, which is `None`
+    Until(Option<Case>),
+}
+
+/// Replacement text of `:s`, with vim's `\u`, `\U`, `\l`, `\L` and `\e` case escapes.
+/// `\0` to `\9` are the groups of the match and `&` the whole of it, `\&` is an `&`
+/// and `\\` a backslash. Other backslashes and `$` are kept as they are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    parts: Vec<Part>,
+    /// Whether the replacement follows the case of each match, as with `:S`
+    preserve_case: bool,
+}
+
+impl Replacement {
+    pub fn parse(input: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            let escape = match (c, chars.peek()) {
+                ('\\', Some('u')) => Part::Next(Case::Upper),
+                ('\\', Some('l')) => Part::Next(Case::Lower),
+                ('\\', Some('U')) => Part::Until(Some(Case::Upper)),
+                ('\\', Some('L')) => Part::Until(Some(Case::Lower)),
+                ('\\', Some('e' | 'E')) => Part::Until(None),
+                ('\\', Some(&escaped @ ('\\' | '&'))) => {
+                    chars.next();
+                    text.push(escaped);
+                    continue;
+                }
+                ('\\', Some(&digit)) if digit.is_ascii_digit() => {
+                    chars.next();
+                    text.push_str(&format!("${{{digit}}}"));
+                    continue;
+                }
+                ('&', _) => {
+                    text.push_str("${0}");
+                    continue;
+                }
+                // Doubled, so that expanding the groups leaves it as typed
+                ('$', _) => {
+                    text.push_str("$$");
+                    continue;
+                }
+                (c, _) => {
+                    text.push(c);
+                    continue;
+                }
+            };
+            chars.next();
+            if !text.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut text)));
+            }
+            parts.push(escape);
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Self {
+            parts,
+            preserve_case: false,
+        }
+    }
+
+    pub fn preserving_case(mut self) -> Self {
+        self.preserve_case = true;
+        self
+    }
+
+    /// Text replacing the match
+    pub fn expand(&self, captures: &Captures) -> String {
+        let mut replaced = String::new();
+        let mut next = None;
+        let mut until = None;
+        for part in &self.parts {
+            match part {
+                Part::Next(case) => next = Some(*case),
+                Part::Until(case) => until = *case,
+                Part::Text(text) => {
+                    let mut expanded = String::new();
+                    captures.expand(text, &mut expanded);
+                    for c in expanded.chars() {
+                        match next.take().or(until) {
+                            Some(Case::Upper) => replaced.extend(c.to_uppercase()),
+                            Some(Case::Lower) => replaced.extend(c.to_lowercase()),
+                            None => replaced.push(c),
+                        }
+                    }
+                }
+            }
+        }
+        match self.preserve_case {
+            true => match_case(&captures[0], &replaced),
+            false => replaced,
+        }
+    }
+}
+
+/// How the letters of a text are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextCase {
+    /// `foo`
+    Lower,
+    /// `Foo`, or a single capital
+    Title,
+    /// `FOO`
+    Upper,
+    /// `fooBar`, or no letters at all
+    Mixed,
+}
+
+pub fn text_case(text: &str) -> TextCase {
+    let mut letters = text.chars().filter(|c| c.is_lowercase() || c.is_uppercase());
+    let Some(first) = letters.next() else {
+        return TextCase::Mixed;
+    };
+    let rest = letters.collect::<Vec<_>>();
+    let rest_lower = rest.iter().all(|c| c.is_lowercase());
+    let rest_upper = rest.iter().all(|c| c.is_uppercase());
+    match (first.is_uppercase(), rest_lower, rest_upper) {
+        (false, true, _) => TextCase::Lower,
+        (true, true, _) => TextCase::Title,
+        (true, false, true) => TextCase::Upper,
+        _ => TextCase::Mixed,
+    }
+}
+
+/// The replacement written like the matched text: all capitals for `FOO`, and the
+/// first letter changed for `foo` and `Foo`. Mixed case leaves it as typed
+pub fn match_case(matched: &str, replacement: &str) -> String {
+    let mut chars = replacement.chars();
+    let first = chars.next();
+    let change_first = |change: fn(char) -> String| {
+        first.map(change).unwrap_or_default() + chars.as_str()
+    };
+    match text_case(matched) {
+        TextCase::Upper => replacement.to_uppercase(),
+        TextCase::Title => change_first(|c| c.to_uppercase().collect()),
+        TextCase::Lower => change_first(|c| c.to_lowercase().collect()),
+        TextCase::Mixed => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn replace(pattern: &str, replacement: Replacement, text: &str) -> String {
+        Regex::new(pattern)
+            .unwrap()
+            .replace_all(text, |captures: &Captures| replacement.expand(captures))
+            .into_owned()
+    }
+
+    #[test]
+    fn test_case_escapes() {
+        let cases = [
+            (r"\u\1_\2", "old_name", "Old_name"),
+            (r"\U\1\e_\2", "old_name", "OLD_name"),
+            (r"\L\u\1\2", "OLD_NAME", "Oldname"),
+            (r"\l\1 \\u", "OLD_NAME", r"oLD \u"),
+            (r"\U\1", "straße_x", "STRASSE"),
+            (r"\u\2 for $1", "old_name", "Name for $1"),
+            (r"a\nb", "x_y", r"a\nb"),
+            (r"\2\1_\0", "old_name", "nameold_old_name"),
+        ];
+        for (replacement, text, expected) in cases {
+            let replacement = Replacement::parse(replacement);
+            assert_eq!(replace(r"^(\w+?)_(\w+)$", replacement, text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_groups() {
+        let cases = [
+            (r"[\1] & \&", "old_name", "[old] old_name &"),
+            (r"\U&", "old_name", "OLD_NAME"),
+            ("$1 ${2} $$", "old_name", "$1 ${2} $$"),
+            ("$", "old_name", "$"),
+        ];
+        for (replacement, text, expected) in cases {
+            let replacement = Replacement::parse(replacement);
+            assert_eq!(replace(r"^(\w+?)_(\w+)$", replacement, text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_text_case() {
+        assert_eq!(text_case("foo"), TextCase::Lower);
+        assert_eq!(text_case("Foo"), TextCase::Title);
+        assert_eq!(text_case("F"), TextCase::Title);
+        assert_eq!(text_case("FOO_2"), TextCase::Upper);
+        assert_eq!(text_case("fooBar"), TextCase::Mixed);
+        assert_eq!(text_case("ÉTÉ"), TextCase::Upper);
+        assert_eq!(text_case("42"), TextCase::Mixed);
+    }
+
+    #[test]
+    fn test_preserve_case() {
+        let replacement = Replacement::parse("bar").preserving_case();
+        assert_eq!(replace("(?i)foo", replacement, "foo Foo FOO fOo"), "bar Bar BAR bar");
+        assert_eq!(match_case("foo", "Élan"), "élan");
+        assert_eq!(match_case("FOO", "newName"), "NEWNAME");
+    }
+}
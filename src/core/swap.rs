@@ -0,0 +1,127 @@
+use crate::core::document::Document;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tree_sitter::Point;
+
+/// Start of the first line of a swap file, which holds the cursor
+const HEADER: &str = "viron swap";
+
+/// Unsaved text of a document, written every few seconds and when the editor is
+/// killed, to be recovered the next time the file is opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub cursor: Point,
+    pub text: String,
+}
+
+impl Swap {
+    /// A header line with the cursor, then the text as it is
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let Point { row, column } = self.cursor;
+        let mut bytes = format!("{HEADER} {row} {column}\n").into_bytes();
+        bytes.extend_from_slice(self.text.as_bytes());
+        bytes
+    }
+
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let content = String::from_utf8_lossy(bytes);
+        let (header, text) = content.split_once('\n')?;
+        let mut numbers = header.strip_prefix(HEADER)?.split_whitespace().map(str::parse);
+        let (Some(Ok(row)), Some(Ok(column))) = (numbers.next(), numbers.next()) else {
+            return None;
+        };
+        Some(Self {
+            cursor: Point::new(row, column),
+            text: text.to_string(),
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read swap file: {}", path.display()))?;
+        Self::parse(&bytes).with_context(|| format!("Not a swap file: {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, self.to_bytes())
+            .with_context(|| format!("Failed to write swap file: {}", path.display()))
+    }
+
+    /// Writes the swap file on a blocking thread, so that a large file doesn't hold up
+    /// drawing and typing
+    pub fn write_in_background(self, path: PathBuf) {
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = self.write(&path) {
+                log::warn!("{e:#}");
+            }
+        });
+    }
+}
+
+/// Where the document's swap file goes in the directory, named after a hash of the
+/// file's path, or after the process and buffer for a document without a file
+pub fn swap_path(directory: &Path, document: &Document, index: usize) -> PathBuf {
+    let name = match document.full_file_path() {
+        Some(path) => format!("{:016x}.swp", path_hash(&path)),
+        None => format!("viron-{}-{index}.swp", std::process::id()),
+    };
+    directory.join(name)
+}
+
+/// FNV-1a, which unlike the standard hasher gives the same name in every version
+fn path_hash(path: &Path) -> u64 {
+    path.as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// The swap file of a document just opened when it's newer than the file, as one left
+/// by an editor that didn't exit cleanly. One holding the text of the file already,
+/// which a write that raced a save may leave, is deleted instead
+pub fn newer_swap(directory: &Path, document: &Document, index: usize) -> Option<PathBuf> {
+    let path = swap_path(directory, document, index);
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let swap_time = modified(&path)?;
+    if document.path.as_deref().and_then(modified).is_some_and(|time| time >= swap_time) {
+        return None;
+    }
+    if Swap::read(&path).is_ok_and(|swap| swap.text == document.buffer.to_string()) {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_file() {
+        let swap = Swap {
+            cursor: Point::new(2, 5),
+            text: "one\ntwo\n".to_string(),
+        };
+        assert_eq!(swap.to_bytes(), b"viron swap 2 5\none\ntwo\n");
+        assert_eq!(Swap::parse(&swap.to_bytes()), Some(swap));
+        assert_eq!(Swap::parse(b"one\ntwo\n"), None);
+
+        let directory = Path::new("/swap");
+        let mut document = Document::new();
+        let unnamed = swap_path(directory, &document, 2);
+        assert!(unnamed.to_string_lossy().ends_with("-2.swp"));
+
+        document.set_path(Path::new("/src/main.rs"));
+        let named = swap_path(directory, &document, 0);
+        assert_eq!(named, swap_path(directory, &document, 1));
+        assert_eq!(named.parent(), Some(directory));
+        document.set_path(Path::new("/src/lib.rs"));
+        assert_ne!(swap_path(directory, &document, 0), named);
+    }
+}
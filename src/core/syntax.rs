@@ -1,48 +1,182 @@
 use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
-use tree_sitter::{Parser, Point, Query, QueryCursor, StreamingIterator, Tree};
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use tree_sitter::{Parser, Point, Query, QueryCursor, QueryError, StreamingIterator, Tree};
 
+use crate::config::get_config_dir;
+use crate::core::buffer::Buffer;
 use crate::core::history::edit::Edit;
 use crate::core::language::Language;
 
-#[derive(Debug, Clone)]
-pub struct TokenInfo {
-    pub byte_range: Range<usize>,
-    pub start_position: Point,
-    pub end_position: Point,
-    pub scope: String,
+/// Directory of the config holding `<language>/highlights.scm` overrides
+const QUERY_DIRECTORY: &str = "queries";
+
+thread_local! {
+    static REGISTRY: RefCell<SyntaxRegistry> = RefCell::new(SyntaxRegistry::default());
+    static QUERY_WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
 }
 
-pub struct SyntaxEngine {
+/// Returns the problems found compiling highlight queries since the last call
+pub fn take_query_warnings() -> Vec<String> {
+    QUERY_WARNINGS.with(|warnings| warnings.take())
+}
+
+/// Keeps the parser and query of a language loaded after its last document closes,
+/// trading memory for not compiling the query again when another one opens
+pub fn keep_unused_parsers(keep: bool) {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.keep_unused = keep;
+        if !keep {
+            registry.kept.clear();
+        }
+    });
+}
+
+/// Parser and highlight query of a language, shared by the documents of the language
+struct LanguageSyntax {
     parser: Parser,
     query: Query,
-    tree: Option<Tree>,
 }
 
-impl SyntaxEngine {
-    pub fn new(language: &Language) -> Result<Self> {
+/// Per-language parsers, built when a document of the language first needs one and
+/// dropped with the last document holding it, so grammars of languages never opened
+/// are never loaded
+#[derive(Default)]
+struct SyntaxRegistry {
+    entries: HashMap<Language, Weak<RefCell<LanguageSyntax>>>,
+    /// Entries held on to when `keep_unused` is set
+    kept: HashMap<Language, Rc<RefCell<LanguageSyntax>>>,
+    keep_unused: bool,
+    /// Languages whose query was compiled before, so its problems are reported once
+    compiled: HashSet<Language>,
+    /// Languages without a usable query, which aren't compiled again on every open
+    failed: HashSet<Language>,
+}
+
+impl SyntaxRegistry {
+    fn get(&mut self, language: Language) -> Result<Rc<RefCell<LanguageSyntax>>> {
+        if let Some(entry) = self.entries.get(&language).and_then(Weak::upgrade) {
+            return Ok(entry);
+        }
         let Some(ts_language) = language.get_tree_sitter_language() else {
             return Err(anyhow!(
                 "{} does not have a Tree-sitter language defined",
                 language.to_str()
             ));
         };
-        let Some(query_src) = language.get_highlight_query() else {
-            return Err(anyhow!(
-                "{} does not have a Tree-sitter query defined",
-                language.to_str()
-            ));
+        let unusable = || anyhow!("{} does not have a usable Tree-sitter query", language.to_str());
+        if self.failed.contains(&language) {
+            return Err(unusable());
+        }
+
+        let (query, warnings) = compile_query(language, &get_config_dir());
+        if self.compiled.insert(language) {
+            for warning in &warnings {
+                log::warn!("{warning}");
+            }
+            QUERY_WARNINGS.with(|pending| pending.borrow_mut().extend(warnings));
+        }
+        let Some(query) = query else {
+            self.failed.insert(language);
+            return Err(unusable());
         };
 
         let mut parser = Parser::new();
         parser.set_language(&ts_language)?;
-        let query = Query::new(&ts_language, query_src)?;
+        let entry = Rc::new(RefCell::new(LanguageSyntax { parser, query }));
+        self.entries.insert(language, Rc::downgrade(&entry));
+        if self.keep_unused {
+            self.kept.insert(language, Rc::clone(&entry));
+        }
+        Ok(entry)
+    }
+}
 
-        Ok(Self {
-            parser,
-            query,
-            tree: None,
-        })
+/// Compiles the user's query for the language if there is one, else the bundled one.
+/// A broken user query falls back to the bundled one, and a broken bundled query to
+/// no highlighting, with the reasons returned as warnings
+fn compile_query(language: Language, config_dir: &Path) -> (Option<Query>, Vec<String>) {
+    let Some(ts_language) = language.get_tree_sitter_language() else {
+        return (None, Vec::new());
+    };
+    let name = language.to_str();
+    let mut warnings = Vec::new();
+
+    let path = config_dir.join(QUERY_DIRECTORY).join(name).join("highlights.scm");
+    if let Ok(source) = std::fs::read_to_string(&path) {
+        match Query::new(&ts_language, &source) {
+            Ok(query) => return (Some(query), warnings),
+            Err(e) => warnings.push(format!(
+                "{}: {}, using the bundled query",
+                path.display(),
+                describe_error(&e)
+            )),
+        }
+    }
+
+    let Some(source) = language.get_highlight_query() else {
+        return (None, warnings);
+    };
+    match Query::new(&ts_language, source) {
+        Ok(query) => (Some(query), warnings),
+        Err(e) => {
+            warnings.push(format!(
+                "Highlight query for {name}: {}, showing {name} as plain text",
+                describe_error(&e)
+            ));
+            (None, warnings)
+        }
+    }
+}
+
+fn describe_error(error: &QueryError) -> String {
+    format!(
+        "{} at line {} column {}",
+        error.message.trim_end(),
+        error.row + 1,
+        error.column + 1
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    pub byte_range: Range<usize>,
+    pub start_position: Point,
+    pub end_position: Point,
+    pub scope: String,
+}
+
+/// Tokens of the lines in view, with the text of the document they were found in
+struct Highlights {
+    /// Revision of the document the text is from
+    revision: u64,
+    byte_range: Range<usize>,
+    code: Vec<u8>,
+    tokens: Vec<TokenInfo>,
+}
+
+/// Syntax tree of a document, parsed with the parser shared by its language
+pub struct SyntaxEngine {
+    syntax: Rc<RefCell<LanguageSyntax>>,
+    tree: Option<Tree>,
+    /// Kept between frames, as most only move the cursor
+    highlights: Option<Highlights>,
+    parses: usize,
+}
+
+impl SyntaxEngine {
+    pub fn new(language: &Language) -> Result<Self> {
+        let syntax = REGISTRY.with(|registry| registry.borrow_mut().get(*language))?;
+        Ok(Self { syntax, tree: None, highlights: None, parses: 0 })
+    }
+
+    /// Times the code was parsed
+    pub fn parse_count(&self) -> usize {
+        self.parses
     }
 
     pub fn apply_edit(&mut self, edit: &Edit) -> Result<()> {
@@ -56,24 +190,75 @@ impl SyntaxEngine {
             Edit::Delete(delete) => {
                 tree.edit(&delete.edit_summary());
             }
+            Edit::Group(edits) => {
+                for edit in edits {
+                    self.apply_edit(edit)?;
+                }
+            }
+        };
+        Ok(())
+    }
+
+    /// Parses the code again, reusing the parts of the last tree that the edits passed
+    /// to `apply_edit` since left alone
+    pub fn parse(&mut self, code: &[u8]) {
+        let mut syntax = self.syntax.borrow_mut();
+        self.tree = syntax.parser.parse(code, self.tree.as_ref());
+        self.parses += 1;
+    }
+
+    /// Tokens of the code with a byte in the range, as the lines in view, after
+    /// parsing it again
+    pub fn highlight(&mut self, code: &[u8], byte_range: Range<usize>) -> Result<Vec<TokenInfo>> {
+        self.parse(code);
+        self.query(code, byte_range)
+    }
+
+    /// Finds the tokens of a revision of the buffer in the byte range for `highlights`.
+    /// The buffer is only read and parsed again after an edit made a new revision, and
+    /// the tokens only looked for again after that or a change of range
+    pub fn update(&mut self, buffer: &Buffer, revision: u64, byte_range: Range<usize>) -> Result<()> {
+        let code = match self.highlights.take() {
+            Some(highlights) if highlights.revision == revision && highlights.byte_range == byte_range => {
+                self.highlights = Some(highlights);
+                return Ok(());
+            }
+            Some(highlights) if highlights.revision == revision => highlights.code,
+            _ => {
+                let code = buffer.to_bytes();
+                self.parse(&code);
+                code
+            }
         };
+        let tokens = self.query(&code, byte_range.clone())?;
+        self.highlights = Some(Highlights { revision, byte_range, code, tokens });
         Ok(())
     }
 
-    pub fn highlight(&mut self, code: &[u8]) -> Result<Vec<TokenInfo>> {
+    /// Text and tokens of the last `update`
+    pub fn highlights(&self) -> (&[u8], &[TokenInfo]) {
+        match &self.highlights {
+            Some(highlights) => (&highlights.code, &highlights.tokens),
+            None => (&[], &[]),
+        }
+    }
+
+    fn query(&self, code: &[u8], byte_range: Range<usize>) -> Result<Vec<TokenInfo>> {
         let mut tokens = Vec::new();
-        self.tree = self.parser.parse(code, self.tree.as_ref());
         let Some(tree) = &self.tree else {
             return Ok(tokens);
         };
+        let syntax = self.syntax.borrow();
+        let query = &syntax.query;
 
         let mut cursor = QueryCursor::new();
-        let mut matches = cursor.matches(&self.query, tree.root_node(), code);
+        cursor.set_byte_range(byte_range);
+        let mut matches = cursor.matches(query, tree.root_node(), code);
 
         while let Some(matching) = matches.next() {
             for capture in matching.captures {
                 let node = capture.node;
-                let scope = self.query.capture_names()[capture.index as usize];
+                let scope = query.capture_names()[capture.index as usize];
 
                 tokens.push(TokenInfo {
                     byte_range: node.byte_range(),
@@ -86,3 +271,102 @@ impl SyntaxEngine {
         Ok(tokens)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_broken_query_override() {
+        let config_dir = std::env::temp_dir().join(format!("viron-{}-queries", std::process::id()));
+        let path = config_dir.join("queries/rust/highlights.scm");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "(function_item\n  name: (identifier) @function").unwrap();
+
+        // The bundled query is used instead, with the position of the problem reported
+        let (query, warnings) = compile_query(Language::Rust, &config_dir);
+        assert!(query.is_some());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with(&path.display().to_string()), "{}", warnings[0]);
+        assert!(warnings[0].contains("at line 2"), "{}", warnings[0]);
+
+        std::fs::write(&path, "(function_item name: (identifier) @function)").unwrap();
+        let (query, warnings) = compile_query(Language::Rust, &config_dir);
+        assert_eq!(query.unwrap().capture_names(), ["function"]);
+        assert!(warnings.is_empty());
+        std::fs::remove_dir_all(config_dir).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_parse() {
+        let item = |index: usize| {
+            let body = format!("    value * {index}\n");
+            format!("/// Item {index}\npub fn item_{index}(value: u64) -> u64 {{\n{body}}}\n\n")
+        };
+        let module = |index: usize| format!("mod m{index} {{\n{}}}\n", (0..50).map(item).collect::<String>());
+        let mut code = (0..100).map(module).collect::<String>();
+        let mut engine = SyntaxEngine::new(&Language::Rust).unwrap();
+        engine.parse(code.as_bytes());
+
+        // A line inserted in the middle of the file, between two items
+        let start_byte = code[..code.len() / 2].rfind("\n\n").unwrap() + 1;
+        let row = code[..start_byte].matches('\n').count();
+        let start = Point::new(row, 0);
+        code.insert_str(start_byte, "// note\n");
+        let edit = Edit::insert(start_byte, start, "// note\n".to_string(), start, Point::new(row + 1, 0));
+        engine.apply_edit(&edit).unwrap();
+
+        let started = Instant::now();
+        engine.parse(code.as_bytes());
+        let incremental = started.elapsed();
+        let mut fresh = SyntaxEngine::new(&Language::Rust).unwrap();
+        let started = Instant::now();
+        fresh.parse(code.as_bytes());
+        let full = started.elapsed();
+
+        // Reusing the old tree gives the tokens parsing from scratch does, in far less time
+        let range = start_byte - 100..start_byte + 100;
+        let tokens = |engine: &mut SyntaxEngine| {
+            let tokens = engine.highlight(code.as_bytes(), range.clone()).unwrap();
+            tokens.into_iter().map(|token| (token.byte_range, token.scope)).collect::<Vec<_>>()
+        };
+        let reparsed = tokens(&mut engine);
+        assert!(reparsed.contains(&(start_byte..start_byte + 7, "comment".to_string())));
+        // Only the captures of the items around the range, out of some 70,000
+        assert!(reparsed.len() < 100, "{} tokens", reparsed.len());
+        assert_eq!(reparsed, tokens(&mut fresh));
+        assert!(incremental * 4 < full, "{incremental:?} reparsing, {full:?} from scratch");
+    }
+
+    fn is_loaded(language: Language) -> bool {
+        REGISTRY.with(|registry| {
+            let registry = registry.borrow();
+            registry.entries.get(&language).is_some_and(|entry| entry.strong_count() > 0)
+        })
+    }
+
+    #[test]
+    fn test_parser_shared_by_language() {
+        assert!(!is_loaded(Language::Rust));
+        let mut first = SyntaxEngine::new(&Language::Rust).unwrap();
+        let mut second = SyntaxEngine::new(&Language::Rust).unwrap();
+        assert!(Rc::ptr_eq(&first.syntax, &second.syntax));
+
+        // Each document keeps its own tree
+        assert_eq!(first.highlight(b"fn main() {}", 0..12).unwrap()[0].scope, "keyword");
+        assert_eq!(second.highlight(b"// note", 0..7).unwrap()[0].scope, "comment");
+        assert_eq!(first.highlight(b"fn main() {}", 0..12).unwrap()[0].scope, "keyword");
+
+        drop(first);
+        assert!(is_loaded(Language::Rust));
+        drop(second);
+        assert!(!is_loaded(Language::Rust));
+
+        keep_unused_parsers(true);
+        drop(SyntaxEngine::new(&Language::Rust).unwrap());
+        assert!(is_loaded(Language::Rust));
+        keep_unused_parsers(false);
+        assert!(!is_loaded(Language::Rust));
+    }
+}
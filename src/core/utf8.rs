@@ -38,7 +38,7 @@ impl<'a> Iterator for Utf8CharIterator<'a> {
             (1, first_byte as char)
         } else if first_byte < 0xE0 {
             // 2-byte UTF8
-            if self.byte_pos + 2 >= self.bytes.len() {
+            if self.byte_pos + 2 > self.bytes.len() {
                 return None;
             }
             let bytes = [first_byte, self.bytes[self.byte_pos + 1]];
@@ -48,7 +48,7 @@ impl<'a> Iterator for Utf8CharIterator<'a> {
             }
         } else if first_byte < 0xF0 {
             // 3-byte UTF-8
-            if self.byte_pos + 3 >= self.bytes.len() {
+            if self.byte_pos + 3 > self.bytes.len() {
                 return None;
             }
             let bytes = [
@@ -1,5 +1,4 @@
 use crate::core::buffer::Buffer;
-use crate::core::cursor::Cursor;
 
 /// Viewport manages which part of the buffer is visible on screen
 #[derive(Debug)]
@@ -57,34 +56,94 @@ impl Viewport {
         self.width.saturating_sub(gutter_width)
     }
 
-    /// Scrolls the viewport to ensure the cursor is visible, accounting for gutter
-    pub fn scroll_to_cursor_with_gutter(&mut self, cursor: &Cursor, gutter_width: usize) -> bool {
-        let (row, column) = cursor.get_display_cursor();
+    /// Scrolls the viewport to ensure the cursor at a row and screen column is visible,
+    /// accounting for gutter, with `scrolloff` lines and `sidescrolloff` columns around
+    /// it. The context shrinks at the ends of the file and of the line, and to half the
+    /// view
+    pub fn scroll_to_cursor_with_gutter(
+        &mut self,
+        (row, column): (usize, usize),
+        (line_count, line_width): (usize, usize),
+        gutter_width: usize,
+        (scrolloff, sidescrolloff): (usize, usize),
+    ) -> bool {
         let content_width = self.content_width(gutter_width);
 
         let mut scrolled = false;
 
-        // Scroll vertically if needed (unchanged)
-        if row < self.start_row {
-            self.start_row = row;
+        // Scroll vertically if needed
+        let scrolloff = scrolloff.min(self.height.saturating_sub(1) / 2);
+        let first = row.saturating_sub(scrolloff);
+        let last = (row + scrolloff).min(line_count.saturating_sub(1)).max(row);
+        if first < self.start_row {
+            self.start_row = first;
             scrolled = true;
-        } else if row >= self.start_row + self.height {
-            self.start_row = row - self.height + 1;
+        } else if last >= self.start_row + self.height {
+            self.start_row = last + 1 - self.height.max(1);
             scrolled = true;
         }
 
         // Scroll horizontally if needed (accounting for reduced content width)
-        if column < self.start_column {
-            self.start_column = column;
+        let sidescrolloff = sidescrolloff.min(content_width.saturating_sub(1) / 2);
+        let first = column.saturating_sub(sidescrolloff);
+        let last = (column + sidescrolloff).min(line_width).max(column);
+        if first < self.start_column {
+            self.start_column = first;
             scrolled = true;
-        } else if column >= self.start_column + content_width {
-            self.start_column = column - content_width + 1;
+        } else if last >= self.start_column + content_width {
+            self.start_column = last + 1 - content_width.max(1);
             scrolled = true;
         }
 
         scrolled
     }
 
+    /// Scrolls the viewport to ensure the cursor is visible when lines wrap, where
+    /// `offset` is the screen row of the cursor within its line and `rows` gives the
+    /// screen rows a line takes. `scrolloff` lines around the cursor are kept in view
+    /// when they fit. Wrapped lines never scroll sideways
+    pub fn scroll_to_wrapped_cursor(
+        &mut self,
+        row: usize,
+        offset: usize,
+        scrolloff: usize,
+        rows: impl Fn(usize) -> usize,
+    ) -> bool {
+        let start = (self.start_row, self.start_column);
+        self.start_column = 0;
+
+        let scrolloff = scrolloff.min(self.height.saturating_sub(1) / 2);
+        let below = (row + 1..=row + scrolloff).map(&rows).sum::<usize>();
+        let mut used = match offset + 1 + below <= self.height {
+            true => offset + 1 + below,
+            false => offset + 1,
+        };
+        if row.saturating_sub(scrolloff) < self.start_row {
+            self.start_row = row.saturating_sub(scrolloff);
+        } else {
+            // Lowest top line that still shows the cursor, found from the cursor up
+            let mut top = row;
+            while top > self.start_row {
+                let above = rows(top - 1);
+                if used + above > self.height {
+                    break;
+                }
+                used += above;
+                top -= 1;
+            }
+            self.start_row = top;
+        }
+
+        (self.start_row, self.start_column) != start
+    }
+
+    /// Scrolls sideways to put a screen column that's out of view in the middle
+    pub fn center_on_column(&mut self, column: usize, content_width: usize) {
+        if column < self.start_column || column >= self.start_column + content_width {
+            self.start_column = column.saturating_sub(content_width / 2);
+        }
+    }
+
     /// Scrolls up by the specified number of lines
     pub fn scroll_up(&mut self, lines: usize) {
         self.start_row = self.start_row.saturating_sub(lines);
@@ -96,6 +155,11 @@ impl Viewport {
         self.start_row = (self.start_row + lines).min(max_top);
     }
 
+    /// Moves the viewport along with buffer content that moved by the given number of rows
+    pub fn shift_rows(&mut self, rows: isize) {
+        self.start_row = self.start_row.saturating_add_signed(rows);
+    }
+
     /// Scrolls left by the specified number of columns
     pub fn scroll_left(&mut self, columns: usize) {
         self.start_column = self.start_column.saturating_sub(columns);
@@ -118,3 +182,24 @@ impl Viewport {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrolloff() {
+        // 10 rows of 20 lines, 10 columns of a 30 column line, with 2 of context
+        let mut viewport = Viewport::new(10, 10);
+        let scroll = |viewport: &mut Viewport, row, column| {
+            viewport.scroll_to_cursor_with_gutter((row, column), (20, 30), 0, (2, 2));
+            (viewport.top_line(), viewport.left_column())
+        };
+        assert_eq!(scroll(&mut viewport, 7, 7), (0, 0));
+        assert_eq!(scroll(&mut viewport, 8, 8), (1, 1));
+        assert_eq!(scroll(&mut viewport, 4, 4), (1, 1));
+        assert_eq!(scroll(&mut viewport, 2, 2), (0, 0));
+        // The context shrinks at the last line and the end of the line
+        assert_eq!(scroll(&mut viewport, 19, 30), (10, 21));
+    }
+}
@@ -1,12 +1,19 @@
 use std::path::{Path, PathBuf};
 use anyhow::Result;
 use crate::config::Config;
+use crate::core::language::Language;
 use crate::editor::Editor;
 
 #[derive(Default)]
 pub struct EditorBuilder {
     pub(super) config: Option<Config>,
-    pub(super) file: Option<PathBuf>,
+    /// Files opened in buffers, the first one shown
+    pub(super) files: Vec<PathBuf>,
+    /// Line the first file starts at, counted from 1
+    pub(super) line: Option<usize>,
+    /// Text read from a pipe, opened in a scratch buffer instead of a file
+    pub(super) text: Option<String>,
+    pub(super) language: Option<Language>,
 }
 
 impl EditorBuilder {
@@ -20,7 +27,27 @@ impl EditorBuilder {
     }
 
     pub fn with_file(mut self, file: impl AsRef<Path>) -> Self {
-        self.file = Some(file.as_ref().to_path_buf());
+        self.files.push(file.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_files(self, files: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        files.into_iter().fold(self, Self::with_file)
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    pub fn with_text(mut self, text: String) -> Self {
+        self.text = Some(text);
+        self
+    }
+
+    /// Language of the first buffer, instead of the one guessed from the file name
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
         self
     }
 
@@ -1,10 +1,18 @@
-use crate::constants::{MIN_GUTTER_WIDTH, RESERVED_ROW_COUNT};
+use crate::config::Config;
+use crate::constants::RESERVED_ROW_COUNT;
 use crate::core::buffer_manager::BufferManager;
+use crate::core::completions::Completions;
 use crate::core::cursor::Cursor;
 use crate::core::document::Document;
+use crate::core::hover::HoverText;
 use crate::core::mode::Mode;
+use crate::core::quickfix::QuickfixList;
 use crate::core::register::RegisterSystem;
 use crate::core::viewport::Viewport;
+use crate::service::jobs::Jobs;
+use crate::ui::display::{display_column, display_width, wrapped_position, wrapped_rows};
+use anyhow::Result;
+use std::path::Path;
 
 pub struct EditorCore {
     pub buffer_manager: BufferManager,
@@ -12,6 +20,14 @@ pub struct EditorCore {
     pub cursor: Cursor,
     pub viewport: Viewport,
     pub mode: Mode,
+    pub jobs: Jobs,
+    /// Row `V` was typed on, the other end of the lines selected in visual line mode
+    pub visual_anchor: usize,
+    /// Completion menu open in insert mode
+    pub completion: Option<Completions>,
+    /// Hover popup shown by `K` until the next key
+    pub hover: Option<HoverText>,
+    pub quickfix: QuickfixList,
 }
 
 impl EditorCore {
@@ -22,6 +38,11 @@ impl EditorCore {
             cursor: Cursor::new(),
             viewport: Viewport::new(width, height - RESERVED_ROW_COUNT),
             mode: Mode::Normal,
+            jobs: Jobs::new(),
+            visual_anchor: 0,
+            completion: None,
+            hover: None,
+            quickfix: QuickfixList::default(),
         }
     }
 
@@ -33,20 +54,71 @@ impl EditorCore {
         self.buffer_manager.current_mut()
     }
 
+    /// Keeps the part of the file around the cursor loaded for documents that
+    /// are not fully in memory. Returns true if the buffer content changed
+    pub fn sync_backend(&mut self) -> Result<bool> {
+        let row = self.cursor.get_point().row;
+        let document = self.buffer_manager.current_mut();
+        let Some(shift) = document.backend.ensure_loaded(&mut document.buffer, row)? else {
+            return Ok(false);
+        };
+
+        let buffer = &document.buffer;
+        self.cursor
+            .go_to_line(row.saturating_add_signed(shift), buffer, &self.mode);
+        self.viewport.shift_rows(shift);
+        Ok(true)
+    }
+
+    /// Writes the swap files of the buffers changed since their last write, in the
+    /// background. The cursor is kept for the current buffer only
+    pub fn write_swaps(&mut self, directory: &Path) {
+        let current = self.buffer_manager.current_index();
+        let cursor = self.cursor.get_point();
+        for (index, document) in self.buffer_manager.documents_mut().enumerate() {
+            let cursor = if index == current { cursor } else { Default::default() };
+            if let Some((path, swap)) = document.pending_swap(directory, index, cursor) {
+                swap.write_in_background(path);
+            }
+        }
+    }
+
+    /// Deletes the swap files of all buffers, as on a clean exit
+    pub fn remove_swaps(&mut self) {
+        for document in self.buffer_manager.documents_mut() {
+            document.remove_swap();
+        }
+    }
+
     pub fn resize_viewport(&mut self, width: usize, height: usize) {
-        self.viewport.resize(width, height - RESERVED_ROW_COUNT);
+        self.viewport.resize(width, self.quickfix.editor_height(height));
     }
 
-    pub fn scroll_viewport(&mut self, has_gutter: bool) -> bool {
-        let line_count = self.current_document().buffer.line_count();
-        let gutter_width = if has_gutter {
-            0
-        } else {
-            (line_count.to_string().len() + 1).max(MIN_GUTTER_WIDTH)
-        };
-        self
-            .viewport
-            .scroll_to_cursor_with_gutter(&self.cursor, gutter_width)
+    pub fn scroll_viewport(&mut self, config: &Config) -> bool {
+        let document = self.buffer_manager.current();
+        let line_count = document.buffer.line_count();
+        let gutter_width = config.gutter_width(document);
+        let layout = config.layout();
+        let (row, column) = self.cursor.get_display_cursor();
+        let line = document.buffer.get_line_as_string(row);
+        let column = display_column(&line, column, layout);
+        if config.wrap {
+            let width = self.viewport.content_width(gutter_width);
+            // Lines past the end take no rows, so the context shrinks at the end
+            let rows = |row| match row < line_count {
+                true => wrapped_rows(&document.buffer.get_line_as_string(row), width, layout),
+                false => 0,
+            };
+            let (offset, _) = wrapped_position(column, width, rows(row));
+            return self.viewport.scroll_to_wrapped_cursor(row, offset, config.scrolloff, rows);
+        }
+        let line_width = display_width(line.trim_end_matches('\n'), layout);
+        self.viewport.scroll_to_cursor_with_gutter(
+            (row, column),
+            (line_count, line_width),
+            gutter_width,
+            (config.scrolloff, config.sidescrolloff),
+        )
     }
 }
 
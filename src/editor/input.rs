@@ -21,7 +21,7 @@ impl InputSystem {
 
     pub fn clear_all(&mut self) {
         self.command_buffer.clear();
-        self.search_buffer.buffer.clear();
+        self.search_buffer.clear_input();
         self.input_state.clear();
     }
 
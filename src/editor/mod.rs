@@ -2,32 +2,46 @@ mod builder;
 mod core;
 mod input;
 mod terminal;
+#[cfg(any(test, feature = "bench"))]
+// The benchmarks drive only part of it
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) mod testing;
 mod ui;
 
 pub use builder::EditorBuilder;
+pub use terminal::restore as restore_terminal;
 
 use crate::actions::context::{ActionContext, EditorContext, InputContext, UIContext};
 use crate::actions::core::Executable;
-use crate::actions::{buffer, mode};
+use crate::actions::{buffer, lsp, mode, movement, system};
 use crate::config::Config;
-use crate::config::editor::Gutter;
-use crate::constants::components::{EDITOR_VIEW, PENDING_KEYS, STATUS_LINE};
-use crate::core::message::MessageManager;
+use crate::constants::components::{BOTTOM_BAR, EDITOR_VIEW, JOBS, STATUS_LINE};
+use crate::core::buffer::Buffer;
+use crate::core::message::{Message, MessageManager};
 use crate::core::mode::Mode;
+use crate::core::syntax;
 use crate::editor::core::EditorCore;
 use crate::editor::input::InputSystem;
 use crate::editor::terminal::TerminalContext;
 use crate::editor::ui::UISystem;
-use crate::input::{events::InputEvent, get_default_input_action};
-use crate::service::LspService;
+use crate::input::events::{EventLoop, InputEvent};
+use crate::input::{
+    get_completion_action, get_default_input_action, get_jobs_panel_action, get_pager_action,
+    get_paste_action, get_recovery_action,
+};
+use crate::service::{LspService, SymbolIndex};
+use crate::ui::components::line_at_row;
 use crate::ui::context::{
     DiagnosticRenderContext, EditorRenderContext, InputRenderContext, RenderContext,
 };
 use anyhow::Result;
+use async_trait::async_trait;
 use crossterm::QueueableCommand;
 use crossterm::cursor::SetCursorStyle;
-use crossterm::{cursor, event::KeyEvent};
+use crossterm::cursor;
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use std::io::Write;
+use std::time::{Duration, Instant};
 
 pub struct Editor {
     core: EditorCore,
@@ -38,16 +52,26 @@ pub struct Editor {
     message_manager: MessageManager,
     config: Config,
     lsp_service: LspService,
+    symbol_index: SymbolIndex,
     running: bool,
+    emergency_exit: Option<EmergencyExit>,
+    /// When swap files were last written
+    last_swap: Instant,
+}
+
+/// Set when a signal terminates the editor, with where the unsaved text went
+pub struct EmergencyExit {
+    pub exit_code: i32,
+    pub report: Vec<String>,
 }
 
 impl Editor {
     pub async fn from_builder(builder: EditorBuilder) -> Result<Self> {
-        let terminal = TerminalContext::new()?;
+        let config = builder.config.unwrap_or_default();
+        let terminal = TerminalContext::new(config.mouse)?;
         let core = EditorCore::new(terminal.width, terminal.height);
         let input = InputSystem::new();
         let ui = UISystem::new(terminal.width, terminal.height)?;
-        let config = builder.config.unwrap_or_default();
 
         let mut editor = Self {
             terminal,
@@ -57,15 +81,59 @@ impl Editor {
             message_manager: MessageManager::new(),
             config,
             lsp_service: LspService::new(),
+            symbol_index: SymbolIndex::new(),
             running: true,
+            emergency_exit: None,
+            last_swap: Instant::now(),
         };
 
-        if let Some(file) = builder.file {
-            let action = buffer::OpenBuffer::new(file);
-            editor.execute_action(&action).await?;
+        syntax::keep_unused_parsers(editor.config.keep_parsers);
+
+        for warning in &editor.config.warnings {
+            log::warn!("{warning}");
+        }
+        if let Some(warning) = editor.config.warnings.first() {
+            let more = editor.config.warnings.len() - 1;
+            let content = match more {
+                0 => warning.clone(),
+                _ => format!("{warning} (and {more} more)"),
+            };
+            editor
+                .execute_action(&system::ShowMessage(Message::info(content)))
+                .await?;
+        }
+
+        // Definitions for languages without a language server
+        if let Ok(root) = std::env::current_dir() {
+            let job = editor.core.jobs.start("Indexing symbols".to_string());
+            editor.symbol_index.start(root, job);
+        }
+
+        if let Some(first) = builder.files.first() {
+            for file in &builder.files {
+                editor.execute_action(&buffer::OpenBuffer::new(file.clone())).await?;
+            }
+            // Already open, so this goes back to it
+            if builder.files.len() > 1 {
+                editor.execute_action(&buffer::OpenBuffer::new(first.clone())).await?;
+            }
+        } else if let Some(text) = builder.text {
+            // Without a path it can be saved with :w {path}, and closed without saving
+            editor.core.buffer_manager.new_buffer();
+            let document = editor.core.buffer_manager.current_mut();
+            document.buffer = Buffer::from_string(&text);
+            document.scratch = true;
         } else {
             editor.core.buffer_manager.new_buffer();
         }
+        if let Some(language) = builder.language {
+            let action = buffer::SetLanguage::new(language);
+            editor.execute_action(&action).await?;
+        }
+        if let Some(line) = builder.line {
+            let action = movement::SeekToLine::new(line.saturating_sub(1));
+            editor.execute_action(&action).await?;
+        }
 
         Ok(editor)
     }
@@ -73,31 +141,66 @@ impl Editor {
     pub async fn run(&mut self) -> Result<()> {
         // Main event loop
         while self.running {
-            // Handle events
-            self.render()?;
-            match self.input.event_handler.next().await? {
-                InputEvent::Key(key) => {
-                    if let Some(action) = self.handle_key(key)? {
-                        self.execute_action(action.as_ref()).await?;
-                        if self.input.input_state.is_empty()
-                            && matches!(self.core.mode, Mode::OperationPending(_))
-                        {
-                            self.execute_action(&mode::EnterMode::new(Mode::Normal))
-                                .await?;
-                        }
+            self.step().await?;
+        }
+
+        // Killed, the swap files hold what the editor couldn't save
+        if self.emergency_exit.is_none() {
+            self.core.remove_swaps();
+        }
+        Ok(())
+    }
+
+    async fn handle_event(&mut self, event: InputEvent) -> Result<()> {
+        match event {
+            InputEvent::Key(key) => {
+                if let Some(action) = self.handle_key(key)? {
+                    self.execute_action(action.as_ref()).await?;
+                    if self.input.input_state.is_empty()
+                        && matches!(self.core.mode, Mode::OperationPending(_))
+                    {
+                        self.execute_action(&mode::EnterMode::new(Mode::Normal))
+                            .await?;
                     }
+                    self.execute_action(&lsp::RefilterCompletion).await?;
+                    self.execute_action(&lsp::CloseHover).await?;
+                    self.execute_action(&system::ShowQueuedMessages).await?;
                 }
-                InputEvent::Resize(width, height) => {
-                    self.handle_resize(width as usize, height as usize)?;
-                }
-                InputEvent::Tick => {
-                    self.handle_tick().await?;
+            }
+            InputEvent::Paste(text) => {
+                if let Some(action) = self.handle_paste(&text) {
+                    self.execute_action(action.as_ref()).await?;
+                    self.execute_action(&lsp::RefilterCompletion).await?;
+                    self.execute_action(&lsp::CloseHover).await?;
+                    self.execute_action(&system::ShowQueuedMessages).await?;
                 }
-                _ => {}
             }
+            InputEvent::Mouse(mouse_event) => {
+                self.handle_mouse(mouse_event).await?;
+            }
+            InputEvent::Resize(width, height) => {
+                self.handle_resize(width as usize, height as usize)?;
+            }
+            InputEvent::Tick => {
+                self.handle_tick().await?;
+            }
+            InputEvent::Terminate { exit_code } => {
+                self.handle_terminate(exit_code);
+            }
+            InputEvent::Suspend => {
+                self.handle_suspend()?;
+            }
+            InputEvent::Resume => {
+                self.redraw()?;
+            }
+            _ => {}
         }
-
-        Ok(())
+        // Quitting may have closed the last buffer, leaving nothing to view
+        if !self.running {
+            return Ok(());
+        }
+        // Keys later in the batch may depend on the viewport, e.g. zz
+        self.update_view()
     }
 
     async fn execute_action(&mut self, action: &dyn Executable) -> Result<()> {
@@ -107,6 +210,11 @@ impl Editor {
             mode: &mut self.core.mode,
             buffer_manager: &mut self.core.buffer_manager,
             register_system: &mut self.core.register_system,
+            jobs: &mut self.core.jobs,
+            visual_anchor: &mut self.core.visual_anchor,
+            completion: &mut self.core.completion,
+            hover: &mut self.core.hover,
+            quickfix: &mut self.core.quickfix,
         };
 
         let ui_ctx = UIContext {
@@ -127,12 +235,22 @@ impl Editor {
             config: &self.config,
             running: &mut self.running,
             lsp_service: &mut self.lsp_service,
+            symbol_index: &mut self.symbol_index,
         };
         action.execute(&mut context).await
     }
 
+    /// Loads the text around the cursor and scrolls the viewport to it
+    fn update_view(&mut self) -> Result<()> {
+        if self.core.sync_backend()? {
+            self.ui.compositor.mark_all_dirty();
+        }
+        self.scroll_viewport()
+    }
+
     fn render(&mut self) -> Result<()> {
-        self.scroll_viewport()?;
+        self.fit_to_terminal()?;
+        self.update_view()?;
 
         let document = self.core.buffer_manager.current_mut();
         let uri = document.get_uri().unwrap_or_default();
@@ -142,6 +260,11 @@ impl Editor {
             document,
             cursor: &self.core.cursor,
             mode: &self.core.mode,
+            jobs: &self.core.jobs,
+            visual_anchor: self.core.visual_anchor,
+            completion: self.core.completion.as_ref(),
+            hover: self.core.hover.as_ref(),
+            quickfix: &self.core.quickfix,
         };
 
         let input = InputRenderContext {
@@ -152,6 +275,7 @@ impl Editor {
 
         let diagnostics = DiagnosticRenderContext {
             diagnostics: self.lsp_service.get_diagnostics(&uri),
+            generation: self.lsp_service.diagnostics_generation(),
             message_manager: &self.message_manager,
         };
 
@@ -184,13 +308,27 @@ impl Editor {
     fn scroll_viewport(&mut self) -> Result<()> {
         if self
             .core
-            .scroll_viewport(self.config.gutter == Gutter::None)
+            .scroll_viewport(&self.config)
+            // The selection follows the cursor, which moving doesn't redraw
+            || self.core.mode == Mode::VisualLine
         {
             self.ui.mark_dirty([STATUS_LINE, EDITOR_VIEW])?;
         }
         Ok(())
     }
 
+    /// Resizes before drawing when the terminal changed size without its resize event
+    /// handled yet, as with fast successive resizes, rather than drawing a frame that
+    /// wraps rows and puts the status line mid-screen
+    fn fit_to_terminal(&mut self) -> Result<()> {
+        let (width, height) = crossterm::terminal::size()?;
+        let size = (width as usize, height as usize);
+        if fit_to_size(&mut self.ui, &mut self.core, size) {
+            self.terminal.resize(size.0, size.1)?;
+        }
+        Ok(())
+    }
+
     fn handle_resize(&mut self, width: usize, height: usize) -> Result<()> {
         self.terminal.resize(width, height)?;
         self.ui.resize(width, height);
@@ -198,7 +336,82 @@ impl Editor {
         Ok(())
     }
 
+    /// Clicking or dragging on the scrollbar jumps to that part of the file
+    async fn handle_mouse(&mut self, mouse_event: MouseEvent) -> Result<()> {
+        let (MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)) =
+            mouse_event.kind
+        else {
+            return Ok(());
+        };
+        let height = self.core.viewport.height();
+        let row = mouse_event.row as usize;
+        if !self.config.scrollbar
+            || mouse_event.column as usize + 1 != self.terminal.width
+            || row >= height
+        {
+            return Ok(());
+        }
+        let line_count = self.core.current_document().buffer.line_count();
+        let line = line_at_row(row, height, line_count);
+        self.execute_action(&movement::GoToLine::new(line)).await
+    }
+
+    /// Saves the modified buffers to swap files and stops, the terminal may be gone already
+    fn handle_terminate(&mut self, exit_code: i32) {
+        let current = self.core.buffer_manager.current_index();
+        let cursor = self.core.cursor.get_point();
+        let directory = self.config.swap_directory.clone().unwrap_or_else(std::env::temp_dir);
+        let report = self
+            .core
+            .buffer_manager
+            .documents()
+            .enumerate()
+            .filter(|(_, document)| document.is_modified())
+            .map(|(index, document)| {
+                let cursor = if index == current { cursor } else { Default::default() };
+                document.write_swap(&directory, index, cursor)
+            })
+            .map(|written| match written {
+                Ok(path) => format!("Unsaved changes written to {}", path.display()),
+                Err(e) => format!("{e:#}"),
+            })
+            .collect::<Vec<_>>();
+        for line in &report {
+            log::warn!("{line}");
+        }
+        self.emergency_exit = Some(EmergencyExit { exit_code, report });
+        self.running = false;
+    }
+
+    fn handle_suspend(&mut self) -> Result<()> {
+        self.terminal.suspend()?;
+        // Stops the process until the shell continues it with SIGCONT
+        unsafe { libc::raise(libc::SIGSTOP) };
+        self.terminal.resume()?;
+        self.redraw()
+    }
+
+    /// Draws everything again, the terminal may have been used or resized meanwhile
+    fn redraw(&mut self) -> Result<()> {
+        let (width, height) = crossterm::terminal::size()?;
+        self.handle_resize(width as usize, height as usize)
+    }
+
     fn handle_key(&mut self, key_event: KeyEvent) -> Result<Option<Box<dyn Executable>>> {
+        if self.message_manager.output().is_some() {
+            return Ok(Some(get_pager_action(&key_event)));
+        }
+        if self.message_manager.recovery().is_some() {
+            return Ok(Some(get_recovery_action(&key_event)));
+        }
+        if self.core.jobs.selected.is_some() {
+            return Ok(Some(get_jobs_panel_action(&key_event)));
+        }
+        if self.core.completion.is_some()
+            && let Some(action) = get_completion_action(&key_event)
+        {
+            return Ok(Some(action));
+        }
         let default_action = get_default_input_action(&key_event, &self.core.mode);
 
         if default_action.is_some() {
@@ -206,31 +419,59 @@ impl Editor {
         };
 
         self.input.input_state.add_key(key_event);
-        self.ui.compositor.mark_visible(PENDING_KEYS, true)?;
-        self.ui.compositor.mark_dirty(PENDING_KEYS)?;
+        self.ui.mark_dirty([BOTTOM_BAR, STATUS_LINE])?;
 
         let action = self
             .input
             .input_state
             .get_executable(&self.core.mode, &self.config.keymap);
-        if self.input.input_state.is_empty() {
-            self.ui.compositor.mark_visible(PENDING_KEYS, false)?;
-        }
         Ok(action)
     }
 
+    /// What pasted text does, nothing while a prompt or panel takes the keys
+    fn handle_paste(&self, text: &str) -> Option<Box<dyn Executable>> {
+        if self.message_manager.output().is_some()
+            || self.message_manager.recovery().is_some()
+            || self.core.jobs.selected.is_some()
+        {
+            return None;
+        }
+        get_paste_action(text, &self.core.mode)
+    }
+
     fn get_cursor_style(&self) -> SetCursorStyle {
         if !self.input.input_state.is_empty() {
             return SetCursorStyle::SteadyUnderScore;
         }
         match self.core.mode {
-            Mode::Normal => SetCursorStyle::DefaultUserShape,
+            Mode::Normal | Mode::VisualLine => SetCursorStyle::DefaultUserShape,
             Mode::Insert | Mode::Command | Mode::Search => SetCursorStyle::BlinkingBar,
             Mode::OperationPending(_) => SetCursorStyle::SteadyUnderScore,
         }
     }
 
     async fn handle_tick(&mut self) -> Result<()> {
+        for action in self.core.jobs.take_completions() {
+            self.execute_action(action.as_ref()).await?;
+        }
+        // Elapsed times and outcomes change without any key
+        if self.core.jobs.selected.is_some() {
+            self.ui.compositor.mark_dirty(JOBS)?;
+        }
+        let interval = Duration::from_secs(self.config.swap_interval.0);
+        if let Some(directory) = &self.config.swap_directory
+            && !interval.is_zero()
+            && self.last_swap.elapsed() >= interval
+        {
+            self.core.write_swaps(directory);
+            self.last_swap = Instant::now();
+        }
+        let document = self.core.buffer_manager.current_mut();
+        if document.take_disk_change() {
+            let message = format!("{} changed on disk, :e! reloads it", document.display_name());
+            self.execute_action(&system::ShowMessage(Message::info(message))).await?;
+        }
+
         let Some(client) = self.lsp_service.get_client_mut() else {
             return Ok(());
         };
@@ -240,6 +481,10 @@ impl Editor {
         Ok(())
     }
 
+    pub fn take_emergency_exit(&mut self) -> Option<EmergencyExit> {
+        self.emergency_exit.take()
+    }
+
     pub async fn cleanup(mut self) -> Result<()> {
         // Restore terminal state
         self.terminal.cleanup()?;
@@ -247,3 +492,38 @@ impl Editor {
         Ok(())
     }
 }
+
+/// Lays the screen out again at the terminal's size when it was drawn at another one,
+/// returning whether it did
+fn fit_to_size(ui: &mut UISystem, core: &mut EditorCore, size: (usize, usize)) -> bool {
+    if ui.size() == size {
+        return false;
+    }
+    log::warn!("Drawing at {:?} in a terminal of {size:?}, resizing first", ui.size());
+    ui.resize(size.0, size.1);
+    core.resize_viewport(size.0, size.1);
+    true
+}
+
+#[async_trait(?Send)]
+impl EventLoop for Editor {
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn render_frame(&mut self) -> Result<()> {
+        self.render()
+    }
+
+    async fn next_event(&mut self) -> Result<InputEvent> {
+        self.input.event_handler.next().await
+    }
+
+    fn try_next_event(&mut self) -> Result<Option<InputEvent>> {
+        self.input.event_handler.try_next()
+    }
+
+    async fn handle_event(&mut self, event: InputEvent) -> Result<()> {
+        Editor::handle_event(self, event).await
+    }
+}
@@ -1,29 +1,31 @@
 use std::io;
 use anyhow::Result;
-use crossterm::{ExecutableCommand, cursor, style, terminal};
-use std::io::Stdout;
+use crossterm::{ExecutableCommand, cursor, event, style, terminal};
+use std::io::{Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set while the terminal is in raw mode on the alternate screen
+static ENTERED: AtomicBool = AtomicBool::new(false);
+/// Whether the mouse was captured on entering, to release it on leaving
+static MOUSE: AtomicBool = AtomicBool::new(false);
 
 pub struct TerminalContext {
     pub width: usize,
     pub height: usize,
     pub stdout: Stdout,
+    guard: TerminalGuard,
 }
 
 impl TerminalContext {
-    pub fn new() -> Result<Self> {
-        terminal::enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        stdout
-            .execute(terminal::EnterAlternateScreen)?
-            .execute(cursor::Hide)?
-            .execute(terminal::Clear(terminal::ClearType::All))?;
-
+    pub fn new(mouse: bool) -> Result<Self> {
+        let guard = TerminalGuard::enter(mouse)?;
         let (width, height) = terminal::size()?;
 
         Ok(Self {
             width: width as usize,
             height: height as usize,
-            stdout,
+            stdout: io::stdout(),
+            guard,
         })
     }
 
@@ -35,13 +37,117 @@ impl TerminalContext {
         Ok(())
     }
 
-    pub fn cleanup(mut self) -> Result<()> {
-        self.stdout
-            .execute(style::ResetColor)?
-            .execute(cursor::Show)?
-            .execute(cursor::SetCursorStyle::DefaultUserShape)?
-            .execute(terminal::LeaveAlternateScreen)?;
-        terminal::disable_raw_mode()?;
-        Ok(())
+    /// Hands the terminal back to the shell while the process is stopped
+    pub fn suspend(&mut self) -> Result<()> {
+        restore()
+    }
+
+    pub fn resume(&mut self) -> Result<()> {
+        self.guard.reenter()
+    }
+
+    pub fn cleanup(self) -> Result<()> {
+        self.guard.release()
+    }
+}
+
+/// Holds the terminal in raw mode on the alternate screen, and puts it back the way
+/// the shell had it when dropped, so every way out of the editor restores it. Leaving
+/// normally goes through `release`, which reports the error instead of logging it
+pub struct TerminalGuard {
+    mouse: bool,
+}
+
+impl TerminalGuard {
+    pub fn enter(mouse: bool) -> Result<Self> {
+        // Created first, so a failure halfway through entering is undone too
+        let guard = Self { mouse };
+        guard.reenter()?;
+        Ok(guard)
+    }
+
+    fn reenter(&self) -> Result<()> {
+        MOUSE.store(self.mouse, Ordering::SeqCst);
+        ENTERED.store(true, Ordering::SeqCst);
+        enter(&mut io::stdout(), self.mouse)
+    }
+
+    pub fn release(self) -> Result<()> {
+        restore()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Err(e) = restore() {
+            log::error!("Error restoring the terminal: {e}");
+        }
+    }
+}
+
+/// Leaves raw mode and the alternate screen. Only the first call after entering does
+/// anything, so the guard, the panic hook and suspending can all call it
+pub fn restore() -> Result<()> {
+    restore_to(&mut io::stdout())
+}
+
+fn restore_to(writer: &mut impl Write) -> Result<()> {
+    if !ENTERED.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    leave(writer, MOUSE.load(Ordering::SeqCst))
+}
+
+fn enter(stdout: &mut Stdout, mouse: bool) -> Result<()> {
+    terminal::enable_raw_mode()?;
+    stdout
+        .execute(terminal::EnterAlternateScreen)?
+        .execute(cursor::Hide)?
+        .execute(event::EnableBracketedPaste)?
+        .execute(terminal::Clear(terminal::ClearType::All))?;
+    if mouse {
+        stdout.execute(event::EnableMouseCapture)?;
+    }
+    Ok(())
+}
+
+/// Every step is tried even when one before it fails, and the first error is returned
+fn leave(writer: &mut impl Write, mouse: bool) -> Result<()> {
+    let mouse = match mouse {
+        true => writer.execute(event::DisableMouseCapture).map(drop),
+        false => Ok(()),
+    };
+    let results = [
+        mouse,
+        writer.execute(event::DisableBracketedPaste).map(drop),
+        writer.execute(style::ResetColor).map(drop),
+        writer.execute(cursor::Show).map(drop),
+        writer.execute(cursor::SetCursorStyle::DefaultUserShape).map(drop),
+        writer.execute(terminal::LeaveAlternateScreen).map(drop),
+        terminal::disable_raw_mode(),
+    ];
+    Ok(results.into_iter().collect::<io::Result<()>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_is_idempotent() {
+        let mut written = Vec::new();
+        restore_to(&mut written).unwrap();
+        assert!(written.is_empty());
+
+        ENTERED.store(true, Ordering::SeqCst);
+        restore_to(&mut written).unwrap();
+        let restored = String::from_utf8_lossy(&written).into_owned();
+        assert!(restored.ends_with("\x1b[?1049l"), "{restored:?}");
+
+        // Later calls, as from the panic hook after the guard was dropped, do nothing
+        written.clear();
+        restore_to(&mut written).unwrap();
+        restore_to(&mut written).unwrap();
+        assert!(written.is_empty());
     }
 }
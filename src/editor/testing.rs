@@ -0,0 +1,351 @@
+use crate::actions::context::{ActionContext, EditorContext, InputContext, UIContext};
+use crate::actions::core::Executable;
+use crate::actions::{lsp, mode, system};
+use crate::config::Config;
+use crate::constants::components::{EDITOR_VIEW, STATUS_LINE};
+use crate::core::buffer::Buffer;
+use crate::core::command::{CommandBuffer, SearchBuffer};
+use crate::core::message::MessageManager;
+use crate::core::mode::Mode;
+use crate::editor::core::EditorCore;
+use crate::editor::ui::UISystem;
+use crate::input::events::{EventLoop, InputEvent};
+use crate::input::default_keymap::default_keymap;
+use crate::input::keys::decode_keys;
+use crate::input::{
+    InputProcessor, get_completion_action, get_default_input_action, get_jobs_panel_action,
+    get_pager_action, get_paste_action, get_recovery_action,
+};
+use crate::service::{LspService, SymbolIndex};
+use crate::ui::context::{
+    DiagnosticRenderContext, EditorRenderContext, InputRenderContext, RenderContext,
+};
+use crate::ui::render_buffer::RenderBuffer;
+use anyhow::Result;
+use async_trait::async_trait;
+use lsp_types::Diagnostic;
+use std::collections::VecDeque;
+use crossterm::event::KeyEvent;
+
+const WIDTH: usize = 80;
+const HEIGHT: usize = 24;
+
+/// Headless editor used by tests to drive actions and key sequences
+/// without a terminal, using the default keymap.
+pub(crate) struct TestEditor {
+    pub core: EditorCore,
+    pub ui: UISystem,
+    pub command_buffer: CommandBuffer,
+    pub search_buffer: SearchBuffer,
+    pub input_state: InputProcessor,
+    pub message_manager: MessageManager,
+    pub config: Config,
+    pub lsp_service: LspService,
+    pub symbol_index: SymbolIndex,
+    pub running: bool,
+    /// Size of the pretend terminal, which `render` fits the screen to
+    terminal_size: (usize, usize),
+    /// Times the screen was laid out at a new size
+    pub relayouts: usize,
+}
+
+/// Screen drawn by `TestEditor::render`
+pub(crate) struct Frame {
+    pub buffer: RenderBuffer,
+    /// Row and column of the terminal cursor
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl TestEditor {
+    pub fn new(content: &str) -> Self {
+        let config = Config {
+            keymap: default_keymap().clone(),
+            ..Default::default()
+        };
+        Self::with_config(content, config)
+    }
+
+    pub fn with_config(content: &str, config: Config) -> Self {
+        let mut core = EditorCore::new(WIDTH, HEIGHT);
+        core.buffer_manager.new_buffer();
+        core.current_document_mut().buffer = Buffer::from_string(content);
+
+        // Tests never start language servers
+        let mut lsp_service = LspService::new();
+        lsp_service.set_enabled(false);
+
+        Self {
+            core,
+            ui: UISystem::new(WIDTH, HEIGHT).expect("Failed to create UI"),
+            command_buffer: CommandBuffer::new(),
+            search_buffer: SearchBuffer::new(),
+            input_state: InputProcessor::new(),
+            message_manager: MessageManager::new(),
+            config,
+            lsp_service,
+            symbol_index: SymbolIndex::new(),
+            running: true,
+            terminal_size: (WIDTH, HEIGHT),
+            relayouts: 0,
+        }
+    }
+
+    /// Stores diagnostics for the current document as if a language server had sent them
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        let uri = self.core.current_document().get_uri().unwrap_or_default();
+        self.lsp_service.update_diagnostics(&uri, diagnostics);
+        self.ui.mark_all_dirty();
+    }
+
+    /// Resizes the screen as a terminal resize event does
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.terminal_size = (width, height);
+        self.ui.resize(width, height);
+        self.core.resize_viewport(width, height);
+        self.relayouts += 1;
+    }
+
+    /// Changes the size of the terminal without a resize event, as when it's late
+    pub fn set_terminal_size(&mut self, width: usize, height: usize) {
+        self.terminal_size = (width, height);
+    }
+
+    /// Draws a frame the way the main loop does, scrolling the viewport to the cursor first
+    pub fn render(&mut self) -> Result<Frame> {
+        if super::fit_to_size(&mut self.ui, &mut self.core, self.terminal_size) {
+            self.relayouts += 1;
+        }
+        if self.core.sync_backend()? {
+            self.ui.mark_all_dirty();
+        }
+        if self
+            .core
+            .scroll_viewport(&self.config)
+            // The selection follows the cursor, which moving doesn't redraw
+            || self.core.mode == Mode::VisualLine
+        {
+            self.ui.mark_dirty([STATUS_LINE, EDITOR_VIEW])?;
+        }
+
+        let document = self.core.buffer_manager.current_mut();
+        let uri = document.get_uri().unwrap_or_default();
+        let mut context = RenderContext {
+            editor: EditorRenderContext {
+                viewport: &self.core.viewport,
+                document,
+                cursor: &self.core.cursor,
+                mode: &self.core.mode,
+                jobs: &self.core.jobs,
+                visual_anchor: self.core.visual_anchor,
+                completion: self.core.completion.as_ref(),
+                hover: self.core.hover.as_ref(),
+                quickfix: &self.core.quickfix,
+            },
+            input: InputRenderContext {
+                command_buffer: &self.command_buffer,
+                search_buffer: &self.search_buffer,
+                input_state: &self.input_state,
+            },
+            config: &self.config,
+            diagnostics: DiagnosticRenderContext {
+                diagnostics: self.lsp_service.get_diagnostics(&uri),
+                generation: self.lsp_service.diagnostics_generation(),
+                message_manager: &self.message_manager,
+            },
+        };
+        self.ui.compositor.render(&mut context, &mut std::io::sink())?;
+        Ok(Frame {
+            buffer: self.ui.compositor.current_buffer().clone(),
+            cursor: self.ui.compositor.get_cursor_position(&context),
+        })
+    }
+
+    pub async fn execute(&mut self, action: &dyn Executable) -> Result<()> {
+        let mut context = ActionContext {
+            editor: EditorContext {
+                cursor: &mut self.core.cursor,
+                viewport: &mut self.core.viewport,
+                mode: &mut self.core.mode,
+                buffer_manager: &mut self.core.buffer_manager,
+                register_system: &mut self.core.register_system,
+                jobs: &mut self.core.jobs,
+                visual_anchor: &mut self.core.visual_anchor,
+                completion: &mut self.core.completion,
+                hover: &mut self.core.hover,
+                quickfix: &mut self.core.quickfix,
+            },
+            ui: UIContext {
+                compositor: &mut self.ui.compositor,
+            },
+            input: InputContext {
+                command_buffer: &mut self.command_buffer,
+                search_buffer: &mut self.search_buffer,
+                input_state: &mut self.input_state,
+            },
+            message: &mut self.message_manager,
+            config: &self.config,
+            running: &mut self.running,
+            lsp_service: &mut self.lsp_service,
+            symbol_index: &mut self.symbol_index,
+        };
+        action.execute(&mut context).await
+    }
+
+    /// Waits for the background jobs to finish, then runs what they left for the main
+    /// loop as its ticks would
+    pub async fn wait_for_jobs(&mut self) -> Result<()> {
+        while self.core.jobs.is_running() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        for action in self.core.jobs.take_completions() {
+            self.execute(action.as_ref()).await?;
+        }
+        Ok(())
+    }
+
+    /// Feeds keys written in keymap notation, e.g. `A<Esc>dd`
+    pub async fn keys(&mut self, keys: &str) -> Result<()> {
+        for key in decode_keys(keys) {
+            self.press(key).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs the main loop over keys that are all queued at once, returning the number
+    /// of frames it rendered while handling them
+    pub async fn keys_queued(&mut self, keys: &str) -> Result<usize> {
+        let mut input = QueuedInput {
+            queue: decode_keys(keys).into_iter().map(InputEvent::Key).collect(),
+            editor: self,
+            renders: 0,
+        };
+        while !input.queue.is_empty() && input.editor.running {
+            input.step().await?;
+        }
+        Ok(input.renders)
+    }
+
+    /// Pastes the text at once, as a terminal with bracketed paste sends it
+    pub async fn paste(&mut self, text: &str) -> Result<()> {
+        let prompt = self.message_manager.output().is_some()
+            || self.message_manager.recovery().is_some()
+            || self.core.jobs.selected.is_some();
+        let action = if prompt { None } else { get_paste_action(text, &self.core.mode) };
+        if let Some(action) = action {
+            self.execute(action.as_ref()).await?;
+            self.execute(&lsp::RefilterCompletion).await?;
+            self.execute(&lsp::CloseHover).await?;
+            self.execute(&system::ShowQueuedMessages).await?;
+        }
+        Ok(())
+    }
+
+    async fn press(&mut self, key: KeyEvent) -> Result<()> {
+        let action = if self.message_manager.output().is_some() {
+            Some(get_pager_action(&key))
+        } else if self.message_manager.recovery().is_some() {
+            Some(get_recovery_action(&key))
+        } else if self.core.jobs.selected.is_some() {
+            Some(get_jobs_panel_action(&key))
+        } else if self.core.completion.is_some()
+            && let Some(action) = get_completion_action(&key)
+        {
+            Some(action)
+        } else {
+            match get_default_input_action(&key, &self.core.mode) {
+                Some(action) => Some(action),
+                None => {
+                    self.input_state.add_key(key);
+                    self.input_state
+                        .get_executable(&self.core.mode, &self.config.keymap)
+                }
+            }
+        };
+
+        if let Some(action) = action {
+            self.execute(action.as_ref()).await?;
+            if self.input_state.is_empty() && matches!(self.core.mode, Mode::OperationPending(_))
+            {
+                self.execute(&mode::EnterMode::new(Mode::Normal)).await?;
+            }
+            self.execute(&lsp::RefilterCompletion).await?;
+            self.execute(&lsp::CloseHover).await?;
+            self.execute(&system::ShowQueuedMessages).await?;
+        }
+        Ok(())
+    }
+
+    /// Context for drawing UI components into a render buffer directly
+    pub fn render_context(&mut self) -> RenderContext<'_> {
+        let document = self.core.buffer_manager.current_mut();
+        let uri = document.get_uri().unwrap_or_default();
+        RenderContext {
+            editor: EditorRenderContext {
+                viewport: &self.core.viewport,
+                document,
+                cursor: &self.core.cursor,
+                mode: &self.core.mode,
+                jobs: &self.core.jobs,
+                visual_anchor: self.core.visual_anchor,
+                completion: self.core.completion.as_ref(),
+                hover: self.core.hover.as_ref(),
+                quickfix: &self.core.quickfix,
+            },
+            input: InputRenderContext {
+                command_buffer: &self.command_buffer,
+                search_buffer: &self.search_buffer,
+                input_state: &self.input_state,
+            },
+            config: &self.config,
+            diagnostics: DiagnosticRenderContext {
+                diagnostics: self.lsp_service.get_diagnostics(&uri),
+                generation: self.lsp_service.diagnostics_generation(),
+                message_manager: &self.message_manager,
+            },
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.core.current_document().buffer.to_string()
+    }
+
+    /// Cursor position as (row, character column)
+    pub fn cursor(&self) -> (usize, usize) {
+        self.core.cursor.get_display_cursor()
+    }
+}
+
+/// Keys queued up for the main loop, read without waiting
+struct QueuedInput<'a> {
+    editor: &'a mut TestEditor,
+    queue: VecDeque<InputEvent>,
+    renders: usize,
+}
+
+#[async_trait(?Send)]
+impl EventLoop for QueuedInput<'_> {
+    fn is_running(&self) -> bool {
+        self.editor.running
+    }
+
+    fn render_frame(&mut self) -> Result<()> {
+        self.editor.render()?;
+        self.renders += 1;
+        Ok(())
+    }
+
+    async fn next_event(&mut self) -> Result<InputEvent> {
+        Ok(self.queue.pop_front().unwrap_or(InputEvent::None))
+    }
+
+    fn try_next_event(&mut self) -> Result<Option<InputEvent>> {
+        Ok(self.queue.pop_front())
+    }
+
+    async fn handle_event(&mut self, event: InputEvent) -> Result<()> {
+        match event {
+            InputEvent::Key(key) => self.editor.press(key).await,
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::ui::components::{CommandLine, EditorView, MessageArea, PendingKeys, SearchBox, StatusLine};
+use crate::ui::components::{
+    BottomBar, CompletionMenu, EditorView, HoverPopup, JobsPanel, Pager, QuickfixPanel, StatusLine,
+};
 use crate::ui::compositor::Compositor;
 use anyhow::Result;
-use crate::constants::components::{COMMAND_LINE, EDITOR_VIEW, MESSAGE_AREA, PENDING_KEYS, SEARCH_BOX, STATUS_LINE};
+use crate::constants::components::{
+    BOTTOM_BAR, COMPLETION, EDITOR_VIEW, HOVER, JOBS, PAGER, QUICKFIX, STATUS_LINE,
+};
 
 pub struct UISystem {
     pub compositor: Compositor,
@@ -12,15 +16,17 @@ impl UISystem {
         let mut compositor = Compositor::new(width, height);
 
         // Add components to the compositor
-        compositor.add_component(STATUS_LINE, StatusLine, true)?;
+        compositor.add_component(STATUS_LINE, StatusLine::new(), true)?;
         compositor.add_focusable_component(EDITOR_VIEW, EditorView::new(), true)?;
+        compositor.add_focusable_component(BOTTOM_BAR, BottomBar, true)?;
+        compositor.add_component(QUICKFIX, QuickfixPanel, false)?;
         compositor.set_focus(EDITOR_VIEW)?;
 
         // Add invisible components
-        compositor.add_component(PENDING_KEYS, PendingKeys, false)?;
-        compositor.add_focusable_component(COMMAND_LINE, CommandLine, false)?;
-        compositor.add_focusable_component(SEARCH_BOX, SearchBox, false)?;
-        compositor.add_component(MESSAGE_AREA, MessageArea, false)?;
+        compositor.add_overlay_component(COMPLETION, CompletionMenu::new())?;
+        compositor.add_overlay_component(HOVER, HoverPopup::new())?;
+        compositor.add_overlay_component(PAGER, Pager)?;
+        compositor.add_overlay_component(JOBS, JobsPanel)?;
 
 
         Ok(Self {
@@ -32,6 +38,10 @@ impl UISystem {
         self.compositor.resize(width, height);
     }
 
+    pub fn size(&self) -> (usize, usize) {
+        self.compositor.size()
+    }
+
     pub fn mark_all_dirty(&mut self) {
         self.compositor.mark_all_dirty()
     }
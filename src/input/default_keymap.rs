@@ -0,0 +1,178 @@
+use crate::actions::core::ActionDefinition as A;
+use crate::core::mode::Mode;
+use crate::core::operation::Operator;
+use crate::input::keymaps::{KeyMap, KeyMapSection};
+use std::sync::LazyLock;
+
+/// Keymap the config's `[keymap]` tables are layered on. Also used where user
+/// mappings must not apply, such as `:normal!`, and for looking up default keys
+pub fn default_keymap() -> &'static KeyMap {
+    static KEYMAP: LazyLock<KeyMap> = LazyLock::new(build);
+    &KEYMAP
+}
+
+fn enter(mode: Mode) -> A {
+    A::EnterMode { mode }
+}
+
+fn composite(description: &str, actions: Vec<A>) -> A {
+    A::Composite {
+        description: description.to_string(),
+        actions,
+    }
+}
+
+fn build() -> KeyMap {
+    let mut keymap = KeyMap::new();
+
+    keymap.bind_all(KeyMapSection::Default, [
+        ("q", A::Quit),
+        ("<Esc>", enter(Mode::Normal)),
+    ]);
+
+    keymap.bind_all(KeyMapSection::Movement, [
+        ("h", A::MoveLeft { inline: true }),
+        ("j", A::MoveDown),
+        ("k", A::MoveUp),
+        ("l", A::MoveRight { inline: true }),
+        ("<Left>", A::MoveLeft { inline: true }),
+        ("<Right>", A::MoveRight { inline: true }),
+        ("<Up>", A::MoveUp),
+        ("<Down>", A::MoveDown),
+        ("<Backspace>", A::MoveLeft { inline: false }),
+        ("$", A::MoveToLineEnd),
+        ("0", A::MoveToLineStart),
+        // 20| goes to column 20 and 100go to byte 100 of the buffer
+        ("|", A::GoToColumn { column: 0 }),
+        ("go", A::GoToByte { offset: 0 }),
+        // 15G and 15gg go to line 15, and after an operator cover whole lines to it
+        ("gg", A::MoveToTop),
+        ("G", A::MoveToBottom),
+        ("w", A::MoveToNextWord),
+        ("b", A::MoveToPreviousWord),
+        ("}", A::MoveToNextParagraph),
+        ("{", A::MoveToPreviousParagraph),
+        (")", A::MoveToNextSentence),
+        ("(", A::MoveToPreviousSentence),
+        // 50% goes to the middle of the file
+        ("%", A::MoveToMatchingBracket { percent: 0 }),
+        ("<Home>", A::MoveToLineStart),
+        ("<End>", A::MoveToLineEnd),
+    ]);
+
+    keymap.bind_all(KeyMapSection::Normal, [
+        ("zz", A::MoveToViewportCenter),
+        ("ZZ", composite("Write all buffers and quit", vec![
+            A::WriteAllBuffers,
+            A::QuitAll { force: false },
+        ])),
+        ("x", A::DeleteChar { inline: true }),
+        ("X", A::Backspace { inline: true }),
+        ("d", enter(Mode::OperationPending(Operator::Delete))),
+        ("c", enter(Mode::OperationPending(Operator::Change))),
+        ("y", enter(Mode::OperationPending(Operator::Yank))),
+        ("<gt>", enter(Mode::OperationPending(Operator::Indent))),
+        ("<lt>", enter(Mode::OperationPending(Operator::Dedent))),
+        ("i", enter(Mode::Insert)),
+        ("V", enter(Mode::VisualLine)),
+        (":", enter(Mode::Command)),
+        ("/", enter(Mode::Search)),
+        ("n", A::SearchNext),
+        ("N", A::SearchPrevious),
+        ("*", A::SearchWordForward),
+        ("#", A::SearchWordBackward),
+        ("u", A::Undo),
+        ("<C-r>", A::Redo),
+        ("<C-g>", A::ShowFileInfo),
+        ("<C-z>", A::Suspend),
+        // Terminals send Ctrl-^ as Ctrl-6
+        ("<C-^>", A::SwitchToAlternate),
+        ("<C-6>", A::SwitchToAlternate),
+        ("gd", A::GoToDefinition),
+        ("gr", A::FindReferences),
+        ("]d", A::NextDiagnostic),
+        ("[d", A::PreviousDiagnostic),
+        // Terminals send Ctrl-i as Tab
+        ("<C-o>", A::JumpBack),
+        ("<Tab>", A::JumpForward),
+        ("<C-i>", A::JumpForward),
+        ("K", A::Hover),
+        // In a :diff view or symbol list, go to the line shown
+        ("<Enter>", A::FollowLine),
+        ("a", composite("Insert after the cursor", vec![
+            enter(Mode::Insert),
+            A::MoveRight { inline: false },
+        ])),
+        ("A", composite("Insert at the end of the line", vec![
+            enter(Mode::Insert),
+            A::MoveToLineEnd,
+        ])),
+        // Insert mode comes first, so the new line is undone with what's typed on it
+        ("o", composite("Insert new line below the current line", vec![
+            enter(Mode::Insert),
+            A::InsertNewLineBelow,
+        ])),
+        ("O", composite("Insert new line above the current line", vec![
+            enter(Mode::Insert),
+            A::InsertNewLineAbove,
+        ])),
+        ("P", A::PasteBeforeCursor { count: 1 }),
+        ("p", A::PasteAfterCursor { count: 1 }),
+        ("gP", A::PasteBeforeCursorAndMove { count: 1 }),
+        ("gp", A::PasteAfterCursorAndMove { count: 1 }),
+    ]);
+
+    keymap.bind_all(KeyMapSection::Delete, [("d", A::DeleteCurrentLine)]);
+    keymap.bind_all(KeyMapSection::Change, [("c", A::ChangeCurrentLine)]);
+    keymap.bind_all(KeyMapSection::Yank, [("y", A::YankCurrentLine)]);
+    keymap.bind_all(KeyMapSection::Indent, [("<gt>", A::IndentCurrentLine)]);
+    keymap.bind_all(KeyMapSection::Dedent, [("<lt>", A::DedentCurrentLine)]);
+
+    keymap.bind_all(KeyMapSection::Visual, [
+        ("V", enter(Mode::Normal)),
+        ("d", A::DeleteSelectedLines),
+        ("x", A::DeleteSelectedLines),
+        ("c", A::ChangeSelectedLines),
+        ("y", A::YankSelectedLines),
+    ]);
+
+    keymap.bind_all(KeyMapSection::Insert, [
+        ("<Left>", A::MoveLeft { inline: false }),
+        ("<Right>", A::MoveRight { inline: false }),
+        ("<Up>", A::MoveUp),
+        ("<Down>", A::MoveDown),
+        ("<Backspace>", A::Backspace { inline: false }),
+        ("<Delete>", A::DeleteChar { inline: false }),
+        ("<Enter>", A::InsertNewLine),
+        ("<Tab>", A::InsertTab),
+        ("<Home>", A::MoveToLineStart),
+        ("<End>", A::MoveToLineEnd),
+        ("<C-w>", A::DeleteWordBefore),
+        ("<C-u>", A::DeleteToLineStart),
+        ("<C-Space>", A::RequestCompletion),
+    ]);
+
+    keymap.bind_all(KeyMapSection::Command, [
+        ("<Enter>", A::CommandExecute),
+        ("<Left>", A::CommandMoveLeft),
+        ("<Right>", A::CommandMoveRight),
+        ("<Backspace>", A::CommandBackspace),
+        ("<Delete>", A::CommandDeleteChar),
+        ("<Up>", A::CommandHistoryOlder),
+        ("<Down>", A::CommandHistoryNewer),
+        ("<Tab>", A::CommandComplete),
+        ("<S-Tab>", A::CommandCompleteBack),
+    ]);
+
+    keymap.bind_all(KeyMapSection::Search, [
+        ("<Enter>", A::SearchSubmit),
+        ("<Left>", A::SearchMoveLeft),
+        ("<Right>", A::SearchMoveRight),
+        ("<Backspace>", A::SearchBackspace),
+        ("<Delete>", A::SearchDeleteChar),
+        ("<Up>", A::SearchHistoryOlder),
+        ("<Down>", A::SearchHistoryNewer),
+    ]);
+
+    keymap
+}
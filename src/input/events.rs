@@ -1,49 +1,268 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crossterm::event::{Event, EventStream, KeyEvent};
+use async_trait::async_trait;
+use crossterm::event::{Event, EventStream, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
+use tokio::signal::unix::{signal, Signal, SignalKind};
 use tokio::time::{interval, Interval};
 
+/// Most events handled between two renders
+const MAX_BATCH_SIZE: usize = 256;
+/// Longest time spent handling queued events before rendering, so long bursts still show progress
+const MAX_BATCH_DURATION: Duration = Duration::from_millis(16);
+
 // Handle input events from the terminal
 pub struct EventHandler {
     event_stream: EventStream,
     tick_interval: Interval,
+    /// None when the handlers could not be installed, leaving the default behavior
+    signals: Option<Signals>,
+    /// Event read while looking for more resizes behind one, returned next
+    pending: Option<InputEvent>,
 }
 
 impl EventHandler {
     pub fn new() -> Self {
+        let signals = Signals::new()
+            .inspect_err(|e| log::error!("Failed to install signal handlers: {}", e))
+            .ok();
         Self {
             event_stream: EventStream::new(),
             tick_interval: interval(Duration::from_millis(500)),
+            signals,
+            pending: None,
         }
     }
 
     /// Poll for events, returning a tick if no events are available
     pub async fn next(&mut self) -> anyhow::Result<InputEvent> {
-        tokio::select! {
-            event = self.event_stream.next().fuse() => {
-                match event {
-                    Some(Ok(event)) => match event {
-                        Event::Key(key_event) => Ok(InputEvent::Key(key_event)),
-                        Event::Resize(width, height) => Ok(InputEvent::Resize(width, height)),
-                        _ => Ok(InputEvent::None), // Ignore other events for now
-                    }
-                    Some(Err(e)) => Err(anyhow::anyhow!("Error reading event: {}", e)),
-                    None => Ok(InputEvent::None), // Stream closed
-                }
-            }
-            _ = self.tick_interval.tick().fuse() => {
-                Ok(InputEvent::Tick)
+        if let Some(event) = self.pending.take() {
+            return Ok(event);
+        }
+        let signals = &mut self.signals;
+        let signal = async {
+            match signals {
+                Some(signals) => signals.recv().await,
+                None => std::future::pending().await,
             }
+        };
+        let event = tokio::select! {
+            event = signal.fuse() => event,
+            event = self.event_stream.next().fuse() => to_input_event(event)?,
+            _ = self.tick_interval.tick().fuse() => InputEvent::Tick,
+        };
+        self.skip_to_last_resize(event)
+    }
+
+    /// Returns an event that is already queued, without waiting for one
+    pub fn try_next(&mut self) -> anyhow::Result<Option<InputEvent>> {
+        if let Some(event) = self.pending.take() {
+            return Ok(Some(event));
+        }
+        match self.read_queued()? {
+            Some(event) => self.skip_to_last_resize(event).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn read_queued(&mut self) -> anyhow::Result<Option<InputEvent>> {
+        match self.event_stream.next().now_or_never() {
+            Some(event) => to_input_event(event).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn skip_to_last_resize(&mut self, event: InputEvent) -> anyhow::Result<InputEvent> {
+        let (event, pending) = last_resize(event, || self.read_queued())?;
+        self.pending = pending;
+        Ok(event)
+    }
+}
+
+/// Replaces a resize with the last of the resizes queued right behind it, so a burst
+/// of them is laid out once at the final size. Returns the first other event read
+/// while looking, to be handled after it
+fn last_resize(
+    mut event: InputEvent,
+    mut read_queued: impl FnMut() -> anyhow::Result<Option<InputEvent>>,
+) -> anyhow::Result<(InputEvent, Option<InputEvent>)> {
+    if !matches!(event, InputEvent::Resize(..)) {
+        return Ok((event, None));
+    }
+    loop {
+        match read_queued()? {
+            Some(next @ InputEvent::Resize(..)) => event = next,
+            next => return Ok((event, next)),
+        }
+    }
+}
+
+/// Signals the editor handles itself instead of being killed or stopped by them
+struct Signals {
+    terminate: Signal,
+    hangup: Signal,
+    suspend: Signal,
+    resume: Signal,
+}
+
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+            suspend: signal(SignalKind::from_raw(libc::SIGTSTP))?,
+            resume: signal(SignalKind::from_raw(libc::SIGCONT))?,
+        })
+    }
+
+    async fn recv(&mut self) -> InputEvent {
+        // Shells report a process killed by a signal with 128 plus its number
+        tokio::select! {
+            _ = self.terminate.recv() => InputEvent::Terminate { exit_code: 128 + libc::SIGTERM },
+            _ = self.hangup.recv() => InputEvent::Terminate { exit_code: 128 + libc::SIGHUP },
+            _ = self.suspend.recv() => InputEvent::Suspend,
+            _ = self.resume.recv() => InputEvent::Resume,
         }
     }
 }
 
+fn to_input_event(event: Option<std::io::Result<Event>>) -> anyhow::Result<InputEvent> {
+    match event {
+        Some(Ok(event)) => match event {
+            Event::Key(key_event) => Ok(InputEvent::Key(key_event)),
+            Event::Resize(width, height) => Ok(InputEvent::Resize(width, height)),
+            Event::Mouse(mouse_event) => Ok(InputEvent::Mouse(mouse_event)),
+            Event::Paste(text) => Ok(InputEvent::Paste(text)),
+            _ => Ok(InputEvent::None), // Ignore other events for now
+        },
+        Some(Err(e)) => Err(anyhow::anyhow!("Error reading event: {}", e)),
+        None => Ok(InputEvent::None), // Stream closed
+    }
+}
+
 // Possible input events
 #[derive(Debug)]
 pub enum InputEvent {
     Key(KeyEvent),
+    /// Only reported when the mouse is enabled in the config
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal, which comes whole with bracketed paste instead
+    /// of as a key for each character
+    Paste(String),
     Resize(u16, u16),
     Tick,
+    /// SIGTERM or SIGHUP, to save the unsaved text and exit
+    Terminate { exit_code: i32 },
+    /// SIGTSTP, sent by Ctrl-z
+    Suspend,
+    /// SIGCONT, after the process was stopped
+    Resume,
     None,
 }
+
+/// Events handled since the last render. Input that queued up while the editor
+/// was busy is handled without rendering in between, within the batch limits
+pub struct InputBatch {
+    started: Instant,
+    size: usize,
+    resized: bool,
+}
+
+impl InputBatch {
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            size: 0,
+            resized: false,
+        }
+    }
+
+    pub fn push(&mut self, event: &InputEvent) {
+        self.size += 1;
+        self.resized |= matches!(event, InputEvent::Resize(..));
+    }
+
+    /// Returns true if another queued event can be handled before rendering.
+    /// A resize ends the batch so the new size is drawn right away
+    pub fn has_room(&self) -> bool {
+        !self.resized
+            && self.size < MAX_BATCH_SIZE
+            && self.started.elapsed() < MAX_BATCH_DURATION
+    }
+}
+
+/// What the main loop reads events from, handles them with and renders, so that tests
+/// can run the loop without a terminal
+#[async_trait(?Send)]
+pub trait EventLoop {
+    fn is_running(&self) -> bool;
+    fn render_frame(&mut self) -> anyhow::Result<()>;
+    async fn next_event(&mut self) -> anyhow::Result<InputEvent>;
+    fn try_next_event(&mut self) -> anyhow::Result<Option<InputEvent>>;
+    async fn handle_event(&mut self, event: InputEvent) -> anyhow::Result<()>;
+
+    /// Renders, then waits for an event and handles the input that queued up meanwhile
+    /// before rendering again
+    async fn step(&mut self) -> anyhow::Result<()> {
+        self.render_frame()?;
+        let mut batch = InputBatch::start();
+        let mut event = Some(self.next_event().await?);
+        while let Some(current) = event {
+            batch.push(&current);
+            self.handle_event(current).await?;
+            event = if self.is_running() && batch.has_room() {
+                self.try_next_event()?
+            } else {
+                None
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use std::collections::VecDeque;
+
+    #[tokio::test]
+    async fn test_resize_burst_is_laid_out_once() {
+        let key = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        let mut queued = (1..50)
+            .map(|i| InputEvent::Resize(40 + i, 10 + i))
+            .chain([InputEvent::Key(key), InputEvent::Resize(20, 5)])
+            .collect::<VecDeque<_>>();
+
+        // The 50 resizes become the last one, the key after them is handled next
+        let (event, pending) = last_resize(InputEvent::Resize(40, 10), || Ok(queued.pop_front()))
+            .unwrap();
+        let InputEvent::Resize(width, height) = event else {
+            panic!("Expected a resize, got {event:?}");
+        };
+        assert_eq!((width, height), (89, 59));
+        assert!(matches!(pending, Some(InputEvent::Key(_))));
+        assert_eq!(queued.len(), 1);
+
+        // Other events are returned without reading further
+        let (event, pending) = last_resize(InputEvent::Key(key), || panic!("read")).unwrap();
+        assert!(matches!(event, InputEvent::Key(_)) && pending.is_none());
+
+        let mut editor = TestEditor::new("one\ntwo\n");
+        editor.resize(width as usize, height as usize);
+        assert_eq!(editor.render().unwrap().buffer.get_size(), (89, 59));
+        assert_eq!(editor.relayouts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_queued_keys_render_in_batches() {
+        let mut editor = TestEditor::new("one\ntwo\nthree\n");
+        let renders = editor.keys_queued("jjk").await.unwrap();
+        assert_eq!((renders, editor.cursor()), (1, (1, 0)));
+
+        let renders = editor.keys_queued(&"jk".repeat(500)).await.unwrap();
+        assert_eq!(editor.cursor(), (1, 0));
+        assert!(renders < 100, "{renders} renders for 1000 keys");
+    }
+}
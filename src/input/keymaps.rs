@@ -1,28 +1,70 @@
 use crate::actions::core::ActionDefinition;
 use crate::core::mode::Mode;
 use crate::core::operation::Operator;
+use crate::input::keys::decode_keys;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct KeyMapItem(pub HashMap<String, ActionDefinition>);
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Table of a keymap, named as in the `[keymap]` section of the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMapSection {
+    Default,
+    Movement,
+    Normal,
+    Insert,
+    Search,
+    Command,
+    Visual,
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Dedent,
+}
+
+impl KeyMapSection {
+    const ALL: [KeyMapSection; 12] = [
+        KeyMapSection::Default,
+        KeyMapSection::Movement,
+        KeyMapSection::Normal,
+        KeyMapSection::Insert,
+        KeyMapSection::Search,
+        KeyMapSection::Command,
+        KeyMapSection::Visual,
+        KeyMapSection::Delete,
+        KeyMapSection::Change,
+        KeyMapSection::Yank,
+        KeyMapSection::Indent,
+        KeyMapSection::Dedent,
+    ];
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct KeyMap {
+    /// Use only the bindings of the config, without the default keymap under them
+    pub clear_defaults: bool,
     default: KeyMapItem,
     movement: KeyMapItem,
     normal: KeyMapItem,
     insert: KeyMapItem,
     search: KeyMapItem,
     command: KeyMapItem,
+    visual: KeyMapItem,
     pending: PendingKeyMap,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PendingKeyMap {
     delete: KeyMapItem,
     change: KeyMapItem,
     yank: KeyMapItem,
+    indent: KeyMapItem,
+    dedent: KeyMapItem,
 }
 
 impl KeyMap {
@@ -30,6 +72,95 @@ impl KeyMap {
         Self::default()
     }
 
+    fn section(&self, section: KeyMapSection) -> &HashMap<String, ActionDefinition> {
+        match section {
+            KeyMapSection::Default => &self.default.0,
+            KeyMapSection::Movement => &self.movement.0,
+            KeyMapSection::Normal => &self.normal.0,
+            KeyMapSection::Insert => &self.insert.0,
+            KeyMapSection::Search => &self.search.0,
+            KeyMapSection::Command => &self.command.0,
+            KeyMapSection::Visual => &self.visual.0,
+            KeyMapSection::Delete => &self.pending.delete.0,
+            KeyMapSection::Change => &self.pending.change.0,
+            KeyMapSection::Yank => &self.pending.yank.0,
+            KeyMapSection::Indent => &self.pending.indent.0,
+            KeyMapSection::Dedent => &self.pending.dedent.0,
+        }
+    }
+
+    fn section_mut(&mut self, section: KeyMapSection) -> &mut HashMap<String, ActionDefinition> {
+        match section {
+            KeyMapSection::Default => &mut self.default.0,
+            KeyMapSection::Movement => &mut self.movement.0,
+            KeyMapSection::Normal => &mut self.normal.0,
+            KeyMapSection::Insert => &mut self.insert.0,
+            KeyMapSection::Search => &mut self.search.0,
+            KeyMapSection::Command => &mut self.command.0,
+            KeyMapSection::Visual => &mut self.visual.0,
+            KeyMapSection::Delete => &mut self.pending.delete.0,
+            KeyMapSection::Change => &mut self.pending.change.0,
+            KeyMapSection::Yank => &mut self.pending.yank.0,
+            KeyMapSection::Indent => &mut self.pending.indent.0,
+            KeyMapSection::Dedent => &mut self.pending.dedent.0,
+        }
+    }
+
+    pub fn bind_all<'a>(
+        &mut self,
+        section: KeyMapSection,
+        bindings: impl IntoIterator<Item = (&'a str, ActionDefinition)>,
+    ) {
+        let section = self.section_mut(section);
+        for (key, definition) in bindings {
+            section.insert(key.to_string(), definition);
+        }
+    }
+
+    /// Adds the bindings of the other keymap, replacing those of the same keys
+    pub fn extend(&mut self, mut other: KeyMap) {
+        for section in KeyMapSection::ALL {
+            let bindings = std::mem::take(other.section_mut(section));
+            self.section_mut(section).extend(bindings);
+        }
+    }
+
+    /// Describes the bindings of this keymap that can't be typed anymore once the
+    /// other one is layered on top, because one of its keys is a prefix of theirs
+    pub fn shadowed_by(&self, other: &KeyMap) -> Vec<String> {
+        // Keys that are typed one after the other in normal mode
+        const SECTIONS: [KeyMapSection; 3] =
+            [KeyMapSection::Default, KeyMapSection::Movement, KeyMapSection::Normal];
+        let keys = |keymap: &KeyMap| -> Vec<String> {
+            let mut keys = SECTIONS
+                .iter()
+                .flat_map(|&section| keymap.section(section).keys().cloned())
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            keys
+        };
+
+        let own_keys = keys(self);
+        keys(other)
+            .into_iter()
+            .filter_map(|prefix| {
+                let prefix_events = decode_keys(&prefix);
+                let shadowed = own_keys
+                    .iter()
+                    .filter(|key| {
+                        let events = decode_keys(key);
+                        events.len() > prefix_events.len() && events.starts_with(&prefix_events)
+                    })
+                    .map(|key| format!("`{key}`"))
+                    .collect::<Vec<_>>();
+                (!shadowed.is_empty()).then(|| {
+                    format!("`{prefix}` shadows the default keys {}", shadowed.join(", "))
+                })
+            })
+            .collect()
+    }
+
     pub fn get_action(&self, mode: &Mode, sequence: &str) -> Option<&ActionDefinition> {
         let definition = match mode {
             Mode::Normal => self
@@ -49,6 +180,11 @@ impl KeyMap {
                 .command
                 .0
                 .get(sequence),
+            Mode::VisualLine => self
+                .visual
+                .0
+                .get(sequence)
+                .or_else(|| self.movement.0.get(sequence)),
             Mode::OperationPending(Operator::Delete) => self
                 .movement
                 .0
@@ -64,14 +200,67 @@ impl KeyMap {
                 .0
                 .get(sequence)
                 .or_else(|| self.pending.yank.0.get(sequence)),
+            Mode::OperationPending(Operator::Indent) => self
+                .movement
+                .0
+                .get(sequence)
+                .or_else(|| self.pending.indent.0.get(sequence)),
+            Mode::OperationPending(Operator::Dedent) => self
+                .movement
+                .0
+                .get(sequence)
+                .or_else(|| self.pending.dedent.0.get(sequence)),
         };
         definition.or_else(|| self.default.0.get(sequence))
     }
 
+    /// Finds the shortest normal mode key sequence bound to a matching action,
+    /// including line-wise operator commands such as `dd`
+    pub fn find_normal_key(&self, matches: impl Fn(&ActionDefinition) -> bool) -> Option<String> {
+        let mut candidates = self.normal_keys(&matches);
+
+        let pending = [
+            (Operator::Delete, &self.pending.delete),
+            (Operator::Change, &self.pending.change),
+            (Operator::Yank, &self.pending.yank),
+            (Operator::Indent, &self.pending.indent),
+            (Operator::Dedent, &self.pending.dedent),
+        ];
+        for (operator, keymap) in pending {
+            let operator_keys = self.normal_keys(&|definition| {
+                matches!(definition, ActionDefinition::EnterMode { mode: Mode::OperationPending(op) } if *op == operator)
+            });
+            let Some(operator_key) = shortest(operator_keys) else {
+                continue;
+            };
+            candidates.extend(
+                keymap
+                    .0
+                    .iter()
+                    .filter(|(_, definition)| matches(definition))
+                    .map(|(key, _)| format!("{operator_key}{key}")),
+            );
+        }
+
+        shortest(candidates)
+    }
+
+    fn normal_keys(&self, matches: &dyn Fn(&ActionDefinition) -> bool) -> Vec<String> {
+        self.normal
+            .0
+            .iter()
+            .chain(self.movement.0.iter())
+            .chain(self.default.0.iter())
+            .filter(|(_, definition)| matches(definition))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
     pub fn is_partial_match(&self, mode: &Mode, sequence: &str) -> bool {
         let mut keys: Box<dyn Iterator<Item = &String>> = match mode {
             Mode::Normal => Box::new(self.movement.0.keys().chain(self.normal.0.keys())),
             Mode::OperationPending(_) => Box::new(self.movement.0.keys()),
+            Mode::VisualLine => Box::new(self.movement.0.keys().chain(self.visual.0.keys())),
             _ => {
                 return false; // No partial matches in other modes
             }
@@ -82,3 +271,8 @@ impl KeyMap {
         })
     }
 }
+
+fn shortest(keys: Vec<String>) -> Option<String> {
+    keys.into_iter()
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+}
@@ -0,0 +1,79 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Parses keys written in keymap notation, e.g. `A;<Esc>` or `<C-r>`.
+/// A `<` that doesn't start a known key name is taken literally
+pub fn decode_keys(keys: &str) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut rest = keys;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<'
+            && let Some(end) = rest.find('>')
+            && let Some(event) = decode_key(&rest[1..end])
+        {
+            events.push(event);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        events.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    events
+}
+
+/// Decodes the name between `<` and `>`, the inverse of `KeyEncoder`
+fn decode_key(name: &str) -> Option<KeyEvent> {
+    let (modifiers, name) = match name.split_once('-') {
+        Some(("C", name)) if !name.is_empty() => (KeyModifiers::CONTROL, name),
+        Some(("A", name)) if !name.is_empty() => (KeyModifiers::ALT, name),
+        Some(("S", name)) if !name.is_empty() => (KeyModifiers::SHIFT, name),
+        _ => (KeyModifiers::NONE, name),
+    };
+    let code = match name {
+        "Backspace" => KeyCode::Backspace,
+        "Enter" => KeyCode::Enter,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Tab" if modifiers == KeyModifiers::SHIFT => KeyCode::BackTab,
+        "Tab" => KeyCode::Tab,
+        "Delete" => KeyCode::Delete,
+        "Esc" => KeyCode::Esc,
+        "Space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        name => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if modifiers != KeyModifiers::NONE => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keys::KeyEncoder;
+
+    #[test]
+    fn test_decode_is_inverse_of_encode() {
+        let keys = "dd<C-r>A<lt>x<gt><Esc><Left><S-Tab> <C-Space>";
+        let encoded = decode_keys(keys)
+            .iter()
+            .map(|event| event.encode().unwrap())
+            .collect::<String>();
+        assert_eq!(encoded, keys);
+
+        assert_eq!(decode_keys("<x>").len(), 3);
+        assert_eq!(decode_keys("a<b").len(), 3);
+    }
+}
@@ -19,6 +19,8 @@ impl KeyEncoder for KeyCode {
             KeyCode::PageUp => "<PageUp>".to_string(),
             KeyCode::PageDown => "<PageDown>".to_string(),
             KeyCode::Tab => "<Tab>".to_string(),
+            // Shift-Tab, which terminals send as a key of its own
+            KeyCode::BackTab => "<S-Tab>".to_string(),
             KeyCode::Delete => "<Delete>".to_string(),
             KeyCode::Esc => "<Esc>".to_string(),
             KeyCode::Char(c) => {
@@ -40,13 +42,19 @@ impl KeyEncoder for KeyCode {
 
 impl KeyEncoder for KeyEvent {
     fn encode(&self) -> Result<String> {
-        let key = self.code.encode()?;
+        let key = match self.code {
+            // `<C-Space>` rather than the hard to read `<C- >`
+            KeyCode::Char(' ') if matches!(self.modifiers, KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                "Space".to_string()
+            }
+            code => code.encode()?,
+        };
         match self.modifiers { 
             KeyModifiers::NONE => Ok(key),
             KeyModifiers::CONTROL => Ok(format!("<C-{}>", key)),
             KeyModifiers::ALT => Ok(format!("<A-{}>", key)),
             KeyModifiers::SHIFT => match self.code {
-                KeyCode::Char(_) => {
+                KeyCode::Char(_) | KeyCode::BackTab => {
                     Ok(key)
                 }
                 _ => Ok(format!("<S-{}>", key)),
@@ -1,2 +1,4 @@
+mod decode;
 mod encode;
+pub use decode::decode_keys;
 pub use encode::KeyEncoder;
@@ -1,18 +1,22 @@
-use crate::actions::core::{ActionDefinition, Executable};
-use crate::actions::{command, editing, search};
+use crate::actions::core::{ActionDefinition, CompositeExecutable, Executable};
+use crate::actions::{command, editing, search, system};
+use crate::actions::jobs::JobsPanelKey;
+use crate::actions::lsp::CompletionKey;
+use crate::actions::system::PageOutput;
 use crate::core::mode::Mode;
 use crate::core::operation::Operator;
 use crate::input::keymaps::KeyMap;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use crate::actions::buffer::SetRegister;
+use crate::actions::buffer::{RecoveryKey, SetRegister};
 use crate::actions::composite::{ComboAction, RepeatingAction};
 use crate::actions::core::definition::create_action_from_definition;
 use crate::core::register::RegisterName;
 use crate::input::keys::KeyEncoder;
 use crate::input::state::{InputState};
-use crate::input::state::internal::RepeatState;
+use crate::input::state::internal::{PendingCommand, RepeatState};
 use crate::input::state::parser::{from_keymap_with_repeat, register, ParserResult};
 
+pub mod default_keymap;
 pub mod events;
 pub mod keymaps;
 pub mod keys;
@@ -24,6 +28,7 @@ pub struct InputProcessor {
 
     // Internal states for processing input
     repeats: RepeatState,
+    register: Option<RegisterName>,
 }
 
 impl InputProcessor {
@@ -31,6 +36,7 @@ impl InputProcessor {
         InputProcessor {
             state: InputState::new(),
             repeats: RepeatState::new(),
+            register: None,
         }
     }
 
@@ -47,18 +53,46 @@ impl InputProcessor {
     pub fn clear(&mut self) {
         self.state.clear();
         self.repeats.clear();
+        self.register = None;
     }
 
     pub fn display_input(&self) -> &str {
         self.state.display()
     }
 
+    /// Returns the parsed form of the pending input, or None if nothing is pending
+    pub fn pending_command(&self, mode: &Mode) -> Option<PendingCommand> {
+        if self.state.is_empty() {
+            return None;
+        }
+
+        let input = self.state.get_input();
+        let keys = input.trim_start_matches(|c: char| c.is_ascii_digit());
+        let typed_count = input[..input.len() - keys.len()].parse::<usize>().ok();
+        let count = match (self.repeats.pending_repeat, typed_count) {
+            (None, None) => None,
+            (pending, typed) => Some(pending.unwrap_or(1) * typed.unwrap_or(1)),
+        };
+        let operator = match mode {
+            Mode::OperationPending(operator) => Some(*operator),
+            _ => None,
+        };
+
+        Some(PendingCommand {
+            register: self.register,
+            count,
+            operator,
+            keys: keys.to_string(),
+        })
+    }
+
     pub fn get_executable(&mut self, mode: &Mode, keymap: &KeyMap) -> Option<Box<dyn Executable>> {
         // Get the register if it exists
         let result = register(self.state.get_input());
         match result {
             Ok((_, ParserResult { result, length })) => {
                 self.state.advance(length);
+                self.register = Some(result);
                 return Some(Box::new(SetRegister::new(result)));
             }
             Err(nom::Err::Incomplete(_)) => {
@@ -106,6 +140,45 @@ impl InputProcessor {
         }
 
         let repeat = self.repeats.get_total_repeat();
+
+        // A count on gg/G, |, go and % is an absolute position rather than a repetition
+        let has_repeat = self.repeats.has_repeat();
+        let (definition, repeat) = match definition {
+            ActionDefinition::MoveToTop | ActionDefinition::MoveToBottom if has_repeat => {
+                let line_number = repeat.saturating_sub(1);
+                (ActionDefinition::GoToLine { line_number }, 1)
+            }
+            ActionDefinition::GoToColumn { .. } if has_repeat => {
+                let column = repeat.saturating_sub(1);
+                (ActionDefinition::GoToColumn { column }, 1)
+            }
+            ActionDefinition::GoToByte { .. } if has_repeat => {
+                let offset = repeat.saturating_sub(1);
+                (ActionDefinition::GoToByte { offset }, 1)
+            }
+            ActionDefinition::MoveToMatchingBracket { .. } if has_repeat => {
+                (ActionDefinition::MoveToMatchingBracket { percent: repeat.min(100) }, 1)
+            }
+            // A count on r replaces that many characters as one edit
+            ActionDefinition::ReplaceChar { ch, .. } if has_repeat => {
+                (ActionDefinition::ReplaceChar { ch, count: repeat }, 1)
+            }
+            // A count on p pastes that many copies as one edit, undone at once
+            ActionDefinition::PasteBeforeCursor { .. } if has_repeat => {
+                (ActionDefinition::PasteBeforeCursor { count: repeat }, 1)
+            }
+            ActionDefinition::PasteAfterCursor { .. } if has_repeat => {
+                (ActionDefinition::PasteAfterCursor { count: repeat }, 1)
+            }
+            ActionDefinition::PasteBeforeCursorAndMove { .. } if has_repeat => {
+                (ActionDefinition::PasteBeforeCursorAndMove { count: repeat }, 1)
+            }
+            ActionDefinition::PasteAfterCursorAndMove { .. } if has_repeat => {
+                (ActionDefinition::PasteAfterCursorAndMove { count: repeat }, 1)
+            }
+            definition => (definition, repeat),
+        };
+
         if let Mode::OperationPending(operator) = mode {
             self.clear();
             if definition.is_movement_type() {
@@ -127,6 +200,21 @@ impl InputProcessor {
                     repeat - 1,
                     ActionDefinition::MoveDown,
                 )),
+                ActionDefinition::YankCurrentLine => Box::new(ComboAction::new(
+                    Operator::Yank,
+                    repeat - 1,
+                    ActionDefinition::MoveDown,
+                )),
+                ActionDefinition::IndentCurrentLine => Box::new(ComboAction::new(
+                    Operator::Indent,
+                    repeat - 1,
+                    ActionDefinition::MoveDown,
+                )),
+                ActionDefinition::DedentCurrentLine => Box::new(ComboAction::new(
+                    Operator::Dedent,
+                    repeat - 1,
+                    ActionDefinition::MoveDown,
+                )),
                 ActionDefinition::DeleteChar { inline } => Box::new(ComboAction::new(
                     Operator::Delete,
                     repeat,
@@ -148,6 +236,11 @@ impl InputProcessor {
 }
 
 pub fn get_default_input_action(key_event: &KeyEvent, mode: &Mode) -> Option<Box<dyn Executable>> {
+    // Ctrl-c interrupts in every mode, whatever keys are pending
+    if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+        return Some(Box::new(system::Interrupt));
+    }
+
     let KeyEvent { code: KeyCode::Char(c), modifiers, .. } = key_event else {
         return None;
     };
@@ -167,3 +260,149 @@ pub fn get_default_input_action(key_event: &KeyEvent, mode: &Mode) -> Option<Box
 
     Some(executable)
 }
+
+/// Action for text pasted into the terminal, in the modes that take text. Only the
+/// first line goes to the command line or a search, where a line break would run it
+pub fn get_paste_action(text: &str, mode: &Mode) -> Option<Box<dyn Executable>> {
+    // Terminals send the line breaks of a paste as `\r`
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    match mode {
+        Mode::Insert => Some(Box::new(editing::InsertText::new(text))),
+        Mode::Command | Mode::Search => {
+            let line = text.lines().next().unwrap_or_default();
+            let mut executable = CompositeExecutable::new();
+            for c in line.chars() {
+                match mode {
+                    Mode::Command => executable.add(command::CommandInsertChar::new(c)),
+                    _ => executable.add(search::SearchInsertChar::new(c)),
+                };
+            }
+            Some(Box::new(executable))
+        }
+        _ => None,
+    }
+}
+
+/// Keys of the `-- More --` prompt, which takes every key while output is shown
+pub fn get_pager_action(key_event: &KeyEvent) -> Box<dyn Executable> {
+    let control = key_event.modifiers == KeyModifiers::CONTROL;
+    let action = match key_event.code {
+        KeyCode::Char('c') if control => PageOutput::Quit,
+        KeyCode::Char('f') if control => PageOutput::Pages(1),
+        KeyCode::Char('b') if control => PageOutput::Pages(-1),
+        _ if control => PageOutput::Other,
+        KeyCode::Char(' ' | 'f') | KeyCode::PageDown => PageOutput::Pages(1),
+        KeyCode::Char('b') | KeyCode::PageUp => PageOutput::Pages(-1),
+        KeyCode::Enter | KeyCode::Char('j') | KeyCode::Down => PageOutput::Lines(1),
+        KeyCode::Char('k') | KeyCode::Up => PageOutput::Lines(-1),
+        KeyCode::Char('g') | KeyCode::Home => PageOutput::First,
+        KeyCode::Char('G') | KeyCode::End => PageOutput::Last,
+        KeyCode::Char('q') | KeyCode::Esc => PageOutput::Quit,
+        _ => PageOutput::Other,
+    };
+    Box::new(action)
+}
+
+/// Keys of the prompt shown when a file opens with a swap file newer than it, which
+/// takes every key until it's answered
+pub fn get_recovery_action(key_event: &KeyEvent) -> Box<dyn Executable> {
+    let action = match key_event.code {
+        KeyCode::Char('r') if key_event.modifiers == KeyModifiers::NONE => RecoveryKey::Recover,
+        KeyCode::Char('d') if key_event.modifiers == KeyModifiers::NONE => RecoveryKey::Delete,
+        _ => RecoveryKey::Other,
+    };
+    Box::new(action)
+}
+
+/// Keys of the `:jobs` panel, which takes every key while open
+pub fn get_jobs_panel_action(key_event: &KeyEvent) -> Box<dyn Executable> {
+    let control = key_event.modifiers == KeyModifiers::CONTROL;
+    let action = match key_event.code {
+        KeyCode::Char('c') if control => JobsPanelKey::Close,
+        _ if control => JobsPanelKey::Other,
+        KeyCode::Char('j') | KeyCode::Down => JobsPanelKey::Select(1),
+        KeyCode::Char('k') | KeyCode::Up => JobsPanelKey::Select(-1),
+        KeyCode::Char('x' | 'd') | KeyCode::Delete => JobsPanelKey::Cancel,
+        KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => JobsPanelKey::Close,
+        _ => JobsPanelKey::Other,
+    };
+    Box::new(action)
+}
+
+/// Keys of the completion menu open in insert mode, none for the keys it leaves to
+/// insert mode
+pub fn get_completion_action(key_event: &KeyEvent) -> Option<Box<dyn Executable>> {
+    let control = key_event.modifiers == KeyModifiers::CONTROL;
+    let action = match key_event.code {
+        KeyCode::Char('n') if control => CompletionKey::Select(1),
+        KeyCode::Char('p') if control => CompletionKey::Select(-1),
+        KeyCode::Down => CompletionKey::Select(1),
+        KeyCode::Up => CompletionKey::Select(-1),
+        KeyCode::Enter | KeyCode::Tab => CompletionKey::Accept,
+        KeyCode::Esc => CompletionKey::Dismiss,
+        _ => return None,
+    };
+    Some(Box::new(action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::TestEditor;
+
+    fn showcmd(editor: &TestEditor) -> Option<String> {
+        editor
+            .input_state
+            .pending_command(&editor.core.mode)
+            .map(|command| command.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_interrupt() {
+        let mut editor = TestEditor::new("one two three\n");
+        editor.keys("2d<C-c>w").await.unwrap();
+        assert_eq!(editor.text(), "one two three\n");
+        assert_eq!(editor.cursor(), (0, 4));
+        let message = editor.message_manager.current_message().unwrap();
+        assert_eq!(message.content, "Type :q to exit");
+
+        // Back in normal mode after leaving insert mode, x deletes the typed space
+        editor.keys("ihey <C-c>:s/one/1<C-c>x").await.unwrap();
+        assert_eq!(editor.text(), "one heytwo three\n");
+        assert!(showcmd(&editor).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pending_command() {
+        let mut editor = TestEditor::new("one two three\n");
+
+        editor.keys("\"a2d").await.unwrap();
+        assert_eq!(showcmd(&editor).as_deref(), Some("\"a2d"));
+
+        editor.keys("3").await.unwrap();
+        assert_eq!(showcmd(&editor).as_deref(), Some("\"a6d"));
+
+        editor.keys("<Esc>").await.unwrap();
+        assert_eq!(showcmd(&editor), None);
+
+        editor.keys("g").await.unwrap();
+        assert_eq!(showcmd(&editor).as_deref(), Some("g"));
+
+        editor.keys("g").await.unwrap();
+        assert_eq!(showcmd(&editor), None);
+    }
+
+    #[test]
+    fn test_count_on_gg_and_g_is_a_line_number() {
+        let keymap = default_keymap::default_keymap();
+        let mut input = InputProcessor::new();
+        let mut action = |keys: &str| {
+            input.state.add_string(keys);
+            format!("{:?}", input.get_executable(&Mode::Normal, keymap).unwrap())
+        };
+        assert_eq!(action("15G"), "GoToLine { line_number: 14 }");
+        assert_eq!(action("3gg"), "GoToLine { line_number: 2 }");
+        assert_eq!(action("G"), "MoveToBottom");
+        assert_eq!(action("gg"), "MoveToTop");
+    }
+}
@@ -1,3 +1,7 @@
+use crate::core::operation::Operator;
+use crate::core::register::RegisterName;
+use std::fmt::{Display, Formatter};
+
 #[derive(Debug)]
 pub struct RepeatState {
     pub repeat: Option<usize>,
@@ -21,7 +25,35 @@ impl RepeatState {
         self.pending_repeat = self.repeat.take();
     }
 
+    pub fn has_repeat(&self) -> bool {
+        self.repeat.is_some() || self.pending_repeat.is_some()
+    }
+
     pub fn get_total_repeat(&self) -> usize {
         self.repeat.unwrap_or(1) * self.pending_repeat.unwrap_or(1)
     }
 }
+
+/// Parsed view of a partially entered command, used by the showcmd indicator
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingCommand {
+    pub register: Option<RegisterName>,
+    pub count: Option<usize>,
+    pub operator: Option<Operator>,
+    pub keys: String,
+}
+
+impl Display for PendingCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(register) = self.register {
+            write!(f, "\"{}", register.to_char())?;
+        }
+        if let Some(count) = self.count {
+            write!(f, "{count}")?;
+        }
+        if let Some(operator) = self.operator {
+            write!(f, "{}", operator.to_string())?;
+        }
+        write!(f, "{}", self.keys)
+    }
+}
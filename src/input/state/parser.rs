@@ -1,9 +1,14 @@
 use crate::actions::core::ActionDefinition;
+use crate::core::buffer::text_object::TEXT_OBJECTS;
+use crate::core::marks::is_mark_name;
 use crate::core::mode::Mode;
 use crate::core::register::RegisterName;
 use crate::input::keymaps::KeyMap;
+use crate::input::keys::{KeyEncoder, decode_keys};
+use crossterm::event::{KeyCode, KeyModifiers};
 use nom::IResult;
 use nom::Parser;
+use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::anychar;
 use nom::combinator::opt;
@@ -67,13 +72,108 @@ fn from_keymap(
     }
 }
 
+/// `i` or `a` and the object, such as `iw` or `a(`, taken only while an operator is
+/// pending since they insert text in normal mode
+fn text_object(mode: &Mode) -> impl Fn(&str) -> IResult<&str, ParserResult<ActionDefinition>> {
+    move |input: &str| {
+        let error = || nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag));
+        if !matches!(mode, Mode::OperationPending(_)) {
+            return Err(error());
+        }
+        let inner = match input.chars().next() {
+            Some('i') => true,
+            Some('a') => false,
+            _ => return Err(error()),
+        };
+        let rest = &input[1..];
+        if rest.is_empty() {
+            return Err(nom::Err::Incomplete(nom::Needed::Unknown));
+        }
+        // `<` and `>` arrive encoded as `<lt>` and `<gt>`
+        let (object, key) = TEXT_OBJECTS
+            .chars()
+            .filter_map(|object| Some((object, KeyCode::Char(object).encode().ok()?)))
+            .find(|(_, key)| rest.starts_with(key.as_str()))
+            .ok_or_else(error)?;
+        Ok((
+            &rest[key.len()..],
+            ParserResult {
+                result: ActionDefinition::TextObject { object, inner },
+                length: 1 + key.len(),
+            },
+        ))
+    }
+}
+
+/// `r` and the character to replace with, which may be `<Enter>` or `<Tab>`. Other
+/// keys such as `<Esc>` after `r` fail, dropping the input
+fn replace_char(mode: &Mode) -> impl Fn(&str) -> IResult<&str, ParserResult<ActionDefinition>> {
+    move |input: &str| {
+        let error = || nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag));
+        if *mode != Mode::Normal || !input.starts_with('r') {
+            return Err(error());
+        }
+        let rest = &input[1..];
+        let Some(key) = decode_keys(rest).into_iter().next() else {
+            return Err(nom::Err::Incomplete(nom::Needed::Unknown));
+        };
+        let ch = match (key.code, key.modifiers) {
+            (KeyCode::Char(ch), KeyModifiers::NONE) => ch,
+            (KeyCode::Enter, KeyModifiers::NONE) => '\n',
+            (KeyCode::Tab, KeyModifiers::NONE) => '\t',
+            _ => return Err(error()),
+        };
+        let length = key.encode().map_err(|_| error())?.len();
+        Ok((
+            &rest[length..],
+            ParserResult {
+                result: ActionDefinition::ReplaceChar { ch, count: 1 },
+                length: 1 + length,
+            },
+        ))
+    }
+}
+
+/// `m` and a mark name in normal mode, or `'` or `` ` `` and one to jump to it, which
+/// also works after an operator
+fn mark(mode: &Mode) -> impl Fn(&str) -> IResult<&str, ParserResult<ActionDefinition>> {
+    move |input: &str| {
+        let error = || nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag));
+        let mut chars = input.chars();
+        let definition = match (chars.next(), mode) {
+            (Some('m'), Mode::Normal) => |name| ActionDefinition::SetMark { name },
+            (Some('\''), Mode::Normal | Mode::OperationPending(_)) => {
+                |name| ActionDefinition::GoToMark { name, exact: false }
+            }
+            (Some('`'), Mode::Normal | Mode::OperationPending(_)) => {
+                |name| ActionDefinition::GoToMark { name, exact: true }
+            }
+            _ => return Err(error()),
+        };
+        let Some(name) = chars.next() else {
+            return Err(nom::Err::Incomplete(nom::Needed::Unknown));
+        };
+        if !is_mark_name(name) || (input.starts_with('m') && !name.is_ascii_lowercase()) {
+            return Err(error());
+        }
+        let length = 1 + name.len_utf8();
+        Ok((
+            &input[length..],
+            ParserResult {
+                result: definition(name),
+                length,
+            },
+        ))
+    }
+}
+
 pub fn from_keymap_with_repeat(
     mode: &Mode,
     keymap: &KeyMap,
 ) -> impl Fn(&str) -> IResult<&str, ParserResult<(Option<usize>, ActionDefinition)>> {
     move |input: &str| {
         let (input, repeat) = opt(positive_count).parse(input)?;
-        let (input, action) = from_keymap(mode, keymap)(input)?;
+        let (input, action) = alt((text_object(mode), replace_char(mode), mark(mode), from_keymap(mode, keymap))).parse(input)?;
         let length = action.length + repeat.as_ref().map_or(0, |r| r.length);
         let result = ParserResult {
             result: (repeat.map(|r| r.result), action.result),
@@ -0,0 +1,14 @@
+mod config;
+mod constants;
+mod core;
+mod editor;
+mod input;
+mod service;
+mod ui;
+mod actions;
+#[cfg(feature = "bench")]
+pub mod bench;
+
+pub use crate::config::{get_config_dir, init_config, Config};
+pub use crate::core::language::Language;
+pub use crate::editor::{EditorBuilder, restore_terminal};
@@ -1,64 +1,125 @@
-mod config;
-mod constants;
-mod core;
-mod editor;
-mod input;
-mod service;
-mod ui;
-mod actions;
-
-use crate::config::{get_config_dir, Config};
-use anyhow::Result;
-use crossterm::terminal::ClearType;
-use crossterm::{cursor, terminal};
-use std::{env, io::stdout, panic};
-use crossterm::cursor::SetCursorStyle;
-use crate::editor::EditorBuilder;
+use anyhow::{anyhow, Context, Result};
+use std::io::{IsTerminal, Read};
+use std::{env, panic};
+use viron::{get_config_dir, init_config, Config, EditorBuilder, Language, restore_terminal};
+
+/// `viron [--filetype NAME] [+LINE] [FILE]...`, where a FILE of `-` reads the text from
+/// stdin. Each file opens in a buffer, the first one shown at the line
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    files: Vec<String>,
+    /// Line of the first file to start at, counted from 1
+    line: Option<usize>,
+    filetype: Option<String>,
+    init_config: bool,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--init-config" {
+            parsed.init_config = true;
+        } else if arg == "--filetype" || arg == "--ft" {
+            parsed.filetype = Some(args.next().context("Missing value for --filetype")?);
+        } else if let Some(name) = arg
+            .strip_prefix("--filetype=")
+            .or_else(|| arg.strip_prefix("--ft="))
+        {
+            parsed.filetype = Some(name.to_string());
+        } else if arg.starts_with("--") {
+            return Err(anyhow!("Unknown option: {arg}"));
+        } else if let Some(line) = arg.strip_prefix('+') {
+            let line = line.parse().with_context(|| format!("Invalid line number: {arg}"))?;
+            parsed.line = Some(line);
+        } else {
+            parsed.files.push(arg);
+        }
+    }
+    if parsed.files.len() > 1 && parsed.files.iter().any(|file| file == "-") {
+        return Err(anyhow!("Text from stdin can't be opened along with files"));
+    }
+    Ok(parsed)
+}
+
+/// Reads piped text before the terminal is set up. Crossterm then reads keys from
+/// /dev/tty, since stdin is no longer a terminal
+fn read_stdin() -> Result<String> {
+    let mut stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err(anyhow!("Nothing is piped to stdin, try `command | viron -`"));
+    }
+    let mut bytes = Vec::new();
+    stdin.read_to_end(&mut bytes).context("Failed to read stdin")?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Enable better panic messages
+    // Enable better panic messages, shown once the terminal is restored
     better_panic::install();
+    let panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        _ = restore_terminal();
+        log::error!("{}", info);
+        panic_hook(info);
+    }));
 
     // Initialize logging if needed
     setup_log()?;
 
     // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
-    let file_name = args.get(1);
+    let args = parse_args(env::args().skip(1))?;
+
+    if args.init_config {
+        let config_dir = get_config_dir();
+        let written = init_config(&config_dir)?;
+        if written.is_empty() {
+            println!("Config already exists in {}", config_dir.display());
+        }
+        for path in written {
+            println!("Created {}", path.display());
+        }
+        return Ok(());
+    }
 
-    let config_path = get_config_dir().join("config.toml");
-    let config = Config::load_from_file(config_path)?;
+    let config = Config::load(&get_config_dir());
 
     // Build the editor
     let mut builder = EditorBuilder::new()
         .with_config(config);
 
-    if let Some(file) = file_name {
-        builder = builder.with_file(file);
+    match args.files.first().map(String::as_str) {
+        Some("-") => builder = builder.with_text(read_stdin()?),
+        _ => builder = builder.with_files(&args.files),
+    }
+    if let Some(line) = args.line {
+        builder = builder.with_line(line);
+    }
+    if let Some(name) = &args.filetype {
+        let language = Language::from_name(name)
+            .with_context(|| format!("Unknown filetype: {name}"))?;
+        builder = builder.with_language(language);
     }
     let mut editor = builder.build().await?;
 
     // Set up error handling for the editor's run method
     let result = editor.run().await;
+    let emergency_exit = editor.take_emergency_exit();
 
-    // Always clean up terminal state, even if run_editor fails
+    // Always clean up terminal state, even if run_editor fails. Errors before this,
+    // such as a failed build, restore it when the editor's terminal guard is dropped
     if let Err(e) = editor.cleanup().await {
         log::error!("Error cleaning up terminal: {}", e);
     }
 
-    panic::set_hook(Box::new(|info| {
-        let mut stdout = stdout();
-        _ = crossterm::execute!(
-            stdout,
-            terminal::Clear(ClearType::All),
-            SetCursorStyle::DefaultUserShape,
-            cursor::Show,
-            terminal::LeaveAlternateScreen,
-        );
-        _ = terminal::disable_raw_mode();
-        log::error!("{}", info);
-    }));
+    // Killed by SIGTERM or SIGHUP, exit right away with the status of the signal
+    if let Some(exit) = emergency_exit {
+        for line in &exit.report {
+            eprintln!("{line}");
+        }
+        std::process::exit(exit.exit_code);
+    }
 
     // Return the result from run_editor
     result
@@ -77,3 +138,26 @@ fn setup_log() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["--filetype", "rust", "-"]).unwrap();
+        assert_eq!(args.files, ["-"]);
+        assert_eq!(args.filetype.as_deref(), Some("rust"));
+        assert_eq!(parse(&["--ft=x", "a.txt"]).unwrap().files, ["a.txt"]);
+        assert!(parse(&["--init-config"]).unwrap().init_config);
+        let args = parse(&["a", "+120", "b"]).unwrap();
+        assert_eq!((args.files, args.line), (vec!["a".to_string(), "b".to_string()], Some(120)));
+        assert!(parse(&["a", "-"]).is_err());
+        assert!(parse(&["+x", "a"]).is_err());
+        assert!(parse(&["--filetype"]).is_err());
+    }
+}
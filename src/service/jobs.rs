@@ -0,0 +1,221 @@
+use crate::actions::core::Executable;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// How long a finished job stays listed with its outcome
+const FINISHED_KEPT: Duration = Duration::from_secs(10);
+
+/// Flag a background task checks, or waits on, to stop early
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Waits until the token is cancelled, for racing it against the work in `select!`
+    pub async fn cancelled(&self) {
+        loop {
+            // Created before checking, so a cancel in between still wakes it
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Done(String),
+    Failed(String),
+    Cancelled,
+}
+
+/// What `:jobs` shows of a job
+#[derive(Debug, Clone)]
+pub struct JobSummary {
+    pub id: usize,
+    pub description: String,
+    pub state: JobState,
+    /// Time it ran for, or has been running
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+struct Job {
+    id: usize,
+    description: String,
+    state: JobState,
+    started: Instant,
+    finished: Option<Instant>,
+    token: CancellationToken,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    jobs: Vec<Job>,
+    /// Actions of finished jobs, run on the main loop as it can't be reached from a task
+    completions: Vec<Box<dyn Executable + Send>>,
+}
+
+/// Background operations, such as indexing the project or running `keywordprg`. A task
+/// registers when it starts and gets a handle to report how it ended, and `:jobs`
+/// lists them and cancels them through their tokens
+#[derive(Debug, Default)]
+pub struct Jobs {
+    shared: Arc<Mutex<Shared>>,
+    next_id: usize,
+    /// Row selected in the `:jobs` panel while it is open
+    pub selected: Option<usize>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&mut self, description: String) -> JobHandle {
+        self.next_id += 1;
+        let token = CancellationToken::default();
+        lock(&self.shared).jobs.push(Job {
+            id: self.next_id,
+            description,
+            state: JobState::Running,
+            started: Instant::now(),
+            finished: None,
+            token: token.clone(),
+        });
+        JobHandle {
+            id: self.next_id,
+            token,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Running jobs and those that finished a short while ago, oldest first
+    pub fn list(&self) -> Vec<JobSummary> {
+        let mut shared = lock(&self.shared);
+        shared
+            .jobs
+            .retain(|job| job.finished.is_none_or(|finished| finished.elapsed() < FINISHED_KEPT));
+        shared
+            .jobs
+            .iter()
+            .map(|job| JobSummary {
+                id: job.id,
+                description: job.description.clone(),
+                state: job.state.clone(),
+                elapsed: job.finished.unwrap_or_else(Instant::now) - job.started,
+            })
+            .collect()
+    }
+
+    pub fn is_running(&self) -> bool {
+        lock(&self.shared).jobs.iter().any(|job| job.state == JobState::Running)
+    }
+
+    /// Asks the job to stop, returning false if it isn't running
+    pub fn cancel(&self, id: usize) -> bool {
+        let shared = lock(&self.shared);
+        let job = shared.jobs.iter().find(|job| job.id == id);
+        match job {
+            Some(job) if job.state == JobState::Running => {
+                job.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn take_completions(&self) -> Vec<Box<dyn Executable + Send>> {
+        std::mem::take(&mut lock(&self.shared).completions)
+    }
+}
+
+/// Held by the task doing the work. A handle dropped without finishing, as when the
+/// task panics, marks the job failed
+#[derive(Debug)]
+pub struct JobHandle {
+    id: usize,
+    token: CancellationToken,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl JobHandle {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn finish(self, state: JobState) {
+        self.set_state(state);
+    }
+
+    /// Finishes the job, running the action on the main loop, such as showing its result
+    pub fn finish_with(self, state: JobState, action: impl Executable + Send + 'static) {
+        lock(&self.shared).completions.push(Box::new(action));
+        self.set_state(state);
+    }
+
+    fn set_state(&self, state: JobState) {
+        let mut shared = lock(&self.shared);
+        if let Some(job) = shared.jobs.iter_mut().find(|job| job.id == self.id)
+            && job.state == JobState::Running
+        {
+            job.state = state;
+            job.finished = Some(Instant::now());
+        }
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        // Does nothing once the job finished
+        self.set_state(JobState::Failed("stopped unexpectedly".to_string()));
+    }
+}
+
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    // The list stays consistent even if a task panicked while holding the lock
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_job_lifecycle() {
+        let mut jobs = Jobs::new();
+        let first = jobs.start("first".to_string());
+        let second = jobs.start("second".to_string());
+        let token = second.token();
+        assert!(jobs.is_running());
+
+        assert!(jobs.cancel(2));
+        token.cancelled().await;
+        second.finish(JobState::Cancelled);
+        assert!(!jobs.cancel(2));
+        drop(first);
+
+        let states = jobs.list().into_iter().map(|job| job.state).collect::<Vec<_>>();
+        assert_eq!(states, [
+            JobState::Failed("stopped unexpectedly".to_string()),
+            JobState::Cancelled,
+        ]);
+        assert!(!jobs.is_running());
+    }
+}
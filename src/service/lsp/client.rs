@@ -1,36 +1,44 @@
 use crate::core::document::Document;
+use crate::core::history::edit::Edit;
 use crate::core::language::Language;
 use crate::service::lsp::message_handler::{parse_notification, parse_response};
 use crate::service::lsp::messages::{lsp_receive, lsp_send, InboundMessage, OutboundMessage};
 use crate::service::lsp::params::get_initialize_params;
 use crate::service::lsp::LspAction;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use lsp_types::notification::{
     DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument, Exit,
     Notification,
 };
 use lsp_types::request::{
-    DocumentDiagnosticRequest, GotoDefinition, Initialize, Request, Shutdown,
+    CodeActionRequest, Completion, DocumentDiagnosticRequest, ExecuteCommand, Formatting,
+    GotoDefinition, HoverRequest, Initialize, References, Rename, Request, Shutdown,
 };
 use lsp_types::{
+    CodeActionContext, CodeActionParams, Command as LspCommand, CompletionParams, Diagnostic,
+    ExecuteCommandParams, Range, ReferenceContext, ReferenceParams, RenameParams,
     DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    DidSaveTextDocumentParams, DocumentDiagnosticParams, GotoDefinitionParams, Position,
+    DidSaveTextDocumentParams, DocumentDiagnosticParams, DocumentFormattingParams,
+    FormattingOptions, GotoDefinitionParams, HoverParams, HoverProviderCapability, OneOf, Position,
     ServerCapabilities, TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
-    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
-    VersionedTextDocumentIdentifier,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncSaveOptions, TextEdit, Uri, VersionedTextDocumentIdentifier,
 };
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
 use std::str::FromStr;
 use std::sync::atomic::AtomicI32;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{
     process::Stdio,
     sync::atomic::{self},
 };
 
-use crate::service::lsp::util::calculate_changes;
-use crate::service::lsp::version::VersionedContents;
+use crate::service::lsp::util::{PositionEncoding, content_changes};
+use crate::service::lsp::version::DocumentVersions;
 use tokio::process::Child;
+use tree_sitter::Point;
 use tokio::sync::Mutex;
 use tokio::{
     io::{BufReader, BufWriter},
@@ -40,6 +48,8 @@ use tokio::{
 
 static ID: AtomicI32 = AtomicI32::new(1);
 const CHANNEL_SIZE: usize = 32;
+/// Longest wait for the result of a request the editor waits for, like formatting
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
 
 pub fn next_id() -> i32 {
     ID.fetch_add(1, atomic::Ordering::SeqCst)
@@ -57,27 +67,35 @@ pub struct LspClient {
     pub(super) language: Language,
     pub(super) state: LspClientState,
     pub(super) server_capabilities: Option<ServerCapabilities>,
+    /// What the columns of positions count, as the server chose when it started
+    pub(super) position_encoding: PositionEncoding,
 
     request_sender: mpsc::Sender<OutboundMessage>,
     response_receiver: mpsc::Receiver<InboundMessage>,
     pending_responses: HashMap<i32, String>,
+    /// Messages that came while waiting for the result of a request
+    deferred: VecDeque<InboundMessage>,
 
     process: Arc<Mutex<Option<Child>>>,
 
-    versioned_contents: VersionedContents,
+    versions: DocumentVersions,
 }
 
 impl LspClient {
-    pub async fn new(language: Language, args: &[&str]) -> Result<Self> {
-        let command = language
-            .get_language_server()
-            .context("Language is not supported")?;
+    pub async fn new(language: Language, command: &str, args: &[String]) -> Result<Self> {
         let mut child = Command::new(command)
             .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .spawn()?;
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => anyhow!(
+                    "command not found: {command}, set the {} server in [lsp.servers]",
+                    language.to_str()
+                ),
+                _ => anyhow!("{command}: {e}"),
+            })?;
 
         let stdin = child.stdin.take().context("Failed to get stdin")?;
         let stdout = child.stdout.take().context("Failed to get stdout")?;
@@ -115,15 +133,17 @@ impl LspClient {
             request_sender,
             response_receiver,
             server_capabilities: None,
+            position_encoding: PositionEncoding::default(),
             pending_responses: HashMap::new(),
+            deferred: VecDeque::new(),
             process: Arc::new(Mutex::new(Some(child))),
-            versioned_contents: VersionedContents::default(),
+            versions: DocumentVersions::default(),
         })
     }
 
-    pub async fn initialize(&mut self) -> Result<()> {
+    pub async fn initialize(&mut self, init_options: Option<Value>) -> Result<()> {
         self.state = LspClientState::Initializing;
-        self.send_request::<Initialize>(get_initialize_params()?, true)
+        self.send_request::<Initialize>(get_initialize_params(init_options)?, true)
             .await?;
         Ok(())
     }
@@ -133,8 +153,7 @@ impl LspClient {
             return Ok(());
         };
 
-        self.versioned_contents
-            .update_document(&uri, document.buffer.to_string());
+        self.versions.increment(&uri);
 
         self.send_notification::<DidOpenTextDocument>(
             DidOpenTextDocumentParams {
@@ -152,14 +171,24 @@ impl LspClient {
         Ok(())
     }
 
+    /// Tells the server the document was written, with its text only when the server
+    /// asked for it
     pub async fn did_save(&mut self, document: &Document) -> Result<()> {
         let Some(path) = document.full_path_string() else {
             return Ok(());
         };
+        let sync = self.server_capabilities.as_ref().and_then(|c| c.text_document_sync.as_ref());
+        let include_text = match sync {
+            Some(TextDocumentSyncCapability::Options(options)) => matches!(
+                &options.save,
+                Some(TextDocumentSyncSaveOptions::SaveOptions(save)) if save.include_text == Some(true)
+            ),
+            _ => false,
+        };
 
         self.send_notification::<DidSaveTextDocument>(
             DidSaveTextDocumentParams {
-                text: Some(document.buffer.to_string()),
+                text: include_text.then(|| document.buffer.to_string()),
                 text_document: TextDocumentIdentifier {
                     uri: Uri::from_str(&path)?,
                 },
@@ -188,14 +217,14 @@ impl LspClient {
         Ok(())
     }
 
-    pub async fn did_change(&mut self, document: &Document) -> Result<()> {
+    /// Sends the edit made to the document, or its whole text without one, as after a
+    /// reload or when the server only syncs whole documents
+    pub async fn did_change(&mut self, document: &Document, edit: Option<&Edit>) -> Result<()> {
         let Some(uri) = document.get_uri() else {
             return Ok(());
         };
         self.request_diagnostics(document).await?;
 
-        let content = document.buffer.to_string();
-
         let sync_kind = self
             .server_capabilities
             .as_ref()
@@ -207,25 +236,26 @@ impl LspClient {
             .cloned()
             .unwrap_or(TextDocumentSyncKind::FULL);
 
-        let content_changes = match sync_kind {
-            TextDocumentSyncKind::FULL => {
+        let content_changes = match (sync_kind, edit) {
+            (TextDocumentSyncKind::INCREMENTAL, Some(edit)) => {
+                content_changes(edit, &document.buffer, self.position_encoding)
+            }
+            (TextDocumentSyncKind::FULL | TextDocumentSyncKind::INCREMENTAL, _) => {
                 vec![TextDocumentContentChangeEvent {
                     range: None,
                     range_length: None,
                     text: document.buffer.to_string(),
                 }]
             }
-            TextDocumentSyncKind::INCREMENTAL => {
-                let old_content = self.versioned_contents.get_content(&uri);
-                calculate_changes(old_content, &content)
-            }
             _ => {
                 return Ok(());
             }
         };
+        if content_changes.is_empty() {
+            return Ok(());
+        }
 
-        self.versioned_contents.update_document(&uri, content);
-        let version = self.versioned_contents.get_version(&uri);
+        let version = self.versions.increment(&uri);
 
         let params = DidChangeTextDocumentParams {
             text_document: VersionedTextDocumentIdentifier {
@@ -241,6 +271,11 @@ impl LspClient {
         Ok(())
     }
 
+    /// The position of a byte column of the document, in the units of the server
+    fn position(&self, document: &Document, line: usize, column: usize) -> Position {
+        self.position_encoding.position(&document.buffer, Point::new(line, column))
+    }
+
     pub async fn goto_definition(
         &mut self,
         document: &Document,
@@ -258,11 +293,139 @@ impl LspClient {
                     text_document: TextDocumentIdentifier {
                         uri: Uri::from_str(&uri)?,
                     },
-                    position: Position {
-                        line: line as u32,
-                        character: character as u32,
+                    position: self.position(document, line, character),
+                },
+            },
+            false,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn completion(
+        &mut self,
+        document: &Document,
+        line: usize,
+        character: usize,
+    ) -> Result<()> {
+        let Some(uri) = document.get_uri() else {
+            return Ok(());
+        };
+        self.send_request::<Completion>(
+            CompletionParams {
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Uri::from_str(&uri)?,
                     },
+                    position: self.position(document, line, character),
+                },
+                context: None,
+            },
+            false,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn references(
+        &mut self,
+        document: &Document,
+        line: usize,
+        character: usize,
+    ) -> Result<()> {
+        let Some(uri) = document.get_uri() else {
+            return Ok(());
+        };
+        self.send_request::<References>(
+            ReferenceParams {
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Uri::from_str(&uri)?,
+                    },
+                    position: self.position(document, line, character),
+                },
+                context: ReferenceContext {
+                    include_declaration: true,
+                },
+            },
+            false,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn rename(
+        &mut self,
+        document: &Document,
+        line: usize,
+        character: usize,
+        new_name: &str,
+    ) -> Result<()> {
+        let Some(uri) = document.get_uri() else {
+            return Ok(());
+        };
+        self.send_request::<Rename>(
+            RenameParams {
+                work_done_progress_params: Default::default(),
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Uri::from_str(&uri)?,
+                    },
+                    position: self.position(document, line, character),
+                },
+                new_name: new_name.to_string(),
+            },
+            false,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Asks for the code actions of the range, fixing the diagnostics given or not
+    pub async fn code_action(
+        &mut self,
+        document: &Document,
+        range: Range,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Result<()> {
+        let Some(uri) = document.get_uri() else {
+            return Ok(());
+        };
+        self.send_request::<CodeActionRequest>(
+            CodeActionParams {
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_str(&uri)?,
                 },
+                range,
+                context: CodeActionContext {
+                    diagnostics,
+                    ..Default::default()
+                },
+            },
+            false,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Runs a command of the server, as a code action may come with
+    pub async fn execute_command(&mut self, command: &LspCommand) -> Result<()> {
+        self.send_request::<ExecuteCommand>(
+            ExecuteCommandParams {
+                command: command.command.clone(),
+                arguments: command.arguments.clone().unwrap_or_default(),
+                work_done_progress_params: Default::default(),
             },
             false,
         )
@@ -271,6 +434,103 @@ impl LspClient {
         Ok(())
     }
 
+    /// Asks for the edits formatting the whole document and waits for them, None when
+    /// the server doesn't format documents
+    pub async fn formatting(
+        &mut self,
+        document: &Document,
+        options: FormattingOptions,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let Some(uri) = document.get_uri() else {
+            return Ok(None);
+        };
+
+        let can_format = self
+            .server_capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.document_formatting_provider.as_ref())
+            .is_some_and(|provider| !matches!(provider, OneOf::Left(false)));
+        if !can_format {
+            return Ok(None);
+        }
+
+        let id = self
+            .send_request::<Formatting>(
+                DocumentFormattingParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Uri::from_str(&uri)?,
+                    },
+                    options,
+                    work_done_progress_params: Default::default(),
+                },
+                false,
+            )
+            .await?;
+        // No edits come as null when nothing changes
+        let result = self.wait_for_response(id).await?;
+        let edits = serde_json::from_value::<Option<Vec<TextEdit>>>(result.unwrap_or_default())?;
+        Ok(Some(edits.unwrap_or_default()))
+    }
+
+    /// Waits for the result of the request, for an action that can't go on without it.
+    /// Messages coming before it are kept for `get_lsp_action`
+    async fn wait_for_response(&mut self, id: i32) -> Result<Option<Value>> {
+        let deadline = tokio::time::Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            let message = tokio::time::timeout_at(deadline, self.response_receiver.recv()).await;
+            let message = match message {
+                Ok(Some(message)) => message,
+                Ok(None) => return Err(anyhow!("The language server stopped")),
+                Err(_) => {
+                    self.pending_responses.remove(&id);
+                    return Err(anyhow!("The language server didn't answer in time"));
+                }
+            };
+            match message {
+                InboundMessage::Response(response) if response.id == id => {
+                    self.pending_responses.remove(&id);
+                    if let Some(error) = response.error {
+                        return Err(anyhow!(error.message));
+                    }
+                    return Ok(response.result);
+                }
+                message => self.deferred.push_back(message),
+            }
+        }
+    }
+
+    /// Asks for hover information at the position, false when the server doesn't give any
+    pub async fn hover(&mut self, document: &Document, line: usize, character: usize) -> Result<bool> {
+        let Some(uri) = document.get_uri() else {
+            return Ok(false);
+        };
+
+        let can_hover = self
+            .server_capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.hover_provider.as_ref())
+            .is_some_and(|provider| !matches!(provider, HoverProviderCapability::Simple(false)));
+        if !can_hover {
+            return Ok(false);
+        }
+
+        self.send_request::<HoverRequest>(
+            HoverParams {
+                work_done_progress_params: Default::default(),
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Uri::from_str(&uri)?,
+                    },
+                    position: self.position(document, line, character),
+                },
+            },
+            false,
+        )
+        .await?;
+
+        Ok(true)
+    }
+
     pub async fn request_diagnostics(&mut self, document: &Document) -> Result<Option<i32>> {
         let Some(uri) = document.get_uri() else {
             return Ok(None);
@@ -344,8 +604,12 @@ impl LspClient {
     }
 
     pub async fn get_lsp_action(&mut self) -> Result<Option<LspAction>> {
-        let Ok(message) = self.response_receiver.try_recv() else {
-            return Ok(None);
+        let message = match self.deferred.pop_front() {
+            Some(message) => message,
+            None => match self.response_receiver.try_recv() {
+                Ok(message) => message,
+                Err(_) => return Ok(None),
+            },
         };
 
         let handler = match message {
@@ -423,6 +687,7 @@ impl LspClient {
 
 #[cfg(test)]
 mod tests {
+    use crate::service::lsp::util::uri_to_path;
     use lsp_types::Uri;
     use std::str::FromStr;
 
@@ -431,5 +696,8 @@ mod tests {
         let uri = Uri::from_str("file:///tmp/sample").unwrap();
         assert!(uri.is_absolute());
         assert_eq!(uri.to_string(), "file:///tmp/sample");
+
+        let uri = Uri::from_str("file:///tmp/my%20notes/%C3%A9t%C3%A9.rs").unwrap();
+        assert_eq!(uri_to_path(&uri), std::path::PathBuf::from("/tmp/my notes/été.rs"));
     }
 }
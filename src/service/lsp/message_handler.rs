@@ -1,13 +1,13 @@
-use std::path::PathBuf;
-
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
-use lsp_types::request::DocumentDiagnosticRequest;
+use lsp_types::request::{
+    CodeActionRequest, Completion, DocumentDiagnosticRequest, HoverRequest, References, Rename,
+};
 use lsp_types::{
-    notification::{Initialized, Notification, PublishDiagnostics}, request::{Initialize, Request}, DocumentDiagnosticReport, GotoDefinitionResponse,
+    notification::{Initialized, Notification, PublishDiagnostics}, request::{Initialize, Request}, CodeActionOrCommand, CompletionResponse, DocumentDiagnosticReport, Hover, GotoDefinitionResponse,
     InitializeResult, InitializedParams,
     Location,
-    PublishDiagnosticsParams,
+    PublishDiagnosticsParams, WorkspaceEdit,
 };
 use serde_json::Value;
 
@@ -15,7 +15,8 @@ use crate::{
     service::lsp::{
         client::{LspClient, LspClientState},
         messages::InboundNotification,
-        LspAction,
+        util::PositionEncoding,
+        uri_to_path, LspAction,
     },
 };
 use crate::actions::{buffer, lsp, movement};
@@ -54,6 +55,7 @@ impl LspMessageHandler for UnknownResponse {
 impl LspMessageHandler for InitializeResult {
     async fn handle_client(&self, client: &mut LspClient) -> Result<()> {
         client.server_capabilities = Some(self.capabilities.clone());
+        client.position_encoding = PositionEncoding::new(self.capabilities.position_encoding.as_ref());
         client
             .send_notification::<Initialized>(InitializedParams {}, true)
             .await?;
@@ -77,18 +79,49 @@ impl LspMessageHandler for GotoDefinitionResponse {
 
         let mut action = CompositeExecutable::new();
 
-        action.add(buffer::OpenBuffer::new(PathBuf::from(location.uri.as_str())));
+        action.add(movement::RecordJump);
+        action.add(buffer::OpenBuffer::new(uri_to_path(&location.uri)));
 
-        let position = location.range.start;
-        action.add(movement::GoToPosition::new(
-            position.line as usize,
-            position.character as usize,
-        ));
+        action.add(movement::GoToPosition::checked(location.range.start));
 
         Some(Box::new(action))
     }
 }
 
+impl LspMessageHandler for CompletionResponse {
+    fn get_lsp_action(&self) -> Option<LspAction> {
+        let items = match self {
+            CompletionResponse::Array(items) => items.clone(),
+            CompletionResponse::List(list) => list.items.clone(),
+        };
+        Some(Box::new(lsp::ShowCompletions::new(items)))
+    }
+}
+
+impl LspMessageHandler for Hover {
+    fn get_lsp_action(&self) -> Option<LspAction> {
+        Some(Box::new(lsp::ShowHover::new(self.contents.clone())))
+    }
+}
+
+impl LspMessageHandler for Vec<Location> {
+    fn get_lsp_action(&self) -> Option<LspAction> {
+        Some(Box::new(lsp::ShowReferences::new(self.clone())))
+    }
+}
+
+impl LspMessageHandler for WorkspaceEdit {
+    fn get_lsp_action(&self) -> Option<LspAction> {
+        Some(Box::new(lsp::ApplyWorkspaceEdit::new(self.clone())))
+    }
+}
+
+impl LspMessageHandler for Vec<CodeActionOrCommand> {
+    fn get_lsp_action(&self) -> Option<LspAction> {
+        Some(Box::new(lsp::ShowCodeActions::new(self.clone())))
+    }
+}
+
 impl LspMessageHandler for DocumentDiagnosticReport {
     fn get_lsp_action(&self) -> Option<LspAction> {
         match self {
@@ -104,6 +137,13 @@ impl LspMessageHandler for DocumentDiagnosticReport {
 pub fn parse_response(method: &str, result: Value) -> Result<Box<dyn LspMessageHandler>> {
     let handler: Box<dyn LspMessageHandler> = match method {
         Initialize::METHOD => Box::new(serde_json::from_value::<InitializeResult>(result)?),
+        Completion::METHOD => Box::new(serde_json::from_value::<CompletionResponse>(result)?),
+        HoverRequest::METHOD => Box::new(serde_json::from_value::<Hover>(result)?),
+        References::METHOD => Box::new(serde_json::from_value::<Vec<Location>>(result)?),
+        Rename::METHOD => Box::new(serde_json::from_value::<WorkspaceEdit>(result)?),
+        CodeActionRequest::METHOD => {
+            Box::new(serde_json::from_value::<Vec<CodeActionOrCommand>>(result)?)
+        }
         DocumentDiagnosticRequest::METHOD => {
             Box::new(serde_json::from_value::<DocumentDiagnosticReport>(result)?)
         }
@@ -5,8 +5,9 @@ mod params;
 mod util;
 mod version;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use crate::config::editor::LanguageServer;
 use crate::core::language::Language;
 use crate::service::lsp::client::LspClientState;
 use anyhow::Result;
@@ -14,12 +15,16 @@ use lsp_types::Diagnostic;
 use crate::actions::core::Executable;
 
 pub(crate) use crate::service::lsp::client::LspClient;
+pub(crate) use crate::service::lsp::util::{PositionEncoding, uri_to_path};
 
 #[derive(Debug, Default)]
 pub struct LspService {
     client: Option<LspClient>,
     diagnostics: HashMap<String, Vec<Diagnostic>>,
+    diagnostics_generation: usize,
     enabled: bool,
+    /// Languages whose server failed to start, not tried again until a restart
+    failed: HashSet<Language>,
 }
 
 type LspAction = Box<dyn Executable>;
@@ -29,7 +34,9 @@ impl LspService {
         Self {
             client: None,
             diagnostics: HashMap::new(),
+            diagnostics_generation: 0,
             enabled: true,
+            failed: HashSet::new(),
         }
     }
 
@@ -48,11 +55,23 @@ impl LspService {
         self.enabled = enabled;
     }
 
+    /// What the columns of the positions of the running server count
+    pub fn position_encoding(&self) -> PositionEncoding {
+        self.client.as_ref().map_or_else(PositionEncoding::default, |client| client.position_encoding)
+    }
+
     pub fn is_running(&self) -> bool {
         self.client.is_some()
     }
 
-    pub async fn start_server(&mut self, language: Language) -> Result<Option<&mut LspClient>> {
+    /// Starts the server of the language, the configured one before the builtin one,
+    /// stopping the server of another language. Fails with the reason the first time the
+    /// server doesn't start, the next times there's no server
+    pub async fn start_server(
+        &mut self,
+        language: Language,
+        server: Option<&LanguageServer>,
+    ) -> Result<Option<&mut LspClient>> {
         if !self.enabled {
             return Ok(None);
         }
@@ -66,12 +85,26 @@ impl LspService {
             }
         }
 
-        let Ok(mut client) = LspClient::new(language, &[]).await else {
+        let command = match server {
+            Some(server) if !server.enabled => None,
+            Some(server) => server.command.as_deref().or(language.get_language_server()),
+            None => language.get_language_server(),
+        };
+        let Some(command) = command.filter(|_| !self.failed.contains(&language)) else {
             self.shutdown().await?;
             return Ok(None);
         };
+        let args = server.map_or(&[][..], |server| &server.args);
+        let mut client = match LspClient::new(language, command, args).await {
+            Ok(client) => client,
+            Err(e) => {
+                self.shutdown().await?;
+                self.failed.insert(language);
+                return Err(e);
+            }
+        };
 
-        client.initialize().await?;
+        client.initialize(server.and_then(|server| server.init_options.clone())).await?;
 
         while client.state != LspClientState::Initialized {
             let _ = client.get_lsp_action().await?;
@@ -90,13 +123,18 @@ impl LspService {
         Ok(())
     }
 
-    pub async fn restart(&mut self, language: Language) -> Result<Option<&mut LspClient>> {
+    pub async fn restart(
+        &mut self,
+        language: Language,
+        server: Option<&LanguageServer>,
+    ) -> Result<Option<&mut LspClient>> {
         // Shutdown existing client
         self.shutdown().await?;
 
         // Enable and start new client
         self.enabled = true;
-        self.start_server(language).await
+        self.failed.clear();
+        self.start_server(language, server).await
     }
 
     pub fn get_diagnostics(&self, uri: &str) -> &[Diagnostic] {
@@ -106,7 +144,53 @@ impl LspService {
             .unwrap_or_default()
     }
 
-    pub fn update_diagnostics(&mut self, path: &str, diagnostics: Vec<Diagnostic>) {
+    /// The diagnostics of every document the server reported on, by URI
+    pub fn all_diagnostics(&self) -> impl Iterator<Item = (&str, &[Diagnostic])> {
+        self.diagnostics
+            .iter()
+            .map(|(uri, diagnostics)| (uri.as_str(), diagnostics.as_slice()))
+    }
+
+    /// Incremented every time diagnostics are replaced, so renderers can cache derived data
+    pub fn diagnostics_generation(&self) -> usize {
+        self.diagnostics_generation
+    }
+
+    /// Keeps the diagnostics of the document in the order they appear in it
+    pub fn update_diagnostics(&mut self, path: &str, mut diagnostics: Vec<Diagnostic>) {
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start.line, diagnostic.range.start.character));
         self.diagnostics.insert(path.to_string(), diagnostics);
+        self.diagnostics_generation = self.diagnostics_generation.wrapping_add(1);
+    }
+
+    pub fn clear_diagnostics(&mut self, uri: &str) {
+        if self.diagnostics.remove(uri).is_some() {
+            self.diagnostics_generation = self.diagnostics_generation.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_missing_server_fails_once() {
+        let mut service = LspService::new();
+        let server = LanguageServer {
+            command: Some("viron-missing-server".to_string()),
+            args: Vec::new(),
+            init_options: None,
+            enabled: true,
+        };
+        let error = service.start_server(Language::Rust, Some(&server)).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "command not found: viron-missing-server, set the rust server in [lsp.servers]"
+        );
+        assert!(service.start_server(Language::Rust, Some(&server)).await.unwrap().is_none());
+
+        let disabled = LanguageServer { enabled: false, ..server };
+        assert!(service.restart(Language::Rust, Some(&disabled)).await.unwrap().is_none());
     }
 }
@@ -1,8 +1,13 @@
 use anyhow::Result;
 use lsp_types::{
-    ClientCapabilities, ClientInfo, GotoCapability, InitializeParams,
-    TextDocumentClientCapabilities, Uri, WorkspaceFolder,
+    ClientCapabilities, ClientInfo, CodeActionClientCapabilities, CodeActionKind,
+    CodeActionKindLiteralSupport, CodeActionLiteralSupport, CompletionClientCapabilities,
+    CompletionItemCapability, DocumentFormattingClientCapabilities, GeneralClientCapabilities,
+    GotoCapability, HoverClientCapabilities, InitializeParams, MarkupKind, PositionEncodingKind,
+    ReferenceClientCapabilities, RenameClientCapabilities, TextDocumentClientCapabilities, Uri,
+    WorkspaceFolder,
 };
+use serde_json::Value;
 use std::str::FromStr;
 
 fn get_workspace() -> Result<WorkspaceFolder> {
@@ -19,13 +24,52 @@ fn get_workspace() -> Result<WorkspaceFolder> {
     })
 }
 
-pub fn get_initialize_params() -> Result<InitializeParams> {
+pub fn get_initialize_params(init_options: Option<Value>) -> Result<InitializeParams> {
     let client_capabilities = ClientCapabilities {
+        // Bytes first, which need no converting, when the server takes them
+        general: Some(GeneralClientCapabilities {
+            position_encodings: Some(vec![PositionEncodingKind::UTF8, PositionEncodingKind::UTF16]),
+            ..Default::default()
+        }),
         text_document: Some(TextDocumentClientCapabilities {
             definition: Some(GotoCapability {
                 link_support: Some(false),
                 dynamic_registration: Some(true),
             }),
+            references: Some(ReferenceClientCapabilities::default()),
+            rename: Some(RenameClientCapabilities::default()),
+            formatting: Some(DocumentFormattingClientCapabilities::default()),
+            // Without literal support servers answer with bare commands instead of edits
+            code_action: Some(CodeActionClientCapabilities {
+                code_action_literal_support: Some(CodeActionLiteralSupport {
+                    code_action_kind: CodeActionKindLiteralSupport {
+                        value_set: [
+                            CodeActionKind::EMPTY,
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::REFACTOR,
+                            CodeActionKind::REFACTOR_EXTRACT,
+                            CodeActionKind::REFACTOR_INLINE,
+                            CodeActionKind::REFACTOR_REWRITE,
+                            CodeActionKind::SOURCE,
+                            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        ]
+                        .map(|kind| kind.as_str().to_string())
+                        .to_vec(),
+                    },
+                }),
+                ..Default::default()
+            }),
+            hover: Some(HoverClientCapabilities {
+                content_format: Some(vec![MarkupKind::Markdown, MarkupKind::PlainText]),
+                ..Default::default()
+            }),
+            completion: Some(CompletionClientCapabilities {
+                completion_item: Some(CompletionItemCapability {
+                    snippet_support: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
             ..Default::default()
         }),
         ..Default::default()
@@ -39,6 +83,7 @@ pub fn get_initialize_params() -> Result<InitializeParams> {
         }),
         capabilities: client_capabilities,
         workspace_folders: Some(vec![get_workspace()?]),
+        initialization_options: init_options,
         ..Default::default()
     })
 }
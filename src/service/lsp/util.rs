@@ -1,121 +1,195 @@
-use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
-use similar::{Algorithm, DiffOp, TextDiff};
-
-pub fn calculate_changes(old_text: &str, new_text: &str) -> Vec<TextDocumentContentChangeEvent> {
-    let diff = TextDiff::configure()
-        .algorithm(Algorithm::Myers)
-        .timeout(std::time::Duration::from_secs(1))
-        .diff_chars(old_text, new_text);
-
-    let mut changes = Vec::new();
-    let mut current_change = String::new();
-    let mut start_offset = 0;
-    let mut old_offset = 0;
-
-    for op in diff.grouped_ops(3).iter().flatten() {
-        match op {
-            DiffOp::Equal { old_index, len, .. } => {
-                flush_insert(old_text, &mut changes, &mut current_change, start_offset);
-                old_offset = old_index + len;
-            }
-            DiffOp::Delete {
-                old_index, old_len, ..
-            } => {
-                flush_insert(old_text, &mut changes, &mut current_change, start_offset);
-                let start_pos = calculate_position(old_text, *old_index);
-                let end_pos = calculate_position(old_text, old_index + old_len);
-                changes.push(TextDocumentContentChangeEvent {
-                    range: Some(Range {
-                        start: start_pos,
-                        end: end_pos,
-                    }),
-                    range_length: None,
-                    text: String::new(),
-                });
-
-                start_offset = old_index + old_len;
-                old_offset = old_index + old_len;
-            }
-            DiffOp::Insert {
-                new_index, new_len, ..
-            } => {
-                if current_change.is_empty() {
-                    start_offset = old_offset;
-                }
-                let text = new_text
-                    .chars()
-                    .skip(*new_index)
-                    .take(*new_len)
-                    .collect::<String>();
-                current_change.push_str(&text);
+use crate::core::buffer::{Buffer, utf16_to_byte_column};
+use crate::core::history::edit::Edit;
+use lsp_types::{Position, PositionEncodingKind, Range, TextDocumentContentChangeEvent, Uri};
+use std::path::PathBuf;
+use tree_sitter::Point;
+
+/// The path of a `file://` URI, with its percent escapes decoded
+pub fn uri_to_path(uri: &Uri) -> PathBuf {
+    let uri = uri.as_str();
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail.get(..2).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
             }
-            DiffOp::Replace {
-                old_index,
-                old_len,
-                new_index,
-                new_len,
-            } => {
-                flush_insert(old_text, &mut changes, &mut current_change, start_offset);
-                let start_pos = calculate_position(old_text, *old_index);
-                let end_pos = calculate_position(old_text, old_index + old_len);
-                let text = new_text
-                    .chars()
-                    .skip(*new_index)
-                    .take(*new_len)
-                    .collect::<String>();
-                changes.push(TextDocumentContentChangeEvent {
-                    range: Some(Range {
-                        start: start_pos,
-                        end: end_pos,
-                    }),
-                    range_length: None,
-                    text,
-                });
-                start_offset = old_index + old_len;
-                old_offset = old_index + old_len;
+            _ => {
+                bytes.push(byte);
+                rest = tail;
             }
         }
     }
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// What the columns of LSP positions count. Points of the buffer count bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    /// Bytes, when the server takes them
+    Utf8,
+    /// UTF-16 code units, what servers count unless they say otherwise
+    #[default]
+    Utf16,
+}
+
+impl PositionEncoding {
+    /// The encoding the server chose, from those the editor offered
+    pub fn new(kind: Option<&PositionEncodingKind>) -> Self {
+        match kind {
+            Some(kind) if *kind == PositionEncodingKind::UTF8 => Self::Utf8,
+            _ => Self::Utf16,
+        }
+    }
 
-    flush_insert(old_text, &mut changes, &mut current_change, start_offset);
+    /// Units of the text
+    pub fn len(self, text: &str) -> u32 {
+        match self {
+            Self::Utf8 => text.len() as u32,
+            Self::Utf16 => text.chars().map(|c| c.len_utf16() as u32).sum(),
+        }
+    }
+
+    /// Byte column of the column of a position on the line
+    pub fn byte_column(self, line: &str, character: u32) -> usize {
+        match self {
+            Self::Utf8 => character as usize,
+            Self::Utf16 => utf16_to_byte_column(line, character as usize),
+        }
+    }
 
+    /// The position of a point of the buffer
+    pub fn position(self, buffer: &Buffer, point: Point) -> Position {
+        let character = match self {
+            Self::Utf8 => point.column,
+            Self::Utf16 => buffer.byte_column_to_utf16(point.row, point.column),
+        };
+        Position::new(point.row as u32, character as u32)
+    }
+
+    /// The point of a position in the buffer
+    pub fn point(self, buffer: &Buffer, position: Position) -> Point {
+        let (row, character) = (position.line as usize, position.character as usize);
+        match self {
+            Self::Utf8 => Point::new(row, character),
+            Self::Utf16 => Point::new(row, buffer.utf16_to_byte_column(row, character)),
+        }
+    }
+}
+
+/// The change events of an edit made to the buffer, which has it already. Columns that
+/// don't count bytes are measured on the line as it was right before each edit
+pub fn content_changes(
+    edit: &Edit,
+    buffer: &Buffer,
+    encoding: PositionEncoding,
+) -> Vec<TextDocumentContentChangeEvent> {
+    let mut edits = Vec::new();
+    flatten(edit, &mut edits);
+    if let [edit] = edits.as_slice() {
+        // The text before a single edit is the same after it
+        let (start_byte, start_point) = start_of(edit);
+        let before = buffer.get_string(start_byte - start_point.column, start_point.column);
+        return vec![change_event(edit, &before, encoding)];
+    }
+
+    // Edits of a group are taken back last to first to find the text before each. The
+    // text ahead of the first line they change stays the same, so it isn't copied
+    let line_starts = edits.iter().map(|edit| start_of(edit)).map(|(byte, point)| byte - point.column);
+    let offset = line_starts.min().unwrap_or(0);
+    let mut text = buffer.get_string(offset, buffer.len_bytes() - offset);
+    let mut changes = Vec::with_capacity(edits.len());
+    for edit in edits.iter().rev() {
+        let (start_byte, start_point) = start_of(edit);
+        let start_byte = start_byte - offset;
+        match edit {
+            Edit::Insert(insert) => text.replace_range(start_byte..start_byte + insert.text.len(), ""),
+            Edit::Delete(delete) => text.insert_str(start_byte, &delete.text),
+            Edit::Group(_) => {}
+        }
+        let before = &text[start_byte - start_point.column..start_byte];
+        changes.push(change_event(edit, before, encoding));
+    }
+    changes.reverse();
     changes
 }
 
-fn flush_insert(
-    old_text: &str,
-    changes: &mut Vec<TextDocumentContentChangeEvent>,
-    current_change: &mut String,
-    start_offset: usize,
-) {
-    if !current_change.is_empty() {
-        let start_pos = calculate_position(old_text, start_offset);
-        changes.push(TextDocumentContentChangeEvent {
-            range: Some(Range {
-                start: start_pos,
-                end: start_pos,
-            }),
-            range_length: None,
-            text: std::mem::take(current_change),
-        });
+fn flatten<'a>(edit: &'a Edit, edits: &mut Vec<&'a Edit>) {
+    match edit {
+        Edit::Group(group) => group.iter().for_each(|edit| flatten(edit, edits)),
+        edit => edits.push(edit),
     }
 }
 
-fn calculate_position(text: &str, offset: usize) -> Position {
-    let mut line = 0u32;
-    let mut character = 0u32;
+fn start_of(edit: &Edit) -> (usize, Point) {
+    match edit {
+        Edit::Insert(insert) => (insert.start_byte, insert.start_point),
+        Edit::Delete(delete) => (delete.start_byte, delete.start_point),
+        Edit::Group(_) => unreachable!("groups are flattened"),
+    }
+}
 
-    for (i, c) in text.char_indices() {
-        if i >= offset {
-            break;
-        }
-        if c == '\n' {
-            line += 1;
-            character = 0;
-        } else {
-            character += 1;
-        }
+/// The change event of an insert or delete, with the text of its line before its start
+fn change_event(edit: &Edit, before: &str, encoding: PositionEncoding) -> TextDocumentContentChangeEvent {
+    let (_, point) = start_of(edit);
+    let start = Position {
+        line: point.row as u32,
+        character: encoding.len(before),
+    };
+    let (end, text) = match edit {
+        Edit::Delete(delete) => (end_position(start, &delete.text, encoding), String::new()),
+        Edit::Insert(insert) => (start, insert.text.clone()),
+        Edit::Group(_) => unreachable!("groups are flattened"),
+    };
+    TextDocumentContentChangeEvent {
+        range: Some(Range { start, end }),
+        range_length: None,
+        text,
+    }
+}
+
+/// Where the text ends when it starts at the position
+fn end_position(start: Position, text: &str, encoding: PositionEncoding) -> Position {
+    match text.rsplit_once('\n') {
+        Some((before, last)) => Position {
+            line: start.line + before.matches('\n').count() as u32 + 1,
+            character: encoding.len(last),
+        },
+        None => Position {
+            line: start.line,
+            character: start.character + encoding.len(text),
+        },
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Position { line, character }
+    #[test]
+    fn test_content_changes_count_utf16() {
+        // "é" is two bytes and one unit, "😀" four bytes and two units
+        let buffer = Buffer::from_string("é😀x\nab\n");
+        let insert = Edit::insert(6, Point::new(0, 6), "x".to_string(), Point::default(), Point::default());
+        let range = |(line, character), (end_line, end_character)| {
+            Some(Range {
+                start: Position { line, character },
+                end: Position { line: end_line, character: end_character },
+            })
+        };
+        let changes = content_changes(&insert, &buffer, PositionEncoding::Utf16);
+        assert_eq!((changes[0].range, changes[0].text.as_str()), (range((0, 3), (0, 3)), "x"));
+
+        // "é😀yz\nab\n" had "yz\na" replaced by "x\na" in two edits
+        let group = Edit::Group(vec![
+            Edit::delete(6, Point::new(0, 6), "yz\na".to_string(), Point::default(), Point::default()),
+            Edit::insert(6, Point::new(0, 6), "x\na".to_string(), Point::default(), Point::default()),
+        ]);
+        let changes = content_changes(&group, &buffer, PositionEncoding::Utf16);
+        assert_eq!((changes[0].range, changes[0].text.as_str()), (range((0, 3), (1, 1)), ""));
+        assert_eq!((changes[1].range, changes[1].text.as_str()), (range((0, 3), (0, 3)), "x\na"));
+    }
 }
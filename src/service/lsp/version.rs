@@ -1,23 +1,16 @@
 use std::collections::HashMap;
 
+/// Versions of the open documents, counted up with each change sent
 #[derive(Debug, Default)]
-pub struct VersionedContents {
-    contents: HashMap<String, String>,
+pub struct DocumentVersions {
     versions: HashMap<String, i32>,
 }
 
-impl VersionedContents {
-    pub fn update_document(&mut self, uri: &str, content: String) {
-        self.contents.insert(uri.to_string(), content);
+impl DocumentVersions {
+    /// Counts up the version of the document, returning the new one
+    pub fn increment(&mut self, uri: &str) -> i32 {
         let version = self.versions.entry(uri.to_string()).or_insert(0);
         *version += 1;
-    }
-
-    pub fn get_version(&self, uri: &str) -> i32 {
-        self.versions.get(uri).cloned().unwrap_or_default()
-    }
-
-    pub fn get_content(&self, uri: &str) -> &str {
-        self.contents.get(uri).map(String::as_str).unwrap_or("")
+        *version
     }
 }
@@ -1,2 +1,5 @@
+pub mod jobs;
 pub mod lsp;
+pub mod symbols;
 pub use lsp::LspService;
+pub use symbols::SymbolIndex;
@@ -0,0 +1,419 @@
+use crate::core::buffer::Buffer;
+use crate::core::language::Language;
+use anyhow::{Result, anyhow};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use crate::service::jobs::{CancellationToken, JobHandle, JobState};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+use tree_sitter::{Node, Parser, Query, QueryCursor, StreamingIterator};
+
+/// Most symbols kept, so indexing a huge directory stays bounded in memory
+const MAX_SYMBOLS: usize = 200_000;
+/// Most files looked at in one walk of the project
+const MAX_VISITED_FILES: usize = 100_000;
+/// Larger files are most likely generated and are skipped
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Bytes read from the start of a file to tell if it is binary
+const BINARY_CHECK_SIZE: usize = 8 * 1024;
+/// Directories holding build output or dependencies rather than project code
+const SKIPPED_DIRECTORIES: [&str; 2] = ["target", "node_modules"];
+
+/// Definition found in a file of the project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// Kind given by the tags query, such as `function` or `class`
+    pub kind: String,
+    pub path: PathBuf,
+    pub row: usize,
+    /// Character column of the name
+    pub column: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    files: HashMap<PathBuf, Vec<Symbol>>,
+    count: usize,
+    /// Set when files were left out because the index was full
+    full: bool,
+}
+
+impl Entries {
+    /// Replaces the symbols of the file, returning false if they don't fit
+    fn replace(&mut self, path: &Path, symbols: Vec<Symbol>) -> bool {
+        let old_count = self.files.get(path).map_or(0, Vec::len);
+        if self.count - old_count + symbols.len() > MAX_SYMBOLS {
+            self.full = true;
+            return false;
+        }
+        self.count = self.count - old_count + symbols.len();
+        if symbols.is_empty() {
+            self.files.remove(path);
+        } else {
+            self.files.insert(path.to_path_buf(), symbols);
+        }
+        true
+    }
+}
+
+/// Definitions of the project found with the tags queries of the tree-sitter grammars,
+/// for going to a definition without a language server. The project is indexed on a
+/// blocking thread, and files are indexed again when they are written
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    entries: Arc<Mutex<Entries>>,
+    cancelled: CancellationToken,
+    task: Option<JoinHandle<()>>,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts indexing the files under the root, replacing any index being built. The
+    /// job is cancelled along with the index, or from `:jobs`
+    pub fn start(&mut self, root: PathBuf, job: JobHandle) {
+        self.cancel();
+        // A cancelled walk may still be finishing a file, so it keeps its own entries
+        self.cancelled = job.token();
+        self.entries = Arc::new(Mutex::new(Entries::default()));
+
+        let entries = Arc::clone(&self.entries);
+        let cancelled = job.token();
+        self.task = Some(tokio::task::spawn_blocking(move || {
+            let mut walk = Walk {
+                entries,
+                cancelled,
+                taggers: HashMap::new(),
+                visited: 0,
+            };
+            walk.directory(&root);
+            let state = match walk.cancelled.is_cancelled() {
+                true => JobState::Cancelled,
+                false => JobState::Done(format!("{} symbols", walk.lock().count)),
+            };
+            job.finish(state);
+        }));
+    }
+
+    /// Stops the indexing in progress, keeping the files indexed so far
+    pub fn cancel(&mut self) {
+        self.cancelled.cancel();
+        self.task = None;
+    }
+
+    pub fn is_indexing(&self) -> bool {
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    /// Returns true if files were left out because the index was full
+    pub fn is_full(&self) -> bool {
+        self.lock().full
+    }
+
+    #[cfg(test)]
+    pub async fn wait(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.await.unwrap();
+        }
+    }
+
+    /// Indexes the text written to the file again, reading the chunks of the buffer
+    pub fn refresh(&self, path: &Path, buffer: &Buffer) {
+        let symbols = Tagger::new(Language::from_path(path))
+            .map(|mut tagger| tagger.symbols(path, &Chunks::new(buffer.chunks())))
+            .unwrap_or_default();
+        self.lock().replace(path, symbols);
+    }
+
+    /// Definitions with the name, ordered by file and line
+    pub fn find(&self, name: &str) -> Vec<Symbol> {
+        self.collect(|symbol| symbol.name == name)
+    }
+
+    /// Definitions whose name contains the query, ignoring case
+    pub fn search(&self, query: &str) -> Vec<Symbol> {
+        let query = query.to_lowercase();
+        self.collect(|symbol| symbol.name.to_lowercase().contains(&query))
+    }
+
+    fn collect(&self, filter: impl Fn(&Symbol) -> bool) -> Vec<Symbol> {
+        let mut symbols = self
+            .lock()
+            .files
+            .values()
+            .flatten()
+            .filter(|symbol| filter(symbol))
+            .cloned()
+            .collect::<Vec<_>>();
+        symbols.sort_by(|a, b| (&a.path, a.row).cmp(&(&b.path, b.row)));
+        symbols
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        // The data stays consistent even if a walk panicked while holding the lock
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for SymbolIndex {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Walk of the project on the indexing thread
+struct Walk {
+    entries: Arc<Mutex<Entries>>,
+    cancelled: CancellationToken,
+    /// Parsers made so far, none for languages without a tags query
+    taggers: HashMap<Language, Option<Tagger>>,
+    visited: usize,
+}
+
+impl Walk {
+    /// Indexes the directory, returning false once the walk should stop
+    fn directory(&mut self, directory: &Path) -> bool {
+        let Ok(read_dir) = std::fs::read_dir(directory) else {
+            return true;
+        };
+        let mut paths = read_dir
+            .flatten()
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        for path in paths {
+            if self.cancelled.is_cancelled() || self.visited >= MAX_VISITED_FILES {
+                return false;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+            // Symlinks are left out so a link to a parent directory can't loop
+            let Ok(metadata) = path.symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if !SKIPPED_DIRECTORIES.contains(&name.as_ref()) && !self.directory(&path) {
+                    return false;
+                }
+            } else if metadata.is_file() {
+                self.visited += 1;
+                if metadata.len() <= MAX_FILE_SIZE && !self.file(&path) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Indexes the file, returning false once the index is full
+    fn file(&mut self, path: &Path) -> bool {
+        let language = Language::from_path(path);
+        let tagger = self
+            .taggers
+            .entry(language)
+            .or_insert_with(|| Tagger::new(language).ok());
+        let Some(tagger) = tagger else {
+            return true;
+        };
+        let Ok(text) = read_text(path) else {
+            return true;
+        };
+
+        let symbols = tagger.symbols(path, &Chunks::new(std::iter::once(text.as_bytes())));
+        self.lock().replace(path, symbols)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Reads a file that isn't binary, telling them apart by a null byte near the start
+fn read_text(path: &Path) -> Result<String> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?.read_to_end(&mut bytes)?;
+    if bytes.iter().take(BINARY_CHECK_SIZE).any(|&byte| byte == 0) {
+        return Err(anyhow!("{} is binary", path.display()));
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Text in pieces, as a buffer keeps it, read without joining them
+struct Chunks<'a> {
+    chunks: Vec<&'a [u8]>,
+    /// Offset of each chunk in the text
+    starts: Vec<usize>,
+}
+
+impl<'a> Chunks<'a> {
+    fn new(chunks: impl Iterator<Item = &'a [u8]>) -> Self {
+        let chunks = chunks.filter(|chunk| !chunk.is_empty()).collect::<Vec<_>>();
+        let starts = chunks
+            .iter()
+            .scan(0, |offset, chunk| {
+                let start = *offset;
+                *offset += chunk.len();
+                Some(start)
+            })
+            .collect();
+        Self { chunks, starts }
+    }
+
+    /// The rest of the chunk holding the byte, empty past the end. Tree-sitter decodes
+    /// the text a piece at a time, so a character split between two chunks is completed
+    /// from the next one
+    fn from(&self, byte: usize) -> Cow<'a, [u8]> {
+        let index = self.starts.partition_point(|&start| start <= byte).saturating_sub(1);
+        let (Some(chunk), Some(&start)) = (self.chunks.get(index), self.starts.get(index)) else {
+            return Cow::Borrowed(&[]);
+        };
+        let rest = chunk.get(byte - start..).unwrap_or_default();
+        match std::str::from_utf8(rest) {
+            Err(e) if e.error_len().is_none() => {
+                let width = (rest[e.valid_up_to()].leading_ones() as usize).max(1);
+                let end = start + chunk.len();
+                let missing = width.saturating_sub(rest.len() - e.valid_up_to());
+                let mut joined = rest.to_vec();
+                joined.extend(self.range(end..end + missing).flatten());
+                Cow::Owned(joined)
+            }
+            _ => Cow::Borrowed(rest),
+        }
+    }
+
+    /// Pieces of the text in the range
+    fn range(&self, range: Range<usize>) -> impl Iterator<Item = &'a [u8]> {
+        let first = self.starts.partition_point(|&start| start <= range.start).saturating_sub(1);
+        self.chunks[first..]
+            .iter()
+            .zip(&self.starts[first..])
+            .take_while(move |&(_, &start)| start < range.end)
+            .map(move |(chunk, &start)| {
+                let end = (range.end - start).min(chunk.len());
+                &chunk[range.start.saturating_sub(start)..end]
+            })
+    }
+
+    fn string(&self, range: Range<usize>) -> String {
+        String::from_utf8_lossy(&self.range(range).collect::<Vec<_>>().concat()).into_owned()
+    }
+}
+
+/// Parser and tags query of a language
+struct Tagger {
+    parser: Parser,
+    query: Query,
+}
+
+impl Tagger {
+    fn new(language: Language) -> Result<Self> {
+        let (Some(ts_language), Some(query_src)) =
+            (language.get_tree_sitter_language(), language.get_tags_query())
+        else {
+            return Err(anyhow!("{} has no tags query", language.to_str()));
+        };
+        let mut parser = Parser::new();
+        parser.set_language(&ts_language)?;
+        let query = Query::new(&ts_language, query_src)?;
+        Ok(Self { parser, query })
+    }
+
+    fn symbols(&mut self, path: &Path, text: &Chunks) -> Vec<Symbol> {
+        let Some(tree) = self.parser.parse_with_options(&mut |byte, _| text.from(byte), None, None) else {
+            return Vec::new();
+        };
+        let capture_names = self.query.capture_names();
+        let mut symbols = Vec::new();
+        // A name matched by several patterns, such as a method that is also a function, is kept once
+        let mut seen = HashSet::new();
+
+        let mut cursor = QueryCursor::new();
+        let node_text = |node: Node| text.range(node.byte_range());
+        let mut matches = cursor.matches(&self.query, tree.root_node(), node_text);
+        while let Some(matching) = matches.next() {
+            let kind = matching.captures.iter().find_map(|capture| {
+                capture_names[capture.index as usize].strip_prefix("definition.")
+            });
+            let name = matching
+                .captures
+                .iter()
+                .find(|capture| capture_names[capture.index as usize] == "name");
+            let (Some(kind), Some(name)) = (kind, name) else {
+                continue;
+            };
+            let node = name.node;
+            if !seen.insert(node.start_byte()) {
+                continue;
+            }
+
+            let position = node.start_position();
+            let line_start = node.start_byte() - position.column;
+            symbols.push(Symbol {
+                name: text.string(node.byte_range()),
+                kind: kind.to_string(),
+                path: path.to_path_buf(),
+                row: position.row,
+                column: text.string(line_start..node.start_byte()).chars().count(),
+            });
+        }
+        symbols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::buffer::storage::StorageKind;
+    use crate::service::jobs::Jobs;
+
+    #[tokio::test]
+    async fn test_index_project() {
+        let root = std::env::temp_dir().join(format!("viron-{}-symbols", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        let source = "struct Point;\n\nimpl Point {\n    fn länge() {}\n}\n\nfn main() {}\n";
+        std::fs::write(root.join("src/main.rs"), source).unwrap();
+        std::fs::write(root.join("target/build.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/data.rs"), b"fn binary() {}\0").unwrap();
+
+        let mut index = SymbolIndex::new();
+        index.start(root.clone(), Jobs::new().start("Indexing symbols".to_string()));
+        index.wait().await;
+
+        let main = root.join("src/main.rs");
+        let found = index
+            .search("")
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.row, symbol.column))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [
+            ("Point".to_string(), "class".to_string(), 0, 7),
+            ("länge".to_string(), "method".to_string(), 3, 7),
+            ("main".to_string(), "function".to_string(), 6, 3),
+        ]);
+        assert_eq!(index.find("main")[0].path, main);
+
+        index.refresh(&main, &Buffer::from_string("fn start() {}\n"));
+        assert!(index.find("main").is_empty());
+        assert_eq!(index.search("STA").len(), 1);
+
+        // Names are read across the chunks of a rope
+        let text = (0..1000).map(|i| format!("fn é{i}() {{}}\n")).collect::<String>();
+        index.refresh(&main, &Buffer::with_storage(&text, StorageKind::Rope));
+        let found = index.search("é");
+        assert_eq!(found.len(), 1000);
+        for (i, symbol) in found.iter().enumerate() {
+            assert_eq!((symbol.name.as_str(), symbol.row, symbol.column), (format!("é{i}").as_str(), i, 3));
+        }
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+use crate::core::command::CommandBuffer;
+use crate::core::message::{Message, MessageType, Notice};
+use crate::core::mode::Mode;
+use crate::ui::context::RenderContext;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::theme::Style;
+use crate::ui::truncate::truncate_end;
+use crate::ui::{Bounds, Drawable, Focusable};
+
+/// Columns on the right kept for the pending keys
+const PENDING_WIDTH: usize = 10;
+
+/// Shown first when the start of a long command or search is scrolled off
+const SCROLLED: char = '<';
+
+/// The last row of the screen. It shows one thing at a time, so nothing else draws
+/// there: the command or search being typed, else the latest message or search
+/// result, else the pending keys on the right. The search result leaves room for them
+pub struct BottomBar;
+
+/// What the bar shows, from the most to the least important
+enum Content<'a> {
+    Command,
+    Search,
+    Message(&'a Message),
+    SearchResult,
+    Idle,
+}
+
+fn content<'a>(context: &'a RenderContext) -> Content<'a> {
+    match context.editor.mode {
+        Mode::Command => return Content::Command,
+        Mode::Search => return Content::Search,
+        _ => {}
+    }
+    match context.diagnostics.message_manager.notice() {
+        Some(Notice::Message(message)) => Content::Message(message),
+        Some(Notice::SearchResult) => Content::SearchResult,
+        None => Content::Idle,
+    }
+}
+
+/// The part of a `:command` or `/pattern` that fits the width, and the cursor column in
+/// it. Input too long for the row scrolls to keep the cursor in view, with a `<` in
+/// place of the part cut off on the left
+fn visible(prompt: char, input: &CommandBuffer, width: usize) -> (String, usize) {
+    if width == 0 {
+        return (String::new(), 0);
+    }
+    let text = format!("{prompt}{}", input.content());
+    let cursor = input.cursor_position() + 1;
+    // The cursor takes a cell of its own after the input
+    let first = (cursor + 1).saturating_sub(width);
+    if first == 0 {
+        return (text.chars().take(width).collect(), cursor);
+    }
+    let shown = std::iter::once(SCROLLED)
+        .chain(text.chars().skip(first + 1))
+        .take(width)
+        .collect();
+    (shown, cursor - first)
+}
+
+impl BottomBar {
+    fn draw_search_result(&self, buffer: &mut RenderBuffer, context: &RenderContext, row: usize) {
+        let theme = &context.config.theme;
+        let width = buffer.width.saturating_sub(PENDING_WIDTH);
+        let search_buffer = context.input.search_buffer;
+        let error_style = Style {
+            foreground: theme.colors.diagnostic.error.foreground,
+            background: theme.colors.editor.background,
+            ..Default::default()
+        };
+
+        let last_search = &search_buffer.last_search;
+        if last_search.is_empty() {
+            let message = format!("{:<width$}", "E: No search pattern");
+            buffer.set_text(row, 0, &message, &error_style);
+            return;
+        }
+        match search_buffer.current {
+            Some(index) => {
+                let counter = format!("[{}/{}]", index + 1, search_buffer.results.len());
+                buffer.set_text(row, 0, &format!("{counter:>width$}"), &theme.editor_style());
+                buffer.set_text(row, 0, &format!("/{last_search}"), &theme.editor_style());
+            }
+            None => {
+                let formatted = format!("{:<width$}", format!("E: No pattern found: {last_search}"));
+                buffer.set_text(row, 0, &formatted, &error_style);
+            }
+        }
+    }
+}
+
+impl Drawable for BottomBar {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
+        let Bounds {
+            start_row, width, ..
+        } = self.bounds(buffer, context);
+        let style = context.config.theme.editor_style();
+        self.clear(buffer, context)?;
+
+        match content(context) {
+            Content::Command => {
+                let (command, _) = visible(':', context.input.command_buffer, width);
+                buffer.set_text(start_row, 0, &command, &style);
+                return Ok(());
+            }
+            Content::Search => {
+                let (search, _) = visible('/', &context.input.search_buffer.buffer, width);
+                buffer.set_text(start_row, 0, &search, &style);
+                return Ok(());
+            }
+            Content::Message(message) => {
+                let content = truncate_end(&message.content, width);
+                let style = get_style_for_message(&message.message_type, context);
+                buffer.set_text(start_row, 0, &format!("{content:<width$}"), &style);
+                return Ok(());
+            }
+            Content::SearchResult => self.draw_search_result(buffer, context, start_row),
+            Content::Idle => {}
+        }
+
+        let pending_keys = context.input.input_state.display_input();
+        if !pending_keys.is_empty() && width > PENDING_WIDTH {
+            let text = format!("  {:w$}", pending_keys, w = PENDING_WIDTH - 2);
+            buffer.set_text(start_row, width - PENDING_WIDTH, &text, &style);
+        }
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, _context: &RenderContext) -> Bounds {
+        Bounds {
+            start_row: buffer.height - 1,
+            start_col: 0,
+            width: buffer.width,
+            height: 1,
+        }
+    }
+}
+
+impl Focusable for BottomBar {
+    fn get_display_cursor(&self, buffer: &RenderBuffer, context: &RenderContext) -> (usize, usize) {
+        let column = match content(context) {
+            Content::Command => visible(':', context.input.command_buffer, buffer.width).1,
+            Content::Search => visible('/', &context.input.search_buffer.buffer, buffer.width).1,
+            _ => 0,
+        };
+        (buffer.height - 1, column)
+    }
+}
+
+fn get_style_for_message(message_type: &MessageType, context: &RenderContext) -> Style {
+    let mut style = context.config.theme.editor_style();
+    if let MessageType::Error = message_type {
+        style.foreground = context.config.theme.colors.diagnostic.error.foreground;
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_input_scrolls() {
+        let mut input = CommandBuffer::new();
+        input.set_content("s/héllo/wörld/g");
+        assert_eq!(visible(':', &input, 20), (":s/héllo/wörld/g".to_string(), 16));
+
+        // The end stays in view with the cursor after it
+        assert_eq!(visible(':', &input, 10), ("</wörld/g".to_string(), 9));
+        for _ in 0..12 {
+            input.move_cursor_left();
+        }
+        assert_eq!(visible('/', &input, 10), ("/s/héllo/w".to_string(), 4));
+        assert_eq!(visible('/', &input, 0), (String::new(), 0));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::core::completions::Completions;
+use crate::ui::components::EditorView;
+use crate::ui::context::RenderContext;
+use crate::ui::display::display_width;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::theme::Style;
+use crate::ui::truncate::truncate_end;
+use crate::ui::{Bounds, Drawable, Focusable};
+use lsp_types::CompletionItem;
+
+/// Items shown at once, the selection is kept in view
+const MAX_ROWS: usize = 10;
+
+/// The completions offered for the word before the cursor, drawn below it, or above
+/// it when there's no room left below, with the selected one highlighted
+pub struct CompletionMenu {
+    /// Finds the cursor on screen
+    view: EditorView,
+}
+
+impl CompletionMenu {
+    pub fn new() -> Self {
+        Self { view: EditorView::new() }
+    }
+
+    /// The label, kind and detail of each item, in columns
+    fn rows(completions: &Completions) -> Vec<String> {
+        let kind = |item: &CompletionItem| item.kind.map(|kind| format!("{kind:?}")).unwrap_or_default();
+        let label_width = completions.shown().map(|item| item.label.chars().count()).max().unwrap_or(0);
+        let kind_width = completions.shown().map(|item| kind(item).len()).max().unwrap_or(0);
+        completions
+            .shown()
+            .map(|item| {
+                let detail = item.detail.as_deref().unwrap_or_default();
+                let row = format!(" {:<label_width$}  {:<kind_width$}  {detail}", item.label, kind(item));
+                format!("{} ", row.trim_end())
+            })
+            .collect()
+    }
+}
+
+impl Drawable for CompletionMenu {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
+        let Some(completions) = context.editor.completion else {
+            return Ok(());
+        };
+        let Bounds {
+            start_row,
+            start_col,
+            width,
+            height,
+        } = self.bounds(buffer, context);
+        let theme = &context.config.theme;
+        let style = Style::from(theme.colors.status.inner);
+        let mut selected_style = Style::from(theme.colors.status.normal);
+        selected_style.bold = true;
+
+        let top = (completions.selected + 1).saturating_sub(height);
+        for (index, row) in Self::rows(completions).iter().enumerate().skip(top).take(height) {
+            let row_style = if index == completions.selected { &selected_style } else { &style };
+            let text = truncate_end(row, width);
+            buffer.set_text(start_row + index - top, start_col, &format!("{text:<width$}"), row_style);
+        }
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
+        let Some(completions) = context.editor.completion else {
+            return Bounds { start_row: 0, start_col: 0, width: 0, height: 0 };
+        };
+        let (row, column) = self.view.get_display_cursor(buffer, context);
+        // Labels start under the start of the word, where the completion goes
+        let start = completions.start;
+        let point = context.editor.cursor.get_point();
+        let line = context.editor.document.buffer.get_line_as_string(point.row);
+        let typed = line.get(start.column..point.column).unwrap_or_default();
+        let column = column.saturating_sub(display_width(typed, context.config.layout()) + 1);
+
+        let editor_height = context.editor.quickfix.editor_height(buffer.height);
+        let below = editor_height.saturating_sub(row + 1);
+        let rows = completions.len().min(MAX_ROWS);
+        let (start_row, height) = match below >= rows || below >= row {
+            true => (row + 1, rows.min(below)),
+            false => (row - rows.min(row), rows.min(row)),
+        };
+        let rows = Self::rows(completions);
+        let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0).min(buffer.width);
+        Bounds {
+            start_row,
+            start_col: column.min(buffer.width - width),
+            width,
+            height,
+        }
+    }
+}
+
+impl Focusable for CompletionMenu {
+    fn get_display_cursor(&self, buffer: &RenderBuffer, context: &RenderContext) -> (usize, usize) {
+        self.view.get_display_cursor(buffer, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::lsp::ShowCompletions;
+    use crate::editor::testing::TestEditor;
+    use lsp_types::CompletionItemKind;
+
+    #[tokio::test]
+    async fn test_menu_under_word() {
+        let item = |label: &str, detail: &str| CompletionItem {
+            label: label.to_string(),
+            kind: Some(CompletionItemKind::METHOD),
+            detail: Some(detail.to_string()),
+            ..Default::default()
+        };
+        let mut editor = TestEditor::new("\n");
+        editor.resize(40, 8);
+        editor.keys("iv.p").await.unwrap();
+        let items = vec![item("push", "fn(&mut self, T)"), item("pop", "fn(&mut self)")];
+        editor.execute(&ShowCompletions::new(items)).await.unwrap();
+
+        let buffer = editor.render().unwrap().buffer;
+        let row = |row: usize| {
+            let cells = &buffer.cells[row * buffer.width..(row + 1) * buffer.width];
+            cells.iter().map(|cell| cell.c).collect::<String>()
+        };
+        let column = row(0).find("v.p").unwrap() + 1;
+        assert_eq!(&row(1)[column..], format!("{:<width$}", " pop   Method  fn(&mut self) ", width = 40 - column));
+        assert!(row(2)[column..].starts_with(" push  Method  fn(&mut self, T) "));
+    }
+}
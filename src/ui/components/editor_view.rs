@@ -1,6 +1,8 @@
-use crate::constants::RESERVED_ROW_COUNT;
+use crate::core::mode::{Mode, selected_lines};
 use crate::ui::components::gutter::Gutter;
+use crate::ui::components::scrollbar::Scrollbar;
 use crate::ui::context::RenderContext;
+use crate::ui::display::{display_column, display_width, wrapped_position, wrapped_rows};
 use crate::ui::render_buffer::RenderBuffer;
 use crate::ui::theme::Style;
 use crate::ui::{Bounds, Drawable, Focusable};
@@ -12,14 +14,20 @@ use std::str::from_utf8;
 use tree_sitter::Point;
 
 const DIAGNOSTIC_MARGIN: usize = 4;
+/// Drawn in the last column beside lines that go on past the right edge
+const OVERFLOW_MARK: char = '>';
 
 pub struct EditorView {
     gutter: Gutter,
+    scrollbar: Scrollbar,
 }
 
 impl EditorView {
     pub fn new() -> Self {
-        Self { gutter: Gutter }
+        Self {
+            gutter: Gutter,
+            scrollbar: Scrollbar,
+        }
     }
 }
 
@@ -32,7 +40,7 @@ impl EditorView {
         let gutter_width = self.gutter.get_width(context);
         let mut bounds = self.bounds(render_buffer, context);
         bounds.start_col += gutter_width;
-        bounds.width -= gutter_width;
+        bounds.width -= gutter_width + self.scrollbar.get_width(context);
         bounds
     }
 
@@ -52,26 +60,31 @@ impl EditorView {
         let theme = &context.config.theme;
 
         let top_line = viewport.top_line();
-        let left_col = viewport.left_column();
+        let right_column = viewport.left_column() + visible_width;
         let editor_style = theme.editor_style();
 
         for viewport_row in 0..visible_height {
             let buffer_row = top_line + viewport_row;
-
-            let content = if buffer_row >= buffer.line_count() {
-                " ".repeat(visible_width)
-            } else {
-                let line = buffer.get_line_as_string(buffer_row);
-                format!(
-                    "{:<visible_width$}",
-                    line.chars()
-                        .skip(left_col)
-                        .take_while(|c| c != &'\n')
-                        .collect::<String>()
-                )
+            // Rows of a diff view are colored by the change they show
+            let style = match &context.editor.document.diff {
+                Some(diff) => diff
+                    .row(buffer_row)
+                    .map_or(editor_style.clone(), |row| theme.diff_style(row.kind)),
+                None => editor_style.clone(),
             };
+            render_buffer.set_text(viewport_row, start_col, &" ".repeat(visible_width), &style);
+            if buffer_row >= buffer.line_count() {
+                continue;
+            }
 
-            render_buffer.set_text(viewport_row, start_col, &content, &editor_style);
+            let line = buffer.get_line_as_string(buffer_row);
+            let mut position = Point { row: viewport_row, column: 0 };
+            for c in line.chars().take_while(|c| c != &'\n') {
+                if position.column >= right_column {
+                    break;
+                }
+                draw_char(render_buffer, context, &mut position, c, &style, start_col);
+            }
         }
 
         Ok(())
@@ -89,20 +102,28 @@ impl EditorView {
             ..
         } = self.get_buffer_bounds(render_buffer, context);
 
-        let Some(ref mut syntax_engine) = context.editor.document.syntax_engine else {
-            return Err(anyhow::anyhow!("Syntax highlighter is not available"));
-        };
-
         let viewport = context.editor.viewport;
-        let buffer = &context.editor.document.buffer;
-        let theme = &context.config.theme;
-        let editor_style = theme.editor_style();
-
-        let code = buffer.to_bytes();
-        let tokens = syntax_engine.highlight(&code)?;
-
         let top_line = viewport.top_line();
         let left_column = viewport.left_column();
+        let document = &mut *context.editor.document;
+        let Some(syntax_engine) = document.syntax_engine.as_mut() else {
+            return Err(anyhow::anyhow!("Syntax highlighter is not available"));
+        };
+        // Captures are only looked for in the lines in view
+        let buffer = &document.buffer;
+        let line_start = |row: usize| match row < buffer.line_count() {
+            true => buffer.cursor_position(&Point { row, column: 0 }),
+            false => buffer.len_bytes(),
+        };
+        let byte_range = line_start(top_line)..line_start(top_line + visible_height);
+        syntax_engine.update(buffer, document.revision, byte_range)?;
+
+        let context = &*context;
+        let Some(syntax_engine) = &context.editor.document.syntax_engine else {
+            return Ok(());
+        };
+        let (code, tokens) = syntax_engine.highlights();
+        let editor_style = context.config.theme.editor_style();
 
         // Filter tokens to only those visible in the viewport
         let mut info_iter = tokens
@@ -111,19 +132,15 @@ impl EditorView {
                 info.end_position.row >= top_line
                     && info.start_position.row < top_line + visible_height as usize
             })
-            .map(|info| {
-                let mut new_info = info.clone();
-                new_info.start_position.row -= top_line;
-                new_info.end_position.row -= top_line;
-                new_info
-            })
             .peekable();
+        // Rows of the tokens are counted from the top line
+        let in_view = |point: Point| Point { row: point.row - top_line, ..point };
 
         // Render the first unhighlighted part of the code
         let first = if let Some(info) = info_iter.peek() {
             &code[..info.byte_range.start]
         } else {
-            &code
+            code
         };
 
         let mut lines = first.split(|&b| b == b'\n').skip(top_line).peekable();
@@ -134,15 +151,7 @@ impl EditorView {
             let text = from_utf8(line)?;
 
             for c in text.chars() {
-                if position.column >= left_column {
-                    render_buffer.set_cell(
-                        position.row,
-                        position.column - left_column + start_col,
-                        c,
-                        &editor_style,
-                    );
-                }
-                position.column += 1;
+                draw_char(render_buffer, context, &mut position, c, &editor_style, start_col);
             }
 
             if lines.peek().is_some() {
@@ -160,19 +169,10 @@ impl EditorView {
         while let Some(info) = info_iter.next() {
             let style = context.config.theme.style_for_token(&info.scope);
             let bytes = &code[info.byte_range.start..info.byte_range.end];
-            position.row = info.end_position.row;
-            position.column = info.end_position.column;
-
-            self.set_text_on_viewport(
-                render_buffer,
-                context,
-                &mut Point {
-                    row: info.start_position.row,
-                    column: info.start_position.column,
-                },
-                bytes,
-                &style,
-            )?;
+            let mut start = self.to_screen_position(context, in_view(info.start_position));
+            position = self.to_screen_position(context, in_view(info.end_position));
+
+            self.set_text_on_viewport(render_buffer, context, &mut start, bytes, &style)?;
 
             match info_iter.peek() {
                 // Next highlight on the same line
@@ -219,7 +219,7 @@ impl EditorView {
     fn set_text_on_viewport(
         &self,
         render_buffer: &mut RenderBuffer,
-        context: &mut RenderContext,
+        context: &RenderContext,
         position: &mut Point,
         bytes: &[u8],
         style: &Style,
@@ -238,15 +238,7 @@ impl EditorView {
             let text = from_utf8(line)?;
 
             for c in text.chars() {
-                if position.column >= left_column {
-                    render_buffer.set_cell(
-                        position.row,
-                        position.column - left_column + start_col,
-                        c,
-                        &style,
-                    );
-                }
-                position.column += 1;
+                draw_char(render_buffer, context, position, c, style, start_col);
             }
 
             if lines.peek().is_some() {
@@ -266,6 +258,17 @@ impl EditorView {
         Ok(())
     }
 
+    /// Converts a viewport row and byte column to a viewport row and screen column
+    fn to_screen_position(&self, context: &RenderContext, point: Point) -> Point {
+        let buffer = &context.editor.document.buffer;
+        let line = buffer.get_line_as_bytes(context.editor.viewport.top_line() + point.row);
+        let before = String::from_utf8_lossy(&line[..point.column.min(line.len())]);
+        Point {
+            row: point.row,
+            column: display_width(&before, context.config.layout()),
+        }
+    }
+
     fn draw_diagnostics(
         &self,
         render_buffer: &mut RenderBuffer,
@@ -279,10 +282,12 @@ impl EditorView {
 
         let mut line_diagnostics: HashMap<u32, &Diagnostic> = HashMap::new();
 
+        // Diagnostics sent for an older version of the text can be past its end
+        let line_count = buffer.line_count() as u32;
         for diagnostic in context.diagnostics.diagnostics.iter().filter(|d| {
             let start = &d.range.start;
             start.line >= starting_line
-                && start.line < ending_line
+                && start.line < ending_line.min(line_count)
                 && d.severity.unwrap_or(DiagnosticSeverity::ERROR) <= DiagnosticSeverity::WARNING
         }) {
             let line = diagnostic.range.start.line;
@@ -303,8 +308,8 @@ impl EditorView {
                 continue;
             };
             let formatted = format!("■  {message}");
-            let line_length = buffer.get_line_length(line as usize);
-            let column = line_length + DIAGNOSTIC_MARGIN;
+            let line_text = buffer.get_line_as_string(line as usize);
+            let column = display_width(&line_text, context.config.layout()) + DIAGNOSTIC_MARGIN;
 
             let formatted: String = formatted
                 .chars()
@@ -329,12 +334,67 @@ impl EditorView {
         Ok(())
     }
 
+    /// Highlights the matches of the search in view, or of the pattern being typed
+    fn draw_search_matches(&self, render_buffer: &mut RenderBuffer, context: &RenderContext) {
+        let bounds = self.get_buffer_bounds(render_buffer, context);
+        let viewport = context.editor.viewport;
+        let (top_line, left_column) = (viewport.top_line(), viewport.left_column());
+        let buffer = &context.editor.document.buffer;
+        let colors = &context.config.theme.colors.search;
+        let typing = *context.editor.mode == Mode::Search;
+
+        let rows = top_line..(top_line + bounds.height).min(buffer.line_count());
+        for (viewport_row, row) in rows.enumerate() {
+            let line = buffer.get_line_as_string(row);
+            let line = line.trim_end_matches('\n');
+            for (range, current) in context.input.search_buffer.highlights(row, line, typing) {
+                let colors = if current { colors.current } else { colors.matched };
+                // Display columns, cut to the part of the line scrolled into view
+                let start = display_column(line, range.start, context.config.layout());
+                let end = display_column(line, range.end, context.config.layout());
+                let start = start.max(left_column);
+                let end = end.min(left_column + bounds.width);
+                let offset = viewport_row * render_buffer.width + bounds.start_col;
+                for column in start..end {
+                    let cell = &mut render_buffer.cells[offset + column - left_column];
+                    cell.style.background = colors.background.or(cell.style.background);
+                    cell.style.foreground = colors.foreground.or(cell.style.foreground);
+                }
+            }
+        }
+    }
+
+    /// Gives the selected lines the selection background across the whole text area,
+    /// past the end of short lines too
+    fn draw_selection(&self, render_buffer: &mut RenderBuffer, context: &RenderContext) {
+        if *context.editor.mode != Mode::VisualLine {
+            return;
+        }
+        let Some(background) = context.config.theme.colors.selection.background else {
+            return;
+        };
+        let bounds = self.get_buffer_bounds(render_buffer, context);
+        let top_line = context.editor.viewport.top_line();
+        let row = context.editor.cursor.get_point().row;
+        let line_count = context.editor.document.buffer.line_count();
+        let (first, last) = selected_lines(context.editor.visual_anchor, row, line_count);
+
+        let rows = first.max(top_line)..=last.min(top_line + bounds.height - 1);
+        for viewport_row in rows.map(|row| row - top_line) {
+            let start = viewport_row * render_buffer.width + bounds.start_col;
+            for cell in &mut render_buffer.cells[start..start + bounds.width] {
+                cell.style.background = Some(background);
+            }
+        }
+    }
+
     fn draw_buffer(
         &self,
         render_buffer: &mut RenderBuffer,
         context: &mut RenderContext,
     ) -> Result<()> {
-        if context.editor.document.language.is_plain_text() {
+        let document = &context.editor.document;
+        if document.language.is_plain_text() || document.is_large_file() {
             return self.render_plain_text(render_buffer, context);
         }
 
@@ -347,16 +407,132 @@ impl EditorView {
     }
 }
 
-impl Drawable for EditorView {
-    fn draw(&self, render_buffer: &mut RenderBuffer, context: &mut RenderContext) -> Result<()> {
+/// Draws a character of the buffer at a screen column of the text area, leaving out
+/// the cells scrolled out on the left, and moves the position past it
+fn draw_char(
+    render_buffer: &mut RenderBuffer,
+    context: &RenderContext,
+    position: &mut Point,
+    c: char,
+    style: &Style,
+    start_col: usize,
+) {
+    let left_column = context.editor.viewport.left_column();
+    let glyph = context.config.layout().glyph(c, position.column);
+    let control_style;
+    let style = if glyph.control {
+        control_style = context.config.theme.control_style();
+        &control_style
+    } else {
+        style
+    };
+
+    for (index, cell) in glyph.cells().enumerate() {
+        let column = position.column + index;
+        if column >= left_column {
+            render_buffer.set_cell(position.row, column - left_column + start_col, cell, style);
+        }
+    }
+    position.column += glyph.width();
+}
+
+impl EditorView {
+    /// Draws the gutter and the lines from the top line, one screen row each
+    fn draw_lines(&self, render_buffer: &mut RenderBuffer, context: &mut RenderContext) -> Result<()> {
         self.gutter.draw(render_buffer, context)?;
         self.draw_buffer(render_buffer, context)?;
-        self.draw_diagnostics(render_buffer, context)
+        self.draw_search_matches(render_buffer, context);
+        self.draw_selection(render_buffer, context);
+        self.draw_diagnostics(render_buffer, context)?;
+        self.draw_overflow_marks(render_buffer, context);
+        Ok(())
+    }
+
+    /// Marks the rows whose line is cut off at the right edge of the text area
+    fn draw_overflow_marks(&self, render_buffer: &mut RenderBuffer, context: &RenderContext) {
+        let bounds = self.get_buffer_bounds(render_buffer, context);
+        if bounds.width == 0 {
+            return;
+        }
+        let viewport = context.editor.viewport;
+        let buffer = &context.editor.document.buffer;
+        let right_column = viewport.left_column() + bounds.width;
+        let style = Style::from(context.config.theme.colors.gutter);
+
+        let top_line = viewport.top_line();
+        let rows = top_line..(top_line + bounds.height).min(buffer.line_count());
+        for (viewport_row, row) in rows.enumerate() {
+            let line = buffer.get_line_as_string(row);
+            if display_width(line.trim_end_matches('\n'), context.config.layout()) > right_column {
+                let column = bounds.start_col + bounds.width - 1;
+                render_buffer.set_cell(viewport_row, column, OVERFLOW_MARK, &style);
+            }
+        }
+    }
+
+    /// Screen rows of each line in view when lines wrap at the width of the text area
+    fn wrapped_rows(&self, render_buffer: &RenderBuffer, context: &RenderContext) -> Vec<usize> {
+        let bounds = self.get_buffer_bounds(render_buffer, context);
+        let buffer = &context.editor.document.buffer;
+        let top_line = context.editor.viewport.top_line();
+        (top_line..(top_line + bounds.height).min(buffer.line_count()))
+            .map(|row| {
+                let line = buffer.get_line_as_string(row);
+                wrapped_rows(&line, bounds.width, context.config.layout())
+            })
+            .collect()
     }
 
-    fn bounds(&self, render_buffer: &RenderBuffer, _context: &RenderContext<'_>) -> Bounds {
+    /// Draws the lines unwrapped into a buffer as wide as the longest, so highlights
+    /// crossing a wrap point are kept, then copies them a screen row at a time. Rows
+    /// continuing a line leave the gutter blank
+    fn draw_wrapped(&self, render_buffer: &mut RenderBuffer, context: &mut RenderContext) -> Result<()> {
+        let bounds = self.get_buffer_bounds(render_buffer, context);
+        let rows = self.wrapped_rows(render_buffer, context);
+        let longest = rows.iter().max().copied().unwrap_or(1) * bounds.width;
+        let mut lines = RenderBuffer::new(
+            render_buffer.width - bounds.width + longest,
+            render_buffer.height,
+        );
+        self.draw_lines(&mut lines, context)?;
+
+        let gutter_style = Style::from(context.config.theme.colors.gutter);
+        let blank_gutter = " ".repeat(bounds.start_col);
+        let segments = rows.iter().enumerate().flat_map(|(line, &rows)| {
+            (0..rows).map(move |segment| (line, segment))
+        });
+        // Rows past the end of the file are copied from the blank row after it
+        let blank = (rows.len(), 0);
+        let segments = segments.chain(std::iter::repeat(blank));
+        for (screen_row, (line, segment)) in segments.take(bounds.height).enumerate() {
+            let from = line * lines.width;
+            let to = screen_row * render_buffer.width;
+            let text = from + bounds.start_col + segment * bounds.width;
+            render_buffer.cells[to + bounds.start_col..to + bounds.start_col + bounds.width]
+                .clone_from_slice(&lines.cells[text..text + bounds.width]);
+            match segment {
+                0 => render_buffer.cells[to..to + bounds.start_col]
+                    .clone_from_slice(&lines.cells[from..from + bounds.start_col]),
+                _ => render_buffer.set_text(screen_row, 0, &blank_gutter, &gutter_style),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drawable for EditorView {
+    fn draw(&self, render_buffer: &mut RenderBuffer, context: &mut RenderContext) -> Result<()> {
+        match context.config.wrap {
+            true => self.draw_wrapped(render_buffer, context)?,
+            false => self.draw_lines(render_buffer, context)?,
+        }
+        // Last, as text and diagnostics past the text area are not cut off
+        self.scrollbar.draw(render_buffer, context)
+    }
+
+    fn bounds(&self, render_buffer: &RenderBuffer, context: &RenderContext<'_>) -> Bounds {
         let width = render_buffer.width;
-        let height = render_buffer.height - RESERVED_ROW_COUNT;
+        let height = context.editor.quickfix.editor_height(render_buffer.height);
         Bounds {
             start_row: 0,
             start_col: 0,
@@ -367,12 +543,242 @@ impl Drawable for EditorView {
 }
 
 impl Focusable for EditorView {
-    fn get_display_cursor(&self, _: &RenderBuffer, context: &RenderContext) -> (usize, usize) {
+    fn get_display_cursor(&self, render_buffer: &RenderBuffer, context: &RenderContext) -> (usize, usize) {
         let viewport = context.editor.viewport;
         let (row, column) = context.editor.cursor.get_display_cursor();
+        let line = context.editor.document.buffer.get_line_as_string(row);
+        let column = display_column(&line, column, context.config.layout());
         let gutter_width = self.gutter.get_width(context);
+        if context.config.wrap {
+            let rows = self.wrapped_rows(render_buffer, context);
+            let index = row - viewport.top_line();
+            let width = self.get_buffer_bounds(render_buffer, context).width;
+            let line_rows = rows.get(index).copied().unwrap_or(1);
+            let (offset, column) = wrapped_position(column, width, line_rows);
+            let above = rows.iter().take(index).sum::<usize>();
+            return (above + offset, column + gutter_width);
+        }
         let screen_row = row - viewport.top_line();
         let screen_col = column - viewport.left_column();
         (screen_row, screen_col + gutter_width)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::language::Language;
+    use crate::editor::testing::TestEditor;
+    use crate::ui::theme::Theme;
+    use crossterm::style::Color;
+    use lsp_types::{Position, Range};
+
+    fn row_text(render_buffer: &RenderBuffer, row: usize) -> String {
+        let start = row * render_buffer.width;
+        render_buffer.cells[start..start + render_buffer.width]
+            .iter()
+            .map(|cell| cell.c)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_highlights_kept_until_an_edit() {
+        let source = (0..100).map(|n| format!("fn f{n}() {{}}\n")).collect::<String>();
+        let mut editor = TestEditor::new(&source);
+        editor.core.current_document_mut().set_language(Language::Rust);
+        let parses = |editor: &TestEditor| {
+            editor.core.current_document().syntax_engine.as_ref().unwrap().parse_count()
+        };
+        editor.render().unwrap();
+        assert_eq!(parses(&editor), 1);
+
+        // Moving the cursor, even scrolling, doesn't parse the text again
+        editor.keys("jjl").await.unwrap();
+        editor.render().unwrap();
+        editor.keys("50j").await.unwrap();
+        editor.render().unwrap();
+        assert_eq!(parses(&editor), 1);
+
+        editor.keys("x").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(parses(&editor), 2);
+        let rows = (0..frame.buffer.height).map(|row| row_text(&frame.buffer, row));
+        assert!(rows.clone().any(|row| row.contains("f f52() {}")));
+        assert!(rows.clone().any(|row| row.contains("fn f51() {}")));
+    }
+
+    #[tokio::test]
+    async fn test_control_chars_are_drawn_in_caret_notation() {
+        let mut editor = TestEditor::new("a\x07b\x1bc\x00d\n");
+        editor.config.theme = Theme::embedded();
+        editor.keys("4l").await.unwrap();
+        let mut render_buffer = RenderBuffer::new(80, 24);
+        let view = EditorView::new();
+        let mut context = editor.render_context();
+        view.draw(&mut render_buffer, &mut context).unwrap();
+
+        let gutter_width = view.gutter.get_width(&context);
+        let row = row_text(&render_buffer, 0);
+        assert_eq!(row[gutter_width..].trim_end(), "a^Gb^[c^@d");
+        assert!(!render_buffer.cells.iter().any(|cell| cell.c.is_ascii_control()));
+
+        let theme = &context.config.theme;
+        assert_ne!(theme.control_style(), theme.editor_style());
+        assert_eq!(render_buffer.cells[gutter_width + 1].style, theme.control_style());
+        assert_eq!(render_buffer.cells[gutter_width + 3].style, theme.editor_style());
+        assert_eq!(view.get_display_cursor(&render_buffer, &context), (0, gutter_width + 6));
+    }
+
+    #[tokio::test]
+    async fn test_tabs_are_drawn_to_the_next_stop() {
+        let mut editor = TestEditor::new("\tab\tc\n");
+        editor.keys("$").await.unwrap();
+        let mut render_buffer = RenderBuffer::new(80, 24);
+        let view = EditorView::new();
+        let mut context = editor.render_context();
+        view.draw(&mut render_buffer, &mut context).unwrap();
+
+        let gutter_width = view.gutter.get_width(&context);
+        let row = row_text(&render_buffer, 0);
+        assert_eq!(row[gutter_width..].trim_end(), "    ab  c");
+        assert_eq!(view.get_display_cursor(&render_buffer, &context), (0, gutter_width + 8));
+    }
+
+    #[tokio::test]
+    async fn test_long_lines_wrap() {
+        let mut editor = TestEditor::new("0123456789abcdef\nxy\n");
+        editor.config.wrap = true;
+        editor.resize(14, 6);
+        editor.keys("$").await.unwrap();
+        let frame = editor.render().unwrap();
+        let gutter_width = EditorView::new().gutter.get_width(&editor.render_context());
+        let rows = (0..4)
+            .map(|row| row_text(&frame.buffer, row))
+            .collect::<Vec<_>>();
+        assert_eq!(rows[0][gutter_width..].trim_end(), "0123456789");
+        assert_eq!(rows[1].trim_end(), format!("{}abcdef", " ".repeat(gutter_width)));
+        assert_eq!(rows[2][gutter_width..].trim_end(), "xy");
+        assert!(!rows[2][..gutter_width].trim().is_empty());
+        assert_eq!(frame.cursor, Some((1, gutter_width + 5)));
+
+        // The cursor stays in view when the lines above it take several rows
+        let line = "x".repeat(25);
+        let mut editor = TestEditor::new(&format!("{line}\n").repeat(5));
+        editor.config.wrap = true;
+        editor.resize(14, 6);
+        editor.keys("4j").await.unwrap();
+        editor.render().unwrap();
+        assert_eq!(editor.core.viewport.top_line(), 3);
+        editor.keys("$").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(editor.core.viewport.top_line(), 4);
+        assert_eq!(frame.cursor, Some((2, gutter_width + 4)));
+    }
+
+    #[tokio::test]
+    async fn test_cut_off_lines_are_marked() {
+        let mut editor = TestEditor::new("0123456789abcdef\nshort\n");
+        editor.resize(14, 6);
+        let frame = editor.render().unwrap();
+        let gutter_width = EditorView::new().gutter.get_width(&editor.render_context());
+        assert_eq!(row_text(&frame.buffer, 0)[gutter_width..], *"012345678>");
+        assert_eq!(row_text(&frame.buffer, 1)[gutter_width..].trim_end(), "short");
+
+        // Scrolled to the end, nothing is cut off
+        editor.keys("$").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(row_text(&frame.buffer, 0)[gutter_width..], *"6789abcdef");
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_past_the_end() {
+        // Sent before the text lost its last lines
+        let mut editor = TestEditor::new("one\n");
+        editor.set_diagnostics(vec![Diagnostic {
+            range: Range::new(Position::new(5, 0), Position::new(5, 3)),
+            message: "stale".to_string(),
+            ..Default::default()
+        }]);
+        let frame = editor.render().unwrap();
+        assert!(!(0..frame.buffer.height).any(|row| row_text(&frame.buffer, row).contains("stale")));
+    }
+
+    #[tokio::test]
+    async fn test_visual_line_highlights_whole_rows() {
+        let mut editor = TestEditor::new("a
+longer line
+b
+c
+");
+        editor.config.theme = Theme::embedded();
+        editor.resize(30, 8);
+        editor.keys("jjVk").await.unwrap();
+        let frame = editor.render().unwrap();
+        let buffer = &frame.buffer;
+        assert!(row_text(buffer, 6).starts_with(" VISUAL LINE "));
+
+        let selection = editor.config.theme.colors.selection.background;
+        assert_ne!(selection, editor.config.theme.colors.editor.background);
+        let selected = |row: usize| {
+            let cells = &buffer.cells[row * buffer.width..(row + 1) * buffer.width];
+            cells.iter().filter(|cell| cell.style.background == selection).count()
+        };
+        let gutter_width = EditorView::new().gutter.get_width(&editor.render_context());
+        assert_eq!([0, 1, 2, 3].map(selected), [0, 30 - gutter_width, 30 - gutter_width, 0]);
+
+        editor.keys("<Esc>").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert!(!frame.buffer.cells.iter().any(|cell| cell.style.background == selection));
+    }
+
+    /// Characters of a row with the background
+    fn with_background(render_buffer: &RenderBuffer, row: usize, background: Option<Color>) -> String {
+        let start = row * render_buffer.width;
+        render_buffer.cells[start..start + render_buffer.width]
+            .iter()
+            .filter(|cell| cell.style.background == background)
+            .map(|cell| cell.c)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_are_highlighted() {
+        let long_line = format!("{}fooX foo", "-".repeat(100));
+        let mut editor = TestEditor::new(&format!("foo bar fo\n{long_line}\nbar foo\n"));
+        editor.config.theme = Theme::embedded();
+        editor.resize(40, 8);
+        let colors = editor.config.theme.colors.search.clone();
+        assert_ne!(colors.matched.background, colors.current.background);
+
+        // Incrementally while typing, without a current match
+        editor.keys("/fo").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(with_background(&frame.buffer, 0, colors.matched.background), "fofo");
+        assert_eq!(with_background(&frame.buffer, 2, colors.matched.background), "fo");
+        editor.keys("o").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(with_background(&frame.buffer, 0, colors.matched.background), "foo");
+
+        editor.keys("<Enter>").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(with_background(&frame.buffer, 0, colors.current.background), "foo");
+        assert_eq!(with_background(&frame.buffer, 2, colors.matched.background), "foo");
+
+        // Scrolled to the end of the long line, the matches are shifted with the text
+        editor.keys("nn").await.unwrap();
+        assert_eq!(editor.cursor(), (1, 105));
+        editor.keys("$").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(with_background(&frame.buffer, 1, colors.current.background), "foo");
+        assert_eq!(with_background(&frame.buffer, 1, colors.matched.background), "foo");
+
+        // An edit clears them
+        editor.keys("x").await.unwrap();
+        let frame = editor.render().unwrap();
+        let highlighted = |background| (0..3).any(|row| {
+            !with_background(&frame.buffer, row, background).is_empty()
+        });
+        assert!(!highlighted(colors.matched.background));
+        assert!(!highlighted(colors.current.background));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::config::editor::Gutter as GutterConfig;
-use crate::constants::{MIN_GUTTER_WIDTH, RESERVED_ROW_COUNT};
+use crate::constants::MIN_GUTTER_WIDTH;
 use crate::ui::render_buffer::RenderBuffer;
 use crate::ui::theme::Style;
 use crate::ui::{Bounds, Drawable};
@@ -13,8 +13,8 @@ impl Gutter {
         if context.config.gutter == GutterConfig::None {
             return 0;
         }
-        let line_count = context.editor.document.buffer.line_count();
-        let digits = line_count.to_string().len();
+        let last_line = context.editor.document.buffer.line_count().saturating_sub(1);
+        let digits = context.editor.document.line_label(last_line).len();
         (digits + 1).max(MIN_GUTTER_WIDTH)
     }
 
@@ -22,14 +22,19 @@ impl Gutter {
         match context.config.gutter {
             GutterConfig::None => String::new(),
             GutterConfig::Absolute => {
-                format!("{:>w$}", line + 1, w = self.get_width(context) - 1)
+                let label = context.editor.document.line_label(line);
+                format!("{:>w$}", label, w = self.get_width(context) - 1)
             }
-            GutterConfig::Relative => {
+            GutterConfig::Relative | GutterConfig::RelativeNumber => {
+                let width = self.get_width(context) - 1;
                 let distance = line.abs_diff(current_line);
-                if distance == 0 {
-                    format!("{:<w$}", line + 1, w = self.get_width(context) - 1)
-                } else {
-                    format!("{:>w$}", distance, w = self.get_width(context) - 1)
+                if distance != 0 {
+                    return format!("{distance:>width$}");
+                }
+                let label = context.editor.document.line_label(line);
+                match context.config.gutter {
+                    GutterConfig::Relative => format!("{label:<width$}"),
+                    _ => format!("{label:>width$}"),
                 }
             }
         }
@@ -70,7 +75,41 @@ impl Drawable for Gutter {
             start_row: 0,
             start_col: 0,
             width: self.get_width(context),
-            height: buffer.height - RESERVED_ROW_COUNT,
+            height: context.editor.quickfix.editor_height(buffer.height),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::config::editor::Gutter;
+    use crate::editor::testing::TestEditor;
+    use crate::input::default_keymap::default_keymap;
+
+    #[tokio::test]
+    async fn test_relative_number_gutter() {
+        let text = "line\n".repeat(12);
+        let config = Config {
+            gutter: Gutter::RelativeNumber,
+            keymap: default_keymap().clone(),
+            ..Default::default()
+        };
+        let mut editor = TestEditor::with_config(&text, config);
+        editor.resize(20, 10);
+        editor.keys("10G").await.unwrap();
+        let frame = editor.render().unwrap();
+        let buffer = &frame.buffer;
+        let gutter = |row: usize| {
+            let cells = &buffer.cells[row * buffer.width..][..3];
+            cells.iter().map(|cell| cell.c).collect::<String>()
+        };
+
+        // The cursor line's number lines up with the distances, which keep the width
+        // of the last line number
+        let top = 9 - frame.cursor.unwrap().0;
+        assert_eq!(gutter(9 - top), " 10");
+        assert_eq!(gutter(8 - top), "  1");
+        assert_eq!(gutter(11 - top), "  2");
+    }
+}
@@ -0,0 +1,117 @@
+use crate::ui::components::EditorView;
+use crate::ui::context::RenderContext;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::theme::Style;
+use crate::ui::truncate::truncate_end;
+use crate::ui::{Bounds, Drawable, Focusable};
+
+/// Hover information of the symbol under the cursor, drawn above it, or below it when
+/// there's no room above. Lines of code blocks take the color of strings
+pub struct HoverPopup {
+    /// Finds the cursor on screen
+    view: EditorView,
+}
+
+impl HoverPopup {
+    pub fn new() -> Self {
+        Self { view: EditorView::new() }
+    }
+}
+
+impl Drawable for HoverPopup {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
+        let Some(hover) = context.editor.hover else {
+            return Ok(());
+        };
+        let Bounds {
+            start_row,
+            start_col,
+            width,
+            height,
+        } = self.bounds(buffer, context);
+        let theme = &context.config.theme;
+        let style = Style::from(theme.colors.status.inner);
+        let code_style = Style {
+            foreground: theme.style_for_token("string").foreground,
+            ..style.clone()
+        };
+
+        for (index, line) in hover.lines.iter().skip(hover.scroll).take(height).enumerate() {
+            let line_style = if line.code { &code_style } else { &style };
+            let text = truncate_end(&format!(" {}", line.text), width);
+            buffer.set_text(start_row + index, start_col, &format!("{text:<width$}"), line_style);
+        }
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
+        let Some(hover) = context.editor.hover else {
+            return Bounds { start_row: 0, start_col: 0, width: 0, height: 0 };
+        };
+        let (row, column) = self.view.get_display_cursor(buffer, context);
+        let editor_height = context.editor.quickfix.editor_height(buffer.height);
+        let height = hover.height(editor_height);
+        let start_row = match row >= height {
+            true => row - height,
+            false => (row + 1).min(editor_height.saturating_sub(height)),
+        };
+        let widest = hover.lines.iter().map(|line| line.text.chars().count()).max().unwrap_or(0);
+        let width = (widest + 2).min(buffer.width);
+        Bounds {
+            start_row,
+            start_col: column.min(buffer.width - width),
+            width,
+            height,
+        }
+    }
+}
+
+impl Focusable for HoverPopup {
+    fn get_display_cursor(&self, buffer: &RenderBuffer, context: &RenderContext) -> (usize, usize) {
+        self.view.get_display_cursor(buffer, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::lsp::ShowHover;
+    use crate::editor::testing::TestEditor;
+    use lsp_types::{HoverContents, MarkupContent, MarkupKind};
+
+    #[tokio::test]
+    async fn test_hover_popup() {
+        let mut editor = TestEditor::new(&"\n".repeat(20));
+        editor.resize(30, 12);
+        editor.keys("5j").await.unwrap();
+        let value = (1..=8).map(|n| format!("line {n}")).collect::<Vec<_>>().join("\n");
+        let contents = HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value });
+        editor.execute(&ShowHover::new(contents)).await.unwrap();
+
+        let frame = editor.render().unwrap();
+        let buffer = &frame.buffer;
+        let row = |row: usize| {
+            let cells = &buffer.cells[row * buffer.width..(row + 1) * buffer.width];
+            cells.iter().map(|cell| cell.c).collect::<String>()
+        };
+        // Five rows fit above the cursor on the sixth, half of the editor area
+        let (cursor_row, column) = frame.cursor.unwrap();
+        assert_eq!(cursor_row, 5);
+        assert_eq!(&row(0)[column..column + 9], " line 1  ");
+        assert_eq!(&row(4)[column..column + 9], " line 5  ");
+
+        // `K` scrolls to the end, then a key closes the popup and does what it does
+        editor.keys("K").await.unwrap();
+        assert_eq!(editor.core.hover.as_ref().unwrap().scroll, 3);
+        editor.keys("K").await.unwrap();
+        assert!(editor.core.hover.is_none());
+        editor.execute(&ShowHover::new(HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::PlainText,
+            value: "one\ntwo".to_string(),
+        })))
+        .await
+        .unwrap();
+        editor.keys("j").await.unwrap();
+        assert!(editor.core.hover.is_none());
+        assert_eq!(editor.cursor().0, 6);
+    }
+}
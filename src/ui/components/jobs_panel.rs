@@ -0,0 +1,163 @@
+use crate::service::jobs::{JobState, JobSummary};
+use crate::ui::context::RenderContext;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::theme::Style;
+use crate::ui::truncate::truncate_end;
+use crate::ui::{Bounds, Drawable, Focusable};
+use std::time::Duration;
+
+const HEADER: &str = "  ID    TIME  STATUS     DESCRIPTION";
+const PROMPT: &str = "j/k select, x cancel, q close";
+
+/// The `:jobs` list of background operations, drawn over the bottom of the screen with
+/// the selected one highlighted. It's drawn again on every tick while open, so the
+/// times and outcomes stay current
+pub struct JobsPanel;
+
+/// `1.2s`, or `3m05s` from a minute on
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..60 => format!("{:.1}s", elapsed.as_secs_f64()),
+        _ => format!("{}m{:02}s", seconds / 60, seconds % 60),
+    }
+}
+
+fn format_job(job: &JobSummary) -> String {
+    let (status, outcome) = match &job.state {
+        JobState::Running => ("running", None),
+        JobState::Done(outcome) => ("done", Some(outcome)),
+        JobState::Failed(reason) => ("failed", Some(reason)),
+        JobState::Cancelled => ("cancelled", None),
+    };
+    let description = match outcome {
+        Some(outcome) => format!("{} ({outcome})", job.description),
+        None => job.description.clone(),
+    };
+    let elapsed = format_elapsed(job.elapsed);
+    format!("{:>4}  {elapsed:>6}  {status:<9}  {description}", job.id)
+}
+
+impl JobsPanel {
+    fn rows(context: &RenderContext) -> Vec<String> {
+        let jobs = context.editor.jobs.list();
+        if jobs.is_empty() {
+            return vec!["  No background jobs".to_string()];
+        }
+        jobs.iter().map(format_job).collect()
+    }
+}
+
+impl Drawable for JobsPanel {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
+        let Some(selected) = context.editor.jobs.selected else {
+            return Ok(());
+        };
+        let Bounds {
+            start_row,
+            width,
+            height,
+            ..
+        } = self.bounds(buffer, context);
+        let theme = &context.config.theme;
+        let style = theme.editor_style();
+        let mut selected_style = Style::from(theme.colors.status.normal);
+        selected_style.bold = true;
+
+        let rows = Self::rows(context);
+        // The header and prompt take a row each, the selection is kept in view
+        let shown = height.saturating_sub(2);
+        let selected = selected.min(rows.len() - 1);
+        let top = (selected + 1).saturating_sub(shown);
+
+        buffer.set_text(start_row, 0, &format!("{:<width$}", truncate_end(HEADER, width)), &style);
+        for (index, row) in rows.iter().enumerate().skip(top).take(shown) {
+            let row_style = if index == selected { &selected_style } else { &style };
+            let text = truncate_end(row, width);
+            buffer.set_text(start_row + 1 + index - top, 0, &format!("{text:<width$}"), row_style);
+        }
+        let prompt = truncate_end(PROMPT, width);
+        buffer.set_text(start_row + height - 1, 0, &format!("{prompt:<width$}"), &style);
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
+        // Closed, the whole screen is cleared like the pager's, to be drawn again
+        let lines = match context.editor.jobs.selected {
+            Some(_) => (Self::rows(context).len() + 2).min(buffer.height),
+            None => buffer.height,
+        };
+        Bounds {
+            start_row: buffer.height - lines,
+            start_col: 0,
+            width: buffer.width,
+            height: lines,
+        }
+    }
+}
+
+impl Focusable for JobsPanel {
+    fn get_display_cursor(&self, buffer: &RenderBuffer, _context: &RenderContext) -> (usize, usize) {
+        (buffer.height - 1, PROMPT.len().min(buffer.width - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::testing::{Frame, TestEditor};
+
+    fn rows(frame: &Frame) -> Vec<String> {
+        let buffer = &frame.buffer;
+        buffer
+            .cells
+            .chunks(buffer.width)
+            .map(|row| row.iter().map(|cell| cell.c).collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_format_job() {
+        let mut job = JobSummary {
+            id: 3,
+            description: "Indexing symbols".to_string(),
+            state: JobState::Running,
+            elapsed: Duration::from_millis(1300),
+        };
+        assert_eq!(format_job(&job), "   3    1.3s  running    Indexing symbols");
+
+        job.state = JobState::Done("120 symbols".to_string());
+        job.elapsed = Duration::from_secs(185);
+        assert_eq!(format_job(&job), "   3   3m05s  done       Indexing symbols (120 symbols)");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_from_panel() {
+        let mut editor = TestEditor::new("");
+        let done = editor.core.jobs.start("Indexing symbols".to_string());
+        done.finish(JobState::Done("3 symbols".to_string()));
+        let running = editor.core.jobs.start("sleep 60".to_string());
+        let token = running.token();
+
+        editor.resize(60, 8);
+        editor.keys(":jobs<Enter>").await.unwrap();
+        let frame = editor.render().unwrap();
+        let rows = rows(&frame);
+        assert_eq!(rows[4], HEADER);
+        assert!(rows[5].ends_with("done       Indexing symbols (3 symbols)"), "{rows:?}");
+        assert!(rows[6].ends_with("running    sleep 60"), "{rows:?}");
+        assert_eq!(frame.cursor, Some((7, PROMPT.len())));
+
+        // The finished job can't be cancelled, the running one below it can
+        editor.keys("x").await.unwrap();
+        assert!(!token.is_cancelled());
+        editor.keys("jjx").await.unwrap();
+        assert!(token.is_cancelled());
+        running.finish(JobState::Cancelled);
+
+        editor.keys("q").await.unwrap();
+        assert!(editor.core.jobs.selected.is_none());
+        editor.keys("ihi<Esc>").await.unwrap();
+        assert_eq!(editor.text(), "hi");
+    }
+}
@@ -1,18 +1,24 @@
-mod command_line;
+mod bottom_bar;
+mod completion_menu;
 mod editor_view;
 mod gutter;
-mod message_area;
-mod pending_keys;
-mod search_box;
+mod hover_popup;
+mod jobs_panel;
+mod pager;
+mod quickfix_panel;
+mod scrollbar;
 mod status_line;
 
 use std::rc::Rc;
 
-pub use command_line::CommandLine;
+pub use bottom_bar::BottomBar;
+pub use completion_menu::CompletionMenu;
 pub use editor_view::EditorView;
-pub use message_area::MessageArea;
-pub use pending_keys::PendingKeys;
-pub use search_box::SearchBox;
+pub use hover_popup::HoverPopup;
+pub use jobs_panel::JobsPanel;
+pub use pager::Pager;
+pub use quickfix_panel::QuickfixPanel;
+pub use scrollbar::line_at_row;
 pub use status_line::StatusLine;
 
 use crate::ui::{Drawable, Focusable};
@@ -20,6 +26,8 @@ use crate::ui::{Drawable, Focusable};
 pub struct Component {
     pub dirty: bool,
     pub visible: bool,
+    /// Drawn over the other components, after them
+    pub overlay: bool,
     pub(in crate::ui) drawable: Rc<dyn Drawable>,
     pub(in crate::ui) focusable: Option<Rc<dyn Focusable>>,
 }
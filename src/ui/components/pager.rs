@@ -0,0 +1,121 @@
+use crate::ui::context::RenderContext;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::truncate::truncate_end;
+use crate::ui::{Bounds, Drawable, Focusable};
+
+const MORE: &str = "-- More --";
+const END: &str = "Press ENTER to continue";
+
+/// Output of a command too long for the message area, drawn over the bottom of the
+/// screen a page at a time, with the prompt on the last row
+pub struct Pager;
+
+impl Pager {
+    fn prompt(buffer: &RenderBuffer, context: &RenderContext) -> &'static str {
+        let at_end = context
+            .diagnostics
+            .message_manager
+            .output()
+            .is_none_or(|output| output.at_end(buffer.height - 1));
+        if at_end { END } else { MORE }
+    }
+}
+
+impl Drawable for Pager {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
+        let Some(output) = context.diagnostics.message_manager.output() else {
+            return Ok(());
+        };
+        let Bounds {
+            start_row,
+            width,
+            height,
+            ..
+        } = self.bounds(buffer, context);
+        let style = context.config.theme.editor_style();
+
+        let lines = output.lines.iter().skip(output.top).take(height - 1);
+        for (row, line) in lines.enumerate() {
+            let line = truncate_end(line, width);
+            buffer.set_text(start_row + row, 0, &format!("{line:<width$}"), &style);
+        }
+        let prompt = Self::prompt(buffer, context);
+        buffer.set_text(start_row + height - 1, 0, &format!("{prompt:<width$}"), &style);
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
+        // Short output only covers the rows it needs, the rest of the screen stays.
+        // Once closed the whole screen is cleared, as the output is gone and every
+        // component is drawn again anyway
+        let lines = context
+            .diagnostics
+            .message_manager
+            .output()
+            .map_or(buffer.height, |output| output.lines.len().min(buffer.height - 1) + 1);
+        Bounds {
+            start_row: buffer.height - lines,
+            start_col: 0,
+            width: buffer.width,
+            height: lines,
+        }
+    }
+}
+
+impl Focusable for Pager {
+    fn get_display_cursor(&self, buffer: &RenderBuffer, context: &RenderContext) -> (usize, usize) {
+        let column = Self::prompt(buffer, context).len();
+        (buffer.height - 1, column.min(buffer.width - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::testing::{Frame, TestEditor};
+
+    fn rows(frame: &Frame) -> Vec<String> {
+        let buffer = &frame.buffer;
+        buffer
+            .cells
+            .chunks(buffer.width)
+            .map(|row| row.iter().map(|cell| cell.c).collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_paging() {
+        let content = (1..=12).map(|line| format!("line {line}\n")).collect::<String>();
+        let mut editor = TestEditor::new(&content);
+        editor.resize(30, 6);
+
+        // Five rows of output fit above the prompt
+        editor.keys(":g/line/p<Enter>").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(rows(&frame), ["line 1", "line 2", "line 3", "line 4", "line 5", "-- More --"]);
+        assert_eq!(frame.cursor, Some((5, 10)));
+
+        editor.keys("<Enter> ").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(rows(&frame)[0], "line 7");
+        editor.keys("b").await.unwrap();
+        assert_eq!(rows(&editor.render().unwrap())[0], "line 2");
+
+        // The last page stays until a key is pressed on it
+        editor.keys("G").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(rows(&frame)[0], "line 8");
+        assert_eq!(rows(&frame)[5], "Press ENTER to continue");
+        editor.keys(" ").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(rows(&frame)[0], " 10 line 10");
+        assert_eq!(frame.cursor, Some((2, 4)));
+
+        // Short output only covers the bottom rows
+        editor.keys(":1,2p<Enter>").await.unwrap();
+        let frame = editor.render().unwrap();
+        assert_eq!(rows(&frame)[2], "  3 line 3");
+        assert_eq!(rows(&frame)[3..], ["line 1", "line 2", "Press ENTER to continue"]);
+        editor.keys("q").await.unwrap();
+        assert!(editor.message_manager.output().is_none());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::ui::components::status_line::relative_path;
+use crate::ui::context::RenderContext;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::theme::Style;
+use crate::ui::truncate::truncate_end;
+use crate::ui::{Bounds, Drawable};
+use lsp_types::DiagnosticSeverity;
+
+/// The `:copen` list of diagnostics across files, between the status line and the
+/// command line. The text above takes fewer rows while it's open, and the entry
+/// `:cnext` and `:cprev` went to is highlighted
+pub struct QuickfixPanel;
+
+fn severity_name(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "info",
+        DiagnosticSeverity::HINT => "hint",
+        _ => "error",
+    }
+}
+
+impl Drawable for QuickfixPanel {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
+        let quickfix = context.editor.quickfix;
+        let Bounds {
+            start_row,
+            width,
+            height,
+            ..
+        } = self.bounds(buffer, context);
+        if height == 0 {
+            return Ok(());
+        }
+        let theme = &context.config.theme;
+        let style = theme.editor_style();
+        let title_style = Style::from(theme.colors.status.inner);
+        let mut selected_style = Style::from(theme.colors.status.normal);
+        selected_style.bold = true;
+
+        let count = quickfix.entries.len();
+        let title = match quickfix.selected {
+            Some(selected) => format!(" Quickfix  {} of {count}", selected + 1),
+            None if count == 1 => " Quickfix  1 diagnostic".to_string(),
+            None => format!(" Quickfix  {count} diagnostics"),
+        };
+        buffer.set_text(start_row, 0, &format!("{:<width$}", truncate_end(&title, width)), &title_style);
+
+        // The title takes a row, the selection is kept in view
+        let shown = height - 1;
+        let top = quickfix.selected.map_or(0, |selected| (selected + 1).saturating_sub(shown));
+        for row in 0..shown {
+            let index = top + row;
+            let (text, row_style) = match quickfix.entries.get(index) {
+                Some(entry) => {
+                    let text = format!(
+                        " {}:{}:{} {}: {}",
+                        relative_path(&entry.path),
+                        entry.position.line + 1,
+                        entry.position.character + 1,
+                        severity_name(entry.severity),
+                        entry.message
+                    );
+                    let row_style = if quickfix.selected == Some(index) { &selected_style } else { &style };
+                    (text, row_style)
+                }
+                None if count == 0 && row == 0 => (" No diagnostics".to_string(), &style),
+                None => (String::new(), &style),
+            };
+            let text = truncate_end(&text, width);
+            buffer.set_text(start_row + 1 + row, 0, &format!("{text:<width$}"), row_style);
+        }
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
+        let quickfix = context.editor.quickfix;
+        Bounds {
+            // Below the status line, which stays with the text
+            start_row: quickfix.editor_height(buffer.height) + 1,
+            start_col: 0,
+            width: buffer.width,
+            height: quickfix.rows(buffer.height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::lsp::UpdateDiagnostics;
+    use crate::editor::testing::{Frame, TestEditor};
+    use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+    fn rows(frame: &Frame) -> Vec<String> {
+        let buffer = &frame.buffer;
+        buffer
+            .cells
+            .chunks(buffer.width)
+            .map(|row| row.iter().map(|cell| cell.c).collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_panel_below_status_line() {
+        let mut editor = TestEditor::new("one\ntwo\n");
+        editor.resize(40, 12);
+        let diagnostic = Diagnostic {
+            range: Range {
+                start: Position { line: 1, character: 2 },
+                end: Position { line: 1, character: 3 },
+            },
+            severity: Some(DiagnosticSeverity::WARNING),
+            message: "unused\nmore".to_string(),
+            ..Default::default()
+        };
+        let uri = "file:///src/main.rs".to_string();
+        editor.execute(&UpdateDiagnostics::new(Some(uri), vec![diagnostic])).await.unwrap();
+        editor.keys(":copen<Enter>").await.unwrap();
+
+        // The text gets five of the ten rows the status and command lines leave
+        let screen = rows(&editor.render().unwrap());
+        assert_eq!(editor.core.viewport.height(), 5);
+        assert!(screen[5].contains("NORMAL"));
+        assert_eq!(screen[6], " Quickfix  1 diagnostic");
+        assert_eq!(screen[7], " /src/main.rs:2:3 warning: unused");
+
+        editor.keys(":cclose<Enter>").await.unwrap();
+        let screen = rows(&editor.render().unwrap());
+        assert_eq!(editor.core.viewport.height(), 10);
+        assert!(screen[10].contains("NORMAL"));
+        assert!(screen.iter().all(|row| !row.contains("Quickfix")));
+    }
+}
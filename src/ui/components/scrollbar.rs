@@ -0,0 +1,133 @@
+use crate::ui::context::RenderContext;
+use crate::ui::render_buffer::RenderBuffer;
+use crate::ui::theme::Style;
+use crate::ui::{Bounds, Drawable};
+use anyhow::Result;
+use lsp_types::DiagnosticSeverity;
+use std::ops::Range;
+
+const MARK: char = '■';
+
+/// One column on the right edge of the editor view: the track stands for the whole
+/// file, the thumb for the lines in the viewport, and marks for diagnostics
+pub struct Scrollbar;
+
+impl Scrollbar {
+    pub fn get_width(&self, context: &RenderContext) -> usize {
+        usize::from(context.config.scrollbar)
+    }
+}
+
+/// Rows of the track covered by the thumb, None when the whole file fits in the view
+pub fn thumb(top_line: usize, height: usize, line_count: usize) -> Option<Range<usize>> {
+    if line_count <= height || height == 0 {
+        return None;
+    }
+    let size = (height * height).div_ceil(line_count).clamp(1, height);
+    // The thumb only reaches the bottom once the last line is shown
+    let start = if top_line + height >= line_count {
+        height - size
+    } else {
+        (top_line * height / line_count).min(height - size - 1)
+    };
+    Some(start..start + size)
+}
+
+/// Track row standing for the line. In a file shorter than the view it is the row
+/// the line is drawn on
+pub fn row_of_line(line: usize, height: usize, line_count: usize) -> usize {
+    (line * height / line_count.max(height)).min(height.saturating_sub(1))
+}
+
+/// First line of the part of the file a track row stands for
+pub fn line_at_row(row: usize, height: usize, line_count: usize) -> usize {
+    (row * line_count.max(height) / height.max(1)).min(line_count.saturating_sub(1))
+}
+
+impl Drawable for Scrollbar {
+    fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> Result<()> {
+        if !context.config.scrollbar {
+            return Ok(());
+        }
+        let Bounds {
+            start_col, height, ..
+        } = self.bounds(buffer, context);
+        let colors = &context.config.theme.colors.scrollbar;
+        let line_count = context.editor.document.buffer.line_count();
+        let thumb = thumb(context.editor.viewport.top_line(), height, line_count);
+
+        let styles = (0..height)
+            .map(|row| {
+                let in_thumb = thumb.as_ref().is_some_and(|thumb| thumb.contains(&row));
+                Style::from(if in_thumb { colors.thumb } else { colors.track })
+            })
+            .collect::<Vec<_>>();
+        for (row, style) in styles.iter().enumerate() {
+            buffer.set_cell(row, start_col, ' ', style);
+        }
+
+        // The most severe diagnostic of each row, errors drawn over warnings
+        let mut marks: Vec<Option<DiagnosticSeverity>> = vec![None; height];
+        for diagnostic in context.diagnostics.diagnostics {
+            let severity = diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR);
+            if severity > DiagnosticSeverity::WARNING {
+                continue;
+            }
+            let row = row_of_line(diagnostic.range.start.line as usize, height, line_count);
+            if let Some(mark) = marks.get_mut(row)
+                && mark.is_none_or(|existing| severity < existing)
+            {
+                *mark = Some(severity);
+            }
+        }
+        for (row, severity) in marks.into_iter().enumerate() {
+            let Some(severity) = severity else { continue };
+            let mut style = context.config.theme.get_diagnostic_style(&severity);
+            style.background = styles[row].background;
+            buffer.set_cell(row, start_col, MARK, &style);
+        }
+        Ok(())
+    }
+
+    fn bounds(&self, buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
+        let width = self.get_width(context);
+        Bounds {
+            start_row: 0,
+            start_col: buffer.width.saturating_sub(width),
+            width,
+            height: context.editor.quickfix.editor_height(buffer.height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumb() {
+        // A file that fits has no thumb
+        assert_eq!(thumb(0, 10, 10), None);
+        assert_eq!(thumb(0, 10, 3), None);
+
+        assert_eq!(thumb(0, 10, 100), Some(0..1));
+        assert_eq!(thumb(0, 10, 20), Some(0..5));
+        assert_eq!(thumb(5, 10, 20), Some(2..7));
+        assert_eq!(thumb(10, 10, 20), Some(5..10));
+        // Only the last page puts the thumb at the bottom
+        assert_eq!(thumb(985, 10, 1000), Some(8..9));
+        assert_eq!(thumb(990, 10, 1000), Some(9..10));
+    }
+
+    #[test]
+    fn test_rows_and_lines() {
+        assert_eq!(row_of_line(0, 10, 100), 0);
+        assert_eq!(row_of_line(55, 10, 100), 5);
+        assert_eq!(row_of_line(99, 10, 100), 9);
+        assert_eq!(row_of_line(3, 10, 5), 3);
+
+        assert_eq!(line_at_row(5, 10, 100), 50);
+        assert_eq!(line_at_row(9, 10, 100), 90);
+        assert_eq!(line_at_row(8, 10, 5), 4);
+    }
+}
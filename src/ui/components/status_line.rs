@@ -1,12 +1,99 @@
-use crate::constants::RESERVED_ROW_COUNT;
+use crate::config::editor::StatusSegment;
+use crate::core::command::Completion;
+use crate::core::encoding::FileEncoding;
+use crate::core::language::Language;
 use crate::core::mode::Mode;
 use crate::ui::context::RenderContext;
 use crate::ui::render_buffer::RenderBuffer;
 use crate::ui::theme::Style;
+use crate::ui::truncate::{layout, text_width, Segment};
 use crate::ui::{Bounds, Drawable};
 use anyhow::Ok;
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+use std::cell::RefCell;
+use std::path::Path;
 
-pub struct StatusLine;
+#[derive(Debug, Clone, Copy, Default)]
+struct DiagnosticCounts {
+    errors: usize,
+    warnings: usize,
+}
+
+impl DiagnosticCounts {
+    fn from_diagnostics(diagnostics: &[Diagnostic]) -> Self {
+        let mut counts = Self::default();
+        for diagnostic in diagnostics {
+            match diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR) {
+                DiagnosticSeverity::ERROR => counts.errors += 1,
+                DiagnosticSeverity::WARNING => counts.warnings += 1,
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+pub struct StatusLine {
+    // Diagnostic counts keyed by the document URI and the diagnostics generation
+    diagnostic_cache: RefCell<Option<(Option<String>, usize, DiagnosticCounts)>>,
+}
+
+impl StatusLine {
+    pub fn new() -> Self {
+        Self {
+            diagnostic_cache: RefCell::new(None),
+        }
+    }
+
+    fn diagnostic_counts(&self, context: &RenderContext) -> DiagnosticCounts {
+        let uri = context.editor.document.get_uri();
+        let generation = context.diagnostics.generation;
+        let mut cache = self.diagnostic_cache.borrow_mut();
+
+        if let Some((cached_uri, cached_generation, counts)) = cache.as_ref()
+            && *cached_uri == uri
+            && *cached_generation == generation
+        {
+            return *counts;
+        }
+
+        let counts = DiagnosticCounts::from_diagnostics(context.diagnostics.diagnostics);
+        *cache = Some((uri, generation, counts));
+        counts
+    }
+
+    fn diagnostic_segments(&self, context: &RenderContext) -> Vec<Segment> {
+        let counts = self.diagnostic_counts(context);
+        let theme = &context.config.theme;
+        let signs = &context.config.diagnostic_signs;
+        let background = theme.colors.status.inner.background;
+
+        [
+            (counts.errors, &signs.error, DiagnosticSeverity::ERROR),
+            (counts.warnings, &signs.warning, DiagnosticSeverity::WARNING),
+        ]
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, sign, severity)| {
+            let mut style = theme.get_diagnostic_style(&severity);
+            style.background = background;
+            Segment::new(format!(" {sign}{count}"), style, priority::DIAGNOSTICS)
+        })
+        .collect()
+    }
+}
+
+/// What stays longest on a narrow status line
+mod priority {
+    pub const MODE: u8 = 50;
+    pub const POSITION: u8 = 40;
+    pub const PENDING_COMMAND: u8 = 35;
+    pub const FILE: u8 = 30;
+    pub const DIAGNOSTICS: u8 = 20;
+    pub const PERCENT: u8 = 15;
+    pub const LANGUAGE: u8 = 10;
+    pub const ENCODING: u8 = 5;
+}
 
 impl Drawable for StatusLine {
     fn draw(&self, buffer: &mut RenderBuffer, context: &mut RenderContext) -> anyhow::Result<()> {
@@ -15,19 +102,12 @@ impl Drawable for StatusLine {
         } = self.bounds(buffer, context);
         let document = &context.editor.document;
         let theme = &context.config.theme;
-
-        let left = format!(" {} ", context.editor.mode.to_name().to_uppercase());
-
-        let (row, column) = context.editor.cursor.get_display_cursor();
-        let right = format!(" {}:{} ", row + 1, column + 1);
-
-        let file = format!(
-            " {}{}",
-            document.file_name().as_deref().unwrap_or("new file"),
-            if document.modified { " [+]" } else { "" }
-        );
-        let center_width = width - left.len() - right.len();
-        let center = format!("{file:<center_width$}");
+        if let (Mode::Command, Some(completion)) =
+            (context.editor.mode, context.input.command_buffer.completion())
+        {
+            draw_completion(buffer, context, completion, start_row, width);
+            return Ok(());
+        }
 
         let colors = match context.editor.mode {
             Mode::Normal => theme.colors.status.normal,
@@ -35,25 +115,296 @@ impl Drawable for StatusLine {
             Mode::Command => theme.colors.status.command,
             Mode::Search => theme.colors.status.search,
             Mode::OperationPending(_) => theme.colors.status.normal,
+            Mode::VisualLine => theme.colors.status.visual,
         };
-
         let mut outer = Style::from(colors);
         outer.bold = true;
         let inner = Style::from(theme.colors.status.inner);
 
-        buffer.set_text(start_row, 0, &left, &outer);
-        buffer.set_text(start_row, left.len(), &center, &inner);
-        buffer.set_text(start_row, left.len() + center_width, &right, &outer);
+        let (row, column) = context.editor.cursor.get_display_cursor();
+        let mut segments = Vec::new();
+        for segment in &context.config.statusline.0 {
+            match segment {
+                StatusSegment::Mode => {
+                    let mode = context.editor.mode.to_name().to_uppercase();
+                    let initial = mode.chars().next().unwrap_or_default();
+                    segments.push(
+                        Segment::new(format!(" {mode} "), outer.clone(), priority::MODE)
+                            .or(format!(" {initial} ")),
+                    );
+                }
+                // The path from the working directory, then the file name, then only
+                // the flags
+                StatusSegment::File => {
+                    let flags = format!(
+                        "{}{}{}",
+                        if document.is_modified() { " [+]" } else { "" },
+                        if document.is_read_only() { " [RO]" } else { "" },
+                        if document.is_large_file() { " [big file]" } else { "" }
+                    );
+                    let name = document.file_name().unwrap_or_else(|| "new file".to_string());
+                    let path = document
+                        .path
+                        .as_deref()
+                        .map_or_else(|| name.clone(), relative_path);
+                    segments.push(
+                        Segment::new(format!(" {path}{flags}"), inner.clone(), priority::FILE)
+                            .or(format!(" {name}{flags}"))
+                            .or(flags)
+                            .fill(),
+                    );
+                }
+                StatusSegment::Language if document.language != Language::PlainText => {
+                    let language = format!(" {}", document.language.to_str());
+                    segments.push(Segment::new(language, inner.clone(), priority::LANGUAGE));
+                }
+                StatusSegment::Encoding if document.encoding != FileEncoding::default() => {
+                    let encoding = format!(" {}", document.encoding);
+                    segments.push(Segment::new(encoding, inner.clone(), priority::ENCODING));
+                }
+                StatusSegment::Diagnostics => segments.extend(self.diagnostic_segments(context)),
+                StatusSegment::Command => {
+                    let mode = context.editor.mode;
+                    if let Some(command) = context.input.input_state.pending_command(mode) {
+                        let command = format!(" {command} ");
+                        segments.push(Segment::new(command, inner.clone(), priority::PENDING_COMMAND));
+                    }
+                }
+                StatusSegment::Percent => {
+                    let line_count = document.buffer.line_count();
+                    let percent = match row {
+                        0 => "Top".to_string(),
+                        row if row + 1 >= line_count => "Bot".to_string(),
+                        row => format!("{}%", (row + 1) * 100 / line_count),
+                    };
+                    segments.push(Segment::new(format!(" {percent} "), inner.clone(), priority::PERCENT));
+                }
+                StatusSegment::Position => {
+                    let line_number = document.backend.line_number(row);
+                    let position = format!(" {}:{} ", line_number, column + 1);
+                    segments.push(Segment::new(position, outer.clone(), priority::POSITION));
+                }
+                StatusSegment::Language | StatusSegment::Encoding => {}
+            }
+        }
+        // Without the file the space left is at the end
+        if !context.config.statusline.0.contains(&StatusSegment::File) {
+            segments.push(Segment::new("", inner, priority::FILE).fill());
+        }
+
+        let mut column = 0;
+        for (text, style) in layout(&segments, width) {
+            buffer.set_text(start_row, column, &text, &style);
+            column += text_width(&text);
+        }
 
         Ok(())
     }
 
-    fn bounds(&self, render_buffer: &RenderBuffer, _context: &RenderContext) -> Bounds {
+    fn bounds(&self, render_buffer: &RenderBuffer, context: &RenderContext) -> Bounds {
         Bounds {
-            start_row: render_buffer.height - RESERVED_ROW_COUNT,
+            start_row: context.editor.quickfix.editor_height(render_buffer.height),
             start_col: 0,
             width: render_buffer.width,
             height: 1,
         }
     }
 }
+
+/// The path from the working directory when the file is in it, as it was opened otherwise
+pub(super) fn relative_path(path: &Path) -> String {
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|directory| path.strip_prefix(directory).ok().map(Path::to_path_buf));
+    relative.as_deref().unwrap_or(path).display().to_string()
+}
+
+/// The matches of a completion by their names, as vim's wildmenu shows them, scrolled
+/// to keep the selected one in view
+fn draw_completion(
+    buffer: &mut RenderBuffer,
+    context: &RenderContext,
+    completion: &Completion,
+    row: usize,
+    width: usize,
+) {
+    let theme = &context.config.theme;
+    let inner = Style::from(theme.colors.status.inner);
+    let mut selected_style = Style::from(theme.colors.status.command);
+    selected_style.bold = true;
+    let names = completion
+        .matches
+        .iter()
+        .map(|path| {
+            let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or(path);
+            let slash = if path.ends_with('/') { "/" } else { "" };
+            format!(" {name}{slash} ")
+        })
+        .collect::<Vec<_>>();
+
+    let selected = completion.selected;
+    let mut first = 0;
+    let span = |first: usize| names[first..=selected].iter().map(|name| text_width(name)).sum::<usize>();
+    while first < selected && span(first) > width {
+        first += 1;
+    }
+    buffer.set_text(row, 0, &" ".repeat(width), &inner);
+    let mut column = 0;
+    for (index, name) in names.iter().enumerate().skip(first) {
+        if column + text_width(name) > width {
+            break;
+        }
+        let style = if index == selected { &selected_style } else { &inner };
+        buffer.set_text(row, column, name, style);
+        column += text_width(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::system;
+    use crate::core::message::Message;
+    use crate::editor::testing::{Frame, TestEditor};
+    use std::path::PathBuf;
+
+    fn row(frame: &Frame, row: usize) -> String {
+        let buffer = &frame.buffer;
+        buffer.cells[row * buffer.width..(row + 1) * buffer.width]
+            .iter()
+            .map(|cell| cell.c)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_narrow_widths() {
+        let mut editor = TestEditor::new("fn main() {}\n");
+        let path = "src/deeply/nested/module.rs";
+        editor.core.current_document_mut().path = Some(PathBuf::from(path));
+        editor.keys(":set ft=rust<Enter>x\"a2").await.unwrap();
+        let message = "E: Something long went wrong while doing a thing, try again";
+        let action = system::ShowMessage(Message::error(message.to_string()));
+        editor.execute(&action).await.unwrap();
+
+        for width in 20..=200 {
+            editor.resize(width, 6);
+            let frame = editor.render().unwrap();
+            let status = row(&frame, 4);
+            assert!(status.starts_with(" NORMAL ") || status.starts_with(" N "), "{status:?}");
+            assert!(status.ends_with(" 1:1 ") && status.contains(" \"a2 "), "{status:?}");
+            if width >= 60 {
+                assert!(status.contains(" src/deeply/nested/module.rs [+] "), "{status:?}");
+            }
+            if width >= 30 {
+                assert!(status.contains("[+]"), "{status:?}");
+            }
+
+            let shown = row(&frame, 5);
+            let shown = shown.trim_end();
+            if message.chars().count() > width {
+                assert_eq!(shown.chars().count(), width, "{shown:?}");
+                assert!(shown.ends_with('…'), "{shown:?}");
+            } else {
+                assert_eq!(shown, message);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encoding() {
+        let mut editor = TestEditor::new("café\n");
+        editor.resize(60, 6);
+        assert!(!row(&editor.render().unwrap(), 4).contains("utf-8"));
+
+        editor.keys(":set fenc=utf-16le<Enter>").await.unwrap();
+        let status = row(&editor.render().unwrap(), 4);
+        assert!(status.ends_with(" utf-16le bom Top  1:1 "), "{status:?}");
+    }
+
+    #[tokio::test]
+    async fn test_configured_segments() {
+        use crate::config::Config;
+        use crate::config::editor::{StatusLineSegments, StatusSegment};
+
+        let statusline = StatusLineSegments(vec![StatusSegment::Position, StatusSegment::Percent]);
+        let config = Config { statusline, ..Default::default() };
+        let mut editor = TestEditor::with_config("one\ntwo\nthree\nfour\n", config);
+        editor.resize(30, 6);
+        let status = row(&editor.render().unwrap(), 4);
+        assert_eq!(status, format!("{:<30}", " 1:1  Top "));
+
+        // The file is named from the working directory, the rest after it on the right
+        let config = Config::default();
+        let mut editor = TestEditor::with_config("one\n", config);
+        let path = std::env::current_dir().unwrap().join("src/main.rs");
+        editor.core.current_document_mut().path = Some(path);
+        editor.resize(30, 6);
+        let status = row(&editor.render().unwrap(), 4);
+        assert_eq!(status, " NORMAL  src/main.rs Top  1:1 ");
+    }
+
+    #[tokio::test]
+    async fn test_stale_size() {
+        let mut editor = TestEditor::new("one\ntwo\n");
+        editor.resize(60, 8);
+        editor.render().unwrap();
+
+        // The terminal shrank without a resize event, the frame is drawn at its size
+        editor.set_terminal_size(40, 5);
+        let frame = editor.render().unwrap();
+        assert_eq!(frame.buffer.get_size(), (40, 5));
+        assert_eq!(editor.core.viewport.height(), 3);
+        assert_eq!(editor.relayouts, 2);
+        let status = row(&frame, 3);
+        assert!(status.starts_with(" NORMAL ") && status.ends_with(" 1:1 "), "{status:?}");
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic_counts() {
+        use super::{DiagnosticCounts, StatusLine};
+        use crate::actions::lsp::UpdateDiagnostics;
+        use crate::constants::components::STATUS_LINE;
+        use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+        let diagnostic = |severity| Diagnostic {
+            severity: Some(severity),
+            ..Default::default()
+        };
+        let mut editor = TestEditor::new("one
+two
+");
+        let path = std::env::temp_dir().join(format!("viron-{}-counts.rs", std::process::id()));
+        editor.core.current_document_mut().set_path(&path);
+        let uri = editor.core.current_document().get_uri();
+        editor.resize(60, 6);
+
+        // No diagnostics leave no segment, not a zero count
+        let status = row(&editor.render().unwrap(), 4);
+        assert!(!status.contains("E:") && !status.contains("W:"), "{status:?}");
+
+        // A published set redraws the status line with its counts
+        let errors = vec![diagnostic(DiagnosticSeverity::ERROR); 2];
+        editor.execute(&UpdateDiagnostics::new(uri.clone(), errors)).await.unwrap();
+        assert!(editor.ui.compositor.get_component_mut(STATUS_LINE).unwrap().dirty);
+        let status = row(&editor.render().unwrap(), 4);
+        assert!(status.contains(" E:2 ") && !status.contains("W:"), "{status:?}");
+
+        let mixed = vec![diagnostic(DiagnosticSeverity::WARNING), diagnostic(DiagnosticSeverity::ERROR)];
+        editor.execute(&UpdateDiagnostics::new(uri, mixed)).await.unwrap();
+        let status = row(&editor.render().unwrap(), 4);
+        assert!(status.contains(" E:1 W:1 "), "{status:?}");
+
+        // The counts are kept until the generation changes
+        let status_line = StatusLine::new();
+        let counts = status_line.diagnostic_counts(&editor.render_context());
+        assert_eq!((counts.errors, counts.warnings), (1, 1));
+        if let Some((_, _, cached)) = status_line.diagnostic_cache.borrow_mut().as_mut() {
+            *cached = DiagnosticCounts { errors: 9, warnings: 9 };
+        }
+        let counts = status_line.diagnostic_counts(&editor.render_context());
+        assert_eq!((counts.errors, counts.warnings), (9, 9));
+
+        editor.set_diagnostics(Vec::new());
+        let counts = status_line.diagnostic_counts(&editor.render_context());
+        assert_eq!((counts.errors, counts.warnings), (0, 0));
+    }
+}
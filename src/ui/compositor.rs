@@ -32,6 +32,7 @@ impl Compositor {
         let component = Component {
             dirty: true,
             visible,
+            overlay: false,
             drawable: Rc::new(drawable),
             focusable: None,
         };
@@ -50,12 +51,26 @@ impl Compositor {
         let component = Component {
             dirty: true,
             visible,
+            overlay: false,
             drawable,
             focusable: Some(focusable),
         };
         self.add_internal_component(id, component)
     }
 
+    /// Adds a hidden component drawn over the others while it is shown
+    pub fn add_overlay_component<C: Drawable + Focusable + 'static>(
+        &mut self,
+        id: &str,
+        drawable: C,
+    ) -> Result<String> {
+        self.add_focusable_component(id, drawable, false)?;
+        if let Some(component) = self.components.get_mut(id) {
+            component.overlay = true;
+        }
+        Ok(id.to_string())
+    }
+
     fn add_internal_component(&mut self, id: &str, component: Component) -> Result<String> {
         if self.components.contains_key(id) {
             return Err(anyhow!("Component already exists"));
@@ -126,28 +141,31 @@ impl Compositor {
         context: &mut RenderContext<'a>,
         writer: &mut W,
     ) -> Result<()> {
-        // Render all dirty components to the current buffer
-        for component in self.components.values_mut().filter(|c| c.dirty) {
-            if component.visible {
+        // Hidden components are cleared before visible ones are drawn, since they may
+        // share an area, like the command line and search box on the last row
+        for component in self.components.values().filter(|c| c.dirty && !c.visible) {
+            component
+                .drawable
+                .clear(&mut self.current_buffer, context)?;
+        }
+        for component in self.components.values().filter(|c| c.dirty && c.visible && !c.overlay) {
+            component.drawable.draw(&mut self.current_buffer, context)?;
+        }
+        // Anything drawn may have covered an overlay, so it's drawn again on top
+        if self.components.values().any(|c| c.dirty) {
+            for component in self.components.values().filter(|c| c.visible && c.overlay) {
                 component.drawable.draw(&mut self.current_buffer, context)?;
-            } else {
-                component
-                    .drawable
-                    .clear(&mut self.current_buffer, context)?;
             }
+        }
+        for component in self.components.values_mut() {
             component.dirty = false; // Clear dirty flag after rendering
         }
 
-        // If we have a previous buffer, do differential rendering
+        self.current_buffer.blank_split_wide_chars();
+
+        // Only what changed since the previous frame is written, everything without one
         let editor_style = context.config.theme.editor_style();
-        if let Some(ref previous) = self.previous_buffer {
-            for change in self.current_buffer.diff(previous) {
-                change.flush(writer, &editor_style)?
-            }
-        } else {
-            // No previous buffer, do full render
-            self.current_buffer.flush(writer, &editor_style)?;
-        }
+        self.current_buffer.flush(writer, &editor_style, self.previous_buffer.as_ref())?;
 
         // Store current buffer as previous for next diff
         self.previous_buffer = Some(self.current_buffer.clone());
@@ -162,6 +180,17 @@ impl Compositor {
         Some(focusable.get_display_cursor(&self.current_buffer, context))
     }
 
+    /// Frame drawn by the last render
+    #[cfg(any(test, feature = "bench"))]
+    pub fn current_buffer(&self) -> &RenderBuffer {
+        &self.current_buffer
+    }
+
+    /// Width and height of the frames drawn
+    pub fn size(&self) -> (usize, usize) {
+        self.current_buffer.get_size()
+    }
+
     // Force a full re-render (useful after resize or major changes)
     pub fn invalidate(&mut self) {
         self.previous_buffer = None;
@@ -1,11 +1,15 @@
 use crate::config::Config;
 use crate::core::command::{CommandBuffer, SearchBuffer};
+use crate::core::completions::Completions;
 use crate::core::cursor::Cursor;
 use crate::core::document::Document;
+use crate::core::hover::HoverText;
 use crate::core::message::MessageManager;
 use crate::core::mode::Mode;
+use crate::core::quickfix::QuickfixList;
 use crate::core::viewport::Viewport;
 use crate::input::InputProcessor;
+use crate::service::jobs::Jobs;
 use lsp_types::Diagnostic;
 
 pub struct EditorRenderContext<'a> {
@@ -13,6 +17,11 @@ pub struct EditorRenderContext<'a> {
     pub document: &'a mut Document,
     pub cursor: &'a Cursor,
     pub mode: &'a Mode,
+    pub jobs: &'a Jobs,
+    pub visual_anchor: usize,
+    pub completion: Option<&'a Completions>,
+    pub hover: Option<&'a HoverText>,
+    pub quickfix: &'a QuickfixList,
 }
 
 pub struct InputRenderContext<'a> {
@@ -23,6 +32,7 @@ pub struct InputRenderContext<'a> {
 
 pub struct DiagnosticRenderContext<'a> {
     pub diagnostics: &'a [Diagnostic],
+    pub generation: usize,
     pub message_manager: &'a MessageManager,
 }
 
@@ -0,0 +1,158 @@
+use crate::config::editor::ControlChars;
+use unicode_width::UnicodeWidthChar;
+
+/// Stands in the cell after a double-width character, which the terminal draws over
+pub const WIDE_CONTINUATION: char = '\0';
+
+/// Returns true for characters the terminal would act on instead of drawing,
+/// leaving out tab and newline which the editor handles itself
+pub fn is_control(c: char) -> bool {
+    c.is_ascii_control() && c != '\t' && c != '\n'
+}
+
+/// Cells the terminal draws a character in, two for wide ones like CJK and most emoji,
+/// none for the marks it combines with the character before
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(1)
+}
+
+/// Cells a character of the buffer is drawn as. A tab is blanks up to the next tab stop,
+/// a wide character is followed by a `WIDE_CONTINUATION` cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyph {
+    cells: [char; 2],
+    width: usize,
+    pub control: bool,
+}
+
+/// How the characters of a line are laid out on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub control_chars: ControlChars,
+    pub tab_width: usize,
+}
+
+impl Layout {
+    /// The glyph of a character drawn at a screen column of its line
+    pub fn glyph(&self, c: char, column: usize) -> Glyph {
+        if c != '\t' {
+            return Glyph::new(c, self.control_chars);
+        }
+        let tab_width = self.tab_width.max(1);
+        Glyph {
+            cells: [' ', ' '],
+            width: tab_width - column % tab_width,
+            control: false,
+        }
+    }
+}
+
+impl Glyph {
+    pub fn new(c: char, control_chars: ControlChars) -> Self {
+        if !is_control(c) {
+            return Self {
+                cells: [c, WIDE_CONTINUATION],
+                width: char_width(c),
+                control: false,
+            };
+        }
+
+        match control_chars {
+            ControlChars::Caret => {
+                // DEL is `^?`, the others are the character 64 places after them, `^@` to `^_`
+                let shown = if c == '\x7f' { '?' } else { (c as u8 + b'@') as char };
+                Self {
+                    cells: ['^', shown],
+                    width: 2,
+                    control: true,
+                }
+            }
+            ControlChars::Replacement => Self {
+                cells: [char::REPLACEMENT_CHARACTER, ' '],
+                width: 1,
+                control: true,
+            },
+        }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = char> + '_ {
+        self.cells.iter().copied().chain(std::iter::repeat(' ')).take(self.width)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
+
+/// Number of cells the text takes on screen, starting at the start of a line
+pub fn display_width(text: &str, layout: Layout) -> usize {
+    text.chars()
+        .fold(0, |column, c| column + layout.glyph(c, column).width())
+}
+
+/// Screen column of a character column of the line. Columns past the end of the line take one cell each
+pub fn display_column(line: &str, char_column: usize, layout: Layout) -> usize {
+    let width = line
+        .chars()
+        .take(char_column)
+        .fold(0, |column, c| column + layout.glyph(c, column).width());
+    width + char_column.saturating_sub(line.chars().count())
+}
+
+/// Screen rows the line takes when it wraps at the width
+pub fn wrapped_rows(line: &str, width: usize, layout: Layout) -> usize {
+    let line = line.trim_end_matches('\n');
+    display_width(line, layout).div_ceil(width.max(1)).max(1)
+}
+
+/// Row within a line wrapped over `rows` rows and column on it for a screen column of
+/// the line. A column past the last row stays on its last cell
+pub fn wrapped_position(column: usize, width: usize, rows: usize) -> (usize, usize) {
+    let width = width.max(1);
+    let row = (column / width).min(rows.saturating_sub(1));
+    (row, (column - row * width).min(width - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabs_reach_the_next_stop() {
+        let layout = Layout {
+            control_chars: ControlChars::Caret,
+            tab_width: 4,
+        };
+        assert_eq!(display_width("\tx", layout), 5);
+        assert_eq!(display_width("ab\tx", layout), 5);
+        assert_eq!(display_width("abcd\t", layout), 8);
+        assert_eq!(display_column("a\tb\x07\tc", 4, layout), 7);
+        assert_eq!(display_column("a\tb\x07\tc", 5, layout), 8);
+        assert_eq!(layout.glyph('\t', 6).cells().collect::<String>(), "  ");
+    }
+
+    #[test]
+    fn test_wide_and_combining_chars() {
+        let layout = Layout {
+            control_chars: ControlChars::Caret,
+            tab_width: 4,
+        };
+        assert_eq!(display_width("日\tx", layout), 5);
+        assert_eq!(display_column("e\u{301}日x", 3, layout), 3);
+        assert_eq!(layout.glyph('日', 0).cells().collect::<Vec<_>>(), ['日', WIDE_CONTINUATION]);
+        assert_eq!(layout.glyph('\u{301}', 1).cells().count(), 0);
+    }
+
+    #[test]
+    fn test_wrapped_lines() {
+        let layout = Layout {
+            control_chars: ControlChars::Caret,
+            tab_width: 4,
+        };
+        assert_eq!(wrapped_rows("\n", 4, layout), 1);
+        assert_eq!(wrapped_rows("abcd\n", 4, layout), 1);
+        assert_eq!(wrapped_rows("abcde\n", 4, layout), 2);
+        assert_eq!(wrapped_position(5, 4, 2), (1, 1));
+        assert_eq!(wrapped_position(8, 4, 2), (1, 3));
+    }
+}
@@ -3,9 +3,14 @@ use context::RenderContext;
 
 pub(crate) mod components;
 pub mod compositor;
+pub mod display;
+pub mod osc;
 pub mod render_buffer;
 pub mod theme;
+pub mod truncate;
 pub mod context;
+#[cfg(test)]
+mod snapshot_tests;
 
 pub struct Bounds {
     pub start_row: usize,
@@ -0,0 +1,47 @@
+use std::io::Write;
+use std::path::Path;
+
+/// Builds the OSC 7 sequence that tells the terminal the current directory.
+///
+/// The host is left empty, which terminals read as the local machine.
+pub fn current_directory(directory: &Path) -> String {
+    format!(
+        "\x1b]7;file://{}\x1b\\",
+        percent_encode(&directory.to_string_lossy())
+    )
+}
+
+/// Reports the directory to the terminal. Terminals without OSC 7 support
+/// ignore the sequence, so it is sent without checking for support first
+pub fn report_directory(directory: &Path) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(current_directory(directory).as_bytes())?;
+    stdout.flush()
+}
+
+/// Encodes every byte of the path except unreserved URI characters and `/`
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_directory_is_percent_encoded() {
+        assert_eq!(
+            current_directory(Path::new("/home/me/my project/%ü")),
+            "\x1b]7;file:///home/me/my%20project/%25%C3%BC\x1b\\"
+        );
+    }
+}
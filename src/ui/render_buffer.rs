@@ -1,6 +1,8 @@
+use super::display::{WIDE_CONTINUATION, char_width, is_control};
 use super::theme::Style;
 use anyhow::Result;
-use crossterm::{cursor, style, QueueableCommand};
+use crossterm::style::{Attribute, ContentStyle, Print, SetAttribute, SetStyle};
+use crossterm::{cursor, QueueableCommand};
 use std::fmt::{Debug, Write as DebugWrite};
 use std::io::Write;
 
@@ -10,22 +12,17 @@ pub struct Cell {
     pub style: Style,
 }
 
+/// A run of changed cells in a row, written after a single move of the cursor
 #[derive(Debug, Clone)]
 pub struct Change<'a> {
     pub x: usize,
     pub y: usize,
-    pub cell: &'a Cell,
+    pub cells: &'a [Cell],
 }
 
-impl<'a> Change<'a> {
-    pub(super) fn flush<W: Write>(&self, writer: &mut W, style: &Style) -> Result<()> {
-        let style = self.cell.style.to_content_style(&style);
-        let content = style::StyledContent::new(style, self.cell.c);
-        writer
-            .queue(cursor::MoveTo(self.x as u16, self.y as u16))?
-            .queue(style::Print(content))?;
-        Ok(())
-    }
+/// Keeps control characters that reached a cell from being written to the terminal
+fn printable(c: char) -> char {
+    if is_control(c) { char::REPLACEMENT_CHARACTER } else { c }
 }
 
 #[derive(Clone)]
@@ -41,7 +38,7 @@ impl Debug for RenderBuffer {
         for i in 0..self.height {
             let start = i * self.width;
             let end = start + self.width;
-            for cell in &self.cells[start..end] {
+            for cell in self.cells[start..end].iter().filter(|cell| cell.c != WIDE_CONTINUATION) {
                 let format = if cell.c == ' ' { '·' } else { cell.c };
                 f.write_char(format)?;
             }
@@ -97,24 +94,95 @@ impl RenderBuffer {
         }
     }
 
-    pub fn diff(&self, other: &Self) -> Vec<Change> {
+    /// Blanks out the halves of wide characters left without the other one, as by an
+    /// overlay drawn over one or a wrapped row splitting one, since the terminal
+    /// would draw the character whole over the cell next to it
+    pub(super) fn blank_split_wide_chars(&mut self) {
+        for row in self.cells.chunks_mut(self.width.max(1)) {
+            for column in 0..row.len() {
+                let next = row.get(column + 1).map(|next| next.c);
+                let whole = match row[column].c {
+                    WIDE_CONTINUATION => column > 0 && char_width(row[column - 1].c) == 2,
+                    c if char_width(c) == 2 => next == Some(WIDE_CONTINUATION),
+                    _ => true,
+                };
+                if !whole {
+                    row[column].c = ' ';
+                }
+            }
+        }
+    }
+
+    /// Runs of the cells that differ from the other frame of the same size, row by row.
+    /// Rows that stayed the same are skipped whole
+    pub fn diff<'a>(&'a self, other: &Self) -> Vec<Change<'a>> {
+        let width = self.width.max(1);
         let mut changes = Vec::new();
-        for (pos, cell) in self.cells.iter().enumerate() {
-            if *cell != other.cells[pos] {
-                let x = pos % self.width;
-                let y = pos / self.width;
-                changes.push(Change { x, y, cell });
+        for (y, (row, previous)) in self.cells.chunks(width).zip(other.cells.chunks(width)).enumerate() {
+            if row == previous {
+                continue;
+            }
+            let mut x = 0;
+            while x < row.len() {
+                if row[x] == previous[x] {
+                    x += 1;
+                    continue;
+                }
+                // The changed half of a wide character is written with the character
+                let start = match row[x].c {
+                    WIDE_CONTINUATION => x.saturating_sub(1),
+                    _ => x,
+                };
+                while x < row.len() && row[x] != previous[x] {
+                    x += 1;
+                }
+                changes.push(Change { x: start, y, cells: &row[start..x] });
             }
         }
         changes
     }
 
-    pub(super) fn flush<W: Write>(&self, writer: &mut W, editor_style: &Style) -> Result<()> {
-        writer.queue(cursor::MoveTo(0, 0))?;
-        for cell in self.cells.iter() {
-            let style = cell.style.to_content_style(editor_style);
-            let content = style::StyledContent::new(style, cell.c);
-            writer.queue(style::Print(content))?;
+    /// Writes the cells that changed since the previous frame, or all of them without
+    /// one. The style is only set where it differs from the cell written before, and
+    /// reset at the end
+    pub(super) fn flush<W: Write>(
+        &self,
+        writer: &mut W,
+        editor_style: &Style,
+        previous: Option<&Self>,
+    ) -> Result<()> {
+        let changes = match previous {
+            Some(previous) => self.diff(previous),
+            None => {
+                let rows = self.cells.chunks(self.width.max(1)).enumerate();
+                rows.map(|(y, cells)| Change { x: 0, y, cells }).collect()
+            }
+        };
+
+        let mut current: Option<ContentStyle> = None;
+        let mut text = String::new();
+        for change in changes {
+            writer.queue(cursor::MoveTo(change.x as u16, change.y as u16))?;
+            // Printing a wide character moves the terminal past the cell after it
+            for cell in change.cells.iter().filter(|cell| cell.c != WIDE_CONTINUATION) {
+                let style = cell.style.to_content_style(editor_style);
+                if current != Some(style) {
+                    if !text.is_empty() {
+                        writer.queue(Print(&text))?;
+                        text.clear();
+                    }
+                    writer.queue(SetAttribute(Attribute::Reset))?.queue(SetStyle(style))?;
+                    current = Some(style);
+                }
+                text.push(printable(cell.c));
+            }
+            if !text.is_empty() {
+                writer.queue(Print(&text))?;
+                text.clear();
+            }
+        }
+        if current.is_some() {
+            writer.queue(SetAttribute(Attribute::Reset))?;
         }
         Ok(())
     }
@@ -123,3 +191,55 @@ impl RenderBuffer {
         (self.width, self.height)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::style::Color;
+
+    #[test]
+    fn test_flush_changed_runs() {
+        let red = Style { foreground: Some(Color::Red), ..Default::default() };
+        let blue = Style { foreground: Some(Color::Blue), bold: true, ..Default::default() };
+        let previous = RenderBuffer::new(6, 3);
+        let mut current = previous.clone();
+        current.set_text(0, 1, "hi", &red);
+        current.set_cell(0, 3, 'x', &blue);
+        current.set_text(2, 4, "ok", &blue);
+        let mut written = Vec::new();
+        current.flush(&mut written, &Style::default(), Some(&previous)).unwrap();
+
+        let style = |style: &Style| SetStyle(style.to_content_style(&Style::default()));
+        let mut expected = Vec::new();
+        let reset = SetAttribute(Attribute::Reset);
+        crossterm::queue!(
+            expected,
+            cursor::MoveTo(1, 0),
+            reset,
+            style(&red),
+            Print("hi"),
+            reset,
+            style(&blue),
+            Print("x"),
+            // The row in between is skipped, and the style carries over to the next run
+            cursor::MoveTo(4, 2),
+            Print("ok"),
+            reset,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&written), String::from_utf8_lossy(&expected));
+
+        // Nothing is written for the same frame, the changed half of a wide character
+        // is written with the character
+        written.clear();
+        current.flush(&mut written, &Style::default(), Some(&current)).unwrap();
+        assert!(written.is_empty());
+        let mut wide = current.clone();
+        wide.set_text(1, 0, "日\0", &Style::default());
+        let mut changed = wide.clone();
+        changed.set_cell(1, 1, WIDE_CONTINUATION, &red);
+        let changes = changed.diff(&wide);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].x, changes[0].y, changes[0].cells.len()), (0, 1, 2));
+    }
+}
@@ -0,0 +1,185 @@
+//! Rendered frames compared with the golden files in `src/ui/snapshots`.
+//! Run with `UPDATE_SNAPSHOTS=1` to write the frames after an intended change
+use crate::actions::system::ShowMessage;
+use crate::core::message::Message;
+use crate::editor::testing::{Frame, TestEditor};
+use crate::ui::theme::{Style, Theme};
+use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use std::path::{Path, PathBuf};
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 10;
+
+const FIXTURE: &str = "\
+fn main() {
+    let value = compute(2);
+    println!(\"{value}\");
+}
+
+fn compute(x: u32) -> u32 {
+    x * 2
+}
+";
+
+fn editor(content: &str) -> TestEditor {
+    let mut editor = TestEditor::new(content);
+    editor.config.theme = Theme::embedded();
+    editor.core.current_document_mut().path = Some(PathBuf::from("src/main.rs"));
+    editor.resize(WIDTH, HEIGHT);
+    editor
+}
+
+/// Text of the frame with trailing blanks dropped, then the cursor position. With
+/// `styles`, each row is followed by a row naming the style of every cell with a
+/// letter, given in the order the styles first appear
+fn encode(frame: &Frame, styles: bool) -> String {
+    let buffer = &frame.buffer;
+    let mut seen: Vec<&Style> = Vec::new();
+    let mut out = String::new();
+    for row in buffer.cells.chunks(buffer.width) {
+        let text = row.iter().map(|cell| cell.c).collect::<String>();
+        out.push_str(text.trim_end());
+        out.push('\n');
+        if styles {
+            for cell in row {
+                let index = match seen.iter().position(|style| **style == cell.style) {
+                    Some(index) => index,
+                    None => {
+                        seen.push(&cell.style);
+                        seen.len() - 1
+                    }
+                };
+                out.push(char::from(b'a' + (index % 26) as u8));
+            }
+            out.push('\n');
+        }
+    }
+    match frame.cursor {
+        Some((row, column)) => out.push_str(&format!("cursor: {row}, {column}\n")),
+        None => out.push_str("cursor: hidden\n"),
+    }
+    out
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/ui/snapshots")
+        .join(format!("{name}.snap"));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        expected == actual,
+        "Frame differs from {}, rerun with UPDATE_SNAPSHOTS=1 to accept it\n\
+         --- expected\n{expected}--- actual\n{actual}",
+        path.display()
+    );
+}
+
+#[tokio::test]
+async fn test_fresh_open() {
+    let mut editor = editor(FIXTURE);
+    editor.keys(":set ft=rust<Enter>").await.unwrap();
+    editor.set_diagnostics(vec![Diagnostic {
+        range: Range::new(Position::new(1, 8), Position::new(1, 13)),
+        severity: Some(DiagnosticSeverity::WARNING),
+        message: "unused variable: `value`".to_string(),
+        ..Default::default()
+    }]);
+    let frame = editor.render().unwrap();
+    assert_snapshot("fresh_open", &encode(&frame, true));
+}
+
+#[tokio::test]
+async fn test_scrolled_viewport() {
+    let content = (1..=40).map(|line| format!("line {line}\n")).collect::<String>();
+    let mut editor = editor(&content);
+    editor.keys("30Gzz").await.unwrap();
+    let frame = editor.render().unwrap();
+    assert_snapshot("scrolled_viewport", &encode(&frame, false));
+}
+
+#[tokio::test]
+async fn test_insert_mode_typing() {
+    let mut editor = editor(FIXTURE);
+    editor.render().unwrap();
+    editor.keys("Gofn added() {}").await.unwrap();
+    let frame = editor.render().unwrap();
+    assert_snapshot("insert_mode_typing", &encode(&frame, false));
+}
+
+#[tokio::test]
+async fn test_search_highlight() {
+    let mut editor = editor(FIXTURE);
+    editor.keys("/compute<Enter>").await.unwrap();
+    let frame = editor.render().unwrap();
+    assert_snapshot("search_highlight", &encode(&frame, true));
+}
+
+#[tokio::test]
+async fn test_resize_to_narrow_width() {
+    let mut editor = editor(FIXTURE);
+    editor.keys("jj").await.unwrap();
+    editor.render().unwrap();
+    editor.resize(16, 6);
+    let frame = editor.render().unwrap();
+    assert_snapshot("resize_to_narrow_width", &encode(&frame, false));
+}
+
+#[tokio::test]
+async fn test_scrollbar() {
+    let content = (1..=40).map(|line| format!("line {line}\n")).collect::<String>();
+    let mut editor = editor(&content);
+    editor.config.scrollbar = true;
+    editor.set_diagnostics(vec![Diagnostic {
+        range: Range::new(Position::new(35, 0), Position::new(35, 4)),
+        severity: Some(DiagnosticSeverity::ERROR),
+        message: "expected item".to_string(),
+        ..Default::default()
+    }]);
+    editor.keys("12G").await.unwrap();
+    let frame = editor.render().unwrap();
+    assert_snapshot("scrollbar", &encode(&frame, true));
+}
+
+/// The last rows of the frame and the cursor, under a heading for the step
+fn bottom_rows(step: &str, frame: &Frame, rows: usize) -> String {
+    let encoded = encode(frame, false);
+    let lines = encoded.lines().collect::<Vec<_>>();
+    let shown = &lines[lines.len() - 1 - rows..];
+    format!("-- {step}\n{}\n", shown.join("\n"))
+}
+
+#[tokio::test]
+async fn test_bottom_bar() {
+    let mut editor = editor(FIXTURE);
+    let mut out = String::new();
+    let message = |text: &str| ShowMessage(Message::info(text.to_string()));
+
+    // A message arriving while a command is typed waits for the prompt to close
+    editor.keys(":wri").await.unwrap();
+    editor.execute(&message("indexed 2 symbols")).await.unwrap();
+    out += &bottom_rows("message while typing a command", &editor.render().unwrap(), 1);
+    editor.keys("<Esc>").await.unwrap();
+    out += &bottom_rows("after escape", &editor.render().unwrap(), 1);
+
+    // Pending keys show on the right when there is nothing else, and beside a search
+    editor.keys(":<Esc>\"a2").await.unwrap();
+    out += &bottom_rows("pending keys", &editor.render().unwrap(), 1);
+    editor.keys("<Esc>/compute<Enter>\"b").await.unwrap();
+    out += &bottom_rows("search result and pending keys", &editor.render().unwrap(), 1);
+
+    // Several queued messages and the command's error are shown together
+    editor.keys("<Esc>:set ft=nope").await.unwrap();
+    editor.execute(&message("indexed 2 symbols")).await.unwrap();
+    editor.execute(&message("server started")).await.unwrap();
+    editor.keys("<Enter>").await.unwrap();
+    out += &bottom_rows("queued messages and an error", &editor.render().unwrap(), 4);
+    editor.keys("q").await.unwrap();
+    out += &bottom_rows("after the output", &editor.render().unwrap(), 1);
+
+    assert_snapshot("bottom_bar", &out);
+}
@@ -1,5 +1,6 @@
+use crate::core::diff::DiffRowKind;
 use crate::ui::theme::vscode::VsCodeTheme;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use crossterm::style::{Attribute, Attributes, Color, Colors, ContentStyle};
 use std::collections::HashMap;
 use std::fs;
@@ -8,6 +9,10 @@ use lsp_types::DiagnosticSeverity;
 
 pub mod vscode;
 
+/// Theme used when the configured one can't be loaded
+pub const DEFAULT_THEME_NAME: &str = "catppuchin/mocha";
+pub const DEFAULT_THEME: &str = include_str!("../../../themes/catppuchin/mocha.json");
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Style {
     pub foreground: Option<Color>,
@@ -68,6 +73,13 @@ pub struct ThemeColors {
     pub gutter: Colors,
     pub status: StatusColors,
     pub diagnostic: DiagnosticColors,
+    /// Control characters shown in caret notation
+    pub control: Colors,
+    pub diff: DiffColors,
+    pub scrollbar: ScrollbarColors,
+    /// Background of the lines selected in visual mode
+    pub selection: Colors,
+    pub search: SearchColors,
 }
 
 impl Default for ThemeColors {
@@ -75,8 +87,13 @@ impl Default for ThemeColors {
         Self {
             editor: default_colors(),
             gutter: default_colors(),
+            control: default_colors(),
+            diff: Default::default(),
             status: Default::default(),
             diagnostic: Default::default(),
+            scrollbar: Default::default(),
+            selection: default_colors(),
+            search: Default::default(),
         }
     }
 }
@@ -94,6 +111,18 @@ impl From<&VsCodeTheme> for ThemeColors {
             },
             status: StatusColors::from(vscode),
             diagnostic: DiagnosticColors::from(vscode),
+            control: Colors {
+                foreground: vscode.get_color("terminal.ansiCyan"),
+                background: vscode.get_color("editor.background"),
+            },
+            diff: DiffColors::from(vscode),
+            scrollbar: ScrollbarColors::from(vscode),
+            // Opaque, unlike the `editor.selectionBackground` of most themes
+            selection: Colors {
+                foreground: None,
+                background: vscode.get_color("menu.selectionBackground"),
+            },
+            search: SearchColors::from(vscode),
         }
     }
 }
@@ -104,6 +133,7 @@ pub struct StatusColors {
     pub insert: Colors,
     pub command: Colors,
     pub search: Colors,
+    pub visual: Colors,
     pub inner: Colors,
 }
 
@@ -114,6 +144,7 @@ impl Default for StatusColors {
             insert: default_colors(),
             command: default_colors(),
             search: default_colors(),
+            visual: default_colors(),
             inner: default_colors(),
         }
     }
@@ -148,11 +179,17 @@ impl From<&VsCodeTheme> for StatusColors {
             background: vscode.get_color("terminal.ansiMagenta"),
         };
 
+        let visual = Colors {
+            foreground: outer_foreground,
+            background: vscode.get_color("terminal.ansiCyan"),
+        };
+
         StatusColors {
             normal,
             insert,
             search,
             command,
+            visual,
             inner,
         }
     }
@@ -209,6 +246,114 @@ impl From<&VsCodeTheme> for DiagnosticColors {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct DiffColors {
+    pub header: Colors,
+    pub added: Colors,
+    pub removed: Colors,
+}
+
+impl Default for DiffColors {
+    fn default() -> Self {
+        Self {
+            header: default_colors(),
+            added: default_colors(),
+            removed: default_colors(),
+        }
+    }
+}
+
+impl From<&VsCodeTheme> for DiffColors {
+    fn from(vscode: &VsCodeTheme) -> Self {
+        let background = vscode.get_color("editor.background");
+        DiffColors {
+            header: Colors {
+                foreground: vscode.get_color("terminal.ansiBlue"),
+                background,
+            },
+            added: Colors {
+                foreground: vscode.get_color("gitDecoration.addedResourceForeground"),
+                background: vscode
+                    .get_color_with_alpha("diffEditor.insertedLineBackground", background.as_ref()),
+            },
+            removed: Colors {
+                foreground: vscode.get_color("gitDecoration.deletedResourceForeground"),
+                background: vscode
+                    .get_color_with_alpha("diffEditor.removedLineBackground", background.as_ref()),
+            },
+        }
+    }
+}
+
+/// Matches of the search, highlighted in the text
+#[derive(Debug, Clone)]
+pub struct SearchColors {
+    pub matched: Colors,
+    /// The match the cursor was put on
+    pub current: Colors,
+}
+
+impl Default for SearchColors {
+    fn default() -> Self {
+        Self {
+            matched: default_colors(),
+            current: default_colors(),
+        }
+    }
+}
+
+impl From<&VsCodeTheme> for SearchColors {
+    fn from(vscode: &VsCodeTheme) -> Self {
+        // Themes without find colors get terminal colors, with dark text on them
+        let colors = |key: &str, fallback: &str| match vscode.get_color(key) {
+            Some(background) => Colors {
+                foreground: None,
+                background: Some(background),
+            },
+            None => Colors {
+                foreground: vscode.get_color("editor.background"),
+                background: vscode.get_color(fallback),
+            },
+        };
+        SearchColors {
+            matched: colors("editor.findMatchHighlightBackground", "terminal.ansiYellow"),
+            current: colors("editor.findMatchBackground", "terminal.ansiRed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScrollbarColors {
+    pub track: Colors,
+    pub thumb: Colors,
+}
+
+impl Default for ScrollbarColors {
+    fn default() -> Self {
+        Self {
+            track: default_colors(),
+            thumb: default_colors(),
+        }
+    }
+}
+
+impl From<&VsCodeTheme> for ScrollbarColors {
+    fn from(vscode: &VsCodeTheme) -> Self {
+        let background = vscode.get_color("editor.background");
+        ScrollbarColors {
+            track: Colors {
+                foreground: None,
+                background: vscode.get_color("scrollbar.shadow").or(background),
+            },
+            thumb: Colors {
+                foreground: None,
+                background: vscode
+                    .get_color_with_alpha("scrollbarSlider.background", background.as_ref()),
+            },
+        }
+    }
+}
+
 impl Theme {
     pub fn style_for_token(&self, token_type: &str) -> Style {
         let mut style = self.editor_style();
@@ -225,12 +370,22 @@ impl Theme {
         style
     }
 
+    /// Loads a VS Code theme, naming the file and the location of a parse error on failure
     pub fn load_from_file(path: impl AsRef<std::path::Path>) -> Result<Theme> {
-        let reader = BufReader::new(fs::File::open(path)?);
-        let vscode: VsCodeTheme = serde_json::from_reader(reader)?;
+        let path = path.as_ref();
+        let file = fs::File::open(path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        let vscode: VsCodeTheme = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
         Ok(Theme::from(&vscode))
     }
 
+    /// The theme compiled into the editor
+    pub fn embedded() -> Theme {
+        let vscode: VsCodeTheme =
+            serde_json::from_str(DEFAULT_THEME).expect("Invalid embedded theme");
+        Theme::from(&vscode)
+    }
+
     pub fn editor_style(&self) -> Style {
         Style {
             foreground: self.colors.editor.foreground,
@@ -239,6 +394,28 @@ impl Theme {
         }
     }
 
+    pub fn control_style(&self) -> Style {
+        Style {
+            foreground: self.colors.control.foreground,
+            background: self.colors.control.background,
+            ..Default::default()
+        }
+    }
+
+    pub fn diff_style(&self, kind: DiffRowKind) -> Style {
+        let colors = match kind {
+            DiffRowKind::Context => return self.editor_style(),
+            DiffRowKind::Header => &self.colors.diff.header,
+            DiffRowKind::Added => &self.colors.diff.added,
+            DiffRowKind::Removed => &self.colors.diff.removed,
+        };
+        Style {
+            foreground: colors.foreground,
+            background: colors.background,
+            ..Default::default()
+        }
+    }
+
     pub fn get_diagnostic_style(&self, severity: &DiagnosticSeverity) -> Style {
         let colors = match severity {
             &DiagnosticSeverity::ERROR => &self.colors.diagnostic.error,
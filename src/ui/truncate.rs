@@ -0,0 +1,178 @@
+use crate::ui::theme::Style;
+
+const ELLIPSIS: char = '…';
+
+/// Cells the text takes on a row, one per character until wide characters are supported
+pub fn text_width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// The text cut to the width, ending with `…` when something was cut off
+pub fn truncate_end(text: &str, width: usize) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+    match width {
+        0 => String::new(),
+        width => text.chars().take(width - 1).chain([ELLIPSIS]).collect(),
+    }
+}
+
+/// The text cut to the width, starting with `…` when something was cut off
+pub fn truncate_start(text: &str, width: usize) -> String {
+    let length = text_width(text);
+    if length <= width {
+        return text.to_string();
+    }
+    match width {
+        0 => String::new(),
+        width => [ELLIPSIS]
+            .into_iter()
+            .chain(text.chars().skip(length - width + 1))
+            .collect(),
+    }
+}
+
+/// Part of a row, such as the mode or the file name of the status line, that can
+/// be shortened or dropped when the row is too narrow for all of them
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// Longest first
+    forms: Vec<String>,
+    style: Style,
+    /// Segments with the lowest priority are shortened and dropped first
+    priority: u8,
+    fill: bool,
+}
+
+impl Segment {
+    pub fn new(text: impl Into<String>, style: Style, priority: u8) -> Self {
+        Self {
+            forms: vec![text.into()],
+            style,
+            priority,
+            fill: false,
+        }
+    }
+
+    /// Adds a shorter form, used when the ones before it don't fit
+    pub fn or(mut self, shorter: impl Into<String>) -> Self {
+        let shorter = shorter.into();
+        if self.forms.last() != Some(&shorter) {
+            self.forms.push(shorter);
+        }
+        self
+    }
+
+    /// Pads the segment with the width the others leave, so the ones after it are
+    /// right aligned. Before taking a shorter form it is cut down with a leading `…`,
+    /// as long as that shows more of it than the shorter form would. The shortest
+    /// form is never cut
+    pub fn fill(mut self) -> Self {
+        self.fill = true;
+        self
+    }
+}
+
+/// Lays the segments out left to right in exactly the width. While they don't fit,
+/// the one with the lowest priority, the rightmost among equals, takes its next
+/// shorter form or is dropped when it has none left
+pub fn layout(segments: &[Segment], width: usize) -> Vec<(String, Style)> {
+    // Form of each segment, None once dropped
+    let mut chosen = vec![Some(0); segments.len()];
+    let form_width = |index: usize, form: Option<usize>| {
+        form.map_or(0, |form| text_width(&segments[index].forms[form]))
+    };
+    let used = |chosen: &[Option<usize>]| -> usize {
+        chosen.iter().enumerate().map(|(index, &form)| form_width(index, form)).sum()
+    };
+
+    let mut cut = None;
+    while used(&chosen) > width {
+        let Some(index) = (0..segments.len())
+            .filter(|&index| chosen[index].is_some())
+            .min_by_key(|&index| (segments[index].priority, std::cmp::Reverse(index)))
+        else {
+            break;
+        };
+        let form = chosen[index].map(|form| form + 1);
+        let shorter = form.filter(|&form| form < segments[index].forms.len());
+        let room = width.saturating_sub(used(&chosen) - form_width(index, chosen[index]));
+        if segments[index].fill && shorter.is_some() && room > form_width(index, shorter) + 1 {
+            cut = Some((index, room));
+            break;
+        }
+        chosen[index] = shorter;
+    }
+
+    let mut padding = width.saturating_sub(used(&chosen));
+    let mut row = Vec::new();
+    for (index, (segment, form)) in segments.iter().zip(&chosen).enumerate() {
+        // A dropped fill segment still pads, keeping the ones after it right aligned
+        let mut text = match form {
+            Some(form) => segment.forms[*form].clone(),
+            None if segment.fill => String::new(),
+            None => continue,
+        };
+        if let Some((_, room)) = cut.filter(|&(cut, _)| cut == index) {
+            text = truncate_start(&text, room);
+        }
+        if segment.fill {
+            text.extend(std::iter::repeat_n(' ', padding));
+            padding = 0;
+        }
+        row.push((text, segment.style.clone()));
+    }
+    if padding > 0 {
+        row.push((" ".repeat(padding), Style::default()));
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(segments: &[Segment], width: usize) -> String {
+        layout(segments, width)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate_end("message", 10), "message");
+        assert_eq!(truncate_end("message", 4), "mes…");
+        assert_eq!(truncate_end("message", 0), "");
+        assert_eq!(truncate_start(":write", 4), "…ite");
+        assert_eq!(truncate_start("é", 1), "é");
+    }
+
+    #[test]
+    fn test_layout() {
+        let style = Style::default();
+        let segments = [
+            Segment::new(" NORMAL ", style.clone(), 50).or(" N "),
+            Segment::new(" src/main.rs [+]", style.clone(), 30)
+                .or(" main.rs [+]")
+                .or(" [+]")
+                .fill(),
+            Segment::new(" rust", style.clone(), 10),
+            Segment::new(" 12:5 ", style, 40),
+        ];
+
+        assert_eq!(render(&segments, 40), " NORMAL  src/main.rs [+]      rust 12:5 ");
+        // The language goes first, then the file name is cut and shortened
+        assert_eq!(render(&segments, 30), " NORMAL  src/main.rs [+] 12:5 ");
+        assert_eq!(render(&segments, 28), " NORMAL …c/main.rs [+] 12:5 ");
+        assert_eq!(render(&segments, 26), " NORMAL  main.rs [+] 12:5 ");
+        assert_eq!(render(&segments, 22), " NORMAL ….rs [+] 12:5 ");
+        assert_eq!(render(&segments, 17), " NORMAL     12:5 ");
+        assert_eq!(render(&segments, 8), " NORMAL ");
+        for width in 0..=200 {
+            let row = render(&segments, width);
+            assert_eq!(text_width(&row), width, "{row:?}");
+        }
+    }
+}